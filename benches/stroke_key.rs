@@ -0,0 +1,125 @@
+use std::num::NonZeroUsize;
+
+use criterion::measurement::WallTime;
+use criterion::{
+    criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion, Throughput,
+};
+use typing_engine::{
+    KeyStrokeChar, LapRequest, QueryRequest, TypingEngine, VocabularyEntry, VocabularyOrder,
+    VocabularyQuantifier, VocabularySeparator, VocabularySpellElement,
+};
+
+// 「きょ」「う」「と」の3チャンクからなる語彙
+const CHUNK_COUNT_PER_VOCABULARY: usize = 3;
+
+// 「しゅ」「っ」「ちょ」「う」の4チャンクからなる語彙
+// 各チャンクが長いキーストロークの候補を多く持つので候補との照合の時間が支配的になる
+const CHUNK_COUNT_PER_MANY_CANDIDATES_VOCABULARY: usize = 4;
+
+fn vocabulary_entry() -> VocabularyEntry {
+    gen_vocabulary_entry("京都", &["きょう", "と"])
+}
+
+fn many_candidates_vocabulary_entry() -> VocabularyEntry {
+    gen_vocabulary_entry("出張", &["しゅっ", "ちょう"])
+}
+
+fn gen_vocabulary_entry(view: &str, spells: &[&str]) -> VocabularyEntry {
+    VocabularyEntry::new(
+        view.to_string(),
+        spells
+            .iter()
+            .map(|spell| VocabularySpellElement::Normal(spell.to_string().try_into().unwrap()))
+            .collect(),
+    )
+    .unwrap()
+}
+
+// 指定したチャンク数のクエリで開始したエンジンと，最後まで打ち切るキーストローク列を構築する
+fn started_engine(
+    vocabulary_entry: &VocabularyEntry,
+    chunk_count_per_vocabulary: usize,
+    chunk_count: usize,
+) -> (TypingEngine, Vec<KeyStrokeChar>) {
+    let mut engine = TypingEngine::new();
+    engine
+        .init(QueryRequest::new(
+            &[vocabulary_entry],
+            VocabularyQuantifier::Vocabulary(
+                NonZeroUsize::new(chunk_count / chunk_count_per_vocabulary).unwrap(),
+            ),
+            VocabularySeparator::None,
+            VocabularyOrder::InOrder,
+        ))
+        .unwrap();
+    engine.start().unwrap();
+
+    let key_strokes = engine
+        .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+        .unwrap()
+        .key_stroke_info()
+        .key_stroke()
+        .chars()
+        .map(|c| c.try_into().unwrap())
+        .collect();
+
+    (engine, key_strokes)
+}
+
+fn stroke_key(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stroke_key");
+
+    // キーストローク1回あたりの時間がチャンク数に依存しないことを確認する
+    // correctは比較の基準として以前から計測している語彙のまま残しておく
+    // correct_many_candidatesでは候補との照合のコストを計測する
+    for (id, vocabulary_entry, chunk_count_per_vocabulary) in [
+        ("correct", vocabulary_entry(), CHUNK_COUNT_PER_VOCABULARY),
+        (
+            "correct_many_candidates",
+            many_candidates_vocabulary_entry(),
+            CHUNK_COUNT_PER_MANY_CANDIDATES_VOCABULARY,
+        ),
+    ] {
+        bench_correct_key_strokes(
+            &mut group,
+            id,
+            &vocabulary_entry,
+            chunk_count_per_vocabulary,
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_correct_key_strokes(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    id: &str,
+    vocabulary_entry: &VocabularyEntry,
+    chunk_count_per_vocabulary: usize,
+) {
+    for chunk_count in [1_000, 10_000] {
+        let (_, key_strokes) =
+            started_engine(vocabulary_entry, chunk_count_per_vocabulary, chunk_count);
+        group.throughput(Throughput::Elements(key_strokes.len() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new(id, chunk_count),
+            &chunk_count,
+            |b, &chunk_count| {
+                b.iter_batched(
+                    || started_engine(vocabulary_entry, chunk_count_per_vocabulary, chunk_count),
+                    |(mut engine, key_strokes)| {
+                        for key_stroke in key_strokes {
+                            engine.stroke_key(key_stroke).unwrap();
+                        }
+                        engine
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+}
+
+criterion_group!(benches, stroke_key);
+criterion_main!(benches);
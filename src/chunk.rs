@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::num::NonZeroUsize;
 
-use crate::chunk_key_stroke_dictionary::CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY;
+use crate::input_scheme::{InputScheme, RomanizationPreference};
 use crate::key_stroke::{KeyStrokeChar, KeyStrokeString};
 use crate::spell::SpellString;
 use crate::utility::convert_by_weighted_count;
@@ -9,6 +9,7 @@ use crate::utility::convert_by_weighted_count;
 pub(crate) mod confirmed;
 pub(crate) mod has_actual_key_strokes;
 pub(crate) mod typed;
+pub(crate) mod view;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum ChunkSpell {
@@ -18,6 +19,18 @@ pub(crate) enum ChunkSpell {
 }
 
 impl ChunkSpell {
+    // キーストローク候補が次のチャンクに依存するか
+    // 「ん」と「っ」の候補は次のチャンクによって変わる
+    fn depends_on_next_chunk(&self) -> bool {
+        match self {
+            Self::DisplayableAscii(_) => false,
+            Self::SingleChar(spell_string) => {
+                spell_string.as_str() == "ん" || spell_string.as_str() == "っ"
+            }
+            Self::DoubleChar(spell_string) => spell_string.ends_with('ん'),
+        }
+    }
+
     fn new(ss: SpellString) -> Self {
         if ss.contains_displayable_ascii() {
             assert!(ss.chars().count() == 1);
@@ -113,7 +126,7 @@ impl Chunk {
 
     // このチャンクを打つのに必要な最小のキーストローク数を推測する
     // キーストロークをまだ付与していないチャンクに対して行うため推測である
-    pub fn estimate_min_key_stroke_count(&self) -> usize {
+    pub fn estimate_min_key_stroke_count(&self, input_scheme: &InputScheme) -> usize {
         assert!(self.key_stroke_candidates.is_none());
 
         // ここで推測するのはあくまでも最小なので基本的には変換辞書から引いたものをそのまま使う
@@ -125,8 +138,8 @@ impl Chunk {
                 if spell_string.as_str() == "っ" {
                     1
                 } else {
-                    CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                        .get(spell_string.as_str())
+                    input_scheme
+                        .key_strokes(spell_string.as_str())
                         .unwrap()
                         .iter()
                         .map(|key_stroke_str| key_stroke_str.chars().count())
@@ -175,6 +188,46 @@ impl Chunk {
         self.min_candidate(None).calc_key_stroke_count()
     }
 
+    // 制限を満たす最小の候補の各キーストロークについてそのキーストロークまでが一致する候補の数を数える
+    // 数が減る位置でキーストロークの分岐が起きていることになる
+    pub(crate) fn construct_candidate_counts_of_min_candidate(
+        &self,
+        chunk_head_striction: Option<KeyStrokeChar>,
+    ) -> Vec<usize> {
+        let min_candidate_key_stroke: Vec<char> = self
+            .min_candidate(chunk_head_striction.clone())
+            .whole_key_stroke()
+            .chars()
+            .collect();
+
+        let candidate_key_strokes: Vec<Vec<char>> = self
+            .key_stroke_candidates
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter(|candidate| {
+                if let Some(chunk_head_striction) = &chunk_head_striction {
+                    &candidate.key_stroke_char_at_position(0) == chunk_head_striction
+                } else {
+                    true
+                }
+            })
+            .map(|candidate| candidate.whole_key_stroke().chars().collect())
+            .collect();
+
+        (0..min_candidate_key_stroke.len())
+            .map(|i| {
+                candidate_key_strokes
+                    .iter()
+                    .filter(|candidate_key_stroke| {
+                        candidate_key_stroke.len() > i
+                            && candidate_key_stroke[..=i] == min_candidate_key_stroke[..=i]
+                    })
+                    .count()
+            })
+            .collect()
+    }
+
     pub(crate) fn key_stroke_candidates_count(&self) -> Option<usize> {
         self.key_stroke_candidates.as_ref().map(|v| v.len())
     }
@@ -236,151 +289,340 @@ impl Chunk {
             is_hit
         });
     }
-}
 
-// 綴りのみの不完全なチャンク列にキーストローク候補を追加する
-pub fn append_key_stroke_to_chunks(chunks: &mut [Chunk]) {
-    let mut next_chunk_spell: Option<ChunkSpell> = None;
+    // 後続のチャンクを考慮してキーストローク候補を付与し直す
+    // 後続のチャンクがない状態で付与された末尾のチャンクは「ん」や「っ」の候補が少なくなっている
+    // 逆に後続のチャンクがなくなった場合にはそれに依存する候補を取り除く必要がある
+    // キーストローク数の制限も解除される
+    pub(crate) fn reassign_key_stroke(
+        &mut self,
+        next_chunk: Option<&Chunk>,
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) {
+        let mut chunks = vec![self.spell_only()];
+        if let Some(next_chunk) = next_chunk {
+            chunks.push(next_chunk.spell_only());
+        }
 
-    // 次のチャンク先頭のキーストローク
-    let mut next_chunk_head_key_strokes: Option<Vec<KeyStrokeChar>> = None;
+        append_key_stroke_to_chunks(&mut chunks, input_scheme, romanization_preference);
 
+        *self = chunks.swap_remove(0);
+    }
+
+    // キーストローク候補を付与する前の状態のチャンク
+    pub(crate) fn spell_only(&self) -> Self {
+        Self {
+            spell: self.spell.clone(),
+            key_stroke_candidates: None,
+            ideal_candidate: None,
+        }
+    }
+}
+
+// キーストローク候補を追加する際に必要な次のチャンクの情報
+#[derive(Debug, Clone, Default)]
+struct NextChunkInfo {
+    next_chunk_spell: Option<ChunkSpell>,
+    // 次のチャンク先頭のキーストローク
+    next_chunk_head_key_strokes: Option<Vec<KeyStrokeChar>>,
     // このチャンクが「っ」としたときにキーストロークの連続によって表現できるキーストローク群
     // 次のチャンク先頭の子音などのキーストロークともいえる
     // ex. 次のチャンクが「た」だったときには [t] となる
-    let mut key_strokes_can_represent_ltu_by_repeat: Option<Vec<KeyStrokeChar>> = None;
+    key_strokes_can_represent_ltu_by_repeat: Option<Vec<KeyStrokeChar>>,
+}
+
+// 並列にキーストローク候補を追加するチャンク数の下限
+// チャンク数が少ない場合にはスレッドを生成するコストの方が大きい
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_COUNT_THRESHOLD: usize = 4096;
+
+// 綴りのみの不完全なチャンク列にキーストローク候補を追加する
+// ローマ字の方式の好みがある場合にはそれに従って候補を絞り込み並べ替える
+pub fn append_key_stroke_to_chunks(
+    chunks: &mut [Chunk],
+    input_scheme: &InputScheme,
+    romanization_preference: Option<&RomanizationPreference>,
+) {
+    #[cfg(feature = "parallel")]
+    if chunks.len() >= PARALLEL_CHUNK_COUNT_THRESHOLD {
+        let thread_count = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+        append_key_stroke_to_chunks_in_parallel(
+            chunks,
+            thread_count,
+            input_scheme,
+            romanization_preference,
+        );
+        append_ideal_candidates_to_chunks(chunks, None);
+        return;
+    }
+
+    let mut next_chunk_info = NextChunkInfo::default();
 
     // キーストローク候補は次のチャンクに依存するので後ろから走査する
-    for chunk in chunks.iter_mut().rev() {
-        assert!(chunk.key_stroke_candidates.is_none());
+    chunks.iter_mut().rev().for_each(|chunk| {
+        append_key_stroke_to_chunk(
+            chunk,
+            &mut next_chunk_info,
+            input_scheme,
+            romanization_preference,
+        );
+    });
 
-        let mut key_stroke_candidates = Vec::<ChunkKeyStrokeCandidate>::new();
+    append_ideal_candidates_to_chunks(chunks, None);
+}
 
-        match &chunk.spell {
-            // 表示可能なASCIIで構成されるチャンクならそのままキーストロークにする
-            ChunkSpell::DisplayableAscii(spell_string) => {
-                key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
-                    vec![String::from(spell_string.clone()).try_into().unwrap()],
-                    None,
-                    None,
-                ));
+// 後続のチャンク列を考慮して綴りのみのチャンク列にキーストローク候補を追加する
+// 後続のチャンクは次のチャンクに依存しないチャンクまでを複製して候補を追加することで次のチャンクの情報を求める
+// 理想的な候補は直前のチャンクの理想的な候補による次のチャンク先頭の制限を引き継いで付与する
+pub(crate) fn append_key_stroke_to_chunks_followed_by<'a>(
+    chunks: &mut [Chunk],
+    following_chunks: impl Iterator<Item = &'a Chunk>,
+    next_chunk_head_constraint: Option<KeyStrokeChar>,
+    input_scheme: &InputScheme,
+    romanization_preference: Option<&RomanizationPreference>,
+) {
+    let mut dependent_chunks = vec![];
+    for following_chunk in following_chunks {
+        dependent_chunks.push(following_chunk.spell_only());
+
+        if !following_chunk.spell.depends_on_next_chunk() {
+            break;
+        }
+    }
+
+    let mut next_chunk_info = NextChunkInfo::default();
+
+    chunks
+        .iter_mut()
+        .chain(dependent_chunks.iter_mut())
+        .rev()
+        .for_each(|chunk| {
+            append_key_stroke_to_chunk(
+                chunk,
+                &mut next_chunk_info,
+                input_scheme,
+                romanization_preference,
+            );
+        });
+
+    append_ideal_candidates_to_chunks(chunks, next_chunk_head_constraint);
+}
+
+// 後続のチャンクが変わったときにキーストローク候補が変わりうる末尾のチャンク数
+// 末尾のチャンクとその前に次のチャンクに依存するチャンクが続く範囲が対象となる
+pub(crate) fn count_last_chunks_depending_on_following<'a>(
+    chunks: impl DoubleEndedIterator<Item = &'a Chunk>,
+) -> usize {
+    let mut chunks = chunks.rev();
+    if chunks.next().is_none() {
+        return 0;
+    }
+
+    1 + chunks
+        .take_while(|chunk| chunk.spell.depends_on_next_chunk())
+        .count()
+}
+
+// 後続のチャンクがなくなったチャンク列の末尾のチャンクにキーストローク候補を付与し直す
+// 「っ」や「ん」が末尾に続く場合にはそれらの候補も後続のチャンクを前提としているのでまとめて付与し直す
+pub(crate) fn reassign_key_stroke_to_last_chunks(
+    chunks: &mut [Chunk],
+    input_scheme: &InputScheme,
+    romanization_preference: Option<&RomanizationPreference>,
+) {
+    let start = chunks.len() - count_last_chunks_depending_on_following(chunks.iter());
+    let (preceding_chunks, last_chunks) = chunks.split_at_mut(start);
+
+    let next_chunk_head_constraint = preceding_chunks
+        .last()
+        .and_then(|chunk| chunk.ideal_candidate.as_ref())
+        .and_then(|candidate| candidate.next_chunk_head_constraint.clone());
+
+    last_chunks
+        .iter_mut()
+        .for_each(|chunk| *chunk = chunk.spell_only());
+    append_key_stroke_to_chunks_followed_by(
+        last_chunks,
+        std::iter::empty(),
+        next_chunk_head_constraint,
+        input_scheme,
+        romanization_preference,
+    );
+}
+
+// チャンク列を分割してそれぞれ別のスレッドでキーストローク候補を追加する
+// 次のチャンクに依存しないチャンクは単独で次のチャンクの情報を求められるので分割の境界とする
+#[cfg(feature = "parallel")]
+fn append_key_stroke_to_chunks_in_parallel(
+    chunks: &mut [Chunk],
+    thread_count: usize,
+    input_scheme: &InputScheme,
+    romanization_preference: Option<&RomanizationPreference>,
+) {
+    let segment_len = chunks.len().div_ceil(thread_count);
+
+    std::thread::scope(|scope| {
+        let mut rest_chunks = chunks;
+
+        while !rest_chunks.is_empty() {
+            let mut split_position = segment_len.min(rest_chunks.len());
+            while split_position < rest_chunks.len()
+                && rest_chunks[split_position].spell.depends_on_next_chunk()
+            {
+                split_position += 1;
             }
-            ChunkSpell::SingleChar(spell_string) => match spell_string.chars().as_str() {
-                "ん" => {
-                    CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                        .get("ん")
-                        .unwrap()
-                        .iter()
-                        // 「n」というキーストロークは次のチャンクによっては使えない
-                        .filter_map(|key_stroke| match *key_stroke {
-                            "n" => {
-                                let single_n_avail = allow_single_n_as_key_stroke(
-                                    &next_chunk_spell,
-                                    next_chunk_head_key_strokes.as_ref(),
-                                );
 
-                                match single_n_avail {
-                                    SingleNAvailability::All(avail_as_next_key_strokes) => {
-                                        Some((key_stroke, None, Some(avail_as_next_key_strokes)))
-                                    }
-                                    SingleNAvailability::Partial(avail_as_next_key_strokes) => {
-                                        Some((
-                                            key_stroke,
-                                            Some(avail_as_next_key_strokes[0].clone()),
-                                            Some(avail_as_next_key_strokes),
-                                        ))
-                                    }
-                                    SingleNAvailability::Cannot => None,
+            let (segment, following_chunks) = rest_chunks.split_at_mut(split_position);
+
+            let mut next_chunk_info = NextChunkInfo::default();
+            if let Some(following_chunk) = following_chunks.first() {
+                append_key_stroke_to_chunk(
+                    &mut following_chunk.clone(),
+                    &mut next_chunk_info,
+                    input_scheme,
+                    romanization_preference,
+                );
+            }
+
+            scope.spawn(move || {
+                segment.iter_mut().rev().for_each(|chunk| {
+                    append_key_stroke_to_chunk(
+                        chunk,
+                        &mut next_chunk_info,
+                        input_scheme,
+                        romanization_preference,
+                    );
+                });
+            });
+
+            rest_chunks = following_chunks;
+        }
+    });
+}
+
+// 1つのチャンクにキーストローク候補を追加し次のチャンクの情報をこのチャンクのものに更新する
+fn append_key_stroke_to_chunk(
+    chunk: &mut Chunk,
+    next_chunk_info: &mut NextChunkInfo,
+    input_scheme: &InputScheme,
+    romanization_preference: Option<&RomanizationPreference>,
+) {
+    let NextChunkInfo {
+        next_chunk_spell,
+        next_chunk_head_key_strokes,
+        key_strokes_can_represent_ltu_by_repeat,
+    } = next_chunk_info;
+
+    assert!(chunk.key_stroke_candidates.is_none());
+
+    let mut key_stroke_candidates = Vec::<ChunkKeyStrokeCandidate>::new();
+
+    match &chunk.spell {
+        // 表示可能なASCIIで構成されるチャンクならそのままキーストロークにする
+        ChunkSpell::DisplayableAscii(spell_string) => {
+            key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
+                vec![String::from(spell_string.clone()).try_into().unwrap()],
+                None,
+                None,
+            ));
+        }
+        ChunkSpell::SingleChar(spell_string) => match spell_string.chars().as_str() {
+            "ん" => {
+                input_scheme
+                    .key_strokes("ん")
+                    .unwrap()
+                    .iter()
+                    // 「n」というキーストロークは次のチャンクによっては使えない
+                    .filter_map(|key_stroke| match *key_stroke {
+                        "n" => {
+                            let single_n_avail = allow_single_n_as_key_stroke(
+                                next_chunk_spell,
+                                next_chunk_head_key_strokes.as_ref(),
+                            );
+
+                            match single_n_avail {
+                                SingleNAvailability::All(avail_as_next_key_strokes) => {
+                                    Some((key_stroke, None, Some(avail_as_next_key_strokes)))
                                 }
+                                SingleNAvailability::Partial(avail_as_next_key_strokes) => Some((
+                                    key_stroke,
+                                    Some(avail_as_next_key_strokes[0].clone()),
+                                    Some(avail_as_next_key_strokes),
+                                )),
+                                SingleNAvailability::Cannot => None,
                             }
-                            _ => Some((key_stroke, None, None)),
-                        })
-                        .for_each(
-                            |(
-                                key_stroke,
-                                next_chunk_head_constraint,
-                                avail_as_next_key_strokes,
-                            )| {
-                                key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
-                                    vec![key_stroke.to_string().try_into().unwrap()],
-                                    next_chunk_head_constraint,
-                                    avail_as_next_key_strokes
-                                        .map(DelayedConfirmedCandidateInfo::new),
-                                ))
-                            },
-                        );
-                }
-                // 「っ」は単独で打つ以外にも次のチャンクの子音で済ませる(「った」なら「tta」)ことができる
-                "っ" => {
-                    // 「ltu」「ltsu」「xtu」は任意の状況で次のチャンクへの制限なしに打てる
-                    CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                        .get("っ")
-                        .unwrap()
-                        .iter()
-                        .for_each(|key_stroke| {
+                        }
+                        _ => Some((key_stroke, None, None)),
+                    })
+                    .for_each(
+                        |(key_stroke, next_chunk_head_constraint, avail_as_next_key_strokes)| {
                             key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
                                 vec![key_stroke.to_string().try_into().unwrap()],
-                                None,
-                                None,
+                                next_chunk_head_constraint,
+                                avail_as_next_key_strokes.map(DelayedConfirmedCandidateInfo::new),
                             ))
-                        });
+                        },
+                    );
+            }
+            // 「っ」は単独で打つ以外にも次のチャンクの子音で済ませる(「った」なら「tta」)ことができる
+            "っ" => {
+                // 「ltu」「ltsu」「xtu」は任意の状況で次のチャンクへの制限なしに打てる
+                input_scheme
+                    .key_strokes("っ")
+                    .unwrap()
+                    .iter()
+                    .for_each(|key_stroke| {
+                        key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
+                            vec![key_stroke.to_string().try_into().unwrap()],
+                            None,
+                            None,
+                        ))
+                    });
 
-                    // 子音の連続で打つ場合には次のチャンクへの制限をする
-                    if let Some(ref key_strokes_can_represent_ltu_by_repeat) =
-                        key_strokes_can_represent_ltu_by_repeat
-                    {
-                        key_strokes_can_represent_ltu_by_repeat
-                            .iter()
-                            .for_each(|key_stroke| match char::from(key_stroke.clone()) {
-                                'l' | 'x' => {
-                                    key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
-                                        vec![char::from(key_stroke.clone())
-                                            .to_string()
-                                            .try_into()
-                                            .unwrap()],
-                                        Some(key_stroke.clone()),
-                                        // 次のチャンクへの制限があるときには遅延確定候補を確定できるのはその制限だけである
-                                        Some(DelayedConfirmedCandidateInfo::new(
-                                            next_chunk_head_key_strokes
-                                                .as_ref()
-                                                .map_or(&vec![], |v| v)
-                                                .iter()
-                                                .filter(|ks| *ks == key_stroke)
-                                                .cloned()
-                                                .collect(),
-                                        )),
-                                    ))
-                                }
-                                _ => key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
+                // 子音の連続で打つ場合には次のチャンクへの制限をする
+                if let Some(key_strokes_can_represent_ltu_by_repeat) =
+                    key_strokes_can_represent_ltu_by_repeat
+                        .as_ref()
+                        .filter(|_| input_scheme.can_represent_ltu_by_repeat())
+                {
+                    key_strokes_can_represent_ltu_by_repeat
+                        .iter()
+                        .for_each(|key_stroke| match char::from(key_stroke.clone()) {
+                            'l' | 'x' => {
+                                key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
                                     vec![char::from(key_stroke.clone())
                                         .to_string()
                                         .try_into()
                                         .unwrap()],
                                     Some(key_stroke.clone()),
-                                    None,
-                                )),
-                            });
-                    }
-                }
-                _ => {
-                    CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                        .get(spell_string.as_str())
-                        .unwrap()
-                        .iter()
-                        .for_each(|key_stroke| {
-                            key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
-                                vec![key_stroke.to_string().try_into().unwrap()],
-                                None,
+                                    // 次のチャンクへの制限があるときには遅延確定候補を確定できるのはその制限だけである
+                                    Some(DelayedConfirmedCandidateInfo::new(
+                                        next_chunk_head_key_strokes
+                                            .as_ref()
+                                            .map_or(&vec![], |v| v)
+                                            .iter()
+                                            .filter(|ks| *ks == key_stroke)
+                                            .cloned()
+                                            .collect(),
+                                    )),
+                                ))
+                            }
+                            _ => key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
+                                vec![char::from(key_stroke.clone())
+                                    .to_string()
+                                    .try_into()
+                                    .unwrap()],
+                                Some(key_stroke.clone()),
                                 None,
-                            ));
+                            )),
                         });
                 }
-            },
-            // 2文字のチャンクはまとめて入力する場合と1文字ずつ入力する場合がある
-            ChunkSpell::DoubleChar(spell_string) => {
-                // まとめて入力できるキーストローク
-                CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                    .get(spell_string.as_str())
+            }
+            _ => {
+                input_scheme
+                    .key_strokes(spell_string.as_str())
                     .unwrap()
                     .iter()
                     .for_each(|key_stroke| {
@@ -390,96 +632,164 @@ pub fn append_key_stroke_to_chunks(chunks: &mut [Chunk]) {
                             None,
                         ));
                     });
-
-                let (first_spell_string, second_spell_string) = chunk.spell.split_double_char();
-
-                // 1文字ずつのキーストローク
-                CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                    .get(first_spell_string.as_str())
-                    .unwrap()
-                    .iter()
-                    .for_each(|first_key_stroke| {
-                        CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
-                            .get(second_spell_string.as_str())
-                            .unwrap()
-                            .iter()
-                            .for_each(|second_key_stroke| {
+            }
+        },
+        // 2文字のチャンクはまとめて入力する場合と1文字ずつ入力する場合がある
+        ChunkSpell::DoubleChar(spell_string) => {
+            // まとめて入力できるキーストローク
+            input_scheme
+                .key_strokes(spell_string.as_str())
+                .unwrap()
+                .iter()
+                .for_each(|key_stroke| {
+                    key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
+                        vec![key_stroke.to_string().try_into().unwrap()],
+                        None,
+                        None,
+                    ));
+                });
+
+            let (first_spell_string, second_spell_string) = chunk.spell.split_double_char();
+
+            // 1文字ずつのキーストローク
+            input_scheme
+                .key_strokes(first_spell_string.as_str())
+                .unwrap()
+                .iter()
+                .for_each(|first_key_stroke| {
+                    input_scheme
+                        .key_strokes(second_spell_string.as_str())
+                        .unwrap()
+                        .iter()
+                        // 2文字目が「ん」の場合(「かん」など)には「n」というキーストロークは次のチャンクによっては使えない
+                        .filter_map(|second_key_stroke| {
+                            if second_spell_string.as_str() == "ん" && *second_key_stroke == "n" {
+                                match allow_single_n_as_key_stroke(
+                                    next_chunk_spell,
+                                    next_chunk_head_key_strokes.as_ref(),
+                                ) {
+                                    SingleNAvailability::All(avail_as_next_key_strokes) => Some((
+                                        second_key_stroke,
+                                        None,
+                                        Some(avail_as_next_key_strokes),
+                                    )),
+                                    SingleNAvailability::Partial(avail_as_next_key_strokes) => {
+                                        Some((
+                                            second_key_stroke,
+                                            Some(avail_as_next_key_strokes[0].clone()),
+                                            Some(avail_as_next_key_strokes),
+                                        ))
+                                    }
+                                    SingleNAvailability::Cannot => None,
+                                }
+                            } else {
+                                Some((second_key_stroke, None, None))
+                            }
+                        })
+                        .for_each(
+                            |(
+                                second_key_stroke,
+                                next_chunk_head_constraint,
+                                avail_as_next_key_strokes,
+                            )| {
                                 key_stroke_candidates.push(ChunkKeyStrokeCandidate::new(
                                     vec![
                                         first_key_stroke.to_string().try_into().unwrap(),
                                         second_key_stroke.to_string().try_into().unwrap(),
                                     ],
-                                    None,
-                                    None,
+                                    next_chunk_head_constraint,
+                                    avail_as_next_key_strokes
+                                        .map(DelayedConfirmedCandidateInfo::new),
                                 ));
-                            });
-                    });
-            }
+                            },
+                        );
+                });
+        }
+    }
+
+    if let Some(romanization_preference) = romanization_preference {
+        // 全ての候補が除外される場合には除外しない
+        if key_stroke_candidates
+            .iter()
+            .any(|candidate| !romanization_preference.excludes(candidate.key_stroke_element_strs()))
+        {
+            key_stroke_candidates.retain(|candidate| {
+                !romanization_preference.excludes(candidate.key_stroke_element_strs())
+            });
         }
 
-        // タイプ数が少ないキーストロークを第一候補として選択する
-        key_stroke_candidates.sort_by(|a, b| {
-            a.calc_key_stroke_count()
-                .partial_cmp(&(b.calc_key_stroke_count()))
-                .unwrap()
+        // タイプ数が同じ場合には好みの方式の候補を優先するために先に並べておく
+        key_stroke_candidates.sort_by_key(|candidate| {
+            romanization_preference.rank_of_key_strokes(candidate.key_stroke_element_strs())
         });
+    }
+
+    // タイプ数が少ないキーストロークを第一候補として選択する
+    key_stroke_candidates.sort_by(|a, b| {
+        a.calc_key_stroke_count()
+            .partial_cmp(&(b.calc_key_stroke_count()))
+            .unwrap()
+    });
 
-        chunk.key_stroke_candidates.replace(key_stroke_candidates);
+    chunk.key_stroke_candidates.replace(key_stroke_candidates);
 
-        next_chunk_spell.replace(chunk.spell.clone());
+    next_chunk_spell.replace(chunk.spell.clone());
 
-        // 次のチャンク先頭のキーストロークを更新する
-        next_chunk_head_key_strokes.replace(vec![]);
+    // 次のチャンク先頭のキーストロークを更新する
+    next_chunk_head_key_strokes.replace(vec![]);
 
-        let mut already_pushed_next_chunk_head_key_strokes = HashSet::<KeyStrokeChar>::new();
-        chunk
-            .key_stroke_candidates
+    let mut already_pushed_next_chunk_head_key_strokes = HashSet::<KeyStrokeChar>::new();
+    chunk
+        .key_stroke_candidates
+        .as_ref()
+        .unwrap()
+        .iter()
+        .for_each(|key_stroke_candidate| {
+            let first_char = key_stroke_candidate.key_stroke_char_at_position(0);
+            if !already_pushed_next_chunk_head_key_strokes.contains(&first_char) {
+                already_pushed_next_chunk_head_key_strokes.insert(first_char.clone());
+                next_chunk_head_key_strokes
+                    .as_mut()
+                    .unwrap()
+                    .push(first_char);
+            }
+        });
+
+    key_strokes_can_represent_ltu_by_repeat.replace(
+        next_chunk_head_key_strokes
             .as_ref()
             .unwrap()
             .iter()
-            .for_each(|key_stroke_candidate| {
-                let first_char = key_stroke_candidate.key_stroke_char_at_position(0);
-                if !already_pushed_next_chunk_head_key_strokes.contains(&first_char) {
-                    already_pushed_next_chunk_head_key_strokes.insert(first_char.clone());
-                    next_chunk_head_key_strokes
-                        .as_mut()
-                        .unwrap()
-                        .push(first_char);
-                }
-            });
-
-        key_strokes_can_represent_ltu_by_repeat.replace(
-            next_chunk_head_key_strokes
-                .as_ref()
-                .unwrap()
-                .iter()
-                .filter(|ksc| {
-                    match &chunk.spell {
-                        ChunkSpell::SingleChar(_) | ChunkSpell::DoubleChar(_) =>
-                        // 直後のチャンクの先頭が「n」を除く子音だった場合に「っ」を子音の連続で表すことができる
-                        {
-                            **ksc != 'a'
-                                && **ksc != 'i'
-                                && **ksc != 'u'
-                                && **ksc != 'e'
-                                && **ksc != 'o'
-                                && **ksc != 'n'
-                        }
-                        // 直後のチャンクがASCIIだったら子音の連続で表すことはできない
-                        ChunkSpell::DisplayableAscii(_) => false,
+            .filter(|ksc| {
+                match &chunk.spell {
+                    ChunkSpell::SingleChar(_) | ChunkSpell::DoubleChar(_) =>
+                    // 直後のチャンクの先頭が「n」を除く子音だった場合に「っ」を子音の連続で表すことができる
+                    // AZIKの「;」のように子音でないキーストロークで始まる場合は表せない
+                    {
+                        char::from((*ksc).clone()).is_ascii_alphabetic()
+                            && **ksc != 'a'
+                            && **ksc != 'i'
+                            && **ksc != 'u'
+                            && **ksc != 'e'
+                            && **ksc != 'o'
+                            && **ksc != 'n'
                     }
-                })
-                .cloned()
-                .collect(),
-        );
-    }
-
-    append_ideal_candidates_to_chunks(chunks);
+                    // 直後のチャンクがASCIIだったら子音の連続で表すことはできない
+                    ChunkSpell::DisplayableAscii(_) => false,
+                }
+            })
+            .cloned()
+            .collect(),
+    );
 }
 
 /// 理想的なキーストローク候補をチャンク列に付与する
 /// 候補が削減されていないことを前提とする
-fn append_ideal_candidates_to_chunks(chunks: &mut [Chunk]) {
+/// 先頭のチャンクは直前のチャンクの理想的な候補による制限を受ける
+fn append_ideal_candidates_to_chunks(
+    chunks: &mut [Chunk],
+    mut next_chunk_head_constraint: Option<KeyStrokeChar>,
+) {
     // 本来なら理想的なキーストローク候補は全探索によって付与されるべきであるが計算量の観点から前のチャンクから貪欲に行うことで付与している
     // このことによって理想的ではないキーストローク候補が付与されてしまう可能性は以下の理由からないと言える
     //
@@ -494,8 +804,6 @@ fn append_ideal_candidates_to_chunks(chunks: &mut [Chunk]) {
     // しかし次チャンクへの制限を持つ候補がある「っ」「ん」の次のチャンクでAとB両方の候補を持つのはそれぞれ「い(AがiでBがyi)」「う(AがuでBがwuなど)」と「う」だけであり
     // これらのAとBの最短キーストローク数の差は制限を持つ候補による短縮分以下である
 
-    let mut next_chunk_head_constraint: Option<KeyStrokeChar> = None;
-
     chunks.iter_mut().for_each(|chunk| {
         let ideal_candidate = chunk.min_candidate(next_chunk_head_constraint.clone());
         next_chunk_head_constraint = ideal_candidate.next_chunk_head_constraint.clone();
@@ -546,8 +854,9 @@ fn allow_single_n_as_key_stroke(
     } else if available_key_stroke_chars.len() == next_chunk_head.len() {
         SingleNAvailability::All(available_key_stroke_chars)
     } else {
-        assert_eq!(available_key_stroke_chars.len(), 1);
-        SingleNAvailability::Partial(available_key_stroke_chars)
+        // 次のチャンクへの制限は1つのキーストロークでしか表せないので複数使える場合でも最初のもののみ許容する
+        // ex. 次のチャンクが「っや」の場合には「l」「x」が使えるが「l」のみを許容する
+        SingleNAvailability::Partial(vec![available_key_stroke_chars[0].clone()])
     }
 }
 
@@ -640,6 +949,12 @@ impl ChunkKeyStrokeCandidate {
         s.try_into().unwrap()
     }
 
+    pub(crate) fn key_stroke_element_strs(&self) -> impl Iterator<Item = &str> {
+        self.key_stroke_elements
+            .iter()
+            .map(|key_stroke_element| key_stroke_element.as_str())
+    }
+
     pub(crate) fn construct_key_stroke_element_count(&self) -> KeyStrokeElementCount {
         KeyStrokeElementCount::new(
             &(self
@@ -651,13 +966,13 @@ impl ChunkKeyStrokeCandidate {
     }
 
     // この候補のキーストローク系列の特定のキーストロークを取り出す
-    fn key_stroke_char_at_position(&self, position: usize) -> KeyStrokeChar {
-        let whole_key_stroke = self.whole_key_stroke();
+    // キーストロークのたびに呼ばれるので文字列全体を構築せずに取り出す
+    pub(crate) fn key_stroke_char_at_position(&self, position: usize) -> KeyStrokeChar {
+        assert!(position < self.calc_key_stroke_count());
 
-        assert!(position < whole_key_stroke.chars().count());
-
-        whole_key_stroke
-            .chars()
+        self.key_stroke_elements
+            .iter()
+            .flat_map(|key_stroke_element| key_stroke_element.chars())
             .nth(position)
             .unwrap()
             .try_into()
@@ -665,14 +980,11 @@ impl ChunkKeyStrokeCandidate {
     }
 
     // 何回のキーストロークで打つことができるか
-    fn calc_key_stroke_count(&self) -> usize {
-        let mut s = String::new();
-
-        for key_stroke in &self.key_stroke_elements {
-            s.push_str(key_stroke);
-        }
-
-        s.chars().count()
+    pub(crate) fn calc_key_stroke_count(&self) -> usize {
+        self.key_stroke_elements
+            .iter()
+            .map(|key_stroke_element| key_stroke_element.chars().count())
+            .sum()
     }
 
     /// この候補のキーストローク回数をkey_stroke_count_striction回に制限する
@@ -901,6 +1213,10 @@ impl DelayedConfirmedCandidateInfo {
     pub(crate) fn is_valid_key_stroke(&self, key_stroke: KeyStrokeChar) -> bool {
         self.next_chunk_head.contains(&key_stroke)
     }
+
+    pub(crate) fn next_chunk_head(&self) -> &[KeyStrokeChar] {
+        &self.next_chunk_head
+    }
 }
 
 #[cfg(test)]
@@ -909,11 +1225,46 @@ mod test {
 
     use crate::{gen_candidate, gen_chunk, gen_unprocessed_chunk};
 
+    #[cfg(all(feature = "parallel", not(feature = "ascii_only")))]
+    #[test]
+    fn append_key_stroke_to_chunks_in_parallel_is_same_as_sequential() {
+        // 「ん」や「っ」が分割の境界付近に来るように様々な綴りを繰り返す
+        let spells = [
+            "か", "ん", "っ", "た", "じょ", "き", "a", "っ", "ん", "ん", "や",
+        ];
+        let chunks: Vec<Chunk> = (0..PARALLEL_CHUNK_COUNT_THRESHOLD * 2 + 1)
+            .map(|i| {
+                Chunk::new(
+                    spells[i % spells.len()].to_string().try_into().unwrap(),
+                    None,
+                    None,
+                )
+            })
+            .collect();
+
+        let mut parallel_chunks = chunks.clone();
+        append_key_stroke_to_chunks_in_parallel(
+            &mut parallel_chunks,
+            7,
+            &InputScheme::Standard,
+            None,
+        );
+
+        let mut sequential_chunks = chunks;
+        let mut next_chunk_info = NextChunkInfo::default();
+        sequential_chunks.iter_mut().rev().for_each(|chunk| {
+            append_key_stroke_to_chunk(chunk, &mut next_chunk_info, &InputScheme::Standard, None);
+        });
+
+        assert_eq!(parallel_chunks, sequential_chunks);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn append_key_stroke_to_chunks_1() {
         let mut chunks = vec![gen_unprocessed_chunk!("じょ"), gen_unprocessed_chunk!("ん")];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -940,6 +1291,7 @@ mod test {
         );
     }
 
+    #[cfg(not(any(feature = "ascii_only", feature = "no_rare_romanization")))]
     #[test]
     fn append_key_stroke_to_chunks_2() {
         let mut chunks = vec![
@@ -948,7 +1300,7 @@ mod test {
             gen_unprocessed_chunk!("う"),
         ];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -985,6 +1337,7 @@ mod test {
         );
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn append_key_stroke_to_chunks_3() {
         let mut chunks = vec![
@@ -993,7 +1346,7 @@ mod test {
             gen_unprocessed_chunk!("じ"),
         ];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -1029,7 +1382,7 @@ mod test {
             gen_unprocessed_chunk!("g"),
         ];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -1041,11 +1394,12 @@ mod test {
         );
     }
 
+    #[cfg(not(any(feature = "ascii_only", feature = "no_rare_romanization")))]
     #[test]
     fn append_key_stroke_to_chunks_5() {
         let mut chunks = vec![gen_unprocessed_chunk!("っ"), gen_unprocessed_chunk!("っ")];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -1074,11 +1428,12 @@ mod test {
         );
     }
 
+    #[cfg(not(any(feature = "ascii_only", feature = "no_rare_romanization")))]
     #[test]
     fn append_key_stroke_to_chunks_6() {
         let mut chunks = vec![gen_unprocessed_chunk!("っ"), gen_unprocessed_chunk!("か")];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -1103,6 +1458,7 @@ mod test {
         );
     }
 
+    #[cfg(not(any(feature = "ascii_only", feature = "no_rare_romanization")))]
     #[test]
     fn append_key_stroke_to_chunks_7() {
         let mut chunks = vec![
@@ -1112,7 +1468,7 @@ mod test {
             gen_unprocessed_chunk!("う"),
         ];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
@@ -1158,11 +1514,12 @@ mod test {
         );
     }
 
+    #[cfg(not(any(feature = "ascii_only", feature = "no_rare_romanization")))]
     #[test]
     fn append_key_stroke_to_chunks_8() {
         let mut chunks = vec![gen_unprocessed_chunk!("ん"), gen_unprocessed_chunk!("う")];
 
-        append_key_stroke_to_chunks(&mut chunks);
+        append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
 
         assert_eq!(
             chunks,
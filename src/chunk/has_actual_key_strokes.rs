@@ -53,6 +53,7 @@ pub(crate) trait ChunkHasActualKeyStrokes: AsRef<Chunk> {
         vec![false; self.effective_candidate().calc_key_stroke_count()]
     }
 
+    #[cfg(feature = "statistics")]
     /// キーストロークが何個の綴りに対するものなのか
     /// 基本的には1だが複数文字の綴りをまとめて打つ場合には2となる
     fn effective_spell_count(&self) -> usize {
@@ -9,6 +9,9 @@ use super::confirmed::ConfirmedChunk;
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct TypedChunk {
     chunk: Chunk,
+    // キーストローク候補のそれぞれのキーストローク系列
+    // キーストロークのたびに候補から取り出さなくて済むようにチャンクを打ち始める時点で展開しておく
+    key_strokes_of_candidates: Vec<Vec<KeyStrokeChar>>,
     // キーストローク候補のそれぞれに対するカーソル位置
     cursor_positions_of_candidates: Vec<usize>,
     // ミスタイプも含めた実際のキーストローク
@@ -26,6 +29,7 @@ impl TypedChunk {
         pending_key_strokes: Vec<ActualKeyStroke>,
     ) -> Self {
         Self {
+            key_strokes_of_candidates: expand_key_strokes_of_candidates(&chunk),
             chunk,
             cursor_positions_of_candidates,
             key_strokes,
@@ -33,6 +37,10 @@ impl TypedChunk {
         }
     }
 
+    pub(crate) fn cursor_positions_of_candidates(&self) -> &[usize] {
+        &self.cursor_positions_of_candidates
+    }
+
     pub(crate) fn pending_key_strokes(&self) -> &[ActualKeyStroke] {
         &self.pending_key_strokes
     }
@@ -53,11 +61,11 @@ impl TypedChunk {
 
         let mut is_confirmed = false;
 
-        key_stroke_candidates
+        self.key_strokes_of_candidates
             .iter()
             .zip(&self.cursor_positions_of_candidates)
-            .for_each(|(candidate, cursor_position)| {
-                if *cursor_position >= candidate.calc_key_stroke_count() {
+            .for_each(|(key_strokes, cursor_position)| {
+                if *cursor_position >= key_strokes.len() {
                     assert!(!is_confirmed);
 
                     is_confirmed = true;
@@ -77,10 +85,11 @@ impl TypedChunk {
 
         key_stroke_candidates
             .iter()
+            .zip(&self.key_strokes_of_candidates)
             .zip(&self.cursor_positions_of_candidates)
-            .filter(|(candidate, _)| candidate.is_delayed_confirmed_candidate())
-            .for_each(|(candidate, cursor_position)| {
-                if *cursor_position >= candidate.calc_key_stroke_count() {
+            .filter(|((candidate, _), _)| candidate.is_delayed_confirmed_candidate())
+            .for_each(|((_, key_strokes), cursor_position)| {
+                if *cursor_position >= key_strokes.len() {
                     // 同時に２つの遅延確定候補が終了することはないはずである
                     assert!(!is_delayed_confirmable);
 
@@ -122,14 +131,31 @@ impl TypedChunk {
 
         // 遅延確定候補以外の候補で確定した場合にはpendingしていたキーストロークを加える必要がある
         if self.is_confirmed() && !self.is_delayed_confirmable() {
-            self.pending_key_strokes
-                .drain(..)
-                .for_each(|key_stroke| self.key_strokes.push(key_stroke));
+            self.key_strokes.append(&mut self.pending_key_strokes);
         }
 
         key_stroke_result
     }
 
+    /// 候補と照合せずにミスタイプとしてキーストロークを記録する
+    /// 厳格モードで訂正されていないミスタイプがある場合に用いる
+    pub(crate) fn stroke_wrong_key(&mut self, key_stroke: KeyStrokeChar, elapsed_time: Duration) {
+        assert!(!self.is_confirmed());
+
+        if let Some(last_key_stroke) = self.key_strokes.last() {
+            assert!(&elapsed_time >= last_key_stroke.elapsed_time());
+        }
+
+        let actual_key_stroke = ActualKeyStroke::new(elapsed_time, key_stroke, false);
+
+        // 遅延確定候補を打ち終えている場合にはどちらのチャンクに属するかまだ確定しない
+        if self.is_delayed_confirmable() {
+            self.pending_key_strokes.push(actual_key_stroke);
+        } else {
+            self.key_strokes.push(actual_key_stroke);
+        }
+    }
+
     /// 遅延確定候補ではないかそうであってもまだ打ち終えていないチャンクを対象にキーストロークを行う
     fn stroke_key_to_no_delayed_confirmable(
         &mut self,
@@ -148,26 +174,18 @@ impl TypedChunk {
         );
 
         // それぞれの候補においてタイプされたキーストロークが有効かどうか
-        let candidate_hit_miss: Vec<bool> = key_stroke_candidates
+        let candidate_hit_miss: Vec<bool> = self
+            .key_strokes_of_candidates
             .iter()
             .zip(self.cursor_positions_of_candidates.iter())
-            .map(|(candidate, cursor_position)| {
-                candidate.key_stroke_char_at_position(*cursor_position) == key_stroke
-            })
+            .map(|(key_strokes, cursor_position)| key_strokes[*cursor_position] == key_stroke)
             .collect();
 
         let is_hit = candidate_hit_miss.contains(&true);
 
         // 何かしらの候補についてキーストロークが有効だったらそれらの候補のみを残しカーソル位置を進める
         if is_hit {
-            self.chunk.reduce_candidate(&candidate_hit_miss);
-
-            let mut index = 0;
-            self.cursor_positions_of_candidates.retain(|_| {
-                let is_hit = *candidate_hit_miss.get(index).unwrap();
-                index += 1;
-                is_hit
-            });
+            self.retain_candidates(&candidate_hit_miss);
 
             self.cursor_positions_of_candidates
                 .iter_mut()
@@ -223,15 +241,7 @@ impl TypedChunk {
             let mut candidate_reduce_vec = vec![false; key_stroke_candidates.len()];
             candidate_reduce_vec[delayed_confirmed_candidate_index] = true;
 
-            self.chunk.reduce_candidate(&candidate_reduce_vec);
-
-            // 遅延確定候補以外のカーソル位置も削除する
-            let mut index = 0;
-            self.cursor_positions_of_candidates.retain(|_| {
-                let is_hit = *candidate_reduce_vec.get(index).unwrap();
-                index += 1;
-                is_hit
-            });
+            self.retain_candidates(&candidate_reduce_vec);
 
             self.pending_key_strokes
                 .push(ActualKeyStroke::new(elapsed_time, key_stroke, true));
@@ -242,13 +252,14 @@ impl TypedChunk {
         // それぞれの候補においてタイプされたキーストロークが有効かどうか
         let candidate_hit_miss: Vec<bool> = key_stroke_candidates
             .iter()
+            .zip(&self.key_strokes_of_candidates)
             .zip(self.cursor_positions_of_candidates.iter())
-            .map(|(candidate, cursor_position)| {
+            .map(|((candidate, key_strokes), cursor_position)| {
                 // 遅延確定候補は既にミスであることが確定している
                 if candidate.is_delayed_confirmed_candidate() {
                     false
                 } else {
-                    candidate.key_stroke_char_at_position(*cursor_position) == key_stroke
+                    key_strokes[*cursor_position] == key_stroke
                 }
             })
             .collect();
@@ -257,14 +268,7 @@ impl TypedChunk {
 
         // 何かしらの候補についてキーストロークが有効だったらそれらの候補のみを残しカーソル位置を進める
         if is_hit {
-            self.chunk.reduce_candidate(&candidate_hit_miss);
-
-            let mut index = 0;
-            self.cursor_positions_of_candidates.retain(|_| {
-                let is_hit = *candidate_hit_miss.get(index).unwrap();
-                index += 1;
-                is_hit
-            });
+            self.retain_candidates(&candidate_hit_miss);
 
             self.cursor_positions_of_candidates
                 .iter_mut()
@@ -281,8 +285,135 @@ impl TypedChunk {
         }
     }
 
+    /// 次のキーストロークとして正しいと判定されるキーストロークを列挙する
+    /// 打ち終えた遅延確定候補がある場合には次のチャンク先頭のキーストロークも含む
+    pub(crate) fn acceptable_key_strokes(&self) -> Vec<KeyStrokeChar> {
+        assert!(self.chunk.key_stroke_candidates().is_some());
+        let key_stroke_candidates = self.chunk.key_stroke_candidates().as_ref().unwrap();
+
+        let mut acceptable_key_strokes: Vec<KeyStrokeChar> = vec![];
+
+        key_stroke_candidates
+            .iter()
+            .zip(&self.key_strokes_of_candidates)
+            .zip(&self.cursor_positions_of_candidates)
+            .for_each(|((candidate, key_strokes), cursor_position)| {
+                let key_strokes = if *cursor_position < key_strokes.len() {
+                    vec![key_strokes[*cursor_position].clone()]
+                } else {
+                    // 打ち終えた候補は遅延確定候補のみであり次のチャンク先頭で確定する
+                    candidate
+                        .delayed_confirmed_candiate_info()
+                        .as_ref()
+                        .map_or(vec![], |info| info.next_chunk_head().to_vec())
+                };
+
+                key_strokes.into_iter().for_each(|key_stroke| {
+                    if !acceptable_key_strokes.contains(&key_stroke) {
+                        acceptable_key_strokes.push(key_stroke);
+                    }
+                });
+            });
+
+        acceptable_key_strokes
+    }
+
+    /// チャンクを打ち終えるために次に打つべきキーストロークを選ぶ
+    /// 遅延確定候補はそれだけではチャンクを確定できないのでそれ以外の候補から選ぶ
+    /// prefer_idealが真の場合には理想的な候補がまだ残っていればそれを優先し，そうでなければ残りのキーストロークが最小の候補を選ぶ
+    pub(crate) fn next_completion_key_stroke(&self, prefer_ideal: bool) -> KeyStrokeChar {
+        assert!(self.chunk.key_stroke_candidates().is_some());
+        let key_stroke_candidates = self.chunk.key_stroke_candidates().as_ref().unwrap();
+        let cursor_position = self.current_key_stroke_cursor_position();
+
+        let completable_candidates: Vec<_> = key_stroke_candidates
+            .iter()
+            .filter(|candidate| {
+                !candidate.is_delayed_confirmed_candidate()
+                    && cursor_position < candidate.calc_key_stroke_count()
+            })
+            .collect();
+        assert!(!completable_candidates.is_empty());
+
+        let ideal_candidate = self
+            .chunk
+            .ideal_key_stroke_candidate()
+            .as_ref()
+            .filter(|_| prefer_ideal)
+            .and_then(|ideal_candidate| {
+                completable_candidates.iter().find(|candidate| {
+                    candidate.whole_key_stroke() == ideal_candidate.whole_key_stroke()
+                })
+            });
+
+        // 同じキーストローク回数の候補が複数あった場合にはもともとの順番が早い方を選択する
+        let candidate = ideal_candidate.copied().unwrap_or_else(|| {
+            completable_candidates
+                .iter()
+                .copied()
+                .reduce(|min_candidate, candidate| {
+                    if candidate.calc_key_stroke_count() < min_candidate.calc_key_stroke_count() {
+                        candidate
+                    } else {
+                        min_candidate
+                    }
+                })
+                .unwrap()
+        });
+
+        candidate.key_stroke_char_at_position(cursor_position)
+    }
+
+    /// 付与し直したチャンクの候補に含まれない候補を取り除き理想的な候補を置き換える
+    /// 後続のチャンクがなくなった場合などに既に打ったキーストロークを保ったまま候補を絞り込むために用いる
+    pub(crate) fn restrict_candidates(&mut self, reassigned_chunk: &Chunk) {
+        let reassigned_candidates = reassigned_chunk.key_stroke_candidates().as_ref().unwrap();
+
+        let retain_vector: Vec<bool> = self
+            .chunk
+            .key_stroke_candidates()
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|candidate| {
+                reassigned_candidates.iter().any(|reassigned_candidate| {
+                    reassigned_candidate.whole_key_stroke() == candidate.whole_key_stroke()
+                })
+            })
+            .collect();
+        assert!(retain_vector.contains(&true));
+
+        self.retain_candidates(&retain_vector);
+
+        self.chunk.ideal_candidate = reassigned_chunk.ideal_candidate.clone();
+
+        // 遅延確定候補がなくなった場合には保持していたキーストロークはこのチャンクに属する
+        if !self.is_delayed_confirmable() {
+            self.key_strokes.append(&mut self.pending_key_strokes);
+        }
+    }
+
+    // 候補とそれに付随するキーストローク系列・カーソル位置をまとめて絞り込む
+    fn retain_candidates(&mut self, retain_vector: &[bool]) {
+        self.chunk.reduce_candidate(retain_vector);
+
+        let mut index = 0;
+        self.key_strokes_of_candidates.retain(|_| {
+            let is_retained = retain_vector[index];
+            index += 1;
+            is_retained
+        });
+
+        let mut index = 0;
+        self.cursor_positions_of_candidates.retain(|_| {
+            let is_retained = retain_vector[index];
+            index += 1;
+            is_retained
+        });
+    }
+
     pub(crate) fn take_pending_key_strokes(&mut self) -> Vec<ActualKeyStroke> {
-        self.pending_key_strokes.drain(..).collect()
+        std::mem::take(&mut self.pending_key_strokes)
     }
 
     // チャンクのキーストロークのどこにカーソルを当てるべきか
@@ -326,6 +457,23 @@ impl TypedChunk {
     }
 }
 
+// チャンクのそれぞれの候補のキーストローク系列を1文字ずつに展開する
+fn expand_key_strokes_of_candidates(chunk: &Chunk) -> Vec<Vec<KeyStrokeChar>> {
+    chunk
+        .key_stroke_candidates()
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|candidate| {
+            candidate
+                .whole_key_stroke()
+                .chars()
+                .map(|c| c.try_into().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
 impl ChunkHasActualKeyStrokes for TypedChunk {
     fn actual_key_strokes(&self) -> &[ActualKeyStroke] {
         &self.key_strokes
@@ -344,6 +492,7 @@ impl From<Chunk> for TypedChunk {
         };
 
         Self {
+            key_strokes_of_candidates: expand_key_strokes_of_candidates(&chunk),
             chunk,
             cursor_positions_of_candidates: vec![0; key_stroke_candidates_count],
             key_strokes: vec![],
@@ -380,8 +529,8 @@ mod test {
 
     #[test]
     fn stroke_key_1() {
-        let mut typed_chunk = TypedChunk {
-            chunk: gen_chunk!(
+        let mut typed_chunk = TypedChunk::new(
+            gen_chunk!(
                 "じょ",
                 vec![
                     gen_candidate!(["jo"]),
@@ -394,18 +543,18 @@ mod test {
                 ],
                 gen_candidate!(["jo"])
             ),
-            cursor_positions_of_candidates: vec![0; 7],
-            key_strokes: vec![],
-            pending_key_strokes: vec![],
-        };
+            vec![0; 7],
+            vec![],
+            vec![],
+        );
 
         let stroke_result = typed_chunk.stroke_key('j'.try_into().unwrap(), Duration::new(1, 0));
         assert_eq!(stroke_result, KeyStrokeResult::Correct);
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "じょ",
                     vec![
                         gen_candidate!(["jo"]),
@@ -415,14 +564,14 @@ mod test {
                     ],
                     gen_candidate!(["jo"])
                 ),
-                cursor_positions_of_candidates: vec![1; 4],
-                key_strokes: vec![ActualKeyStroke::new(
+                vec![1; 4],
+                vec![ActualKeyStroke::new(
                     Duration::new(1, 0),
                     'j'.try_into().unwrap(),
                     true
                 )],
-                pending_key_strokes: vec![],
-            }
+                vec![]
+            )
         );
 
         let stroke_result = typed_chunk.stroke_key('j'.try_into().unwrap(), Duration::new(2, 0));
@@ -430,8 +579,8 @@ mod test {
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "じょ",
                     vec![
                         gen_candidate!(["jo"]),
@@ -441,13 +590,13 @@ mod test {
                     ],
                     gen_candidate!(["jo"])
                 ),
-                cursor_positions_of_candidates: vec![1; 4],
-                key_strokes: vec![
+                vec![1; 4],
+                vec![
                     ActualKeyStroke::new(Duration::new(1, 0), 'j'.try_into().unwrap(), true),
                     ActualKeyStroke::new(Duration::new(2, 0), 'j'.try_into().unwrap(), false)
                 ],
-                pending_key_strokes: vec![],
-            }
+                vec![]
+            )
         );
 
         let stroke_result = typed_chunk.stroke_key('o'.try_into().unwrap(), Duration::new(3, 0));
@@ -455,23 +604,23 @@ mod test {
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!("じょ", vec![gen_candidate!(["jo"])], gen_candidate!(["jo"])),
-                cursor_positions_of_candidates: vec![2],
-                key_strokes: vec![
+            TypedChunk::new(
+                gen_chunk!("じょ", vec![gen_candidate!(["jo"])], gen_candidate!(["jo"])),
+                vec![2],
+                vec![
                     ActualKeyStroke::new(Duration::new(1, 0), 'j'.try_into().unwrap(), true),
                     ActualKeyStroke::new(Duration::new(2, 0), 'j'.try_into().unwrap(), false),
                     ActualKeyStroke::new(Duration::new(3, 0), 'o'.try_into().unwrap(), true)
                 ],
-                pending_key_strokes: vec![],
-            }
+                vec![]
+            )
         );
     }
 
     #[test]
     fn stroke_key_2() {
-        let mut typed_chunk = TypedChunk {
-            chunk: gen_chunk!(
+        let mut typed_chunk = TypedChunk::new(
+            gen_chunk!(
                 "ん",
                 vec![
                     gen_candidate!(["n"], ['j']),
@@ -480,30 +629,30 @@ mod test {
                 ],
                 gen_candidate!(["n"], ['j'])
             ),
-            cursor_positions_of_candidates: vec![0; 3],
-            key_strokes: vec![],
-            pending_key_strokes: vec![],
-        };
+            vec![0; 3],
+            vec![],
+            vec![],
+        );
 
         let stroke_result = typed_chunk.stroke_key('n'.try_into().unwrap(), Duration::new(1, 0));
         assert_eq!(stroke_result, KeyStrokeResult::Correct);
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "ん",
                     vec![gen_candidate!(["n"], ['j']), gen_candidate!(["nn"])],
                     gen_candidate!(["n"], ['j'])
                 ),
-                cursor_positions_of_candidates: vec![1, 1],
-                key_strokes: vec![ActualKeyStroke::new(
+                vec![1, 1],
+                vec![ActualKeyStroke::new(
                     Duration::new(1, 0),
                     'n'.try_into().unwrap(),
                     true
                 ),],
-                pending_key_strokes: vec![]
-            }
+                vec![]
+            )
         );
 
         assert!(!typed_chunk.is_confirmed());
@@ -514,24 +663,24 @@ mod test {
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "ん",
                     vec![gen_candidate!(["n"], ['j']), gen_candidate!(["nn"])],
                     gen_candidate!(["n"], ['j'])
                 ),
-                cursor_positions_of_candidates: vec![1, 1],
-                key_strokes: vec![ActualKeyStroke::new(
+                vec![1, 1],
+                vec![ActualKeyStroke::new(
                     Duration::new(1, 0),
                     'n'.try_into().unwrap(),
                     true
                 ),],
-                pending_key_strokes: vec![ActualKeyStroke::new(
+                vec![ActualKeyStroke::new(
                     Duration::new(2, 0),
                     'm'.try_into().unwrap(),
                     false
                 )]
-            }
+            )
         );
 
         let stroke_result = typed_chunk.stroke_key('n'.try_into().unwrap(), Duration::new(3, 0));
@@ -539,20 +688,20 @@ mod test {
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "ん",
                     vec![gen_candidate!(["nn"])],
                     gen_candidate!(["n"], ['j'])
                 ),
-                cursor_positions_of_candidates: vec![2],
-                key_strokes: vec![
+                vec![2],
+                vec![
                     ActualKeyStroke::new(Duration::new(1, 0), 'n'.try_into().unwrap(), true),
                     ActualKeyStroke::new(Duration::new(2, 0), 'm'.try_into().unwrap(), false),
                     ActualKeyStroke::new(Duration::new(3, 0), 'n'.try_into().unwrap(), true),
                 ],
-                pending_key_strokes: vec![]
-            }
+                vec![]
+            )
         );
 
         assert!(typed_chunk.is_confirmed());
@@ -560,8 +709,8 @@ mod test {
 
     #[test]
     fn stroke_key_3() {
-        let mut typed_chunk = TypedChunk {
-            chunk: gen_chunk!(
+        let mut typed_chunk = TypedChunk::new(
+            gen_chunk!(
                 "ん",
                 vec![
                     gen_candidate!(["n"], ['j']),
@@ -570,30 +719,30 @@ mod test {
                 ],
                 gen_candidate!(["n"], ['j'])
             ),
-            cursor_positions_of_candidates: vec![0; 3],
-            key_strokes: vec![],
-            pending_key_strokes: vec![],
-        };
+            vec![0; 3],
+            vec![],
+            vec![],
+        );
 
         let stroke_result = typed_chunk.stroke_key('n'.try_into().unwrap(), Duration::new(1, 0));
         assert_eq!(stroke_result, KeyStrokeResult::Correct);
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "ん",
                     vec![gen_candidate!(["n"], ['j']), gen_candidate!(["nn"])],
                     gen_candidate!(["n"], ['j'])
                 ),
-                cursor_positions_of_candidates: vec![1, 1],
-                key_strokes: vec![ActualKeyStroke::new(
+                vec![1, 1],
+                vec![ActualKeyStroke::new(
                     Duration::new(1, 0),
                     'n'.try_into().unwrap(),
                     true
                 ),],
-                pending_key_strokes: vec![]
-            }
+                vec![]
+            )
         );
 
         assert!(!typed_chunk.is_confirmed());
@@ -604,24 +753,24 @@ mod test {
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "ん",
                     vec![gen_candidate!(["n"], ['j']), gen_candidate!(["nn"])],
                     gen_candidate!(["n"], ['j'])
                 ),
-                cursor_positions_of_candidates: vec![1, 1],
-                key_strokes: vec![ActualKeyStroke::new(
+                vec![1, 1],
+                vec![ActualKeyStroke::new(
                     Duration::new(1, 0),
                     'n'.try_into().unwrap(),
                     true
                 ),],
-                pending_key_strokes: vec![ActualKeyStroke::new(
+                vec![ActualKeyStroke::new(
                     Duration::new(2, 0),
                     'm'.try_into().unwrap(),
                     false
                 ),]
-            }
+            )
         );
 
         let stroke_result = typed_chunk.stroke_key('j'.try_into().unwrap(), Duration::new(3, 0));
@@ -629,23 +778,23 @@ mod test {
 
         assert_eq!(
             typed_chunk,
-            TypedChunk {
-                chunk: gen_chunk!(
+            TypedChunk::new(
+                gen_chunk!(
                     "ん",
                     vec![gen_candidate!(["n"], ['j'])],
                     gen_candidate!(["n"], ['j'])
                 ),
-                cursor_positions_of_candidates: vec![1],
-                key_strokes: vec![ActualKeyStroke::new(
+                vec![1],
+                vec![ActualKeyStroke::new(
                     Duration::new(1, 0),
                     'n'.try_into().unwrap(),
                     true
                 ),],
-                pending_key_strokes: vec![
+                vec![
                     ActualKeyStroke::new(Duration::new(2, 0), 'm'.try_into().unwrap(), false),
                     ActualKeyStroke::new(Duration::new(3, 0), 'j'.try_into().unwrap(), true)
                 ]
-            }
+            )
         );
 
         assert!(typed_chunk.is_confirmed());
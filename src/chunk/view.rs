@@ -0,0 +1,112 @@
+use crate::chunk::{append_key_stroke_to_chunks, Chunk};
+use crate::input_scheme::InputScheme;
+use crate::spell::SpellString;
+use crate::vocabulary::construct_chunks_of_spell;
+
+/// A read-only view of a chunk, which is a unit of typing.
+///
+/// A chunk is basically a single character of spell, but can be 2 characters such as `きょ`.
+/// This is constructed by [`parse_into_chunks`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkView {
+    spell: String,
+    key_stroke_candidates: Vec<String>,
+    ideal_key_stroke: String,
+}
+
+impl ChunkView {
+    fn new(chunk: &Chunk) -> Self {
+        Self {
+            spell: chunk.spell().as_ref().to_string(),
+            key_stroke_candidates: chunk
+                .key_stroke_candidates()
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|candidate| candidate.whole_key_stroke().into())
+                .collect(),
+            ideal_key_stroke: chunk
+                .ideal_key_stroke_candidate()
+                .as_ref()
+                .unwrap()
+                .whole_key_stroke()
+                .into(),
+        }
+    }
+
+    /// Get spell of this chunk.
+    pub fn spell(&self) -> &str {
+        &self.spell
+    }
+
+    /// Get whole key strokes of each candidate to type this chunk.
+    ///
+    /// Candidates which need fewer key strokes come first.
+    pub fn key_stroke_candidates(&self) -> &[String] {
+        &self.key_stroke_candidates
+    }
+
+    /// Get key strokes which need the least key strokes to type this chunk.
+    pub fn ideal_key_stroke(&self) -> &str {
+        &self.ideal_key_stroke
+    }
+
+    /// Get count of key strokes of [`ideal_key_stroke`](Self::ideal_key_stroke()).
+    pub fn ideal_key_stroke_count(&self) -> usize {
+        self.ideal_key_stroke.chars().count()
+    }
+}
+
+/// Split `spell` into chunks and assign key stroke candidates to them in the same way as
+/// [`TypingEngine`](crate::TypingEngine) does.
+///
+/// This is useful to precompute key stroke counts of word lists without constructing
+/// [`QueryRequest`](crate::QueryRequest).
+/// Key strokes of the last chunk are assigned as if `spell` is the end of query.
+///
+/// If `spell` cannot be typed with `input_scheme`, this function returns `None`.
+pub fn parse_into_chunks(
+    spell: &SpellString,
+    input_scheme: &InputScheme,
+) -> Option<Vec<ChunkView>> {
+    let mut chunks = construct_chunks_of_spell(spell, input_scheme)?;
+    append_key_stroke_to_chunks(&mut chunks, input_scheme, None);
+
+    Some(chunks.iter().map(ChunkView::new).collect())
+}
+
+// テストは組み込みの辞書を前提としている
+#[cfg(all(test, not(feature = "ascii_only")))]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::input_scheme::CustomDictionary;
+
+    #[test]
+    fn parse_into_chunks_1() {
+        let spell: SpellString = "きょっと".to_string().try_into().unwrap();
+        let chunks = parse_into_chunks(&spell, &InputScheme::Standard).unwrap();
+
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.spell()).collect::<Vec<_>>(),
+            vec!["きょ", "っ", "と"]
+        );
+        assert_eq!(chunks[0].ideal_key_stroke(), "kyo");
+        assert!(chunks[0]
+            .key_stroke_candidates()
+            .contains(&"kilyo".to_string()));
+        // 「っ」は次のチャンク先頭のキーストロークを重ねて打てる
+        assert_eq!(chunks[1].ideal_key_stroke(), "t");
+        assert_eq!(chunks[1].ideal_key_stroke_count(), 1);
+        assert_eq!(chunks[2].key_stroke_candidates(), ["to".to_string()]);
+
+        let spell: SpellString = "ゔぁ".to_string().try_into().unwrap();
+        let input_scheme = InputScheme::Custom(
+            CustomDictionary::new(HashMap::from([("き".to_string(), vec!["ki".to_string()])]))
+                .unwrap(),
+        );
+        assert!(parse_into_chunks(&spell, &input_scheme).is_none());
+    }
+}
@@ -1,6 +1,13 @@
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 
+// 滅多に使われないキーストローク
+// 除いても各綴りには他のキーストロークが残る
+#[cfg(feature = "no_rare_romanization")]
+const RARE_KEY_STROKES: [&str; 10] = [
+    "whu", "lyi", "xyi", "lye", "xye", "ltsu", "jyi", "cyi", "vyi", "vye",
+];
+
 pub static CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY: Lazy<HashMap<&'static str, Vec<&'static str>>> =
     // XXX どの規格に従うのかを一貫させておいたほうがよい
     Lazy::new(|| {
@@ -214,11 +221,13 @@ pub static CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY: Lazy<HashMap<&'static str, Vec<
         // 記号
         m.insert("　", vec![" "]);
         m.insert("！", vec!["!"]);
+        m.insert("“", vec!["\""]);
         m.insert("”", vec!["\""]);
         m.insert("＃", vec!["#"]);
         m.insert("＄", vec!["$"]);
         m.insert("％", vec!["%"]);
         m.insert("＆", vec!["&"]);
+        m.insert("‘", vec!["'"]);
         m.insert("’", vec!["'"]);
         m.insert("（", vec!["("]);
         m.insert("）", vec![")"]);
@@ -247,5 +256,13 @@ pub static CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY: Lazy<HashMap<&'static str, Vec<
         m.insert("｝", vec!["}"]);
         m.insert("〜", vec!["~"]);
 
+        // 候補が少ない方が判定も速くなるので辞書を構築する時点で除いておく
+        #[cfg(feature = "no_rare_romanization")]
+        {
+            m.values_mut().for_each(|key_strokes| {
+                key_strokes.retain(|key_stroke| !RARE_KEY_STROKES.contains(key_stroke))
+            });
+        }
+
         m
     });
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_scheme::InputScheme;
+use crate::vocabulary::{chunk_spell_length_at, normalize_spell_chars};
+
+/// A report of whether spells of a corpus can be typed with an input scheme.
+///
+/// This is constructed by [`analyze_dictionary_coverage`] and useful to check word packs before
+/// shipping them, especially with [`InputScheme::Custom`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DictionaryCoverageReport {
+    line_count: usize,
+    typable_line_count: usize,
+    untypable_spells: Vec<SpellFrequency>,
+    fallback_splits: Vec<SpellFrequency>,
+}
+
+impl DictionaryCoverageReport {
+    /// Get count of lines in corpus.
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+
+    /// Get count of lines which can be typed.
+    pub fn typable_line_count(&self) -> usize {
+        self.typable_line_count
+    }
+
+    /// Get ratio of lines which can be typed to all lines.
+    ///
+    /// If corpus is empty, this function returns 1.
+    pub fn coverage(&self) -> f64 {
+        if self.line_count == 0 {
+            1.0
+        } else {
+            self.typable_line_count as f64 / self.line_count as f64
+        }
+    }
+
+    /// Get spells which cannot be typed, that is, spells not contained in dictionary.
+    ///
+    /// Spells which occur more frequently come first.
+    pub fn untypable_spells(&self) -> &[SpellFrequency] {
+        &self.untypable_spells
+    }
+
+    /// Get 2 characters spells which are typed as a chunk in [`InputScheme::Standard`] but not
+    /// contained in dictionary.
+    ///
+    /// Such spells are split into each character and typed character by character.
+    /// ex. When dictionary does not contain `きゃ`, it is typed as `き` and `ゃ`, like `kilya`.
+    ///
+    /// Spells which occur more frequently come first.
+    pub fn fallback_splits(&self) -> &[SpellFrequency] {
+        &self.fallback_splits
+    }
+}
+
+/// A spell and count of its occurrences in corpus.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpellFrequency {
+    spell: String,
+    occurrence_count: usize,
+}
+
+impl SpellFrequency {
+    /// Get spell.
+    pub fn spell(&self) -> &str {
+        &self.spell
+    }
+
+    /// Get count of occurrences in corpus.
+    pub fn occurrence_count(&self) -> usize {
+        self.occurrence_count
+    }
+}
+
+/// Analyze whether each line of `corpus` can be typed with `input_scheme`.
+///
+/// Each line is regarded as spell of a vocabulary and split into chunks in the same way as
+/// [`TypingEngine`](crate::TypingEngine) does.
+pub fn analyze_dictionary_coverage<S: AsRef<str>>(
+    corpus: &[S],
+    input_scheme: &InputScheme,
+) -> DictionaryCoverageReport {
+    let mut typable_line_count = 0;
+    let mut untypable_spells: BTreeMap<String, usize> = BTreeMap::new();
+    let mut fallback_splits: BTreeMap<String, usize> = BTreeMap::new();
+
+    corpus.iter().for_each(|line| {
+        let spell_chars = normalize_spell_chars(line.as_ref(), input_scheme);
+        let mut is_typable = true;
+
+        let mut i = 0;
+        while i < spell_chars.len() {
+            match chunk_spell_length_at(&spell_chars, i, input_scheme) {
+                Some(chunk_spell_length) => {
+                    // 標準の辞書では1つのチャンクとして打てる綴りを分けて打つことになる
+                    if chunk_spell_length == 1 && i + 1 < spell_chars.len() {
+                        let bi: String = spell_chars[i..(i + 2)].iter().collect();
+
+                        if InputScheme::Standard.contains_spell(&bi)
+                            && !input_scheme.contains_spell(&bi)
+                        {
+                            *fallback_splits.entry(bi).or_default() += 1;
+                        }
+                    }
+
+                    i += chunk_spell_length;
+                }
+                None => {
+                    is_typable = false;
+                    *untypable_spells
+                        .entry(spell_chars[i].to_string())
+                        .or_default() += 1;
+
+                    i += 1;
+                }
+            }
+        }
+
+        if is_typable {
+            typable_line_count += 1;
+        }
+    });
+
+    DictionaryCoverageReport {
+        line_count: corpus.len(),
+        typable_line_count,
+        untypable_spells: sort_by_frequency(untypable_spells),
+        fallback_splits: sort_by_frequency(fallback_splits),
+    }
+}
+
+fn sort_by_frequency(spells: BTreeMap<String, usize>) -> Vec<SpellFrequency> {
+    let mut spells: Vec<SpellFrequency> = spells
+        .into_iter()
+        .map(|(spell, occurrence_count)| SpellFrequency {
+            spell,
+            occurrence_count,
+        })
+        .collect();
+    spells.sort_by_key(|spell| std::cmp::Reverse(spell.occurrence_count));
+
+    spells
+}
+
+// テストは組み込みの辞書を前提としている
+#[cfg(all(test, not(feature = "ascii_only")))]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::input_scheme::CustomDictionary;
+
+    #[test]
+    fn analyze_dictionary_coverage_1() {
+        let input_scheme = InputScheme::Custom(
+            CustomDictionary::new(HashMap::from([
+                ("き".to_string(), vec!["ki".to_string()]),
+                ("ゃ".to_string(), vec!["lya".to_string()]),
+                ("ん".to_string(), vec!["nn".to_string()]),
+            ]))
+            .unwrap(),
+        );
+
+        let report = analyze_dictionary_coverage(
+            &["きゃんきゃん", "ききゃ", "い", "いい", "abc"],
+            &input_scheme,
+        );
+
+        assert_eq!(report.line_count(), 5);
+        assert_eq!(report.typable_line_count(), 3);
+        assert!((report.coverage() - 0.6).abs() < f64::EPSILON);
+        assert_eq!(report.untypable_spells().len(), 1);
+        assert_eq!(report.untypable_spells()[0].spell(), "い");
+        assert_eq!(report.untypable_spells()[0].occurrence_count(), 3);
+        // 「きゃ」は辞書にないので「き」と「ゃ」に分けて打つ
+        assert_eq!(report.fallback_splits().len(), 1);
+        assert_eq!(report.fallback_splits()[0].spell(), "きゃ");
+        assert_eq!(report.fallback_splits()[0].occurrence_count(), 3);
+
+        let report = analyze_dictionary_coverage(&["きゃん"], &InputScheme::Standard);
+        assert_eq!(report.typable_line_count(), 1);
+        assert!(report.fallback_splits().is_empty());
+    }
+}
@@ -1,7 +1,12 @@
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-use crate::vocabulary::convert_spell_positions_to_view_positions;
-use crate::{statistics::OnTypingStatisticsTarget, vocabulary::ViewPosition};
+#[cfg(feature = "statistics")]
+use crate::statistics::{LapRequest, LapSummary, OnTypingStatisticsTarget};
+use crate::vocabulary::ViewPosition;
+use crate::vocabulary::{convert_spell_positions_to_view_positions, VocabularyAssets};
 
 /// A type for composing typing game UI.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -9,18 +14,42 @@ pub struct DisplayInfo {
     view: ViewDisplayInfo,
     spell: SpellDisplayInfo,
     key_stroke: KeyStrokeDisplayInfo,
+    progress: ProgressDisplayInfo,
+    upcoming_candidate_counts: Vec<usize>,
+    current_vocabulary_assets: Option<VocabularyAssets>,
+    speed: SpeedDisplayInfo,
+    #[cfg(feature = "statistics")]
+    lap_infos: Vec<LapDisplayInfo>,
+    line_break: LineBreakDisplayInfo,
+    pace: Option<PaceDisplayInfo>,
 }
 
 impl DisplayInfo {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         view: ViewDisplayInfo,
         spell: SpellDisplayInfo,
         key_stroke: KeyStrokeDisplayInfo,
+        progress: ProgressDisplayInfo,
+        upcoming_candidate_counts: Vec<usize>,
+        current_vocabulary_assets: Option<VocabularyAssets>,
+        speed: SpeedDisplayInfo,
+        #[cfg(feature = "statistics")] lap_infos: Vec<LapDisplayInfo>,
+        line_break: LineBreakDisplayInfo,
+        pace: Option<PaceDisplayInfo>,
     ) -> Self {
         Self {
             view,
             spell,
             key_stroke,
+            progress,
+            upcoming_candidate_counts,
+            current_vocabulary_assets,
+            speed,
+            #[cfg(feature = "statistics")]
+            lap_infos,
+            line_break,
+            pace,
         }
     }
     /// Get an information about query string itself.
@@ -37,6 +66,768 @@ impl DisplayInfo {
     pub fn key_stroke_info(&self) -> &KeyStrokeDisplayInfo {
         &self.key_stroke
     }
+
+    /// Get an information about progress of query.
+    pub fn progress_info(&self) -> &ProgressDisplayInfo {
+        &self.progress
+    }
+
+    /// Count of key stroke candidates which still include each upcoming key stroke.
+    ///
+    /// Each element corresponds to key stroke from [`current_cursor_position`](KeyStrokeDisplayInfo::current_cursor_position())
+    /// to the end of [`key_stroke`](KeyStrokeDisplayInfo::key_stroke()).
+    /// Count decreases where multiple romanizations diverge, so this is useful to shade such forks.
+    ///
+    /// ex. When query is `きょ` and nothing is typed, key stroke is `kyo` and candidates are `kyo`,
+    /// `kilyo` and `kixyo`, so this function returns `[3,1,1]`.
+    pub fn upcoming_candidate_counts(&self) -> &Vec<usize> {
+        &self.upcoming_candidate_counts
+    }
+
+    /// Assets attached to vocabulary currently typed.
+    ///
+    /// If whole query is typed, this function returns `None`.
+    /// Separators have no assets.
+    pub fn current_vocabulary_assets(&self) -> Option<&VocabularyAssets> {
+        self.current_vocabulary_assets.as_ref()
+    }
+
+    /// Get an information about typing speed of recent key strokes.
+    pub fn speed_info(&self) -> &SpeedDisplayInfo {
+        &self.speed
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get informations about laps for each requested lap request.
+    ///
+    /// Elements are in the same order as requested lap requests.
+    pub fn lap_infos(&self) -> &[LapDisplayInfo] {
+        &self.lap_infos
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get an information about laps taken by `lap_request`.
+    ///
+    /// If `lap_request` is not requested, this function returns `None`.
+    pub fn lap_info(&self, lap_request: &LapRequest) -> Option<&LapDisplayInfo> {
+        self.lap_infos
+            .iter()
+            .find(|lap_info| lap_info.lap_request() == lap_request)
+    }
+
+    /// Get an information about line breaks of query.
+    pub fn line_break_info(&self) -> &LineBreakDisplayInfo {
+        &self.line_break
+    }
+
+    /// Get an information about progress compared with target speed.
+    ///
+    /// If target speed is not configured by
+    /// [`set_target_kpm`](crate::TypingEngine::set_target_kpm()), this function returns `None`.
+    pub fn pace_info(&self) -> Option<&PaceDisplayInfo> {
+        self.pace.as_ref()
+    }
+
+    /// Construct [`DisplayInfo`] whose key stroke string is transformed per character.
+    ///
+    /// In addition to [`KeyStrokeDisplayInfo::transform`], all positions in key stroke string
+    /// such as line break positions, lap end positions of lap infos and
+    /// [`upcoming_candidate_counts`](Self::upcoming_candidate_counts()) are converted to positions
+    /// in transformed string.
+    /// Each count of upcoming candidates is repeated for all characters transformed from the
+    /// corresponding key stroke.
+    ///
+    /// Use this instead of transforming [`key_stroke_info`](Self::key_stroke_info()) alone when
+    /// other informations are rendered together.
+    pub fn transform_key_stroke<F: Fn(char) -> String>(&self, transformation: F) -> Self {
+        let (key_stroke, transformed_heads) = self.key_stroke.transform_with_heads(&transformation);
+
+        // 変換後の各文字は変換前の文字と同じ候補数を持つ
+        let upcoming_candidate_counts = self
+            .upcoming_candidate_counts
+            .iter()
+            .zip(self.key_stroke.current_cursor_position..)
+            .flat_map(|(count, position)| {
+                std::iter::repeat_n(
+                    *count,
+                    transformed_heads[position + 1] - transformed_heads[position],
+                )
+            })
+            .collect();
+
+        #[cfg(feature = "statistics")]
+        let (_, ideal_transformed_heads) =
+            transform_string(&self.key_stroke.ideal_key_stroke, &transformation);
+        #[cfg(feature = "statistics")]
+        let lap_infos = self
+            .lap_infos
+            .iter()
+            .map(|lap_info| LapDisplayInfo {
+                key_stroke: lap_info.key_stroke.convert_lap_end_positions(|position| {
+                    transformed_lap_end_position(&transformed_heads, position)
+                }),
+                ideal_key_stroke: if self.key_stroke.ideal_key_stroke.is_empty() {
+                    lap_info.ideal_key_stroke.clone()
+                } else {
+                    lap_info
+                        .ideal_key_stroke
+                        .convert_lap_end_positions(|position| {
+                            transformed_lap_end_position(&ideal_transformed_heads, position)
+                        })
+                },
+                ..lap_info.clone()
+            })
+            .collect();
+
+        let line_break = LineBreakDisplayInfo {
+            key_stroke_positions: self
+                .line_break
+                .key_stroke_positions
+                .iter()
+                .map(|position| transformed_heads[*position])
+                .collect(),
+            ..self.line_break.clone()
+        };
+
+        Self {
+            key_stroke,
+            upcoming_candidate_counts,
+            #[cfg(feature = "statistics")]
+            lap_infos,
+            line_break,
+            ..self.clone()
+        }
+    }
+
+    /// Compare with `prev` constructed at previous frame and get what is changed since then.
+    ///
+    /// This is useful for renderers to redraw only changed parts.
+    /// Laps are compared between lap infos at the same index, so `prev` should be constructed
+    /// with the same lap requests.
+    pub fn diff(&self, prev: &DisplayInfo) -> DisplayInfoDiff {
+        let unchanged_key_stroke_count = self
+            .key_stroke
+            .key_stroke
+            .chars()
+            .zip(prev.key_stroke.key_stroke.chars())
+            .take_while(|(c, prev_c)| c == prev_c)
+            .count();
+
+        #[cfg(feature = "statistics")]
+        let newly_completed_lap_indices = self
+            .lap_infos
+            .iter()
+            .enumerate()
+            .map(|(i, lap_info)| {
+                let prev_completed_lap_count = prev
+                    .lap_infos
+                    .get(i)
+                    .map_or(0, |prev_lap_info| prev_lap_info.lap_summaries.len());
+
+                (prev_completed_lap_count..lap_info.lap_summaries.len()).collect()
+            })
+            .collect();
+
+        DisplayInfoDiff {
+            unchanged_key_stroke_count,
+            previous_key_stroke_cursor_position: prev.key_stroke.current_cursor_position,
+            key_stroke_cursor_position: self.key_stroke.current_cursor_position,
+            newly_missed_key_stroke_positions: newly_added_positions(
+                &self.key_stroke.missed_positions,
+                &prev.key_stroke.missed_positions,
+            ),
+            previous_spell_cursor_positions: prev.spell.current_cursor_positions.clone(),
+            spell_cursor_positions: self.spell.current_cursor_positions.clone(),
+            newly_missed_spell_positions: newly_added_positions(
+                &self.spell.missed_positions,
+                &prev.spell.missed_positions,
+            ),
+            #[cfg(feature = "statistics")]
+            newly_completed_lap_indices,
+        }
+    }
+
+    // 再構築する際に再利用するために長さがクエリに比例する文字列を取り出す
+    pub(crate) fn take_string_buffers(&mut self) -> DisplayStringBuffers {
+        DisplayStringBuffers {
+            view: std::mem::take(&mut self.view.view),
+            spell: std::mem::take(&mut self.spell.spell),
+            key_stroke: std::mem::take(&mut self.key_stroke.key_stroke),
+            ideal_key_stroke: std::mem::take(&mut self.key_stroke.ideal_key_stroke),
+        }
+    }
+}
+
+/// 表示用の情報を構築する際に再利用する文字列の領域
+#[derive(Debug, Default)]
+pub(crate) struct DisplayStringBuffers {
+    pub(crate) view: String,
+    pub(crate) spell: String,
+    pub(crate) key_stroke: String,
+    pub(crate) ideal_key_stroke: String,
+}
+
+/// Changes of [`DisplayInfo`] since last construction.
+///
+/// Strings are given as edits to strings at last construction, so UI can keep strings and
+/// update only changed ranges instead of receiving whole strings every frame.
+/// Informations which do not grow with query such as progress and speed are given as they are.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DisplayInfoDelta {
+    newly_confirmed_chunk_count: usize,
+    view: TextDelta,
+    view_last_position: usize,
+    spell: TextDelta,
+    spell_last_position: usize,
+    key_stroke: TextDelta,
+    ideal_key_stroke: TextDelta,
+    #[cfg(feature = "statistics")]
+    combo: usize,
+    #[cfg(feature = "statistics")]
+    max_combo: usize,
+    #[cfg(feature = "statistics")]
+    newly_completed_lap_summaries: Vec<LapSummary>,
+    progress: ProgressDisplayInfo,
+    speed: SpeedDisplayInfo,
+    pace: Option<PaceDisplayInfo>,
+}
+
+impl DisplayInfoDelta {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        newly_confirmed_chunk_count: usize,
+        view: TextDelta,
+        view_last_position: usize,
+        spell: TextDelta,
+        spell_last_position: usize,
+        key_stroke: TextDelta,
+        ideal_key_stroke: TextDelta,
+        #[cfg(feature = "statistics")] (combo, max_combo): (usize, usize),
+        #[cfg(feature = "statistics")] newly_completed_lap_summaries: Vec<LapSummary>,
+        progress: ProgressDisplayInfo,
+        speed: SpeedDisplayInfo,
+        pace: Option<PaceDisplayInfo>,
+    ) -> Self {
+        #[cfg(feature = "statistics")]
+        assert!(combo <= max_combo);
+
+        Self {
+            newly_confirmed_chunk_count,
+            view,
+            view_last_position,
+            spell,
+            spell_last_position,
+            key_stroke,
+            ideal_key_stroke,
+            #[cfg(feature = "statistics")]
+            combo,
+            #[cfg(feature = "statistics")]
+            max_combo,
+            #[cfg(feature = "statistics")]
+            newly_completed_lap_summaries,
+            progress,
+            speed,
+            pace,
+        }
+    }
+
+    /// Get count of chunks confirmed since last construction.
+    pub fn newly_confirmed_chunk_count(&self) -> usize {
+        self.newly_confirmed_chunk_count
+    }
+
+    /// Get changes of query string itself.
+    ///
+    /// See [`ViewDisplayInfo`] for meanings of positions.
+    pub fn view(&self) -> &TextDelta {
+        &self.view
+    }
+
+    /// Get index of last view string to be typed.
+    pub fn view_last_position(&self) -> usize {
+        self.view_last_position
+    }
+
+    /// Get changes of spell of query string.
+    ///
+    /// See [`SpellDisplayInfo`] for meanings of positions.
+    pub fn spell(&self) -> &TextDelta {
+        &self.spell
+    }
+
+    /// Get index of last spell to be typed.
+    pub fn spell_last_position(&self) -> usize {
+        self.spell_last_position
+    }
+
+    /// Get changes of key strokes of query string.
+    ///
+    /// See [`KeyStrokeDisplayInfo`] for meanings of positions.
+    pub fn key_stroke(&self) -> &TextDelta {
+        &self.key_stroke
+    }
+
+    /// Get changes of ideal key strokes of query string.
+    ///
+    /// Ideal key strokes have no missed positions.
+    pub fn ideal_key_stroke(&self) -> &TextDelta {
+        &self.ideal_key_stroke
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get count of consecutive correct key strokes at this time.
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get max count of consecutive correct key strokes at this time.
+    pub fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get summaries of laps completed since last construction.
+    ///
+    /// When lap request differs from last construction, all completed laps of the new lap
+    /// request are returned.
+    pub fn newly_completed_lap_summaries(&self) -> &[LapSummary] {
+        &self.newly_completed_lap_summaries
+    }
+
+    /// Get an information about progress of query.
+    pub fn progress_info(&self) -> &ProgressDisplayInfo {
+        &self.progress
+    }
+
+    /// Get an information about typing speed of recent key strokes.
+    pub fn speed_info(&self) -> &SpeedDisplayInfo {
+        &self.speed
+    }
+
+    /// Get an information about progress compared with target speed.
+    ///
+    /// If target speed is not configured, this function returns `None`.
+    pub fn pace_info(&self) -> Option<&PaceDisplayInfo> {
+        self.pace.as_ref()
+    }
+}
+
+/// Changes of a string of [`DisplayInfo`] since last construction of [`DisplayInfoDelta`].
+///
+/// Positions are indices of characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TextDelta {
+    edits: Vec<TextEdit>,
+    cursor_positions: Vec<usize>,
+    missed_positions_from: usize,
+    missed_positions: Vec<usize>,
+}
+
+impl TextDelta {
+    pub(crate) fn new(
+        edits: Vec<TextEdit>,
+        cursor_positions: Vec<usize>,
+        missed_positions_from: usize,
+        missed_positions: Vec<usize>,
+    ) -> Self {
+        assert!(missed_positions
+            .iter()
+            .all(|position| *position >= missed_positions_from));
+
+        // 何も変えない編集は送らない
+        let edits = edits
+            .into_iter()
+            .filter(|edit| edit.removed_count != 0 || !edit.inserted.is_empty())
+            .collect();
+
+        Self {
+            edits,
+            cursor_positions,
+            missed_positions_from,
+            missed_positions,
+        }
+    }
+
+    /// Get edits to be applied to string at last construction in order.
+    ///
+    /// Position of each edit is in the string which preceding edits are applied to.
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+
+    /// Get cursor positions at this time.
+    pub fn cursor_positions(&self) -> &[usize] {
+        &self.cursor_positions
+    }
+
+    /// Get position from which missed positions are given again.
+    ///
+    /// Missed positions before this position are the same as last construction.
+    pub fn missed_positions_from(&self) -> usize {
+        self.missed_positions_from
+    }
+
+    /// Get missed positions after [`missed_positions_from`](Self::missed_positions_from()).
+    pub fn missed_positions(&self) -> &[usize] {
+        &self.missed_positions
+    }
+
+    /// Apply edits to `text` which is the string at last construction.
+    pub fn apply(&self, text: &mut String) {
+        self.edits.iter().for_each(|edit| edit.apply(text));
+    }
+
+    /// Apply changes to `missed_positions` which are the missed positions at last construction.
+    pub fn apply_missed_positions(&self, missed_positions: &mut Vec<usize>) {
+        missed_positions.retain(|position| *position < self.missed_positions_from);
+        missed_positions.extend_from_slice(&self.missed_positions);
+    }
+}
+
+/// A replacement of characters of a string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TextEdit {
+    position: usize,
+    removed_count: usize,
+    inserted: String,
+}
+
+impl TextEdit {
+    pub(crate) fn new(position: usize, removed_count: usize, inserted: String) -> Self {
+        Self {
+            position,
+            removed_count,
+            inserted,
+        }
+    }
+
+    /// Get position of first replaced character.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Get count of characters removed from [`position`](Self::position()).
+    pub fn removed_count(&self) -> usize {
+        self.removed_count
+    }
+
+    /// Get string inserted at [`position`](Self::position()) instead of removed characters.
+    pub fn inserted(&self) -> &str {
+        &self.inserted
+    }
+
+    /// Apply this edit to `text`.
+    pub fn apply(&self, text: &mut String) {
+        let byte_position = |char_position: usize| {
+            text.char_indices()
+                .nth(char_position)
+                .map_or(text.len(), |(byte_position, _)| byte_position)
+        };
+        let start = byte_position(self.position);
+        let end = byte_position(self.position + self.removed_count);
+
+        text.replace_range(start..end, &self.inserted);
+    }
+}
+
+/// Changes between two [`DisplayInfo`] computed by [`DisplayInfo::diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DisplayInfoDiff {
+    unchanged_key_stroke_count: usize,
+    previous_key_stroke_cursor_position: usize,
+    key_stroke_cursor_position: usize,
+    newly_missed_key_stroke_positions: Vec<usize>,
+    previous_spell_cursor_positions: Vec<usize>,
+    spell_cursor_positions: Vec<usize>,
+    newly_missed_spell_positions: Vec<usize>,
+    #[cfg(feature = "statistics")]
+    newly_completed_lap_indices: Vec<Vec<usize>>,
+}
+
+impl DisplayInfoDiff {
+    /// Get count of leading key strokes which are the same as previous frame.
+    ///
+    /// Key strokes after this position are appended or replaced by change of candidates.
+    pub fn unchanged_key_stroke_count(&self) -> usize {
+        self.unchanged_key_stroke_count
+    }
+
+    /// Get cursor position of key strokes at previous frame.
+    pub fn previous_key_stroke_cursor_position(&self) -> usize {
+        self.previous_key_stroke_cursor_position
+    }
+
+    /// Get cursor position of key strokes at this frame.
+    pub fn key_stroke_cursor_position(&self) -> usize {
+        self.key_stroke_cursor_position
+    }
+
+    /// Get whether cursor of key strokes or spell is moved since previous frame.
+    pub fn is_cursor_moved(&self) -> bool {
+        self.previous_key_stroke_cursor_position != self.key_stroke_cursor_position
+            || self.previous_spell_cursor_positions != self.spell_cursor_positions
+    }
+
+    /// Get positions of key strokes which are newly missed since previous frame.
+    pub fn newly_missed_key_stroke_positions(&self) -> &[usize] {
+        &self.newly_missed_key_stroke_positions
+    }
+
+    /// Get cursor positions of spell at previous frame.
+    pub fn previous_spell_cursor_positions(&self) -> &[usize] {
+        &self.previous_spell_cursor_positions
+    }
+
+    /// Get cursor positions of spell at this frame.
+    pub fn spell_cursor_positions(&self) -> &[usize] {
+        &self.spell_cursor_positions
+    }
+
+    /// Get positions of spell which are newly missed since previous frame.
+    pub fn newly_missed_spell_positions(&self) -> &[usize] {
+        &self.newly_missed_spell_positions
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get indices of laps newly completed since previous frame for each lap info.
+    ///
+    /// Elements are in the same order as [`DisplayInfo::lap_infos`].
+    pub fn newly_completed_lap_indices(&self) -> &[Vec<usize>] {
+        &self.newly_completed_lap_indices
+    }
+}
+
+// 前回はなかった位置を取り出す
+fn newly_added_positions(positions: &[usize], prev_positions: &[usize]) -> Vec<usize> {
+    positions
+        .iter()
+        .filter(|position| !prev_positions.contains(position))
+        .copied()
+        .collect()
+}
+
+/// A request for range of window of [`WindowedDisplayInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DisplayWindowRequest {
+    /// Window consists of `before` chunks before the chunk currently typed, that chunk and
+    /// `after` chunks after it.
+    Chunk { before: usize, after: usize },
+    /// Window consists of `before` characters before cursor, characters at cursor and `after`
+    /// characters after it.
+    ///
+    /// Windows of spell and key strokes are determined by their own cursor positions.
+    Character { before: usize, after: usize },
+}
+
+/// Information about spell and key strokes restricted to a window around cursor.
+///
+/// This is useful for UI whose width is limited such as terminal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WindowedDisplayInfo {
+    spell: WindowedTextDisplayInfo,
+    key_stroke: WindowedTextDisplayInfo,
+}
+
+impl WindowedDisplayInfo {
+    pub(crate) fn new(
+        spell_display_info: &SpellDisplayInfo,
+        key_stroke_display_info: &KeyStrokeDisplayInfo,
+        window_request: &DisplayWindowRequest,
+        current_chunk_index: usize,
+        spell_chunk_heads: &[usize],
+        key_stroke_chunk_heads: &[usize],
+    ) -> Self {
+        let key_stroke_cursor_positions = [key_stroke_display_info.current_cursor_position()];
+
+        Self {
+            spell: WindowedTextDisplayInfo::new(
+                spell_display_info.spell(),
+                spell_display_info.current_cursor_positions(),
+                spell_display_info.missed_positions(),
+                window_request,
+                current_chunk_index,
+                spell_chunk_heads,
+            ),
+            key_stroke: WindowedTextDisplayInfo::new(
+                key_stroke_display_info.key_stroke(),
+                &key_stroke_cursor_positions,
+                key_stroke_display_info.missed_positions(),
+                window_request,
+                current_chunk_index,
+                key_stroke_chunk_heads,
+            ),
+        }
+    }
+
+    /// Get an information about spell in window.
+    pub fn spell(&self) -> &WindowedTextDisplayInfo {
+        &self.spell
+    }
+
+    /// Get an information about key strokes in window.
+    pub fn key_stroke(&self) -> &WindowedTextDisplayInfo {
+        &self.key_stroke
+    }
+}
+
+/// Information about a string restricted to a window.
+///
+/// All positions are relative to head of window.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WindowedTextDisplayInfo {
+    text: String,
+    head_position: usize,
+    cursor_positions: Vec<usize>,
+    missed_positions: Vec<usize>,
+}
+
+impl WindowedTextDisplayInfo {
+    fn new(
+        whole_text: &str,
+        whole_cursor_positions: &[usize],
+        whole_missed_positions: &[usize],
+        window_request: &DisplayWindowRequest,
+        current_chunk_index: usize,
+        chunk_heads: &[usize],
+    ) -> Self {
+        let text_count = whole_text.chars().count();
+
+        // 窓の範囲を元の文字列上の半開区間で表す
+        let (head, tail) = match window_request {
+            DisplayWindowRequest::Chunk { before, after } => {
+                let chunk_count = chunk_heads.len() - 1;
+                let tail_chunk = (current_chunk_index + after + 1).min(chunk_count);
+                let head_chunk = current_chunk_index.saturating_sub(*before).min(tail_chunk);
+
+                (chunk_heads[head_chunk], chunk_heads[tail_chunk])
+            }
+            DisplayWindowRequest::Character { before, after } => {
+                // カーソルが複数ある場合には全てのカーソルを含める
+                let first_cursor_position = *whole_cursor_positions.iter().min().unwrap();
+                let last_cursor_position = *whole_cursor_positions.iter().max().unwrap();
+
+                let tail = (last_cursor_position + after + 1).min(text_count);
+                let head = first_cursor_position.saturating_sub(*before).min(tail);
+
+                (head, tail)
+            }
+        };
+
+        Self {
+            text: whole_text.chars().skip(head).take(tail - head).collect(),
+            head_position: head,
+            // 全て打ち終えた場合にはカーソルは末尾の次を指すので窓の末尾の次も含める
+            cursor_positions: whole_cursor_positions
+                .iter()
+                .filter(|position| (head..=tail).contains(*position))
+                .map(|position| position - head)
+                .collect(),
+            missed_positions: whole_missed_positions
+                .iter()
+                .filter(|position| (head..tail).contains(*position))
+                .map(|position| position - head)
+                .collect(),
+        }
+    }
+
+    /// String in window.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Position of head of window in whole string.
+    pub fn head_position(&self) -> usize {
+        self.head_position
+    }
+
+    /// Cursor positions in window.
+    ///
+    /// If cursor is out of window, this function returns empty vector.
+    pub fn cursor_positions(&self) -> &Vec<usize> {
+        &self.cursor_positions
+    }
+
+    /// Positions in window which are not correctly typed.
+    pub fn missed_positions(&self) -> &Vec<usize> {
+        &self.missed_positions
+    }
+}
+
+#[cfg(feature = "statistics")]
+/// Information about laps taken by a lap request.
+///
+/// Laps are taken on target of lap request, and lap end positions are also converted to other
+/// targets like [`SpellDisplayInfo`] and [`KeyStrokeDisplayInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LapDisplayInfo {
+    lap_request: LapRequest,
+    spell: OnTypingStatisticsTarget,
+    key_stroke: OnTypingStatisticsTarget,
+    ideal_key_stroke: OnTypingStatisticsTarget,
+    lap_summaries: Vec<LapSummary>,
+}
+
+#[cfg(feature = "statistics")]
+impl LapDisplayInfo {
+    pub(crate) fn new(
+        lap_request: LapRequest,
+        spell_display_info: &SpellDisplayInfo,
+        key_stroke_display_info: &KeyStrokeDisplayInfo,
+        lap_summaries: Vec<LapSummary>,
+    ) -> Self {
+        Self::with_statistics(
+            lap_request,
+            spell_display_info.on_typing_statistics().clone(),
+            key_stroke_display_info.on_typing_statistics().clone(),
+            key_stroke_display_info.on_typing_statistics_ideal().clone(),
+            lap_summaries,
+        )
+    }
+
+    // 表示用の文字列を構築せずに統計情報のみから構築する
+    pub(crate) fn with_statistics(
+        lap_request: LapRequest,
+        spell: OnTypingStatisticsTarget,
+        key_stroke: OnTypingStatisticsTarget,
+        ideal_key_stroke: OnTypingStatisticsTarget,
+        lap_summaries: Vec<LapSummary>,
+    ) -> Self {
+        Self {
+            lap_request,
+            spell,
+            key_stroke,
+            ideal_key_stroke,
+            lap_summaries,
+        }
+    }
+
+    /// Get lap request by which laps are taken.
+    pub fn lap_request(&self) -> &LapRequest {
+        &self.lap_request
+    }
+
+    /// Get statistics of spell with laps.
+    pub fn spell(&self) -> &OnTypingStatisticsTarget {
+        &self.spell
+    }
+
+    /// Get statistics of actual key strokes with laps.
+    pub fn key_stroke(&self) -> &OnTypingStatisticsTarget {
+        &self.key_stroke
+    }
+
+    /// Get statistics of ideal key strokes with laps.
+    pub fn ideal_key_stroke(&self) -> &OnTypingStatisticsTarget {
+        &self.ideal_key_stroke
+    }
+
+    /// Get summaries of key strokes of each finished lap in order.
+    ///
+    /// This is useful to show speed and accuracy of each lap.
+    pub fn lap_summaries(&self) -> &[LapSummary] {
+        &self.lap_summaries
+    }
 }
 
 /// Information about query string itself.
@@ -52,17 +843,17 @@ impl ViewDisplayInfo {
     pub(crate) fn new(
         spell_display_info: &SpellDisplayInfo,
         view: String,
-        view_position_of_spell_position: Vec<ViewPosition>,
+        view_position_of_spell_position: &[ViewPosition],
     ) -> Self {
         Self {
             view,
             current_cursor_positions: convert_spell_positions_to_view_positions(
                 &spell_display_info.current_cursor_positions,
-                &view_position_of_spell_position,
+                view_position_of_spell_position,
             ),
             missed_positions: convert_spell_positions_to_view_positions(
                 &spell_display_info.missed_positions,
-                &view_position_of_spell_position,
+                view_position_of_spell_position,
             ),
             last_position: view_position_of_spell_position[spell_display_info.last_position]
                 .last_position(),
@@ -111,6 +902,7 @@ pub struct SpellDisplayInfo {
     // タイプすべき最後のチャンクの綴りの末尾の位置
     // クエリをタイプ数で指定する場合には語彙の途中のチャンクで切れている可能性がある
     last_position: usize,
+    #[cfg(feature = "statistics")]
     on_typing_statistics: OnTypingStatisticsTarget,
 }
 
@@ -120,20 +912,33 @@ impl SpellDisplayInfo {
         current_cursor_positions: Vec<usize>,
         missed_positions: Vec<usize>,
         last_position: usize,
-        on_typing_statistics: OnTypingStatisticsTarget,
+        #[cfg(feature = "statistics")] on_typing_statistics: OnTypingStatisticsTarget,
     ) -> Self {
         Self {
             spell,
             current_cursor_positions,
             missed_positions,
             last_position,
+            #[cfg(feature = "statistics")]
             on_typing_statistics,
         }
     }
 
+    // 綴りの文字列のみを差し替える
+    // 各位置は差し替える前の綴りと1文字ずつ対応している必要がある
+    pub(crate) fn with_spell(self, spell: String) -> Self {
+        assert_eq!(spell.chars().count(), self.spell.chars().count());
+
+        Self { spell, ..self }
+    }
+
     /// Spell of query string.
     ///
     /// ex. When query string is `巨大`, this function returns `きょだい`.
+    ///
+    /// If presentation spell is configured by
+    /// [`VocabularyEntry::with_presentation_spell`](crate::VocabularyEntry::with_presentation_spell()),
+    /// it is used instead.
     pub fn spell(&self) -> &str {
         &self.spell
     }
@@ -162,6 +967,11 @@ impl SpellDisplayInfo {
     pub fn last_position(&self) -> usize {
         self.last_position
     }
+
+    #[cfg(feature = "statistics")]
+    pub(crate) fn on_typing_statistics(&self) -> &OnTypingStatisticsTarget {
+        &self.on_typing_statistics
+    }
 }
 
 /// Information about key stroke of query string.
@@ -170,8 +980,16 @@ pub struct KeyStrokeDisplayInfo {
     key_stroke: String,
     current_cursor_position: usize,
     missed_positions: Vec<usize>,
+    #[cfg(feature = "statistics")]
     on_typing_statistics: OnTypingStatisticsTarget,
+    #[cfg(feature = "statistics")]
     on_typing_statistics_ideal: OnTypingStatisticsTarget,
+    ideal_key_stroke: String,
+    ideal_cursor_position: usize,
+    #[cfg(feature = "statistics")]
+    combo: usize,
+    #[cfg(feature = "statistics")]
+    max_combo: usize,
 }
 
 impl KeyStrokeDisplayInfo {
@@ -179,15 +997,50 @@ impl KeyStrokeDisplayInfo {
         key_stroke: String,
         current_cursor_position: usize,
         missed_positions: Vec<usize>,
-        on_typing_statistics: OnTypingStatisticsTarget,
-        on_typing_statistics_ideal: OnTypingStatisticsTarget,
+        #[cfg(feature = "statistics")] on_typing_statistics: OnTypingStatisticsTarget,
+        #[cfg(feature = "statistics")] on_typing_statistics_ideal: OnTypingStatisticsTarget,
     ) -> Self {
         Self {
             key_stroke,
             current_cursor_position,
             missed_positions,
+            #[cfg(feature = "statistics")]
             on_typing_statistics,
+            #[cfg(feature = "statistics")]
             on_typing_statistics_ideal,
+            ideal_key_stroke: String::new(),
+            ideal_cursor_position: 0,
+            #[cfg(feature = "statistics")]
+            combo: 0,
+            #[cfg(feature = "statistics")]
+            max_combo: 0,
+        }
+    }
+
+    #[cfg(feature = "statistics")]
+    // コンボ数はチャンクごとには求まらないので別途設定する
+    pub(crate) fn with_combo(self, combo: usize, max_combo: usize) -> Self {
+        assert!(combo <= max_combo);
+
+        Self {
+            combo,
+            max_combo,
+            ..self
+        }
+    }
+
+    // 理想的なキーストロークの文字列とその中でのカーソル位置を設定する
+    pub(crate) fn with_ideal_key_stroke(
+        self,
+        ideal_key_stroke: String,
+        ideal_cursor_position: usize,
+    ) -> Self {
+        assert!(ideal_cursor_position <= ideal_key_stroke.chars().count());
+
+        Self {
+            ideal_key_stroke,
+            ideal_cursor_position,
+            ..self
         }
     }
 
@@ -206,11 +1059,620 @@ impl KeyStrokeDisplayInfo {
         &self.missed_positions
     }
 
+    #[cfg(feature = "statistics")]
     pub fn on_typing_statistics(&self) -> &OnTypingStatisticsTarget {
         &self.on_typing_statistics
     }
 
+    #[cfg(feature = "statistics")]
     pub fn on_typing_statistics_ideal(&self) -> &OnTypingStatisticsTarget {
         &self.on_typing_statistics_ideal
     }
+
+    /// Ideal key strokes of query string, that is, key strokes typed with ideal candidate of each
+    /// chunk.
+    ///
+    /// This is useful to show optimal key strokes alongside key strokes actually typed.
+    /// Chunks already typed are also represented by ideal key strokes regardless of actually
+    /// typed key strokes.
+    /// Lap end positions of [`on_typing_statistics_ideal`](Self::on_typing_statistics_ideal())
+    /// are positions in this string.
+    ///
+    /// ex. When query is `きょ` and `kilyo` is typed, key stroke string is `kilyo` but ideal key
+    /// stroke string is `kyo`.
+    pub fn ideal_key_stroke(&self) -> &str {
+        &self.ideal_key_stroke
+    }
+
+    /// Index of [`ideal_key_stroke`](Self::ideal_key_stroke()) corresponding to
+    /// [`current_cursor_position`](Self::current_cursor_position()).
+    ///
+    /// When chunk currently typed is typed with key strokes different from ideal one, position is
+    /// converted in proportion to typed key strokes.
+    pub fn ideal_cursor_position(&self) -> usize {
+        self.ideal_cursor_position
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Count of correct key strokes typed in a row without wrong key stroke.
+    ///
+    /// Combo is reset to 0 by a wrong key stroke.
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Max [`combo`](Self::combo()) reached so far.
+    pub fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+
+    /// Construct [`KeyStrokeDisplayInfo`] whose key stroke string is converted to uppercase.
+    ///
+    /// This is a shortcut of [`transform`](Self::transform()) with uppercase conversion.
+    pub fn to_uppercase(&self) -> Self {
+        self.transform(|c| c.to_uppercase().collect())
+    }
+
+    /// Construct [`KeyStrokeDisplayInfo`] whose key stroke string is transformed per character.
+    ///
+    /// This transformation only affects display and key strokes are still matched by original
+    /// characters.
+    /// Because transformed string of a character may have different length, cursor position,
+    /// missed positions and lap end positions are also converted to positions in transformed string.
+    /// [`ideal_key_stroke`](Self::ideal_key_stroke()) is also transformed in the same way.
+    ///
+    /// ex. When key stroke string is `kyo` and `k` is missed and transformation converts `k` to `[k]`,
+    /// key stroke string becomes `[k]yo` and missed positions become `[0,1,2]`.
+    pub fn transform<F: Fn(char) -> String>(&self, transformation: F) -> Self {
+        self.transform_with_heads(&transformation).0
+    }
+
+    // 変換後の情報と合わせて変換前の各文字が変換後の文字列のどこから始まるかを返す
+    // 他の情報に含まれるキーストロークの位置を同じように変換するのに使う
+    fn transform_with_heads<F: Fn(char) -> String>(
+        &self,
+        transformation: &F,
+    ) -> (Self, Vec<usize>) {
+        let (key_stroke, transformed_heads) = transform_string(&self.key_stroke, transformation);
+        let (ideal_key_stroke, ideal_transformed_heads) =
+            transform_string(&self.ideal_key_stroke, transformation);
+
+        let missed_positions = self
+            .missed_positions
+            .iter()
+            .flat_map(|position| transformed_heads[*position]..transformed_heads[*position + 1])
+            .collect();
+
+        #[cfg(feature = "statistics")]
+        let on_typing_statistics =
+            self.on_typing_statistics
+                .convert_lap_end_positions(|position| {
+                    transformed_lap_end_position(&transformed_heads, position)
+                });
+
+        // 理想的なキーストロークの文字列が構築されていない場合にはラップ末の位置を変換できない
+        #[cfg(feature = "statistics")]
+        let on_typing_statistics_ideal = if self.ideal_key_stroke.is_empty() {
+            self.on_typing_statistics_ideal.clone()
+        } else {
+            self.on_typing_statistics_ideal
+                .convert_lap_end_positions(|position| {
+                    transformed_lap_end_position(&ideal_transformed_heads, position)
+                })
+        };
+
+        let transformed = Self {
+            key_stroke,
+            current_cursor_position: transformed_heads[self.current_cursor_position],
+            missed_positions,
+            #[cfg(feature = "statistics")]
+            on_typing_statistics,
+            #[cfg(feature = "statistics")]
+            on_typing_statistics_ideal,
+            ideal_key_stroke,
+            ideal_cursor_position: ideal_transformed_heads[self.ideal_cursor_position],
+            #[cfg(feature = "statistics")]
+            combo: self.combo,
+            #[cfg(feature = "statistics")]
+            max_combo: self.max_combo,
+        };
+
+        (transformed, transformed_heads)
+    }
+}
+
+// 文字列を1文字ずつ変換し変換前の各文字が変換後の文字列のどこから始まるかと合わせて返す
+// 末尾にはカーソル位置の変換のために変換後の文字数を入れておく
+fn transform_string<F: Fn(char) -> String>(
+    string: &str,
+    transformation: &F,
+) -> (String, Vec<usize>) {
+    let mut transformed_string = String::new();
+    let mut transformed_heads: Vec<usize> = vec![];
+
+    let mut transformed_count = 0;
+    string.chars().for_each(|c| {
+        let transformed = transformation(c);
+
+        transformed_heads.push(transformed_count);
+        transformed_count += transformed.chars().count();
+        transformed_string.push_str(&transformed);
+    });
+    transformed_heads.push(transformed_count);
+
+    (transformed_string, transformed_heads)
+}
+
+#[cfg(feature = "statistics")]
+// ラップ末は変換前の文字を変換した文字列の最後の文字の位置に変換する
+fn transformed_lap_end_position(transformed_heads: &[usize], position: usize) -> usize {
+    transformed_heads[position + 1].saturating_sub(1)
+}
+
+/// Information about line breaks inserted by [`VocabularySeparator::Newline`](crate::VocabularySeparator::Newline).
+///
+/// Each position is a position of the first character of a new line, so characters before it
+/// should be rendered in the previous line.
+/// Line breaks are not included in strings of query, so cursor positions and wrong positions
+/// can be used as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LineBreakDisplayInfo {
+    view_positions: Vec<usize>,
+    spell_positions: Vec<usize>,
+    key_stroke_positions: Vec<usize>,
+}
+
+impl LineBreakDisplayInfo {
+    pub(crate) fn new(
+        view_positions: Vec<usize>,
+        spell_positions: Vec<usize>,
+        key_stroke_positions: Vec<usize>,
+    ) -> Self {
+        assert_eq!(view_positions.len(), spell_positions.len());
+        assert_eq!(view_positions.len(), key_stroke_positions.len());
+
+        Self {
+            view_positions,
+            spell_positions,
+            key_stroke_positions,
+        }
+    }
+
+    /// Positions of line breaks in [`view`](ViewDisplayInfo::view()).
+    pub fn view_positions(&self) -> &Vec<usize> {
+        &self.view_positions
+    }
+
+    /// Positions of line breaks in [`spell`](SpellDisplayInfo::spell()).
+    pub fn spell_positions(&self) -> &Vec<usize> {
+        &self.spell_positions
+    }
+
+    /// Positions of line breaks in [`key_stroke`](KeyStrokeDisplayInfo::key_stroke()).
+    ///
+    /// Positions after the current chunk can move because key strokes of them are not decided
+    /// yet.
+    pub fn key_stroke_positions(&self) -> &Vec<usize> {
+        &self.key_stroke_positions
+    }
+}
+
+/// Information about progress of query.
+///
+/// This is useful to compose progress bars which move every key stroke instead of jumping at
+/// chunk boundaries.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProgressDisplayInfo {
+    finished_chunk_count: usize,
+    whole_chunk_count: usize,
+    // タイプ中のチャンクの現在の候補内のカーソル位置と候補のキーストローク数
+    in_chunk_key_stroke_cursor_position: usize,
+    in_chunk_key_stroke_count: usize,
+    // 理想的なキーストローク系列での進捗
+    // 打つ候補によらないので残り時間の見積もりに使う
+    ideal_key_stroke_cursor_position: usize,
+    ideal_key_stroke_count: usize,
+    elapsed_time: Duration,
+}
+
+impl ProgressDisplayInfo {
+    pub(crate) fn new(
+        finished_chunk_count: usize,
+        whole_chunk_count: usize,
+        in_chunk_key_stroke_cursor_position: usize,
+        in_chunk_key_stroke_count: usize,
+    ) -> Self {
+        assert!(finished_chunk_count <= whole_chunk_count);
+        assert!(in_chunk_key_stroke_cursor_position <= in_chunk_key_stroke_count);
+
+        Self {
+            finished_chunk_count,
+            whole_chunk_count,
+            in_chunk_key_stroke_cursor_position,
+            in_chunk_key_stroke_count,
+            ideal_key_stroke_cursor_position: 0,
+            ideal_key_stroke_count: 0,
+            elapsed_time: Duration::ZERO,
+        }
+    }
+
+    /// 理想的なキーストローク系列での進捗と経過時間を付与する
+    pub(crate) fn with_ideal_key_stroke_progress(
+        self,
+        ideal_key_stroke_cursor_position: usize,
+        ideal_key_stroke_count: usize,
+        elapsed_time: Duration,
+    ) -> Self {
+        assert!(ideal_key_stroke_cursor_position <= ideal_key_stroke_count);
+
+        Self {
+            ideal_key_stroke_cursor_position,
+            ideal_key_stroke_count,
+            elapsed_time,
+            ..self
+        }
+    }
+
+    /// Count of chunks which are already typed.
+    pub fn finished_chunk_count(&self) -> usize {
+        self.finished_chunk_count
+    }
+
+    /// Count of all chunks in query.
+    pub fn whole_chunk_count(&self) -> usize {
+        self.whole_chunk_count
+    }
+
+    /// Index of chunk currently typed in query.
+    ///
+    /// Chunks are indexed from 0 in the order of query and appending query does not change
+    /// indices of existing chunks.
+    /// When typing is finished, this function returns `None`.
+    pub fn current_chunk_index(&self) -> Option<usize> {
+        if self.finished_chunk_count < self.whole_chunk_count {
+            Some(self.finished_chunk_count)
+        } else {
+            None
+        }
+    }
+
+    /// Index of key stroke currently typed in active candidate of chunk currently typed.
+    ///
+    /// When typing is finished, this function returns `0`.
+    pub fn in_chunk_key_stroke_cursor_position(&self) -> usize {
+        self.in_chunk_key_stroke_cursor_position
+    }
+
+    /// Count of key strokes of active candidate of chunk currently typed.
+    ///
+    /// When typing is finished, this function returns `0`.
+    pub fn in_chunk_key_stroke_count(&self) -> usize {
+        self.in_chunk_key_stroke_count
+    }
+
+    /// Completion fraction of chunk currently typed in range `0.0..=1.0`.
+    ///
+    /// This is key strokes already typed on active candidate divided by key stroke count of that
+    /// candidate.
+    ///
+    /// ex. When typing `きょ` and active candidate is `kyo`, after key stroke `k` this function
+    /// returns `0.333...`.
+    pub fn chunk_progress(&self) -> f64 {
+        if self.in_chunk_key_stroke_count == 0 {
+            0.0
+        } else {
+            self.in_chunk_key_stroke_cursor_position as f64 / self.in_chunk_key_stroke_count as f64
+        }
+    }
+
+    /// Completion fraction of whole query in range `0.0..=1.0`.
+    ///
+    /// Unlike fraction of finished chunks, this value is interpolated within chunk currently
+    /// typed using [`chunk_progress`](Self::chunk_progress()).
+    pub fn progress(&self) -> f64 {
+        if self.whole_chunk_count == 0 {
+            1.0
+        } else {
+            (self.finished_chunk_count as f64 + self.chunk_progress())
+                / self.whole_chunk_count as f64
+        }
+    }
+
+    /// Count of ideal key strokes which are already typed.
+    ///
+    /// Ideal key strokes do not depend on candidates actually typed, so this is suitable to
+    /// measure progress of chunks such as `きょ` which have candidates of different lengths.
+    pub fn finished_ideal_key_stroke_count(&self) -> usize {
+        self.ideal_key_stroke_cursor_position
+    }
+
+    /// Count of ideal key strokes which are not typed yet.
+    pub fn remaining_ideal_key_stroke_count(&self) -> usize {
+        self.ideal_key_stroke_count - self.ideal_key_stroke_cursor_position
+    }
+
+    /// Percentage of ideal key strokes already typed in range `0.0..=100.0`.
+    ///
+    /// If query has no key strokes, this function returns `100.0`.
+    pub fn ideal_key_stroke_percentage(&self) -> f64 {
+        if self.ideal_key_stroke_count == 0 {
+            100.0
+        } else {
+            self.ideal_key_stroke_cursor_position as f64 * 100.0
+                / self.ideal_key_stroke_count as f64
+        }
+    }
+
+    /// Estimated time to finish remaining ideal key strokes at average pace so far.
+    ///
+    /// If no ideal key stroke is typed yet, pace cannot be estimated and this function returns
+    /// `None`.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.ideal_key_stroke_cursor_position == 0 {
+            return None;
+        }
+
+        Some(self.elapsed_time.mul_f64(
+            self.remaining_ideal_key_stroke_count() as f64
+                / self.ideal_key_stroke_cursor_position as f64,
+        ))
+    }
+}
+
+/// Information about typing speed of recent key strokes.
+///
+/// Unlike statistics of laps, this is available before first lap is finished and follows
+/// changes of speed while typing.
+/// Only correctly typed key strokes are counted and a word is regarded as 5 key strokes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpeedDisplayInfo {
+    // 集計した期間
+    // 開始直後は窓の長さより短い
+    rolling_duration: Duration,
+    rolling_key_stroke_count: usize,
+    // 直近のキーストローク数とその最初と最後の間の時間
+    recent_key_stroke_count: usize,
+    recent_key_stroke_span: Duration,
+}
+
+impl SpeedDisplayInfo {
+    /// Length of rolling window used in [`rolling_kpm`](Self::rolling_kpm()).
+    pub const ROLLING_WINDOW: Duration = Duration::from_secs(5);
+    /// Count of recent key strokes used in [`instantaneous_kpm`](Self::instantaneous_kpm()).
+    pub const INSTANTANEOUS_KEY_STROKE_COUNT: usize = 5;
+
+    pub(crate) fn new(
+        rolling_duration: Duration,
+        rolling_key_stroke_count: usize,
+        recent_key_stroke_count: usize,
+        recent_key_stroke_span: Duration,
+    ) -> Self {
+        Self {
+            rolling_duration,
+            rolling_key_stroke_count,
+            recent_key_stroke_count,
+            recent_key_stroke_span,
+        }
+    }
+
+    /// Duration over which [`rolling_kpm`](Self::rolling_kpm()) is computed.
+    ///
+    /// This is [`ROLLING_WINDOW`](Self::ROLLING_WINDOW) except just after starting, when this
+    /// is elapsed time from start.
+    pub fn rolling_duration(&self) -> Duration {
+        self.rolling_duration
+    }
+
+    /// Count of key strokes in [`rolling_duration`](Self::rolling_duration()).
+    pub fn rolling_key_stroke_count(&self) -> usize {
+        self.rolling_key_stroke_count
+    }
+
+    /// Key strokes per minute in [`rolling_duration`](Self::rolling_duration()).
+    ///
+    /// This decreases while not typing.
+    pub fn rolling_kpm(&self) -> f64 {
+        per_minute(self.rolling_key_stroke_count, self.rolling_duration)
+    }
+
+    /// Words per minute in [`rolling_duration`](Self::rolling_duration()).
+    pub fn rolling_wpm(&self) -> f64 {
+        self.rolling_kpm() / 5.0
+    }
+
+    /// Key strokes per minute computed from intervals of last
+    /// [`INSTANTANEOUS_KEY_STROKE_COUNT`](Self::INSTANTANEOUS_KEY_STROKE_COUNT) key strokes.
+    ///
+    /// This does not change while not typing.
+    /// If there are less than 2 key strokes, this function returns `0.0`.
+    pub fn instantaneous_kpm(&self) -> f64 {
+        per_minute(
+            self.recent_key_stroke_count.saturating_sub(1),
+            self.recent_key_stroke_span,
+        )
+    }
+
+    /// Words per minute computed from intervals of last
+    /// [`INSTANTANEOUS_KEY_STROKE_COUNT`](Self::INSTANTANEOUS_KEY_STROKE_COUNT) key strokes.
+    pub fn instantaneous_wpm(&self) -> f64 {
+        self.instantaneous_kpm() / 5.0
+    }
+}
+
+/// Information about progress compared with an imaginary player typing at target speed.
+///
+/// Progress is measured by cursor position in ideal key strokes, so it does not depend on
+/// candidates chosen while typing.
+/// Imaginary player starts at the same time and types ideal key strokes at constant speed until
+/// the end of query.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PaceDisplayInfo {
+    target_kpm: NonZeroUsize,
+    elapsed_time: Duration,
+    ideal_key_stroke_cursor_position: usize,
+    ideal_key_stroke_count: usize,
+}
+
+impl PaceDisplayInfo {
+    pub(crate) fn new(
+        target_kpm: NonZeroUsize,
+        elapsed_time: Duration,
+        ideal_key_stroke_cursor_position: usize,
+        ideal_key_stroke_count: usize,
+    ) -> Self {
+        Self {
+            target_kpm,
+            elapsed_time,
+            ideal_key_stroke_cursor_position,
+            ideal_key_stroke_count,
+        }
+    }
+
+    /// Get target key strokes per minute.
+    pub fn target_kpm(&self) -> NonZeroUsize {
+        self.target_kpm
+    }
+
+    /// Get target words per minute.
+    pub fn target_wpm(&self) -> f64 {
+        self.target_kpm.get() as f64 / 5.0
+    }
+
+    /// Get cursor position in ideal key strokes which target speed reaches at current elapsed
+    /// time.
+    ///
+    /// This does not exceed the number of ideal key strokes of the whole query.
+    pub fn target_cursor_position(&self) -> usize {
+        let position =
+            self.elapsed_time.as_millis() * self.target_kpm.get() as u128 / MILLIS_PER_MINUTE;
+
+        position.min(self.ideal_key_stroke_count as u128) as usize
+    }
+
+    /// Get how many ideal key strokes the user is ahead of target speed.
+    ///
+    /// Negative value means the user is behind.
+    pub fn key_stroke_lead(&self) -> isize {
+        self.ideal_key_stroke_cursor_position as isize - self.target_cursor_position() as isize
+    }
+
+    /// Get how many milliseconds the user is ahead of target speed.
+    ///
+    /// This is the time target speed takes to reach current cursor position minus current
+    /// elapsed time, so negative value means the user is behind.
+    pub fn time_lead_millis(&self) -> i64 {
+        let target_time = self.ideal_key_stroke_cursor_position as u128 * MILLIS_PER_MINUTE
+            / self.target_kpm.get() as u128;
+
+        target_time as i64 - self.elapsed_time.as_millis() as i64
+    }
+}
+
+const MILLIS_PER_MINUTE: u128 = 60_000;
+
+// 期間が0の場合には速度は0とする
+pub(crate) fn per_minute(count: usize, duration: Duration) -> f64 {
+    if duration.is_zero() {
+        0.0
+    } else {
+        count as f64 * 60.0 / duration.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "statistics")]
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn transform_key_stroke_display_info_1() {
+        let ksdi = KeyStrokeDisplayInfo::new(
+            "kyoudai".to_string(),
+            4,
+            vec![1, 3],
+            #[cfg(feature = "statistics")]
+            OnTypingStatisticsTarget::new(
+                4,
+                7,
+                2,
+                2,
+                NonZeroUsize::new(3),
+                Some(vec![]),
+                vec![2, 5],
+            ),
+            #[cfg(feature = "statistics")]
+            OnTypingStatisticsTarget::new(4, 7, 2, 2, None, None, vec![]),
+        );
+
+        let uppercase = ksdi.to_uppercase();
+        assert_eq!(uppercase.key_stroke(), "KYOUDAI");
+        assert_eq!(uppercase.current_cursor_position(), 4);
+        assert_eq!(uppercase.missed_positions(), &vec![1, 3]);
+        #[cfg(feature = "statistics")]
+        {
+            assert_eq!(
+                uppercase.on_typing_statistics().lap_end_positions(),
+                &vec![2, 5]
+            );
+            assert_eq!(
+                uppercase
+                    .on_typing_statistics()
+                    .lap_index_of_position(uppercase.current_cursor_position()),
+                1
+            );
+            assert_eq!(uppercase.on_typing_statistics().lap_index_of_position(2), 0);
+            assert_eq!(uppercase.on_typing_statistics().lap_index_of_position(6), 2);
+        }
+
+        let bracketed = ksdi.transform(|c| {
+            if c == 'y' {
+                "[y]".to_string()
+            } else {
+                c.to_string()
+            }
+        });
+        assert_eq!(bracketed.key_stroke(), "k[y]oudai");
+        assert_eq!(bracketed.current_cursor_position(), 6);
+        assert_eq!(bracketed.missed_positions(), &vec![1, 2, 3, 5]);
+        #[cfg(feature = "statistics")]
+        assert_eq!(
+            bracketed.on_typing_statistics().lap_end_positions(),
+            &vec![4, 7]
+        );
+    }
+
+    #[test]
+    fn progress_display_info_1() {
+        let pdi = ProgressDisplayInfo::new(1, 4, 1, 2);
+
+        assert_eq!(pdi.chunk_progress(), 0.5);
+        assert_eq!(pdi.progress(), 0.375);
+        assert_eq!(pdi.current_chunk_index(), Some(1));
+
+        let pdi = ProgressDisplayInfo::new(4, 4, 0, 0);
+
+        assert_eq!(pdi.chunk_progress(), 0.0);
+        assert_eq!(pdi.progress(), 1.0);
+        assert_eq!(pdi.current_chunk_index(), None);
+
+        let pdi = ProgressDisplayInfo::new(1, 4, 1, 2).with_ideal_key_stroke_progress(
+            2,
+            10,
+            Duration::new(4, 0),
+        );
+        assert_eq!(pdi.finished_ideal_key_stroke_count(), 2);
+        assert_eq!(pdi.remaining_ideal_key_stroke_count(), 8);
+        assert_eq!(pdi.ideal_key_stroke_percentage(), 20.0);
+        assert_eq!(pdi.eta(), Some(Duration::new(16, 0)));
+
+        let pdi = ProgressDisplayInfo::new(0, 4, 0, 2).with_ideal_key_stroke_progress(
+            0,
+            10,
+            Duration::new(4, 0),
+        );
+        assert_eq!(pdi.eta(), None);
+    }
 }
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::Rng;
+
+use crate::input_scheme::InputScheme;
+use crate::statistics::skill::SkillStatistics;
+use crate::vocabulary::VocabularyEntry;
+
+// 苦手なキーを含まない綴りも出題されるように全ての綴りに加える重み
+const BASE_WEIGHT: f64 = 0.05;
+// 単独では語の先頭に現れない綴り
+const SMALL_KANA: &str = "ぁぃぅぇぉっゃゅょゎゕゖ";
+
+/// Generate a drill vocabulary emphasizing weak key strokes and key stroke bigrams in
+/// `skill_statistics`.
+///
+/// The drill consists of `length` hiragana spells contained in dictionary of `input_scheme`, so
+/// it can always be typed.
+/// Spells starting with small kana such as `ゃ` are not used.
+/// Each spell is chosen randomly with weight of sum of miss rates of key strokes and key stroke
+/// bigrams in its first key strokes of dictionary.
+/// View of the drill is the same as its spell.
+///
+/// Returns `None` if dictionary of `input_scheme` contains no such hiragana spell, for example
+/// when [`InputScheme::Custom`] has only symbols or built-in dictionaries are excluded by
+/// `ascii_only` feature.
+pub fn generate_drill(
+    skill_statistics: &SkillStatistics,
+    input_scheme: &InputScheme,
+    length: NonZeroUsize,
+) -> Option<VocabularyEntry> {
+    generate_drill_with_rng(
+        skill_statistics,
+        input_scheme,
+        length,
+        &mut rand::thread_rng(),
+    )
+}
+
+fn generate_drill_with_rng<R: Rng>(
+    skill_statistics: &SkillStatistics,
+    input_scheme: &InputScheme,
+    length: NonZeroUsize,
+    rng: &mut R,
+) -> Option<VocabularyEntry> {
+    let key_stroke_miss_rates: BTreeMap<char, f64> = skill_statistics
+        .key_strokes()
+        .iter()
+        .map(|statistics| (statistics.key_stroke(), statistics.miss_rate()))
+        .collect();
+    let bigram_miss_rates: BTreeMap<(char, char), f64> = skill_statistics
+        .bigrams()
+        .iter()
+        .map(|statistics| {
+            (
+                (statistics.previous_key_stroke(), statistics.key_stroke()),
+                statistics.miss_rate(),
+            )
+        })
+        .collect();
+
+    let spells: Vec<&str> = input_scheme
+        .spells()
+        .into_iter()
+        .filter(|spell| {
+            spell.chars().all(is_hiragana) && !spell.starts_with(|c| SMALL_KANA.contains(c))
+        })
+        .collect();
+
+    let weights: Vec<f64> = spells
+        .iter()
+        .map(|spell| {
+            let key_stroke: Vec<char> = input_scheme.key_strokes(spell).unwrap()[0]
+                .chars()
+                .collect();
+
+            BASE_WEIGHT
+                + key_stroke
+                    .iter()
+                    .filter_map(|c| key_stroke_miss_rates.get(c))
+                    .sum::<f64>()
+                + key_stroke
+                    .windows(2)
+                    .filter_map(|bigram| bigram_miss_rates.get(&(bigram[0], bigram[1])))
+                    .sum::<f64>()
+        })
+        .collect();
+
+    // 出題できる綴りがない場合には重みが空になるので分布を作れない
+    let distribution = WeightedIndex::new(&weights).ok()?;
+
+    let drill: String = (0..length.get())
+        .map(|_| spells[distribution.sample(rng)])
+        .collect();
+
+    // 表示と綴りは同じなので1文字ずつ対応させる
+    Some(VocabularyEntry::from_kana_text(&drill).unwrap())
+}
+
+fn is_hiragana(c: char) -> bool {
+    ('ぁ'..='ゖ').contains(&c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+    #[cfg(not(feature = "ascii_only"))]
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::chunk::confirmed::ConfirmedChunk;
+    use crate::input_scheme::CustomDictionary;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::key_stroke::ActualKeyStroke;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::query::{QueryRequest, VocabularyOrder, VocabularyQuantifier, VocabularySeparator};
+    use crate::statistics::skill::construct_skill_statistics;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::typing_engine::TypingEngine;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::{gen_candidate, gen_chunk};
+
+    // テストは組み込みの辞書を前提としている
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn generate_drill_emphasizing_weak_key_stroke() {
+        // zを打つべき時には必ずミスタイプしている
+        let confirmed_chunks = vec![ConfirmedChunk::new(
+            gen_chunk!("ざ", vec![gen_candidate!(["za"])], gen_candidate!(["za"])),
+            vec![
+                ActualKeyStroke::new(Duration::new(1, 0), 'x'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(2, 0), 'z'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(3, 0), 'a'.try_into().unwrap(), true),
+            ],
+        )];
+        let skill_statistics = construct_skill_statistics(&confirmed_chunks, None);
+
+        let drill = generate_drill_with_rng(
+            &skill_statistics,
+            &InputScheme::Standard,
+            NonZeroUsize::new(100).unwrap(),
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+
+        let spell = String::from(drill.construct_spell_string());
+        assert!(spell.chars().count() >= 100);
+        // 「ざ」行の綴りが多く出題される
+        let weak_spell_count = spell.chars().filter(|c| "ざじずぜぞ".contains(*c)).count();
+        assert!(weak_spell_count > 30, "{}", spell);
+
+        // 生成した語彙は必ず打てる
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&drill],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn generate_drill_without_hiragana_spell() {
+        let skill_statistics = construct_skill_statistics(&[], None);
+        // 記号のみの辞書では出題できる綴りがない
+        let custom_dictionary =
+            CustomDictionary::new(HashMap::from([("、".to_string(), vec![",".to_string()])]))
+                .unwrap();
+
+        assert!(generate_drill_with_rng(
+            &skill_statistics,
+            &InputScheme::Custom(custom_dictionary),
+            NonZeroUsize::new(10).unwrap(),
+            &mut StdRng::seed_from_u64(0),
+        )
+        .is_none());
+    }
+}
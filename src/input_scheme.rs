@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(not(feature = "ascii_only"))]
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(feature = "ascii_only"))]
+use crate::chunk_key_stroke_dictionary::CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY;
+#[cfg(not(feature = "ascii_only"))]
+use crate::kana_key_stroke_dictionary::KANA_SPELL_TO_KEY_STROKE_DICTIONARY;
+use crate::key_stroke::KeyStrokeString;
+use crate::spell::SpellString;
+use crate::utility::is_katakana;
+
+/// An input scheme which determines key strokes to type each spell.
+///
+/// With `ascii_only` feature, built-in dictionaries are not included and input schemes other
+/// than [`Custom`](Self::Custom) can type only ASCII.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputScheme {
+    /// Standard romaji input.
+    #[default]
+    Standard,
+    /// AZIK, an extension of romaji input.
+    ///
+    /// In addition to standard romaji input, syllabic nasal and double vowel are typed by a
+    /// key stroke instead of a vowel.
+    /// ex. `かん` can be typed by `kz` and `こう` can be typed by `kp`.
+    Azik,
+    /// Direct kana input on JIS keyboard.
+    ///
+    /// Each key stroke is represented by character typed by the same key in alphanumeric mode.
+    /// Voiced and semi-voiced sounds are typed by following `@` (゛) and `[` (゜) key.
+    /// ex. `か` can be typed by `t` and `が` can be typed by `t@`.
+    ///
+    /// `を` (`Shift` + `0`), which types no character in alphanumeric mode, is represented by `~`
+    /// and `ろ`, which types the same character as `¥` key, is represented by `_`.
+    Kana,
+    /// Input with user-defined dictionary.
+    Custom(CustomDictionary),
+}
+
+impl InputScheme {
+    // 綴りを入力できるか
+    pub(crate) fn contains_spell(&self, spell: &str) -> bool {
+        match self {
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Standard => CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY.contains_key(spell),
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Azik => AZIK_DICTIONARY.contains_key(spell),
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Kana => KANA_SPELL_TO_KEY_STROKE_DICTIONARY.contains_key(spell),
+            // ASCIIのみの場合には組み込みの辞書がないのでASCII以外の綴りは入力できない
+            #[cfg(feature = "ascii_only")]
+            Self::Standard | Self::Azik | Self::Kana => false,
+            Self::Custom(custom_dictionary) => custom_dictionary.dictionary.contains_key(spell),
+        }
+    }
+
+    /// Get key strokes to type `spell` with this input scheme in order of dictionary.
+    ///
+    /// `spell` must be one of [`spells`](Self::spells()), otherwise this returns `None`.
+    /// Key strokes depending on the adjacent spell are not listed. ex. `っ` before `た` can also be
+    /// typed by `t`, and `n` for `ん` cannot be used before some spells such as `あ`.
+    pub fn key_strokes(&self, spell: &str) -> Option<Vec<&str>> {
+        match self {
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Standard => CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY.get(spell).cloned(),
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Azik => AZIK_DICTIONARY
+                .get(spell)
+                .map(|key_strokes| key_strokes.iter().map(String::as_str).collect()),
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Kana => KANA_SPELL_TO_KEY_STROKE_DICTIONARY.get(spell).cloned(),
+            #[cfg(feature = "ascii_only")]
+            Self::Standard | Self::Azik | Self::Kana => None,
+            Self::Custom(custom_dictionary) => custom_dictionary
+                .dictionary
+                .get(spell)
+                .map(|key_strokes| key_strokes.iter().map(String::as_str).collect()),
+        }
+    }
+
+    /// Get all spells in dictionary of this input scheme in order of code point.
+    ///
+    /// Each spell is 1 or 2 characters, and a longer spell is typed by splitting it into these
+    /// spells.
+    /// This is useful to list romanization variants in settings screen with
+    /// [`key_strokes`](Self::key_strokes()).
+    pub fn spells(&self) -> Vec<&str> {
+        // 辞書の順番は不定なので並べ替えて決定的にする
+        let mut spells: Vec<&str> = match self {
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Standard => CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
+                .keys()
+                .copied()
+                .collect(),
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Azik => AZIK_DICTIONARY.keys().map(String::as_str).collect(),
+            #[cfg(not(feature = "ascii_only"))]
+            Self::Kana => KANA_SPELL_TO_KEY_STROKE_DICTIONARY
+                .keys()
+                .copied()
+                .collect(),
+            #[cfg(feature = "ascii_only")]
+            Self::Standard | Self::Azik | Self::Kana => vec![],
+            Self::Custom(custom_dictionary) => custom_dictionary
+                .dictionary
+                .keys()
+                .map(String::as_str)
+                .collect(),
+        };
+        spells.sort_unstable();
+
+        spells
+    }
+
+    // 「っ」を次のチャンク先頭の子音の連続で表せるか
+    // かな入力では子音という概念がないので表せない
+    pub(crate) fn can_represent_ltu_by_repeat(&self) -> bool {
+        !matches!(self, Self::Kana)
+    }
+}
+
+/// A style of romanization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RomanizationStyle {
+    /// Hepburn romanization such as `shi`, `chu` and `fu`.
+    Hepburn,
+    /// Kunrei romanization such as `si`, `tyu` and `hu`.
+    Kunrei,
+}
+
+impl RomanizationStyle {
+    // キーストロークがどちらの方式に特有のものか
+    // どちらの方式でも同じキーストローク(「ka」など)はどちらにも属さない
+    pub(crate) fn of_key_stroke(key_stroke: &str) -> Option<Self> {
+        match key_stroke {
+            "shi" | "chi" | "tsu" | "fu" | "ji" | "sha" | "shu" | "she" | "sho" | "cha" | "chu"
+            | "che" | "cho" | "ja" | "ju" | "je" | "jo" => Some(Self::Hepburn),
+            "si" | "ti" | "tu" | "hu" | "zi" | "sya" | "syu" | "sye" | "syo" | "tya" | "tyu"
+            | "tye" | "tyo" | "zya" | "zyu" | "zye" | "zyo" => Some(Self::Kunrei),
+            _ => None,
+        }
+    }
+}
+
+/// A preference of romanization style used to choose key stroke candidates.
+///
+/// This only affects spells which have key strokes specific to a style such as `し`, so other
+/// key strokes are kept regardless of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RomanizationPreference {
+    /// Prefer key strokes of the style when key stroke counts tie.
+    ///
+    /// Key strokes specific to neither style are preferred to ones of the other style.
+    /// ex. When [`Hepburn`](RomanizationStyle::Hepburn) is preferred, ideal key strokes of `ちゅ`
+    /// are `chu` instead of `tyu`, and ones of `し` are `ci` because `shi` needs more key strokes.
+    Prefer(RomanizationStyle),
+    /// Prefer key strokes of the style and exclude key strokes of the other style.
+    ///
+    /// ex. When [`Hepburn`](RomanizationStyle::Hepburn) is specified, `し` can be typed only by
+    /// `shi` (and `ci`, which is specific to neither style).
+    Only(RomanizationStyle),
+}
+
+impl RomanizationPreference {
+    // キーストロークが優先される度合い
+    // 小さいほど優先される
+    pub(crate) fn rank_of_key_strokes<'a>(
+        &self,
+        key_strokes: impl Iterator<Item = &'a str>,
+    ) -> usize {
+        let preferred_style = match self {
+            Self::Prefer(style) | Self::Only(style) => style,
+        };
+
+        key_strokes
+            .filter_map(RomanizationStyle::of_key_stroke)
+            .map(|style| if style == *preferred_style { 0 } else { 2 })
+            .max()
+            .unwrap_or(1)
+    }
+
+    // キーストロークを候補から除外すべきか
+    pub(crate) fn excludes<'a>(&self, key_strokes: impl Iterator<Item = &'a str>) -> bool {
+        match self {
+            Self::Prefer(_) => false,
+            Self::Only(_) => self.rank_of_key_strokes(key_strokes) == 2,
+        }
+    }
+}
+
+/// A user-defined dictionary which maps spell to key strokes.
+///
+/// ex. To disable `ca` for `か`, map `"か"` to `["ka"]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct CustomDictionary {
+    dictionary: BTreeMap<String, Vec<String>>,
+}
+
+// デシリアライズ時にも辞書の条件を検証する
+impl TryFrom<BTreeMap<String, Vec<String>>> for CustomDictionary {
+    type Error = &'static str;
+
+    fn try_from(dictionary: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        Self::new(dictionary.into_iter().collect()).ok_or("invalid custom dictionary")
+    }
+}
+
+impl From<CustomDictionary> for BTreeMap<String, Vec<String>> {
+    fn from(custom_dictionary: CustomDictionary) -> Self {
+        custom_dictionary.dictionary
+    }
+}
+
+impl CustomDictionary {
+    /// Construct a new [`CustomDictionary`] which replaces built-in dictionary.
+    ///
+    /// Each spell must be 1 or 2 characters and mapped to at least one key stroke.
+    /// Each character of 2 characters spell must also be contained in dictionary because such
+    /// spell can be typed character by character.
+    /// `ん` must be mapped to key stroke other than `n` because `n` cannot be used depending on
+    /// following spell.
+    ///
+    /// Returns `None` if `dictionary` does not satisfy these conditions.
+    pub fn new(dictionary: HashMap<String, Vec<String>>) -> Option<Self> {
+        let dictionary: BTreeMap<String, Vec<String>> = dictionary.into_iter().collect();
+
+        let is_valid_entry = |spell: &String, key_strokes: &Vec<String>| {
+            let spell_char_count = spell.chars().count();
+
+            (1..=2).contains(&spell_char_count)
+                && spell.chars().all(|c| !c.is_ascii() && !is_katakana(c))
+                && SpellString::try_from(spell.clone()).is_ok()
+                && (spell_char_count == 1
+                    || spell
+                        .chars()
+                        .all(|c| dictionary.contains_key(&c.to_string())))
+                && !key_strokes.is_empty()
+                && key_strokes.iter().all(|key_stroke| {
+                    !key_stroke.is_empty() && KeyStrokeString::try_from(key_stroke.clone()).is_ok()
+                })
+                && (spell != "ん" || key_strokes.iter().any(|key_stroke| key_stroke != "n"))
+        };
+
+        if dictionary
+            .iter()
+            .all(|(spell, key_strokes)| is_valid_entry(spell, key_strokes))
+        {
+            Some(Self { dictionary })
+        } else {
+            None
+        }
+    }
+
+    /// Construct a new [`CustomDictionary`] which augments built-in dictionary of
+    /// [`InputScheme::Standard`].
+    ///
+    /// Key strokes of spell contained in `dictionary` replace built-in ones and other spells
+    /// are typed as built-in dictionary.
+    ///
+    /// Returns `None` on the same conditions as [`new`](Self::new()).
+    /// With `ascii_only` feature, built-in dictionary is not included and this is the same as
+    /// [`new`](Self::new()).
+    pub fn augmenting_standard(dictionary: HashMap<String, Vec<String>>) -> Option<Self> {
+        #[cfg(feature = "ascii_only")]
+        let augmented_dictionary = dictionary;
+
+        #[cfg(not(feature = "ascii_only"))]
+        let mut augmented_dictionary: HashMap<String, Vec<String>> =
+            CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
+                .iter()
+                .map(|(spell, key_strokes)| {
+                    (
+                        spell.to_string(),
+                        key_strokes.iter().map(|s| s.to_string()).collect(),
+                    )
+                })
+                .collect();
+
+        #[cfg(not(feature = "ascii_only"))]
+        augmented_dictionary.extend(dictionary);
+
+        Self::new(augmented_dictionary)
+    }
+}
+
+// AZIKで拡張する行と各段の綴り
+// 綴りが空文字列の段は拡張しない
+#[cfg(not(feature = "ascii_only"))]
+const AZIK_ROWS: [(&str, [&str; 5]); 14] = [
+    ("k", ["か", "き", "く", "け", "こ"]),
+    ("s", ["さ", "し", "す", "せ", "そ"]),
+    ("t", ["た", "ち", "つ", "て", "と"]),
+    ("n", ["な", "に", "ぬ", "ね", "の"]),
+    ("h", ["は", "ひ", "ふ", "へ", "ほ"]),
+    ("m", ["ま", "み", "む", "め", "も"]),
+    ("y", ["や", "", "ゆ", "", "よ"]),
+    ("r", ["ら", "り", "る", "れ", "ろ"]),
+    ("w", ["わ", "", "", "", ""]),
+    ("g", ["が", "ぎ", "ぐ", "げ", "ご"]),
+    ("z", ["ざ", "じ", "ず", "ぜ", "ぞ"]),
+    ("d", ["だ", "ぢ", "づ", "で", "ど"]),
+    ("b", ["ば", "び", "ぶ", "べ", "ぼ"]),
+    ("p", ["ぱ", "ぴ", "ぷ", "ぺ", "ぽ"]),
+];
+
+// 撥音拡張で各段の母音の代わりに打つキー
+// ex. 「かん」は「kz」
+#[cfg(not(feature = "ascii_only"))]
+const AZIK_SYLLABIC_NASAL_KEYS: [&str; 5] = ["z", "k", "j", "d", "l"];
+
+// 二重母音拡張で各段の母音の代わりに打つキーと後続する綴り
+// ex. 「かい」は「kq」
+#[cfg(not(feature = "ascii_only"))]
+const AZIK_DOUBLE_VOWEL_KEYS: [Option<(&str, &str)>; 5] = [
+    Some(("q", "い")),
+    None,
+    Some(("h", "う")),
+    Some(("w", "い")),
+    Some(("p", "う")),
+];
+
+#[cfg(not(feature = "ascii_only"))]
+static AZIK_DICTIONARY: Lazy<HashMap<String, Vec<String>>> = Lazy::new(|| {
+    let mut m: HashMap<String, Vec<String>> = CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY
+        .iter()
+        .map(|(spell, key_strokes)| {
+            (
+                spell.to_string(),
+                key_strokes.iter().map(|s| s.to_string()).collect(),
+            )
+        })
+        .collect();
+
+    let mut insert = |spell: String, key_stroke: String| {
+        m.entry(spell).or_default().push(key_stroke);
+    };
+
+    AZIK_ROWS.iter().for_each(|(consonant, spells)| {
+        spells
+            .iter()
+            .enumerate()
+            .filter(|(_, spell)| !spell.is_empty())
+            .for_each(|(i, spell)| {
+                insert(
+                    format!("{}ん", spell),
+                    format!("{}{}", consonant, AZIK_SYLLABIC_NASAL_KEYS[i]),
+                );
+
+                if let Some((key, following_spell)) = AZIK_DOUBLE_VOWEL_KEYS[i] {
+                    insert(
+                        format!("{}{}", spell, following_spell),
+                        format!("{}{}", consonant, key),
+                    );
+                }
+            });
+    });
+
+    insert("ん".to_string(), "q".to_string());
+    insert("っ".to_string(), ";".to_string());
+
+    m
+});
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn azik_key_strokes() {
+        assert_eq!(InputScheme::Standard.key_strokes("きん"), None);
+        assert_eq!(InputScheme::Azik.key_strokes("きん"), Some(vec!["kk"]));
+        assert_eq!(InputScheme::Azik.key_strokes("こう"), Some(vec!["kp"]));
+        assert_eq!(
+            InputScheme::Azik.key_strokes("ん"),
+            Some(vec!["n", "nn", "xn", "q"])
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn introspect_dictionary() {
+        let spells = InputScheme::Standard.spells();
+        assert!(spells.windows(2).all(|spells| spells[0] < spells[1]));
+        // 設定画面で一覧にできるように全ての綴りのキーストロークを引ける
+        assert!(spells
+            .iter()
+            .all(|spell| InputScheme::Standard.key_strokes(spell).is_some()));
+        assert_eq!(
+            InputScheme::Standard.key_strokes("し"),
+            Some(vec!["si", "ci", "shi"])
+        );
+        assert_eq!(InputScheme::Standard.key_strokes("きょう"), None);
+    }
+
+    #[cfg(feature = "ascii_only")]
+    #[test]
+    fn builtin_dictionaries_are_excluded_with_ascii_only() {
+        assert!(InputScheme::Standard.spells().is_empty());
+        assert_eq!(InputScheme::Azik.key_strokes("かん"), None);
+        assert!(!InputScheme::Kana.contains_spell("か"));
+
+        // ユーザ定義の辞書は使える
+        let custom_dictionary = CustomDictionary::augmenting_standard(HashMap::from([(
+            "か".to_string(),
+            vec!["ka".to_string()],
+        )]))
+        .unwrap();
+        let input_scheme = InputScheme::Custom(custom_dictionary);
+        assert_eq!(input_scheme.key_strokes("か"), Some(vec!["ka"]));
+        assert!(!input_scheme.contains_spell("き"));
+    }
+
+    #[cfg(all(feature = "no_rare_romanization", not(feature = "ascii_only")))]
+    #[test]
+    fn rare_key_strokes_are_excluded() {
+        assert_eq!(
+            InputScheme::Standard.key_strokes("う"),
+            Some(vec!["u", "wu"])
+        );
+        assert_eq!(
+            InputScheme::Standard.key_strokes("っ"),
+            Some(vec!["ltu", "xtu"])
+        );
+        // AZIKの辞書も組み込みの辞書から構築するので除かれる
+        assert!(!InputScheme::Azik
+            .key_strokes("ぃ")
+            .unwrap()
+            .contains(&"lyi"));
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_custom_dictionary() {
+        let dictionary = |entries: &[(&str, &[&str])]| -> HashMap<String, Vec<String>> {
+            entries
+                .iter()
+                .map(|(spell, key_strokes)| {
+                    (
+                        spell.to_string(),
+                        key_strokes.iter().map(|s| s.to_string()).collect(),
+                    )
+                })
+                .collect()
+        };
+
+        let custom_dictionary =
+            CustomDictionary::augmenting_standard(dictionary(&[("か", &["ka"])])).unwrap();
+        let input_scheme = InputScheme::Custom(custom_dictionary);
+        assert_eq!(input_scheme.key_strokes("か"), Some(vec!["ka"]));
+        assert_eq!(input_scheme.key_strokes("き"), Some(vec!["ki"]));
+
+        let custom_dictionary = CustomDictionary::new(dictionary(&[("か", &["ka"])])).unwrap();
+        let input_scheme = InputScheme::Custom(custom_dictionary);
+        assert!(!input_scheme.contains_spell("き"));
+
+        // 2文字の綴りを構成する各文字も辞書に含まれている必要がある
+        assert!(CustomDictionary::new(dictionary(&[("きゃ", &["kya"])])).is_none());
+        assert!(CustomDictionary::new(dictionary(&[("か", &[])])).is_none());
+        assert!(CustomDictionary::new(dictionary(&[("か", &["かa"])])).is_none());
+        assert!(CustomDictionary::new(dictionary(&[("ん", &["n"])])).is_none());
+    }
+}
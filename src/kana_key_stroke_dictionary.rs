@@ -0,0 +1,117 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+// JISかな配列で各綴りを入力するキーストローク
+// キーストロークは同じキーを英数モードで押したときに入力される文字で表す
+// ただし英数モードで文字が入力されない「を(Shift+0)」は「~」で，「￥」キーと同じ文字が入力される「ろ」は「_」で表す
+// 濁音・半濁音は清音のキーに続けて「゛(@)」「゜([)」のキーを押す
+pub static KANA_SPELL_TO_KEY_STROKE_DICTIONARY: Lazy<HashMap<&'static str, Vec<&'static str>>> =
+    Lazy::new(|| {
+        let mut m = HashMap::new();
+        // あ行
+        m.insert("あ", vec!["3"]);
+        m.insert("い", vec!["e"]);
+        m.insert("う", vec!["4"]);
+        m.insert("え", vec!["5"]);
+        m.insert("お", vec!["6"]);
+        m.insert("ぁ", vec!["#"]);
+        m.insert("ぃ", vec!["E"]);
+        m.insert("ぅ", vec!["$"]);
+        m.insert("ぇ", vec!["%"]);
+        m.insert("ぉ", vec!["&"]);
+        // か行
+        m.insert("か", vec!["t"]);
+        m.insert("き", vec!["g"]);
+        m.insert("く", vec!["h"]);
+        m.insert("け", vec![":"]);
+        m.insert("こ", vec!["b"]);
+        // さ行
+        m.insert("さ", vec!["x"]);
+        m.insert("し", vec!["d"]);
+        m.insert("す", vec!["r"]);
+        m.insert("せ", vec!["p"]);
+        m.insert("そ", vec!["c"]);
+        // た行
+        m.insert("た", vec!["q"]);
+        m.insert("ち", vec!["a"]);
+        m.insert("つ", vec!["z"]);
+        m.insert("て", vec!["w"]);
+        m.insert("と", vec!["s"]);
+        m.insert("っ", vec!["Z"]);
+        // な行
+        m.insert("な", vec!["u"]);
+        m.insert("に", vec!["i"]);
+        m.insert("ぬ", vec!["1"]);
+        m.insert("ね", vec![","]);
+        m.insert("の", vec!["k"]);
+        // は行
+        m.insert("は", vec!["f"]);
+        m.insert("ひ", vec!["v"]);
+        m.insert("ふ", vec!["2"]);
+        m.insert("へ", vec!["^"]);
+        m.insert("ほ", vec!["-"]);
+        // ま行
+        m.insert("ま", vec!["j"]);
+        m.insert("み", vec!["n"]);
+        m.insert("む", vec!["]"]);
+        m.insert("め", vec!["/"]);
+        m.insert("も", vec!["m"]);
+        // や行
+        m.insert("や", vec!["7"]);
+        m.insert("ゆ", vec!["8"]);
+        m.insert("よ", vec!["9"]);
+        m.insert("ゃ", vec!["'"]);
+        m.insert("ゅ", vec!["("]);
+        m.insert("ょ", vec![")"]);
+        // ら行
+        m.insert("ら", vec!["o"]);
+        m.insert("り", vec!["l"]);
+        m.insert("る", vec!["."]);
+        m.insert("れ", vec![";"]);
+        m.insert("ろ", vec!["_"]);
+        // わ行
+        m.insert("わ", vec!["0"]);
+        m.insert("を", vec!["~"]);
+        m.insert("ん", vec!["y"]);
+        // が行
+        m.insert("が", vec!["t@"]);
+        m.insert("ぎ", vec!["g@"]);
+        m.insert("ぐ", vec!["h@"]);
+        m.insert("げ", vec![":@"]);
+        m.insert("ご", vec!["b@"]);
+        // ざ行
+        m.insert("ざ", vec!["x@"]);
+        m.insert("じ", vec!["d@"]);
+        m.insert("ず", vec!["r@"]);
+        m.insert("ぜ", vec!["p@"]);
+        m.insert("ぞ", vec!["c@"]);
+        // だ行
+        m.insert("だ", vec!["q@"]);
+        m.insert("ぢ", vec!["a@"]);
+        m.insert("づ", vec!["z@"]);
+        m.insert("で", vec!["w@"]);
+        m.insert("ど", vec!["s@"]);
+        // ば行
+        m.insert("ば", vec!["f@"]);
+        m.insert("び", vec!["v@"]);
+        m.insert("ぶ", vec!["2@"]);
+        m.insert("べ", vec!["^@"]);
+        m.insert("ぼ", vec!["-@"]);
+        m.insert("ゔ", vec!["4@"]);
+        // ぱ行
+        m.insert("ぱ", vec!["f["]);
+        m.insert("ぴ", vec!["v["]);
+        m.insert("ぷ", vec!["2["]);
+        m.insert("ぺ", vec!["^["]);
+        m.insert("ぽ", vec!["-["]);
+        // 記号
+        m.insert("ー", vec!["\\"]);
+        m.insert("、", vec!["<"]);
+        m.insert("。", vec![">"]);
+        m.insert("・", vec!["?"]);
+        m.insert("「", vec!["{"]);
+        m.insert("」", vec!["}"]);
+        m.insert("　", vec![" "]);
+
+        m
+    });
@@ -3,10 +3,16 @@ use std::fmt::Display;
 use std::ops::Deref;
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
+
 use crate::utility::is_displayable_ascii;
 
+// Shiftキーと同時に打つ記号
+const SHIFTED_SYMBOLS: &str = "~!@#$%^&*()_+{}|:\"<>?";
+
 /// A type representing a character can be used as a key stroke.
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+#[serde(try_from = "char", into = "char")]
 pub struct KeyStrokeChar(char);
 
 impl From<KeyStrokeChar> for char {
@@ -15,6 +21,17 @@ impl From<KeyStrokeChar> for char {
     }
 }
 
+impl KeyStrokeChar {
+    /// Whether Shift key is needed to type this key stroke.
+    ///
+    /// This is true for capital letters and symbols typed with Shift key such as `!` and `@`.
+    /// Keyboard layouts supported by [`KeyboardLayout`](crate::KeyboardLayout) need Shift key
+    /// for the same characters.
+    pub fn is_shifted(&self) -> bool {
+        self.0.is_ascii_uppercase() || SHIFTED_SYMBOLS.contains(self.0)
+    }
+}
+
 impl PartialEq<char> for KeyStrokeChar {
     fn eq(&self, other: &char) -> bool {
         self.0 == *other
@@ -94,9 +111,9 @@ impl TryFrom<String> for KeyStrokeString {
     }
 }
 
-// タイピング中のそれぞれのキーストローク
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
-pub(crate) struct ActualKeyStroke {
+/// A key stroke actually typed by player.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct ActualKeyStroke {
     // タイピングを開始した時点からこのキーストロークが起こった時点までにかかった時間
     elapsed_time: Duration,
     key_stroke: KeyStrokeChar,
@@ -112,15 +129,18 @@ impl ActualKeyStroke {
         }
     }
 
-    pub(crate) fn elapsed_time(&self) -> &Duration {
+    /// Get elapsed time from start when this key stroke occurred.
+    pub fn elapsed_time(&self) -> &Duration {
         &self.elapsed_time
     }
 
-    pub(crate) fn key_stroke(&self) -> &KeyStrokeChar {
+    /// Get typed key.
+    pub fn key_stroke(&self) -> &KeyStrokeChar {
         &self.key_stroke
     }
 
-    pub(crate) fn is_correct(&self) -> bool {
+    /// Get whether this key stroke is correct.
+    pub fn is_correct(&self) -> bool {
         self.is_correct
     }
 }
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+
+use crate::key_stroke::KeyStrokeChar;
+
+// 各配列のキーを同じ物理位置の順番で並べたもの
+// 前半はシフトなし，後半はシフトありで入力される文字である
+const QWERTY_KEYS: &str = concat!(
+    "`1234567890-=qwertyuiop[]\\asdfghjkl;'zxcvbnm,./",
+    "~!@#$%^&*()_+QWERTYUIOP{}|ASDFGHJKL:\"ZXCVBNM<>?",
+);
+const DVORAK_KEYS: &str = concat!(
+    "`1234567890[]',.pyfgcrl/=\\aoeuidhtns-;qjkxbmwvz",
+    "~!@#$%^&*(){}\"<>PYFGCRL?+|AOEUIDHTNS_:QJKXBMWVZ",
+);
+const COLEMAK_KEYS: &str = concat!(
+    "`1234567890-=qwfpgjluy;[]\\arstdhneio'zxcvbkm,./",
+    "~!@#$%^&*()_+QWFPGJLUY:{}|ARSTDHNEIO\"ZXCVBKM<>?",
+);
+// 各配列のキーの前半を上の行から順に区切ったときの各行のキー数と行頭の横方向のずれ
+// ずれはキー幅の1/4を単位とする
+#[cfg(feature = "analysis")]
+const KEY_ROWS: [(usize, usize); 4] = [(13, 0), (13, 6), (11, 7), (10, 9)];
+
+/// A position of key on physical keyboard.
+///
+/// A position is identified by character which is typed by that key on QWERTY layout.
+/// ex. The key at the right of `Tab` key is `PhysicalKey::new('q')` regardless of keyboard layout.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicalKey {
+    qwerty_char: KeyStrokeChar,
+}
+
+impl PhysicalKey {
+    /// Construct a new [`PhysicalKey`] from character typed by the key on QWERTY layout.
+    pub fn new(qwerty_char: KeyStrokeChar) -> Self {
+        Self { qwerty_char }
+    }
+
+    /// Character typed by this key on QWERTY layout.
+    pub fn qwerty_char(&self) -> &KeyStrokeChar {
+        &self.qwerty_char
+    }
+}
+
+/// A keyboard layout which translates [`PhysicalKey`] to [`KeyStrokeChar`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyboardLayout {
+    /// QWERTY layout. Physical keys are translated as it is.
+    #[default]
+    Qwerty,
+    /// Dvorak simplified keyboard layout.
+    Dvorak,
+    /// Colemak layout.
+    Colemak,
+}
+
+impl KeyboardLayout {
+    /// Translate a physical key to a key stroke character according to this layout.
+    ///
+    /// Keys which are not affected by layout such as space are translated as it is.
+    pub fn translate(&self, physical_key: &PhysicalKey) -> KeyStrokeChar {
+        let qwerty_char: char = physical_key.qwerty_char().clone().into();
+
+        match QWERTY_KEYS.chars().position(|c| c == qwerty_char) {
+            Some(position) => self
+                .layout_keys()
+                .chars()
+                .nth(position)
+                .unwrap()
+                .try_into()
+                .unwrap(),
+            None => physical_key.qwerty_char().clone(),
+        }
+    }
+
+    /// Get a key stroke character typed by the same key as `key_stroke` with Shift key held down
+    /// according to this layout.
+    ///
+    /// ex. `'a'` is shifted to `'A'` and `'1'` is shifted to `'!'`.
+    /// Key strokes which are already shifted or are not affected by Shift key are returned as it
+    /// is.
+    pub fn shift(&self, key_stroke: &KeyStrokeChar) -> KeyStrokeChar {
+        let layout_keys = self.layout_keys();
+        // 前半と後半の同じ位置の文字は同じキーで入力される
+        let half_count = layout_keys.chars().count() / 2;
+        let c: char = key_stroke.clone().into();
+
+        match layout_keys.chars().take(half_count).position(|k| k == c) {
+            Some(position) => layout_keys
+                .chars()
+                .nth(position + half_count)
+                .unwrap()
+                .try_into()
+                .unwrap(),
+            None => key_stroke.clone(),
+        }
+    }
+
+    // 物理キーボード上で隣接するキーで入力されるかどうか
+    // 同じ行の隣か隣の行で横方向に重なっているキーを隣接しているとみなす
+    #[cfg(feature = "analysis")]
+    pub(crate) fn are_adjacent(&self, a: &KeyStrokeChar, b: &KeyStrokeChar) -> bool {
+        match (self.key_coordinate(a), self.key_coordinate(b)) {
+            (Some((a_row, a_x)), Some((b_row, b_x))) => {
+                let row_distance = a_row.abs_diff(b_row);
+                let x_distance = a_x.abs_diff(b_x);
+
+                (row_distance == 0 && x_distance == 4) || (row_distance == 1 && x_distance < 4)
+            }
+            _ => false,
+        }
+    }
+
+    // キーの行と行内の横方向の位置を求める
+    #[cfg(feature = "analysis")]
+    fn key_coordinate(&self, key_stroke: &KeyStrokeChar) -> Option<(usize, usize)> {
+        self.key_position(key_stroke)
+            .map(|(row, position)| (row, KEY_ROWS[row].1 + position * 4))
+    }
+
+    // キーの行と行内で何番目のキーかを求める
+    // シフトありで入力される文字はシフトなしで入力される文字と同じ位置とする
+    #[cfg(feature = "analysis")]
+    pub(crate) fn key_position(&self, key_stroke: &KeyStrokeChar) -> Option<(usize, usize)> {
+        let layout_keys = self.layout_keys();
+        let half_count = layout_keys.chars().count() / 2;
+        let c: char = key_stroke.clone().into();
+
+        let mut position = layout_keys.chars().position(|k| k == c)? % half_count;
+
+        KEY_ROWS
+            .iter()
+            .enumerate()
+            .find_map(|(row, (key_count, _))| {
+                if position < *key_count {
+                    Some((row, position))
+                } else {
+                    position -= key_count;
+                    None
+                }
+            })
+    }
+
+    fn layout_keys(&self) -> &'static str {
+        match self {
+            Self::Qwerty => QWERTY_KEYS,
+            Self::Dvorak => DVORAK_KEYS,
+            Self::Colemak => COLEMAK_KEYS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn layout_keys_are_aligned() {
+        [DVORAK_KEYS, COLEMAK_KEYS].iter().for_each(|layout_keys| {
+            assert_eq!(layout_keys.chars().count(), QWERTY_KEYS.chars().count());
+
+            // 各配列で全てのキーが重複なく配置されている
+            let mut layout_chars: Vec<char> = layout_keys.chars().collect();
+            let mut qwerty_chars: Vec<char> = QWERTY_KEYS.chars().collect();
+            layout_chars.sort();
+            qwerty_chars.sort();
+            assert_eq!(layout_chars, qwerty_chars);
+        });
+    }
+
+    #[cfg(feature = "analysis")]
+    #[test]
+    fn judge_adjacent_keys() {
+        let key_stroke = |c: char| KeyStrokeChar::try_from(c).unwrap();
+
+        assert!(KeyboardLayout::Qwerty.are_adjacent(&key_stroke('a'), &key_stroke('s')));
+        assert!(KeyboardLayout::Qwerty.are_adjacent(&key_stroke('s'), &key_stroke('E')));
+        assert!(KeyboardLayout::Qwerty.are_adjacent(&key_stroke('z'), &key_stroke('a')));
+        assert!(!KeyboardLayout::Qwerty.are_adjacent(&key_stroke('s'), &key_stroke('q')));
+        assert!(!KeyboardLayout::Qwerty.are_adjacent(&key_stroke('a'), &key_stroke('A')));
+        // 配列が異なっても物理的な位置で判断する
+        assert!(KeyboardLayout::Dvorak.are_adjacent(&key_stroke('a'), &key_stroke('o')));
+        assert!(!KeyboardLayout::Dvorak.are_adjacent(&key_stroke('a'), &key_stroke(' ')));
+    }
+
+    #[test]
+    fn translate_physical_key() {
+        let physical_key = |c: char| PhysicalKey::new(c.try_into().unwrap());
+
+        assert_eq!(KeyboardLayout::Qwerty.translate(&physical_key('s')), 's');
+        assert_eq!(KeyboardLayout::Dvorak.translate(&physical_key('s')), 'o');
+        assert_eq!(KeyboardLayout::Dvorak.translate(&physical_key('Q')), '"');
+        assert_eq!(KeyboardLayout::Colemak.translate(&physical_key('s')), 'r');
+        assert_eq!(KeyboardLayout::Colemak.translate(&physical_key(' ')), ' ');
+    }
+
+    #[test]
+    fn shift_key_stroke() {
+        let key_stroke = |c: char| -> KeyStrokeChar { c.try_into().unwrap() };
+
+        assert_eq!(KeyboardLayout::Qwerty.shift(&key_stroke('h')), 'H');
+        assert_eq!(KeyboardLayout::Qwerty.shift(&key_stroke('1')), '!');
+        assert_eq!(KeyboardLayout::Qwerty.shift(&key_stroke('H')), 'H');
+        assert_eq!(KeyboardLayout::Qwerty.shift(&key_stroke(' ')), ' ');
+        assert_eq!(KeyboardLayout::Dvorak.shift(&key_stroke('\'')), '"');
+        assert_eq!(KeyboardLayout::Colemak.shift(&key_stroke(';')), ':');
+    }
+}
@@ -1,22 +1,94 @@
-pub use crate::display_info::DisplayInfo;
-pub use crate::key_stroke::{KeyStrokeChar, KeyStrokeCharError};
-pub use crate::query::{QueryRequest, VocabularyOrder, VocabularyQuantifier, VocabularySeparator};
+pub use crate::chunk::view::{parse_into_chunks, ChunkView};
+#[cfg(feature = "analysis")]
+pub use crate::coverage::{analyze_dictionary_coverage, DictionaryCoverageReport, SpellFrequency};
+pub use crate::display_info::{
+    DisplayInfo, DisplayInfoDelta, DisplayInfoDiff, DisplayWindowRequest, TextDelta, TextEdit,
+    WindowedDisplayInfo,
+};
+#[cfg(feature = "analysis")]
+pub use crate::drill::generate_drill;
+pub use crate::input_scheme::{
+    CustomDictionary, InputScheme, RomanizationPreference, RomanizationStyle,
+};
+pub use crate::key_stroke::{ActualKeyStroke, KeyStrokeChar, KeyStrokeCharError};
+pub use crate::keyboard_layout::{KeyboardLayout, PhysicalKey};
+pub use crate::query::{
+    QueryRequest, VocabularyBoundaryRounding, VocabularyOrder, VocabularyPool,
+    VocabularyQuantifier, VocabularySeparator,
+};
 pub use crate::spell::{SpellString, SpellStringError};
-pub use crate::statistics::result::{TypingResultStatistics, TypingResultStatisticsTarget};
-pub use crate::statistics::{LapRequest, OnTypingStatisticsTarget};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::context::ContextualMissStatistics;
+#[cfg(feature = "analysis")]
+pub use crate::statistics::finger::{
+    Finger, FingerMap, FingerSkillStatistics, FingerStatistics, Hand,
+};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::heatmap::MistypeHeatmap;
+#[cfg(feature = "analysis")]
+pub use crate::statistics::interval::{InterKeyIntervalStatistics, QuantileSketch};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::plausibility::{
+    HumanlikeCriteria, HumanlikeValidation, ImplausiblePattern,
+};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::profile::{DailyTypingStatistics, ProfileDate, TypingProfile};
+#[cfg(feature = "statistics")]
+pub use crate::statistics::result::{
+    AccumulatedResultStatistics, SpellCandidateStatistics, SpellHintStatistics,
+    SpellMistakeStatistics, TypingResultStatistics, TypingResultStatisticsTarget,
+    VocabularyResultStatistics,
+};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::score::{ScoreFormula, StandardScoreFormula};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::skill::{
+    KeyStrokeBigramSkillStatistics, KeyStrokeSkillStatistics, RomanizationHabit,
+    ShiftSkillStatistics, SkillStatistics,
+};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::telemetry::{KeyTelemetry, TelemetryLapRequest, TypingTelemetry};
+#[cfg(feature = "analysis")]
+pub use crate::statistics::typo::{Typo, TypoAnalysis, TypoCategory};
+pub use crate::statistics::LapRequest;
+#[cfg(feature = "statistics")]
+pub use crate::statistics::{LapSummary, OnTypingStatisticsTarget};
 pub use crate::typing_engine::*;
-pub use crate::vocabulary::{VocabularyEntry, VocabularySpellElement};
+#[cfg(feature = "import")]
+pub use crate::vocabulary::{
+    import_vocabularies_from_csv, import_vocabularies_from_json, import_vocabularies_from_tsv,
+};
+pub use crate::vocabulary::{
+    parse_aozora_ruby, parse_html_ruby, parse_vocabulary_entry, parse_vocabulary_file,
+    VocabularyAssets, VocabularyEntry, VocabularyParseError, VocabularySpellElement,
+};
+#[cfg(feature = "wasm")]
+pub use crate::wasm::WasmTypingEngine;
 
 mod chunk;
+// ASCIIのみの場合には組み込みの日本語の辞書を含めない
+#[cfg(not(feature = "ascii_only"))]
 mod chunk_key_stroke_dictionary;
+#[cfg(feature = "analysis")]
+mod coverage;
 pub mod display_info;
+#[cfg(feature = "analysis")]
+mod drill;
+mod input_scheme;
+#[cfg(not(feature = "ascii_only"))]
+mod kana_key_stroke_dictionary;
 mod key_stroke;
+mod keyboard_layout;
 mod query;
 mod spell;
 mod statistics;
+#[cfg(feature = "test_support")]
+pub mod test_support;
 mod typing_engine;
 mod utility;
 mod vocabulary;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 #[cfg(test)]
 mod test_utility;
@@ -1,8 +1,10 @@
 use rand::random;
 use std::num::NonZeroUsize;
+use std::ops::{Bound, RangeBounds};
 
 use crate::{
-    chunk::{append_key_stroke_to_chunks, Chunk},
+    chunk::{append_key_stroke_to_chunks, reassign_key_stroke_to_last_chunks, Chunk},
+    input_scheme::{InputScheme, RomanizationPreference},
     vocabulary::{VocabularyEntry, VocabularyInfo, VocabularySpellElement},
 };
 
@@ -13,6 +15,27 @@ pub enum VocabularyQuantifier {
     KeyStroke(NonZeroUsize),
     /// Vocabularies are selected to meet vocabulary count.
     Vocabulary(NonZeroUsize),
+    /// Vocabularies are selected to meet key stroke count, but query ends at boundary of
+    /// vocabularies.
+    ///
+    /// Unlike [`KeyStroke`](Self::KeyStroke), last vocabulary is never truncated, so key stroke
+    /// count of query is rounded to boundary of vocabularies by [`VocabularyBoundaryRounding`].
+    /// Vocabulary separators are not regarded as boundary, so query never ends with separator.
+    KeyStrokeAtVocabularyBoundary(NonZeroUsize, VocabularyBoundaryRounding),
+}
+
+/// A direction to round key stroke count to boundary of vocabularies.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum VocabularyBoundaryRounding {
+    /// Key stroke count is rounded up, so query has at least requested key strokes.
+    Up,
+    /// Key stroke count is rounded down, so query has at most requested key strokes.
+    ///
+    /// When even first vocabulary has more key strokes than requested, query consists of only
+    /// first vocabulary.
+    Down,
+    /// Key stroke count is rounded to nearer boundary. Ties are rounded up.
+    Nearest,
 }
 
 /// A vocabulary used to separate between vocabularies of query string.
@@ -24,25 +47,66 @@ pub enum VocabularySeparator {
     WhiteSpace,
     /// Any vocabulary is inserted between selected vocabularies.
     Vocabulary(VocabularyEntry),
+    /// A string is inserted between selected vocabularies.
+    ///
+    /// Each character is typed as it is, so string must consist of characters which can be used
+    /// as spells. ex. `"、"` or `" / "`
+    /// If string is empty or cannot be used as spells, query cannot be constructed.
+    Custom(String),
+    /// Line break is inserted between selected vocabularies.
+    ///
+    /// Line break is not typed, so vocabularies are typed in the same way as
+    /// [`None`](Self::None).
+    /// Positions of line breaks are available in
+    /// [`DisplayInfo::line_break_info`](crate::DisplayInfo::line_break_info()), so UI can render
+    /// multi-line query.
+    Newline,
 }
 
 impl VocabularySeparator {
-    // 語彙の区切り語彙がないかどうか
-    fn is_none(&self) -> bool {
-        matches!(self, Self::None)
+    // 語彙の区切りとして挿入する語彙があるかどうか
+    fn has_separator_vocabulary(&self) -> bool {
+        !matches!(self, Self::None | Self::Newline)
+    }
+
+    // 語彙の区切りとして改行するかどうか
+    fn breaks_line(&self) -> bool {
+        matches!(self, Self::Newline)
     }
 
-    fn generate_separator_vocabulary(&self) -> VocabularyEntry {
+    fn generate_separator_vocabulary(&self) -> Option<VocabularyEntry> {
         match self {
-            Self::Vocabulary(v) => v.clone(),
-            Self::WhiteSpace => VocabularyEntry::new(
-                " ".to_string(),
-                vec![VocabularySpellElement::Normal(
-                    " ".to_string().try_into().unwrap(),
-                )],
-            )
-            .unwrap(),
-            Self::None => unreachable!("this function cannot be called when self is None"),
+            Self::Vocabulary(v) => Some(v.clone()),
+            Self::WhiteSpace => Some(
+                VocabularyEntry::new(
+                    " ".to_string(),
+                    vec![VocabularySpellElement::Normal(
+                        " ".to_string().try_into().unwrap(),
+                    )],
+                )
+                .unwrap(),
+            ),
+            Self::Custom(separator) => {
+                if separator.is_empty() {
+                    return None;
+                }
+
+                // 1文字ずつそのまま打つ
+                let spells = separator
+                    .chars()
+                    .map(|c| {
+                        c.to_string()
+                            .try_into()
+                            .ok()
+                            .map(VocabularySpellElement::Normal)
+                    })
+                    .collect::<Option<Vec<VocabularySpellElement>>>()?;
+
+                VocabularyEntry::new(separator.clone(), spells)
+            }
+            Self::None | Self::Newline => {
+                unreachable!("this function cannot be called when self has no separator vocabulary")
+            }
         }
     }
 }
@@ -78,12 +142,53 @@ impl VocabularyOrder {
     }
 }
 
+/// A named vocabulary list used to construct query from multiple vocabulary lists.
+pub struct VocabularyPool<'vocabulary> {
+    name: String,
+    vocabulary_entries: Vec<&'vocabulary VocabularyEntry>,
+    vocabulary_order: VocabularyOrder,
+    ratio: NonZeroUsize,
+}
+
+impl<'vocabulary> VocabularyPool<'vocabulary> {
+    /// Construct a new [`VocabularyPool`].
+    ///
+    /// `ratio` is a count of vocabularies selected from this pool in a row before moving to next
+    /// pool.
+    /// ex. When there are pools `easy` with ratio 2 and `hard` with ratio 1, vocabularies are
+    /// selected in order of `easy`, `easy`, `hard`, `easy`, `easy`, `hard` and so on.
+    pub fn new(
+        name: String,
+        vocabulary_entries: &[&'vocabulary VocabularyEntry],
+        vocabulary_order: VocabularyOrder,
+        ratio: NonZeroUsize,
+    ) -> Self {
+        Self {
+            name,
+            vocabulary_entries: From::<&[&VocabularyEntry]>::from(vocabulary_entries),
+            vocabulary_order,
+            ratio,
+        }
+    }
+
+    /// Name of this pool.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Count of vocabularies selected from this pool in a row.
+    pub fn ratio(&self) -> NonZeroUsize {
+        self.ratio
+    }
+}
+
 /// A request for constructing query.
 pub struct QueryRequest<'vocabulary> {
-    vocabulary_entries: Vec<&'vocabulary VocabularyEntry>,
+    vocabulary_pools: Vec<VocabularyPool<'vocabulary>>,
     vocabulary_quantifier: VocabularyQuantifier,
     vocabulary_separator: VocabularySeparator,
-    vocabulary_order: VocabularyOrder,
+    // 語彙リストから選ぶ語彙の推測した最小キーストローク回数の範囲
+    key_stroke_count_range: (Bound<usize>, Bound<usize>),
 }
 
 impl<'vocabulary> QueryRequest<'vocabulary> {
@@ -93,27 +198,121 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
         vocabulary_quantifier: VocabularyQuantifier,
         vocabulary_separator: VocabularySeparator,
         vocabulary_order: VocabularyOrder,
+    ) -> Self {
+        Self::with_pools(
+            vec![VocabularyPool::new(
+                String::new(),
+                vocabulary_entries,
+                vocabulary_order,
+                NonZeroUsize::new(1).unwrap(),
+            )],
+            vocabulary_quantifier,
+            vocabulary_separator,
+        )
+    }
+
+    /// Construct a new [`QueryRequest`] which selects vocabularies from multiple pools.
+    ///
+    /// Vocabularies are selected from each pool in round-robin fashion weighted by
+    /// [`ratio`](VocabularyPool::ratio()) of pools.
+    /// Vocabularies in each pool are selected by [`VocabularyOrder`] of that pool.
+    pub fn with_pools(
+        vocabulary_pools: Vec<VocabularyPool<'vocabulary>>,
+        vocabulary_quantifier: VocabularyQuantifier,
+        vocabulary_separator: VocabularySeparator,
     ) -> Self {
         Self {
-            vocabulary_entries: From::<&[&VocabularyEntry]>::from(vocabulary_entries),
+            vocabulary_pools,
             vocabulary_quantifier,
             vocabulary_separator,
-            vocabulary_order,
+            key_stroke_count_range: (Bound::Unbounded, Bound::Unbounded),
         }
     }
 
-    pub(crate) fn construct_query(&self) -> Query {
+    /// Select only vocabularies whose minimum key stroke count is in `range`.
+    ///
+    /// Minimum key stroke count is estimated by
+    /// [`VocabularyEntry::estimate_min_key_stroke_count`].
+    /// This is useful to construct queries of difficulty tiers such as short words only.
+    /// [`VocabularySeparator`] is not filtered.
+    ///
+    /// If no vocabulary in a pool satisfies `range`, query cannot be constructed.
+    pub fn with_key_stroke_count_range<R: RangeBounds<usize>>(mut self, range: R) -> Self {
+        self.key_stroke_count_range = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Compute total count of ideal key strokes of query constructed by this request.
+    ///
+    /// Unlike summing up [`VocabularyEntry::estimate_min_key_stroke_count`], key strokes of
+    /// chunks depending on the following vocabulary such as `っ` and `ん` are taken into account.
+    /// This is useful to show the goal before typing and to normalize scores across vocabulary
+    /// lists.
+    ///
+    /// When vocabularies are selected randomly, query constructed by
+    /// [`TypingEngine::init`](crate::TypingEngine::init()) can be different from the one counted
+    /// here.
+    /// If query cannot be constructed, this method returns `None`.
+    pub fn ideal_key_stroke_count(
+        &self,
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) -> Option<usize> {
+        self.construct_query(input_scheme, romanization_preference)
+            .map(|query| query.ideal_key_stroke_count())
+    }
+
+    // 入力方式で入力できない綴りを含む語彙があるかどうか
+    // クエリを構築できなかった原因を区別するのに使う
+    pub(crate) fn contains_untypeable_vocabulary(&self, input_scheme: &InputScheme) -> bool {
+        self.vocabulary_pools.iter().any(|vocabulary_pool| {
+            vocabulary_pool
+                .vocabulary_entries
+                .iter()
+                .any(|vocabulary_entry| vocabulary_entry.construct_chunks(input_scheme).is_none())
+        })
+    }
+
+    // 語彙を選べない場合や選んだ語彙を入力できない場合にはクエリを構築できない
+    // ex. 語彙リストが空であったりユーザ定義の順番が範囲外のインデックスを返す場合
+    pub(crate) fn construct_query(
+        &self,
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) -> Option<Query> {
         // 語彙リストから選んだ語彙の区切りとして使う語彙
-        let separator_vocabulary = if self.vocabulary_separator.is_none() {
-            None
+        let separator_vocabulary = if self.vocabulary_separator.has_separator_vocabulary() {
+            Some(self.vocabulary_separator.generate_separator_vocabulary()?)
         } else {
-            Some(self.vocabulary_separator.generate_separator_vocabulary())
+            None
         };
 
+        // 範囲外の語彙は語彙リストにないものとして扱う
+        let vocabulary_entries_of_pools: Vec<Vec<&VocabularyEntry>> = self
+            .vocabulary_pools
+            .iter()
+            .map(|vocabulary_pool| {
+                vocabulary_pool
+                    .vocabulary_entries
+                    .iter()
+                    .copied()
+                    .filter(|vocabulary_entry| {
+                        self.key_stroke_count_range == (Bound::Unbounded, Bound::Unbounded)
+                            || vocabulary_entry
+                                .estimate_min_key_stroke_count(input_scheme)
+                                .is_some_and(|key_stroke_count| {
+                                    self.key_stroke_count_range.contains(&key_stroke_count)
+                                })
+                    })
+                    .collect()
+            })
+            .collect();
+
         let next_vocabulary_generator = NextVocabularyGenerator::new(
-            &self.vocabulary_entries,
+            &self.vocabulary_pools,
+            &vocabulary_entries_of_pools,
             &separator_vocabulary,
-            &self.vocabulary_order,
+            self.vocabulary_separator.breaks_line(),
         );
 
         match self.vocabulary_quantifier {
@@ -121,12 +320,26 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
                 Self::construct_query_with_key_stroke_striction(
                     key_stroke_threshold,
                     next_vocabulary_generator,
+                    input_scheme,
+                    romanization_preference,
                 )
             }
             VocabularyQuantifier::Vocabulary(vocabulary_count) => {
                 Self::construct_query_with_vocabulary_count(
                     vocabulary_count,
                     next_vocabulary_generator,
+                    input_scheme,
+                    romanization_preference,
+                )
+            }
+            VocabularyQuantifier::KeyStrokeAtVocabularyBoundary(key_stroke_threshold, rounding) => {
+                Self::construct_query_with_key_stroke_at_vocabulary_boundary(
+                    key_stroke_threshold,
+                    rounding,
+                    next_vocabulary_generator,
+                    separator_vocabulary.as_ref(),
+                    input_scheme,
+                    romanization_preference,
                 )
             }
         }
@@ -135,7 +348,9 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
     fn construct_query_with_key_stroke_striction(
         key_stroke_threshold: NonZeroUsize,
         mut next_vocabulary_generator: NextVocabularyGenerator,
-    ) -> Query {
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) -> Option<Query> {
         let mut query_chunks = Vec::<Chunk>::new();
         let mut query_vocabulary_infos = Vec::<VocabularyInfo>::new();
 
@@ -149,27 +364,29 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
         // キーストロークによる制限は最後にまとめて行う
         while min_key_stroke_count < key_stroke_threshold.get() {
             // 1
-            let vocabulary_entry = next_vocabulary_generator.next().unwrap();
+            let vocabulary_entry = next_vocabulary_generator.next()?;
 
             // 2
             // 語彙区切りによっては語彙ごとにキーストロークを付与してはいけないケースがあるためまだ付与しない
             // 例えば語彙区切りがない場合には語彙の末尾のキーストロークは次の語彙の先頭チャンクに依存する
-            let chunks = vocabulary_entry.construct_chunks();
+            let chunks = vocabulary_entry.construct_chunks(input_scheme)?;
 
             let chunk_count = chunks.len().try_into().unwrap();
-            query_vocabulary_infos.push(vocabulary_entry.construct_vocabulary_info(chunk_count));
+            query_vocabulary_infos.push(
+                next_vocabulary_generator.construct_vocabulary_info(vocabulary_entry, chunk_count),
+            );
 
             // 3
             for chunk in chunks {
                 // チャンクのキーストロークの取りうる最小値なのでもし大きかったとしても後で制限する際に削られる
-                min_key_stroke_count += chunk.estimate_min_key_stroke_count();
+                min_key_stroke_count += chunk.estimate_min_key_stroke_count(input_scheme);
 
                 query_chunks.push(chunk);
             }
         }
 
         // 全ての語彙や語彙区切りが確定してからキーストロークを付与する
-        append_key_stroke_to_chunks(&mut query_chunks);
+        append_key_stroke_to_chunks(&mut query_chunks, input_scheme, romanization_preference);
 
         // キーストロークを付与したので推測ではない実際のキーストローク回数が分かる
         let mut actual_key_stroke_count: usize = 0;
@@ -217,13 +434,120 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
                 .unwrap(),
         );
 
-        Query::new(query_vocabulary_infos, query_chunks)
+        Some(Query::new(query_vocabulary_infos, query_chunks))
+    }
+
+    fn construct_query_with_key_stroke_at_vocabulary_boundary(
+        key_stroke_threshold: NonZeroUsize,
+        rounding: VocabularyBoundaryRounding,
+        mut next_vocabulary_generator: NextVocabularyGenerator,
+        separator_vocabulary: Option<&VocabularyEntry>,
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) -> Option<Query> {
+        let mut query_chunks = Vec::<Chunk>::new();
+        let mut query_vocabulary_infos = Vec::<VocabularyInfo>::new();
+
+        let mut min_key_stroke_count: usize = 0;
+        // 語彙区切りではない語彙の末尾の位置を(語彙数,チャンク数)で表したもの
+        let mut boundaries = Vec::<(usize, usize)>::new();
+
+        // 要求キーストローク回数を満たしかつ語彙区切りではない語彙で終わるまで語彙を選ぶ
+        // 切り上げる場合には最後の境界が必要になるので丸め方によらず同じだけ選ぶ
+        while min_key_stroke_count < key_stroke_threshold.get()
+            || boundaries
+                .last()
+                .map(|(vocabulary_count, _)| *vocabulary_count)
+                != Some(query_vocabulary_infos.len())
+        {
+            let vocabulary_entry = next_vocabulary_generator.next()?;
+            let is_separator = separator_vocabulary.is_some_and(|separator_vocabulary| {
+                std::ptr::eq(vocabulary_entry, separator_vocabulary)
+            });
+
+            // 語彙区切りによっては語彙ごとにキーストロークを付与してはいけないケースがあるためまだ付与しない
+            let chunks = vocabulary_entry.construct_chunks(input_scheme)?;
+
+            let chunk_count = chunks.len().try_into().unwrap();
+            query_vocabulary_infos.push(
+                next_vocabulary_generator.construct_vocabulary_info(vocabulary_entry, chunk_count),
+            );
+
+            for chunk in chunks {
+                min_key_stroke_count += chunk.estimate_min_key_stroke_count(input_scheme);
+
+                query_chunks.push(chunk);
+            }
+
+            if !is_separator {
+                boundaries.push((query_vocabulary_infos.len(), query_chunks.len()));
+            }
+        }
+
+        append_key_stroke_to_chunks(&mut query_chunks, input_scheme, romanization_preference);
+
+        // 各境界までの実際のキーストローク回数
+        let mut boundary_key_stroke_counts = Vec::<usize>::with_capacity(boundaries.len());
+        let mut actual_key_stroke_count: usize = 0;
+        let mut chunk_index = 0;
+        for (_, chunk_count) in boundaries.iter() {
+            actual_key_stroke_count += query_chunks[chunk_index..*chunk_count]
+                .iter()
+                .map(|chunk| chunk.calc_min_key_stroke_count())
+                .sum::<usize>();
+            chunk_index = *chunk_count;
+
+            boundary_key_stroke_counts.push(actual_key_stroke_count);
+        }
+
+        // 推測したキーストローク回数は実際の回数以下なので最後の境界は必ず要求回数以上である
+        let round_up_index = boundary_key_stroke_counts
+            .iter()
+            .position(|count| *count >= key_stroke_threshold.get())
+            .unwrap_or(boundaries.len() - 1);
+        // 最初の語彙だけで要求回数を超える場合には最初の語彙で打ち切る
+        let round_down_index = boundary_key_stroke_counts
+            .iter()
+            .rposition(|count| *count <= key_stroke_threshold.get())
+            .unwrap_or(0);
+
+        let boundary_index = match rounding {
+            VocabularyBoundaryRounding::Up => round_up_index,
+            VocabularyBoundaryRounding::Down => round_down_index,
+            VocabularyBoundaryRounding::Nearest => {
+                let round_up_distance =
+                    boundary_key_stroke_counts[round_up_index].abs_diff(key_stroke_threshold.get());
+                let round_down_distance = boundary_key_stroke_counts[round_down_index]
+                    .abs_diff(key_stroke_threshold.get());
+
+                if round_down_distance < round_up_distance {
+                    round_down_index
+                } else {
+                    round_up_index
+                }
+            }
+        };
+
+        // 語彙の境界で打ち切るので最後のチャンクのキーストロークを制限する必要はない
+        // ただし末尾の「ん」や「っ」には打ち切った後続の語彙を前提とした候補が付与されているので付与し直す
+        let (vocabulary_count, chunk_count) = boundaries[boundary_index];
+        query_vocabulary_infos.truncate(vocabulary_count);
+        query_chunks.truncate(chunk_count);
+        reassign_key_stroke_to_last_chunks(
+            &mut query_chunks,
+            input_scheme,
+            romanization_preference,
+        );
+
+        Some(Query::new(query_vocabulary_infos, query_chunks))
     }
 
     fn construct_query_with_vocabulary_count(
         vocabulary_count: NonZeroUsize,
         mut next_vocabulary_generator: NextVocabularyGenerator,
-    ) -> Query {
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) -> Option<Query> {
         let mut query_chunks = Vec::<Chunk>::new();
         let mut query_vocabulary_infos = Vec::<VocabularyInfo>::new();
 
@@ -234,15 +558,17 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
         let mut current_vocabulary_count = 0;
         while current_vocabulary_count < vocabulary_count.get() {
             // 1
-            let vocabulary_entry = next_vocabulary_generator.next().unwrap();
+            let vocabulary_entry = next_vocabulary_generator.next()?;
 
             // 2
             // 語彙区切りによっては語彙ごとにキーストロークを付与してはいけないケースがあるためまだ付与しない
             // 例えば語彙区切りがない場合には語彙の末尾のキーストロークは次の語彙の先頭チャンクに依存する
-            let chunks = vocabulary_entry.construct_chunks();
+            let chunks = vocabulary_entry.construct_chunks(input_scheme)?;
 
             let chunk_count = chunks.len().try_into().unwrap();
-            query_vocabulary_infos.push(vocabulary_entry.construct_vocabulary_info(chunk_count));
+            query_vocabulary_infos.push(
+                next_vocabulary_generator.construct_vocabulary_info(vocabulary_entry, chunk_count),
+            );
 
             // 3
             for chunk in chunks {
@@ -253,59 +579,108 @@ impl<'vocabulary> QueryRequest<'vocabulary> {
         }
 
         // 全ての語彙や語彙区切りが確定してからキーストロークを付与する
-        append_key_stroke_to_chunks(&mut query_chunks);
+        append_key_stroke_to_chunks(&mut query_chunks, input_scheme, romanization_preference);
 
-        Query::new(query_vocabulary_infos, query_chunks)
+        Some(Query::new(query_vocabulary_infos, query_chunks))
     }
 }
 
 // 次の語彙を生成するイテレータ
 struct NextVocabularyGenerator<'this, 'vocabulary> {
-    vocabulary_entries: &'this [&'vocabulary VocabularyEntry],
-    is_prev_vocabulary: bool,
-    prev_vocabulary_index: Option<usize>,
+    vocabulary_pools: &'this [VocabularyPool<'vocabulary>],
+    // プールごとの選ぶ対象となる語彙
+    vocabulary_entries_of_pools: &'this [Vec<&'vocabulary VocabularyEntry>],
+    // 語彙を選ぶプールのインデックスを比率に従って並べたもの
+    // ex. 比率が2:1なら[0,0,1]
+    pool_schedule: Vec<usize>,
+    pool_schedule_position: usize,
+    // 次に語彙区切りを挿入すべきか
+    // 直前に語彙リストから選んだ語彙が語彙区切りを必要とする場合のみ挿入する
+    needs_separator: bool,
+    // プールごとの前回の語彙のインデックス
+    prev_vocabulary_indexes: Vec<Option<usize>>,
     separator_vocabulary: &'vocabulary Option<VocabularyEntry>,
-    vocabulary_order: &'this VocabularyOrder,
+    // 語彙区切りを必要とする語彙の後で改行するか
+    breaks_line: bool,
 }
 
 impl<'this, 'vocabulary> NextVocabularyGenerator<'this, 'vocabulary> {
     fn new(
-        vocabulary_entries: &'this [&'vocabulary VocabularyEntry],
+        vocabulary_pools: &'this [VocabularyPool<'vocabulary>],
+        vocabulary_entries_of_pools: &'this [Vec<&'vocabulary VocabularyEntry>],
         separator_vocabulary: &'vocabulary Option<VocabularyEntry>,
-        vocabulary_order: &'this VocabularyOrder,
+        breaks_line: bool,
     ) -> Self {
+        let pool_schedule = vocabulary_pools
+            .iter()
+            .enumerate()
+            .flat_map(|(i, vocabulary_pool)| std::iter::repeat_n(i, vocabulary_pool.ratio.get()))
+            .collect();
+
         Self {
-            vocabulary_entries,
-            is_prev_vocabulary: false,
-            prev_vocabulary_index: None,
+            vocabulary_pools,
+            vocabulary_entries_of_pools,
+            pool_schedule,
+            pool_schedule_position: 0,
+            needs_separator: false,
+            prev_vocabulary_indexes: vec![None; vocabulary_pools.len()],
             separator_vocabulary,
-            vocabulary_order,
+            breaks_line,
         }
     }
+
+    // このイテレータが生成した語彙のクエリ用の語彙情報を生成する
+    fn construct_vocabulary_info(
+        &self,
+        vocabulary_entry: &VocabularyEntry,
+        chunk_count: NonZeroUsize,
+    ) -> VocabularyInfo {
+        vocabulary_entry
+            .construct_vocabulary_info(chunk_count)
+            .with_trailing_line_break(
+                self.breaks_line && vocabulary_entry.needs_trailing_separator(),
+            )
+    }
 }
 
 impl<'this, 'vocabulary> Iterator for NextVocabularyGenerator<'this, 'vocabulary> {
     type Item = &'vocabulary VocabularyEntry;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(
-            if self.is_prev_vocabulary && self.separator_vocabulary.is_some() {
-                self.is_prev_vocabulary = false;
-                self.separator_vocabulary.as_ref().unwrap()
-            // 直前に追加した語彙が語彙リストから選んだ語彙ではなかったり語彙区切りがない場合のみ語彙リストから語彙を選択する
-            } else {
-                self.is_prev_vocabulary = true;
+        if self.needs_separator && self.separator_vocabulary.is_some() {
+            self.needs_separator = false;
+            self.separator_vocabulary.as_ref()
+        // 語彙区切りを挿入する必要がない場合のみ語彙リストから語彙を選択する
+        } else {
+            // 語彙を選ぶプールがない場合や空のプールからは語彙を選べない
+            if self.pool_schedule.is_empty() {
+                return None;
+            }
+
+            let pool_index = self.pool_schedule[self.pool_schedule_position];
+            self.pool_schedule_position =
+                (self.pool_schedule_position + 1) % self.pool_schedule.len();
 
-                let vocabulary_index = self.vocabulary_order.next_vocabulary_entry_index(
-                    &self.prev_vocabulary_index,
-                    self.vocabulary_entries,
-                );
+            let vocabulary_pool = &self.vocabulary_pools[pool_index];
+            let vocabulary_entries = &self.vocabulary_entries_of_pools[pool_index];
+            if vocabulary_entries.is_empty() {
+                return None;
+            }
 
-                self.prev_vocabulary_index.replace(vocabulary_index);
+            let prev_vocabulary_index = &mut self.prev_vocabulary_indexes[pool_index];
 
-                self.vocabulary_entries.get(vocabulary_index).unwrap()
-            },
-        )
+            let vocabulary_index = vocabulary_pool
+                .vocabulary_order
+                .next_vocabulary_entry_index(prev_vocabulary_index, vocabulary_entries);
+
+            prev_vocabulary_index.replace(vocabulary_index);
+
+            let vocabulary_entry = vocabulary_entries.get(vocabulary_index).copied()?;
+
+            self.needs_separator = vocabulary_entry.needs_trailing_separator();
+
+            Some(vocabulary_entry)
+        }
     }
 }
 
@@ -326,6 +701,20 @@ impl Query {
     pub(crate) fn decompose(self) -> (Vec<VocabularyInfo>, Vec<Chunk>) {
         (self.vocabulary_infos, self.chunks)
     }
+
+    // 理想的な候補はチャンク間の制限を考慮して付与されているのでその合計とする
+    fn ideal_key_stroke_count(&self) -> usize {
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                chunk
+                    .ideal_key_stroke_candidate()
+                    .as_ref()
+                    .unwrap()
+                    .calc_key_stroke_count()
+            })
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -336,6 +725,7 @@ mod test {
         gen_candidate, gen_chunk, gen_view_position, gen_vocabulary_entry, gen_vocabulary_info,
     };
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_query_1() {
         let vocabularies = vec![gen_vocabulary_entry!("イオン", [("い"), ("お"), ("ん")])];
@@ -351,7 +741,7 @@ mod test {
             VocabularyOrder::InOrder,
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -387,6 +777,7 @@ mod test {
         );
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_query_2() {
         let vocabularies = vec![gen_vocabulary_entry!("イオン", [("い"), ("お"), ("ん")])];
@@ -402,7 +793,7 @@ mod test {
             VocabularyOrder::InOrder,
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -451,6 +842,7 @@ mod test {
         );
     }
 
+    #[cfg(not(any(feature = "ascii_only", feature = "no_rare_romanization")))]
     #[test]
     fn construct_query_3() {
         let vocabularies = vec![
@@ -470,7 +862,7 @@ mod test {
             VocabularyOrder::InOrder,
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -575,7 +967,7 @@ mod test {
             })),
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -594,6 +986,7 @@ mod test {
         );
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_query_5() {
         let vocabularies = vec![gen_vocabulary_entry!("イオン", [("い"), ("お"), ("ん")])];
@@ -609,7 +1002,7 @@ mod test {
             VocabularyOrder::InOrder,
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -645,6 +1038,7 @@ mod test {
         );
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_query_6() {
         let vocabularies = vec![gen_vocabulary_entry!("印字", [("いん"), ("じ")])];
@@ -660,7 +1054,7 @@ mod test {
             VocabularyOrder::InOrder,
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -700,6 +1094,7 @@ mod test {
         );
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_query_7() {
         let vocabularies = vec![gen_vocabulary_entry!("印字", [("いん"), ("じ")])];
@@ -715,7 +1110,7 @@ mod test {
             VocabularyOrder::InOrder,
         );
 
-        let query = qr.construct_query();
+        let query = qr.construct_query(&InputScheme::Standard, None).unwrap();
 
         assert_eq!(
             query,
@@ -745,4 +1140,286 @@ mod test {
             )
         );
     }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_query_with_pools_1() {
+        let easy_vocabularies = [
+            gen_vocabulary_entry!("あ", [("あ")]),
+            gen_vocabulary_entry!("い", [("い")]),
+        ];
+        let hard_vocabularies = [gen_vocabulary_entry!("う", [("う")])];
+
+        let qr = QueryRequest::with_pools(
+            vec![
+                VocabularyPool::new(
+                    "easy".to_string(),
+                    easy_vocabularies
+                        .iter()
+                        .collect::<Vec<&VocabularyEntry>>()
+                        .as_slice(),
+                    VocabularyOrder::InOrder,
+                    NonZeroUsize::new(2).unwrap(),
+                ),
+                VocabularyPool::new(
+                    "hard".to_string(),
+                    hard_vocabularies
+                        .iter()
+                        .collect::<Vec<&VocabularyEntry>>()
+                        .as_slice(),
+                    VocabularyOrder::InOrder,
+                    NonZeroUsize::new(1).unwrap(),
+                ),
+            ],
+            VocabularyQuantifier::Vocabulary(NonZeroUsize::new(7).unwrap()),
+            VocabularySeparator::None,
+        );
+
+        let (vocabulary_infos, _) = qr
+            .construct_query(&InputScheme::Standard, None)
+            .unwrap()
+            .decompose();
+
+        assert_eq!(
+            vocabulary_infos
+                .iter()
+                .map(|vocabulary_info| vocabulary_info.view())
+                .collect::<Vec<&str>>(),
+            vec!["あ", "い", "う", "あ", "い", "う", "あ"]
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_query_without_trailing_separator() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あ。", [("あ"), ("。")]).with_trailing_separator(false),
+            gen_vocabulary_entry!("い", [("い")]),
+        ];
+
+        let qr = QueryRequest::new(
+            vocabularies
+                .iter()
+                .collect::<Vec<&VocabularyEntry>>()
+                .as_slice(),
+            VocabularyQuantifier::Vocabulary(NonZeroUsize::new(5).unwrap()),
+            VocabularySeparator::WhiteSpace,
+            VocabularyOrder::InOrder,
+        );
+
+        let (vocabulary_infos, _) = qr
+            .construct_query(&InputScheme::Standard, None)
+            .unwrap()
+            .decompose();
+
+        // 語彙区切りを必要としない語彙の後には語彙区切りを挿入しない
+        assert_eq!(
+            vocabulary_infos
+                .iter()
+                .map(|vocabulary_info| vocabulary_info.view())
+                .collect::<Vec<&str>>(),
+            vec!["あ。", "い", " ", "あ。", "い"]
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_query_at_vocabulary_boundary() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("うえお", [("う"), ("え"), ("お")]),
+        ];
+        let vocabularies = vocabularies.iter().collect::<Vec<&VocabularyEntry>>();
+
+        let construct = |key_stroke_count: usize,
+                         rounding: VocabularyBoundaryRounding,
+                         separator: VocabularySeparator| {
+            QueryRequest::new(
+                &vocabularies,
+                VocabularyQuantifier::KeyStrokeAtVocabularyBoundary(
+                    NonZeroUsize::new(key_stroke_count).unwrap(),
+                    rounding,
+                ),
+                separator,
+                VocabularyOrder::InOrder,
+            )
+            .construct_query(&InputScheme::Standard, None)
+            .unwrap()
+            .decompose()
+        };
+
+        // 語彙の境界でのキーストローク回数は2と5
+        let (vocabulary_infos, chunks) =
+            construct(3, VocabularyBoundaryRounding::Up, VocabularySeparator::None);
+        assert_eq!(vocabulary_infos.len(), 2);
+        assert_eq!(chunks.len(), 5);
+        // 最後のチャンクのキーストロークは制限されない
+        assert_eq!(
+            chunks.last().unwrap(),
+            &gen_chunk!("お", vec![gen_candidate!(["o"])], gen_candidate!(["o"]))
+        );
+
+        let (vocabulary_infos, chunks) = construct(
+            3,
+            VocabularyBoundaryRounding::Down,
+            VocabularySeparator::None,
+        );
+        assert_eq!(vocabulary_infos.len(), 1);
+        assert_eq!(chunks.len(), 2);
+
+        let (vocabulary_infos, _) = construct(
+            3,
+            VocabularyBoundaryRounding::Nearest,
+            VocabularySeparator::None,
+        );
+        assert_eq!(vocabulary_infos.len(), 1);
+
+        let (vocabulary_infos, _) = construct(
+            4,
+            VocabularyBoundaryRounding::Nearest,
+            VocabularySeparator::None,
+        );
+        assert_eq!(vocabulary_infos.len(), 2);
+
+        // 最初の語彙だけで要求回数を超える場合でも最初の語彙は含む
+        let (vocabulary_infos, _) = construct(
+            1,
+            VocabularyBoundaryRounding::Down,
+            VocabularySeparator::None,
+        );
+        assert_eq!(vocabulary_infos.len(), 1);
+
+        // 語彙区切りでは終わらない
+        let (vocabulary_infos, chunks) = construct(
+            3,
+            VocabularyBoundaryRounding::Up,
+            VocabularySeparator::WhiteSpace,
+        );
+        assert_eq!(vocabulary_infos.len(), 3);
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(vocabulary_infos.last().unwrap().view(), "うえお");
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_query_with_key_stroke_count_range() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("きょう", [("きょ", 2), ("う")]),
+            gen_vocabulary_entry!("うえお", [("う"), ("え"), ("お")]),
+        ];
+        let vocabularies = vocabularies.iter().collect::<Vec<&VocabularyEntry>>();
+
+        let construct = |range: (Bound<usize>, Bound<usize>)| {
+            QueryRequest::new(
+                &vocabularies,
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            )
+            .with_key_stroke_count_range(range)
+            .construct_query(&InputScheme::Standard, None)
+        };
+
+        // 最小キーストローク回数はそれぞれ2,4,3
+        let (vocabulary_infos, _) = construct((Bound::Included(3), Bound::Unbounded))
+            .unwrap()
+            .decompose();
+        assert_eq!(
+            vocabulary_infos
+                .iter()
+                .map(|vocabulary_info| vocabulary_info.view())
+                .collect::<Vec<&str>>(),
+            vec!["きょう", "うえお"]
+        );
+
+        let (vocabulary_infos, _) = construct((Bound::Unbounded, Bound::Excluded(3)))
+            .unwrap()
+            .decompose();
+        assert_eq!(
+            vocabulary_infos
+                .iter()
+                .map(|vocabulary_info| vocabulary_info.view())
+                .collect::<Vec<&str>>(),
+            vec!["あい", "あい"]
+        );
+
+        // 範囲内の語彙がなければクエリを構築できない
+        assert!(construct((Bound::Included(5), Bound::Unbounded)).is_none());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_query_with_custom_separator() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("うえ", [("う"), ("え")]),
+        ];
+        let vocabularies = vocabularies.iter().collect::<Vec<&VocabularyEntry>>();
+
+        let construct = |separator: &str| {
+            QueryRequest::new(
+                &vocabularies,
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::Custom(separator.to_string()),
+                VocabularyOrder::InOrder,
+            )
+            .construct_query(&InputScheme::Standard, None)
+        };
+
+        let (vocabulary_infos, chunks) = construct(" / ").unwrap().decompose();
+        assert_eq!(
+            vocabulary_infos
+                .iter()
+                .map(|vocabulary_info| vocabulary_info.view())
+                .collect::<Vec<&str>>(),
+            vec!["あい", " / ", "うえ"]
+        );
+        assert_eq!(chunks.len(), 7);
+
+        // 綴りとして使えない文字列や空文字列は語彙区切りにできない
+        assert!(construct("\n").is_none());
+        assert!(construct("").is_none());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn compute_ideal_key_stroke_count() {
+        let vocabularies = [
+            gen_vocabulary_entry!("かん", [("か"), ("ん")]),
+            gen_vocabulary_entry!("だ", [("だ")]),
+        ];
+        let vocabularies = vocabularies.iter().collect::<Vec<&VocabularyEntry>>();
+
+        let ideal_key_stroke_count =
+            |vocabulary_separator: VocabularySeparator, vocabulary_count: usize| {
+                QueryRequest::new(
+                    &vocabularies,
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(vocabulary_count).unwrap()),
+                    vocabulary_separator,
+                    VocabularyOrder::InOrder,
+                )
+                .ideal_key_stroke_count(&InputScheme::Standard, None)
+            };
+
+        // 推定では後続の語彙によらず「ん」を「n」で打てるものとする
+        assert_eq!(
+            vocabularies
+                .iter()
+                .map(|vocabulary| vocabulary
+                    .estimate_min_key_stroke_count(&InputScheme::Standard)
+                    .unwrap())
+                .sum::<usize>(),
+            5
+        );
+        assert_eq!(
+            ideal_key_stroke_count(VocabularySeparator::None, 2),
+            Some(5)
+        );
+        // 語彙区切りの前の「ん」は「n」で打てない
+        assert_eq!(
+            ideal_key_stroke_count(VocabularySeparator::WhiteSpace, 3),
+            Some(7)
+        );
+    }
 }
@@ -1,17 +1,25 @@
-use crate::utility::{is_displayable_ascii, is_hiragana, is_japanese_symbol};
+use serde::{Deserialize, Serialize};
+
+use crate::utility::{is_displayable_ascii, is_hiragana, is_japanese_symbol, is_katakana};
 use std::{
     error::Error,
     fmt::Display,
     ops::{Deref, DerefMut},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 /// An string only contains characters which can be used as spells.
 ///
 /// Characters can be used as spells are
 /// * A displayable ASCII. (`U+20` ~ `U+7E`)
 /// * A japanese hiragana. (`U+3041` ~ `U+308F`, `U+3092` ~ `U+3094`)
-/// * A japanese symbol.
+/// * A japanese katakana which corresponds to hiragana. (`U+30A1` ~ `U+30EF`, `U+30F2` ~ `U+30F4`)
+/// * A japanese symbol including full-width forms of ASCII. (`U+FF01` ~ `U+FF5E`)
+///
+/// Katakana is typed as corresponding hiragana.
+/// Full-width form of ASCII is typed as corresponding ASCII unless input scheme maps it to other
+/// key strokes.
 pub struct SpellString(String);
 
 impl SpellString {
@@ -27,7 +35,7 @@ impl SpellString {
 }
 
 fn can_use_in_spell_string(c: char) -> bool {
-    is_displayable_ascii(c) || is_hiragana(c) || is_japanese_symbol(c)
+    is_displayable_ascii(c) || is_hiragana(c) || is_katakana(c) || is_japanese_symbol(c)
 }
 
 impl From<SpellString> for String {
@@ -1,15 +1,52 @@
+#[cfg(feature = "analysis")]
+use std::collections::BTreeMap;
 use std::{num::NonZeroUsize, time::Duration};
 
 use serde::{Deserialize, Serialize};
 
-mod multi_target_position_convert;
+#[cfg(feature = "analysis")]
+pub(crate) mod context;
+#[cfg(feature = "analysis")]
+pub(crate) mod finger;
+#[cfg(feature = "analysis")]
+pub(crate) mod heatmap;
+#[cfg(feature = "analysis")]
+pub(crate) mod interval;
+pub(crate) mod multi_target_position_convert;
+#[cfg(feature = "analysis")]
+pub(crate) mod plausibility;
+#[cfg(feature = "analysis")]
+pub(crate) mod profile;
+#[cfg(feature = "statistics")]
 pub(crate) mod result;
+#[cfg(feature = "analysis")]
+pub(crate) mod score;
+#[cfg(feature = "analysis")]
+pub(crate) mod skill;
+#[cfg(feature = "analysis")]
+pub(crate) mod telemetry;
+#[cfg(feature = "analysis")]
+pub(crate) mod typo;
 
+#[cfg(feature = "analysis")]
+use crate::chunk::confirmed::ConfirmedChunk;
+#[cfg(feature = "analysis")]
+use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+#[cfg(feature = "analysis")]
+use crate::chunk::typed::TypedChunk;
+#[cfg(feature = "statistics")]
 use crate::chunk::KeyStrokeElementCount;
+#[cfg(feature = "statistics")]
+use crate::display_info::per_minute;
+#[cfg(feature = "analysis")]
+use crate::key_stroke::ActualKeyStroke;
+#[cfg(feature = "statistics")]
 use multi_target_position_convert::MultiTargetDeltaConverter;
 
+#[cfg(feature = "statistics")]
 use self::multi_target_position_convert::BaseTarget;
 
+#[cfg(feature = "statistics")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct OnTypingStatisticsTarget {
     // 対象を何個打ち終えたか
@@ -23,12 +60,15 @@ pub struct OnTypingStatisticsTarget {
     wrong_count: usize,
     // ラップ当たりの対象数
     targets_per_lap: Option<NonZeroUsize>,
+    // 明示的に指定されたラップ末の位置
+    custom_lap_end_positions: Option<Vec<usize>>,
     // 各ラップ末の経過時間
     lap_end_time: Option<Vec<Duration>>,
     // 各ラップ末の位置
     lap_end_position: Vec<usize>,
 }
 
+#[cfg(feature = "statistics")]
 impl OnTypingStatisticsTarget {
     pub(crate) fn new(
         finished_count: usize,
@@ -39,7 +79,8 @@ impl OnTypingStatisticsTarget {
         lap_end_time: Option<Vec<Duration>>,
         lap_end_position: Vec<usize>,
     ) -> Self {
-        assert_eq!(targets_per_lap.is_some(), lap_end_time.is_some());
+        // 時間ごとのラップでは対象数によらずラップ末の経過時間を持つ
+        assert!(targets_per_lap.is_none() || lap_end_time.is_some());
 
         Self {
             finished_count,
@@ -47,6 +88,7 @@ impl OnTypingStatisticsTarget {
             completely_correct_count,
             wrong_count,
             targets_per_lap,
+            custom_lap_end_positions: None,
             lap_end_time,
             lap_end_position,
         }
@@ -85,9 +127,42 @@ impl OnTypingStatisticsTarget {
         &self.lap_end_position
     }
 
+    /// Get index of lap which contains `position` of target.
+    ///
+    /// Laps are indexed from 0 in the order of [`lap_end_positions`](Self::lap_end_positions()),
+    /// so the index is stable while typing and can be used to join position with lap end time.
+    /// Position after last lap end belongs to lap whose index is count of lap end positions.
+    ///
+    /// ex. When lap end positions are `[2,5]`, positions `0..=2` belong to lap `0`, positions
+    /// `3..=5` belong to lap `1` and positions from `6` belong to lap `2`.
+    pub fn lap_index_of_position(&self, position: usize) -> usize {
+        self.lap_end_position
+            .partition_point(|lap_end_position| *lap_end_position < position)
+    }
+
+    /// ラップ末の位置を変換したものを構築する
+    pub(crate) fn convert_lap_end_positions<F: Fn(usize) -> usize>(&self, conversion: F) -> Self {
+        let mut converted = self.clone();
+
+        converted.lap_end_position = self
+            .lap_end_position
+            .iter()
+            .map(|position| conversion(*position))
+            .collect();
+
+        converted
+    }
+
     fn on_finished(&mut self, delta: usize, completely_correct: bool, elapsed_time: Duration) {
         let lap_finish_num = if let Some(tpl) = &self.targets_per_lap {
             ((self.finished_count + delta) / tpl.get()) - (self.finished_count / tpl.get())
+        } else if let Some(custom_lap_end_positions) = &self.custom_lap_end_positions {
+            let finished_range = self.finished_count..(self.finished_count + delta);
+
+            custom_lap_end_positions
+                .iter()
+                .filter(|position| finished_range.contains(position))
+                .count()
         } else {
             0
         };
@@ -110,30 +185,56 @@ impl OnTypingStatisticsTarget {
     ///
     /// ex. 現在の対象数が2でラップあたりの対象数が5のときdeltaが8で呼ばれたケースには[3,8]が返される
     fn on_target_add(&mut self, delta: usize) -> Option<Vec<usize>> {
-        let mut may_lap_end_deltas: Option<Vec<usize>> = None;
+        let lap_end_deltas: Vec<usize> = self
+            .lap_end_positions_in_added(delta)
+            .into_iter()
+            .map(|position| position - self.whole_count + 1)
+            .collect();
+        self.whole_count += delta;
+
+        (!lap_end_deltas.is_empty()).then_some(lap_end_deltas)
+    }
+
+    /// 対象をdelta個追加したときに追加した対象のうちラップ末となる位置
+    /// 追加する対象を1つずつ走査しないのでラップ末の数にのみ比例する
+    fn lap_end_positions_in_added(&self, delta: usize) -> Vec<usize> {
+        let added_end = self.whole_count + delta;
 
         if let Some(tpl) = self.targets_per_lap {
-            let mut lap_end_deltas = vec![];
+            // 位置に1を足したものがラップ当たりの対象数の倍数になる位置がラップ末である
+            let first_lap_end_position = (self.whole_count / tpl.get() + 1) * tpl.get() - 1;
 
-            let mut delta_to_next_lap_end = tpl.get() - (self.whole_count % tpl.get());
-            while delta_to_next_lap_end <= delta {
-                lap_end_deltas.push(delta_to_next_lap_end);
-                delta_to_next_lap_end += tpl.get();
-            }
+            (first_lap_end_position..added_end)
+                .step_by(tpl.get())
+                .collect()
+        } else if let Some(custom_lap_end_positions) = &self.custom_lap_end_positions {
+            // 明示的に指定されたラップ末の位置は並べ替えてある
+            let start =
+                custom_lap_end_positions.partition_point(|position| *position < self.whole_count);
+            let end = custom_lap_end_positions.partition_point(|position| *position < added_end);
 
-            if !lap_end_deltas.is_empty() {
-                may_lap_end_deltas.replace(lap_end_deltas);
-            }
+            custom_lap_end_positions[start..end].to_vec()
+        } else {
+            vec![]
         }
-        self.whole_count += delta;
-
-        may_lap_end_deltas
     }
 
     fn on_wrong(&mut self, delta: usize) {
         self.wrong_count += delta;
     }
 
+    /// 対象を`finished_count`個打ち終えた時点で終えているラップ数
+    /// 時間ごとのラップは対象の数に依らないので含めない
+    fn lap_count_at(&self, finished_count: usize) -> usize {
+        if let Some(tpl) = self.targets_per_lap {
+            finished_count / tpl.get()
+        } else if let Some(custom_lap_end_positions) = &self.custom_lap_end_positions {
+            custom_lap_end_positions.partition_point(|position| *position < finished_count)
+        } else {
+            0
+        }
+    }
+
     fn add_lap_ends(&mut self, lap_end_deltas: &[usize], base_whole_count: usize) {
         lap_end_deltas.iter().for_each(|lap_end_delta| {
             self.lap_end_position
@@ -142,13 +243,98 @@ impl OnTypingStatisticsTarget {
     }
 }
 
+#[cfg(feature = "analysis")]
+/// 実際のキーストロークそれぞれについて，直前のチャンクの綴りの末尾・打つべきキーストローク・実際のキーストロークを順に渡す
+pub(crate) fn for_each_expected_key_stroke<F: FnMut(Option<char>, char, &ActualKeyStroke)>(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+    mut f: F,
+) {
+    let mut preceding_spell: Option<char> = None;
+
+    let typed_chunks = confirmed_chunks
+        .iter()
+        .map(|confirmed_chunk| confirmed_chunk as &dyn ChunkHasActualKeyStrokes)
+        .chain(
+            inflight_chunk
+                .into_iter()
+                .map(|inflight_chunk| inflight_chunk as &dyn ChunkHasActualKeyStrokes),
+        );
+
+    typed_chunks.for_each(|typed_chunk| {
+        let effective_candidate = typed_chunk.effective_candidate();
+        let mut in_candidate_cursor_position = 0;
+
+        typed_chunk
+            .actual_key_strokes()
+            .iter()
+            .for_each(|actual_key_stroke| {
+                let expected_key_stroke = char::from(
+                    effective_candidate.key_stroke_char_at_position(in_candidate_cursor_position),
+                );
+
+                f(preceding_spell, expected_key_stroke, actual_key_stroke);
+
+                if actual_key_stroke.is_correct() {
+                    in_candidate_cursor_position += 1;
+                }
+            });
+
+        preceding_spell = typed_chunk.as_ref().spell().as_ref().chars().last();
+    });
+}
+
+#[cfg(feature = "analysis")]
+/// ミスタイプ回数が多くミス率が高いものほど前に来るように並べる
+/// それでも同じ場合にはキー自体の順番で並べることで順番を決定的にする
+pub(crate) fn sort_by_severity<K: Ord>(
+    counts: BTreeMap<K, (usize, usize)>,
+) -> Vec<(K, usize, usize)> {
+    let mut sorted: Vec<(K, usize, usize)> = counts
+        .into_iter()
+        .map(|(key, (stroke_count, wrong_count))| (key, stroke_count, wrong_count))
+        .collect();
+
+    // 安定ソートなので同じ場合にはキーの順番が保たれる
+    sorted.sort_by(
+        |(_, a_stroke_count, a_wrong_count), (_, b_stroke_count, b_wrong_count)| {
+            b_wrong_count.cmp(a_wrong_count).then_with(|| {
+                // ミス率の比較は除算を避けるため交差乗算で行う
+                (b_wrong_count * a_stroke_count).cmp(&(a_wrong_count * b_stroke_count))
+            })
+        },
+    );
+
+    sorted
+}
+
+/// A request for how to take laps.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LapRequest {
     KeyStroke(NonZeroUsize),
     IdealKeyStroke(NonZeroUsize),
     Spell(NonZeroUsize),
     Chunk(NonZeroUsize),
+    /// Take laps every N vocabularies.
+    ///
+    /// Vocabulary separators are also counted as vocabularies.
+    Vocabulary(NonZeroUsize),
+    /// Take laps every given elapsed time.
+    ///
+    /// Lap end times are kept by key stroke target and lap end positions of each target are
+    /// positions finished at the lap end time.
+    /// A lap is taken when a key stroke after its end time is given, so laps are not taken while
+    /// no key is stroked.
+    /// Zero duration takes no laps.
+    Duration(Duration),
+    /// Take laps which end at given spell positions.
+    ///
+    /// Each position is the last spell position of a lap such as the end of a sentence.
+    /// Positions do not have to be sorted, and positions beyond the query are ignored.
+    CustomSpellPositions(Vec<usize>),
 }
 
+#[cfg(feature = "statistics")]
 impl LapRequest {
     fn construct_base_target(&self) -> BaseTarget {
         match self {
@@ -156,11 +342,62 @@ impl LapRequest {
             Self::IdealKeyStroke(_) => BaseTarget::IdealKeyStroke,
             Self::Spell(_) => BaseTarget::Spell,
             Self::Chunk(_) => BaseTarget::Chunk,
+            Self::Vocabulary(_) => BaseTarget::Vocabulary,
+            Self::CustomSpellPositions(_) => BaseTarget::Spell,
+            // 時間ごとのラップは対象の追加ではラップ末が決まらない
+            Self::Duration(_) => unreachable!("laps by duration are not taken on target add"),
+        }
+    }
+}
+
+#[cfg(feature = "statistics")]
+/// Summary of key strokes typed in a lap.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LapSummary {
+    key_stroke_count: usize,
+    wrong_key_stroke_count: usize,
+    duration: Duration,
+}
+
+#[cfg(feature = "statistics")]
+impl LapSummary {
+    /// Get count of correct key strokes in the lap.
+    pub fn key_stroke_count(&self) -> usize {
+        self.key_stroke_count
+    }
+
+    /// Get count of wrong key strokes in the lap.
+    pub fn wrong_key_stroke_count(&self) -> usize {
+        self.wrong_key_stroke_count
+    }
+
+    /// Get time taken to finish the lap.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Get key strokes per minute in the lap.
+    pub fn kpm(&self) -> f64 {
+        per_minute(self.key_stroke_count, self.duration)
+    }
+
+    /// Get ratio of correct key strokes to all key strokes in the lap.
+    ///
+    /// If there is no key stroke, this function returns `1.0`.
+    pub fn accuracy(&self) -> f64 {
+        let stroke_count = self.key_stroke_count + self.wrong_key_stroke_count;
+
+        if stroke_count == 0 {
+            1.0
+        } else {
+            self.key_stroke_count as f64 / stroke_count as f64
         }
     }
 }
 
+#[cfg(feature = "statistics")]
 /// タイピング中の各対象の統計情報を管理する
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct OnTypingStatisticsManager {
     // 実際のキーストローク系列に基づいた統計
     key_stroke: OnTypingStatisticsTarget,
@@ -168,48 +405,87 @@ pub(crate) struct OnTypingStatisticsManager {
     ideal_key_stroke: OnTypingStatisticsTarget,
     spell: OnTypingStatisticsTarget,
     chunk: OnTypingStatisticsTarget,
+    vocabulary: OnTypingStatisticsTarget,
     lap_request: LapRequest,
     this_key_stroke_wrong: bool,
     this_ideal_key_stroke_wrong: bool,
     this_spell_wrong: bool,
     this_chunk_wrong: bool,
+    this_vocabulary_wrong: bool,
     this_candidate_key_stroke_count: Option<usize>,
     this_ideal_candidate_key_stroke_count: Option<usize>,
     in_candidate_key_stroke_count: usize,
     last_key_stroke_elapsed_time: Option<Duration>,
+    // 終えたラップごとのキーストロークの集計
+    lap_summaries: Vec<LapSummary>,
+    // ミスタイプせずに連続して正しく打ったキーストローク数
+    combo: usize,
+    max_combo: usize,
 }
 
+#[cfg(feature = "statistics")]
 impl OnTypingStatisticsManager {
     pub(crate) fn new(lap_request: LapRequest) -> Self {
         let mut key_stroke_targets_per_lap: Option<NonZeroUsize> = None;
         let mut ideal_key_stroke_targets_per_lap: Option<NonZeroUsize> = None;
         let mut spell_targets_per_lap: Option<NonZeroUsize> = None;
         let mut chunk_targets_per_lap: Option<NonZeroUsize> = None;
+        let mut vocabulary_targets_per_lap: Option<NonZeroUsize> = None;
 
         let mut key_stroke_lap_end_time: Option<Vec<Duration>> = None;
         let mut ideal_key_stroke_lap_end_time: Option<Vec<Duration>> = None;
         let mut spell_lap_end_time: Option<Vec<Duration>> = None;
         let mut chunk_lap_end_time: Option<Vec<Duration>> = None;
+        let mut vocabulary_lap_end_time: Option<Vec<Duration>> = None;
 
-        match lap_request {
+        let mut spell_custom_lap_end_positions: Option<Vec<usize>> = None;
+
+        match &lap_request {
             LapRequest::KeyStroke(tpl) => {
-                key_stroke_targets_per_lap.replace(tpl);
+                key_stroke_targets_per_lap.replace(*tpl);
                 key_stroke_lap_end_time.replace(vec![]);
             }
             LapRequest::IdealKeyStroke(tpl) => {
-                ideal_key_stroke_targets_per_lap.replace(tpl);
+                ideal_key_stroke_targets_per_lap.replace(*tpl);
                 ideal_key_stroke_lap_end_time.replace(vec![]);
             }
             LapRequest::Spell(tpl) => {
-                spell_targets_per_lap.replace(tpl);
+                spell_targets_per_lap.replace(*tpl);
                 spell_lap_end_time.replace(vec![]);
             }
             LapRequest::Chunk(tpl) => {
-                chunk_targets_per_lap.replace(tpl);
+                chunk_targets_per_lap.replace(*tpl);
                 chunk_lap_end_time.replace(vec![]);
             }
+            LapRequest::Vocabulary(tpl) => {
+                vocabulary_targets_per_lap.replace(*tpl);
+                vocabulary_lap_end_time.replace(vec![]);
+            }
+            LapRequest::Duration(_) => {
+                key_stroke_lap_end_time.replace(vec![]);
+            }
+            LapRequest::CustomSpellPositions(positions) => {
+                // ラップ末は位置の順に追加していくので並べ替えておく
+                let mut positions = positions.clone();
+                positions.sort_unstable();
+                positions.dedup();
+
+                spell_custom_lap_end_positions.replace(positions);
+                spell_lap_end_time.replace(vec![]);
+            }
         }
 
+        let mut spell = OnTypingStatisticsTarget::new(
+            0,
+            0,
+            0,
+            0,
+            spell_targets_per_lap,
+            spell_lap_end_time,
+            vec![],
+        );
+        spell.custom_lap_end_positions = spell_custom_lap_end_positions;
+
         Self {
             key_stroke: OnTypingStatisticsTarget::new(
                 0,
@@ -229,22 +505,23 @@ impl OnTypingStatisticsManager {
                 ideal_key_stroke_lap_end_time,
                 vec![],
             ),
-            spell: OnTypingStatisticsTarget::new(
+            spell,
+            chunk: OnTypingStatisticsTarget::new(
                 0,
                 0,
                 0,
                 0,
-                spell_targets_per_lap,
-                spell_lap_end_time,
+                chunk_targets_per_lap,
+                chunk_lap_end_time,
                 vec![],
             ),
-            chunk: OnTypingStatisticsTarget::new(
+            vocabulary: OnTypingStatisticsTarget::new(
                 0,
                 0,
                 0,
                 0,
-                chunk_targets_per_lap,
-                chunk_lap_end_time,
+                vocabulary_targets_per_lap,
+                vocabulary_lap_end_time,
                 vec![],
             ),
             lap_request,
@@ -252,10 +529,66 @@ impl OnTypingStatisticsManager {
             this_ideal_key_stroke_wrong: false,
             this_spell_wrong: false,
             this_chunk_wrong: false,
+            this_vocabulary_wrong: false,
             this_candidate_key_stroke_count: None,
             this_ideal_candidate_key_stroke_count: None,
             in_candidate_key_stroke_count: 0,
             last_key_stroke_elapsed_time: None,
+            lap_summaries: vec![],
+            combo: 0,
+            max_combo: 0,
+        }
+    }
+
+    /// 終えたラップごとのキーストロークの集計
+    pub(crate) fn lap_summaries(&self) -> &[LapSummary] {
+        &self.lap_summaries
+    }
+
+    pub(crate) fn combo(&self) -> usize {
+        self.combo
+    }
+
+    pub(crate) fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+
+    /// ラップを取る対象のラップ末が増えていたら増えたラップの集計を追加する
+    /// 同時に複数のラップを終えた場合には2つ目以降のラップのキーストロークは0とする
+    fn update_lap_summaries(&mut self) {
+        let lap_target = match self.lap_request {
+            LapRequest::KeyStroke(_) => &self.key_stroke,
+            LapRequest::IdealKeyStroke(_) => &self.ideal_key_stroke,
+            LapRequest::Spell(_) => &self.spell,
+            LapRequest::Chunk(_) => &self.chunk,
+            LapRequest::Vocabulary(_) => &self.vocabulary,
+            LapRequest::Duration(_) => &self.key_stroke,
+            LapRequest::CustomSpellPositions(_) => &self.spell,
+        };
+        let lap_end_time = lap_target.lap_end_time().unwrap();
+
+        while self.lap_summaries.len() < lap_end_time.len() {
+            let lap_index = self.lap_summaries.len();
+            let lap_start_time = lap_index
+                .checked_sub(1)
+                .map_or(Duration::ZERO, |previous_lap_index| {
+                    lap_end_time[previous_lap_index]
+                });
+            let (key_stroke_count, wrong_key_stroke_count) = self.lap_summaries.iter().fold(
+                (self.key_stroke.finished_count, self.key_stroke.wrong_count),
+                |(key_stroke_count, wrong_key_stroke_count), lap_summary| {
+                    (
+                        key_stroke_count - lap_summary.key_stroke_count,
+                        wrong_key_stroke_count - lap_summary.wrong_key_stroke_count,
+                    )
+                },
+            );
+
+            self.lap_summaries.push(LapSummary {
+                key_stroke_count,
+                wrong_key_stroke_count,
+                duration: lap_end_time[lap_index].saturating_sub(lap_start_time),
+            });
         }
     }
 
@@ -272,16 +605,78 @@ impl OnTypingStatisticsManager {
     }
 
     /// 現在セットされたチャンクのキーストローク数を元に実際のキーストローク内のインデックスが理想的な候補内のどのインデックスに対応するかを計算する
-    ///
-    /// ex. 実際のキーストロークが「kixyo」で理想的なキーストロークが「kyo」だったとき
-    /// 実際の1キーストロークは理想的なキーストロークに換算すると3/5キーストロークである
-    /// そこでnキーストローク打ったときにはceil(n * 3/5)キーストローク打ったことにする
     fn calc_ideal_key_stroke_index(&self, actual_key_stroke_index: usize) -> usize {
-        let ideal_count = self.this_ideal_candidate_key_stroke_count.unwrap();
-        let actual_count = self.this_candidate_key_stroke_count.unwrap();
+        ideal_key_stroke_index(
+            actual_key_stroke_index,
+            self.this_candidate_key_stroke_count.unwrap(),
+            self.this_ideal_candidate_key_stroke_count.unwrap(),
+        )
+    }
+
+    /// タイプ中のチャンクのキーストロークを反映した場合に終えているラップ数を求める
+    ///
+    /// 各キーストロークは正しいかどうか・打ち終えた綴りの数・経過時間の組で与える
+    /// ラップを取る対象を打ち終えた数のみを追うので統計全体を複製せずにキーストローク数に比例する時間で求まる
+    pub(crate) fn completed_lap_count_with_key_strokes(
+        &self,
+        candidate_key_stroke_count: usize,
+        ideal_candidate_key_stroke_count: usize,
+        key_strokes: impl Iterator<Item = (bool, Option<usize>, Duration)>,
+    ) -> usize {
+        let mut key_stroke_finished_count = self.key_stroke.finished_count;
+        let mut ideal_key_stroke_finished_count = self.ideal_key_stroke.finished_count;
+        let mut spell_finished_count = self.spell.finished_count;
+        let mut in_candidate_key_stroke_count = self.in_candidate_key_stroke_count;
+        let mut last_elapsed_time: Option<Duration> = None;
+
+        key_strokes.for_each(|(is_correct, finished_spell_count, elapsed_time)| {
+            last_elapsed_time.replace(elapsed_time);
+
+            if is_correct {
+                key_stroke_finished_count += 1;
+                in_candidate_key_stroke_count += 1;
+
+                if ideal_key_stroke_index(
+                    in_candidate_key_stroke_count - 1,
+                    candidate_key_stroke_count,
+                    ideal_candidate_key_stroke_count,
+                ) != ideal_key_stroke_index(
+                    in_candidate_key_stroke_count,
+                    candidate_key_stroke_count,
+                    ideal_candidate_key_stroke_count,
+                ) {
+                    ideal_key_stroke_finished_count += 1;
+                }
+
+                spell_finished_count += finished_spell_count.unwrap_or(0);
+            }
+        });
 
-        // ceil(a/b)は (a+b-1)/b とできる
-        (((actual_key_stroke_index + 1) * ideal_count) + actual_count - 1) / actual_count - 1
+        match &self.lap_request {
+            LapRequest::KeyStroke(_) => self.key_stroke.lap_count_at(key_stroke_finished_count),
+            LapRequest::IdealKeyStroke(_) => self
+                .ideal_key_stroke
+                .lap_count_at(ideal_key_stroke_finished_count),
+            LapRequest::Spell(_) | LapRequest::CustomSpellPositions(_) => {
+                self.spell.lap_count_at(spell_finished_count)
+            }
+            // タイプ中のチャンクでは対象を打ち終えない
+            LapRequest::Chunk(_) => self.chunk.lap_count_at(self.chunk.finished_count),
+            LapRequest::Vocabulary(_) => {
+                self.vocabulary.lap_count_at(self.vocabulary.finished_count)
+            }
+            // 各キーストロークの時点までに過ぎたラップ末を取るので最後のキーストロークの経過時間で決まる
+            LapRequest::Duration(lap_duration) => {
+                let lap_count = self.key_stroke.lap_end_time().unwrap().len();
+
+                match last_elapsed_time {
+                    Some(elapsed_time) if !lap_duration.is_zero() => {
+                        lap_count.max((elapsed_time.as_nanos() / lap_duration.as_nanos()) as usize)
+                    }
+                    _ => lap_count,
+                }
+            }
+        }
     }
 
     /// 実際のキーストロークをしたときに呼ぶ
@@ -291,7 +686,13 @@ impl OnTypingStatisticsManager {
         spell_count: usize,
         elapsed_time: Duration,
     ) {
+        // このキーストロークより前に過ぎたラップ末はこのキーストロークを含めずに取る
+        self.take_laps_by_duration(elapsed_time);
+
         if is_correct {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+
             self.in_candidate_key_stroke_count += 1;
             self.key_stroke
                 .on_finished(1, !self.this_key_stroke_wrong, elapsed_time);
@@ -311,6 +712,8 @@ impl OnTypingStatisticsManager {
                 self.this_ideal_key_stroke_wrong = false;
             }
         } else {
+            self.combo = 0;
+
             self.key_stroke.on_wrong(1);
             self.ideal_key_stroke.on_wrong(1);
             self.spell.on_wrong(spell_count);
@@ -319,10 +722,52 @@ impl OnTypingStatisticsManager {
             self.this_ideal_key_stroke_wrong = true;
             self.this_spell_wrong = true;
             self.this_chunk_wrong = true;
+            self.this_vocabulary_wrong = true;
         }
 
         self.this_key_stroke_wrong = !is_correct;
         self.last_key_stroke_elapsed_time.replace(elapsed_time);
+        self.update_lap_summaries();
+    }
+
+    /// 時間ごとのラップを取る場合に経過時間までに過ぎたラップ末を追加する
+    /// ラップ末の位置はその時点で打ち終えている対象の位置とする
+    fn take_laps_by_duration(&mut self, elapsed_time: Duration) {
+        let LapRequest::Duration(lap_duration) = self.lap_request else {
+            return;
+        };
+
+        if lap_duration.is_zero() {
+            return;
+        }
+
+        loop {
+            let lap_count = self.key_stroke.lap_end_time().unwrap().len();
+            let lap_end_time = lap_duration * (lap_count as u32 + 1);
+
+            if elapsed_time < lap_end_time {
+                break;
+            }
+
+            self.key_stroke
+                .lap_end_time
+                .as_mut()
+                .unwrap()
+                .push(lap_end_time);
+
+            for target in [
+                &mut self.key_stroke,
+                &mut self.ideal_key_stroke,
+                &mut self.spell,
+                &mut self.chunk,
+            ] {
+                target
+                    .lap_end_position
+                    .push(target.finished_count.saturating_sub(1));
+            }
+        }
+
+        self.update_lap_summaries();
     }
 
     /// 綴りを打ち終えたときに呼ぶ
@@ -333,14 +778,17 @@ impl OnTypingStatisticsManager {
             self.last_key_stroke_elapsed_time.unwrap(),
         );
         self.this_spell_wrong = false;
+        self.update_lap_summaries();
     }
 
     /// チャンクを打ち終えたときに呼ぶ
+    /// 語彙末のチャンクかどうかは各語彙の末尾までのチャンク数から判定する
     pub(crate) fn finish_chunk(
         &mut self,
         key_stroke_element_count: KeyStrokeElementCount,
         ideal_key_stroke_element_count: KeyStrokeElementCount,
         spell_count: usize,
+        vocabulary_end_chunk_counts: &[usize],
     ) {
         self.chunk.on_finished(
             1,
@@ -349,6 +797,16 @@ impl OnTypingStatisticsManager {
         );
         self.this_chunk_wrong = false;
 
+        if self.is_vocabulary_end_chunk(vocabulary_end_chunk_counts) {
+            self.vocabulary.on_finished(
+                1,
+                !self.this_vocabulary_wrong,
+                self.last_key_stroke_elapsed_time.unwrap(),
+            );
+            self.this_vocabulary_wrong = false;
+        }
+        self.update_lap_summaries();
+
         self.in_candidate_key_stroke_count = 0;
 
         let ks_whole_count = self.key_stroke.whole_count();
@@ -364,15 +822,25 @@ impl OnTypingStatisticsManager {
         let s_whole_count = self.spell.whole_count();
         let sle = self.spell.on_target_add(spell_count);
 
+        // 語彙末かどうかはチャンクを追加する前のチャンク数から判定する
+        let vle = if self.is_vocabulary_end_chunk(vocabulary_end_chunk_counts) {
+            self.vocabulary.on_target_add(1)
+        } else {
+            None
+        };
+
         let c_whole_count = self.chunk.whole_count();
         let cle = self.chunk.on_target_add(1);
 
-        if ksle.is_some() || iksle.is_some() || sle.is_some() || cle.is_some() {
+        if ksle.is_some() || iksle.is_some() || sle.is_some() || cle.is_some() || vle.is_some() {
             let lap_ends = match self.lap_request {
                 LapRequest::KeyStroke(_) => ksle,
                 LapRequest::IdealKeyStroke(_) => iksle,
                 LapRequest::Spell(_) => sle,
                 LapRequest::Chunk(_) => cle,
+                LapRequest::Vocabulary(_) => vle,
+                LapRequest::Duration(_) => None,
+                LapRequest::CustomSpellPositions(_) => sle,
             };
 
             let lap_ends = lap_ends.unwrap();
@@ -401,6 +869,7 @@ impl OnTypingStatisticsManager {
         key_stroke_element_count: KeyStrokeElementCount,
         ideal_key_stroke_element_count: KeyStrokeElementCount,
         spell_count: usize,
+        vocabulary_end_chunk_counts: &[usize],
     ) {
         let ks_whole_count = self.key_stroke.whole_count();
         let ksle = self
@@ -415,15 +884,25 @@ impl OnTypingStatisticsManager {
         let s_whole_count = self.spell.whole_count();
         let sle = self.spell.on_target_add(spell_count);
 
+        // 語彙末かどうかはチャンクを追加する前のチャンク数から判定する
+        let vle = if self.is_vocabulary_end_chunk(vocabulary_end_chunk_counts) {
+            self.vocabulary.on_target_add(1)
+        } else {
+            None
+        };
+
         let c_whole_count = self.chunk.whole_count();
         let cle = self.chunk.on_target_add(1);
 
-        if ksle.is_some() || iksle.is_some() || sle.is_some() || cle.is_some() {
+        if ksle.is_some() || iksle.is_some() || sle.is_some() || cle.is_some() || vle.is_some() {
             let lap_ends = match self.lap_request {
                 LapRequest::KeyStroke(_) => ksle,
                 LapRequest::IdealKeyStroke(_) => iksle,
                 LapRequest::Spell(_) => sle,
                 LapRequest::Chunk(_) => cle,
+                LapRequest::Vocabulary(_) => vle,
+                LapRequest::Duration(_) => None,
+                LapRequest::CustomSpellPositions(_) => sle,
             };
 
             let lap_ends = lap_ends.unwrap();
@@ -446,6 +925,123 @@ impl OnTypingStatisticsManager {
         }
     }
 
+    /// 打ち終えていないチャンク列をまとめてカウントする時に呼ぶ
+    /// 各チャンクを理想的なキーストロークで[`add_unfinished_chunk`](Self::add_unfinished_chunk())した場合と同じになる
+    ///
+    /// `ideal_key_stroke_heads`と`spell_heads`は各チャンクの先頭の位置に末尾の位置を加えたものである
+    /// チャンクを1つずつ走査せずにラップ末となる位置のみを求めるのでチャンク数ではなくラップ末の数に比例する
+    pub(crate) fn add_unfinished_chunks(
+        &mut self,
+        ideal_key_stroke_heads: &[usize],
+        spell_heads: &[usize],
+        ideal_key_stroke_element_count_of: impl Fn(usize) -> KeyStrokeElementCount,
+        vocabulary_end_chunk_counts: &[usize],
+    ) {
+        assert_eq!(ideal_key_stroke_heads.len(), spell_heads.len());
+        let chunk_count = spell_heads.len() - 1;
+        let ideal_key_stroke_count =
+            ideal_key_stroke_heads[chunk_count] - ideal_key_stroke_heads[0];
+        let spell_count = spell_heads[chunk_count] - spell_heads[0];
+
+        // 語彙末かどうかはチャンクを追加する前のチャンク数から判定する
+        let c_whole_count = self.chunk.whole_count();
+        let vocabulary_end_start = vocabulary_end_chunk_counts
+            .partition_point(|chunk_count| *chunk_count <= c_whole_count);
+        let vocabulary_end_count = vocabulary_end_chunk_counts[vocabulary_end_start..]
+            .partition_point(|count| *count <= c_whole_count + chunk_count);
+
+        // 各ラップ末をそれを含むチャンクとそのチャンク内でのラップ末の位置に変換する
+        let in_chunk_lap_ends = |heads: &[usize], target: &OnTypingStatisticsTarget, count| {
+            target
+                .lap_end_positions_in_added(count)
+                .into_iter()
+                .map(|position| {
+                    let offset = position - target.whole_count() + heads[0];
+                    let chunk_index = heads.partition_point(|head| *head <= offset) - 1;
+
+                    (chunk_index, offset - heads[chunk_index] + 1)
+                })
+                .collect::<Vec<(usize, usize)>>()
+        };
+
+        let lap_ends = match self.lap_request {
+            LapRequest::KeyStroke(_) => in_chunk_lap_ends(
+                ideal_key_stroke_heads,
+                &self.key_stroke,
+                ideal_key_stroke_count,
+            ),
+            LapRequest::IdealKeyStroke(_) => in_chunk_lap_ends(
+                ideal_key_stroke_heads,
+                &self.ideal_key_stroke,
+                ideal_key_stroke_count,
+            ),
+            LapRequest::Spell(_) | LapRequest::CustomSpellPositions(_) => {
+                in_chunk_lap_ends(spell_heads, &self.spell, spell_count)
+            }
+            LapRequest::Chunk(_) => self
+                .chunk
+                .lap_end_positions_in_added(chunk_count)
+                .into_iter()
+                .map(|position| (position - c_whole_count, 1))
+                .collect(),
+            LapRequest::Vocabulary(_) => self
+                .vocabulary
+                .lap_end_positions_in_added(vocabulary_end_count)
+                .into_iter()
+                .map(|position| {
+                    let vocabulary_end_chunk_count = vocabulary_end_chunk_counts
+                        [vocabulary_end_start + position - self.vocabulary.whole_count()];
+
+                    (vocabulary_end_chunk_count - c_whole_count - 1, 1)
+                })
+                .collect(),
+            LapRequest::Duration(_) => vec![],
+        };
+
+        lap_ends
+            .into_iter()
+            .for_each(|(chunk_index, lap_end_delta)| {
+                let ideal_key_stroke_element_count = ideal_key_stroke_element_count_of(chunk_index);
+                let mdc = MultiTargetDeltaConverter::new(
+                    spell_heads[chunk_index + 1] - spell_heads[chunk_index],
+                    ideal_key_stroke_element_count.clone(),
+                    ideal_key_stroke_element_count,
+                    self.lap_request.construct_base_target(),
+                );
+                let ideal_key_stroke_offset =
+                    ideal_key_stroke_heads[chunk_index] - ideal_key_stroke_heads[0];
+                let lap_ends = [lap_end_delta];
+
+                self.key_stroke.add_lap_ends(
+                    &mdc.key_stroke_delta(&lap_ends),
+                    self.key_stroke.whole_count() + ideal_key_stroke_offset,
+                );
+                self.ideal_key_stroke.add_lap_ends(
+                    &mdc.ideal_key_stroke_delta(&lap_ends),
+                    self.ideal_key_stroke.whole_count() + ideal_key_stroke_offset,
+                );
+                self.spell.add_lap_ends(
+                    &mdc.spell_delta(&lap_ends),
+                    self.spell.whole_count() + spell_heads[chunk_index] - spell_heads[0],
+                );
+                self.chunk
+                    .add_lap_ends(&mdc.chunk_delta(&lap_ends), c_whole_count + chunk_index);
+            });
+
+        self.key_stroke.whole_count += ideal_key_stroke_count;
+        self.ideal_key_stroke.whole_count += ideal_key_stroke_count;
+        self.spell.whole_count += spell_count;
+        self.chunk.whole_count += chunk_count;
+        self.vocabulary.whole_count += vocabulary_end_count;
+    }
+
+    /// 次に追加するチャンクが語彙の末尾のチャンクかどうか
+    fn is_vocabulary_end_chunk(&self, vocabulary_end_chunk_counts: &[usize]) -> bool {
+        vocabulary_end_chunk_counts
+            .binary_search(&(self.chunk.whole_count() + 1))
+            .is_ok()
+    }
+
     pub(crate) fn emit(
         self,
     ) -> (
@@ -462,3 +1058,18 @@ impl OnTypingStatisticsManager {
         )
     }
 }
+
+#[cfg(feature = "statistics")]
+/// 実際の候補の`actual_key_stroke_index`番目のキーストロークが理想的な候補の何番目のキーストロークに対応するか
+///
+/// ex. 実際のキーストロークが「kixyo」で理想的なキーストロークが「kyo」だったとき
+/// 実際の1キーストロークは理想的なキーストロークに換算すると3/5キーストロークである
+/// そこでnキーストローク打ったときにはceil(n * 3/5)キーストローク打ったことにする
+fn ideal_key_stroke_index(
+    actual_key_stroke_index: usize,
+    actual_count: usize,
+    ideal_count: usize,
+) -> usize {
+    // ceil(a/b)は (a+b-1)/b とできる
+    (((actual_key_stroke_index + 1) * ideal_count) + actual_count - 1) / actual_count - 1
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::typed::TypedChunk;
+use crate::statistics::{for_each_expected_key_stroke, sort_by_severity};
+
+/// Miss statistics of a key stroke conditioned on preceding spell.
+///
+/// ex. `u` of `きゅう` and `u` of `きょう` are distinguished by preceding spell `ゅ` and `ょ`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContextualMissStatistics {
+    preceding_spell: Option<char>,
+    key_stroke: char,
+    stroke_count: usize,
+    wrong_count: usize,
+}
+
+impl ContextualMissStatistics {
+    /// Get last character of spell of the chunk preceding the chunk which the key stroke belongs
+    /// to.
+    ///
+    /// This is `None` for key strokes of the first chunk.
+    pub fn preceding_spell(&self) -> Option<char> {
+        self.preceding_spell
+    }
+
+    /// Get key stroke character that should be typed.
+    pub fn key_stroke(&self) -> char {
+        self.key_stroke
+    }
+
+    /// Get count of key strokes including wrong key strokes.
+    pub fn stroke_count(&self) -> usize {
+        self.stroke_count
+    }
+
+    /// Get count of wrong key strokes.
+    pub fn wrong_count(&self) -> usize {
+        self.wrong_count
+    }
+
+    /// Get ratio of wrong key strokes to all key strokes.
+    pub fn miss_rate(&self) -> f64 {
+        self.wrong_count as f64 / self.stroke_count as f64
+    }
+}
+
+// 直前のチャンクの綴りの末尾と打つべきキーストロークの組
+type ContextKey = (Option<char>, char);
+
+/// 直前のチャンクの綴りの末尾と打つべきキーストロークの組ごとにミスタイプを集計する
+pub(crate) fn construct_contextual_miss_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+) -> Vec<ContextualMissStatistics> {
+    let mut contexts: BTreeMap<ContextKey, (usize, usize)> = BTreeMap::new();
+
+    for_each_expected_key_stroke(
+        confirmed_chunks,
+        inflight_chunk,
+        |preceding_spell, expected_key_stroke, actual_key_stroke| {
+            let (stroke_count, wrong_count) = contexts
+                .entry((preceding_spell, expected_key_stroke))
+                .or_default();
+
+            *stroke_count += 1;
+
+            if !actual_key_stroke.is_correct() {
+                *wrong_count += 1;
+            }
+        },
+    );
+
+    construct_from_counts(contexts)
+}
+
+/// 2つの集計結果を合算する
+pub(crate) fn merge_contextual_miss_statistics(
+    statistics: &[ContextualMissStatistics],
+    other: &[ContextualMissStatistics],
+) -> Vec<ContextualMissStatistics> {
+    let mut contexts: BTreeMap<ContextKey, (usize, usize)> = BTreeMap::new();
+
+    statistics.iter().chain(other.iter()).for_each(|context| {
+        let (stroke_count, wrong_count) = contexts
+            .entry((context.preceding_spell, context.key_stroke))
+            .or_default();
+
+        *stroke_count += context.stroke_count;
+        *wrong_count += context.wrong_count;
+    });
+
+    construct_from_counts(contexts)
+}
+
+fn construct_from_counts(
+    contexts: BTreeMap<ContextKey, (usize, usize)>,
+) -> Vec<ContextualMissStatistics> {
+    sort_by_severity(contexts)
+        .into_iter()
+        .map(
+            |((preceding_spell, key_stroke), stroke_count, wrong_count)| ContextualMissStatistics {
+                preceding_spell,
+                key_stroke,
+                stroke_count,
+                wrong_count,
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn construct_contextual_miss_statistics_1() {
+        let confirmed_chunks = vec![
+            ConfirmedChunk::new(
+                gen_chunk!(
+                    "きゅ",
+                    vec![gen_candidate!(["kyu"])],
+                    gen_candidate!(["kyu"])
+                ),
+                vec![
+                    ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(2, 0), 'y'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(3, 0), 'u'.try_into().unwrap(), true),
+                ],
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("う", vec![gen_candidate!(["u"])], gen_candidate!(["u"])),
+                vec![
+                    ActualKeyStroke::new(Duration::new(4, 0), 'i'.try_into().unwrap(), false),
+                    ActualKeyStroke::new(Duration::new(5, 0), 'u'.try_into().unwrap(), true),
+                ],
+            ),
+        ];
+
+        let statistics = construct_contextual_miss_statistics(&confirmed_chunks, None);
+
+        assert_eq!(
+            statistics[0],
+            ContextualMissStatistics {
+                preceding_spell: Some('ゅ'),
+                key_stroke: 'u',
+                stroke_count: 2,
+                wrong_count: 1,
+            }
+        );
+        assert_eq!(statistics.len(), 4);
+        assert!(statistics[1..]
+            .iter()
+            .all(|context| context.preceding_spell().is_none() && context.wrong_count() == 0));
+
+        let merged = merge_contextual_miss_statistics(&statistics, &statistics);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged[0].stroke_count(), 4);
+        assert_eq!(merged[0].wrong_count(), 2);
+    }
+}
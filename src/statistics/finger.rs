@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::typed::TypedChunk;
+use crate::key_stroke::KeyStrokeChar;
+use crate::keyboard_layout::KeyboardLayout;
+use crate::statistics::for_each_expected_key_stroke;
+
+use Finger::*;
+
+// タッチタイピングで各行のキーを左から順に担当する指
+const STANDARD_FINGERS: [&[Finger]; 4] = [
+    &[
+        LeftPinky,
+        LeftPinky,
+        LeftRing,
+        LeftMiddle,
+        LeftIndex,
+        LeftIndex,
+        RightIndex,
+        RightIndex,
+        RightMiddle,
+        RightRing,
+        RightPinky,
+        RightPinky,
+        RightPinky,
+    ],
+    &[
+        LeftPinky,
+        LeftRing,
+        LeftMiddle,
+        LeftIndex,
+        LeftIndex,
+        RightIndex,
+        RightIndex,
+        RightMiddle,
+        RightRing,
+        RightPinky,
+        RightPinky,
+        RightPinky,
+        RightPinky,
+    ],
+    &[
+        LeftPinky,
+        LeftRing,
+        LeftMiddle,
+        LeftIndex,
+        LeftIndex,
+        RightIndex,
+        RightIndex,
+        RightMiddle,
+        RightRing,
+        RightPinky,
+        RightPinky,
+    ],
+    &[
+        LeftPinky,
+        LeftRing,
+        LeftMiddle,
+        LeftIndex,
+        LeftIndex,
+        RightIndex,
+        RightIndex,
+        RightMiddle,
+        RightRing,
+        RightPinky,
+    ],
+];
+
+/// A hand which types key strokes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+/// A finger which types key strokes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    LeftThumb,
+    RightThumb,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+impl Finger {
+    /// Get the hand this finger belongs to.
+    pub fn hand(&self) -> Hand {
+        match self {
+            LeftPinky | LeftRing | LeftMiddle | LeftIndex | LeftThumb => Hand::Left,
+            RightThumb | RightIndex | RightMiddle | RightRing | RightPinky => Hand::Right,
+        }
+    }
+}
+
+/// A table which assigns a finger to each key stroke character.
+///
+/// Key stroke characters which are not assigned are not counted in [`FingerStatistics`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FingerMap {
+    fingers: BTreeMap<char, Finger>,
+}
+
+impl FingerMap {
+    /// Construct a table of standard touch typing on `keyboard_layout`.
+    ///
+    /// Fingers are assigned by physical position of keys, so characters typed with Shift key are
+    /// assigned to the same finger as the key itself.
+    /// Space is assigned to [`Finger::RightThumb`].
+    pub fn new(keyboard_layout: &KeyboardLayout) -> Self {
+        let fingers = (' '..='~')
+            .filter_map(|c| {
+                let key_stroke = KeyStrokeChar::try_from(c).unwrap();
+
+                keyboard_layout
+                    .key_position(&key_stroke)
+                    .map(|(row, position)| (c, STANDARD_FINGERS[row][position]))
+            })
+            .chain([(' ', RightThumb)])
+            .collect();
+
+        Self { fingers }
+    }
+
+    /// Construct a table which assigns `finger` to `key_stroke`.
+    ///
+    /// This is useful to customize the table for non-standard fingering.
+    pub fn with_assignment(mut self, key_stroke: KeyStrokeChar, finger: Finger) -> Self {
+        self.fingers.insert(key_stroke.into(), finger);
+        self
+    }
+
+    /// Get the finger assigned to `key_stroke`.
+    pub fn finger(&self, key_stroke: &KeyStrokeChar) -> Option<Finger> {
+        self.fingers.get(&char::from(key_stroke.clone())).copied()
+    }
+}
+
+impl Default for FingerMap {
+    fn default() -> Self {
+        Self::new(&KeyboardLayout::default())
+    }
+}
+
+/// Statistics of key strokes aggregated by finger which should type them.
+///
+/// Wrong key strokes are attributed to the finger of the key which should be typed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FingerStatistics {
+    fingers: BTreeMap<Finger, FingerSkillStatistics>,
+}
+
+impl FingerStatistics {
+    /// Get statistics of each finger which typed at least one key stroke.
+    pub fn fingers(&self) -> &BTreeMap<Finger, FingerSkillStatistics> {
+        &self.fingers
+    }
+
+    /// Get statistics of `finger`.
+    pub fn finger(&self, finger: Finger) -> FingerSkillStatistics {
+        self.fingers.get(&finger).cloned().unwrap_or_default()
+    }
+
+    /// Get statistics of all fingers of `hand`.
+    pub fn hand(&self, hand: Hand) -> FingerSkillStatistics {
+        let mut hand_statistics = FingerSkillStatistics::default();
+
+        self.fingers
+            .iter()
+            .filter(|(finger, _)| finger.hand() == hand)
+            .for_each(|(_, statistics)| hand_statistics.accumulate(statistics));
+
+        hand_statistics
+    }
+
+    pub(crate) fn accumulate(&mut self, other: &FingerStatistics) {
+        other.fingers.iter().for_each(|(finger, statistics)| {
+            self.fingers
+                .entry(*finger)
+                .or_default()
+                .accumulate(statistics);
+        });
+    }
+}
+
+/// Statistics of key strokes typed by a finger or a hand.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FingerSkillStatistics {
+    stroke_count: usize,
+    wrong_count: usize,
+    // 直前のキーストロークからの時間を測れた正しいキーストロークについての合計
+    total_latency: Duration,
+    latency_count: usize,
+}
+
+impl FingerSkillStatistics {
+    /// Get count of key strokes including wrong key strokes.
+    pub fn stroke_count(&self) -> usize {
+        self.stroke_count
+    }
+
+    /// Get count of wrong key strokes.
+    pub fn wrong_count(&self) -> usize {
+        self.wrong_count
+    }
+
+    /// Get ratio of wrong key strokes to all key strokes.
+    ///
+    /// If no key stroke is typed, this returns `0.0`.
+    pub fn miss_rate(&self) -> f64 {
+        if self.stroke_count == 0 {
+            0.0
+        } else {
+            self.wrong_count as f64 / self.stroke_count as f64
+        }
+    }
+
+    /// Get average time from the previous key stroke to correct key strokes.
+    ///
+    /// If latency is not measured, this returns `None`.
+    /// Statistics derived from [`SkillStatistics`](crate::SkillStatistics) do not have latency.
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.latency_count == 0 {
+            None
+        } else {
+            Some(self.total_latency / self.latency_count as u32)
+        }
+    }
+
+    fn accumulate(&mut self, other: &FingerSkillStatistics) {
+        self.stroke_count += other.stroke_count;
+        self.wrong_count += other.wrong_count;
+        self.total_latency += other.total_latency;
+        self.latency_count += other.latency_count;
+    }
+}
+
+/// 打つべきキーストロークを担当する指ごとにキーストロークを集計する
+pub(crate) fn construct_finger_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+    finger_map: &FingerMap,
+) -> FingerStatistics {
+    let mut fingers: BTreeMap<Finger, FingerSkillStatistics> = BTreeMap::new();
+    let mut previous_elapsed_time: Option<Duration> = None;
+
+    for_each_expected_key_stroke(
+        confirmed_chunks,
+        inflight_chunk,
+        |_, expected_key_stroke, actual_key_stroke| {
+            let elapsed_time = *actual_key_stroke.elapsed_time();
+
+            if let Some(finger) = finger_map.fingers.get(&expected_key_stroke) {
+                let statistics = fingers.entry(*finger).or_default();
+                statistics.stroke_count += 1;
+
+                if !actual_key_stroke.is_correct() {
+                    statistics.wrong_count += 1;
+                } else if let Some(previous_elapsed_time) = previous_elapsed_time {
+                    statistics.total_latency += elapsed_time.saturating_sub(previous_elapsed_time);
+                    statistics.latency_count += 1;
+                }
+            }
+
+            previous_elapsed_time.replace(elapsed_time);
+        },
+    );
+
+    FingerStatistics { fingers }
+}
+
+// キーストロークごとの回数を指ごとに集計する
+// 時間は記録されていないので遅延は集計しない
+pub(crate) fn aggregate_finger_counts(
+    counts: impl Iterator<Item = (char, usize, usize)>,
+    finger_map: &FingerMap,
+) -> FingerStatistics {
+    let mut fingers: BTreeMap<Finger, FingerSkillStatistics> = BTreeMap::new();
+
+    counts.for_each(|(key_stroke, stroke_count, wrong_count)| {
+        if let Some(finger) = finger_map.fingers.get(&key_stroke) {
+            let statistics = fingers.entry(*finger).or_default();
+            statistics.stroke_count += stroke_count;
+            statistics.wrong_count += wrong_count;
+        }
+    });
+
+    FingerStatistics { fingers }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn finger_map_follows_keyboard_layout() {
+        let qwerty = FingerMap::new(&KeyboardLayout::Qwerty);
+        assert_eq!(qwerty.finger(&'a'.try_into().unwrap()), Some(LeftPinky));
+        assert_eq!(qwerty.finger(&'J'.try_into().unwrap()), Some(RightIndex));
+        assert_eq!(qwerty.finger(&'5'.try_into().unwrap()), Some(LeftIndex));
+        assert_eq!(qwerty.finger(&' '.try_into().unwrap()), Some(RightThumb));
+
+        // Dvorakではoは左手薬指で打つ
+        let dvorak = FingerMap::new(&KeyboardLayout::Dvorak);
+        assert_eq!(dvorak.finger(&'o'.try_into().unwrap()), Some(LeftRing));
+        assert_eq!(dvorak.finger(&'s'.try_into().unwrap()), Some(RightPinky));
+
+        let custom = qwerty.with_assignment('b'.try_into().unwrap(), RightIndex);
+        assert_eq!(custom.finger(&'b'.try_into().unwrap()), Some(RightIndex));
+    }
+
+    #[test]
+    fn construct_finger_statistics_1() {
+        let confirmed_chunks = vec![ConfirmedChunk::new(
+            gen_chunk!(
+                "きょ",
+                vec![gen_candidate!(["kyo"])],
+                gen_candidate!(["kyo"])
+            ),
+            vec![
+                ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(2, 0), 't'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(4, 0), 'y'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(5, 0), 'o'.try_into().unwrap(), true),
+            ],
+        )];
+
+        let finger_statistics =
+            construct_finger_statistics(&confirmed_chunks, None, &FingerMap::default());
+
+        let right_index = finger_statistics.finger(RightIndex);
+        assert_eq!(right_index.stroke_count(), 2);
+        assert_eq!(right_index.wrong_count(), 1);
+        assert_eq!(right_index.miss_rate(), 0.5);
+        assert_eq!(right_index.average_latency(), Some(Duration::new(2, 0)));
+
+        let right_middle = finger_statistics.finger(RightMiddle);
+        assert_eq!(right_middle.stroke_count(), 1);
+        // 最初のキーストロークは直前のキーストロークがないので遅延を測れない
+        assert_eq!(right_middle.average_latency(), None);
+
+        let right_hand = finger_statistics.hand(Hand::Right);
+        assert_eq!(right_hand.stroke_count(), 4);
+        assert_eq!(
+            right_hand.average_latency(),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(finger_statistics.hand(Hand::Left).stroke_count(), 0);
+    }
+}
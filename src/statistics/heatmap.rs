@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::typed::TypedChunk;
+use crate::statistics::for_each_expected_key_stroke;
+
+/// Wrong key strokes aggregated by intended key and actually pressed key.
+///
+/// This is a confusion matrix of keys, so it is useful to render keyboard heatmaps.
+/// Keys are represented by key stroke characters, so convert them to positions on keyboard
+/// according to the layout used by application.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MistypeHeatmap {
+    // 打つべきキーと実際に押したキーの組ごとのミスタイプ回数
+    confusions: BTreeMap<(char, char), usize>,
+}
+
+impl MistypeHeatmap {
+    /// Get count of wrong key strokes for each pair of intended key and actually pressed key.
+    pub fn confusions(&self) -> &BTreeMap<(char, char), usize> {
+        &self.confusions
+    }
+
+    /// Get count of wrong key strokes when `intended_key` should be typed but `actual_key` is
+    /// pressed.
+    pub fn confusion_count(&self, intended_key: char, actual_key: char) -> usize {
+        self.confusions
+            .get(&(intended_key, actual_key))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Get count of wrong key strokes for each intended key.
+    pub fn wrong_count_by_intended_key(&self) -> BTreeMap<char, usize> {
+        let mut wrong_counts: BTreeMap<char, usize> = BTreeMap::new();
+
+        self.confusions
+            .iter()
+            .for_each(|((intended_key, _), count)| {
+                *wrong_counts.entry(*intended_key).or_default() += count;
+            });
+
+        wrong_counts
+    }
+
+    /// Get count of wrong key strokes for each actually pressed key.
+    pub fn wrong_count_by_actual_key(&self) -> BTreeMap<char, usize> {
+        let mut wrong_counts: BTreeMap<char, usize> = BTreeMap::new();
+
+        self.confusions.iter().for_each(|((_, actual_key), count)| {
+            *wrong_counts.entry(*actual_key).or_default() += count;
+        });
+
+        wrong_counts
+    }
+}
+
+/// 打つべきキーと実際に押したキーの組ごとにミスタイプを集計する
+pub(crate) fn construct_mistype_heatmap(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+) -> MistypeHeatmap {
+    let mut confusions: BTreeMap<(char, char), usize> = BTreeMap::new();
+
+    for_each_expected_key_stroke(
+        confirmed_chunks,
+        inflight_chunk,
+        |_, expected_key_stroke, actual_key_stroke| {
+            if !actual_key_stroke.is_correct() {
+                *confusions
+                    .entry((
+                        expected_key_stroke,
+                        char::from(actual_key_stroke.key_stroke().clone()),
+                    ))
+                    .or_default() += 1;
+            }
+        },
+    );
+
+    MistypeHeatmap { confusions }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn construct_mistype_heatmap_1() {
+        let confirmed_chunks = vec![ConfirmedChunk::new(
+            gen_chunk!(
+                "きょ",
+                vec![gen_candidate!(["kyo"])],
+                gen_candidate!(["kyo"])
+            ),
+            vec![
+                ActualKeyStroke::new(Duration::new(1, 0), 'j'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(2, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(3, 0), 'y'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(4, 0), 'p'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(5, 0), 'p'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(6, 0), 'o'.try_into().unwrap(), true),
+            ],
+        )];
+
+        let heatmap = construct_mistype_heatmap(&confirmed_chunks, None);
+
+        assert_eq!(heatmap.confusions().len(), 2);
+        assert_eq!(heatmap.confusion_count('k', 'j'), 1);
+        assert_eq!(heatmap.confusion_count('o', 'p'), 2);
+        assert_eq!(heatmap.confusion_count('y', 'p'), 0);
+        assert_eq!(
+            heatmap.wrong_count_by_intended_key(),
+            BTreeMap::from([('k', 1), ('o', 2)])
+        );
+        assert_eq!(
+            heatmap.wrong_count_by_actual_key(),
+            BTreeMap::from([('j', 1), ('p', 2)])
+        );
+    }
+}
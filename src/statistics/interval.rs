@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+use crate::chunk::typed::TypedChunk;
+
+// 各バケットが表す区間の上限と下限の比
+// 相対誤差が1%となるように(1+0.01)/(1-0.01)とする
+const GAMMA: f64 = 1.01 / 0.99;
+
+/// A sketch of distribution of durations which estimates quantiles.
+///
+/// Durations are counted in buckets whose width grows exponentially, so memory usage depends on
+/// range of durations instead of count of them.
+/// Estimated quantile has relative error within 1%.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuantileSketch {
+    count: usize,
+    // 1マイクロ秒未満の時間の数
+    zero_count: usize,
+    // マイクロ秒単位の時間xはGAMMA^(i-1) < x <= GAMMA^iを満たすバケットiに数える
+    buckets: BTreeMap<i32, usize>,
+}
+
+impl QuantileSketch {
+    /// Get count of durations.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Get estimated median of durations.
+    ///
+    /// If there is no duration, this function returns `None`.
+    pub fn median(&self) -> Option<Duration> {
+        self.quantile(0.5)
+    }
+
+    /// Get estimated 95th percentile of durations.
+    ///
+    /// If there is no duration, this function returns `None`.
+    pub fn p95(&self) -> Option<Duration> {
+        self.quantile(0.95)
+    }
+
+    /// Get estimated `q`-quantile of durations.
+    ///
+    /// If there is no duration or `q` is not in range `0.0..=1.0`, this function returns `None`.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        if self.count == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+
+        let rank = (q * (self.count - 1) as f64) as usize;
+
+        if rank < self.zero_count {
+            return Some(Duration::ZERO);
+        }
+
+        let mut accumulated_count = self.zero_count;
+        self.buckets.iter().find_map(|(index, count)| {
+            accumulated_count += count;
+
+            if rank < accumulated_count {
+                // バケットの区間の中で相対誤差が最小になる値を代表値とする
+                let micros = 2.0 * GAMMA.powi(*index) / (GAMMA + 1.0);
+                Some(Duration::from_secs_f64(micros / 1_000_000.0))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub(crate) fn insert(&mut self, duration: Duration) {
+        self.count += 1;
+
+        let micros = duration.as_secs_f64() * 1_000_000.0;
+        if micros < 1.0 {
+            self.zero_count += 1;
+        } else {
+            let index = (micros.ln() / GAMMA.ln()).ceil() as i32;
+            *self.buckets.entry(index).or_default() += 1;
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.zero_count += other.zero_count;
+
+        other.buckets.iter().for_each(|(index, count)| {
+            *self.buckets.entry(*index).or_default() += count;
+        });
+    }
+}
+
+/// Distribution of intervals between consecutive correct key strokes.
+///
+/// Interval is regarded as time taken to type latter key stroke, so it includes time spent on
+/// wrong key strokes between them.
+/// Unlike average speed, quantiles of intervals reveal hesitations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InterKeyIntervalStatistics {
+    overall: QuantileSketch,
+    per_key: BTreeMap<char, QuantileSketch>,
+    // 標準偏差を正確に求めるためにマイクロ秒単位の和と二乗和を保持する
+    sum_micros: u128,
+    square_sum_micros: u128,
+    longest_pause: Duration,
+}
+
+impl InterKeyIntervalStatistics {
+    /// Get distribution of all intervals.
+    pub fn overall(&self) -> &QuantileSketch {
+        &self.overall
+    }
+
+    /// Get distribution of intervals for each key stroke typed at the end of interval.
+    pub fn per_key(&self) -> &BTreeMap<char, QuantileSketch> {
+        &self.per_key
+    }
+
+    /// Get mean of all intervals.
+    ///
+    /// If there is no interval, this function returns `None`.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.overall.count() != 0)
+            .then(|| Duration::from_secs_f64(self.mean_micros() / 1_000_000.0))
+    }
+
+    /// Get standard deviation of all intervals.
+    ///
+    /// If there is no interval, this function returns `None`.
+    pub fn standard_deviation(&self) -> Option<Duration> {
+        if self.overall.count() == 0 {
+            return None;
+        }
+
+        let count = self.overall.count() as u128;
+        // 和と二乗和は整数なので分散の分子は誤差なく求まる
+        let variance_micros = (count * self.square_sum_micros - self.sum_micros * self.sum_micros)
+            as f64
+            / (count * count) as f64;
+
+        Some(Duration::from_secs_f64(
+            variance_micros.sqrt() / 1_000_000.0,
+        ))
+    }
+
+    /// Get coefficient of variation of all intervals, which is standard deviation divided by
+    /// mean.
+    ///
+    /// This is independent of speed, so it is useful to compare steadiness of players.
+    /// If there is no interval or mean is zero, this function returns `None`.
+    pub fn coefficient_of_variation(&self) -> Option<f64> {
+        let standard_deviation = self.standard_deviation()?;
+        let mean_micros = self.mean_micros();
+
+        (mean_micros != 0.0).then(|| standard_deviation.as_secs_f64() * 1_000_000.0 / mean_micros)
+    }
+
+    /// Get the longest interval.
+    ///
+    /// If there is no interval, this function returns zero.
+    pub fn longest_pause(&self) -> Duration {
+        self.longest_pause
+    }
+
+    /// Get rhythm score in range `0.0..=100.0`.
+    ///
+    /// This is `100 / (1 + coefficient of variation)`, so this is `100` when all intervals are the
+    /// same and decreases as intervals vary.
+    /// If coefficient of variation is not available, this function returns `None`.
+    pub fn rhythm_score(&self) -> Option<f64> {
+        self.coefficient_of_variation()
+            .map(|coefficient_of_variation| 100.0 / (1.0 + coefficient_of_variation))
+    }
+
+    fn mean_micros(&self) -> f64 {
+        self.sum_micros as f64 / self.overall.count() as f64
+    }
+
+    fn insert(&mut self, key_stroke: char, interval: Duration) {
+        self.overall.insert(interval);
+        self.per_key.entry(key_stroke).or_default().insert(interval);
+
+        let micros = interval.as_micros();
+        self.sum_micros += micros;
+        self.square_sum_micros += micros * micros;
+        self.longest_pause = self.longest_pause.max(interval);
+    }
+
+    pub(crate) fn accumulate(&mut self, other: &Self) {
+        self.sum_micros += other.sum_micros;
+        self.square_sum_micros += other.square_sum_micros;
+        self.longest_pause = self.longest_pause.max(other.longest_pause);
+        self.overall.merge(&other.overall);
+
+        other.per_key.iter().for_each(|(key_stroke, sketch)| {
+            self.per_key.entry(*key_stroke).or_default().merge(sketch);
+        });
+    }
+}
+
+/// 連続する正しいキーストローク間の時間の分布を構築する
+/// 最初のキーストロークは開始からの時間であり打鍵間隔ではないので含めない
+pub(crate) fn construct_inter_key_interval_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+) -> InterKeyIntervalStatistics {
+    let mut statistics = InterKeyIntervalStatistics::default();
+    let mut previous_key_stroke_time: Option<Duration> = None;
+
+    confirmed_chunks
+        .iter()
+        .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes())
+        .chain(
+            inflight_chunk
+                .into_iter()
+                .flat_map(|inflight_chunk| inflight_chunk.actual_key_strokes()),
+        )
+        .filter(|actual_key_stroke| actual_key_stroke.is_correct())
+        .for_each(|actual_key_stroke| {
+            let elapsed_time = *actual_key_stroke.elapsed_time();
+
+            if let Some(previous_key_stroke_time) = previous_key_stroke_time {
+                let interval = elapsed_time.saturating_sub(previous_key_stroke_time);
+
+                statistics.insert(char::from(actual_key_stroke.key_stroke().clone()), interval);
+            }
+
+            previous_key_stroke_time.replace(elapsed_time);
+        });
+
+    statistics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn estimate_quantile_within_relative_error() {
+        let mut sketch = QuantileSketch::default();
+        assert_eq!(sketch.median(), None);
+
+        (1..=100).for_each(|millis| sketch.insert(Duration::from_millis(millis)));
+
+        let assert_close = |estimated: Duration, expected: Duration| {
+            let relative_error =
+                (estimated.as_secs_f64() - expected.as_secs_f64()).abs() / expected.as_secs_f64();
+            assert!(relative_error <= 0.01, "{:?} {:?}", estimated, expected);
+        };
+
+        assert_close(sketch.median().unwrap(), Duration::from_millis(50));
+        assert_close(sketch.p95().unwrap(), Duration::from_millis(95));
+        assert_eq!(sketch.quantile(1.5), None);
+
+        let mut merged = sketch.clone();
+        merged.merge(&sketch);
+        assert_eq!(merged.count(), 200);
+        assert_close(merged.median().unwrap(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn construct_inter_key_interval_statistics_1() {
+        let confirmed_chunks = vec![ConfirmedChunk::new(
+            gen_chunk!(
+                "きょ",
+                vec![gen_candidate!(["kyo"])],
+                gen_candidate!(["kyo"])
+            ),
+            vec![
+                ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(2, 0), 'y'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(3, 0), 'p'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(5, 0), 'o'.try_into().unwrap(), true),
+            ],
+        )];
+
+        let statistics = construct_inter_key_interval_statistics(&confirmed_chunks, None);
+
+        assert_eq!(statistics.overall().count(), 2);
+        assert!(!statistics.per_key().contains_key(&'k'));
+        // ミスタイプに費やした時間も含まれる
+        let o_interval = statistics.per_key()[&'o'].median().unwrap();
+        assert!(
+            o_interval > Duration::from_millis(2980) && o_interval < Duration::from_millis(3020)
+        );
+
+        // 打鍵間隔は1秒と3秒なので平均は2秒で標準偏差は1秒となる
+        assert_eq!(statistics.mean(), Some(Duration::from_secs(2)));
+        assert_eq!(
+            statistics.standard_deviation(),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(statistics.coefficient_of_variation(), Some(0.5));
+        assert_eq!(statistics.longest_pause(), Duration::from_secs(3));
+        assert_eq!(statistics.rhythm_score(), Some(100.0 / 1.5));
+        assert_eq!(InterKeyIntervalStatistics::default().rhythm_score(), None);
+    }
+}
@@ -1,14 +1,18 @@
 use crate::chunk::KeyStrokeElementCount;
 use crate::utility::convert_by_weighted_count;
 
+// 統計を無効にした場合には理想的なキーストロークのカーソル位置の変換にのみ用いる
+#[cfg_attr(not(feature = "statistics"), allow(dead_code))]
 pub(crate) enum BaseTarget {
+    Vocabulary,
     Chunk,
     Spell,
     IdealKeyStroke,
     KeyStroke,
 }
 
-/// チャンク・チャンク内の綴り・理想的なキーストローク系列・キーストローク系列間の位置の変換を行う
+/// 語彙・チャンク・チャンク内の綴り・理想的なキーストローク系列・キーストローク系列間の位置の変換を行う
+/// 語彙末は必ずチャンク末でもあるので語彙を基準とする場合はチャンクを基準とする場合と同じである
 pub(crate) struct MultiTargetDeltaConverter {
     spell: usize,
     // Vecになっているのは綴り要素のそれぞれに対応させるため
@@ -17,6 +21,7 @@ pub(crate) struct MultiTargetDeltaConverter {
     base: BaseTarget,
 }
 
+#[cfg_attr(not(feature = "statistics"), allow(dead_code))]
 impl MultiTargetDeltaConverter {
     pub(crate) fn new(
         spell: usize,
@@ -45,7 +50,9 @@ impl MultiTargetDeltaConverter {
     /// 基準の位置は綴りでいうとどこか
     pub(crate) fn spell_delta(&self, base_deltas: &[usize]) -> Vec<usize> {
         match self.base {
-            BaseTarget::Chunk => base_deltas.iter().map(|_| self.spell).collect(),
+            BaseTarget::Vocabulary | BaseTarget::Chunk => {
+                base_deltas.iter().map(|_| self.spell).collect()
+            }
             BaseTarget::Spell => base_deltas.to_vec(),
             BaseTarget::IdealKeyStroke => base_deltas
                 .iter()
@@ -70,7 +77,7 @@ impl MultiTargetDeltaConverter {
     /// 基準の位置は理想的なキーストローク系列でいうとどこか
     pub(crate) fn ideal_key_stroke_delta(&self, base_deltas: &[usize]) -> Vec<usize> {
         match self.base {
-            BaseTarget::Chunk => base_deltas
+            BaseTarget::Vocabulary | BaseTarget::Chunk => base_deltas
                 .iter()
                 .map(|_| self.ideal_key_stroke.whole_count())
                 .collect(),
@@ -99,7 +106,7 @@ impl MultiTargetDeltaConverter {
     /// 基準の位置はキーストローク系列でいうとどこか
     pub(crate) fn key_stroke_delta(&self, base_deltas: &[usize]) -> Vec<usize> {
         match self.base {
-            BaseTarget::Chunk => base_deltas
+            BaseTarget::Vocabulary | BaseTarget::Chunk => base_deltas
                 .iter()
                 .map(|_| self.key_stroke.whole_count())
                 .collect(),
@@ -229,4 +236,19 @@ mod test {
             vec![1, 2, 3, 4, 5]
         );
     }
+
+    #[test]
+    fn multi_target_delta_converter_5() {
+        let m = MultiTargetDeltaConverter::new(
+            2,
+            KeyStrokeElementCount::new(&[3]),
+            KeyStrokeElementCount::new(&[2, 3]),
+            BaseTarget::Vocabulary,
+        );
+
+        assert_eq!(m.chunk_delta(&[1]), vec![1]);
+        assert_eq!(m.spell_delta(&[1]), vec![2]);
+        assert_eq!(m.ideal_key_stroke_delta(&[1]), vec![3]);
+        assert_eq!(m.key_stroke_delta(&[1]), vec![5]);
+    }
 }
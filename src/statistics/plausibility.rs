@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Criteria of [`HumanlikeValidation`] to judge key stroke timing as physically implausible.
+///
+/// Defaults are loose enough for fast human typists, so flagged sessions are worth reviewing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HumanlikeCriteria {
+    min_interval: Duration,
+    sustained_run_length: usize,
+    periodic_tolerance: Duration,
+    periodic_run_length: usize,
+    simultaneous_threshold: Duration,
+}
+
+impl HumanlikeCriteria {
+    /// Construct criteria with default thresholds.
+    pub fn new() -> Self {
+        Self {
+            min_interval: Duration::from_millis(30),
+            sustained_run_length: 10,
+            periodic_tolerance: Duration::from_millis(1),
+            periodic_run_length: 20,
+            simultaneous_threshold: Duration::from_millis(1),
+        }
+    }
+
+    /// Construct criteria which regards intervals shorter than `min_interval` as too fast.
+    ///
+    /// Default is 30ms.
+    pub fn with_min_interval(self, min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            ..self
+        }
+    }
+
+    /// Construct criteria which flags `sustained_run_length` or more consecutive key strokes
+    /// typed too fast.
+    ///
+    /// Default is 10.
+    pub fn with_sustained_run_length(self, sustained_run_length: usize) -> Self {
+        Self {
+            sustained_run_length,
+            ..self
+        }
+    }
+
+    /// Construct criteria which regards intervals differing within `periodic_tolerance` as
+    /// the same period.
+    ///
+    /// Default is 1ms.
+    pub fn with_periodic_tolerance(self, periodic_tolerance: Duration) -> Self {
+        Self {
+            periodic_tolerance,
+            ..self
+        }
+    }
+
+    /// Construct criteria which flags `periodic_run_length` or more consecutive key strokes
+    /// typed in the same period.
+    ///
+    /// This is also the minimum count of key strokes to judge zero variance.
+    /// Default is 20.
+    pub fn with_periodic_run_length(self, periodic_run_length: usize) -> Self {
+        Self {
+            periodic_run_length,
+            ..self
+        }
+    }
+
+    /// Construct criteria which regards different keys typed within `simultaneous_threshold` as
+    /// impossible simultaneous key strokes.
+    ///
+    /// Default is 1ms.
+    pub fn with_simultaneous_threshold(self, simultaneous_threshold: Duration) -> Self {
+        Self {
+            simultaneous_threshold,
+            ..self
+        }
+    }
+}
+
+impl Default for HumanlikeCriteria {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A physically implausible pattern of key stroke timing.
+///
+/// Positions are indices of key strokes including wrong key strokes in typed order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ImplausiblePattern {
+    /// `count` key strokes from `start` are typed with intervals shorter than minimum interval.
+    SustainedFastStrokes { start: usize, count: usize },
+    /// All intervals between key strokes are exactly the same.
+    ZeroVariance,
+    /// `count` key strokes from `start` are typed in almost the same period.
+    PeriodicStrokes { start: usize, count: usize },
+    /// Key stroke at `position` is typed at the same time as the previous different key.
+    SimultaneousKeys { position: usize },
+}
+
+/// A result of validating whether key stroke timing is humanlike.
+///
+/// This is a heuristic for online leaderboards to find sessions typed by programs, so a
+/// flagged session is not necessarily cheated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HumanlikeValidation {
+    patterns: Vec<ImplausiblePattern>,
+}
+
+impl HumanlikeValidation {
+    /// Get detected implausible patterns in order of position.
+    ///
+    /// [`ZeroVariance`](ImplausiblePattern::ZeroVariance) is listed first because it is about
+    /// the whole session.
+    pub fn patterns(&self) -> &[ImplausiblePattern] {
+        &self.patterns
+    }
+
+    /// Get whether no implausible pattern is detected.
+    pub fn is_humanlike(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+/// 打った順のキーストロークの時刻とキーから不自然なパターンを検出する
+pub(crate) fn validate_humanlike(
+    key_strokes: &[(Duration, char)],
+    criteria: &HumanlikeCriteria,
+) -> HumanlikeValidation {
+    // i番目の間隔はi番目とi+1番目のキーストロークの間隔である
+    let intervals: Vec<Duration> = key_strokes
+        .windows(2)
+        .map(|window| window[1].0.saturating_sub(window[0].0))
+        .collect();
+
+    let mut patterns = vec![];
+
+    // 全体で同じ間隔であれば周期的な区間は全体となるので重ねて報告しない
+    let is_zero_variance = key_strokes.len() >= criteria.periodic_run_length.max(2)
+        && intervals.iter().all(|interval| *interval == intervals[0]);
+    if is_zero_variance {
+        patterns.push(ImplausiblePattern::ZeroVariance);
+    }
+
+    let mut positioned_patterns: Vec<(usize, ImplausiblePattern)> = vec![];
+
+    find_runs(&intervals, criteria.sustained_run_length, |run| {
+        run.iter().all(|interval| *interval < criteria.min_interval)
+    })
+    .into_iter()
+    .for_each(|(start, count)| {
+        positioned_patterns.push((
+            start,
+            ImplausiblePattern::SustainedFastStrokes { start, count },
+        ))
+    });
+
+    if !is_zero_variance {
+        find_runs(&intervals, criteria.periodic_run_length, |run| {
+            let max = run.iter().max().unwrap();
+            let min = run.iter().min().unwrap();
+            *max - *min <= criteria.periodic_tolerance
+        })
+        .into_iter()
+        .for_each(|(start, count)| {
+            positioned_patterns.push((start, ImplausiblePattern::PeriodicStrokes { start, count }))
+        });
+    }
+
+    key_strokes
+        .windows(2)
+        .enumerate()
+        .filter(|(i, window)| {
+            window[0].1 != window[1].1 && intervals[*i] < criteria.simultaneous_threshold
+        })
+        .for_each(|(i, _)| {
+            positioned_patterns.push((
+                i + 1,
+                ImplausiblePattern::SimultaneousKeys { position: i + 1 },
+            ))
+        });
+
+    // 同じ位置では区間のパターンを先にする
+    positioned_patterns.sort_by_key(|(position, _)| *position);
+    patterns.extend(positioned_patterns.into_iter().map(|(_, pattern)| pattern));
+
+    HumanlikeValidation { patterns }
+}
+
+// 条件を満たし続ける間隔の連続区間のうちキーストローク数がmin_key_stroke_count以上のものを
+// (先頭のキーストロークの位置, キーストローク数)として返す
+fn find_runs(
+    intervals: &[Duration],
+    min_key_stroke_count: usize,
+    is_satisfied: impl Fn(&[Duration]) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut runs = vec![];
+    let mut run_start = 0;
+
+    for i in 0..=intervals.len() {
+        if i < intervals.len() && is_satisfied(&intervals[run_start..=i]) {
+            continue;
+        }
+
+        let key_stroke_count = i - run_start + 1;
+        if i > run_start && key_stroke_count >= min_key_stroke_count {
+            runs.push((run_start, key_stroke_count));
+        }
+
+        // 条件を満たさなくなった間隔が単独で条件を満たすならそこから次の区間を始める
+        run_start = if i < intervals.len() && is_satisfied(&intervals[i..=i]) {
+            i
+        } else {
+            i + 1
+        };
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key_strokes(intervals_in_millis: &[u64]) -> Vec<(Duration, char)> {
+        let mut elapsed_time = Duration::from_secs(1);
+        let mut key_strokes = vec![(elapsed_time, 'a')];
+
+        intervals_in_millis
+            .iter()
+            .enumerate()
+            .for_each(|(i, interval)| {
+                elapsed_time += Duration::from_millis(*interval);
+                key_strokes.push((elapsed_time, if i % 2 == 0 { 'b' } else { 'a' }));
+            });
+
+        key_strokes
+    }
+
+    #[test]
+    fn validate_humanlike_1() {
+        let criteria = HumanlikeCriteria::new()
+            .with_sustained_run_length(4)
+            .with_periodic_run_length(5);
+
+        // 間隔がばらついていれば人間らしい
+        let validation = validate_humanlike(&key_strokes(&[120, 80, 200, 95, 150, 60]), &criteria);
+        assert!(validation.is_humanlike());
+
+        let validation = validate_humanlike(&key_strokes(&[100, 100, 100, 100, 100]), &criteria);
+        assert_eq!(validation.patterns(), [ImplausiblePattern::ZeroVariance]);
+
+        let validation = validate_humanlike(
+            &key_strokes(&[150, 10, 12, 11, 300, 80, 80, 81, 80, 80, 0]),
+            &criteria,
+        );
+        assert_eq!(
+            validation.patterns(),
+            [
+                ImplausiblePattern::SustainedFastStrokes { start: 1, count: 4 },
+                ImplausiblePattern::PeriodicStrokes { start: 5, count: 6 },
+                ImplausiblePattern::SimultaneousKeys { position: 11 },
+            ]
+        );
+        assert!(!validation.is_humanlike());
+    }
+}
@@ -0,0 +1,299 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::display_info::per_minute;
+use crate::statistics::result::{TypingResultStatistics, TypingResultStatisticsTarget};
+use crate::statistics::skill::SkillStatistics;
+
+/// A calendar date used to group sessions in [`TypingProfile`].
+///
+/// This crate does not read system time, so date of each session must be given by caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "ProfileDateData", into = "ProfileDateData")]
+pub struct ProfileDate {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+// デシリアライズ時にも日付として正しいかを検証するための中間表現
+#[derive(Serialize, Deserialize)]
+struct ProfileDateData {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl TryFrom<ProfileDateData> for ProfileDate {
+    type Error = &'static str;
+
+    fn try_from(data: ProfileDateData) -> Result<Self, Self::Error> {
+        Self::new(data.year, data.month, data.day).ok_or("invalid date")
+    }
+}
+
+impl From<ProfileDate> for ProfileDateData {
+    fn from(date: ProfileDate) -> Self {
+        Self {
+            year: date.year,
+            month: date.month,
+            day: date.day,
+        }
+    }
+}
+
+impl ProfileDate {
+    /// Construct a date in proleptic Gregorian calendar.
+    ///
+    /// If `month` is not in `1..=12` or `day` does not exist in the month of the year (ex.
+    /// February 29th of non-leap year), this function returns `None`.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day))
+            .then_some(Self { year, month, day })
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+// 月の日数を求める
+fn days_in_month(year: u16, month: u8) -> u8 {
+    let is_leap_year =
+        (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+
+    match month {
+        2 if is_leap_year => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A long-term profile of a player accumulated over many typing sessions.
+///
+/// Results of each session are recorded by [`record`](Self::record()).
+/// Profile can be persisted by serde and restored to keep recording.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TypingProfile {
+    session_count: usize,
+    key_stroke: TypingResultStatisticsTarget,
+    total_time: Duration,
+    skill_statistics: SkillStatistics,
+    daily_statistics: Vec<DailyTypingStatistics>,
+}
+
+impl TypingProfile {
+    /// Construct an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record result of a session typed on `date`.
+    ///
+    /// `skill_statistics` is the one constructed by
+    /// [`construct_skill_statistics`](crate::TypingEngine::construct_skill_statistics()) for the
+    /// same session.
+    pub fn record(
+        &mut self,
+        date: ProfileDate,
+        result: &TypingResultStatistics,
+        skill_statistics: &SkillStatistics,
+    ) {
+        self.session_count += 1;
+        self.key_stroke.accumulate(result.key_stroke());
+        self.total_time += result.total_time();
+        self.skill_statistics.merge(skill_statistics);
+
+        // 日付順に並べておくので挿入位置は二分探索で求める
+        let daily_statistics = match self
+            .daily_statistics
+            .binary_search_by_key(&date, |daily_statistics| daily_statistics.date)
+        {
+            Ok(i) => &mut self.daily_statistics[i],
+            Err(i) => {
+                self.daily_statistics
+                    .insert(i, DailyTypingStatistics::new(date));
+                &mut self.daily_statistics[i]
+            }
+        };
+
+        daily_statistics.session_count += 1;
+        daily_statistics.key_stroke.accumulate(result.key_stroke());
+        daily_statistics.total_time += result.total_time();
+    }
+
+    /// Get count of recorded sessions.
+    pub fn session_count(&self) -> usize {
+        self.session_count
+    }
+
+    /// Get summary of key strokes of all sessions.
+    pub fn key_stroke(&self) -> &TypingResultStatisticsTarget {
+        &self.key_stroke
+    }
+
+    /// Get sum of total time of all sessions.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Get miss statistics of each key and key bigram of all sessions.
+    pub fn skill_statistics(&self) -> &SkillStatistics {
+        &self.skill_statistics
+    }
+
+    /// Get statistics of each date on which sessions are recorded in ascending order of date.
+    ///
+    /// This is useful to show trend of speed.
+    pub fn speed_trend(&self) -> &[DailyTypingStatistics] {
+        &self.daily_statistics
+    }
+}
+
+/// Statistics of sessions typed on a date.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DailyTypingStatistics {
+    date: ProfileDate,
+    session_count: usize,
+    key_stroke: TypingResultStatisticsTarget,
+    total_time: Duration,
+}
+
+impl DailyTypingStatistics {
+    fn new(date: ProfileDate) -> Self {
+        Self {
+            date,
+            session_count: 0,
+            key_stroke: TypingResultStatisticsTarget::default(),
+            total_time: Duration::ZERO,
+        }
+    }
+
+    /// Get the date.
+    pub fn date(&self) -> ProfileDate {
+        self.date
+    }
+
+    /// Get count of sessions on the date.
+    pub fn session_count(&self) -> usize {
+        self.session_count
+    }
+
+    /// Get summary of key strokes on the date.
+    pub fn key_stroke(&self) -> &TypingResultStatisticsTarget {
+        &self.key_stroke
+    }
+
+    /// Get sum of total time of sessions on the date.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Get key strokes per minute on the date.
+    pub fn kpm(&self) -> f64 {
+        per_minute(self.key_stroke.whole_count(), self.total_time)
+    }
+
+    /// Get words per minute on the date.
+    pub fn wpm(&self) -> f64 {
+        self.kpm() / 5.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::chunk::confirmed::ConfirmedChunk;
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::statistics::result::construct_result;
+    use crate::statistics::skill::construct_skill_statistics;
+    use crate::typing_engine::TypingEndReason;
+    use crate::{gen_candidate, gen_chunk, LapRequest};
+
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn record_sessions_into_profile() {
+        let confirmed_chunks = vec![ConfirmedChunk::new(
+            gen_chunk!("か", vec![gen_candidate!(["ka"])], gen_candidate!(["ka"])),
+            vec![
+                ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(2, 0), 'u'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(3, 0), 'a'.try_into().unwrap(), true),
+            ],
+        )];
+        let result = construct_result(
+            &confirmed_chunks,
+            None,
+            LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap()),
+            TypingEndReason::Completed,
+            None,
+        );
+        let skill_statistics = construct_skill_statistics(&confirmed_chunks, None);
+
+        let first_date = ProfileDate::new(2024, 1, 2).unwrap();
+        let second_date = ProfileDate::new(2024, 1, 1).unwrap();
+
+        let mut profile = TypingProfile::new();
+        profile.record(first_date, &result, &skill_statistics);
+        profile.record(second_date, &result, &skill_statistics);
+        profile.record(first_date, &result, &skill_statistics);
+
+        assert_eq!(profile.session_count(), 3);
+        assert_eq!(profile.key_stroke().whole_count(), 6);
+        assert_eq!(profile.key_stroke().missed_count(), 3);
+        assert_eq!(profile.total_time(), Duration::new(9, 0));
+        assert_eq!(
+            profile.skill_statistics().key_strokes()[0].key_stroke(),
+            'a'
+        );
+        assert_eq!(profile.skill_statistics().key_strokes()[0].wrong_count(), 3);
+
+        // 記録した順ではなく日付順に並ぶ
+        let trend = profile.speed_trend();
+        assert_eq!(trend.len(), 2);
+        assert_eq!(trend[0].date(), second_date);
+        assert_eq!(trend[0].session_count(), 1);
+        assert_eq!(trend[1].date(), first_date);
+        assert_eq!(trend[1].session_count(), 2);
+        assert_eq!(trend[1].kpm(), 40.0);
+
+        assert_eq!(ProfileDate::new(2024, 13, 1), None);
+    }
+
+    #[test]
+    fn profile_date_validates_day_of_month() {
+        assert!(ProfileDate::new(2024, 2, 29).is_some());
+        assert!(ProfileDate::new(2000, 2, 29).is_some());
+        assert_eq!(ProfileDate::new(2023, 2, 29), None);
+        assert_eq!(ProfileDate::new(1900, 2, 29), None);
+        assert_eq!(ProfileDate::new(2024, 4, 31), None);
+        assert!(ProfileDate::new(2024, 12, 31).is_some());
+        assert_eq!(ProfileDate::new(2024, 1, 0), None);
+
+        // デシリアライズ時にも同じ検証を行う
+        assert!(ProfileDate::try_from(ProfileDateData {
+            year: 2023,
+            month: 2,
+            day: 29,
+        })
+        .is_err());
+        assert_eq!(
+            ProfileDate::try_from(ProfileDateData::from(
+                ProfileDate::new(2024, 2, 29).unwrap()
+            )),
+            ProfileDate::new(2024, 2, 29).ok_or("invalid date")
+        );
+    }
+}
@@ -4,7 +4,25 @@ use serde::{Deserialize, Serialize};
 
 use crate::chunk::confirmed::ConfirmedChunk;
 use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+use crate::chunk::typed::TypedChunk;
+use crate::key_stroke::{ActualKeyStroke, KeyStrokeChar};
+#[cfg(feature = "analysis")]
+use crate::statistics::context::{merge_contextual_miss_statistics, ContextualMissStatistics};
+#[cfg(feature = "analysis")]
+use crate::statistics::finger::FingerStatistics;
+#[cfg(feature = "analysis")]
+use crate::statistics::interval::{
+    construct_inter_key_interval_statistics, InterKeyIntervalStatistics,
+};
+#[cfg(feature = "analysis")]
+use crate::statistics::plausibility::{validate_humanlike, HumanlikeCriteria, HumanlikeValidation};
+#[cfg(feature = "analysis")]
+use crate::statistics::skill::SkillStatistics;
+#[cfg(feature = "analysis")]
+use crate::statistics::typo::TypoAnalysis;
 use crate::statistics::OnTypingStatisticsManager;
+use crate::typing_engine::TypingEndReason;
+use crate::vocabulary::VocabularyInfo;
 use crate::LapRequest;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -12,6 +30,25 @@ pub struct TypingResultStatistics {
     key_stroke: TypingResultStatisticsTarget,
     ideal_key_stroke: TypingResultStatisticsTarget,
     total_time: Duration,
+    idle_time: Duration,
+    end_reason: TypingEndReason,
+    correction_count: usize,
+    premature_miss_count: usize,
+    assisted_key_stroke_count: usize,
+    skipped_vocabulary_count: usize,
+    shift_key_stroke_count: usize,
+    max_combo: usize,
+    #[cfg(feature = "analysis")]
+    inter_key_interval: InterKeyIntervalStatistics,
+    vocabularies: Vec<VocabularyResultStatistics>,
+    spell_mistakes: Vec<SpellMistakeStatistics>,
+    spell_candidates: Vec<SpellCandidateStatistics>,
+    spell_hints: Vec<SpellHintStatistics>,
+    #[cfg(feature = "analysis")]
+    typo_analysis: TypoAnalysis,
+    #[cfg(feature = "analysis")]
+    finger_statistics: FingerStatistics,
+    key_stroke_log: Vec<ActualKeyStroke>,
 }
 
 impl TypingResultStatistics {
@@ -26,9 +63,590 @@ impl TypingResultStatistics {
     pub fn total_time(&self) -> Duration {
         self.total_time
     }
+
+    /// Get time regarded as idle in [`total_time`](Self::total_time()).
+    ///
+    /// When no key stroke arrives within
+    /// [idle threshold](crate::TypingEngine::set_idle_threshold()), the time beyond the
+    /// threshold is idle time.
+    /// This is always zero unless idle threshold is configured.
+    pub fn idle_time(&self) -> Duration {
+        self.idle_time
+    }
+
+    /// Get [`total_time`](Self::total_time()) excluding [`idle_time`](Self::idle_time()).
+    ///
+    /// This is useful to calculate speed which is not affected by stepping away.
+    pub fn active_time(&self) -> Duration {
+        self.total_time.saturating_sub(self.idle_time)
+    }
+
+    /// Get the reason why typing is finished.
+    pub fn end_reason(&self) -> &TypingEndReason {
+        &self.end_reason
+    }
+
+    /// Get count of wrong key strokes deleted by [`backspace`](crate::TypingEngine::backspace()).
+    ///
+    /// This is always 0 unless strict mode is enabled.
+    pub fn correction_count(&self) -> usize {
+        self.correction_count
+    }
+
+    /// Get count of wrong key strokes which are head key strokes of the next chunk.
+    ///
+    /// Such wrong key strokes mean that player gets ahead of themselves.
+    pub fn premature_miss_count(&self) -> usize {
+        self.premature_miss_count
+    }
+
+    /// Get ratio of [`premature_miss_count`](Self::premature_miss_count()) to all wrong key
+    /// strokes.
+    ///
+    /// If there is no wrong key stroke, this function returns 0.
+    pub fn premature_miss_ratio(&self) -> f64 {
+        premature_miss_ratio(self.premature_miss_count, self.key_stroke.missed_count)
+    }
+
+    /// Get count of key strokes injected by
+    /// [`auto_complete_current_chunk`](crate::TypingEngine::auto_complete_current_chunk()).
+    ///
+    /// These key strokes are also counted in [`key_stroke`](Self::key_stroke()) as correct key
+    /// strokes, so subtract this to evaluate key strokes actually typed by player.
+    pub fn assisted_key_stroke_count(&self) -> usize {
+        self.assisted_key_stroke_count
+    }
+
+    /// Get count of vocabularies skipped by
+    /// [`skip_current_vocabulary`](crate::TypingEngine::skip_current_vocabulary()).
+    pub fn skipped_vocabulary_count(&self) -> usize {
+        self.skipped_vocabulary_count
+    }
+
+    /// Get count of Shift key strokes needed for correct key strokes.
+    ///
+    /// This is always 0 unless [Shift key mode](crate::TypingEngine::set_shift_key_mode()) is
+    /// enabled.
+    pub fn shift_key_stroke_count(&self) -> usize {
+        self.shift_key_stroke_count
+    }
+
+    /// Get count of physical key strokes which includes Shift key strokes.
+    ///
+    /// This is the sum of whole count of [`key_stroke`](Self::key_stroke()) and
+    /// [`shift_key_stroke_count`](Self::shift_key_stroke_count()), so this is useful to calculate
+    /// KPM which reflects physical effort.
+    pub fn physical_key_stroke_count(&self) -> usize {
+        self.key_stroke.whole_count + self.shift_key_stroke_count
+    }
+
+    /// Get max count of correct key strokes typed in a row without wrong key stroke.
+    pub fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get distribution of intervals between consecutive correct key strokes.
+    ///
+    /// This is useful to know median and 95th percentile of intervals overall and per key, and
+    /// rhythm metrics such as standard deviation and longest pause.
+    pub fn inter_key_interval(&self) -> &InterKeyIntervalStatistics {
+        &self.inter_key_interval
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get wrong key strokes classified into categories such as adjacent key slips.
+    pub fn typo_analysis(&self) -> &TypoAnalysis {
+        &self.typo_analysis
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get statistics of key strokes aggregated by finger and hand.
+    ///
+    /// Fingers are assigned by [`FingerMap`](crate::FingerMap) configured to the engine.
+    pub fn finger_statistics(&self) -> &FingerStatistics {
+        &self.finger_statistics
+    }
+
+    /// Get statistics of each vocabulary typed completely in order of query.
+    ///
+    /// Vocabulary separators are also listed as vocabularies.
+    /// This is useful to show slowest vocabularies.
+    pub fn vocabularies(&self) -> &[VocabularyResultStatistics] {
+        &self.vocabularies
+    }
+
+    /// Get wrong key strokes of each spell typed with wrong key strokes in order of query.
+    ///
+    /// Spells are split in the same way as chunks of [`DisplayInfo`](crate::DisplayInfo).
+    /// This is useful to review how each spell was actually typed.
+    pub fn spell_mistakes(&self) -> &[SpellMistakeStatistics] {
+        &self.spell_mistakes
+    }
+
+    /// Get key strokes actually typed and ideal key strokes of each spell typed completely in
+    /// order of query.
+    ///
+    /// Spells are split in the same way as [`spell_mistakes`](Self::spell_mistakes()).
+    /// This is useful to show how many key strokes were typed more than ideal.
+    pub fn spell_candidates(&self) -> &[SpellCandidateStatistics] {
+        &self.spell_candidates
+    }
+
+    /// Get usage of [`hint`](crate::TypingEngine::hint()) of each spell typed with hints in order
+    /// of query.
+    ///
+    /// Spells are split in the same way as [`spell_mistakes`](Self::spell_mistakes()).
+    pub fn spell_hints(&self) -> &[SpellHintStatistics] {
+        &self.spell_hints
+    }
+
+    /// Get total count of [`hint`](crate::TypingEngine::hint()) used.
+    pub fn hint_count(&self) -> usize {
+        self.spell_hints
+            .iter()
+            .map(|spell_hint| spell_hint.hint_count)
+            .sum()
+    }
+
+    /// Get all key strokes actually typed including wrong key strokes in typed order.
+    ///
+    /// This is useful to replay or verify typing afterwards.
+    /// Key strokes deleted by [`backspace`](crate::TypingEngine::backspace()) are not included.
+    pub fn key_stroke_log(&self) -> &[ActualKeyStroke] {
+        &self.key_stroke_log
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Validate whether timing of [key stroke log](Self::key_stroke_log()) is humanlike with
+    /// default criteria.
+    ///
+    /// See [`HumanlikeCriteria`] for patterns regarded as physically implausible.
+    pub fn validate_humanlike(&self) -> HumanlikeValidation {
+        self.validate_humanlike_with(&HumanlikeCriteria::default())
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Validate whether timing of [key stroke log](Self::key_stroke_log()) is humanlike with
+    /// `criteria`.
+    pub fn validate_humanlike_with(&self, criteria: &HumanlikeCriteria) -> HumanlikeValidation {
+        let key_strokes: Vec<(Duration, char)> = self
+            .key_stroke_log
+            .iter()
+            .map(|actual_key_stroke| {
+                (
+                    *actual_key_stroke.elapsed_time(),
+                    actual_key_stroke.key_stroke().clone().into(),
+                )
+            })
+            .collect();
+
+        validate_humanlike(&key_strokes, criteria)
+    }
+
+    /// Merge result of the following part of query typed in relay.
+    ///
+    /// The following part is handed off by [`hand_off`](crate::TypingEngine::hand_off()).
+    /// Counts and total time are summed up, and end reason is taken from `following`.
+    /// Elapsed time of key strokes in `following` is shifted by total time of this result.
+    pub fn merge(&self, following: &Self) -> Self {
+        let mut merged = self.clone();
+
+        merged.key_stroke.accumulate(&following.key_stroke);
+        merged
+            .ideal_key_stroke
+            .accumulate(&following.ideal_key_stroke);
+        merged.total_time += following.total_time;
+        merged.idle_time += following.idle_time;
+        merged.end_reason = following.end_reason.clone();
+        merged.correction_count += following.correction_count;
+        merged.premature_miss_count += following.premature_miss_count;
+        merged.assisted_key_stroke_count += following.assisted_key_stroke_count;
+        merged.skipped_vocabulary_count += following.skipped_vocabulary_count;
+        merged.shift_key_stroke_count += following.shift_key_stroke_count;
+        // 引き継ぐ際にコンボは途切れるものとする
+        merged.max_combo = merged.max_combo.max(following.max_combo);
+        #[cfg(feature = "analysis")]
+        merged
+            .inter_key_interval
+            .accumulate(&following.inter_key_interval);
+        merged
+            .vocabularies
+            .extend(following.vocabularies.iter().cloned());
+        merged
+            .spell_mistakes
+            .extend(following.spell_mistakes.iter().cloned());
+        merged
+            .spell_candidates
+            .extend(following.spell_candidates.iter().cloned());
+        merged
+            .spell_hints
+            .extend(following.spell_hints.iter().cloned());
+        #[cfg(feature = "analysis")]
+        merged.typo_analysis.accumulate(&following.typo_analysis);
+        #[cfg(feature = "analysis")]
+        merged
+            .finger_statistics
+            .accumulate(&following.finger_statistics);
+        // 後続の経過時間は後続の開始時点からなので先行の全体時間分ずらす
+        merged
+            .key_stroke_log
+            .extend(following.key_stroke_log.iter().map(|actual_key_stroke| {
+                ActualKeyStroke::new(
+                    self.total_time + *actual_key_stroke.elapsed_time(),
+                    actual_key_stroke.key_stroke().clone(),
+                    actual_key_stroke.is_correct(),
+                )
+            }));
+
+        merged
+    }
+
+    // 訂正はチャンクのキーストロークとしては記録されないので別途設定する
+    pub(crate) fn with_correction_count(mut self, correction_count: usize) -> Self {
+        self.correction_count = correction_count;
+        self
+    }
+
+    // 次のチャンクの先頭かどうかはキーストロークした時点でしか分からないので別途設定する
+    pub(crate) fn with_premature_miss_count(mut self, premature_miss_count: usize) -> Self {
+        self.premature_miss_count = premature_miss_count;
+        self
+    }
+
+    // 自動補完されたキーストロークかどうかはチャンクのキーストロークとしては記録されないので別途設定する
+    pub(crate) fn with_assisted_key_stroke_count(
+        mut self,
+        assisted_key_stroke_count: usize,
+    ) -> Self {
+        self.assisted_key_stroke_count = assisted_key_stroke_count;
+        self
+    }
+
+    // ヒントを使ったかどうかはチャンクには記録されないので別途設定する
+    pub(crate) fn with_spell_hints(mut self, spell_hints: Vec<SpellHintStatistics>) -> Self {
+        self.spell_hints = spell_hints;
+        self
+    }
+
+    // 語彙を飛ばしたかどうかはチャンクには記録されないので別途設定する
+    pub(crate) fn with_skipped_vocabulary_count(mut self, skipped_vocabulary_count: usize) -> Self {
+        self.skipped_vocabulary_count = skipped_vocabulary_count;
+        self
+    }
+
+    // 語彙の区切りはチャンクには記録されないので別途設定する
+    pub(crate) fn with_vocabularies(
+        mut self,
+        vocabularies: Vec<VocabularyResultStatistics>,
+    ) -> Self {
+        self.vocabularies = vocabularies;
+        self
+    }
+
+    // キーボード配列や入力方式はチャンクには記録されないので別途設定する
+    #[cfg(feature = "analysis")]
+    pub(crate) fn with_typo_analysis(mut self, typo_analysis: TypoAnalysis) -> Self {
+        self.typo_analysis = typo_analysis;
+        self
+    }
+
+    // 指の割り当てはチャンクには記録されないので別途設定する
+    #[cfg(feature = "analysis")]
+    pub(crate) fn with_finger_statistics(mut self, finger_statistics: FingerStatistics) -> Self {
+        self.finger_statistics = finger_statistics;
+        self
+    }
+
+    // シフトキーはキーストロークとして与えられないので別途設定する
+    pub(crate) fn with_shift_key_stroke_count(mut self, shift_key_stroke_count: usize) -> Self {
+        self.shift_key_stroke_count = shift_key_stroke_count;
+        self
+    }
+
+    // 放置とみなす閾値はチャンクには記録されないので別途設定する
+    pub(crate) fn with_idle_time(mut self, idle_time: Duration) -> Self {
+        self.idle_time = idle_time;
+        self
+    }
+}
+
+/// Statistics of a vocabulary in [`TypingResultStatistics`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VocabularyResultStatistics {
+    view: String,
+    spell: String,
+    key_stroke_count: usize,
+    wrong_key_stroke_count: usize,
+    duration: Duration,
+}
+
+impl VocabularyResultStatistics {
+    /// Get view of the vocabulary.
+    pub fn view(&self) -> &str {
+        &self.view
+    }
+
+    /// Get spell of the vocabulary.
+    pub fn spell(&self) -> &str {
+        &self.spell
+    }
+
+    /// Get count of correct key strokes to type the vocabulary.
+    pub fn key_stroke_count(&self) -> usize {
+        self.key_stroke_count
+    }
+
+    /// Get count of wrong key strokes while typing the vocabulary.
+    pub fn wrong_key_stroke_count(&self) -> usize {
+        self.wrong_key_stroke_count
+    }
+
+    /// Get duration to type the vocabulary.
+    ///
+    /// This is measured from the last key stroke of the previous vocabulary, or from start for
+    /// the first vocabulary.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Wrong key strokes of a spell in [`TypingResultStatistics`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpellMistakeStatistics {
+    spell: String,
+    key_stroke: String,
+    actual_key_strokes: Vec<KeyStrokeChar>,
+    wrong_key_strokes: Vec<KeyStrokeChar>,
+}
+
+impl SpellMistakeStatistics {
+    /// Get the spell.
+    pub fn spell(&self) -> &str {
+        &self.spell
+    }
+
+    /// Get key strokes with which the spell was finally typed.
+    pub fn key_stroke(&self) -> &str {
+        &self.key_stroke
+    }
+
+    /// Get all key strokes given while typing the spell in order including wrong key strokes.
+    pub fn actual_key_strokes(&self) -> &[KeyStrokeChar] {
+        &self.actual_key_strokes
+    }
+
+    /// Get wrong key strokes given while typing the spell in order.
+    pub fn wrong_key_strokes(&self) -> &[KeyStrokeChar] {
+        &self.wrong_key_strokes
+    }
+}
+
+/// Key strokes with which a spell was typed in [`TypingResultStatistics`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpellCandidateStatistics {
+    spell: String,
+    key_stroke: String,
+    ideal_key_stroke: String,
+}
+
+impl SpellCandidateStatistics {
+    /// Get the spell.
+    pub fn spell(&self) -> &str {
+        &self.spell
+    }
+
+    /// Get key strokes with which the spell was actually typed.
+    pub fn key_stroke(&self) -> &str {
+        &self.key_stroke
+    }
+
+    /// Get ideal key strokes of the spell.
+    pub fn ideal_key_stroke(&self) -> &str {
+        &self.ideal_key_stroke
+    }
+
+    /// Get whether the spell was typed with key strokes other than ideal key strokes.
+    pub fn is_different_from_ideal(&self) -> bool {
+        self.key_stroke != self.ideal_key_stroke
+    }
+
+    /// Get count of key strokes typed more than ideal key strokes.
+    ///
+    /// Key strokes of the spell can be fewer than ideal ones depending on key strokes of the
+    /// previous spell, and in that case this returns `0`.
+    pub fn extra_key_stroke_count(&self) -> usize {
+        self.key_stroke
+            .chars()
+            .count()
+            .saturating_sub(self.ideal_key_stroke.chars().count())
+    }
 }
 
+/// Usage of hints of a spell in [`TypingResultStatistics`].
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpellHintStatistics {
+    spell: String,
+    hint_count: usize,
+}
+
+impl SpellHintStatistics {
+    pub(crate) fn new(spell: String, hint_count: usize) -> Self {
+        Self { spell, hint_count }
+    }
+
+    /// Get the spell.
+    pub fn spell(&self) -> &str {
+        &self.spell
+    }
+
+    /// Get count of hints used while typing the spell.
+    pub fn hint_count(&self) -> usize {
+        self.hint_count
+    }
+}
+
+fn premature_miss_ratio(premature_miss_count: usize, missed_count: usize) -> f64 {
+    if missed_count == 0 {
+        0.0
+    } else {
+        premature_miss_count as f64 / missed_count as f64
+    }
+}
+
+/// Statistics accumulated over multiple rounds of typing.
+///
+/// Rounds are accumulated by [`reset_keeping_statistics`](crate::TypingEngine::reset_keeping_statistics()).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AccumulatedResultStatistics {
+    round_count: usize,
+    key_stroke: TypingResultStatisticsTarget,
+    ideal_key_stroke: TypingResultStatisticsTarget,
+    total_time: Duration,
+    idle_time: Duration,
+    correction_count: usize,
+    premature_miss_count: usize,
+    assisted_key_stroke_count: usize,
+    shift_key_stroke_count: usize,
+    #[cfg(feature = "analysis")]
+    inter_key_interval: InterKeyIntervalStatistics,
+    #[cfg(feature = "analysis")]
+    contextual_miss_statistics: Vec<ContextualMissStatistics>,
+    #[cfg(feature = "analysis")]
+    skill_statistics: SkillStatistics,
+}
+
+impl AccumulatedResultStatistics {
+    /// Get count of accumulated rounds.
+    pub fn round_count(&self) -> usize {
+        self.round_count
+    }
+
+    pub fn key_stroke(&self) -> &TypingResultStatisticsTarget {
+        &self.key_stroke
+    }
+
+    pub fn ideal_key_stroke(&self) -> &TypingResultStatisticsTarget {
+        &self.ideal_key_stroke
+    }
+
+    /// Get sum of total time of accumulated rounds.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Get sum of idle time of accumulated rounds.
+    pub fn idle_time(&self) -> Duration {
+        self.idle_time
+    }
+
+    /// Get sum of correction count of accumulated rounds.
+    pub fn correction_count(&self) -> usize {
+        self.correction_count
+    }
+
+    /// Get sum of premature miss count of accumulated rounds.
+    pub fn premature_miss_count(&self) -> usize {
+        self.premature_miss_count
+    }
+
+    /// Get ratio of premature misses to all wrong key strokes of accumulated rounds.
+    ///
+    /// If there is no wrong key stroke, this function returns 0.
+    pub fn premature_miss_ratio(&self) -> f64 {
+        premature_miss_ratio(self.premature_miss_count, self.key_stroke.missed_count)
+    }
+
+    /// Get sum of assisted key stroke count of accumulated rounds.
+    pub fn assisted_key_stroke_count(&self) -> usize {
+        self.assisted_key_stroke_count
+    }
+
+    /// Get sum of Shift key stroke count of accumulated rounds.
+    pub fn shift_key_stroke_count(&self) -> usize {
+        self.shift_key_stroke_count
+    }
+
+    /// Get sum of physical key stroke count of accumulated rounds.
+    pub fn physical_key_stroke_count(&self) -> usize {
+        self.key_stroke.whole_count + self.shift_key_stroke_count
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get distribution of intervals between consecutive correct key strokes of accumulated
+    /// rounds.
+    pub fn inter_key_interval(&self) -> &InterKeyIntervalStatistics {
+        &self.inter_key_interval
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get miss statistics conditioned on preceding spell accumulated over rounds.
+    ///
+    /// Contexts with more wrong key strokes come first, so leading entries are top problem
+    /// contexts.
+    pub fn contextual_miss_statistics(&self) -> &[ContextualMissStatistics] {
+        &self.contextual_miss_statistics
+    }
+
+    #[cfg(feature = "analysis")]
+    pub(crate) fn accumulate_contextual_miss_statistics(
+        &mut self,
+        contextual_miss_statistics: &[ContextualMissStatistics],
+    ) {
+        self.contextual_miss_statistics = merge_contextual_miss_statistics(
+            &self.contextual_miss_statistics,
+            contextual_miss_statistics,
+        );
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get miss statistics keyed by key stroke and key stroke bigram accumulated over rounds.
+    pub fn skill_statistics(&self) -> &SkillStatistics {
+        &self.skill_statistics
+    }
+
+    #[cfg(feature = "analysis")]
+    pub(crate) fn accumulate_skill_statistics(&mut self, skill_statistics: &SkillStatistics) {
+        self.skill_statistics.merge(skill_statistics);
+    }
+
+    pub(crate) fn accumulate(&mut self, result: &TypingResultStatistics) {
+        self.round_count += 1;
+        self.key_stroke.accumulate(&result.key_stroke);
+        self.ideal_key_stroke.accumulate(&result.ideal_key_stroke);
+        self.total_time += result.total_time;
+        self.idle_time += result.idle_time;
+        self.correction_count += result.correction_count;
+        self.premature_miss_count += result.premature_miss_count;
+        self.assisted_key_stroke_count += result.assisted_key_stroke_count;
+        self.shift_key_stroke_count += result.shift_key_stroke_count;
+        #[cfg(feature = "analysis")]
+        self.inter_key_interval
+            .accumulate(&result.inter_key_interval);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TypingResultStatisticsTarget {
     whole_count: usize,
     completely_correct_count: usize,
@@ -36,6 +654,20 @@ pub struct TypingResultStatisticsTarget {
 }
 
 impl TypingResultStatisticsTarget {
+    // テレメトリの復元に用いる
+    #[cfg(feature = "analysis")]
+    pub(crate) fn new(
+        whole_count: usize,
+        completely_correct_count: usize,
+        missed_count: usize,
+    ) -> Self {
+        Self {
+            whole_count,
+            completely_correct_count,
+            missed_count,
+        }
+    }
+
     pub fn whole_count(&self) -> usize {
         self.whole_count
     }
@@ -47,13 +679,170 @@ impl TypingResultStatisticsTarget {
     pub fn missed_count(&self) -> usize {
         self.missed_count
     }
+
+    pub(crate) fn accumulate(&mut self, other: &Self) {
+        self.whole_count += other.whole_count;
+        self.completely_correct_count += other.completely_correct_count;
+        self.missed_count += other.missed_count;
+    }
 }
 
+/// タイプし終えたチャンクを語彙ごとに集計する
+/// 途中までしかタイプしていない語彙は含めない
+pub(crate) fn construct_vocabulary_result_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+    vocabulary_infos: &[VocabularyInfo],
+) -> Vec<VocabularyResultStatistics> {
+    let mut vocabularies = vec![];
+    let mut chunk_head_index = 0;
+    let mut previous_end_time = Duration::ZERO;
+
+    for vocabulary_info in vocabulary_infos {
+        let chunk_tail_index = chunk_head_index + vocabulary_info.chunk_count().get();
+        if chunk_tail_index > confirmed_chunks.len() {
+            break;
+        }
+
+        let actual_key_strokes = confirmed_chunks[chunk_head_index..chunk_tail_index]
+            .iter()
+            .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes());
+
+        let (key_stroke_count, wrong_key_stroke_count) = actual_key_strokes.clone().fold(
+            (0, 0),
+            |(key_stroke_count, wrong_key_stroke_count), actual_key_stroke| {
+                if actual_key_stroke.is_correct() {
+                    (key_stroke_count + 1, wrong_key_stroke_count)
+                } else {
+                    (key_stroke_count, wrong_key_stroke_count + 1)
+                }
+            },
+        );
+
+        let end_time = actual_key_strokes
+            .last()
+            .map_or(previous_end_time, |actual_key_stroke| {
+                *actual_key_stroke.elapsed_time()
+            });
+
+        vocabularies.push(VocabularyResultStatistics {
+            view: vocabulary_info.view().to_string(),
+            spell: vocabulary_info.spell().to_string(),
+            key_stroke_count,
+            wrong_key_stroke_count,
+            duration: end_time.saturating_sub(previous_end_time),
+        });
+
+        chunk_head_index = chunk_tail_index;
+        previous_end_time = end_time;
+    }
+
+    vocabularies
+}
+
+/// ミスタイプしたチャンクごとに実際のキーストロークを保持する
+fn construct_spell_mistake_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+) -> Vec<SpellMistakeStatistics> {
+    confirmed_chunks
+        .iter()
+        .map(|confirmed_chunk| confirmed_chunk as &dyn ChunkHasActualKeyStrokes)
+        .chain(
+            inflight_chunk
+                .into_iter()
+                .map(|inflight_chunk| inflight_chunk as &dyn ChunkHasActualKeyStrokes),
+        )
+        .filter(|typed_chunk| {
+            typed_chunk
+                .actual_key_strokes()
+                .iter()
+                .any(|actual_key_stroke| !actual_key_stroke.is_correct())
+        })
+        .map(|typed_chunk| SpellMistakeStatistics {
+            spell: typed_chunk.as_ref().spell().as_ref().to_string(),
+            key_stroke: typed_chunk.effective_candidate().whole_key_stroke().into(),
+            actual_key_strokes: typed_chunk
+                .actual_key_strokes()
+                .iter()
+                .map(|actual_key_stroke| actual_key_stroke.key_stroke().clone())
+                .collect(),
+            wrong_key_strokes: typed_chunk
+                .actual_key_strokes()
+                .iter()
+                .filter(|actual_key_stroke| !actual_key_stroke.is_correct())
+                .map(|actual_key_stroke| actual_key_stroke.key_stroke().clone())
+                .collect(),
+        })
+        .collect()
+}
+
+/// 確定したチャンクごとに実際に打った候補と理想的な候補を保持する
+fn construct_spell_candidate_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+) -> Vec<SpellCandidateStatistics> {
+    confirmed_chunks
+        .iter()
+        .map(|confirmed_chunk| SpellCandidateStatistics {
+            spell: confirmed_chunk.as_ref().spell().as_ref().to_string(),
+            key_stroke: confirmed_chunk
+                .confirmed_candidate()
+                .whole_key_stroke()
+                .into(),
+            ideal_key_stroke: confirmed_chunk
+                .as_ref()
+                .ideal_key_stroke_candidate()
+                .as_ref()
+                .unwrap()
+                .whole_key_stroke()
+                .into(),
+        })
+        .collect()
+}
+
+/// 開始から合計時間までのキーストロークの間隔のうち閾値を超えた部分を放置した時間とする
+pub(crate) fn construct_idle_time(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+    total_time: Duration,
+    idle_threshold: Duration,
+) -> Duration {
+    let key_stroke_times = confirmed_chunks
+        .iter()
+        .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes())
+        .chain(inflight_chunk.iter().flat_map(|inflight_chunk| {
+            inflight_chunk
+                .actual_key_strokes()
+                .iter()
+                .chain(inflight_chunk.pending_key_strokes().iter())
+        }))
+        .map(|actual_key_stroke| (*actual_key_stroke.elapsed_time()).min(total_time));
+
+    let mut previous_time = Duration::ZERO;
+    let mut idle_time = Duration::ZERO;
+
+    key_stroke_times
+        .chain(std::iter::once(total_time))
+        .for_each(|time| {
+            idle_time += time
+                .saturating_sub(previous_time)
+                .saturating_sub(idle_threshold);
+            previous_time = previous_time.max(time);
+        });
+
+    idle_time
+}
+
+/// タイプし終えたチャンクと途中までタイプしたチャンクから結果を構築する
+/// 途中までタイプしたチャンクはタイプ途中で終了した場合にのみ渡される
+/// 終了時刻が明示的に与えられた場合にはそれを合計時間とする
 pub(crate) fn construct_result(
     confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
     lap_request: LapRequest,
+    end_reason: TypingEndReason,
+    finish_time: Option<Duration>,
 ) -> TypingResultStatistics {
-    assert!(!confirmed_chunks.is_empty());
+    assert!(!confirmed_chunks.is_empty() || inflight_chunk.is_some());
 
     let mut spell = String::new();
     let mut spell_head_position = 0;
@@ -166,30 +955,122 @@ pub(crate) fn construct_result(
                 .unwrap()
                 .construct_key_stroke_element_count(),
             confirmed_chunk.as_ref().spell().count(),
+            // 結果の統計では語彙ごとのラップを取らない
+            &[],
         );
     });
 
-    let total_time = *(confirmed_chunks
-        .last()
-        .unwrap()
-        .actual_key_strokes()
-        .last()
-        .unwrap()
-        .elapsed_time());
+    // 途中までタイプしたチャンクはキーストロークのみ統計に含めチャンクは打ち終えていないものとする
+    if let Some(inflight_chunk) = inflight_chunk {
+        let spell_count = inflight_chunk.effective_spell_count();
+
+        on_typing_stat_manager.set_this_candidate_key_stroke_count(
+            inflight_chunk
+                .effective_candidate()
+                .whole_key_stroke()
+                .chars()
+                .count(),
+            inflight_chunk
+                .as_ref()
+                .ideal_key_stroke_candidate()
+                .as_ref()
+                .unwrap()
+                .whole_key_stroke()
+                .chars()
+                .count(),
+        );
+
+        inflight_chunk
+            .actual_key_strokes()
+            .iter()
+            .zip(inflight_chunk.construct_spell_end_vector().iter())
+            .for_each(|(actual_key_stroke, spell_end)| {
+                on_typing_stat_manager.on_actual_key_stroke(
+                    actual_key_stroke.is_correct(),
+                    spell_count,
+                    *actual_key_stroke.elapsed_time(),
+                );
+
+                if actual_key_stroke.is_correct() {
+                    if let Some(delta) = spell_end {
+                        on_typing_stat_manager.finish_spell(*delta);
+                    }
+                }
+            });
+
+        // 保留中のキーストロークは全てミスタイプである
+        inflight_chunk
+            .pending_key_strokes()
+            .iter()
+            .for_each(|actual_key_stroke| {
+                assert!(!actual_key_stroke.is_correct());
+
+                on_typing_stat_manager.on_actual_key_stroke(
+                    false,
+                    spell_count,
+                    *actual_key_stroke.elapsed_time(),
+                );
+            });
+    }
+
+    // 保留中のキーストロークは確定したキーストロークより後に打たれている
+    let key_stroke_log: Vec<ActualKeyStroke> = confirmed_chunks
+        .iter()
+        .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes())
+        .chain(inflight_chunk.iter().flat_map(|inflight_chunk| {
+            inflight_chunk
+                .actual_key_strokes()
+                .iter()
+                .chain(inflight_chunk.pending_key_strokes().iter())
+        }))
+        .cloned()
+        .collect();
+
+    let last_key_stroke_time = key_stroke_log
+        .iter()
+        .map(|actual_key_stroke| *actual_key_stroke.elapsed_time())
+        .max();
+
+    // 1度もキーストロークせずに終了する場合は明示的な終了時のみである
+    let total_time = finish_time.or(last_key_stroke_time).unwrap();
 
+    let max_combo = on_typing_stat_manager.max_combo();
     let (key_stroke_ots, ideal_key_stroke_ots, spell_ots, c_ots) = on_typing_stat_manager.emit();
 
+    // 途中で終了した場合にはタイプし終えた対象のみを全体とする
     TypingResultStatistics {
         key_stroke: TypingResultStatisticsTarget {
-            whole_count: key_stroke_ots.whole_count(),
+            whole_count: key_stroke_ots.finished_count(),
             completely_correct_count: key_stroke_ots.completely_correct_count(),
             missed_count: key_stroke_ots.wrong_count,
         },
         ideal_key_stroke: TypingResultStatisticsTarget {
-            whole_count: ideal_key_stroke_ots.whole_count(),
+            whole_count: ideal_key_stroke_ots.finished_count(),
             completely_correct_count: ideal_key_stroke_ots.completely_correct_count(),
             missed_count: ideal_key_stroke_ots.wrong_count(),
         },
         total_time,
+        idle_time: Duration::ZERO,
+        end_reason,
+        correction_count: 0,
+        premature_miss_count: 0,
+        assisted_key_stroke_count: 0,
+        skipped_vocabulary_count: 0,
+        shift_key_stroke_count: 0,
+        max_combo,
+        #[cfg(feature = "analysis")]
+        inter_key_interval: construct_inter_key_interval_statistics(
+            confirmed_chunks,
+            inflight_chunk,
+        ),
+        vocabularies: vec![],
+        spell_mistakes: construct_spell_mistake_statistics(confirmed_chunks, inflight_chunk),
+        spell_candidates: construct_spell_candidate_statistics(confirmed_chunks),
+        spell_hints: vec![],
+        #[cfg(feature = "analysis")]
+        typo_analysis: TypoAnalysis::default(),
+        #[cfg(feature = "analysis")]
+        finger_statistics: FingerStatistics::default(),
+        key_stroke_log,
     }
 }
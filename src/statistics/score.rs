@@ -0,0 +1,172 @@
+use crate::display_info::per_minute;
+use crate::statistics::result::TypingResultStatistics;
+
+/// A formula to convert result of typing into a single numeric score.
+///
+/// Games can plug in their own formula by implementing this trait.
+/// Closures which take [`TypingResultStatistics`] and return `f64` also implement this trait.
+/// [`StandardScoreFormula`] is provided as a default.
+pub trait ScoreFormula {
+    /// Calculate score of `result`.
+    fn score(&self, result: &TypingResultStatistics) -> f64;
+}
+
+impl<F> ScoreFormula for F
+where
+    F: Fn(&TypingResultStatistics) -> f64,
+{
+    fn score(&self, result: &TypingResultStatistics) -> f64 {
+        self(result)
+    }
+}
+
+/// A [`ScoreFormula`] similar to one of e-typing.
+///
+/// Score is `kpm * accuracy ^ accuracy_exponent * consistency ^ consistency_exponent` where
+/// - `kpm` is key strokes per minute
+/// - `accuracy` is ratio of key strokes to key strokes including wrong key strokes
+/// - `consistency` is ratio of median to 95th percentile of
+///   [intervals between key strokes](TypingResultStatistics::inter_key_interval()), which is
+///   closer to `1` when typing in steady rhythm
+///
+/// Exponents default to `3` and `1` respectively.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StandardScoreFormula {
+    accuracy_exponent: f64,
+    consistency_exponent: f64,
+}
+
+impl StandardScoreFormula {
+    /// Construct formula with default exponents.
+    pub fn new() -> Self {
+        Self {
+            accuracy_exponent: 3.0,
+            consistency_exponent: 1.0,
+        }
+    }
+
+    /// Construct formula which raises accuracy to `accuracy_exponent`.
+    ///
+    /// Larger exponent penalizes wrong key strokes more.
+    pub fn with_accuracy_exponent(self, accuracy_exponent: f64) -> Self {
+        Self {
+            accuracy_exponent,
+            ..self
+        }
+    }
+
+    /// Construct formula which raises consistency to `consistency_exponent`.
+    ///
+    /// `0` disables consistency component.
+    pub fn with_consistency_exponent(self, consistency_exponent: f64) -> Self {
+        Self {
+            consistency_exponent,
+            ..self
+        }
+    }
+
+    /// Get key strokes per minute of `result`.
+    pub fn kpm(&self, result: &TypingResultStatistics) -> f64 {
+        per_minute(result.key_stroke().whole_count(), result.total_time())
+    }
+
+    /// Get accuracy of `result` in `0.0..=1.0`.
+    ///
+    /// If there is no key stroke, this function returns `1.0`.
+    pub fn accuracy(&self, result: &TypingResultStatistics) -> f64 {
+        let key_stroke = result.key_stroke();
+        let stroke_count = key_stroke.whole_count() + key_stroke.missed_count();
+
+        if stroke_count == 0 {
+            1.0
+        } else {
+            key_stroke.whole_count() as f64 / stroke_count as f64
+        }
+    }
+
+    /// Get consistency of `result` in `0.0..=1.0`.
+    ///
+    /// If there are no intervals between key strokes, this function returns `1.0`.
+    pub fn consistency(&self, result: &TypingResultStatistics) -> f64 {
+        let overall = result.inter_key_interval().overall();
+
+        match (overall.median(), overall.p95()) {
+            (Some(median), Some(p95)) if !p95.is_zero() => median.as_secs_f64() / p95.as_secs_f64(),
+            _ => 1.0,
+        }
+    }
+}
+
+impl Default for StandardScoreFormula {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScoreFormula for StandardScoreFormula {
+    fn score(&self, result: &TypingResultStatistics) -> f64 {
+        self.kpm(result)
+            * self.accuracy(result).powf(self.accuracy_exponent)
+            * self.consistency(result).powf(self.consistency_exponent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use crate::chunk::confirmed::ConfirmedChunk;
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::statistics::result::construct_result;
+    use crate::typing_engine::TypingEndReason;
+    use crate::{gen_candidate, gen_chunk, LapRequest};
+
+    #[test]
+    fn standard_score_formula_combines_components() {
+        let confirmed_chunks = vec![ConfirmedChunk::new(
+            gen_chunk!(
+                "かき",
+                vec![gen_candidate!(["kaki"])],
+                gen_candidate!(["kaki"])
+            ),
+            vec![
+                ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(3, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(
+                    Duration::new(3, 500_000_000),
+                    'u'.try_into().unwrap(),
+                    false,
+                ),
+                ActualKeyStroke::new(Duration::new(4, 0), 'i'.try_into().unwrap(), true),
+            ],
+        )];
+        let result = construct_result(
+            &confirmed_chunks,
+            None,
+            LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap()),
+            TypingEndReason::Completed,
+            None,
+        );
+
+        let formula = StandardScoreFormula::new();
+        assert_eq!(formula.kpm(&result), 60.0);
+        assert_eq!(formula.accuracy(&result), 0.8);
+        // 一定のリズムで打っているので安定度は最大になる
+        assert_eq!(formula.consistency(&result), 1.0);
+        assert_eq!(formula.score(&result), 60.0 * 0.8_f64.powi(3));
+
+        let formula = formula
+            .with_accuracy_exponent(1.0)
+            .with_consistency_exponent(0.0);
+        assert_eq!(formula.score(&result), 48.0);
+
+        // 独自の計算式も同じように使える
+        let custom_formula =
+            |result: &TypingResultStatistics| result.key_stroke().whole_count() as f64;
+        assert_eq!(custom_formula.score(&result), 4.0);
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::typed::TypedChunk;
+use crate::input_scheme::{RomanizationPreference, RomanizationStyle};
+use crate::key_stroke::KeyStrokeChar;
+use crate::statistics::finger::{aggregate_finger_counts, FingerMap, FingerStatistics};
+use crate::statistics::{for_each_expected_key_stroke, sort_by_severity};
+
+/// Miss statistics of key strokes keyed by single key stroke and by key stroke bigram.
+///
+/// Bigram statistics are useful to analyze transitions between fingers.
+/// In both statistics, entries with more wrong key strokes come first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SkillStatistics {
+    key_strokes: Vec<KeyStrokeSkillStatistics>,
+    bigrams: Vec<KeyStrokeBigramSkillStatistics>,
+    // 以前の形式で保存された統計も読み込めるようにする
+    #[serde(default)]
+    romanization_habit: RomanizationHabit,
+}
+
+impl SkillStatistics {
+    /// Get statistics of each key stroke.
+    pub fn key_strokes(&self) -> &[KeyStrokeSkillStatistics] {
+        &self.key_strokes
+    }
+
+    /// Get statistics of each pair of previous key stroke and key stroke.
+    ///
+    /// First key stroke of query has no previous key stroke, so it is not contained.
+    pub fn bigrams(&self) -> &[KeyStrokeBigramSkillStatistics] {
+        &self.bigrams
+    }
+
+    /// Get counts of spells typed with key strokes specific to each romanization style.
+    ///
+    /// This is useful to prefer the style the user habitually types.
+    pub fn romanization_habit(&self) -> &RomanizationHabit {
+        &self.romanization_habit
+    }
+
+    /// Get statistics of key strokes aggregated by whether Shift key is needed.
+    ///
+    /// This is useful to check whether symbols and capital letters are weak points.
+    pub fn shift_statistics(&self) -> ShiftSkillStatistics {
+        let mut shift_statistics = ShiftSkillStatistics::default();
+
+        self.key_strokes.iter().for_each(|statistics| {
+            // 集計対象のキーストロークは正しいキーストロークなので必ず変換できる
+            let is_shifted = KeyStrokeChar::try_from(statistics.key_stroke)
+                .unwrap()
+                .is_shifted();
+
+            let (stroke_count, wrong_count) = if is_shifted {
+                (
+                    &mut shift_statistics.shifted_stroke_count,
+                    &mut shift_statistics.shifted_wrong_count,
+                )
+            } else {
+                (
+                    &mut shift_statistics.unshifted_stroke_count,
+                    &mut shift_statistics.unshifted_wrong_count,
+                )
+            };
+
+            *stroke_count += statistics.stroke_count;
+            *wrong_count += statistics.wrong_count;
+        });
+
+        shift_statistics
+    }
+
+    /// Get statistics of key strokes aggregated by finger assigned in `finger_map`.
+    ///
+    /// Time of key strokes is not recorded in this statistics, so latencies are not available.
+    pub fn finger_statistics(&self, finger_map: &FingerMap) -> FingerStatistics {
+        aggregate_finger_counts(
+            self.key_strokes.iter().map(|statistics| {
+                (
+                    statistics.key_stroke,
+                    statistics.stroke_count,
+                    statistics.wrong_count,
+                )
+            }),
+            finger_map,
+        )
+    }
+
+    /// Merge statistics of `other` into this.
+    ///
+    /// This is useful to aggregate statistics of multiple sessions.
+    pub fn merge(&mut self, other: &SkillStatistics) {
+        let mut key_strokes: BTreeMap<char, (usize, usize)> = BTreeMap::new();
+        let mut bigrams: BTreeMap<(char, char), (usize, usize)> = BTreeMap::new();
+
+        self.key_strokes
+            .iter()
+            .chain(other.key_strokes.iter())
+            .for_each(|statistics| {
+                let (stroke_count, wrong_count) =
+                    key_strokes.entry(statistics.key_stroke).or_default();
+
+                *stroke_count += statistics.stroke_count;
+                *wrong_count += statistics.wrong_count;
+            });
+
+        self.bigrams
+            .iter()
+            .chain(other.bigrams.iter())
+            .for_each(|statistics| {
+                let (stroke_count, wrong_count) = bigrams
+                    .entry((statistics.previous_key_stroke, statistics.key_stroke))
+                    .or_default();
+
+                *stroke_count += statistics.stroke_count;
+                *wrong_count += statistics.wrong_count;
+            });
+
+        let romanization_habit = RomanizationHabit {
+            hepburn_count: self.romanization_habit.hepburn_count
+                + other.romanization_habit.hepburn_count,
+            kunrei_count: self.romanization_habit.kunrei_count
+                + other.romanization_habit.kunrei_count,
+        };
+
+        *self = construct_from_counts(key_strokes, bigrams, romanization_habit);
+    }
+}
+
+/// Miss statistics of a key stroke.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyStrokeSkillStatistics {
+    key_stroke: char,
+    stroke_count: usize,
+    wrong_count: usize,
+}
+
+impl KeyStrokeSkillStatistics {
+    /// Get key stroke character that should be typed.
+    pub fn key_stroke(&self) -> char {
+        self.key_stroke
+    }
+
+    /// Get count of key strokes including wrong key strokes.
+    pub fn stroke_count(&self) -> usize {
+        self.stroke_count
+    }
+
+    /// Get count of wrong key strokes.
+    pub fn wrong_count(&self) -> usize {
+        self.wrong_count
+    }
+
+    /// Get ratio of wrong key strokes to all key strokes.
+    pub fn miss_rate(&self) -> f64 {
+        self.wrong_count as f64 / self.stroke_count as f64
+    }
+}
+
+/// Miss statistics of key strokes aggregated by whether Shift key is needed.
+///
+/// This is constructed by [`SkillStatistics::shift_statistics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ShiftSkillStatistics {
+    shifted_stroke_count: usize,
+    shifted_wrong_count: usize,
+    unshifted_stroke_count: usize,
+    unshifted_wrong_count: usize,
+}
+
+impl ShiftSkillStatistics {
+    /// Get count of key strokes which need Shift key including wrong key strokes.
+    pub fn shifted_stroke_count(&self) -> usize {
+        self.shifted_stroke_count
+    }
+
+    /// Get count of wrong key strokes for key strokes which need Shift key.
+    pub fn shifted_wrong_count(&self) -> usize {
+        self.shifted_wrong_count
+    }
+
+    /// Get count of key strokes which do not need Shift key including wrong key strokes.
+    pub fn unshifted_stroke_count(&self) -> usize {
+        self.unshifted_stroke_count
+    }
+
+    /// Get count of wrong key strokes for key strokes which do not need Shift key.
+    pub fn unshifted_wrong_count(&self) -> usize {
+        self.unshifted_wrong_count
+    }
+
+    /// Get ratio of wrong key strokes to all key strokes which need Shift key.
+    ///
+    /// If there are no such key strokes, this function returns `None`.
+    pub fn shifted_miss_rate(&self) -> Option<f64> {
+        (self.shifted_stroke_count != 0)
+            .then(|| self.shifted_wrong_count as f64 / self.shifted_stroke_count as f64)
+    }
+
+    /// Get ratio of wrong key strokes to all key strokes which do not need Shift key.
+    ///
+    /// If there are no such key strokes, this function returns `None`.
+    pub fn unshifted_miss_rate(&self) -> Option<f64> {
+        (self.unshifted_stroke_count != 0)
+            .then(|| self.unshifted_wrong_count as f64 / self.unshifted_stroke_count as f64)
+    }
+}
+
+/// Counts of spells typed with key strokes specific to each romanization style.
+///
+/// Key strokes specific to neither style such as `ka` are not counted.
+/// This is constructed by [`SkillStatistics::romanization_habit`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RomanizationHabit {
+    hepburn_count: usize,
+    kunrei_count: usize,
+}
+
+impl RomanizationHabit {
+    /// Get count of spells typed with key strokes specific to `style`.
+    pub fn count(&self, style: RomanizationStyle) -> usize {
+        match style {
+            RomanizationStyle::Hepburn => self.hepburn_count,
+            RomanizationStyle::Kunrei => self.kunrei_count,
+        }
+    }
+
+    /// Get the romanization style typed more often.
+    ///
+    /// If both styles are typed equally often, this returns `None`.
+    pub fn habitual_style(&self) -> Option<RomanizationStyle> {
+        match self.hepburn_count.cmp(&self.kunrei_count) {
+            std::cmp::Ordering::Greater => Some(RomanizationStyle::Hepburn),
+            std::cmp::Ordering::Less => Some(RomanizationStyle::Kunrei),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// Get [`RomanizationPreference`] which prefers the habitual style.
+    ///
+    /// See [`habitual_style`](Self::habitual_style()) for details.
+    pub fn preference(&self) -> Option<RomanizationPreference> {
+        self.habitual_style().map(RomanizationPreference::Prefer)
+    }
+}
+
+/// Miss statistics of a key stroke conditioned on previous key stroke.
+///
+/// ex. `o` after `y` of `kyo` and `o` after `k` of `ko` are distinguished.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyStrokeBigramSkillStatistics {
+    previous_key_stroke: char,
+    key_stroke: char,
+    stroke_count: usize,
+    wrong_count: usize,
+}
+
+impl KeyStrokeBigramSkillStatistics {
+    /// Get key stroke character correctly typed just before.
+    pub fn previous_key_stroke(&self) -> char {
+        self.previous_key_stroke
+    }
+
+    /// Get key stroke character that should be typed.
+    pub fn key_stroke(&self) -> char {
+        self.key_stroke
+    }
+
+    /// Get count of key strokes including wrong key strokes.
+    pub fn stroke_count(&self) -> usize {
+        self.stroke_count
+    }
+
+    /// Get count of wrong key strokes.
+    pub fn wrong_count(&self) -> usize {
+        self.wrong_count
+    }
+
+    /// Get ratio of wrong key strokes to all key strokes.
+    pub fn miss_rate(&self) -> f64 {
+        self.wrong_count as f64 / self.stroke_count as f64
+    }
+}
+
+/// 打つべきキーストローク単体と直前に正しく打ったキーストロークとの組ごとにミスタイプを集計する
+pub(crate) fn construct_skill_statistics(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+) -> SkillStatistics {
+    let mut key_strokes: BTreeMap<char, (usize, usize)> = BTreeMap::new();
+    let mut bigrams: BTreeMap<(char, char), (usize, usize)> = BTreeMap::new();
+    let mut previous_key_stroke: Option<char> = None;
+
+    for_each_expected_key_stroke(
+        confirmed_chunks,
+        inflight_chunk,
+        |_, expected_key_stroke, actual_key_stroke| {
+            let is_correct = actual_key_stroke.is_correct();
+
+            let count = |(stroke_count, wrong_count): &mut (usize, usize)| {
+                *stroke_count += 1;
+
+                if !is_correct {
+                    *wrong_count += 1;
+                }
+            };
+
+            count(key_strokes.entry(expected_key_stroke).or_default());
+
+            if let Some(previous_key_stroke) = previous_key_stroke {
+                count(
+                    bigrams
+                        .entry((previous_key_stroke, expected_key_stroke))
+                        .or_default(),
+                );
+            }
+
+            // ミスタイプしても打つべきキーストロークは変わらないので直前のキーストロークも変わらない
+            if is_correct {
+                previous_key_stroke.replace(expected_key_stroke);
+            }
+        },
+    );
+
+    // 実際に使われた候補は確定したチャンクでしか分からない
+    let mut romanization_habit = RomanizationHabit::default();
+    confirmed_chunks
+        .iter()
+        .flat_map(|confirmed_chunk| {
+            confirmed_chunk
+                .confirmed_candidate()
+                .key_stroke_element_strs()
+        })
+        .filter_map(RomanizationStyle::of_key_stroke)
+        .for_each(|style| match style {
+            RomanizationStyle::Hepburn => romanization_habit.hepburn_count += 1,
+            RomanizationStyle::Kunrei => romanization_habit.kunrei_count += 1,
+        });
+
+    construct_from_counts(key_strokes, bigrams, romanization_habit)
+}
+
+fn construct_from_counts(
+    key_strokes: BTreeMap<char, (usize, usize)>,
+    bigrams: BTreeMap<(char, char), (usize, usize)>,
+    romanization_habit: RomanizationHabit,
+) -> SkillStatistics {
+    SkillStatistics {
+        key_strokes: sort_by_severity(key_strokes)
+            .into_iter()
+            .map(
+                |(key_stroke, stroke_count, wrong_count)| KeyStrokeSkillStatistics {
+                    key_stroke,
+                    stroke_count,
+                    wrong_count,
+                },
+            )
+            .collect(),
+        bigrams: sort_by_severity(bigrams)
+            .into_iter()
+            .map(
+                |((previous_key_stroke, key_stroke), stroke_count, wrong_count)| {
+                    KeyStrokeBigramSkillStatistics {
+                        previous_key_stroke,
+                        key_stroke,
+                        stroke_count,
+                        wrong_count,
+                    }
+                },
+            )
+            .collect(),
+        romanization_habit,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn construct_skill_statistics_1() {
+        let confirmed_chunks = vec![
+            ConfirmedChunk::new(
+                gen_chunk!(
+                    "きょ",
+                    vec![gen_candidate!(["kyo"])],
+                    gen_candidate!(["kyo"])
+                ),
+                vec![
+                    ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(2, 0), 'y'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(3, 0), 'p'.try_into().unwrap(), false),
+                    ActualKeyStroke::new(Duration::new(4, 0), 'o'.try_into().unwrap(), true),
+                ],
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("こ", vec![gen_candidate!(["ko"])], gen_candidate!(["ko"])),
+                vec![
+                    ActualKeyStroke::new(Duration::new(5, 0), 'k'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(6, 0), 'o'.try_into().unwrap(), true),
+                ],
+            ),
+        ];
+
+        let statistics = construct_skill_statistics(&confirmed_chunks, None);
+
+        assert_eq!(statistics.key_strokes().len(), 3);
+        assert_eq!(
+            statistics.key_strokes()[0],
+            KeyStrokeSkillStatistics {
+                key_stroke: 'o',
+                stroke_count: 3,
+                wrong_count: 1,
+            }
+        );
+
+        // チャンクをまたいだ遷移も集計される
+        assert_eq!(statistics.bigrams().len(), 4);
+        assert_eq!(
+            statistics.bigrams()[0],
+            KeyStrokeBigramSkillStatistics {
+                previous_key_stroke: 'y',
+                key_stroke: 'o',
+                stroke_count: 2,
+                wrong_count: 1,
+            }
+        );
+        assert!(statistics.bigrams()[1..]
+            .iter()
+            .all(|bigram| bigram.wrong_count() == 0));
+
+        let mut merged = statistics.clone();
+        merged.merge(&statistics);
+        assert_eq!(merged.key_strokes()[0].stroke_count(), 6);
+        assert_eq!(merged.bigrams()[0].wrong_count(), 2);
+    }
+
+    #[test]
+    fn construct_romanization_habit() {
+        let confirmed_chunks = vec![
+            ConfirmedChunk::new(
+                gen_chunk!("し", vec![gen_candidate!(["shi"])], gen_candidate!(["si"])),
+                vec![
+                    ActualKeyStroke::new(Duration::new(1, 0), 's'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(2, 0), 'h'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(3, 0), 'i'.try_into().unwrap(), true),
+                ],
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("か", vec![gen_candidate!(["ka"])], gen_candidate!(["ka"])),
+                vec![
+                    ActualKeyStroke::new(Duration::new(4, 0), 'k'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(5, 0), 'a'.try_into().unwrap(), true),
+                ],
+            ),
+        ];
+
+        // 理想的な候補ではなく実際に打った候補を集計する
+        let statistics = construct_skill_statistics(&confirmed_chunks, None);
+        let romanization_habit = statistics.romanization_habit();
+        assert_eq!(romanization_habit.count(RomanizationStyle::Hepburn), 1);
+        assert_eq!(romanization_habit.count(RomanizationStyle::Kunrei), 0);
+        assert_eq!(
+            romanization_habit.preference(),
+            Some(RomanizationPreference::Prefer(RomanizationStyle::Hepburn))
+        );
+        assert_eq!(RomanizationHabit::default().habitual_style(), None);
+    }
+
+    #[test]
+    fn construct_shift_statistics() {
+        let confirmed_chunks = vec![
+            ConfirmedChunk::new(
+                gen_chunk!("1", vec![gen_candidate!(["1"])], gen_candidate!(["1"])),
+                vec![ActualKeyStroke::new(
+                    Duration::new(1, 0),
+                    '1'.try_into().unwrap(),
+                    true,
+                )],
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("!", vec![gen_candidate!(["!"])], gen_candidate!(["!"])),
+                vec![
+                    ActualKeyStroke::new(Duration::new(2, 0), '1'.try_into().unwrap(), false),
+                    ActualKeyStroke::new(Duration::new(3, 0), '!'.try_into().unwrap(), true),
+                ],
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("A", vec![gen_candidate!(["A"])], gen_candidate!(["A"])),
+                vec![ActualKeyStroke::new(
+                    Duration::new(4, 0),
+                    'A'.try_into().unwrap(),
+                    true,
+                )],
+            ),
+        ];
+
+        let shift_statistics =
+            construct_skill_statistics(&confirmed_chunks, None).shift_statistics();
+
+        assert_eq!(shift_statistics.shifted_stroke_count(), 3);
+        assert_eq!(shift_statistics.shifted_wrong_count(), 1);
+        assert_eq!(shift_statistics.unshifted_stroke_count(), 1);
+        assert_eq!(shift_statistics.unshifted_wrong_count(), 0);
+        assert_eq!(shift_statistics.unshifted_miss_rate(), Some(0.0));
+        assert_eq!(ShiftSkillStatistics::default().shifted_miss_rate(), None);
+    }
+}
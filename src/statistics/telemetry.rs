@@ -0,0 +1,420 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+use crate::chunk::typed::TypedChunk;
+use crate::statistics::result::{construct_result, TypingResultStatisticsTarget};
+use crate::typing_engine::TypingEndReason;
+use crate::LapRequest;
+
+// バイナリ形式を変更した場合にはインクリメントする
+const TELEMETRY_FORMAT_VERSION: u8 = 2;
+
+/// An anonymized and compact record of a typing session.
+///
+/// This record contains only aggregated metrics, so it does not contain any query string or
+/// key stroke sequence. Key level statistics are keyed by hash of key stroke character.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TypingTelemetry {
+    key_stroke: TypingResultStatisticsTarget,
+    ideal_key_stroke: TypingResultStatisticsTarget,
+    total_time: Duration,
+    lap_request: TelemetryLapRequest,
+    keys: Vec<KeyTelemetry>,
+}
+
+impl TypingTelemetry {
+    /// Get summary of key strokes.
+    pub fn key_stroke(&self) -> &TypingResultStatisticsTarget {
+        &self.key_stroke
+    }
+
+    /// Get summary of ideal key strokes.
+    pub fn ideal_key_stroke(&self) -> &TypingResultStatisticsTarget {
+        &self.ideal_key_stroke
+    }
+
+    /// Get total time of typing.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Get summary of lap configuration used in this session.
+    pub fn lap_request(&self) -> &TelemetryLapRequest {
+        &self.lap_request
+    }
+
+    /// Get key level statistics.
+    /// Each entry is sorted by [`key_hash`](KeyTelemetry::key_hash).
+    pub fn keys(&self) -> &[KeyTelemetry] {
+        &self.keys
+    }
+
+    /// Serialize this record into compact binary blob.
+    ///
+    /// All integers are encoded in little endian and blob starts with format version.
+    /// Counts and sizes are encoded as 64 bit integers, so they are not truncated on any
+    /// platform.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![TELEMETRY_FORMAT_VERSION];
+
+        bytes.extend_from_slice(&saturating_u64(self.total_time.as_millis()).to_le_bytes());
+        bytes.push(self.lap_request.base);
+        bytes.extend_from_slice(&(self.lap_request.size as u64).to_le_bytes());
+
+        [&self.key_stroke, &self.ideal_key_stroke]
+            .iter()
+            .for_each(|target| {
+                bytes.extend_from_slice(&(target.whole_count() as u64).to_le_bytes());
+                bytes.extend_from_slice(&(target.completely_correct_count() as u64).to_le_bytes());
+                bytes.extend_from_slice(&(target.missed_count() as u64).to_le_bytes());
+            });
+
+        bytes.extend_from_slice(&(self.keys.len() as u64).to_le_bytes());
+        self.keys.iter().for_each(|key| {
+            bytes.extend_from_slice(&key.key_hash.to_le_bytes());
+            bytes.extend_from_slice(&(key.stroke_count as u64).to_le_bytes());
+            bytes.extend_from_slice(&(key.wrong_count as u64).to_le_bytes());
+        });
+
+        bytes
+    }
+
+    /// Deserialize a record from binary blob generated by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Returns [`None`](std::option::Option::None) when blob is malformed, its format version
+    /// is not supported or a count does not fit in `usize` of this platform.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.read_u8()? != TELEMETRY_FORMAT_VERSION {
+            return None;
+        }
+
+        let total_time = Duration::from_millis(reader.read_u64()?);
+        let lap_request = TelemetryLapRequest {
+            base: reader.read_u8()?,
+            size: reader.read_usize()?,
+        };
+
+        let key_stroke = reader.read_target()?;
+        let ideal_key_stroke = reader.read_target()?;
+
+        let key_count = reader.read_usize()?;
+        let mut keys = vec![];
+        for _ in 0..key_count {
+            keys.push(KeyTelemetry {
+                key_hash: reader.read_u32()?,
+                stroke_count: reader.read_usize()?,
+                wrong_count: reader.read_usize()?,
+            });
+        }
+
+        if !reader.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            key_stroke,
+            ideal_key_stroke,
+            total_time,
+            lap_request,
+            keys,
+        })
+    }
+}
+
+/// Summary of [`LapRequest`] used in telemetry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TelemetryLapRequest {
+    // 0: キーストローク 1: 理想的なキーストローク 2: 綴り 3: チャンク 4: 語彙 5: 経過時間 6: 綴りの位置
+    base: u8,
+    size: usize,
+}
+
+impl TelemetryLapRequest {
+    fn new(lap_request: &LapRequest) -> Self {
+        let (base, size) = match lap_request {
+            LapRequest::KeyStroke(size) => (0, size.get()),
+            LapRequest::IdealKeyStroke(size) => (1, size.get()),
+            LapRequest::Spell(size) => (2, size.get()),
+            LapRequest::Chunk(size) => (3, size.get()),
+            LapRequest::Vocabulary(size) => (4, size.get()),
+            // 経過時間はミリ秒単位で持つ
+            LapRequest::Duration(duration) => (
+                5,
+                usize::try_from(duration.as_millis()).unwrap_or(usize::MAX),
+            ),
+            // 位置はまちまちなのでラップ末の数を持つ
+            LapRequest::CustomSpellPositions(positions) => (6, positions.len()),
+        };
+
+        Self { base, size }
+    }
+
+    /// Get lap base target.
+    ///
+    /// `0` is key stroke, `1` is ideal key stroke, `2` is spell, `3` is chunk, `4` is
+    /// vocabulary, `5` is elapsed time and `6` is custom spell positions.
+    pub fn base(&self) -> u8 {
+        self.base
+    }
+
+    /// Get count of targets per lap.
+    ///
+    /// For laps by elapsed time, this is milliseconds per lap.
+    /// For laps at custom spell positions, this is count of the positions.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Statistics of a key in telemetry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyTelemetry {
+    key_hash: u32,
+    stroke_count: usize,
+    wrong_count: usize,
+}
+
+impl KeyTelemetry {
+    /// Get hash of key stroke character that should be typed.
+    pub fn key_hash(&self) -> u32 {
+        self.key_hash
+    }
+
+    /// Get count of key strokes including wrong key strokes.
+    pub fn stroke_count(&self) -> usize {
+        self.stroke_count
+    }
+
+    /// Get count of wrong key strokes when this key should be typed.
+    pub fn wrong_count(&self) -> usize {
+        self.wrong_count
+    }
+}
+
+pub(crate) fn construct_telemetry(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+    lap_request: LapRequest,
+    end_reason: TypingEndReason,
+    finish_time: Option<Duration>,
+) -> TypingTelemetry {
+    let telemetry_lap_request = TelemetryLapRequest::new(&lap_request);
+    let result = construct_result(
+        confirmed_chunks,
+        inflight_chunk,
+        lap_request,
+        end_reason,
+        finish_time,
+    );
+
+    // キーごとの統計は打つべきだったキーストロークごとに集計する
+    let mut keys: BTreeMap<u32, KeyTelemetry> = BTreeMap::new();
+
+    let typed_chunks = confirmed_chunks
+        .iter()
+        .map(|confirmed_chunk| confirmed_chunk as &dyn ChunkHasActualKeyStrokes)
+        .chain(
+            inflight_chunk
+                .into_iter()
+                .map(|inflight_chunk| inflight_chunk as &dyn ChunkHasActualKeyStrokes),
+        );
+
+    typed_chunks.for_each(|typed_chunk| {
+        let effective_candidate = typed_chunk.effective_candidate();
+        let mut in_candidate_cursor_position = 0;
+
+        typed_chunk
+            .actual_key_strokes()
+            .iter()
+            .for_each(|actual_key_stroke| {
+                let expected_key_stroke = char::from(
+                    effective_candidate.key_stroke_char_at_position(in_candidate_cursor_position),
+                );
+                let key_hash = hash_key_stroke_char(expected_key_stroke);
+
+                let key = keys.entry(key_hash).or_insert(KeyTelemetry {
+                    key_hash,
+                    stroke_count: 0,
+                    wrong_count: 0,
+                });
+
+                key.stroke_count += 1;
+
+                if actual_key_stroke.is_correct() {
+                    in_candidate_cursor_position += 1;
+                } else {
+                    key.wrong_count += 1;
+                }
+            });
+    });
+
+    TypingTelemetry {
+        key_stroke: result.key_stroke().clone(),
+        ideal_key_stroke: result.ideal_key_stroke().clone(),
+        total_time: result.total_time(),
+        lap_request: telemetry_lap_request,
+        keys: keys.into_values().collect(),
+    }
+}
+
+/// キーストロークの文字をFNV-1aでハッシュ化する
+/// アプリケーション間で比較できるようにするため標準ライブラリのハッシュは使わない
+fn hash_key_stroke_char(c: char) -> u32 {
+    let mut buf = [0; 4];
+
+    c.encode_utf8(&mut buf)
+        .bytes()
+        .fold(0x811c9dc5, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+        })
+}
+
+// 表現できないほど長い時間は最大値に丸める
+fn saturating_u64(value: u128) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.bytes.len() < N {
+            return None;
+        }
+
+        let (head, tail) = self.bytes.split_at(N);
+        self.bytes = tail;
+
+        head.try_into().ok()
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|b| b[0])
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        self.take::<4>().map(u32::from_le_bytes)
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        self.take::<8>().map(u64::from_le_bytes)
+    }
+
+    // 32ビット環境では表現できない値が含まれることがある
+    fn read_usize(&mut self) -> Option<usize> {
+        self.read_u64()
+            .and_then(|value| usize::try_from(value).ok())
+    }
+
+    fn read_target(&mut self) -> Option<TypingResultStatisticsTarget> {
+        Some(TypingResultStatisticsTarget::new(
+            self.read_usize()?,
+            self.read_usize()?,
+            self.read_usize()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::num::NonZeroUsize;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn construct_telemetry_1() {
+        let confirmed_chunks = vec![
+            ConfirmedChunk::new(
+                gen_chunk!("か", vec![gen_candidate!(["ka"])], gen_candidate!(["ka"])),
+                vec![
+                    ActualKeyStroke::new(Duration::new(1, 0), 'k'.try_into().unwrap(), true),
+                    ActualKeyStroke::new(Duration::new(2, 0), 'j'.try_into().unwrap(), false),
+                    ActualKeyStroke::new(Duration::new(3, 0), 'a'.try_into().unwrap(), true),
+                ],
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("あ", vec![gen_candidate!(["a"])], gen_candidate!(["a"])),
+                vec![ActualKeyStroke::new(
+                    Duration::new(4, 0),
+                    'a'.try_into().unwrap(),
+                    true,
+                )],
+            ),
+        ];
+
+        let telemetry = construct_telemetry(
+            &confirmed_chunks,
+            None,
+            LapRequest::Spell(NonZeroUsize::new(1).unwrap()),
+            TypingEndReason::Completed,
+            None,
+        );
+
+        assert_eq!(telemetry.total_time(), Duration::new(4, 0));
+        assert_eq!(telemetry.key_stroke().whole_count(), 3);
+        assert_eq!(telemetry.key_stroke().missed_count(), 1);
+        assert_eq!(telemetry.lap_request().base(), 2);
+        assert_eq!(telemetry.lap_request().size(), 1);
+
+        let mut expected_keys = vec![
+            KeyTelemetry {
+                key_hash: hash_key_stroke_char('k'),
+                stroke_count: 1,
+                wrong_count: 0,
+            },
+            KeyTelemetry {
+                key_hash: hash_key_stroke_char('a'),
+                stroke_count: 3,
+                wrong_count: 1,
+            },
+        ];
+        expected_keys.sort_by_key(|key| key.key_hash);
+        assert_eq!(telemetry.keys(), expected_keys.as_slice());
+
+        let bytes = telemetry.to_bytes();
+        assert_eq!(TypingTelemetry::from_bytes(&bytes), Some(telemetry));
+        assert_eq!(TypingTelemetry::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn telemetry_bytes_keep_counts_beyond_u32() {
+        let large_count = u32::MAX as usize + 1;
+        let telemetry = TypingTelemetry {
+            key_stroke: TypingResultStatisticsTarget::new(large_count, large_count, 1),
+            ideal_key_stroke: TypingResultStatisticsTarget::new(large_count, large_count, 0),
+            total_time: Duration::from_secs(10),
+            lap_request: TelemetryLapRequest::new(&LapRequest::Duration(Duration::from_millis(
+                large_count as u64,
+            ))),
+            keys: vec![KeyTelemetry {
+                key_hash: hash_key_stroke_char('a'),
+                stroke_count: large_count + 1,
+                wrong_count: 1,
+            }],
+        };
+        assert_eq!(telemetry.lap_request().size(), large_count);
+
+        let bytes = telemetry.to_bytes();
+        assert_eq!(TypingTelemetry::from_bytes(&bytes), Some(telemetry));
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+use crate::chunk::typed::TypedChunk;
+use crate::input_scheme::InputScheme;
+use crate::key_stroke::KeyStrokeChar;
+use crate::keyboard_layout::KeyboardLayout;
+
+/// A category of wrong key stroke classified by [`TypoAnalysis`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TypoCategory {
+    /// The key pressed just before is pressed again.
+    DoublePress,
+    /// The key which should be typed next is pressed ahead.
+    Transposition,
+    /// The key is valid at this position in another romanization of the same spell.
+    ///
+    /// ex. `y` after `s` for `しゃ` typed as `sha`.
+    RomanizationVariant,
+    /// The key next to the key which should be typed is pressed on physical keyboard.
+    AdjacentKey,
+    /// The wrong key stroke does not match any other category.
+    Other,
+}
+
+/// A wrong key stroke with its category.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Typo {
+    expected_key_stroke: KeyStrokeChar,
+    actual_key_stroke: KeyStrokeChar,
+    category: TypoCategory,
+}
+
+impl Typo {
+    /// Get key stroke which should be typed.
+    pub fn expected_key_stroke(&self) -> &KeyStrokeChar {
+        &self.expected_key_stroke
+    }
+
+    /// Get key stroke which is actually typed.
+    pub fn actual_key_stroke(&self) -> &KeyStrokeChar {
+        &self.actual_key_stroke
+    }
+
+    /// Get category of this wrong key stroke.
+    pub fn category(&self) -> TypoCategory {
+        self.category
+    }
+}
+
+/// Wrong key strokes classified into categories such as adjacent key slips and transpositions.
+///
+/// When a wrong key stroke matches multiple categories, the first one in declaration order of
+/// [`TypoCategory`] is chosen.
+/// Adjacency of keys is judged on the keyboard layout used while typing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TypoAnalysis {
+    typos: Vec<Typo>,
+}
+
+impl TypoAnalysis {
+    /// Get all wrong key strokes in typed order.
+    pub fn typos(&self) -> &[Typo] {
+        &self.typos
+    }
+
+    /// Get count of wrong key strokes classified into `category`.
+    pub fn count(&self, category: TypoCategory) -> usize {
+        self.typos
+            .iter()
+            .filter(|typo| typo.category == category)
+            .count()
+    }
+
+    pub(crate) fn accumulate(&mut self, other: &Self) {
+        self.typos.extend(other.typos.iter().cloned());
+    }
+}
+
+/// 実際のキーストロークのうちミスタイプを打つべきキーストロークとの関係から分類する
+pub(crate) fn construct_typo_analysis(
+    confirmed_chunks: &[ConfirmedChunk],
+    inflight_chunk: Option<&TypedChunk>,
+    input_scheme: &InputScheme,
+    keyboard_layout: &KeyboardLayout,
+) -> TypoAnalysis {
+    let typed_chunks: Vec<&dyn ChunkHasActualKeyStrokes> = confirmed_chunks
+        .iter()
+        .map(|confirmed_chunk| confirmed_chunk as &dyn ChunkHasActualKeyStrokes)
+        .chain(
+            inflight_chunk
+                .into_iter()
+                .map(|inflight_chunk| inflight_chunk as &dyn ChunkHasActualKeyStrokes),
+        )
+        .collect();
+
+    let mut typos = vec![];
+    let mut previous_key_stroke: Option<&KeyStrokeChar> = None;
+
+    typed_chunks
+        .iter()
+        .enumerate()
+        .for_each(|(i, typed_chunk)| {
+            let effective_candidate = typed_chunk.effective_candidate();
+            let key_stroke_count = effective_candidate.calc_key_stroke_count();
+            let next_typed_chunk = typed_chunks.get(i + 1);
+
+            // 確定した候補以外の候補は残っていないので綴りから候補を付与し直す
+            let mut all_candidates_chunk = typed_chunk.as_ref().clone();
+            all_candidates_chunk.reassign_key_stroke(
+                next_typed_chunk.map(|next_typed_chunk| next_typed_chunk.as_ref()),
+                input_scheme,
+                None,
+            );
+
+            let mut in_candidate_cursor_position = 0;
+
+            typed_chunk
+                .actual_key_strokes()
+                .iter()
+                .for_each(|actual_key_stroke| {
+                    let actual = actual_key_stroke.key_stroke();
+
+                    if !actual_key_stroke.is_correct() {
+                        let expected = effective_candidate
+                            .key_stroke_char_at_position(in_candidate_cursor_position);
+
+                        let next_expected = if in_candidate_cursor_position + 1 < key_stroke_count {
+                            Some(
+                                effective_candidate
+                                    .key_stroke_char_at_position(in_candidate_cursor_position + 1),
+                            )
+                        } else {
+                            next_typed_chunk.map(|next_typed_chunk| {
+                                next_typed_chunk
+                                    .effective_candidate()
+                                    .key_stroke_char_at_position(0)
+                            })
+                        };
+
+                        let is_variant = all_candidates_chunk
+                            .key_stroke_candidates()
+                            .iter()
+                            .flatten()
+                            .filter(|candidate| {
+                                candidate.calc_key_stroke_count() > in_candidate_cursor_position
+                            })
+                            .any(|candidate| {
+                                candidate.key_stroke_char_at_position(in_candidate_cursor_position)
+                                    == *actual
+                            });
+
+                        let category = if previous_key_stroke == Some(actual) {
+                            TypoCategory::DoublePress
+                        } else if next_expected.as_ref() == Some(actual) {
+                            TypoCategory::Transposition
+                        } else if is_variant {
+                            TypoCategory::RomanizationVariant
+                        } else if keyboard_layout.are_adjacent(&expected, actual) {
+                            TypoCategory::AdjacentKey
+                        } else {
+                            TypoCategory::Other
+                        };
+
+                        typos.push(Typo {
+                            expected_key_stroke: expected,
+                            actual_key_stroke: actual.clone(),
+                            category,
+                        });
+                    } else {
+                        in_candidate_cursor_position += 1;
+                    }
+
+                    previous_key_stroke.replace(actual);
+                });
+        });
+
+    TypoAnalysis { typos }
+}
+
+// テストは組み込みの辞書を前提としている
+#[cfg(all(test, not(feature = "ascii_only")))]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::key_stroke::ActualKeyStroke;
+    use crate::{gen_candidate, gen_chunk};
+
+    #[test]
+    fn construct_typo_analysis_1() {
+        let key_strokes = |key_strokes: &[(char, bool)]| -> Vec<ActualKeyStroke> {
+            key_strokes
+                .iter()
+                .map(|(key_stroke, is_correct)| {
+                    ActualKeyStroke::new(
+                        Duration::new(1, 0),
+                        (*key_stroke).try_into().unwrap(),
+                        *is_correct,
+                    )
+                })
+                .collect()
+        };
+
+        let confirmed_chunks = vec![
+            ConfirmedChunk::new(
+                gen_chunk!("か", vec![gen_candidate!(["ka"])], gen_candidate!(["ka"])),
+                key_strokes(&[('a', false), ('k', true), ('q', false), ('a', true)]),
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!(
+                    "しゃ",
+                    vec![gen_candidate!(["sha"])],
+                    gen_candidate!(["sha"])
+                ),
+                key_strokes(&[
+                    ('s', true),
+                    ('s', false),
+                    ('y', false),
+                    ('h', true),
+                    ('a', true),
+                ]),
+            ),
+            ConfirmedChunk::new(
+                gen_chunk!("た", vec![gen_candidate!(["ta"])], gen_candidate!(["ta"])),
+                key_strokes(&[('t', true), ('p', false), ('a', true)]),
+            ),
+        ];
+
+        let typo_analysis = construct_typo_analysis(
+            &confirmed_chunks,
+            None,
+            &InputScheme::default(),
+            &KeyboardLayout::default(),
+        );
+
+        let categories: Vec<TypoCategory> = typo_analysis
+            .typos()
+            .iter()
+            .map(|typo| typo.category())
+            .collect();
+        assert_eq!(
+            categories,
+            vec![
+                TypoCategory::Transposition,
+                TypoCategory::AdjacentKey,
+                TypoCategory::DoublePress,
+                TypoCategory::RomanizationVariant,
+                TypoCategory::Other,
+            ]
+        );
+        assert_eq!(typo_analysis.typos()[1].expected_key_stroke(), &'a');
+        assert_eq!(typo_analysis.typos()[1].actual_key_stroke(), &'q');
+        assert_eq!(typo_analysis.count(TypoCategory::Other), 1);
+    }
+}
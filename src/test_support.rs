@@ -0,0 +1,305 @@
+//! Fixtures and helpers for testing applications built on this crate.
+//!
+//! This module is available only when `test_support` feature is enabled.
+
+use std::num::NonZeroUsize;
+
+use crate::{
+    EngineState, KeyStrokeChar, LapRequest, QueryRequest, TypingEngine, TypingEngineError,
+    TypingEngineErrorKind, VocabularyEntry, VocabularyOrder, VocabularyQuantifier,
+    VocabularySeparator, VocabularySpellElement,
+};
+
+/// Construct a [`VocabularyEntry`] whose view and spell are same.
+///
+/// Each character of `text` must be able to be used as a spell.
+///
+/// # Panics
+///
+/// Panics when `text` contains a character which cannot be used as a spell.
+pub fn same_view_and_spell_entry(text: &str) -> VocabularyEntry {
+    VocabularyEntry::new(
+        text.to_string(),
+        text.chars()
+            .map(|c| VocabularySpellElement::Normal(c.to_string().try_into().unwrap()))
+            .collect(),
+    )
+    .unwrap()
+}
+
+/// Vocabularies which consist of only ASCII characters.
+pub fn english_vocabularies() -> Vec<VocabularyEntry> {
+    ["apple", "banana", "Cherry", "Hello, world!"]
+        .iter()
+        .map(|text| same_view_and_spell_entry(text))
+        .collect()
+}
+
+/// Vocabularies which consist of only hiragana.
+pub fn kana_vocabularies() -> Vec<VocabularyEntry> {
+    [
+        "さくら",
+        "きょうと",
+        "ちゃいろ",
+        "じてんしゃ",
+        "ふぁっしょん",
+    ]
+    .iter()
+    .map(|text| same_view_and_spell_entry(text))
+    .collect()
+}
+
+/// Vocabularies which contain compound spells (熟字訓).
+pub fn compound_vocabularies() -> Vec<VocabularyEntry> {
+    let compound = |spell: &str, count: usize| {
+        VocabularySpellElement::Compound((
+            spell.to_string().try_into().unwrap(),
+            NonZeroUsize::new(count).unwrap(),
+        ))
+    };
+    let normal =
+        |spell: &str| VocabularySpellElement::Normal(spell.to_string().try_into().unwrap());
+
+    vec![
+        VocabularyEntry::new("今日".to_string(), vec![compound("きょう", 2)]).unwrap(),
+        VocabularyEntry::new("五月雨".to_string(), vec![compound("さみだれ", 3)]).unwrap(),
+        VocabularyEntry::new(
+            "七夕送り".to_string(),
+            vec![compound("たなばた", 2), normal("おく"), normal("り")],
+        )
+        .unwrap(),
+        VocabularyEntry::new(
+            "大人買い".to_string(),
+            vec![compound("おとな", 2), normal("が"), normal("い")],
+        )
+        .unwrap(),
+    ]
+}
+
+/// Vocabularies which contain chains of `ん` and `っ`.
+///
+/// Key stroke candidates of these chunks depend on next chunk and some of them are delayed
+/// confirmed, so these are useful to test edge cases.
+pub fn pathological_vocabularies() -> Vec<VocabularyEntry> {
+    [
+        "しんにゅう",
+        "きっっと",
+        "ほんやく",
+        "あんない",
+        "かんい",
+        "ばっくん",
+        "んっんっ",
+        "っん",
+    ]
+    .iter()
+    .map(|text| same_view_and_spell_entry(text))
+    .collect()
+}
+
+/// Construct a [`QueryRequest`] which selects all of given vocabularies in order.
+///
+/// Vocabularies are separated by whitespace.
+///
+/// # Panics
+///
+/// Panics when `vocabulary_entries` is empty.
+pub fn in_order_query_request<'vocabulary>(
+    vocabulary_entries: &[&'vocabulary VocabularyEntry],
+) -> QueryRequest<'vocabulary> {
+    QueryRequest::new(
+        vocabulary_entries,
+        // 語彙区切りも語彙として数えられる
+        VocabularyQuantifier::Vocabulary(
+            NonZeroUsize::new(vocabulary_entries.len() * 2 - 1).unwrap(),
+        ),
+        VocabularySeparator::WhiteSpace,
+        VocabularyOrder::InOrder,
+    )
+}
+
+/// Construct a started [`TypingEngine`] whose query is constructed by `query_request`.
+///
+/// # Panics
+///
+/// Panics when query cannot be constructed from `query_request`.
+pub fn started_engine(query_request: QueryRequest) -> TypingEngine {
+    let mut engine = TypingEngine::new();
+
+    engine.init(query_request).unwrap();
+    engine.start().unwrap();
+
+    engine
+}
+
+/// Get key strokes from current cursor position to the end of key strokes shown in
+/// [`DisplayInfo`](crate::DisplayInfo).
+///
+/// These key strokes are same as key strokes in [`DisplayInfo`](crate::DisplayInfo), so giving
+/// these key strokes in order types them without any miss.
+/// When candidate window is configured by
+/// [`set_candidate_window`](crate::TypingEngine::set_candidate_window()), key strokes only cover
+/// chunks in the window, so giving them may not finish typing.
+/// Use [`stroke_until_finished`] to type whole query.
+pub fn remaining_key_strokes(
+    engine: &TypingEngine,
+) -> Result<Vec<KeyStrokeChar>, TypingEngineError> {
+    let display_info =
+        engine.construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))?;
+    let key_stroke_info = display_info.key_stroke_info();
+
+    Ok(key_stroke_info
+        .key_stroke()
+        .chars()
+        .skip(key_stroke_info.current_cursor_position())
+        .map(|c| c.try_into().unwrap())
+        .collect())
+}
+
+/// Give key strokes in `script` one by one.
+///
+/// Returns whether typing is finished after last key stroke.
+/// Wrong key strokes can be included in `script` to construct arbitrary state.
+///
+/// # Panics
+///
+/// Panics when `script` contains a character which cannot be used as a key stroke.
+pub fn stroke_script(engine: &mut TypingEngine, script: &str) -> Result<bool, TypingEngineError> {
+    let mut is_finished = false;
+
+    for c in script.chars() {
+        is_finished = engine.stroke_key(c.try_into().unwrap())?;
+    }
+
+    Ok(is_finished)
+}
+
+/// Give `count` correct key strokes.
+///
+/// Returns whether typing is finished after last key stroke.
+/// If typing is finished before giving `count` key strokes, this function stops there.
+///
+/// See [`stroke_until_finished`] for errors.
+pub fn stroke_correctly(
+    engine: &mut TypingEngine,
+    count: usize,
+) -> Result<bool, TypingEngineError> {
+    stroke_correctly_inner(engine, Some(count))
+}
+
+/// Give correct key strokes until typing is finished.
+///
+/// Key strokes are fetched by [`remaining_key_strokes`] repeatedly, so this works even if
+/// candidate window is configured.
+/// If key strokes do not make any progress (ex. no key stroke is shown), this function returns
+/// error whose kind is [`TypingEngineErrorKind::InvalidState`].
+pub fn stroke_until_finished(engine: &mut TypingEngine) -> Result<(), TypingEngineError> {
+    stroke_correctly_inner(engine, None).map(|_| ())
+}
+
+// 候補の表示範囲が設定されているとキーストロークは一部しか得られないので得られなくなるまで繰り返す
+fn stroke_correctly_inner(
+    engine: &mut TypingEngine,
+    count: Option<usize>,
+) -> Result<bool, TypingEngineError> {
+    let mut remaining_count = count;
+
+    while remaining_count != Some(0) {
+        if engine.state() == EngineState::Finished {
+            return Ok(true);
+        }
+
+        let mut is_progressed = false;
+
+        for key_stroke in remaining_key_strokes(engine)? {
+            if remaining_count == Some(0) {
+                break;
+            }
+
+            let outcome = engine.stroke_key_with_outcome(key_stroke)?;
+            is_progressed |= outcome.is_correct();
+            remaining_count = remaining_count.map(|count| count - 1);
+
+            if outcome.is_finished() {
+                return Ok(true);
+            }
+        }
+
+        if !is_progressed {
+            return Err(TypingEngineError::with_state(
+                TypingEngineErrorKind::InvalidState,
+                engine.state(),
+            ));
+        }
+    }
+
+    Ok(engine.state() == EngineState::Finished)
+}
+
+// フィクスチャの語彙は組み込みの辞書を前提としている
+#[cfg(all(test, not(feature = "ascii_only")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn all_fixture_vocabularies_can_be_typed() {
+        [
+            english_vocabularies(),
+            kana_vocabularies(),
+            compound_vocabularies(),
+            pathological_vocabularies(),
+        ]
+        .iter()
+        .for_each(|vocabularies| {
+            let vocabulary_entries: Vec<&VocabularyEntry> = vocabularies.iter().collect();
+            let mut engine = started_engine(in_order_query_request(&vocabulary_entries));
+
+            stroke_until_finished(&mut engine).unwrap();
+
+            let result = engine
+                .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+                .unwrap();
+            assert_eq!(result.key_stroke().missed_count(), 0);
+        });
+    }
+
+    #[test]
+    fn stroke_until_finished_with_candidate_window() {
+        let vocabularies = kana_vocabularies();
+        let vocabulary_entries: Vec<&VocabularyEntry> = vocabularies.iter().collect();
+        let mut engine = TypingEngine::new();
+        engine.set_candidate_window(NonZeroUsize::new(1));
+        engine
+            .init(in_order_query_request(&vocabulary_entries))
+            .unwrap();
+        engine.start().unwrap();
+
+        let whole_key_stroke_count =
+            remaining_key_strokes(&started_engine(in_order_query_request(&vocabulary_entries)))
+                .unwrap()
+                .len();
+        assert!(remaining_key_strokes(&engine).unwrap().len() < whole_key_stroke_count);
+
+        assert!(!stroke_correctly(&mut engine, whole_key_stroke_count - 1).unwrap());
+        stroke_until_finished(&mut engine).unwrap();
+        assert_eq!(engine.state(), EngineState::Finished);
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().missed_count(), 0);
+    }
+
+    #[test]
+    fn stroke_script_with_miss() {
+        let vocabularies = kana_vocabularies();
+        let mut engine = started_engine(in_order_query_request(&[&vocabularies[0]]));
+
+        assert!(!stroke_script(&mut engine, "sak#").unwrap());
+        assert!(stroke_correctly(&mut engine, 3).unwrap());
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().missed_count(), 1);
+    }
+}
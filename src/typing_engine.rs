@@ -1,43 +1,162 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::Display;
-use std::time::Instant;
+use std::num::NonZeroUsize;
+use std::time::Duration;
 
-use crate::display_info::{DisplayInfo, ViewDisplayInfo};
-use crate::key_stroke::KeyStrokeChar;
-use crate::query::QueryRequest;
-use crate::statistics::result::{construct_result, TypingResultStatistics};
+use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+use crate::chunk::typed::KeyStrokeResult;
+#[cfg(feature = "statistics")]
+use crate::chunk::typed::TypedChunk;
+#[cfg(feature = "statistics")]
+use crate::display_info::LapDisplayInfo;
+use crate::display_info::{
+    DisplayInfo, DisplayInfoDelta, DisplayStringBuffers, DisplayWindowRequest,
+    KeyStrokeDisplayInfo, LineBreakDisplayInfo, PaceDisplayInfo, SpeedDisplayInfo,
+    SpellDisplayInfo, ViewDisplayInfo, WindowedDisplayInfo,
+};
+use crate::input_scheme::{CustomDictionary, InputScheme, RomanizationPreference};
+use crate::key_stroke::{ActualKeyStroke, KeyStrokeChar};
+use crate::keyboard_layout::{KeyboardLayout, PhysicalKey};
+use crate::query::{Query, QueryRequest};
+#[cfg(feature = "analysis")]
+use crate::statistics::context::{construct_contextual_miss_statistics, ContextualMissStatistics};
+#[cfg(feature = "analysis")]
+use crate::statistics::finger::{construct_finger_statistics, FingerMap};
+#[cfg(feature = "analysis")]
+use crate::statistics::heatmap::{construct_mistype_heatmap, MistypeHeatmap};
+#[cfg(feature = "statistics")]
+use crate::statistics::result::{
+    construct_idle_time, construct_result, construct_vocabulary_result_statistics,
+    AccumulatedResultStatistics, SpellHintStatistics, TypingResultStatistics,
+};
+#[cfg(feature = "analysis")]
+use crate::statistics::skill::{construct_skill_statistics, RomanizationHabit, SkillStatistics};
+#[cfg(feature = "analysis")]
+use crate::statistics::telemetry::{construct_telemetry, TypingTelemetry};
+#[cfg(feature = "analysis")]
+use crate::statistics::typo::construct_typo_analysis;
 use crate::statistics::LapRequest;
-use crate::typing_engine::processed_chunk_info::ProcessedChunkInfo;
-use crate::vocabulary::{construct_view_position_of_spell_positions, VocabularyInfo};
+use crate::typing_engine::clock::EngineClock;
+use crate::typing_engine::correction::CorrectionManager;
+use crate::typing_engine::digest::StateDigester;
+use crate::typing_engine::display_cache::DisplayCache;
+use crate::typing_engine::ghost::GhostFrame;
+use crate::typing_engine::life::LifeManager;
+use crate::typing_engine::processed_chunk_info::{DisplayStatistics, ProcessedChunkInfo};
+use crate::typing_engine::replay::ReplayEvent;
+use crate::vocabulary::{construct_vocabulary_end_chunk_counts, VocabularyInfo};
 
+pub use assist::AutoCompletePolicy;
+pub use candidate::CandidateView;
+pub use clock::Clock;
+#[cfg(feature = "std_clock")]
+pub use clock::StdClock;
+pub use correction::WrongStateEvent;
+pub use event::EngineEvent;
+pub use ghost::{GhostCursor, TypingGhost};
+pub use life::{LifeConfig, LifeEvent, LifeRegeneration};
+pub use outcome::KeyStrokeOutcome;
+pub use profile::PlayerProfile;
+pub use race::{RaceStanding, TypingRace};
+pub use replay::{ReplayFrame, ReplayKeyStroke, ReplayOperation, TypingReplay};
+pub use termination::{TerminationCondition, TypingEndReason};
+
+mod assist;
+mod candidate;
+mod clock;
+mod correction;
+mod digest;
+mod display_cache;
+mod event;
+mod ghost;
+mod life;
+mod outcome;
 mod processed_chunk_info;
+mod profile;
+mod race;
+mod replay;
+mod termination;
 
 /// Error type returned from [`TypingEngine`].
 #[derive(Debug)]
 pub struct TypingEngineError {
     kind: TypingEngineErrorKind,
+    // ライフサイクルに反する操作をしたときのエンジンの状態
+    state: Option<EngineState>,
 }
 
 impl TypingEngineError {
     fn new(kind: TypingEngineErrorKind) -> Self {
-        Self { kind }
+        Self { kind, state: None }
+    }
+
+    pub(crate) fn with_state(kind: TypingEngineErrorKind, state: EngineState) -> Self {
+        Self {
+            kind,
+            state: Some(state),
+        }
+    }
+
+    /// Get kind of this error.
+    ///
+    /// This is useful to recover from errors depending on their cause.
+    pub fn kind(&self) -> TypingEngineErrorKind {
+        self.kind
+    }
+
+    /// Get state of engine when the operation was called.
+    ///
+    /// This is available only for errors of operations illegal in the state such as stroking key
+    /// before start.
+    pub fn state(&self) -> Option<EngineState> {
+        self.state
     }
 }
 
 impl Display for TypingEngineError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.kind)
+        match self.state {
+            Some(state) => write!(f, "{} (engine is {})", self.kind, state),
+            None => write!(f, "{}", self.kind),
+        }
     }
 }
 
 impl Error for TypingEngineError {}
 
-#[derive(Debug)]
-enum TypingEngineErrorKind {
+/// A kind of [`TypingEngineError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TypingEngineErrorKind {
+    /// Engine is not initialized.
     MustBeInitialized,
+    /// Typing is not started.
     MustBeStarted,
+    /// Typing is already started.
+    AlreadyStarted,
+    /// Typing is already finished.
     AlreadyFinished,
+    /// Typing is not finished.
     NotFinished,
+    /// Lap request is not configured.
+    LapRequestNotConfigured,
+    /// Query cannot be constructed from query request.
+    InvalidQueryRequest,
+    /// Query request contains a vocabulary whose spell cannot be typed with the input scheme.
+    UntypeableSpell,
+    /// Strict mode is not enabled.
+    StrictModeNotEnabled,
+    /// Wrong key strokes are not corrected.
+    MustBeCorrected,
+    /// Replay cannot be played back.
+    InvalidReplay,
+    /// Player of race is not found.
+    PlayerNotFound,
+    /// Chunk currently typed already has key strokes.
+    NotAtChunkBoundary,
+    /// Internal state of engine is inconsistent with its lifecycle.
+    InvalidState,
 }
 
 impl TypingEngineErrorKind {
@@ -47,8 +166,18 @@ impl TypingEngineErrorKind {
         match *self {
             MustBeInitialized => "not initialized",
             MustBeStarted => "not started",
+            AlreadyStarted => "already started",
             AlreadyFinished => "already finished",
             NotFinished => "not finished",
+            LapRequestNotConfigured => "lap request not configured",
+            InvalidQueryRequest => "query cannot be constructed from query request",
+            UntypeableSpell => "spell which cannot be typed with input scheme",
+            StrictModeNotEnabled => "strict mode not enabled",
+            MustBeCorrected => "uncorrected wrong key strokes remain",
+            InvalidReplay => "replay cannot be played back",
+            PlayerNotFound => "player not found",
+            NotAtChunkBoundary => "chunk currently typed has key strokes",
+            InvalidState => "internal state inconsistent with lifecycle",
         }
     }
 }
@@ -59,6 +188,33 @@ impl Display for TypingEngineErrorKind {
     }
 }
 
+/// A state in lifecycle of [`TypingEngine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EngineState {
+    /// Query is not constructed by [`init`](TypingEngine::init()) yet.
+    Uninitialized,
+    /// Query is constructed and typing can be started.
+    Ready,
+    /// Typing is in progress.
+    Started,
+    /// Typing is finished by completing query, calling [`finish`](TypingEngine::finish()) or
+    /// other reasons such as time limit.
+    Finished,
+}
+
+impl Display for EngineState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            Self::Uninitialized => "uninitialized",
+            Self::Ready => "ready",
+            Self::Started => "started",
+            Self::Finished => "finished",
+        };
+
+        write!(f, "{}", state)
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 enum TypingEngineState {
     Uninitialized,
@@ -70,9 +226,72 @@ enum TypingEngineState {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct TypingEngine {
     state: TypingEngineState,
-    start_time: Option<Instant>,
+    // 時計で計測した開始時刻
+    start_time: Option<Duration>,
+    // 開始から時計を開始するまでの時間
+    // 最初の正しいキーストロークを待っている間はNone
+    clock_start_offset: Option<Duration>,
+    // 開始時に最初の正しいキーストロークまで時計の開始を待つかどうか
+    first_stroke_starts_clock: bool,
+    clock: EngineClock,
     processed_chunk_info: Option<ProcessedChunkInfo>,
     vocabulary_infos: Option<Vec<VocabularyInfo>>,
+    // 語彙ごとのその語彙の末尾までのチャンク数
+    // キーストロークのたびに語彙を走査しなくて済むように語彙が変わるたびに求め直しておく
+    vocabulary_end_chunk_counts: Vec<usize>,
+    lap_request: Option<LapRequest>,
+    life_config: Option<LifeConfig>,
+    life_manager: Option<LifeManager>,
+    // 明示的に終了した時の開始からの経過時間
+    finish_time: Option<Duration>,
+    // 制限時間が設定されている場合にはそれを超えたキーストロークで終了する
+    time_limit: Option<Duration>,
+    // 制限時間を超えたキーストロークで終了したか
+    // 明示的に制限時間ちょうどで終了した場合と区別する
+    is_time_limit_reached: bool,
+    // 設定されている場合にはミスタイプの数が上限に達したキーストロークで終了する
+    termination_condition: Option<TerminationCondition>,
+    keyboard_layout: KeyboardLayout,
+    // 設定されていない場合にはキーボード配列での標準的な指の割り当てを使う
+    #[cfg(feature = "analysis")]
+    finger_map: Option<FingerMap>,
+    #[cfg(feature = "statistics")]
+    accumulated_statistics: AccumulatedResultStatistics,
+    input_scheme: InputScheme,
+    romanization_preference: Option<RomanizationPreference>,
+    // キーストローク候補を保持しておく未処理のチャンク数
+    candidate_window: Option<NonZeroUsize>,
+    // 設定されている場合にはその速度と比べた進み具合を表示用の情報に含める
+    target_kpm: Option<NonZeroUsize>,
+    // キーストロークの間隔がこれを超えた部分は放置した時間とみなす
+    idle_threshold: Option<Duration>,
+    strict_mode: bool,
+    shift_key_mode: bool,
+    correction_manager: Option<CorrectionManager>,
+    wrong_state_events: Vec<WrongStateEvent>,
+    events: Vec<EngineEvent>,
+    // イベントとして通知済みのラップ数
+    #[cfg(feature = "statistics")]
+    completed_lap_count: usize,
+    // 表示用の情報のうちクエリが変わるまで変化しない部分と確定したチャンクまでを反映した表示用の情報
+    display_cache: DisplayCache,
+    // 直近の初期化にかかった時間
+    init_duration: Option<Duration>,
+    // 次のチャンクの先頭のキーストロークによるミスタイプの数
+    premature_miss_count: usize,
+    // 打ち切り条件の判定に用いるミスタイプの数
+    miss_count: usize,
+    // 自動補完によって与えたキーストロークの数
+    assisted_key_stroke_count: usize,
+    // 飛ばした語彙の数
+    skipped_vocabulary_count: usize,
+    // チャンクのインデックスごとのヒントを使った回数
+    hint_counts: BTreeMap<usize, usize>,
+    // シフトキーモードで正しいキーストロークのために押したシフトキーの数
+    shift_key_stroke_count: usize,
+    // 再生用に記録したプレイヤーのキーストロークとそれ以外の操作
+    replay_key_strokes: Vec<ReplayKeyStroke>,
+    replay_operations: Vec<(usize, ReplayOperation)>,
 }
 
 impl TypingEngine {
@@ -84,50 +303,504 @@ impl TypingEngine {
         Self {
             state: TypingEngineState::Uninitialized,
             start_time: None,
+            clock_start_offset: None,
+            first_stroke_starts_clock: false,
+            clock: EngineClock::default(),
             processed_chunk_info: None,
             vocabulary_infos: None,
+            vocabulary_end_chunk_counts: vec![],
+            lap_request: None,
+            life_config: None,
+            life_manager: None,
+            finish_time: None,
+            time_limit: None,
+            is_time_limit_reached: false,
+            termination_condition: None,
+            keyboard_layout: KeyboardLayout::default(),
+            #[cfg(feature = "analysis")]
+            finger_map: None,
+            #[cfg(feature = "statistics")]
+            accumulated_statistics: AccumulatedResultStatistics::default(),
+            input_scheme: InputScheme::default(),
+            romanization_preference: None,
+            candidate_window: None,
+            target_kpm: None,
+            idle_threshold: None,
+            strict_mode: false,
+            shift_key_mode: false,
+            correction_manager: None,
+            wrong_state_events: vec![],
+            events: vec![],
+            #[cfg(feature = "statistics")]
+            completed_lap_count: 0,
+            display_cache: DisplayCache::default(),
+            init_duration: None,
+            premature_miss_count: 0,
+            miss_count: 0,
+            assisted_key_stroke_count: 0,
+            skipped_vocabulary_count: 0,
+            hint_counts: BTreeMap::new(),
+            shift_key_stroke_count: 0,
+            replay_key_strokes: vec![],
+            replay_operations: vec![],
+        }
+    }
+
+    /// Construct an empty engine which uses `input_scheme` to construct key strokes of query.
+    ///
+    /// See [`new`](Self::new()) for details.
+    pub fn new_with_scheme(input_scheme: InputScheme) -> Self {
+        Self {
+            input_scheme,
+            ..Self::new()
+        }
+    }
+
+    /// Construct an empty engine which uses user-defined dictionary to construct key strokes of
+    /// query.
+    ///
+    /// This is a shorthand of [`new_with_scheme`](Self::new_with_scheme()) with
+    /// [`InputScheme::Custom`].
+    pub fn with_custom_dictionary(custom_dictionary: CustomDictionary) -> Self {
+        Self::new_with_scheme(InputScheme::Custom(custom_dictionary))
+    }
+
+    /// Get [`InputScheme`] used by this engine.
+    pub fn input_scheme(&self) -> &InputScheme {
+        &self.input_scheme
+    }
+
+    /// Configure [`RomanizationPreference`] used to construct key strokes of query.
+    ///
+    /// Preference affects queries constructed by subsequent [`init`](Self::init()) and
+    /// [`append_query`](Self::append_query()) methods. `None` disables preference.
+    pub fn set_romanization_preference(
+        &mut self,
+        romanization_preference: Option<RomanizationPreference>,
+    ) {
+        self.romanization_preference = romanization_preference;
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Configure [`RomanizationPreference`] to prefer the style the user habitually types.
+    ///
+    /// Ideal key strokes of subsequent queries follow the habit when key stroke counts tie.
+    /// If neither style is habitual, preference is disabled.
+    /// See [`set_romanization_preference`](Self::set_romanization_preference()) for details.
+    pub fn set_romanization_preference_by_habit(&mut self, romanization_habit: &RomanizationHabit) {
+        self.set_romanization_preference(romanization_habit.preference());
+    }
+
+    /// Get [`RomanizationPreference`] used to construct key strokes of query.
+    pub fn romanization_preference(&self) -> Option<&RomanizationPreference> {
+        self.romanization_preference.as_ref()
+    }
+
+    /// Configure the number of chunks ahead of the cursor which keep key stroke candidates.
+    ///
+    /// When configured, chunks beyond the window keep only their spells and key stroke
+    /// candidates are constructed again as the cursor approaches them, which reduces memory of
+    /// huge or endless queries.
+    /// Spells beyond the window are displayed but their key strokes are not, and they are not
+    /// targets of statistics until their candidates are constructed.
+    ///
+    /// Window affects queries constructed by subsequent [`init`](Self::init()) method.
+    /// `None` keeps candidates of all chunks.
+    pub fn set_candidate_window(&mut self, candidate_window: Option<NonZeroUsize>) {
+        self.candidate_window = candidate_window;
+    }
+
+    /// Get the number of chunks ahead of the cursor which keep key stroke candidates.
+    pub fn candidate_window(&self) -> Option<NonZeroUsize> {
+        self.candidate_window
+    }
+
+    /// Configure target key strokes per minute to which progress is compared in
+    /// [`DisplayInfo::pace_info`](DisplayInfo::pace_info()).
+    ///
+    /// This method can be called at any time even while typing.
+    /// `None` disables the comparison.
+    pub fn set_target_kpm(&mut self, target_kpm: Option<NonZeroUsize>) {
+        self.target_kpm = target_kpm;
+    }
+
+    /// Configure target words per minute to which progress is compared.
+    ///
+    /// This is a shorthand of [`set_target_kpm`](Self::set_target_kpm()) regarding a word as 5
+    /// key strokes.
+    pub fn set_target_wpm(&mut self, target_wpm: Option<NonZeroUsize>) {
+        self.set_target_kpm(
+            target_wpm.and_then(|target_wpm| target_wpm.checked_mul(NonZeroUsize::new(5).unwrap())),
+        );
+    }
+
+    /// Get target key strokes per minute.
+    pub fn target_kpm(&self) -> Option<NonZeroUsize> {
+        self.target_kpm
+    }
+
+    /// Configure threshold of interval between key strokes beyond which player is regarded as
+    /// idle.
+    ///
+    /// When no key stroke arrives within `idle_threshold`, the time beyond the threshold is
+    /// reported as [`idle_time`](TypingResultStatistics::idle_time()) in result statistics, so
+    /// speed can be calculated excluding the time player stepped away.
+    /// Intervals from start to the first key stroke and from the last key stroke to the end are
+    /// also considered.
+    ///
+    /// This method can be called at any time even while typing.
+    /// `None` disables idle detection.
+    pub fn set_idle_threshold(&mut self, idle_threshold: Option<Duration>) {
+        self.idle_threshold = idle_threshold;
+    }
+
+    /// Get threshold of interval between key strokes beyond which player is regarded as idle.
+    pub fn idle_threshold(&self) -> Option<Duration> {
+        self.idle_threshold
+    }
+
+    /// Configure [`Clock`] used to measure elapsed time by engine itself.
+    ///
+    /// Methods without explicit elapsed time such as [`stroke_key`](Self::stroke_key()) measure
+    /// elapsed time from [`start`](Self::start()) method by this clock.
+    /// Default clock is `StdClock` if `std_clock` feature is enabled, otherwise a clock which
+    /// never advances, so elapsed time must be given explicitly or clock must be configured.
+    ///
+    /// Clock should not be changed while typing is in progress because start time is measured
+    /// by previous clock.
+    pub fn set_clock<C: Clock + Send + Sync + 'static>(&mut self, clock: C) {
+        self.clock = EngineClock::new(clock);
+    }
+
+    /// Construct an empty engine configured by `profile`.
+    ///
+    /// See [`new`](Self::new()) and [`apply_profile`](Self::apply_profile()) for details.
+    pub fn new_with_profile(profile: PlayerProfile) -> Self {
+        let mut engine = Self::new();
+        engine.apply_profile(profile).unwrap();
+
+        engine
+    }
+
+    /// Apply configurations and accumulated statistics of `profile`.
+    ///
+    /// Configurations not contained in `profile` such as lives economy or candidate window are
+    /// cleared, so this is useful to switch players between sessions.
+    /// Settings of previous player are not kept even if they are not configured in `profile`.
+    /// Input scheme of `profile` is used to construct query by subsequent
+    /// [`init`](Self::init()) method.
+    ///
+    /// If this method is called after starting, this method returns error.
+    pub fn apply_profile(&mut self, profile: PlayerProfile) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyStarted))
+        } else {
+            self.input_scheme = profile.input_scheme().clone();
+            self.romanization_preference = profile.romanization_preference().copied();
+            self.keyboard_layout = profile.keyboard_layout().clone();
+            self.strict_mode = profile.strict_mode();
+            self.shift_key_mode = profile.shift_key_mode();
+            self.candidate_window = profile.candidate_window();
+            self.target_kpm = profile.target_kpm();
+            self.idle_threshold = profile.idle_threshold();
+            self.first_stroke_starts_clock = profile.first_stroke_starts_clock();
+            self.life_config = profile.life_config().cloned();
+            self.termination_condition = profile.termination_condition().copied();
+            self.lap_request = profile.lap_request().cloned();
+            #[cfg(feature = "statistics")]
+            {
+                self.accumulated_statistics = profile.accumulated_statistics().clone();
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Export current configurations and accumulated statistics as [`PlayerProfile`] named
+    /// `name`.
+    pub fn export_profile(&self, name: String) -> PlayerProfile {
+        let mut profile = PlayerProfile::new(name)
+            .with_input_scheme(self.input_scheme.clone())
+            .with_keyboard_layout(self.keyboard_layout.clone())
+            .with_strict_mode(self.strict_mode)
+            .with_shift_key_mode(self.shift_key_mode)
+            .with_first_stroke_starts_clock(self.first_stroke_starts_clock);
+
+        #[cfg(feature = "statistics")]
+        {
+            profile = profile.with_accumulated_statistics(self.accumulated_statistics.clone());
+        }
+
+        if let Some(romanization_preference) = self.romanization_preference {
+            profile = profile.with_romanization_preference(romanization_preference);
+        }
+
+        if let Some(candidate_window) = self.candidate_window {
+            profile = profile.with_candidate_window(candidate_window);
+        }
+
+        if let Some(target_kpm) = self.target_kpm {
+            profile = profile.with_target_kpm(target_kpm);
+        }
+
+        if let Some(idle_threshold) = self.idle_threshold {
+            profile = profile.with_idle_threshold(idle_threshold);
+        }
+
+        if let Some(life_config) = self.life_config.as_ref() {
+            profile = profile.with_life_config(life_config.clone());
+        }
+
+        if let Some(termination_condition) = self.termination_condition {
+            profile = profile.with_termination_condition(termination_condition);
+        }
+
+        if let Some(lap_request) = self.lap_request.as_ref() {
+            profile = profile.with_lap_request(lap_request.clone());
         }
+
+        profile
     }
 
     /// Initialize [`TypingEngine`](TypingEngine) by constructing and resetting query using [`QueryRequest`].
-    pub fn init(&mut self, query_request: QueryRequest) {
-        let query = query_request.construct_query();
+    ///
+    /// If query cannot be constructed from `query_request` (ex. vocabulary list is empty), this
+    /// method returns error.
+    /// When the cause is a vocabulary which cannot be typed with input scheme, kind of the error is
+    /// [`TypingEngineErrorKind::UntypeableSpell`].
+    pub fn init(&mut self, query_request: QueryRequest) -> Result<(), TypingEngineError> {
+        let init_start_time = self.clock.now();
+
+        let query = self.construct_query(&query_request)?;
         let (vocabulary_infos, chunks) = query.decompose();
 
+        let mut processed_chunk_info = ProcessedChunkInfo::new(chunks);
+        if let Some(candidate_window) = self.candidate_window {
+            processed_chunk_info = processed_chunk_info.with_candidate_window(
+                candidate_window,
+                self.input_scheme.clone(),
+                self.romanization_preference,
+            );
+        }
+
         self.vocabulary_infos.replace(vocabulary_infos);
-        self.processed_chunk_info
-            .replace(ProcessedChunkInfo::new(chunks));
+        self.processed_chunk_info.replace(processed_chunk_info);
+        self.update_query_caches();
+        self.display_cache.reset_display_string_builders();
+        self.init_duration
+            .replace(self.clock.now().saturating_sub(init_start_time));
 
         self.state = TypingEngineState::Ready;
+
+        Ok(())
+    }
+
+    // クエリ要求からクエリを構築する
+    // 構築できない場合には入力できない綴りが原因かどうかを区別する
+    fn construct_query(&self, query_request: &QueryRequest) -> Result<Query, TypingEngineError> {
+        query_request
+            .construct_query(&self.input_scheme, self.romanization_preference.as_ref())
+            .ok_or_else(|| {
+                if query_request.contains_untypeable_vocabulary(&self.input_scheme) {
+                    TypingEngineError::new(TypingEngineErrorKind::UntypeableSpell)
+                } else {
+                    TypingEngineError::new(TypingEngineErrorKind::InvalidQueryRequest)
+                }
+            })
+    }
+
+    /// Get time taken by last [`init`](Self::init()) to construct query including key stroke
+    /// candidates of all chunks.
+    ///
+    /// This is useful to measure initialization cost of huge queries.
+    /// If engine has never been initialized, this method returns `None`.
+    pub fn init_duration(&self) -> Option<Duration> {
+        self.init_duration
     }
 
     /// Append query using [`QueryRequest`].
     ///
-    /// If this method is called before initializing via calling [`init`](Self::init()) method, this
-    /// method returns error.
+    /// This method can be called while typing is in progress, so new vocabularies can be streamed
+    /// in endless typing.
+    /// Appended vocabularies are also targets of statistics, and typing which is completed is
+    /// resumed from appended vocabularies. Typing finished by [`finish`](Self::finish()) method
+    /// or running out of life is not resumed.
+    ///
+    /// Key strokes of the last vocabulary not yet typed are reconstructed considering appended
+    /// vocabularies, so key strokes such as single `n` for `ん` become available.
+    ///
+    /// If this method is called before initializing via calling [`init`](Self::init()) method or
+    /// query cannot be constructed from `query_request`, this method returns error.
     pub fn append_query(&mut self, query_request: QueryRequest) -> Result<(), TypingEngineError> {
         if self.is_initialized() {
-            assert!(self.processed_chunk_info.is_some());
-            assert!(self.vocabulary_infos.is_some());
+            if self.processed_chunk_info.is_none() || self.vocabulary_infos.is_none() {
+                return Err(TypingEngineError::new(TypingEngineErrorKind::InvalidState));
+            }
 
-            let (mut vocabulary_infos, chunks) = query_request.construct_query().decompose();
+            let query = self.construct_query(&query_request)?;
+            let (mut vocabulary_infos, chunks) = query.decompose();
 
             self.vocabulary_infos
                 .as_mut()
                 .unwrap()
                 .append(&mut vocabulary_infos);
 
-            self.processed_chunk_info
-                .as_mut()
-                .unwrap()
-                .append_chunks(chunks);
+            let processed_chunk_info = self.processed_chunk_info.as_mut().unwrap();
+            processed_chunk_info.reassign_last_chunk_key_stroke(
+                &chunks[0],
+                &self.input_scheme,
+                self.romanization_preference.as_ref(),
+            );
+            processed_chunk_info.append_chunks(chunks);
+            self.update_query_caches();
 
             Ok(())
         } else {
-            Err(TypingEngineError::new(
-                TypingEngineErrorKind::MustBeInitialized,
-            ))
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeInitialized))
+        }
+    }
+
+    /// Remove vocabularies which are not yet typed so that only `remaining` vocabularies follow
+    /// the vocabulary currently typed.
+    ///
+    /// This is useful for dynamic difficulty such as shrinking remaining text when time is
+    /// running out in time attack.
+    /// Separators between vocabularies are also counted as vocabularies.
+    /// Key strokes of the chunks at the new end of query are reconstructed as the end of query.
+    /// If the chunk currently typed becomes the end of query, only its key strokes which cannot
+    /// be typed at the end of query are removed so that typed key strokes remain valid.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method or
+    /// after typing is finished, this method returns error.
+    pub fn truncate_query(&mut self, remaining: usize) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            let processed_chunk_info = self.processed_chunk_info.as_mut().unwrap();
+            let vocabulary_infos = self.vocabulary_infos.as_mut().unwrap();
+            // 打っている最中のチャンクのインデックス
+            let inflight_chunk_index = processed_chunk_info.confirmed_chunks().len();
+
+            // 打っている最中の語彙の後に残す語彙の数だけ語彙とチャンクを残す
+            let mut chunk_count = 0;
+            let mut vocabulary_count = 0;
+            let mut remaining_after_current = None;
+            for vocabulary_info in vocabulary_infos.iter() {
+                if remaining_after_current == Some(0) {
+                    break;
+                }
+
+                chunk_count += vocabulary_info.chunk_count().get();
+                vocabulary_count += 1;
+
+                remaining_after_current = match remaining_after_current {
+                    Some(remaining) => Some(remaining - 1),
+                    None if inflight_chunk_index < chunk_count => Some(remaining),
+                    None => None,
+                };
+            }
+
+            vocabulary_infos.truncate(vocabulary_count);
+            processed_chunk_info.truncate_unprocessed_chunks(
+                chunk_count - inflight_chunk_index - 1,
+                &self.input_scheme,
+                self.romanization_preference.as_ref(),
+            );
+            self.update_query_caches();
+
+            Ok(())
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Hand off the rest of query to a new engine for relay typing.
+    ///
+    /// Returned engine is initialized with the rest of query from the chunk currently typed and
+    /// the same configurations as this engine, so the next player can start typing exactly
+    /// where this player stopped.
+    /// Key strokes of the chunk at the boundary keep the restriction by the previous chunk, ex.
+    /// only `t` for `た` after `っ` typed as `t`.
+    /// A vocabulary which spans the boundary is viewed in both engines but its spells are split.
+    ///
+    /// This engine is finished at the boundary, and results of both engines can be combined by
+    /// [`TypingResultStatistics::merge`].
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// after typing is finished or while the chunk currently typed has key strokes, this method
+    /// returns error.
+    pub fn hand_off(&mut self) -> Result<TypingEngine, TypingEngineError> {
+        if !self.is_started() {
+            return Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted));
+        }
+
+        if self.is_finished() {
+            return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
         }
+
+        let processed_chunk_info = self.processed_chunk_info.as_mut().unwrap();
+        let rest_processed_chunk_info = processed_chunk_info
+            .split_off_from_inflight_chunk()
+            .ok_or(TypingEngineError::new(
+                TypingEngineErrorKind::NotAtChunkBoundary,
+            ))?;
+        let confirmed_chunks = processed_chunk_info.confirmed_chunks();
+        let boundary_chunk_index = confirmed_chunks.len();
+
+        // 境界のチャンクが属する語彙とその先頭のチャンクのインデックスを求める
+        let vocabulary_infos = self.vocabulary_infos.as_mut().unwrap();
+        let mut vocabulary_head_chunk_index = 0;
+        let vocabulary_index = vocabulary_infos
+            .iter()
+            .position(|vocabulary_info| {
+                let next_vocabulary_head_chunk_index =
+                    vocabulary_head_chunk_index + vocabulary_info.chunk_count().get();
+                if boundary_chunk_index < next_vocabulary_head_chunk_index {
+                    true
+                } else {
+                    vocabulary_head_chunk_index = next_vocabulary_head_chunk_index;
+                    false
+                }
+            })
+            .unwrap();
+
+        let mut rest_vocabulary_infos = vocabulary_infos.split_off(vocabulary_index);
+        // 境界をまたぐ語彙は綴りを境界で分割する
+        if vocabulary_head_chunk_index != boundary_chunk_index {
+            let spell_count = confirmed_chunks[vocabulary_head_chunk_index..]
+                .iter()
+                .map(|confirmed_chunk| confirmed_chunk.as_ref().spell().count())
+                .sum();
+            let (former_vocabulary_info, latter_vocabulary_info) = rest_vocabulary_infos[0]
+                .split_at(
+                    NonZeroUsize::new(boundary_chunk_index - vocabulary_head_chunk_index).unwrap(),
+                    spell_count,
+                );
+
+            vocabulary_infos.push(former_vocabulary_info);
+            rest_vocabulary_infos[0] = latter_vocabulary_info;
+        }
+        self.update_query_caches();
+        // 境界をまたぐ語彙は境界で終わるようになるので語彙ごとのラップを取り直す
+        self.display_cache.reset_display_string_builders();
+
+        let mut next_engine = self.clone();
+        next_engine.clear_round();
+        // 集計した統計は引き継がない
+        #[cfg(feature = "statistics")]
+        {
+            next_engine.accumulated_statistics = AccumulatedResultStatistics::default();
+        }
+        next_engine
+            .processed_chunk_info
+            .replace(rest_processed_chunk_info);
+        next_engine.vocabulary_infos.replace(rest_vocabulary_infos);
+        next_engine.update_query_caches();
+        next_engine.state = TypingEngineState::Ready;
+
+        Ok(next_engine)
     }
 
     /// Start typing.
@@ -136,120 +809,6065 @@ impl TypingEngine {
     /// method returns error.
     pub fn start(&mut self) -> Result<(), TypingEngineError> {
         if self.is_initialized() {
-            assert!(self.processed_chunk_info.is_some());
-            assert!(self.vocabulary_infos.is_some());
+            if self.vocabulary_infos.is_none() {
+                return Err(TypingEngineError::new(TypingEngineErrorKind::InvalidState));
+            }
 
             self.processed_chunk_info
                 .as_mut()
-                .unwrap()
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?
                 .move_next_chunk();
 
+            self.life_manager = self.life_config.clone().map(LifeManager::new);
+            self.correction_manager = self.strict_mode.then(CorrectionManager::new);
+            self.wrong_state_events.clear();
+            self.events.clear();
+            #[cfg(feature = "statistics")]
+            {
+                self.completed_lap_count = 0;
+            }
+            self.display_cache.reset_display_string_builders();
+            self.premature_miss_count = 0;
+            self.miss_count = 0;
+            self.assisted_key_stroke_count = 0;
+            self.skipped_vocabulary_count = 0;
+            self.hint_counts.clear();
+            self.shift_key_stroke_count = 0;
+            self.replay_key_strokes.clear();
+            self.replay_operations.clear();
+            self.finish_time = None;
+            self.time_limit = None;
+            self.is_time_limit_reached = false;
+
             self.state = TypingEngineState::Started;
-            self.start_time.replace(Instant::now());
+            self.start_time.replace(self.clock.now());
+            self.clock_start_offset = (!self.first_stroke_starts_clock).then_some(Duration::ZERO);
             Ok(())
         } else {
-            Err(TypingEngineError::new(
-                TypingEngineErrorKind::MustBeInitialized,
-            ))
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeInitialized))
         }
     }
 
+    /// Start typing but begin measuring elapsed time at the first correct key stroke.
+    ///
+    /// This excludes reaction time to start signal such as countdown from statistics.
+    /// Key strokes before the first correct key stroke and the first correct key stroke itself
+    /// are regarded as occurred at zero elapsed time.
+    /// Elapsed time given explicitly such as in
+    /// [`stroke_key_with_elapsed_time`](Self::stroke_key_with_elapsed_time()) is still measured
+    /// from calling this method, and elapsed time of the first correct key stroke is subtracted
+    /// from it.
+    ///
+    /// If this method is called before initializing via calling [`init`](Self::init()) method, this
+    /// method returns error.
+    pub fn arm(&mut self) -> Result<(), TypingEngineError> {
+        self.start()?;
+        self.clock_start_offset = None;
+
+        Ok(())
+    }
+
+    /// Configure whether [`start`](Self::start()) method behaves as [`arm`](Self::arm()) method.
+    ///
+    /// Default is `false`.
+    pub fn set_first_stroke_starts_clock(&mut self, first_stroke_starts_clock: bool) {
+        self.first_stroke_starts_clock = first_stroke_starts_clock;
+    }
+
+    /// Get whether [`start`](Self::start()) method behaves as [`arm`](Self::arm()) method.
+    pub fn first_stroke_starts_clock(&self) -> bool {
+        self.first_stroke_starts_clock
+    }
+
+    /// Get whether the engine is started but waiting for the first correct key stroke to begin
+    /// measuring elapsed time.
+    pub fn is_armed(&self) -> bool {
+        self.is_started() && self.clock_start_offset.is_none()
+    }
+
+    /// Start typing with time limit.
+    ///
+    /// Key strokes at or after `time_limit` from start are not processed, and typing is finished
+    /// at `time_limit` as if [`finish`](Self::finish()) method were called.
+    /// Then [`stroke_key`](Self::stroke_key()) returns `true` and
+    /// [`construst_result_statistics`](Self::construst_result_statistics()) can be called even
+    /// though chunks remain. End reason of result is [`TypingEndReason::TimeLimitReached`].
+    ///
+    /// Time limit is checked only when key stroke is given, so call [`finish`](Self::finish())
+    /// method when [`remaining_time`](Self::remaining_time()) becomes zero without key strokes.
+    ///
+    /// If this method is called before initializing via calling [`init`](Self::init()) method, this
+    /// method returns error.
+    pub fn start_with_limit(&mut self, time_limit: Duration) -> Result<(), TypingEngineError> {
+        self.start()?;
+        self.time_limit.replace(time_limit);
+
+        Ok(())
+    }
+
+    /// Get time limit configured by [`start_with_limit`](Self::start_with_limit()).
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    /// Get remaining time until time limit measured by wall clock from calling
+    /// [`start_with_limit`](Self::start_with_limit()) method.
+    ///
+    /// If time limit is not configured, this method returns `None`.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.time_limit.map(|time_limit| {
+            if self.is_finished() {
+                Duration::ZERO
+            } else {
+                time_limit.saturating_sub(self.display_elapsed_time())
+            }
+        })
+    }
+
     /// Give a key stroke to [`TypingEngine`].
     ///
+    /// Elapsed time of the key stroke is measured by wall clock from calling
+    /// [`start`](Self::start()) method.
+    /// Use [`stroke_key_with_elapsed_time`](Self::stroke_key_with_elapsed_time()) to give
+    /// elapsed time explicitly.
+    ///
+    /// This method returns whether typing is finished by the key stroke.
+    /// Typing is also finished when it is terminated by
+    /// [termination condition](Self::set_termination_condition()).
+    ///
     /// If this method is called before initializing via calling [`start`](Self::start()) method,
     /// this method returns error.
     pub fn stroke_key(&mut self, key_stroke: KeyStrokeChar) -> Result<bool, TypingEngineError> {
         if self.is_started() {
-            let pci = self.processed_chunk_info.as_mut().unwrap();
-            if pci.is_finished() {
-                return Err(TypingEngineError::new(
-                    TypingEngineErrorKind::AlreadyFinished,
-                ));
-            }
+            let elapsed_time = self.elapsed_time_from_start();
 
-            let elapsed_time = self.start_time.as_ref().unwrap().elapsed();
+            self.stroke_key_with_elapsed_time(key_stroke, elapsed_time)
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Give a key stroke which occurred at `elapsed_time` from start to [`TypingEngine`].
+    ///
+    /// This is useful when application has its own clock such as game loop or replay.
+    /// `elapsed_time` should not be less than that of previous key stroke.
+    ///
+    /// If this method is called before initializing via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn stroke_key_with_elapsed_time(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+        elapsed_time: Duration,
+    ) -> Result<bool, TypingEngineError> {
+        self.stroke_key_with_outcome_with_elapsed_time(key_stroke, elapsed_time)
+            .map(|key_stroke_outcome| key_stroke_outcome.is_finished())
+    }
 
-            pci.stroke_key(key_stroke, elapsed_time);
+    /// Give a key stroke to [`TypingEngine`] and get [`KeyStrokeOutcome`] which reports spells,
+    /// chunks, vocabularies and laps completed by the key stroke.
+    ///
+    /// This is the same as [`stroke_key`](Self::stroke_key()) except for return value.
+    pub fn stroke_key_with_outcome(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+    ) -> Result<KeyStrokeOutcome, TypingEngineError> {
+        if self.is_started() {
+            let elapsed_time = self.elapsed_time_from_start();
 
-            Ok(pci.is_finished())
+            self.stroke_key_with_outcome_with_elapsed_time(key_stroke, elapsed_time)
         } else {
-            Err(TypingEngineError::new(TypingEngineErrorKind::MustBeStarted))
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
         }
     }
 
-    /// Construct [`DisplayInfo`] for composing UI.
+    /// Give a key stroke which occurred at `elapsed_time` from start to [`TypingEngine`] and get
+    /// [`KeyStrokeOutcome`].
     ///
-    /// If this method is called before starting via calling [`start`](Self::start()) method,
-    /// this method returns error.
-    pub fn construct_display_info(
-        &self,
-        lap_request: LapRequest,
-    ) -> Result<DisplayInfo, TypingEngineError> {
+    /// This is the same as [`stroke_key_with_elapsed_time`](Self::stroke_key_with_elapsed_time())
+    /// except for return value.
+    pub fn stroke_key_with_outcome_with_elapsed_time(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+        elapsed_time: Duration,
+    ) -> Result<KeyStrokeOutcome, TypingEngineError> {
+        let key_stroke_outcome = self.stroke_key_inner(key_stroke.clone(), elapsed_time)?;
+
+        // 制限時間を過ぎて処理されなかったキーストロークは記録しない
+        if !self.is_time_limit_reached {
+            self.replay_key_strokes
+                .push(ReplayKeyStroke::new(elapsed_time, key_stroke));
+        }
+
+        Ok(key_stroke_outcome)
+    }
+
+    // キーストロークを処理する
+    // 自動補完などで与えるキーストロークはプレイヤーのキーストロークとしては記録しないのでこれを直接呼ぶ
+    fn stroke_key_inner(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+        elapsed_time: Duration,
+    ) -> Result<KeyStrokeOutcome, TypingEngineError> {
         if self.is_started() {
-            let (spell_display_info, key_stroke_display_info) = self
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            let elapsed_time_from_start = elapsed_time;
+            let elapsed_time = self.elapsed_time_from_clock_start(elapsed_time);
+
+            // 制限時間を過ぎたキーストロークは処理せずに制限時間で終了する
+            if let Some(time_limit) = self.time_limit {
+                if elapsed_time >= time_limit {
+                    self.finish_time.replace(time_limit);
+                    self.is_time_limit_reached = true;
+                    return Ok(KeyStrokeOutcome::time_limit_reached());
+                }
+            }
+
+            let processed_chunk_info = self
                 .processed_chunk_info
+                .as_mut()
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?;
+            let confirmed_chunk_count = processed_chunk_info.confirmed_chunks().len();
+            let finished_spell_count =
+                processed_chunk_info.finished_spell_count_from(confirmed_chunk_count);
+            let event_count = self.events.len();
+
+            // 厳格モードでは訂正されていないミスタイプがある限り先に進めない
+            let key_stroke_result = if self
+                .correction_manager
                 .as_ref()
-                .unwrap()
-                // XXX 引数で指定するようにする
-                .construct_display_info(lap_request);
+                .is_some_and(|correction_manager| correction_manager.has_uncorrected())
+            {
+                processed_chunk_info.stroke_wrong_key(key_stroke.clone(), elapsed_time);
+                KeyStrokeResult::Wrong
+            } else {
+                // キーストロークするとチャンクが進む可能性があるので先に判定しておく
+                let is_next_chunk_head_key_stroke =
+                    processed_chunk_info.is_next_chunk_head_key_stroke(&key_stroke);
 
-            let view_position_of_spell_position =
-                construct_view_position_of_spell_positions(self.vocabulary_infos.as_ref().unwrap());
+                let key_stroke_result =
+                    processed_chunk_info.stroke_key(key_stroke.clone(), elapsed_time);
 
-            let view = self
-                .vocabulary_infos
-                .as_ref()
-                .unwrap()
-                .iter()
-                .map(|vocabulary_info| vocabulary_info.view().to_string())
-                .reduce(|accum, item| accum + &item)
-                .unwrap();
+                if key_stroke_result == KeyStrokeResult::Wrong && is_next_chunk_head_key_stroke {
+                    self.premature_miss_count += 1;
+                }
+
+                key_stroke_result
+            };
+
+            // 最初の正しいキーストロークの時点から時計を開始する
+            if self.clock_start_offset.is_none() && key_stroke_result == KeyStrokeResult::Correct {
+                self.clock_start_offset.replace(elapsed_time_from_start);
+            }
 
-            let view_display_info =
-                ViewDisplayInfo::new(&spell_display_info, view, view_position_of_spell_position);
+            if let Some(correction_manager) = self.correction_manager.as_mut() {
+                if key_stroke_result == KeyStrokeResult::Wrong {
+                    correction_manager.on_wrong();
+                }
+            }
+
+            if key_stroke_result == KeyStrokeResult::Wrong {
+                self.miss_count += 1;
+            }
+
+            // シフトキーモードでは大文字などは物理的にはシフトキーとの2打鍵とみなす
+            if self.shift_key_mode
+                && key_stroke_result == KeyStrokeResult::Correct
+                && key_stroke.is_shifted()
+            {
+                self.shift_key_stroke_count += 1;
+            }
+
+            self.display_cache.apply_confirmed_chunks(
+                self.processed_chunk_info.as_ref().unwrap(),
+                &self.vocabulary_end_chunk_counts,
+            );
+            self.record_events(key_stroke, &key_stroke_result, confirmed_chunk_count);
+
+            if let Some(life_manager) = self.life_manager.as_mut() {
+                match key_stroke_result {
+                    KeyStrokeResult::Correct => life_manager.on_correct(),
+                    KeyStrokeResult::Wrong => life_manager.on_wrong(),
+                }
+            }
 
-            Ok(DisplayInfo::new(
-                view_display_info,
-                spell_display_info,
-                key_stroke_display_info,
+            Ok(self.construct_key_stroke_outcome(
+                &key_stroke_result,
+                confirmed_chunk_count,
+                finished_spell_count,
+                event_count,
             ))
         } else {
-            Err(TypingEngineError::new(TypingEngineErrorKind::MustBeStarted))
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
         }
     }
 
-    pub fn construst_result_statistics(
-        &self,
-        lap_request: LapRequest,
-    ) -> Result<TypingResultStatistics, TypingEngineError> {
+    /// Complete the chunk currently typed by injecting remaining key strokes as correct key strokes.
+    ///
+    /// This is useful for power-ups such as "auto-finish word" in arcade games.
+    /// Injected key strokes are chosen by `policy` and processed as if they were typed, so
+    /// statistics, laps, life and events are kept consistent.
+    /// They are also counted as [`assisted_key_stroke_count`](TypingResultStatistics::assisted_key_stroke_count())
+    /// in statistics.
+    ///
+    /// Elapsed time of injected key strokes is measured by wall clock from calling
+    /// [`start`](Self::start()) method.
+    /// Use [`auto_complete_current_chunk_with_elapsed_time`](Self::auto_complete_current_chunk_with_elapsed_time())
+    /// to give elapsed time explicitly.
+    ///
+    /// This method returns whether typing is finished like [`stroke_key`](Self::stroke_key()).
+    /// If this method is called before starting via calling [`start`](Self::start()) method or
+    /// after typing is finished, this method returns error.
+    /// In strict mode, this method also returns error if wrong key strokes are not corrected.
+    pub fn auto_complete_current_chunk(
+        &mut self,
+        policy: AutoCompletePolicy,
+    ) -> Result<bool, TypingEngineError> {
         if self.is_started() {
-            let confirmed_chunks = self
-                .processed_chunk_info
-                .as_ref()
-                .unwrap()
-                .confirmed_chunks();
+            let elapsed_time = self.elapsed_time_from_start();
 
-            if self.processed_chunk_info.as_ref().unwrap().is_finished() {
-                Ok(construct_result(confirmed_chunks, lap_request))
-            } else {
-                Err(TypingEngineError::new(TypingEngineErrorKind::NotFinished))
-            }
+            self.auto_complete_current_chunk_with_elapsed_time(policy, elapsed_time)
         } else {
-            Err(TypingEngineError::new(TypingEngineErrorKind::MustBeStarted))
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
         }
     }
 
-    fn is_initialized(&self) -> bool {
-        !matches!(self.state, TypingEngineState::Uninitialized)
-    }
-
+    /// Complete the chunk currently typed by injecting remaining key strokes which occurred at
+    /// `elapsed_time` from start.
+    ///
+    /// See [`auto_complete_current_chunk`](Self::auto_complete_current_chunk()) for details.
+    pub fn auto_complete_current_chunk_with_elapsed_time(
+        &mut self,
+        policy: AutoCompletePolicy,
+        elapsed_time: Duration,
+    ) -> Result<bool, TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            // 訂正されていないミスタイプがあると与えたキーストロークも全てミスタイプになってしまう
+            if self
+                .correction_manager
+                .as_ref()
+                .is_some_and(|correction_manager| correction_manager.has_uncorrected())
+            {
+                return Err(TypingEngineError::new(
+                    TypingEngineErrorKind::MustBeCorrected,
+                ));
+            }
+
+            let confirmed_chunk_count = self
+                .processed_chunk_info
+                .as_ref()
+                .unwrap()
+                .confirmed_chunks()
+                .len();
+
+            // 通常のキーストロークと同じ経路で与えることで統計やラップ，ライフの整合性を保つ
+            // 制限時間を過ぎている場合にはキーストロークが処理されずに終了する
+            while !self.is_finished()
+                && self
+                    .processed_chunk_info
+                    .as_ref()
+                    .unwrap()
+                    .confirmed_chunks()
+                    .len()
+                    == confirmed_chunk_count
+            {
+                let key_stroke = self
+                    .processed_chunk_info
+                    .as_ref()
+                    .unwrap()
+                    .inflight_chunk()
+                    .unwrap()
+                    .next_completion_key_stroke(policy.prefers_ideal());
+
+                self.stroke_key_inner(key_stroke, elapsed_time)?;
+                self.assisted_key_stroke_count += 1;
+            }
+
+            self.record_replay_operation(ReplayOperation::AutoComplete {
+                policy,
+                elapsed_time,
+            });
+
+            Ok(self.is_finished())
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Skip the vocabulary currently typed and advance to the next vocabulary.
+    ///
+    /// This is useful for "don't know this word" button in learning apps.
+    /// Each remaining key stroke of the vocabulary is deemed finished with a wrong key stroke, so
+    /// remaining spells are shown as missed and statistics are kept consistent.
+    /// Injected correct key strokes are counted as
+    /// [`assisted_key_stroke_count`](TypingResultStatistics::assisted_key_stroke_count()), and
+    /// skip itself is counted as
+    /// [`skipped_vocabulary_count`](TypingResultStatistics::skipped_vocabulary_count()).
+    /// Injected wrong key strokes do not affect life or
+    /// [termination condition](Self::set_termination_condition()).
+    ///
+    /// Elapsed time of injected key strokes is measured by wall clock from calling
+    /// [`start`](Self::start()) method.
+    /// Use [`skip_current_vocabulary_with_elapsed_time`](Self::skip_current_vocabulary_with_elapsed_time())
+    /// to give elapsed time explicitly.
+    ///
+    /// This method returns whether typing is finished like [`stroke_key`](Self::stroke_key()).
+    /// If this method is called before starting via calling [`start`](Self::start()) method or
+    /// after typing is finished, this method returns error.
+    /// In strict mode, this method also returns error if wrong key strokes are not corrected.
+    pub fn skip_current_vocabulary(&mut self) -> Result<bool, TypingEngineError> {
+        if self.is_started() {
+            let elapsed_time = self.elapsed_time_from_start();
+
+            self.skip_current_vocabulary_with_elapsed_time(elapsed_time)
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Skip the vocabulary currently typed at `elapsed_time` from start.
+    ///
+    /// See [`skip_current_vocabulary`](Self::skip_current_vocabulary()) for details.
+    pub fn skip_current_vocabulary_with_elapsed_time(
+        &mut self,
+        elapsed_time: Duration,
+    ) -> Result<bool, TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            if self
+                .correction_manager
+                .as_ref()
+                .is_some_and(|correction_manager| correction_manager.has_uncorrected())
+            {
+                return Err(TypingEngineError::new(
+                    TypingEngineErrorKind::MustBeCorrected,
+                ));
+            }
+
+            let confirmed_chunk_count = self
+                .processed_chunk_info
+                .as_ref()
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?
+                .confirmed_chunks()
+                .len();
+
+            // タイプ中のチャンクが属する語彙の末尾までのチャンク数
+            let vocabulary_end_chunk_count = self
+                .vocabulary_infos
+                .as_ref()
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?
+                .iter()
+                .scan(0, |chunk_count, vocabulary_info| {
+                    *chunk_count += vocabulary_info.chunk_count().get();
+                    Some(*chunk_count)
+                })
+                .find(|chunk_count| *chunk_count > confirmed_chunk_count)
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?;
+
+            let is_time_limit_reached = self.time_limit.is_some_and(|time_limit| {
+                self.elapsed_time_from_clock_start(elapsed_time) >= time_limit
+            });
+
+            // 残りのキーストロークそれぞれの前にミスタイプを記録してから自動補完と同じ経路で与える
+            // ミスタイプはライフや打ち切り条件には影響させないので直接記録する
+            while !self.is_finished()
+                && self
+                    .processed_chunk_info
+                    .as_ref()
+                    .unwrap()
+                    .confirmed_chunks()
+                    .len()
+                    < vocabulary_end_chunk_count
+            {
+                let clock_elapsed_time = self.elapsed_time_from_clock_start(elapsed_time);
+                let processed_chunk_info = self.processed_chunk_info.as_mut().unwrap();
+                let key_stroke = processed_chunk_info
+                    .inflight_chunk()
+                    .unwrap()
+                    .next_completion_key_stroke(false);
+
+                if !is_time_limit_reached {
+                    processed_chunk_info.stroke_wrong_key(key_stroke.clone(), clock_elapsed_time);
+                }
+
+                self.stroke_key_inner(key_stroke, elapsed_time)?;
+                self.assisted_key_stroke_count += 1;
+            }
+
+            if !is_time_limit_reached {
+                self.skipped_vocabulary_count += 1;
+            }
+
+            self.record_replay_operation(ReplayOperation::SkipVocabulary { elapsed_time });
+
+            Ok(self.is_finished())
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Get the next key stroke to type as a hint.
+    ///
+    /// This is useful for tutoring modes which show hints after a stall.
+    /// The key stroke is the next one of ideal key strokes of the chunk currently typed if they are
+    /// still consistent with typed key strokes, otherwise the next one of key strokes which need
+    /// the least remaining key strokes.
+    /// Each call is counted as usage of hint for the spell currently typed, and usage is reported
+    /// by [`spell_hints`](TypingResultStatistics::spell_hints()) in statistics.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method or
+    /// after typing is finished, this method returns error.
+    /// In strict mode, this method also returns error if wrong key strokes are not corrected.
+    pub fn hint(&mut self) -> Result<KeyStrokeChar, TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            // 訂正されていないミスタイプがある場合には次に打つべきなのは訂正である
+            if self
+                .correction_manager
+                .as_ref()
+                .is_some_and(|correction_manager| correction_manager.has_uncorrected())
+            {
+                return Err(TypingEngineError::new(
+                    TypingEngineErrorKind::MustBeCorrected,
+                ));
+            }
+
+            let processed_chunk_info = self
+                .processed_chunk_info
+                .as_ref()
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?;
+            let key_stroke = processed_chunk_info
+                .inflight_chunk()
+                .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidState))?
+                .next_completion_key_stroke(true);
+
+            *self
+                .hint_counts
+                .entry(processed_chunk_info.confirmed_chunks().len())
+                .or_default() += 1;
+
+            // 再生時にもヒントの使用を統計に反映できるように記録する
+            self.record_replay_operation(ReplayOperation::Hint);
+
+            Ok(key_stroke)
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Get key strokes which are accepted as correct key strokes if they are typed next.
+    ///
+    /// All remaining candidates of the chunk currently typed are considered, so the result is
+    /// useful to highlight keys of an on-screen keyboard.
+    /// When a candidate which is confirmed by the head of the next chunk such as `n` of `ん` has
+    /// been typed, head key strokes of the next chunk are also contained.
+    ///
+    /// If typing is not started or already finished, or wrong key strokes are not corrected in
+    /// strict mode, no key stroke is accepted and this method returns empty vector.
+    pub fn current_acceptable_key_strokes(&self) -> Vec<KeyStrokeChar> {
+        if !self.is_started() || self.is_finished() {
+            return vec![];
+        }
+
+        // 厳格モードでは訂正されていないミスタイプがある限りどのキーストロークもミスタイプになる
+        if self
+            .correction_manager
+            .as_ref()
+            .is_some_and(|correction_manager| correction_manager.has_uncorrected())
+        {
+            return vec![];
+        }
+
+        self.processed_chunk_info
+            .as_ref()
+            .unwrap()
+            .inflight_chunk()
+            .map_or(vec![], |inflight_chunk| {
+                inflight_chunk.acceptable_key_strokes()
+            })
+    }
+
+    /// Get candidates of key strokes which remain for the chunk currently typed.
+    ///
+    /// Candidates which do not match key strokes typed so far are already removed.
+    /// This is useful to show alternative key strokes such as `zi` and `ji` of `じ`.
+    ///
+    /// If typing is not started or already finished, this method returns empty vector.
+    pub fn inflight_candidates(&self) -> Vec<CandidateView> {
+        if !self.is_started() || self.is_finished() {
+            return vec![];
+        }
+
+        self.processed_chunk_info
+            .as_ref()
+            .unwrap()
+            .inflight_chunk()
+            .map_or(vec![], |inflight_chunk| {
+                let chunk = inflight_chunk.as_ref();
+                let ideal_key_stroke = chunk
+                    .ideal_key_stroke_candidate()
+                    .as_ref()
+                    .map(|ideal_candidate| ideal_candidate.whole_key_stroke());
+
+                chunk
+                    .key_stroke_candidates()
+                    .as_ref()
+                    .unwrap()
+                    .iter()
+                    .zip(inflight_chunk.cursor_positions_of_candidates())
+                    .map(|(candidate, cursor_position)| {
+                        CandidateView::new(
+                            candidate
+                                .key_stroke_element_strs()
+                                .map(|key_stroke_element| key_stroke_element.to_string())
+                                .collect(),
+                            *cursor_position,
+                            ideal_key_stroke.as_ref().is_some_and(|ideal_key_stroke| {
+                                *ideal_key_stroke == candidate.whole_key_stroke()
+                            }),
+                        )
+                    })
+                    .collect()
+            })
+    }
+
+    /// Give a key stroke of physical key to [`TypingEngine`].
+    ///
+    /// Physical key is translated to key stroke according to keyboard layout configured by
+    /// [`set_keyboard_layout`](Self::set_keyboard_layout()) and then given to
+    /// [`stroke_key`](Self::stroke_key()) method.
+    pub fn stroke_physical_key(
+        &mut self,
+        physical_key: &PhysicalKey,
+    ) -> Result<bool, TypingEngineError> {
+        self.stroke_key(self.keyboard_layout.translate(physical_key))
+    }
+
+    /// Give a key stroke with modifier state of Shift key to [`TypingEngine`].
+    ///
+    /// When `shift` is `true`, `key_stroke` is regarded as typed with Shift key held down and is
+    /// translated according to keyboard layout configured by
+    /// [`set_keyboard_layout`](Self::set_keyboard_layout()).
+    /// ex. `'h'` with Shift key is given as `'H'`.
+    /// Then translated key stroke is given to [`stroke_key`](Self::stroke_key()) method.
+    pub fn stroke_key_with_shift(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+        shift: bool,
+    ) -> Result<bool, TypingEngineError> {
+        let key_stroke = self.shifted_key_stroke(key_stroke, shift);
+        self.stroke_key(key_stroke)
+    }
+
+    /// Give a key stroke with modifier state of Shift key which occurred at `elapsed_time` from
+    /// start to [`TypingEngine`].
+    ///
+    /// See [`stroke_key_with_shift`](Self::stroke_key_with_shift()) and
+    /// [`stroke_key_with_elapsed_time`](Self::stroke_key_with_elapsed_time()) for details.
+    pub fn stroke_key_with_shift_with_elapsed_time(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+        shift: bool,
+        elapsed_time: Duration,
+    ) -> Result<bool, TypingEngineError> {
+        let key_stroke = self.shifted_key_stroke(key_stroke, shift);
+        self.stroke_key_with_elapsed_time(key_stroke, elapsed_time)
+    }
+
+    fn shifted_key_stroke(&self, key_stroke: KeyStrokeChar, shift: bool) -> KeyStrokeChar {
+        if shift {
+            self.keyboard_layout.shift(&key_stroke)
+        } else {
+            key_stroke
+        }
+    }
+
+    /// Configure [`KeyboardLayout`] used in [`stroke_physical_key`](Self::stroke_physical_key()).
+    ///
+    /// QWERTY layout is used by default.
+    pub fn set_keyboard_layout(&mut self, keyboard_layout: KeyboardLayout) {
+        self.keyboard_layout = keyboard_layout;
+    }
+
+    /// Get configured [`KeyboardLayout`].
+    pub fn keyboard_layout(&self) -> &KeyboardLayout {
+        &self.keyboard_layout
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Configure [`FingerMap`] used to aggregate
+    /// [`finger_statistics`](TypingResultStatistics::finger_statistics()) of result.
+    ///
+    /// Standard touch typing on configured [`KeyboardLayout`] is used by default.
+    pub fn set_finger_map(&mut self, finger_map: FingerMap) {
+        self.finger_map.replace(finger_map);
+    }
+
+    /// Finish typing at `elapsed_time` from start even if whole query is not typed.
+    ///
+    /// Remaining targets are regarded as unfinished and `elapsed_time` is used as total time of
+    /// typing, so this is useful when typing is finished by time limit.
+    /// After calling this method, [`construst_result_statistics`](Self::construst_result_statistics())
+    /// can be called.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method or
+    /// after typing is finished, this method returns error.
+    pub fn finish(&mut self, elapsed_time: Duration) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            self.finish_time
+                .replace(self.elapsed_time_from_clock_start(elapsed_time));
+
+            Ok(())
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Construct [`DisplayInfo`] for composing UI.
+    ///
+    /// Speed in [`DisplayInfo`] is measured at wall clock time from calling
+    /// [`start`](Self::start()) method, or at the end of typing if typing is finished.
+    /// Use [`construct_display_info_with_elapsed_time`](Self::construct_display_info_with_elapsed_time())
+    /// to give elapsed time explicitly.
+    ///
+    /// When `lap_request` is the same as one configured by
+    /// [`set_lap_request`](Self::set_lap_request()), confirmed chunks already reflected on each
+    /// key stroke are reused, so this method does not walk whole key stroke history on every call.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn construct_display_info(
+        &self,
+        lap_request: LapRequest,
+    ) -> Result<DisplayInfo, TypingEngineError> {
+        self.construct_display_info_with_lap_requests(&[lap_request])
+    }
+
+    /// Construct [`DisplayInfo`] which contains laps taken by each of `lap_requests`.
+    ///
+    /// Laps of every lap request are available via [`DisplayInfo::lap_infos`], so laps on
+    /// different targets such as key strokes and spell can be shown at once.
+    /// Statistics embedded in [`SpellDisplayInfo`](crate::display_info::SpellDisplayInfo) and
+    /// [`KeyStrokeDisplayInfo`](crate::display_info::KeyStrokeDisplayInfo) take laps by the
+    /// first lap request.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method or
+    /// `lap_requests` is empty, this method returns error.
+    pub fn construct_display_info_with_lap_requests(
+        &self,
+        lap_requests: &[LapRequest],
+    ) -> Result<DisplayInfo, TypingEngineError> {
+        if self.is_started() {
+            self.construct_display_info_inner(
+                lap_requests,
+                self.display_elapsed_time(),
+                DisplayStringBuffers::default(),
+            )
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Construct [`DisplayInfo`] into `display_info` reusing its memory.
+    ///
+    /// This is the same as [`construct_display_info`](Self::construct_display_info()) except that
+    /// strings whose length is proportional to query such as spell and key strokes are written
+    /// into memory of `display_info` constructed previously.
+    /// This is useful for very long queries displayed every frame.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn construct_display_info_reusing(
+        &self,
+        lap_request: LapRequest,
+        display_info: &mut DisplayInfo,
+    ) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            let display_string_buffers = display_info.take_string_buffers();
+            *display_info = self.construct_display_info_inner(
+                &[lap_request],
+                self.display_elapsed_time(),
+                display_string_buffers,
+            )?;
+
+            Ok(())
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Construct [`WindowedDisplayInfo`] which contains spell and key strokes only in window
+    /// specified by `window_request`.
+    ///
+    /// Positions such as cursor positions are relative to head of window, so UI does not have to
+    /// slice strings and convert positions by itself.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn construct_display_info_window(
+        &self,
+        window_request: DisplayWindowRequest,
+    ) -> Result<WindowedDisplayInfo, TypingEngineError> {
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            // ラップの情報は使わないのでラップ要求は何でも良い
+            let (spell_display_info, key_stroke_display_info) = processed_chunk_info
+                .construct_display_info(
+                    LapRequest::Chunk(NonZeroUsize::MIN),
+                    &self.vocabulary_end_chunk_counts,
+                );
+            let spell_display_info =
+                spell_display_info.with_spell(self.display_cache.presentation_spell().to_string());
+
+            let (spell_chunk_heads, key_stroke_chunk_heads) =
+                processed_chunk_info.construct_chunk_head_positions();
+
+            Ok(WindowedDisplayInfo::new(
+                &spell_display_info,
+                &key_stroke_display_info,
+                &window_request,
+                processed_chunk_info.confirmed_chunks().len(),
+                &spell_chunk_heads,
+                &key_stroke_chunk_heads,
+            ))
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Construct [`DisplayInfoDelta`] which tells what is changed since last call of this method.
+    ///
+    /// Unlike [`construct_display_info`](Self::construct_display_info()), this method returns
+    /// only changed ranges of strings, so this is suitable for long queries which are displayed
+    /// every frame.
+    /// Strings of confirmed chunks and unprocessed chunks apart from the chunk currently typed
+    /// are not sent again unless query or key stroke candidates of them are changed.
+    /// This method shares intermediate state built from confirmed chunks with
+    /// [`construct_display_info`](Self::construct_display_info()).
+    ///
+    /// After starting, clearing round or handing off, the first delta replaces whole strings.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn display_info_delta(
+        &mut self,
+        lap_request: LapRequest,
+    ) -> Result<DisplayInfoDelta, TypingEngineError> {
+        if self.is_started() {
+            let elapsed_time = self.display_elapsed_time();
+            let processed_chunk_info = self.processed_chunk_info.as_mut().unwrap();
+
+            let (ideal_cursor_position, ideal_key_stroke_count) =
+                processed_chunk_info.ideal_key_stroke_progress();
+            let progress_display_info = processed_chunk_info
+                .construct_progress_display_info()
+                .with_ideal_key_stroke_progress(
+                    ideal_cursor_position,
+                    ideal_key_stroke_count,
+                    elapsed_time,
+                );
+            let speed_display_info =
+                processed_chunk_info.construct_speed_display_info(elapsed_time);
+            let pace_display_info = self.target_kpm.map(|target_kpm| {
+                PaceDisplayInfo::new(
+                    target_kpm,
+                    elapsed_time,
+                    ideal_cursor_position,
+                    ideal_key_stroke_count,
+                )
+            });
+
+            Ok(self.display_cache.construct_display_info_delta(
+                &lap_request,
+                processed_chunk_info,
+                &self.vocabulary_end_chunk_counts,
+                progress_display_info,
+                speed_display_info,
+                pace_display_info,
+            ))
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    // 時計で計測した開始からの経過時間
+    fn elapsed_time_from_start(&self) -> Duration {
+        self.clock.now().saturating_sub(self.start_time.unwrap())
+    }
+
+    // 開始からの経過時間を時計を開始してからの経過時間に変換する
+    // 時計を開始する前は時計を開始した時点とみなす
+    pub(crate) fn elapsed_time_from_clock_start(&self, elapsed_time: Duration) -> Duration {
+        self.clock_start_offset
+            .map_or(Duration::ZERO, |clock_start_offset| {
+                elapsed_time.saturating_sub(clock_start_offset)
+            })
+    }
+
+    // 表示用の速度を計測する開始からの経過時間
+    // 終了後に速度が変化し続けないように終了時点で計測する
+    pub(crate) fn display_elapsed_time(&self) -> Duration {
+        if self.is_finished() {
+            self.finish_time
+                .or(self
+                    .processed_chunk_info
+                    .as_ref()
+                    .unwrap()
+                    .last_key_stroke_time())
+                .unwrap_or_default()
+        } else {
+            self.elapsed_time_from_clock_start(self.elapsed_time_from_start())
+        }
+    }
+
+    // elapsed_time時点での速度
+    // 終了後に速度が変化し続けないように終了時点より後は終了時点で計測する
+    pub(crate) fn construct_speed_display_info_at(
+        &self,
+        elapsed_time: Duration,
+    ) -> SpeedDisplayInfo {
+        let elapsed_time = if self.is_finished() {
+            elapsed_time.min(self.display_elapsed_time())
+        } else {
+            elapsed_time
+        };
+
+        self.processed_chunk_info
+            .as_ref()
+            .unwrap()
+            .construct_speed_display_info(elapsed_time)
+    }
+
+    // 理想的なキーストローク系列でのカーソル位置とその全体の長さ
+    pub(crate) fn ideal_key_stroke_progress(&self) -> (usize, usize) {
+        self.processed_chunk_info
+            .as_ref()
+            .unwrap()
+            .ideal_key_stroke_progress()
+    }
+
+    // クエリを打ち終えた場合の開始から打ち終えるまでの時間
+    // 明示的な終了やライフの枯渇によって終了した場合は打ち終えていない
+    pub(crate) fn completion_time(&self) -> Option<Duration> {
+        let processed_chunk_info = self.processed_chunk_info.as_ref()?;
+
+        processed_chunk_info.is_finished().then(|| {
+            processed_chunk_info
+                .last_key_stroke_time()
+                .unwrap_or_default()
+        })
+    }
+
+    /// Construct [`DisplayInfo`] whose speed is measured at `elapsed_time` from start.
+    ///
+    /// This is useful when application has its own clock such as game loop or replay.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn construct_display_info_with_elapsed_time(
+        &self,
+        lap_request: LapRequest,
+        elapsed_time: Duration,
+    ) -> Result<DisplayInfo, TypingEngineError> {
+        if self.is_started() {
+            self.construct_display_info_inner(
+                &[lap_request],
+                self.elapsed_time_from_clock_start(elapsed_time),
+                DisplayStringBuffers::default(),
+            )
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    fn construct_display_info_inner(
+        &self,
+        lap_requests: &[LapRequest],
+        elapsed_time: Duration,
+        display_string_buffers: DisplayStringBuffers,
+    ) -> Result<DisplayInfo, TypingEngineError> {
+        let (lap_request, additional_lap_requests) = lap_requests.split_first().ok_or(
+            TypingEngineError::new(TypingEngineErrorKind::LapRequestNotConfigured),
+        )?;
+
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+        // 確定したチャンクまでを反映した表示用の情報にタイプ中のチャンクのみを加えて構築する
+        // 綴りはクエリが変わるまで変化しないので保持している表示用の綴りを与えられた領域に書き込む
+        let DisplayStringBuffers {
+            view,
+            mut spell,
+            key_stroke,
+            ideal_key_stroke,
+        } = display_string_buffers;
+        spell.clear();
+        spell.push_str(self.display_cache.presentation_spell());
+
+        let (spell_display_info, key_stroke_display_info, display_statistics) =
+            self.display_cache.with_display_string_builder(
+                lap_request,
+                processed_chunk_info,
+                &self.vocabulary_end_chunk_counts,
+                |display_string_builder| {
+                    processed_chunk_info.snapshot_display_info(
+                        display_string_builder,
+                        &self.vocabulary_end_chunk_counts,
+                        spell,
+                        key_stroke,
+                    )
+                },
+            );
+
+        Ok(self.assemble_display_info(
+            lap_request,
+            display_statistics,
+            spell_display_info,
+            key_stroke_display_info,
+            additional_lap_requests,
+            elapsed_time,
+            DisplayStringBuffers {
+                view,
+                ideal_key_stroke,
+                ..DisplayStringBuffers::default()
+            },
+        ))
+    }
+
+    // 綴りは表示用の綴りに差し替えたものを受け取る
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+    fn assemble_display_info(
+        &self,
+        lap_request: &LapRequest,
+        display_statistics: DisplayStatistics,
+        spell_display_info: SpellDisplayInfo,
+        key_stroke_display_info: KeyStrokeDisplayInfo,
+        additional_lap_requests: &[LapRequest],
+        elapsed_time: Duration,
+        display_string_buffers: DisplayStringBuffers,
+    ) -> DisplayInfo {
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+        #[cfg(feature = "statistics")]
+        let (lap_summaries, (combo, max_combo)) = display_statistics;
+        #[cfg(feature = "statistics")]
+        let key_stroke_display_info = key_stroke_display_info.with_combo(combo, max_combo);
+
+        // ラップは対象ごとに1種類しか持てないので2つ目以降のラップ要求はそれぞれ別に構築する
+        // 表示用の文字列は1つ目のラップ要求のものと同じなので統計情報のみを構築する
+        #[cfg(feature = "statistics")]
+        let lap_infos = std::iter::once(LapDisplayInfo::new(
+            lap_request.clone(),
+            &spell_display_info,
+            &key_stroke_display_info,
+            lap_summaries,
+        ))
+        .chain(additional_lap_requests.iter().map(|lap_request| {
+            let (spell, key_stroke, ideal_key_stroke, lap_summaries) =
+                self.display_cache.with_display_string_builder(
+                    lap_request,
+                    processed_chunk_info,
+                    &self.vocabulary_end_chunk_counts,
+                    |display_string_builder| {
+                        processed_chunk_info.snapshot_lap_statistics(
+                            display_string_builder,
+                            &self.vocabulary_end_chunk_counts,
+                        )
+                    },
+                );
+
+            LapDisplayInfo::with_statistics(
+                lap_request.clone(),
+                spell,
+                key_stroke,
+                ideal_key_stroke,
+                lap_summaries,
+            )
+        }))
+        .collect();
+
+        // 長さがクエリに比例する文字列は与えられた領域を再利用して構築する
+        let DisplayStringBuffers {
+            mut view,
+            mut ideal_key_stroke,
+            ..
+        } = display_string_buffers;
+
+        let ideal_cursor_position =
+            processed_chunk_info.write_ideal_key_stroke(&mut ideal_key_stroke);
+        let key_stroke_display_info =
+            key_stroke_display_info.with_ideal_key_stroke(ideal_key_stroke, ideal_cursor_position);
+        let progress_display_info = processed_chunk_info
+            .construct_progress_display_info()
+            .with_ideal_key_stroke_progress(
+                key_stroke_display_info.ideal_cursor_position(),
+                key_stroke_display_info.ideal_key_stroke().chars().count(),
+                elapsed_time,
+            );
+        let upcoming_candidate_counts = processed_chunk_info.construct_upcoming_candidate_counts();
+
+        view.clear();
+        view.push_str(self.display_cache.view());
+
+        let view_display_info = ViewDisplayInfo::new(
+            &spell_display_info,
+            view,
+            self.display_cache.view_position_of_spell_positions(),
+        );
+
+        // 打ち終えたチャンクの次のチャンクが現在打っているチャンクである
+        let current_vocabulary_index =
+            self.vocabulary_end_chunk_counts
+                .partition_point(|chunk_count| {
+                    *chunk_count <= progress_display_info.finished_chunk_count()
+                });
+        let current_vocabulary_assets = self
+            .vocabulary_infos
+            .as_ref()
+            .unwrap()
+            .get(current_vocabulary_index)
+            .map(|vocabulary_info| vocabulary_info.assets().clone());
+
+        let speed_display_info = processed_chunk_info.construct_speed_display_info(elapsed_time);
+        let line_break_display_info = self.construct_line_break_display_info();
+        let pace_display_info = self.target_kpm.map(|target_kpm| {
+            PaceDisplayInfo::new(
+                target_kpm,
+                elapsed_time,
+                key_stroke_display_info.ideal_cursor_position(),
+                key_stroke_display_info.ideal_key_stroke().chars().count(),
+            )
+        });
+
+        DisplayInfo::new(
+            view_display_info,
+            spell_display_info,
+            key_stroke_display_info,
+            progress_display_info,
+            upcoming_candidate_counts,
+            current_vocabulary_assets,
+            speed_display_info,
+            #[cfg(feature = "statistics")]
+            lap_infos,
+            line_break_display_info,
+            pace_display_info,
+        )
+    }
+
+    // 改行する語彙の末尾の位置を表示用の文字列ごとに求める
+    // 語彙の末尾までのチャンク数はクエリが変わるたびに求めてあるのでその位置のチャンクの先頭のみを求める
+    fn construct_line_break_display_info(&self) -> LineBreakDisplayInfo {
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+        let line_break_chunk_counts = self.display_cache.line_break_chunk_counts();
+
+        LineBreakDisplayInfo::new(
+            self.display_cache.line_break_view_positions().to_vec(),
+            processed_chunk_info.spell_heads_of(line_break_chunk_counts),
+            processed_chunk_info.key_stroke_heads_of(line_break_chunk_counts),
+        )
+    }
+
+    #[cfg(feature = "statistics")]
+    pub fn construst_result_statistics(
+        &self,
+        lap_request: LapRequest,
+    ) -> Result<TypingResultStatistics, TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                Ok(self.construct_result_inner(lap_request, self.end_reason(), self.finish_time))
+            } else {
+                Err(self.lifecycle_error(TypingEngineErrorKind::NotFinished))
+            }
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Construct [`TypingResultStatistics`] of key strokes typed so far even if typing is not
+    /// finished.
+    ///
+    /// This is useful to save score of abandoned game.
+    /// Targets typed so far are regarded as whole targets as if typing were finished now, and
+    /// end reason of result is [`TypingEndReason::InProgress`].
+    /// Total time is measured by wall clock from calling [`start`](Self::start()) method.
+    /// Use [`construct_result_so_far_with_elapsed_time`](Self::construct_result_so_far_with_elapsed_time())
+    /// to give elapsed time explicitly.
+    ///
+    /// If typing is already finished, this method returns the same result as
+    /// [`construst_result_statistics`](Self::construst_result_statistics()).
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn construct_result_so_far(
+        &self,
+        lap_request: LapRequest,
+    ) -> Result<TypingResultStatistics, TypingEngineError> {
+        if self.is_started() {
+            let elapsed_time = self.elapsed_time_from_start();
+
+            self.construct_result_so_far_with_elapsed_time(lap_request, elapsed_time)
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Construct [`TypingResultStatistics`] of key strokes typed so far whose total time is
+    /// `elapsed_time` from start.
+    ///
+    /// See [`construct_result_so_far`](Self::construct_result_so_far()) for details.
+    pub fn construct_result_so_far_with_elapsed_time(
+        &self,
+        lap_request: LapRequest,
+        elapsed_time: Duration,
+    ) -> Result<TypingResultStatistics, TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                self.construst_result_statistics(lap_request)
+            } else {
+                Ok(self.construct_result_inner(
+                    lap_request,
+                    TypingEndReason::InProgress,
+                    Some(self.elapsed_time_from_clock_start(elapsed_time)),
+                ))
+            }
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    // キーストロークの履歴以外から求める統計も含めて結果を構築する
+    #[cfg(feature = "statistics")]
+    fn construct_result_inner(
+        &self,
+        lap_request: LapRequest,
+        end_reason: TypingEndReason,
+        finish_time: Option<Duration>,
+    ) -> TypingResultStatistics {
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+        let result = construct_result(
+            processed_chunk_info.confirmed_chunks(),
+            self.inflight_chunk_at_end(),
+            lap_request,
+            end_reason,
+            finish_time,
+        )
+        .with_correction_count(self.correction_count())
+        .with_premature_miss_count(self.premature_miss_count)
+        .with_assisted_key_stroke_count(self.assisted_key_stroke_count)
+        .with_skipped_vocabulary_count(self.skipped_vocabulary_count)
+        .with_spell_hints(self.construct_spell_hint_statistics())
+        .with_shift_key_stroke_count(self.shift_key_stroke_count)
+        .with_vocabularies(construct_vocabulary_result_statistics(
+            processed_chunk_info.confirmed_chunks(),
+            self.vocabulary_infos.as_ref().unwrap(),
+        ));
+
+        let result = match self.idle_threshold {
+            Some(idle_threshold) => {
+                let idle_time = construct_idle_time(
+                    processed_chunk_info.confirmed_chunks(),
+                    self.inflight_chunk_at_end(),
+                    result.total_time(),
+                    idle_threshold,
+                );
+
+                result.with_idle_time(idle_time)
+            }
+            None => result,
+        };
+
+        #[cfg(feature = "analysis")]
+        let result = result.with_typo_analysis(construct_typo_analysis(
+            processed_chunk_info.confirmed_chunks(),
+            self.inflight_chunk_at_end(),
+            &self.input_scheme,
+            &self.keyboard_layout,
+        ));
+
+        #[cfg(feature = "analysis")]
+        let result = result.with_finger_statistics(construct_finger_statistics(
+            processed_chunk_info.confirmed_chunks(),
+            self.inflight_chunk_at_end(),
+            &self
+                .finger_map
+                .clone()
+                .unwrap_or_else(|| FingerMap::new(&self.keyboard_layout)),
+        ));
+
+        result
+    }
+
+    /// Construct [`TypingReplay`] which records key strokes and operations such as
+    /// [`backspace`](Self::backspace()) performed so far.
+    ///
+    /// If this method is called before starting via calling [`start`](Self::start()) method,
+    /// this method returns error.
+    pub fn construct_replay(&self) -> Result<TypingReplay, TypingEngineError> {
+        if self.is_started() {
+            Ok(TypingReplay::new(
+                self.processed_chunk_info
+                    .as_ref()
+                    .unwrap()
+                    .construct_query_spell(),
+                self.replay_key_strokes.clone(),
+                self.replay_operations.clone(),
+            ))
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    fn record_replay_operation(&mut self, operation: ReplayOperation) {
+        self.replay_operations
+            .push((self.replay_key_strokes.len(), operation));
+    }
+
+    /// Play back `replay` and construct [`ReplayFrame`]s, one for start and one for each recorded
+    /// key stroke or operation.
+    ///
+    /// This method starts this engine and performs recorded key strokes and operations with
+    /// recorded elapsed time, so this engine must be initialized with the same query as recorded
+    /// one but not started yet.
+    /// Settings such as strict mode or life should also be the same as recorded session.
+    /// Playback time of each frame is recorded elapsed time divided by `speed`.
+    /// Frames of operations which do not depend on time such as
+    /// [`backspace`](Self::backspace()) have the same playback time as previous frames.
+    /// [`DisplayInfo`] of each frame is constructed with configured [`LapRequest`], or lap of each
+    /// chunk if not configured.
+    ///
+    /// If this engine is not initialized, already started, query is different from recorded one,
+    /// `speed` is not a positive finite number or recorded operation cannot be performed, this
+    /// method returns error.
+    pub fn replay(
+        &mut self,
+        replay: &TypingReplay,
+        speed: f64,
+    ) -> Result<Vec<ReplayFrame>, TypingEngineError> {
+        let mut frames = vec![];
+        self.play_back(replay, speed, |playback_time, display_info| {
+            frames.push(ReplayFrame::new(playback_time, display_info));
+        })?;
+
+        Ok(frames)
+    }
+
+    // 記録を再生して開始時と各キーストロークや操作の後の表示用の情報を再生時刻とともに順に渡す
+    fn play_back(
+        &mut self,
+        replay: &TypingReplay,
+        speed: f64,
+        mut on_frame: impl FnMut(Duration, DisplayInfo),
+    ) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyStarted));
+        }
+
+        if !self.is_initialized() {
+            return Err(self.lifecycle_error(TypingEngineErrorKind::MustBeInitialized));
+        }
+
+        let query_spell = self
+            .processed_chunk_info
+            .as_ref()
+            .unwrap()
+            .construct_query_spell();
+
+        if !speed.is_finite() || speed <= 0.0 || query_spell != replay.query_spell() {
+            return Err(TypingEngineError::new(TypingEngineErrorKind::InvalidReplay));
+        }
+
+        let events = replay
+            .events()
+            .ok_or(TypingEngineError::new(TypingEngineErrorKind::InvalidReplay))?;
+
+        let lap_request = self
+            .lap_request
+            .clone()
+            .unwrap_or(LapRequest::Chunk(NonZeroUsize::MIN));
+
+        self.start()?;
+
+        on_frame(
+            Duration::ZERO,
+            self.construct_display_info_with_elapsed_time(lap_request.clone(), Duration::ZERO)?,
+        );
+        let mut elapsed_time = Duration::ZERO;
+
+        for event in events {
+            // 記録した時と設定が異なると記録の途中で終了することがある
+            if self.is_finished() {
+                break;
+            }
+
+            match event {
+                ReplayEvent::KeyStroke(key_stroke_elapsed_time, key_stroke) => {
+                    elapsed_time = key_stroke_elapsed_time;
+                    self.stroke_key_with_elapsed_time(key_stroke, elapsed_time)?;
+                }
+                ReplayEvent::Operation(operation) => {
+                    if let Some(operation_elapsed_time) = operation.elapsed_time() {
+                        elapsed_time = operation_elapsed_time;
+                    }
+
+                    // 記録した時と設定が異なると操作を行えないことがある
+                    match operation {
+                        ReplayOperation::Backspace => self.backspace(),
+                        ReplayOperation::ClearPendingWrongState => self.clear_pending_wrong_state(),
+                        ReplayOperation::AutoComplete { policy, .. } => self
+                            .auto_complete_current_chunk_with_elapsed_time(*policy, elapsed_time)
+                            .map(|_| ()),
+                        ReplayOperation::SkipVocabulary { .. } => self
+                            .skip_current_vocabulary_with_elapsed_time(elapsed_time)
+                            .map(|_| ()),
+                        ReplayOperation::Hint => self.hint().map(|_| ()),
+                    }
+                    .map_err(|_| TypingEngineError::new(TypingEngineErrorKind::InvalidReplay))?;
+                }
+            }
+
+            on_frame(
+                elapsed_time.div_f64(speed),
+                self.construct_display_info_with_elapsed_time(lap_request.clone(), elapsed_time)?,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Construct [`TypingGhost`] which tells cursor positions of the session recorded in `replay`
+    /// at any time, so UI can render a ghost cursor racing the player.
+    ///
+    /// Since cursor positions are computed by playing back `replay` as
+    /// [`replay`](Self::replay()) does, this engine must be initialized with the same query as
+    /// recorded one and this method must be called before starting.
+    /// This engine itself is not changed.
+    ///
+    /// If this engine is not initialized, already started or query is different from recorded
+    /// one, this method returns error.
+    pub fn construct_ghost(&self, replay: &TypingReplay) -> Result<TypingGhost, TypingEngineError> {
+        let mut replaying_engine = self.clone();
+
+        // 再生した表示用の情報からはカーソル位置のみを保持する
+        let mut frames = vec![];
+        replaying_engine.play_back(replay, 1.0, |playback_time, display_info| {
+            frames.push(GhostFrame::new(playback_time, &display_info));
+        })?;
+
+        Ok(TypingGhost::new(frames))
+    }
+
+    /// Configure [`LapRequest`] used in [`construct_configured_display_info`](Self::construct_configured_display_info())
+    /// and [`construct_configured_result_statistics`](Self::construct_configured_result_statistics()).
+    ///
+    /// This method can be called at any time even while typing.
+    /// Laps are always recomputed from whole key stroke history, so already passed lap boundaries
+    /// also reflect the lap request configured last.
+    ///
+    /// Without `statistics` feature, the lap request is only used to construct display info and
+    /// laps are not tracked while typing.
+    pub fn set_lap_request(&mut self, lap_request: LapRequest) {
+        self.lap_request.replace(lap_request.clone());
+        self.display_cache.set_lap_request(lap_request);
+
+        // 変更前のラップ要求で完了したラップを新しいラップ要求で完了したとみなさないようにする
+        #[cfg(feature = "statistics")]
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+            self.display_cache
+                .apply_confirmed_chunks(processed_chunk_info, &self.vocabulary_end_chunk_counts);
+
+            self.completed_lap_count = processed_chunk_info.completed_lap_count(
+                self.display_cache
+                    .configured_display_string_builder()
+                    .unwrap(),
+            );
+        }
+    }
+
+    /// Get configured [`LapRequest`].
+    pub fn lap_request(&self) -> Option<&LapRequest> {
+        self.lap_request.as_ref()
+    }
+
+    /// Construct [`DisplayInfo`] using [`LapRequest`] configured by [`set_lap_request`](Self::set_lap_request()).
+    ///
+    /// If lap request is not configured, this method returns error.
+    pub fn construct_configured_display_info(&self) -> Result<DisplayInfo, TypingEngineError> {
+        match &self.lap_request {
+            Some(lap_request) => self.construct_display_info(lap_request.clone()),
+            None => Err(TypingEngineError::new(
+                TypingEngineErrorKind::LapRequestNotConfigured,
+            )),
+        }
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Construct [`TypingResultStatistics`] using [`LapRequest`] configured by [`set_lap_request`](Self::set_lap_request()).
+    ///
+    /// If lap request is not configured, this method returns error.
+    pub fn construct_configured_result_statistics(
+        &self,
+    ) -> Result<TypingResultStatistics, TypingEngineError> {
+        match &self.lap_request {
+            Some(lap_request) => self.construst_result_statistics(lap_request.clone()),
+            None => Err(TypingEngineError::new(
+                TypingEngineErrorKind::LapRequestNotConfigured,
+            )),
+        }
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Construct [`TypingTelemetry`] which is an anonymized summary of finished typing.
+    ///
+    /// If this method is called before typing is finished, this method returns error.
+    pub fn construct_telemetry(
+        &self,
+        lap_request: LapRequest,
+    ) -> Result<TypingTelemetry, TypingEngineError> {
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            if self.is_finished() {
+                Ok(construct_telemetry(
+                    processed_chunk_info.confirmed_chunks(),
+                    self.inflight_chunk_at_end(),
+                    lap_request,
+                    self.end_reason(),
+                    self.finish_time,
+                ))
+            } else {
+                Err(self.lifecycle_error(TypingEngineErrorKind::NotFinished))
+            }
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Construct miss statistics of key strokes conditioned on preceding spell.
+    ///
+    /// Contexts with more wrong key strokes come first, so leading entries are top problem
+    /// contexts. Unlike result statistics, this method can be called before finishing.
+    ///
+    /// If this method is called before starting, this method returns error.
+    pub fn construct_contextual_miss_statistics(
+        &self,
+    ) -> Result<Vec<ContextualMissStatistics>, TypingEngineError> {
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            Ok(construct_contextual_miss_statistics(
+                processed_chunk_info.confirmed_chunks(),
+                processed_chunk_info.inflight_chunk(),
+            ))
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Construct [`MistypeHeatmap`] which aggregates wrong key strokes by intended key and
+    /// actually pressed key.
+    ///
+    /// Like [`construct_contextual_miss_statistics`](Self::construct_contextual_miss_statistics()),
+    /// this method can be called before finishing.
+    ///
+    /// If this method is called before starting, this method returns error.
+    pub fn construct_mistype_heatmap(&self) -> Result<MistypeHeatmap, TypingEngineError> {
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            Ok(construct_mistype_heatmap(
+                processed_chunk_info.confirmed_chunks(),
+                processed_chunk_info.inflight_chunk(),
+            ))
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Construct miss statistics keyed by key stroke and key stroke bigram.
+    ///
+    /// Like [`construct_contextual_miss_statistics`](Self::construct_contextual_miss_statistics()),
+    /// this method can be called before finishing.
+    ///
+    /// If this method is called before starting, this method returns error.
+    pub fn construct_skill_statistics(&self) -> Result<SkillStatistics, TypingEngineError> {
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            Ok(construct_skill_statistics(
+                processed_chunk_info.confirmed_chunks(),
+                processed_chunk_info.inflight_chunk(),
+            ))
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Configure lives economy.
+    ///
+    /// Each wrong key stroke subtracts life and typing is finished when life is exhausted.
+    /// This method must be called before starting via calling [`start`](Self::start()) method.
+    pub fn set_life_config(&mut self, life_config: LifeConfig) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyStarted))
+        } else {
+            self.life_config.replace(life_config);
+            Ok(())
+        }
+    }
+
+    /// Configure condition to terminate typing by wrong key strokes.
+    ///
+    /// When the condition is met, typing is finished at the wrong key stroke and result can be
+    /// constructed from key strokes typed so far.
+    /// `None` disables termination.
+    /// This method must be called before starting via calling [`start`](Self::start()) method.
+    pub fn set_termination_condition(
+        &mut self,
+        termination_condition: Option<TerminationCondition>,
+    ) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyStarted))
+        } else {
+            self.termination_condition = termination_condition;
+            Ok(())
+        }
+    }
+
+    /// Get current life.
+    ///
+    /// This returns [`None`](std::option::Option::None) when lives economy is not configured or
+    /// typing is not started.
+    pub fn life(&self) -> Option<usize> {
+        self.life_manager
+            .as_ref()
+            .map(|life_manager| life_manager.life())
+    }
+
+    /// Take [`LifeEvent`]s emitted since last call of this method.
+    pub fn take_life_events(&mut self) -> Vec<LifeEvent> {
+        self.life_manager
+            .as_mut()
+            .map_or(vec![], |life_manager| life_manager.take_events())
+    }
+
+    /// Enable or disable strict mode.
+    ///
+    /// In strict mode, a wrong key stroke must be deleted by [`backspace`](Self::backspace())
+    /// method before typing can continue. Key strokes given before deleting are all regarded as
+    /// wrong and also must be deleted.
+    ///
+    /// If this method is called after starting, this method returns error.
+    pub fn set_strict_mode(&mut self, strict_mode: bool) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyStarted))
+        } else {
+            self.strict_mode = strict_mode;
+            Ok(())
+        }
+    }
+
+    /// Get whether strict mode is enabled.
+    pub fn strict_mode(&self) -> bool {
+        self.strict_mode
+    }
+
+    /// Enable or disable Shift key mode.
+    ///
+    /// In Shift key mode, a correct key stroke which needs Shift key such as capital letter is
+    /// regarded as two physical key strokes, Shift key and the key itself.
+    /// Such Shift key strokes are counted as
+    /// [`shift_key_stroke_count`](TypingResultStatistics::shift_key_stroke_count()) and reflected
+    /// in [`physical_key_stroke_count`](TypingResultStatistics::physical_key_stroke_count()).
+    /// Use [`KeyStrokeChar::is_shifted`] to know whether Shift key should be highlighted.
+    ///
+    /// If this method is called after starting, this method returns error.
+    pub fn set_shift_key_mode(&mut self, shift_key_mode: bool) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyStarted))
+        } else {
+            self.shift_key_mode = shift_key_mode;
+            Ok(())
+        }
+    }
+
+    /// Get whether Shift key mode is enabled.
+    pub fn shift_key_mode(&self) -> bool {
+        self.shift_key_mode
+    }
+
+    /// Delete a wrong key stroke which is not corrected yet in strict mode.
+    ///
+    /// Deleted wrong key strokes are counted as corrections in [`TypingResultStatistics`].
+    /// If there is no wrong key stroke to delete, this method does nothing.
+    ///
+    /// If this method is called before starting, after finishing or when strict mode is not
+    /// enabled, this method returns error.
+    pub fn backspace(&mut self) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            match self.correction_manager.as_mut() {
+                Some(correction_manager) => {
+                    correction_manager.on_backspace();
+                    self.record_replay_operation(ReplayOperation::Backspace);
+
+                    Ok(())
+                }
+                None => Err(TypingEngineError::new(
+                    TypingEngineErrorKind::StrictModeNotEnabled,
+                )),
+            }
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Clear transient wrong state such as an Escape key.
+    ///
+    /// In strict mode, wrong key strokes which are not deleted yet are discarded and typing can
+    /// continue without [`backspace`](Self::backspace()). Discarded wrong key strokes are not
+    /// counted as corrections. Wrong key strokes already recorded are not affected in any mode.
+    ///
+    /// [`WrongStateEvent::Cleared`] is emitted even if there is nothing to clear, so UI can
+    /// clear highlights of errors accumulated until acknowledged.
+    ///
+    /// If this method is called before starting or after finishing, this method returns error.
+    pub fn clear_pending_wrong_state(&mut self) -> Result<(), TypingEngineError> {
+        if self.is_started() {
+            if self.is_finished() {
+                return Err(self.lifecycle_error(TypingEngineErrorKind::AlreadyFinished));
+            }
+
+            let discarded_count = self
+                .correction_manager
+                .as_mut()
+                .map_or(0, |correction_manager| {
+                    correction_manager.discard_uncorrected()
+                });
+
+            self.wrong_state_events
+                .push(WrongStateEvent::Cleared { discarded_count });
+            self.record_replay_operation(ReplayOperation::ClearPendingWrongState);
+
+            Ok(())
+        } else {
+            Err(self.lifecycle_error(TypingEngineErrorKind::MustBeStarted))
+        }
+    }
+
+    /// Take [`EngineEvent`]s emitted since last call of this method.
+    ///
+    /// Events are ordered by time they occurred.
+    /// When [`LapRequest`] is configured, lap completion is detected every key stroke only from
+    /// the chunk currently typed.
+    pub fn poll_events(&mut self) -> Vec<EngineEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Take [`WrongStateEvent`]s emitted since last call of this method.
+    pub fn take_wrong_state_events(&mut self) -> Vec<WrongStateEvent> {
+        std::mem::take(&mut self.wrong_state_events)
+    }
+
+    /// Get count of wrong key strokes which must be deleted before typing can continue.
+    ///
+    /// If strict mode is not enabled, this method returns `None`.
+    pub fn uncorrected_key_stroke_count(&self) -> Option<usize> {
+        self.correction_manager
+            .as_ref()
+            .map(|correction_manager| correction_manager.uncorrected_count())
+    }
+
+    /// Compute a deterministic digest of current typing state.
+    ///
+    /// Digest covers query, key strokes given so far with their correctness, cursor positions,
+    /// missed positions, counters of statistics, life and corrections.
+    /// Elapsed times of key strokes are not covered, so the same digest is computed for the same
+    /// sequence of key strokes and configurations regardless of timing.
+    ///
+    /// This is intended to record golden digests in regression tests of applications.
+    /// Digest is a part of compatibility surface of this crate, so it changes only when behavior
+    /// of typing changes or with a note in release notes.
+    pub fn state_digest(&self) -> u64 {
+        let mut digester = StateDigester::new();
+
+        digester.write_usize(match self.state {
+            TypingEngineState::Uninitialized => 0,
+            TypingEngineState::Ready => 1,
+            TypingEngineState::Started => 2,
+        });
+
+        if let Some(vocabulary_infos) = self.vocabulary_infos.as_ref() {
+            digester.write_usize(vocabulary_infos.len());
+            vocabulary_infos.iter().for_each(|vocabulary_info| {
+                digester.write_str(vocabulary_info.view());
+                digester.write_usize(vocabulary_info.chunk_count().get());
+            });
+        }
+
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            digester.write_bool(self.is_finished());
+
+            let actual_key_strokes: Vec<&ActualKeyStroke> = processed_chunk_info
+                .confirmed_chunks()
+                .iter()
+                .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes())
+                .chain(processed_chunk_info.inflight_chunk().into_iter().flat_map(
+                    |inflight_chunk| {
+                        inflight_chunk
+                            .actual_key_strokes()
+                            .iter()
+                            .chain(inflight_chunk.pending_key_strokes())
+                    },
+                ))
+                .collect();
+            digester.write_usize(actual_key_strokes.len());
+            actual_key_strokes.iter().for_each(|actual_key_stroke| {
+                digester.write_str(&char::from(actual_key_stroke.key_stroke().clone()).to_string());
+                digester.write_bool(actual_key_stroke.is_correct());
+            });
+
+            // ラップの情報はダイジェストに含めないのでラップ要求は何でも良い
+            let (spell_display_info, key_stroke_display_info) = processed_chunk_info
+                .construct_display_info(
+                    LapRequest::Chunk(NonZeroUsize::MIN),
+                    &self.vocabulary_end_chunk_counts,
+                );
+
+            digester.write_str(spell_display_info.spell());
+            digester.write_usizes(spell_display_info.current_cursor_positions());
+            digester.write_usizes(spell_display_info.missed_positions());
+            digester.write_str(key_stroke_display_info.key_stroke());
+            digester.write_usize(key_stroke_display_info.current_cursor_position());
+            digester.write_usizes(key_stroke_display_info.missed_positions());
+
+            #[cfg(feature = "statistics")]
+            [
+                spell_display_info.on_typing_statistics(),
+                key_stroke_display_info.on_typing_statistics(),
+                key_stroke_display_info.on_typing_statistics_ideal(),
+            ]
+            .iter()
+            .for_each(|on_typing_statistics| {
+                digester.write_usize(on_typing_statistics.finished_count());
+                digester.write_usize(on_typing_statistics.whole_count());
+                digester.write_usize(on_typing_statistics.completely_correct_count());
+                digester.write_usize(on_typing_statistics.wrong_count());
+            });
+
+            digester.write_optional_usize(self.life());
+            digester.write_optional_usize(self.uncorrected_key_stroke_count());
+            digester.write_usize(self.correction_count());
+            digester.write_usize(self.premature_miss_count);
+            digester.write_usize(self.assisted_key_stroke_count);
+            digester.write_usize(self.shift_key_stroke_count);
+        }
+
+        digester.finish()
+    }
+
+    /// Reset typing state while keeping configurations and accumulating statistics of this round.
+    ///
+    /// Result of this round is accumulated into [`AccumulatedResultStatistics`] even if typing is
+    /// not finished. Rounds without any key stroke are not accumulated.
+    /// Configurations such as lap request, life config, keyboard layout and strict mode are kept,
+    /// so you only need to call [`init`](Self::init()) and [`start`](Self::start()) method for
+    /// next round.
+    pub fn reset_keeping_statistics(&mut self) {
+        #[cfg(feature = "statistics")]
+        if self.is_started() {
+            let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+            if processed_chunk_info.has_key_strokes() || self.finish_time.is_some() {
+                // 集計する対象の統計はラップの設定には依存しない
+                let lap_request = self
+                    .lap_request
+                    .clone()
+                    .unwrap_or(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()));
+
+                let result =
+                    self.construct_result_inner(lap_request, self.end_reason(), self.finish_time);
+
+                self.accumulated_statistics.accumulate(&result);
+                #[cfg(feature = "analysis")]
+                self.accumulated_statistics
+                    .accumulate_contextual_miss_statistics(&construct_contextual_miss_statistics(
+                        processed_chunk_info.confirmed_chunks(),
+                        processed_chunk_info.inflight_chunk(),
+                    ));
+                #[cfg(feature = "analysis")]
+                self.accumulated_statistics.accumulate_skill_statistics(
+                    &construct_skill_statistics(
+                        processed_chunk_info.confirmed_chunks(),
+                        processed_chunk_info.inflight_chunk(),
+                    ),
+                );
+            }
+        }
+
+        self.clear_round();
+    }
+
+    // 1回のタイピングの状態を初期化前の状態に戻す
+    // 設定や集計した統計は残す
+    fn clear_round(&mut self) {
+        self.state = TypingEngineState::Uninitialized;
+        self.start_time = None;
+        self.clock_start_offset = None;
+        self.processed_chunk_info = None;
+        self.vocabulary_infos = None;
+        self.update_query_caches();
+        self.life_manager = None;
+        self.correction_manager = None;
+        self.wrong_state_events.clear();
+        self.events.clear();
+        #[cfg(feature = "statistics")]
+        {
+            self.completed_lap_count = 0;
+        }
+        self.display_cache.reset_display_string_builders();
+        self.premature_miss_count = 0;
+        self.miss_count = 0;
+        self.assisted_key_stroke_count = 0;
+        self.skipped_vocabulary_count = 0;
+        self.hint_counts.clear();
+        self.shift_key_stroke_count = 0;
+        self.replay_key_strokes.clear();
+        self.replay_operations.clear();
+        self.finish_time = None;
+        self.time_limit = None;
+        self.is_time_limit_reached = false;
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get statistics accumulated by [`reset_keeping_statistics`](Self::reset_keeping_statistics()).
+    pub fn accumulated_statistics(&self) -> &AccumulatedResultStatistics {
+        &self.accumulated_statistics
+    }
+
+    fn is_finished(&self) -> bool {
+        self.processed_chunk_info
+            .as_ref()
+            .is_some_and(|pci| pci.is_finished())
+            || self.end_reason() != TypingEndReason::Completed
+    }
+
+    // 終了した理由
+    // 終了していない場合には意味を持たない
+    fn end_reason(&self) -> TypingEndReason {
+        if self
+            .life_manager
+            .as_ref()
+            .is_some_and(|life_manager| life_manager.is_game_over())
+        {
+            TypingEndReason::LifeExhausted
+        } else if self.is_terminated() {
+            TypingEndReason::MissLimitReached
+        } else if self.is_time_limit_reached {
+            TypingEndReason::TimeLimitReached
+        } else if self.finish_time.is_some() {
+            TypingEndReason::Finished
+        } else {
+            TypingEndReason::Completed
+        }
+    }
+
+    // ヒントを使ったチャンクごとに綴りと使った回数をまとめる
+    #[cfg(feature = "statistics")]
+    fn construct_spell_hint_statistics(&self) -> Vec<SpellHintStatistics> {
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+        let confirmed_chunks = processed_chunk_info.confirmed_chunks();
+
+        self.hint_counts
+            .iter()
+            .filter_map(|(chunk_index, hint_count)| {
+                let chunk = if *chunk_index < confirmed_chunks.len() {
+                    Some(confirmed_chunks[*chunk_index].as_ref())
+                } else {
+                    processed_chunk_info
+                        .inflight_chunk()
+                        .map(|inflight_chunk| inflight_chunk.as_ref())
+                };
+
+                chunk.map(|chunk| {
+                    SpellHintStatistics::new(chunk.spell().as_ref().to_string(), *hint_count)
+                })
+            })
+            .collect()
+    }
+
+    // ミスタイプの数が打ち切り条件の上限に達したかどうか
+    fn is_terminated(&self) -> bool {
+        self.termination_condition
+            .is_some_and(|termination_condition| {
+                self.miss_count >= termination_condition.miss_limit().get()
+            })
+    }
+
+    // キーストロークによって起こったイベントを記録する
+    // confirmed_chunk_countはキーストローク前に確定していたチャンク数である
+    fn record_events(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+        key_stroke_result: &KeyStrokeResult,
+        confirmed_chunk_count: usize,
+    ) {
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+        if *key_stroke_result == KeyStrokeResult::Wrong {
+            self.events.push(EngineEvent::MissType {
+                chunk_index: confirmed_chunk_count,
+                key_stroke,
+            });
+        }
+
+        (confirmed_chunk_count..processed_chunk_info.confirmed_chunks().len()).for_each(
+            |chunk_index| {
+                self.events
+                    .push(EngineEvent::ChunkConfirmed { chunk_index });
+            },
+        );
+
+        #[cfg(feature = "statistics")]
+        if let Some(display_string_builder) = self.display_cache.configured_display_string_builder()
+        {
+            let completed_lap_count =
+                processed_chunk_info.completed_lap_count(display_string_builder);
+
+            (self.completed_lap_count..completed_lap_count).for_each(|lap_index| {
+                self.events.push(EngineEvent::LapCompleted { lap_index });
+            });
+            self.completed_lap_count = self.completed_lap_count.max(completed_lap_count);
+        }
+    }
+
+    // キーストローク前の状態との差分からキーストロークの結果を構築する
+    // チャンクとラップはキーストロークで記録したイベントから求める
+    fn construct_key_stroke_outcome(
+        &self,
+        key_stroke_result: &KeyStrokeResult,
+        confirmed_chunk_count: usize,
+        finished_spell_count: usize,
+        event_count: usize,
+    ) -> KeyStrokeOutcome {
+        let processed_chunk_info = self.processed_chunk_info.as_ref().unwrap();
+
+        let mut confirmed_chunk_indices = vec![];
+        let mut completed_lap_indices = vec![];
+        self.events[event_count..]
+            .iter()
+            .for_each(|event| match event {
+                EngineEvent::ChunkConfirmed { chunk_index } => {
+                    confirmed_chunk_indices.push(*chunk_index)
+                }
+                EngineEvent::LapCompleted { lap_index } => completed_lap_indices.push(*lap_index),
+                EngineEvent::MissType { .. } => {}
+            });
+
+        // 語彙の最後のチャンクが確定したら語彙を打ち終えたとみなす
+        let completed_vocabulary_indices = confirmed_chunk_indices
+            .iter()
+            .filter_map(|chunk_index| {
+                self.vocabulary_end_chunk_counts
+                    .binary_search(&(chunk_index + 1))
+                    .ok()
+            })
+            .collect();
+
+        KeyStrokeOutcome::new(
+            *key_stroke_result == KeyStrokeResult::Correct,
+            self.is_finished(),
+            self.is_terminated(),
+            processed_chunk_info.finished_spell_count_from(confirmed_chunk_count)
+                - finished_spell_count,
+            confirmed_chunk_indices,
+            completed_vocabulary_indices,
+            completed_lap_indices,
+        )
+    }
+
+    // クエリが変わるたびに語彙とチャンクから求まる部分を求め直す
+    // キーストロークのたびに語彙を走査しなくて済むようにする
+    fn update_query_caches(&mut self) {
+        let vocabulary_infos = self.vocabulary_infos.as_deref().unwrap_or_default();
+
+        self.vocabulary_end_chunk_counts = construct_vocabulary_end_chunk_counts(vocabulary_infos);
+        self.display_cache.update_query(
+            vocabulary_infos,
+            self.processed_chunk_info
+                .as_ref()
+                .map_or(0, |processed_chunk_info| processed_chunk_info.spell_count()),
+        );
+    }
+
+    // 途中で終了した場合にはタイプ中のチャンクも結果に含める
+    #[cfg(feature = "statistics")]
+    fn inflight_chunk_at_end(&self) -> Option<&TypedChunk> {
+        self.processed_chunk_info.as_ref().unwrap().inflight_chunk()
+    }
+
+    // 厳格モードでない場合には訂正は起こり得ない
+    fn correction_count(&self) -> usize {
+        self.correction_manager
+            .as_ref()
+            .map_or(0, |correction_manager| {
+                correction_manager.correction_count()
+            })
+    }
+
+    /// Get current state in lifecycle of engine.
+    pub fn state(&self) -> EngineState {
+        match self.state {
+            TypingEngineState::Uninitialized => EngineState::Uninitialized,
+            TypingEngineState::Ready => EngineState::Ready,
+            TypingEngineState::Started if self.is_finished() => EngineState::Finished,
+            TypingEngineState::Started => EngineState::Started,
+        }
+    }
+
+    // 現在の状態では呼べない操作をしたときのエラー
+    fn lifecycle_error(&self, kind: TypingEngineErrorKind) -> TypingEngineError {
+        TypingEngineError::with_state(kind, self.state())
+    }
+
+    fn is_initialized(&self) -> bool {
+        !matches!(self.state, TypingEngineState::Uninitialized)
+    }
+
     fn is_started(&self) -> bool {
         matches!(self.state, TypingEngineState::Started)
     }
-}
+}
+
+impl Default for TypingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::display_info::TextEdit;
+    use crate::gen_vocabulary_entry;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::input_scheme::RomanizationStyle;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::query::VocabularyBoundaryRounding;
+    use crate::query::{VocabularyOrder, VocabularyQuantifier, VocabularySeparator};
+    #[cfg(all(feature = "statistics", not(feature = "ascii_only")))]
+    use crate::statistics::LapSummary;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::typing_engine::processed_chunk_info::DisplayStringBuilder;
+    use crate::vocabulary::VocabularyEntry;
+    #[cfg(not(feature = "ascii_only"))]
+    use crate::vocabulary::{
+        construct_view_position_of_spell_positions, write_presentation_spell, VocabularyAssets,
+        VocabularySpellElement,
+    };
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn finish_before_whole_query_is_typed() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                vocabularies
+                    .iter()
+                    .collect::<Vec<&VocabularyEntry>>()
+                    .as_slice(),
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+
+        assert!(engine.finish(Duration::new(1, 0)).is_err());
+
+        engine.start().unwrap();
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('j'.try_into().unwrap()).unwrap();
+
+        let lap_request = LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap());
+        assert!(engine
+            .construst_result_statistics(lap_request.clone())
+            .is_err());
+
+        engine.finish(Duration::new(10, 0)).unwrap();
+
+        assert!(engine.finish(Duration::new(11, 0)).is_err());
+        assert!(engine.stroke_key('i'.try_into().unwrap()).is_err());
+
+        let result = engine.construst_result_statistics(lap_request).unwrap();
+        assert_eq!(result.end_reason(), &TypingEndReason::Finished);
+        assert_eq!(result.total_time(), Duration::new(10, 0));
+        assert_eq!(result.key_stroke().whole_count(), 1);
+        assert_eq!(result.key_stroke().missed_count(), 1);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn stroke_key_with_explicit_elapsed_time() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+
+        assert!(engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(1, 0))
+            .is_err());
+
+        engine.start().unwrap();
+        assert!(!engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap());
+        assert!(engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::new(3, 0))
+            .unwrap());
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.total_time(), Duration::new(3, 0));
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn display_presentation_spell() {
+        let vocabularies = [
+            gen_vocabulary_entry!("珈琲", [("こーひー", 2)])
+                .with_presentation_spell("コーヒー".to_string())
+                .unwrap(),
+            gen_vocabulary_entry!("今日", [("きょう", 2)]),
+        ];
+        assert!(gen_vocabulary_entry!("珈琲", [("こーひー", 2)])
+            .with_presentation_spell("コーヒ".to_string())
+            .is_none());
+
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::WhiteSpace,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        // 表示用の綴りのみが変わりキーストロークは打つ綴りから構築される
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.spell_info().spell(), "コーヒー きょう");
+        assert_eq!(display_info.key_stroke_info().key_stroke(), "ko-hi- kyou");
+
+        engine.stroke_key('k'.try_into().unwrap()).unwrap();
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.spell_info().spell(), "コーヒー きょう");
+        assert_eq!(display_info.spell_info().missed_positions(), &vec![0]);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn type_katakana_and_full_width_spell() {
+        let vocabularies = [gen_vocabulary_entry!("珈琲１", [("コーヒー", 2), ("１")])];
+
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        // 綴りはそのまま表示しキーストロークはひらがなやASCIIとして構築される
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.spell_info().spell(), "コーヒー１");
+        assert_eq!(display_info.key_stroke_info().key_stroke(), "ko-hi-1");
+
+        "ko-hi-1".chars().for_each(|c| {
+            engine.stroke_key(c.try_into().unwrap()).unwrap();
+        });
+        assert!(engine.is_finished());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_speed_display_info() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "あいうえおか",
+            [("あ"), ("い"), ("う"), ("え"), ("お"), ("か")]
+        )];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let speed_at = |engine: &TypingEngine, elapsed_time: Duration| {
+            engine
+                .construct_display_info_with_elapsed_time(
+                    LapRequest::Chunk(NonZeroUsize::new(1).unwrap()),
+                    elapsed_time,
+                )
+                .unwrap()
+                .speed_info()
+                .clone()
+        };
+
+        let speed = speed_at(&engine, Duration::from_millis(500));
+        assert_eq!(speed.rolling_kpm(), 0.0);
+        assert_eq!(speed.instantaneous_kpm(), 0.0);
+
+        [
+            ('a', 1000),
+            ('i', 2000),
+            ('u', 3000),
+            ('x', 3500),
+            ('e', 4000),
+            ('o', 5000),
+        ]
+        .iter()
+        .for_each(|(key_stroke, elapsed_millis)| {
+            engine
+                .stroke_key_with_elapsed_time(
+                    (*key_stroke).try_into().unwrap(),
+                    Duration::from_millis(*elapsed_millis),
+                )
+                .unwrap();
+        });
+
+        // 開始直後は経過時間で割る
+        let speed = speed_at(&engine, Duration::new(2, 0));
+        assert_eq!(speed.rolling_duration(), Duration::new(2, 0));
+        assert_eq!(speed.rolling_kpm(), 60.0);
+
+        // ミスタイプは数えない
+        let speed = speed_at(&engine, Duration::new(8, 0));
+        assert_eq!(speed.rolling_key_stroke_count(), 2);
+        assert_eq!(speed.rolling_kpm(), 24.0);
+        assert_eq!(speed.instantaneous_kpm(), 60.0);
+        assert_eq!(speed.instantaneous_wpm(), 12.0);
+
+        engine
+            .stroke_key_with_elapsed_time('k'.try_into().unwrap(), Duration::new(6, 0))
+            .unwrap();
+        assert!(engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(7, 0))
+            .unwrap());
+
+        // 終了後は終了時点で計測される
+        let speed = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap()
+            .speed_info()
+            .clone();
+        assert_eq!(speed.rolling_key_stroke_count(), 5);
+        assert_eq!(speed.rolling_kpm(), 60.0);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn reset_keeping_statistics_accumulates_rounds() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        (0..2).for_each(|_| {
+            engine
+                .init(QueryRequest::new(
+                    vocabularies
+                        .iter()
+                        .collect::<Vec<&VocabularyEntry>>()
+                        .as_slice(),
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                    VocabularySeparator::None,
+                    VocabularyOrder::InOrder,
+                ))
+                .unwrap();
+            engine.start().unwrap();
+
+            engine.stroke_key('a'.try_into().unwrap()).unwrap();
+            engine.stroke_key('u'.try_into().unwrap()).unwrap();
+            engine.stroke_key('i'.try_into().unwrap()).unwrap();
+
+            engine.reset_keeping_statistics();
+        });
+
+        // キーストロークのない回は集計しない
+        engine.reset_keeping_statistics();
+
+        let accumulated_statistics = engine.accumulated_statistics();
+        assert_eq!(accumulated_statistics.round_count(), 2);
+        assert_eq!(accumulated_statistics.key_stroke().whole_count(), 4);
+        assert_eq!(accumulated_statistics.key_stroke().missed_count(), 2);
+        assert!(engine.stroke_key('a'.try_into().unwrap()).is_err());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn backspace_in_strict_mode() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        engine.set_strict_mode(true).unwrap();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        assert!(engine.set_strict_mode(false).is_err());
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('u'.try_into().unwrap()).unwrap();
+        // 訂正するまでは正しいキーストロークもミスタイプとなる
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        assert_eq!(engine.uncorrected_key_stroke_count(), Some(2));
+
+        engine.backspace().unwrap();
+        engine.backspace().unwrap();
+        engine.backspace().unwrap();
+        assert_eq!(engine.uncorrected_key_stroke_count(), Some(0));
+
+        assert!(engine.stroke_key('i'.try_into().unwrap()).unwrap());
+        assert!(engine.backspace().is_err());
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().missed_count(), 2);
+        assert_eq!(result.correction_count(), 2);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn backspace_without_strict_mode() {
+        let vocabularies = [gen_vocabulary_entry!("あ", [("あ")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.backspace().is_err());
+
+        engine.start().unwrap();
+        engine.stroke_key('u'.try_into().unwrap()).unwrap();
+        assert!(engine.backspace().is_err());
+        assert_eq!(engine.uncorrected_key_stroke_count(), None);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn poll_events() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.set_lap_request(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        engine.start().unwrap();
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('x'.try_into().unwrap()).unwrap();
+        assert_eq!(
+            engine.poll_events(),
+            vec![
+                EngineEvent::ChunkConfirmed { chunk_index: 0 },
+                EngineEvent::MissType {
+                    chunk_index: 1,
+                    key_stroke: 'x'.try_into().unwrap()
+                },
+            ]
+        );
+        assert!(engine.poll_events().is_empty());
+
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        engine.stroke_key('u'.try_into().unwrap()).unwrap();
+        // 統計を無効にした場合にはラップを区切らない
+        assert_eq!(
+            engine.poll_events(),
+            vec![
+                EngineEvent::ChunkConfirmed { chunk_index: 1 },
+                #[cfg(feature = "statistics")]
+                EngineEvent::LapCompleted { lap_index: 0 },
+                EngineEvent::ChunkConfirmed { chunk_index: 2 },
+            ]
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn swap_player_profiles() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let profile = PlayerProfile::new("player".to_string())
+            .with_input_scheme(InputScheme::Azik)
+            .with_keyboard_layout(KeyboardLayout::Dvorak)
+            .with_strict_mode(true)
+            .with_romanization_preference(RomanizationPreference::Prefer(RomanizationStyle::Kunrei))
+            .with_shift_key_mode(true)
+            .with_candidate_window(NonZeroUsize::new(2).unwrap())
+            .with_target_kpm(NonZeroUsize::new(300).unwrap())
+            .with_idle_threshold(Duration::from_secs(3))
+            .with_termination_condition(TerminationCondition::MissLimit(
+                NonZeroUsize::new(5).unwrap(),
+            ))
+            .with_life_config(LifeConfig::new(NonZeroUsize::new(3).unwrap(), 1, None));
+
+        let mut engine = TypingEngine::new_with_profile(profile.clone());
+        assert_eq!(engine.input_scheme(), &InputScheme::Azik);
+        assert_eq!(engine.keyboard_layout(), &KeyboardLayout::Dvorak);
+        assert!(engine.strict_mode());
+        assert_eq!(
+            engine.romanization_preference(),
+            Some(&RomanizationPreference::Prefer(RomanizationStyle::Kunrei))
+        );
+        assert!(engine.shift_key_mode());
+        assert_eq!(engine.candidate_window(), NonZeroUsize::new(2));
+        assert_eq!(engine.target_kpm(), NonZeroUsize::new(300));
+        assert_eq!(engine.idle_threshold(), Some(Duration::from_secs(3)));
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        assert_eq!(engine.life(), Some(3));
+        assert!(engine.apply_profile(PlayerProfile::default()).is_err());
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        engine.reset_keeping_statistics();
+
+        let exported_profile = engine.export_profile("player".to_string());
+        assert_eq!(exported_profile.accumulated_statistics().round_count(), 1);
+        assert_eq!(
+            exported_profile
+                .clone()
+                .with_accumulated_statistics(Default::default()),
+            profile
+        );
+
+        // 別のプレイヤーに切り替えると設定されていない項目は解除される
+        engine
+            .apply_profile(PlayerProfile::new("other".to_string()))
+            .unwrap();
+        assert_eq!(engine.input_scheme(), &InputScheme::Standard);
+        assert!(!engine.strict_mode());
+        assert_eq!(engine.romanization_preference(), None);
+        assert!(!engine.shift_key_mode());
+        assert_eq!(engine.candidate_window(), None);
+        assert_eq!(engine.target_kpm(), None);
+        assert_eq!(engine.idle_threshold(), None);
+        assert_eq!(engine.accumulated_statistics().round_count(), 0);
+
+        engine.apply_profile(exported_profile).unwrap();
+        assert_eq!(engine.accumulated_statistics().round_count(), 1);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_display_info_window() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "あいうえお",
+            [("あ"), ("い"), ("う"), ("え"), ("お")]
+        )];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('x'.try_into().unwrap()).unwrap();
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+
+        let window = engine
+            .construct_display_info_window(DisplayWindowRequest::Chunk {
+                before: 1,
+                after: 1,
+            })
+            .unwrap();
+        assert_eq!(window.spell().text(), "いうえ");
+        assert_eq!(window.spell().head_position(), 1);
+        assert_eq!(window.spell().cursor_positions(), &vec![1]);
+        assert_eq!(window.spell().missed_positions(), &vec![0]);
+        assert_eq!(window.key_stroke().text(), "iue");
+        assert_eq!(window.key_stroke().cursor_positions(), &vec![1]);
+        assert_eq!(window.key_stroke().missed_positions(), &vec![0]);
+
+        let window = engine
+            .construct_display_info_window(DisplayWindowRequest::Character {
+                before: 0,
+                after: 1,
+            })
+            .unwrap();
+        assert_eq!(window.spell().text(), "うえ");
+        assert_eq!(window.spell().head_position(), 2);
+        assert_eq!(window.spell().cursor_positions(), &vec![0]);
+        assert!(window.spell().missed_positions().is_empty());
+
+        engine.stroke_key('u'.try_into().unwrap()).unwrap();
+        engine.stroke_key('e'.try_into().unwrap()).unwrap();
+        engine.stroke_key('o'.try_into().unwrap()).unwrap();
+
+        // 打ち終えた後はカーソルは窓の末尾の次を指す
+        let window = engine
+            .construct_display_info_window(DisplayWindowRequest::Chunk {
+                before: 1,
+                after: 1,
+            })
+            .unwrap();
+        assert_eq!(window.key_stroke().text(), "o");
+        assert_eq!(window.key_stroke().cursor_positions(), &vec![1]);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn count_premature_misses() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        // 「い」の先頭のキーストロークを先走って打つ
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        engine.stroke_key('j'.try_into().unwrap()).unwrap();
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        // 最後のチャンクには次のチャンクがない
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().missed_count(), 3);
+        assert_eq!(result.premature_miss_count(), 1);
+        assert!((result.premature_miss_ratio() - 1.0 / 3.0).abs() < f64::EPSILON);
+
+        engine.reset_keeping_statistics();
+        assert_eq!(engine.accumulated_statistics().premature_miss_count(), 1);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn auto_complete_current_chunk() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "今日缶",
+            [("きょう", 2), ("かん", 1)]
+        )];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        // 打ち始めたキーストロークと矛盾しない候補で補完する
+        engine.stroke_key('k'.try_into().unwrap()).unwrap();
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        assert!(!engine
+            .auto_complete_current_chunk(AutoCompletePolicy::Ideal)
+            .unwrap());
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.key_stroke_info().current_cursor_position(), 5);
+        assert_eq!(display_info.key_stroke_info().key_stroke(), "kilyoukann");
+
+        engine
+            .auto_complete_current_chunk(AutoCompletePolicy::MinKeyStroke)
+            .unwrap();
+        engine.stroke_key('k'.try_into().unwrap()).unwrap();
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        // 遅延確定候補だけではチャンクを確定できない
+        engine.stroke_key('n'.try_into().unwrap()).unwrap();
+        assert!(engine
+            .auto_complete_current_chunk(AutoCompletePolicy::Ideal)
+            .unwrap());
+        assert!(engine
+            .auto_complete_current_chunk(AutoCompletePolicy::Ideal)
+            .is_err());
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().whole_count(), 10);
+        assert_eq!(result.key_stroke().missed_count(), 0);
+        assert_eq!(result.assisted_key_stroke_count(), 5);
+
+        engine.reset_keeping_statistics();
+        assert_eq!(
+            engine.accumulated_statistics().assisted_key_stroke_count(),
+            5
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn append_query_while_typing() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あいん", [("あ"), ("い"), ("ん")]),
+            gen_vocabulary_entry!("か", [("か")]),
+        ];
+        let lap_request = LapRequest::Chunk(NonZeroUsize::new(1).unwrap());
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+
+        engine
+            .append_query(QueryRequest::new(
+                &[&vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        let display_info = engine.construct_display_info(lap_request.clone()).unwrap();
+        assert_eq!(display_info.view_info().view(), "あいんか");
+        assert_eq!(display_info.progress_info().whole_chunk_count(), 4);
+
+        // 末尾だった「ん」も後続のチャンクによって「n」1回で打てるようになる
+        "inka".chars().for_each(|key_stroke| {
+            engine.stroke_key(key_stroke.try_into().unwrap()).unwrap();
+        });
+        assert!(engine.is_finished());
+
+        // 打ち終えた後に追加すると再開できる
+        engine
+            .append_query(QueryRequest::new(
+                &[&vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(!engine.stroke_key('k'.try_into().unwrap()).unwrap());
+        assert!(engine.stroke_key('a'.try_into().unwrap()).unwrap());
+
+        let result = engine.construst_result_statistics(lap_request).unwrap();
+        assert_eq!(result.key_stroke().whole_count(), 7);
+        assert_eq!(result.key_stroke().missed_count(), 0);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn truncate_query_while_typing() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あ", [("あ")]),
+            gen_vocabulary_entry!("いん", [("い"), ("ん")]),
+            gen_vocabulary_entry!("う", [("う")]),
+            gen_vocabulary_entry!("え", [("え")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &vocabularies.iter().collect::<Vec<&VocabularyEntry>>(),
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(4).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.truncate_query(0).is_err());
+        engine.start().unwrap();
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.truncate_query(1).unwrap();
+        engine.truncate_query(0).unwrap();
+
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.view_info().view(), "あいん");
+        assert_eq!(display_info.progress_info().whole_chunk_count(), 3);
+
+        // 末尾になった「ん」は「n」1回では打てない
+        assert!(!engine.stroke_key('i'.try_into().unwrap()).unwrap());
+        assert!(!engine.stroke_key('n'.try_into().unwrap()).unwrap());
+        assert!(engine.stroke_key('n'.try_into().unwrap()).unwrap());
+        assert!(engine.truncate_query(0).is_err());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn truncate_query_ending_with_successive_sokuon() {
+        let vocabularies = [gen_vocabulary_entry!("あっっ", [("あ"), ("っ"), ("っ")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::Vocabulary(gen_vocabulary_entry!("い", [("い")])),
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        engine.truncate_query(0).unwrap();
+
+        // 末尾の「っ」だけでなくそれに続けて打つ前の「っ」の候補も付与し直される
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.view_info().view(), "あっっ");
+        assert_eq!(display_info.key_stroke_info().ideal_key_stroke(), "alltu");
+
+        assert!("altultu".chars().all(|c| engine
+            .stroke_key_with_outcome(c.try_into().unwrap())
+            .unwrap()
+            .is_correct()));
+        assert!(engine.is_finished());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn truncate_query_at_inflight_chunk() {
+        let vocabularies = [
+            gen_vocabulary_entry!("いん", [("い"), ("ん")]),
+            gen_vocabulary_entry!("か", [("か")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        // 「ん」を「n」1回で打ち終えた状態で打っている最中の「ん」が末尾になる
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        engine.stroke_key('n'.try_into().unwrap()).unwrap();
+        engine.truncate_query(0).unwrap();
+
+        let display_info = engine
+            .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(display_info.view_info().view(), "いん");
+        assert_eq!(display_info.key_stroke_info().ideal_key_stroke(), "inn");
+
+        assert!(!engine
+            .stroke_key_with_outcome('k'.try_into().unwrap())
+            .unwrap()
+            .is_correct());
+        let outcome = engine
+            .stroke_key_with_outcome('n'.try_into().unwrap())
+            .unwrap();
+        assert!(outcome.is_correct());
+        assert!(outcome.is_finished());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn finish_by_time_limit() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start_with_limit(Duration::from_secs(10)).unwrap();
+        assert_eq!(engine.time_limit(), Some(Duration::from_secs(10)));
+
+        assert!(!engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap());
+        // 制限時間を過ぎたキーストロークは処理されない
+        assert!(engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::from_secs(11))
+            .unwrap());
+        assert_eq!(engine.remaining_time(), Some(Duration::ZERO));
+        assert!(engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::from_secs(12))
+            .is_err());
+        // 処理されなかったキーストロークはリプレイに含めない
+        let replay = engine.construct_replay().unwrap();
+        assert_eq!(replay.key_strokes().len(), 1);
+        assert_eq!(replay.duration(), Duration::from_secs(1));
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.end_reason(), &TypingEndReason::TimeLimitReached);
+        assert_eq!(result.total_time(), Duration::from_secs(10));
+        assert_eq!(result.key_stroke().whole_count(), 1);
+
+        // 制限時間は開始ごとに設定する
+        engine.reset_keeping_statistics();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        assert_eq!(engine.remaining_time(), None);
+
+        // 制限時間ちょうどに明示的に終了した場合には制限時間による終了とはみなさない
+        engine.reset_keeping_statistics();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start_with_limit(Duration::from_secs(10)).unwrap();
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        engine.finish(Duration::from_secs(10)).unwrap();
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.end_reason(), &TypingEndReason::Finished);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_result_so_far() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let lap_request = LapRequest::Chunk(NonZeroUsize::new(1).unwrap());
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.construct_result_so_far(lap_request.clone()).is_err());
+        engine.start().unwrap();
+
+        // キーストロークがなくても結果を構築できる
+        let result = engine
+            .construct_result_so_far_with_elapsed_time(lap_request.clone(), Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(result.end_reason(), &TypingEndReason::InProgress);
+        assert_eq!(result.key_stroke().whole_count(), 0);
+
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(2))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('u'.try_into().unwrap(), Duration::from_secs(3))
+            .unwrap();
+        let result = engine
+            .construct_result_so_far_with_elapsed_time(lap_request.clone(), Duration::from_secs(4))
+            .unwrap();
+        assert_eq!(result.total_time(), Duration::from_secs(4));
+        assert_eq!(result.key_stroke().whole_count(), 1);
+        assert_eq!(result.key_stroke().missed_count(), 1);
+        assert!(engine.construst_result_statistics(lap_request).is_err());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn replay_typing_session() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")]),
+            gen_vocabulary_entry!("えお", [("え"), ("お")]),
+        ];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        let mut replaying_engine = engine.clone();
+
+        engine.start().unwrap();
+        ['a', 'u', 'i', 'u']
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, key_stroke)| {
+                engine
+                    .stroke_key_with_elapsed_time(
+                        key_stroke.try_into().unwrap(),
+                        Duration::from_secs(i as u64 + 1),
+                    )
+                    .unwrap();
+            });
+
+        let replay = engine.construct_replay().unwrap();
+        assert_eq!(replay.key_strokes().len(), 4);
+        assert_eq!(replay.key_strokes()[1].key_stroke(), 'u');
+        assert_eq!(replay.duration(), Duration::from_secs(4));
+
+        assert!(replaying_engine.replay(&replay, 0.0).is_err());
+
+        let frames = replaying_engine.replay(&replay, 2.0).unwrap();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames[0].playback_time(), Duration::ZERO);
+        assert_eq!(frames[4].playback_time(), Duration::from_secs(2));
+        assert_eq!(
+            frames[4].display_info(),
+            &engine
+                .construct_display_info_with_elapsed_time(
+                    LapRequest::Chunk(NonZeroUsize::MIN),
+                    Duration::from_secs(4)
+                )
+                .unwrap()
+        );
+        assert_eq!(replaying_engine.state_digest(), engine.state_digest());
+
+        // 異なるクエリでは再生できない
+        let mut another_engine = TypingEngine::new();
+        another_engine
+            .init(QueryRequest::new(
+                &[&vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(another_engine.replay(&replay, 1.0).is_err());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn replay_operations_in_strict_mode() {
+        let vocabularies = [
+            gen_vocabulary_entry!("かき", [("か"), ("き")]),
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("う", [("う")]),
+        ];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &vocabularies.iter().collect::<Vec<&VocabularyEntry>>(),
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.set_strict_mode(true).unwrap();
+        let mut replaying_engine = engine.clone();
+
+        engine.start().unwrap();
+        let stroke = |engine: &mut TypingEngine, key_stroke: char, elapsed_secs: u64| {
+            engine
+                .stroke_key_with_elapsed_time(
+                    key_stroke.try_into().unwrap(),
+                    Duration::from_secs(elapsed_secs),
+                )
+                .unwrap()
+        };
+        stroke(&mut engine, 'k', 1);
+        stroke(&mut engine, 'x', 2);
+        engine.backspace().unwrap();
+        engine.hint().unwrap();
+        stroke(&mut engine, 'a', 3);
+        stroke(&mut engine, 'k', 4);
+        engine
+            .auto_complete_current_chunk_with_elapsed_time(
+                AutoCompletePolicy::Ideal,
+                Duration::from_secs(5),
+            )
+            .unwrap();
+        engine
+            .skip_current_vocabulary_with_elapsed_time(Duration::from_secs(6))
+            .unwrap();
+        assert!(stroke(&mut engine, 'u', 7));
+
+        // 自動補完などで与えたキーストロークはプレイヤーのキーストロークとしては記録しない
+        let replay = engine.construct_replay().unwrap();
+        assert_eq!(replay.key_strokes().len(), 5);
+        assert_eq!(
+            replay.operations(),
+            &[
+                (2, ReplayOperation::Backspace),
+                (2, ReplayOperation::Hint),
+                (
+                    4,
+                    ReplayOperation::AutoComplete {
+                        policy: AutoCompletePolicy::Ideal,
+                        elapsed_time: Duration::from_secs(5)
+                    }
+                ),
+                (
+                    4,
+                    ReplayOperation::SkipVocabulary {
+                        elapsed_time: Duration::from_secs(6)
+                    }
+                ),
+            ]
+        );
+        assert_eq!(replay.duration(), Duration::from_secs(7));
+
+        let frames = replaying_engine.replay(&replay, 1.0).unwrap();
+        assert_eq!(frames.len(), 10);
+        // 時間に依存しない操作のフレームは直前のフレームと同時に表示する
+        assert_eq!(frames[3].playback_time(), Duration::from_secs(2));
+        assert_eq!(frames[4].playback_time(), Duration::from_secs(2));
+        assert_eq!(
+            frames[9].display_info(),
+            &engine
+                .construct_display_info_with_elapsed_time(
+                    LapRequest::Chunk(NonZeroUsize::MIN),
+                    Duration::from_secs(7)
+                )
+                .unwrap()
+        );
+        assert!(replaying_engine.is_finished());
+        assert_eq!(replaying_engine.state_digest(), engine.state_digest());
+
+        let lap_request = LapRequest::Chunk(NonZeroUsize::MIN);
+        assert_eq!(
+            replaying_engine
+                .construst_result_statistics(lap_request.clone())
+                .unwrap(),
+            engine
+                .construst_result_statistics(lap_request.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            replaying_engine
+                .construst_result_statistics(lap_request)
+                .unwrap()
+                .spell_hints()
+                .len(),
+            1
+        );
+        assert_eq!(replaying_engine.construct_replay().unwrap(), replay);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_ghost() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        let mut previous_engine = engine.clone();
+
+        previous_engine.start().unwrap();
+        previous_engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        previous_engine
+            .stroke_key_with_elapsed_time('u'.try_into().unwrap(), Duration::from_secs(2))
+            .unwrap();
+        previous_engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::from_secs(3))
+            .unwrap();
+
+        let ghost = engine
+            .construct_ghost(&previous_engine.construct_replay().unwrap())
+            .unwrap();
+        assert_eq!(ghost.duration(), Duration::from_secs(3));
+
+        let cursor = ghost.cursor_at(Duration::from_millis(500));
+        assert_eq!(cursor.key_stroke_cursor_position(), 0);
+        assert_eq!(cursor.spell_cursor_positions(), &vec![0]);
+        assert!((cursor.interpolated_key_stroke_cursor_position() - 0.5).abs() < f64::EPSILON);
+
+        // ミスタイプではカーソルは進まない
+        let cursor = ghost.cursor_at(Duration::from_millis(1500));
+        assert_eq!(cursor.key_stroke_cursor_position(), 1);
+        assert_eq!(cursor.view_cursor_positions(), &vec![1]);
+        assert!((cursor.interpolated_key_stroke_cursor_position() - 1.0).abs() < f64::EPSILON);
+
+        let cursor = ghost.cursor_at(Duration::from_millis(2500));
+        assert!((cursor.interpolated_key_stroke_cursor_position() - 1.5).abs() < f64::EPSILON);
+
+        let cursor = ghost.cursor_at(Duration::from_secs(10));
+        assert_eq!(cursor.key_stroke_cursor_position(), 2);
+        assert_eq!(cursor.spell_cursor_positions(), &vec![2]);
+
+        // 元のエンジンは開始されない
+        assert!(engine.start().is_ok());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_display_info_with_newline_separator() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("きょう", [("きょ", 2), ("う")]),
+        ];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::Newline,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(
+                LapRequest::Chunk(NonZeroUsize::MIN),
+                Duration::from_secs(1),
+            )
+            .unwrap();
+
+        // 改行は文字列に含まれない
+        assert_eq!(display_info.view_info().view(), "あいきょうあい");
+        assert_eq!(display_info.spell_info().spell(), "あいきょうあい");
+        assert_eq!(display_info.key_stroke_info().key_stroke(), "aikyouai");
+        assert_eq!(display_info.line_break_info().view_positions(), &vec![2, 5]);
+        assert_eq!(
+            display_info.line_break_info().spell_positions(),
+            &vec![2, 5]
+        );
+        assert_eq!(
+            display_info.line_break_info().key_stroke_positions(),
+            &vec![2, 6]
+        );
+
+        let transformed = display_info.transform_key_stroke(|c| {
+            if c == 'y' {
+                "[y]".to_string()
+            } else {
+                c.to_string()
+            }
+        });
+        assert_eq!(transformed.key_stroke_info().key_stroke(), "aik[y]ouai");
+        assert_eq!(
+            display_info.upcoming_candidate_counts(),
+            &vec![1, 3, 1, 1, 1, 1, 1]
+        );
+        assert_eq!(
+            transformed.upcoming_candidate_counts(),
+            &vec![1, 3, 1, 1, 1, 1, 1, 1, 1]
+        );
+        #[cfg(feature = "statistics")]
+        {
+            assert_eq!(
+                display_info.lap_infos()[0].key_stroke().lap_end_positions(),
+                &vec![0, 1, 4, 5, 6, 7]
+            );
+            assert_eq!(
+                transformed.lap_infos()[0].key_stroke().lap_end_positions(),
+                &vec![0, 1, 6, 7, 8, 9]
+            );
+            assert_eq!(
+                transformed.lap_infos()[0]
+                    .ideal_key_stroke()
+                    .lap_end_positions(),
+                &vec![0, 1, 6, 7, 8, 9]
+            );
+        }
+        assert_eq!(
+            transformed.line_break_info().key_stroke_positions(),
+            &vec![2, 8]
+        );
+        assert_eq!(
+            transformed.line_break_info().view_positions(),
+            display_info.line_break_info().view_positions()
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_display_info_with_ideal_key_stroke() {
+        let vocabularies = [gen_vocabulary_entry!("きょう", [("きょ", 2), ("う")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let construct_key_stroke_display_info = |engine: &TypingEngine| {
+            engine
+                .construct_display_info_with_elapsed_time(
+                    LapRequest::Chunk(NonZeroUsize::MIN),
+                    Duration::from_secs(1),
+                )
+                .unwrap()
+                .key_stroke_info()
+                .clone()
+        };
+
+        ['k', 'i', 'l', 'y', 'o']
+            .into_iter()
+            .for_each(|key_stroke| {
+                engine
+                    .stroke_key_with_elapsed_time(
+                        key_stroke.try_into().unwrap(),
+                        Duration::from_secs(1),
+                    )
+                    .unwrap();
+            });
+
+        // 実際に打ったキーストロークに関わらず理想的なキーストロークで表す
+        let key_stroke_display_info = construct_key_stroke_display_info(&engine);
+        assert_eq!(key_stroke_display_info.key_stroke(), "kilyou");
+        assert_eq!(key_stroke_display_info.current_cursor_position(), 5);
+        assert_eq!(key_stroke_display_info.ideal_key_stroke(), "kyou");
+        assert_eq!(key_stroke_display_info.ideal_cursor_position(), 3);
+
+        let key_stroke_display_info = key_stroke_display_info.to_uppercase();
+        assert_eq!(key_stroke_display_info.ideal_key_stroke(), "KYOU");
+        assert_eq!(key_stroke_display_info.ideal_cursor_position(), 3);
+
+        engine
+            .stroke_key_with_elapsed_time('u'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        let key_stroke_display_info = construct_key_stroke_display_info(&engine);
+        assert_eq!(key_stroke_display_info.ideal_cursor_position(), 4);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_query_with_romanization_preference() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "しちゅう",
+            [("し"), ("ち"), ("ゅ"), ("う")]
+        )];
+        let construct_engine = |romanization_preference: Option<RomanizationPreference>| {
+            let mut engine = TypingEngine::new();
+            engine.set_romanization_preference(romanization_preference);
+            engine
+                .init(QueryRequest::new(
+                    &[&vocabularies[0]],
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                    VocabularySeparator::None,
+                    VocabularyOrder::InOrder,
+                ))
+                .unwrap();
+            engine.start().unwrap();
+            engine
+        };
+        let construct_key_stroke_display_info = |engine: &TypingEngine| {
+            engine
+                .construct_display_info_with_elapsed_time(
+                    LapRequest::Chunk(NonZeroUsize::MIN),
+                    Duration::from_secs(1),
+                )
+                .unwrap()
+                .key_stroke_info()
+                .clone()
+        };
+
+        let engine = construct_engine(None);
+        let key_stroke_display_info = construct_key_stroke_display_info(&engine);
+        assert_eq!(key_stroke_display_info.key_stroke(), "sityuu");
+        assert_eq!(key_stroke_display_info.ideal_key_stroke(), "sityuu");
+
+        // キーストローク数が同じ場合のみ好みの方式を優先しもう一方の方式は後回しにする
+        let engine = construct_engine(Some(RomanizationPreference::Prefer(
+            RomanizationStyle::Hepburn,
+        )));
+        let key_stroke_display_info = construct_key_stroke_display_info(&engine);
+        assert_eq!(key_stroke_display_info.key_stroke(), "cichuu");
+        assert_eq!(key_stroke_display_info.ideal_key_stroke(), "cichuu");
+
+        // もう一方の方式のキーストロークでは打てない
+        let mut engine = construct_engine(Some(RomanizationPreference::Only(
+            RomanizationStyle::Hepburn,
+        )));
+        let key_stroke_display_info = construct_key_stroke_display_info(&engine);
+        assert_eq!(key_stroke_display_info.key_stroke(), "cichuu");
+        engine
+            .stroke_key_with_elapsed_time('s'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::from_secs(2))
+            .unwrap();
+        let key_stroke_display_info = construct_key_stroke_display_info(&engine);
+        assert_eq!(key_stroke_display_info.key_stroke(), "shichuu");
+        assert_eq!(key_stroke_display_info.missed_positions(), &vec![1]);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "analysis")]
+    #[test]
+    fn set_romanization_preference_by_habit() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "しちゅう",
+            [("し"), ("ち"), ("ゅ"), ("う")]
+        )];
+        let query_request = || {
+            QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            )
+        };
+
+        let mut engine = TypingEngine::new();
+        engine.init(query_request()).unwrap();
+        engine.start().unwrap();
+        "shi".chars().enumerate().for_each(|(i, key_stroke)| {
+            engine
+                .stroke_key_with_elapsed_time(
+                    key_stroke.try_into().unwrap(),
+                    Duration::from_secs(i as u64 + 1),
+                )
+                .unwrap();
+        });
+        let skill_statistics = engine.construct_skill_statistics().unwrap();
+
+        // 習慣的に打っている方式がキーストローク数が同じ場合に優先される
+        let mut engine = TypingEngine::new();
+        engine.set_romanization_preference_by_habit(skill_statistics.romanization_habit());
+        assert_eq!(
+            engine.romanization_preference(),
+            Some(&RomanizationPreference::Prefer(RomanizationStyle::Hepburn))
+        );
+        engine.init(query_request()).unwrap();
+        engine.start().unwrap();
+        let key_stroke_display_info = engine
+            .construct_display_info_with_elapsed_time(
+                LapRequest::Chunk(NonZeroUsize::MIN),
+                Duration::from_secs(1),
+            )
+            .unwrap()
+            .key_stroke_info()
+            .clone();
+        assert_eq!(key_stroke_display_info.ideal_key_stroke(), "cichuu");
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn current_acceptable_key_strokes() {
+        let vocabularies = [gen_vocabulary_entry!("しんか", [("し"), ("ん"), ("か")])];
+        let vocabularies_slice: Vec<&VocabularyEntry> = vocabularies.iter().collect();
+
+        let query_request = QueryRequest::new(
+            &vocabularies_slice,
+            VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+            VocabularySeparator::None,
+            VocabularyOrder::InOrder,
+        );
+
+        let mut engine = TypingEngine::new();
+        engine.set_strict_mode(true).unwrap();
+        engine.init(query_request).unwrap();
+        assert!(engine.current_acceptable_key_strokes().is_empty());
+
+        engine.start().unwrap();
+        let chars = |key_strokes: Vec<KeyStrokeChar>| -> Vec<char> {
+            key_strokes.into_iter().map(char::from).collect()
+        };
+
+        assert_eq!(
+            chars(engine.current_acceptable_key_strokes()),
+            vec!['s', 'c']
+        );
+
+        engine
+            .stroke_key_with_elapsed_time('s'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap();
+        assert_eq!(
+            chars(engine.current_acceptable_key_strokes()),
+            vec!['i', 'h']
+        );
+
+        engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('n'.try_into().unwrap(), Duration::new(3, 0))
+            .unwrap();
+        // 「ん」をnで打ち終えたので次のチャンク先頭のキーストロークも受け付ける
+        assert_eq!(
+            chars(engine.current_acceptable_key_strokes()),
+            vec!['k', 'c', 'n']
+        );
+
+        engine
+            .stroke_key_with_elapsed_time('j'.try_into().unwrap(), Duration::new(4, 0))
+            .unwrap();
+        assert!(engine.current_acceptable_key_strokes().is_empty());
+
+        engine.backspace().unwrap();
+        engine
+            .stroke_key_with_elapsed_time('k'.try_into().unwrap(), Duration::new(5, 0))
+            .unwrap();
+        assert_eq!(chars(engine.current_acceptable_key_strokes()), vec!['a']);
+
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(6, 0))
+            .unwrap();
+        assert!(engine.current_acceptable_key_strokes().is_empty());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn inflight_candidates() {
+        let vocabularies = [gen_vocabulary_entry!("じょう", [("じょ", 2), ("う")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.inflight_candidates().is_empty());
+
+        engine.start().unwrap();
+        let candidates = engine.inflight_candidates();
+        assert_eq!(candidates[0].whole_key_stroke(), "jo");
+        assert!(candidates[0].is_ideal());
+        assert!(candidates
+            .iter()
+            .all(|candidate| candidate.cursor_position() == 0));
+
+        engine
+            .stroke_key_with_elapsed_time('z'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+
+        let candidates = engine.inflight_candidates();
+        assert!(!candidates.is_empty());
+        assert!(candidates.iter().all(|candidate| {
+            candidate.whole_key_stroke().starts_with("zi")
+                && candidate.cursor_position() == 2
+                && !candidate.is_ideal()
+        }));
+        assert!(candidates.iter().any(|candidate| {
+            candidate.key_stroke_elements() == ["zi".to_string(), "lyo".to_string()]
+        }));
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn stroke_key_with_shift_in_shift_key_mode() {
+        let vocabularies = [gen_vocabulary_entry!("Hi!", [("H"), ("i"), ("!")])];
+        let mut engine = TypingEngine::new();
+        engine.set_shift_key_mode(true).unwrap();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        assert!(engine.set_shift_key_mode(false).is_err());
+
+        assert!(!engine
+            .stroke_key_with_shift_with_elapsed_time(
+                'h'.try_into().unwrap(),
+                false,
+                Duration::new(1, 0)
+            )
+            .unwrap());
+        assert!(!engine
+            .stroke_key_with_shift_with_elapsed_time(
+                'h'.try_into().unwrap(),
+                true,
+                Duration::new(2, 0)
+            )
+            .unwrap());
+        assert!(!engine
+            .stroke_key_with_shift_with_elapsed_time(
+                'i'.try_into().unwrap(),
+                false,
+                Duration::new(3, 0)
+            )
+            .unwrap());
+        assert!(engine
+            .stroke_key_with_shift_with_elapsed_time(
+                '1'.try_into().unwrap(),
+                true,
+                Duration::new(4, 0)
+            )
+            .unwrap());
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Spell(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().whole_count(), 3);
+        assert_eq!(result.key_stroke().missed_count(), 1);
+        assert_eq!(result.shift_key_stroke_count(), 2);
+        assert_eq!(result.physical_key_stroke_count(), 5);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn stroke_key_with_outcome_reports_boundaries() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あき", [("あ"), ("き")]),
+            gen_vocabulary_entry!("きょ", [("きょ", 2)]),
+        ];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.set_lap_request(LapRequest::Chunk(NonZeroUsize::new(2).unwrap()));
+        engine.start().unwrap();
+
+        let mut stroke = |c: char, elapsed_secs: u64| {
+            engine
+                .stroke_key_with_outcome_with_elapsed_time(
+                    c.try_into().unwrap(),
+                    Duration::new(elapsed_secs, 0),
+                )
+                .unwrap()
+        };
+
+        let outcome = stroke('a', 1);
+        assert!(outcome.is_correct());
+        assert_eq!(outcome.finished_spell_count(), 1);
+        assert_eq!(outcome.confirmed_chunk_indices(), [0]);
+        assert!(outcome.completed_vocabulary_indices().is_empty());
+        assert!(outcome.completed_lap_indices().is_empty());
+
+        assert!(!stroke('q', 2).is_correct());
+        stroke('k', 3);
+        let outcome = stroke('i', 4);
+        assert_eq!(outcome.confirmed_chunk_indices(), [1]);
+        assert_eq!(outcome.completed_vocabulary_indices(), [0]);
+        #[cfg(feature = "statistics")]
+        assert_eq!(outcome.completed_lap_indices(), [0]);
+        #[cfg(not(feature = "statistics"))]
+        assert!(outcome.completed_lap_indices().is_empty());
+
+        // 複数文字を個別で入力する場合には1文字目を打ち終えた時点で綴りを打ち終えたとみなす
+        assert_eq!(stroke('k', 5).finished_spell_count(), 0);
+        assert_eq!(stroke('i', 6).finished_spell_count(), 1);
+        stroke('x', 7);
+        stroke('y', 8);
+        let outcome = stroke('o', 9);
+        assert!(outcome.is_finished());
+        assert_eq!(outcome.finished_spell_count(), 1);
+        assert_eq!(outcome.confirmed_chunk_indices(), [2]);
+        assert_eq!(outcome.completed_vocabulary_indices(), [1]);
+        assert!(outcome.completed_lap_indices().is_empty());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn configured_display_info_is_same_as_constructed_from_whole_history() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あき", [("あ"), ("き")]),
+            gen_vocabulary_entry!("きょ", [("きょ", 2)]),
+        ];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        // 速度は呼び出した時刻によって異なるので比較しない
+        let assert_same_display_info = |engine: &TypingEngine, lap_request: &LapRequest| {
+            let configured_display_info = engine.construct_configured_display_info().unwrap();
+
+            let mut engine_without_cache = engine.clone();
+            engine_without_cache
+                .display_cache
+                .reset_display_string_builders();
+            let display_info = engine_without_cache
+                .construct_display_info(lap_request.clone())
+                .unwrap();
+
+            assert_eq!(
+                configured_display_info.spell_info(),
+                display_info.spell_info()
+            );
+            assert_eq!(
+                configured_display_info.key_stroke_info(),
+                display_info.key_stroke_info()
+            );
+        };
+        let lap_request = LapRequest::Spell(NonZeroUsize::new(2).unwrap());
+        engine.set_lap_request(lap_request.clone());
+        engine.start().unwrap();
+
+        "aqkikixyo".chars().enumerate().for_each(|(i, c)| {
+            engine
+                .stroke_key_with_elapsed_time(c.try_into().unwrap(), Duration::new(i as u64, 0))
+                .unwrap();
+
+            assert_same_display_info(&engine, &lap_request);
+        });
+
+        // 途中でラップ要求を変更しても変更後のラップ要求で構築する
+        let lap_request = LapRequest::KeyStroke(NonZeroUsize::new(3).unwrap());
+        engine.set_lap_request(lap_request.clone());
+        assert_same_display_info(&engine, &lap_request);
+    }
+
+    // 確定したチャンクを全て走査し未処理のチャンクも全て描画して表示用の情報を構築する
+    // キーストロークのたびに更新しているキャッシュを使わずにクエリから求める
+    #[cfg(not(feature = "ascii_only"))]
+    fn construct_display_info_by_replaying_whole_history(
+        engine: &TypingEngine,
+        lap_requests: &[LapRequest],
+        elapsed_time: Duration,
+    ) -> DisplayInfo {
+        let processed_chunk_info = engine.processed_chunk_info.as_ref().unwrap();
+        let vocabulary_infos = engine.vocabulary_infos.as_ref().unwrap();
+        let vocabulary_end_chunk_counts = construct_vocabulary_end_chunk_counts(vocabulary_infos);
+
+        let replay = |lap_request: &LapRequest| {
+            let mut display_string_builder = DisplayStringBuilder::new(lap_request.clone());
+            processed_chunk_info
+                .apply_confirmed_chunks(&mut display_string_builder, &vocabulary_end_chunk_counts);
+
+            processed_chunk_info.construct_display_info_with_builder(
+                &display_string_builder,
+                &vocabulary_end_chunk_counts,
+            )
+        };
+
+        #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+        let (spell_display_info, key_stroke_display_info, display_statistics) =
+            replay(&lap_requests[0]);
+        #[cfg(feature = "statistics")]
+        let key_stroke_display_info =
+            key_stroke_display_info.with_combo(display_statistics.1 .0, display_statistics.1 .1);
+        #[cfg(feature = "statistics")]
+        let lap_infos = lap_requests
+            .iter()
+            .map(|lap_request| {
+                let (spell_display_info, key_stroke_display_info, (lap_summaries, _)) =
+                    replay(lap_request);
+
+                LapDisplayInfo::new(
+                    lap_request.clone(),
+                    &spell_display_info,
+                    &key_stroke_display_info,
+                    lap_summaries,
+                )
+            })
+            .collect();
+
+        let mut presentation_spell = String::new();
+        write_presentation_spell(
+            vocabulary_infos,
+            spell_display_info.spell().chars().count(),
+            &mut presentation_spell,
+        );
+        let spell_display_info = spell_display_info.with_spell(presentation_spell);
+
+        let mut ideal_key_stroke = String::new();
+        let ideal_cursor_position =
+            processed_chunk_info.write_ideal_key_stroke(&mut ideal_key_stroke);
+        let key_stroke_display_info =
+            key_stroke_display_info.with_ideal_key_stroke(ideal_key_stroke, ideal_cursor_position);
+        let progress_display_info = processed_chunk_info
+            .construct_progress_display_info()
+            .with_ideal_key_stroke_progress(
+                key_stroke_display_info.ideal_cursor_position(),
+                key_stroke_display_info.ideal_key_stroke().chars().count(),
+                elapsed_time,
+            );
+
+        let view_display_info = ViewDisplayInfo::new(
+            &spell_display_info,
+            vocabulary_infos
+                .iter()
+                .map(|vocabulary_info| vocabulary_info.view())
+                .collect(),
+            &construct_view_position_of_spell_positions(vocabulary_infos),
+        );
+
+        let mut chunk_count = 0;
+        let current_vocabulary_assets = vocabulary_infos
+            .iter()
+            .find(|vocabulary_info| {
+                chunk_count += vocabulary_info.chunk_count().get();
+                progress_display_info.finished_chunk_count() < chunk_count
+            })
+            .map(|vocabulary_info| vocabulary_info.assets().clone());
+
+        let (spell_heads, key_stroke_heads) = processed_chunk_info.construct_chunk_head_positions();
+        let mut view_positions = vec![];
+        let mut spell_positions = vec![];
+        let mut key_stroke_positions = vec![];
+        let mut view_position = 0;
+        let mut chunk_index = 0;
+        vocabulary_infos
+            .iter()
+            .take(vocabulary_infos.len() - 1)
+            .for_each(|vocabulary_info| {
+                view_position += vocabulary_info.view().chars().count();
+                chunk_index += vocabulary_info.chunk_count().get();
+
+                if vocabulary_info.has_trailing_line_break() {
+                    view_positions.push(view_position);
+                    spell_positions.push(spell_heads[chunk_index]);
+                    key_stroke_positions.push(key_stroke_heads[chunk_index]);
+                }
+            });
+
+        DisplayInfo::new(
+            view_display_info,
+            spell_display_info,
+            key_stroke_display_info,
+            progress_display_info,
+            processed_chunk_info.construct_upcoming_candidate_counts(),
+            current_vocabulary_assets,
+            processed_chunk_info.construct_speed_display_info(elapsed_time),
+            #[cfg(feature = "statistics")]
+            lap_infos,
+            LineBreakDisplayInfo::new(view_positions, spell_positions, key_stroke_positions),
+            None,
+        )
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn display_info_is_same_as_constructed_by_replaying_whole_history() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あき", [("あ"), ("き")]),
+            gen_vocabulary_entry!("きょ", [("きょ", 2)]),
+            gen_vocabulary_entry!("っった", [("っ"), ("っ"), ("た")]),
+            gen_vocabulary_entry!("んな", [("ん"), ("な")]),
+        ];
+        let lap_requests = [
+            LapRequest::KeyStroke(NonZeroUsize::new(3).unwrap()),
+            LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()),
+            LapRequest::Spell(NonZeroUsize::new(2).unwrap()),
+            LapRequest::Chunk(NonZeroUsize::new(2).unwrap()),
+            LapRequest::Vocabulary(NonZeroUsize::MIN),
+            LapRequest::CustomSpellPositions(vec![1, 4, 6]),
+            LapRequest::Duration(Duration::new(3, 0)),
+        ];
+        let query_request = |count: usize| {
+            QueryRequest::new(
+                &[
+                    &vocabularies[0],
+                    &vocabularies[1],
+                    &vocabularies[2],
+                    &vocabularies[3],
+                ],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(count).unwrap()),
+                VocabularySeparator::Newline,
+                VocabularyOrder::InOrder,
+            )
+        };
+
+        let mut engine = TypingEngine::new();
+        engine.set_candidate_window(NonZeroUsize::new(2));
+        engine.set_strict_mode(true).unwrap();
+        engine.set_lap_request(lap_requests[3].clone());
+        engine.init(query_request(8)).unwrap();
+        engine.start().unwrap();
+
+        let mut elapsed_time = Duration::ZERO;
+        let mut assert_same_display_info = |engine: &TypingEngine| {
+            elapsed_time += Duration::new(1, 0);
+
+            // 設定したラップ要求・保持しているラップ要求・初めて要求されたラップ要求のそれぞれを先頭にする
+            lap_requests.iter().enumerate().for_each(|(i, _)| {
+                let mut lap_requests = lap_requests.to_vec();
+                lap_requests.rotate_left(i);
+
+                assert_eq!(
+                    engine
+                        .construct_display_info_inner(
+                            &lap_requests,
+                            elapsed_time,
+                            DisplayStringBuffers::default()
+                        )
+                        .unwrap(),
+                    construct_display_info_by_replaying_whole_history(
+                        engine,
+                        &lap_requests,
+                        elapsed_time
+                    )
+                );
+            });
+        };
+        let mut key_stroke_time = Duration::ZERO;
+        let mut stroke = |engine: &mut TypingEngine, key_strokes: &str| {
+            key_strokes.chars().for_each(|c| {
+                key_stroke_time += Duration::from_millis(700);
+                engine
+                    .stroke_key_with_elapsed_time(c.try_into().unwrap(), key_stroke_time)
+                    .unwrap();
+            });
+        };
+        assert_same_display_info(&engine);
+
+        // 「あき」を打つ途中でミスタイプして消す
+        stroke(&mut engine, "ak");
+        stroke(&mut engine, "q");
+        assert_same_display_info(&engine);
+        engine.backspace().unwrap();
+        stroke(&mut engine, "i");
+        assert_same_display_info(&engine);
+
+        // 「きょ」を分割して打つ途中で確定する
+        stroke(&mut engine, "ki");
+        assert_same_display_info(&engine);
+        stroke(&mut engine, "xyo");
+        assert_same_display_info(&engine);
+
+        // 「っった」の途中で語彙を飛ばす
+        stroke(&mut engine, "t");
+        assert_same_display_info(&engine);
+        engine
+            .skip_current_vocabulary_with_elapsed_time(Duration::new(10, 0))
+            .unwrap();
+        assert_same_display_info(&engine);
+
+        // 「んな」の「ん」を打った後にクエリを切り詰めてから追加する
+        stroke(&mut engine, "n");
+        assert_same_display_info(&engine);
+        engine.truncate_query(1).unwrap();
+        assert_same_display_info(&engine);
+        engine.append_query(query_request(2)).unwrap();
+        assert_same_display_info(&engine);
+
+        stroke(&mut engine, "nnaa");
+        assert_same_display_info(&engine);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_display_info_reusing_previous_one() {
+        let vocabularies = [gen_vocabulary_entry!("巨大", [("きょ"), ("だい")])
+            .with_presentation_spell("キョダイ".to_string())
+            .unwrap()];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let lap_request = LapRequest::Spell(NonZeroUsize::new(1).unwrap());
+        let mut display_info = engine.construct_display_info(lap_request.clone()).unwrap();
+
+        "kixyo".chars().enumerate().for_each(|(i, c)| {
+            engine
+                .stroke_key_with_elapsed_time(c.try_into().unwrap(), Duration::new(i as u64, 0))
+                .unwrap();
+
+            engine
+                .construct_display_info_reusing(lap_request.clone(), &mut display_info)
+                .unwrap();
+            let expected = engine.construct_display_info(lap_request.clone()).unwrap();
+
+            // 速度は呼び出した時刻によって異なるので比較しない
+            assert_eq!(display_info.view_info(), expected.view_info());
+            assert_eq!(display_info.spell_info(), expected.spell_info());
+            assert_eq!(display_info.key_stroke_info(), expected.key_stroke_info());
+        });
+        assert_eq!(display_info.spell_info().spell(), "キョダイ");
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn init_duration_is_recorded() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+        assert!(engine.init_duration().is_none());
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.init_duration().is_some());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn stroke_key_measures_elapsed_time_by_configured_clock() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        struct ManualClock(Arc<AtomicU64>);
+
+        impl Clock for ManualClock {
+            fn now(&self) -> Duration {
+                Duration::from_millis(self.0.load(Ordering::SeqCst))
+            }
+        }
+
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let now = Arc::new(AtomicU64::new(1000));
+        let mut engine = TypingEngine::new();
+        engine.set_clock(ManualClock(now.clone()));
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        now.store(1500, Ordering::SeqCst);
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+
+        assert_eq!(
+            engine
+                .processed_chunk_info
+                .as_ref()
+                .unwrap()
+                .last_key_stroke_time(),
+            Some(Duration::from_millis(500))
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn typing_race_ranks_players_by_progress() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+
+        let mut race = TypingRace::new(engine, NonZeroUsize::new(3).unwrap()).unwrap();
+        assert!(race.construct_standings().is_err());
+        race.start().unwrap();
+
+        race.stroke_key_with_elapsed_time(1, 'a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        race.stroke_key_with_elapsed_time(2, 'a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        assert!(race
+            .stroke_key_with_elapsed_time(2, 'i'.try_into().unwrap(), Duration::from_secs(2))
+            .unwrap());
+        assert!(race
+            .stroke_key_with_elapsed_time(3, 'a'.try_into().unwrap(), Duration::from_secs(2))
+            .is_err());
+
+        let standings = race
+            .construct_standings_with_elapsed_time(Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(
+            standings
+                .iter()
+                .map(|standing| (
+                    standing.player(),
+                    standing.rank(),
+                    standing.ideal_key_stroke_cursor_position()
+                ))
+                .collect::<Vec<_>>(),
+            vec![(2, 1, 2), (1, 2, 1), (0, 3, 0)]
+        );
+        assert_eq!(standings[0].completion_time(), Some(Duration::from_secs(2)));
+        assert_eq!(standings[0].ideal_key_stroke_count(), 2);
+        assert_eq!(standings[1].completion_time(), None);
+        // 終了した選手の速度は終了時点で計測する
+        assert_eq!(standings[0].wpm(), 12.0);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn hand_off_rest_of_query_keeping_chunk_head_constraint() {
+        let vocabularies = [gen_vocabulary_entry!("あっち", [("あ"), ("っ"), ("ち")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('c'.try_into().unwrap(), Duration::from_secs(2))
+            .unwrap();
+
+        let mut next_engine = engine.hand_off().unwrap();
+        assert!(engine.is_finished());
+        assert!(engine.hand_off().is_err());
+
+        next_engine.start().unwrap();
+        let display_info = next_engine
+            .construct_display_info_with_elapsed_time(
+                LapRequest::Chunk(NonZeroUsize::new(1).unwrap()),
+                Duration::ZERO,
+            )
+            .unwrap();
+        assert_eq!(display_info.view_info().view(), "あっち");
+        assert_eq!(
+            display_info.view_info().current_cursor_positions(),
+            &vec![2]
+        );
+        assert_eq!(display_info.spell_info().spell(), "ち");
+        assert_eq!(display_info.key_stroke_info().key_stroke(), "chi");
+
+        // 「っ」を「c」で打ったので「ti」では打てない
+        assert!(!next_engine
+            .stroke_key_with_elapsed_time('t'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap());
+        for (i, key_stroke) in "chi".chars().enumerate() {
+            next_engine
+                .stroke_key_with_elapsed_time(
+                    key_stroke.try_into().unwrap(),
+                    Duration::from_secs(i as u64 + 2),
+                )
+                .unwrap();
+        }
+        assert!(next_engine.is_finished());
+
+        let lap_request = LapRequest::Chunk(NonZeroUsize::new(1).unwrap());
+        let result = engine
+            .construst_result_statistics(lap_request.clone())
+            .unwrap()
+            .merge(
+                &next_engine
+                    .construst_result_statistics(lap_request)
+                    .unwrap(),
+            );
+        assert_eq!(result.key_stroke().whole_count(), 5);
+        assert_eq!(result.key_stroke().missed_count(), 1);
+        assert_eq!(result.ideal_key_stroke().whole_count(), 4);
+        assert_eq!(result.total_time(), Duration::from_secs(6));
+        assert_eq!(result.end_reason(), &TypingEndReason::Completed);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_result_with_statistics_of_each_vocabulary() {
+        let vocabularies = [
+            gen_vocabulary_entry!("柿", [("かき", 1)]),
+            gen_vocabulary_entry!("く", [("く")]),
+        ];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::WhiteSpace,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        for (i, key_stroke) in "kaxki k".chars().enumerate() {
+            engine
+                .stroke_key_with_elapsed_time(
+                    key_stroke.try_into().unwrap(),
+                    Duration::from_secs(i as u64 + 1),
+                )
+                .unwrap();
+        }
+        engine.finish(Duration::from_secs(10)).unwrap();
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+
+        // 途中までしか打っていない語彙は含まれない
+        let vocabularies = result.vocabularies();
+        assert_eq!(vocabularies.len(), 2);
+        assert_eq!(vocabularies[0].view(), "柿");
+        assert_eq!(vocabularies[0].spell(), "かき");
+        assert_eq!(vocabularies[0].key_stroke_count(), 4);
+        assert_eq!(vocabularies[0].wrong_key_stroke_count(), 1);
+        assert_eq!(vocabularies[0].duration(), Duration::from_secs(5));
+        assert_eq!(vocabularies[1].view(), " ");
+        assert_eq!(vocabularies[1].duration(), Duration::from_secs(1));
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_result_with_wrong_key_strokes_of_each_spell() {
+        let vocabularies = [gen_vocabulary_entry!("じょう", [("じょ", 2), ("う")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        for key_stroke in "jxou".chars() {
+            engine.stroke_key(key_stroke.try_into().unwrap()).unwrap();
+        }
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+
+        // ミスタイプしなかった綴りは含まれない
+        let spell_mistakes = result.spell_mistakes();
+        assert_eq!(spell_mistakes.len(), 1);
+        assert_eq!(spell_mistakes[0].spell(), "じょ");
+        assert_eq!(spell_mistakes[0].key_stroke(), "jo");
+        assert_eq!(
+            spell_mistakes[0].actual_key_strokes(),
+            &['j', 'x', 'o'].map(|c| KeyStrokeChar::try_from(c).unwrap())
+        );
+        assert_eq!(
+            spell_mistakes[0].wrong_key_strokes(),
+            &[KeyStrokeChar::try_from('x').unwrap()]
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_result_with_candidates_of_each_spell() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "じょうし",
+            [("じょ", 2), ("う"), ("し")]
+        )];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        for key_stroke in "jyoushi".chars() {
+            engine.stroke_key(key_stroke.try_into().unwrap()).unwrap();
+        }
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+
+        let spell_candidates = result.spell_candidates();
+        assert_eq!(spell_candidates.len(), 3);
+        assert_eq!(spell_candidates[0].spell(), "じょ");
+        assert_eq!(spell_candidates[0].key_stroke(), "jyo");
+        assert_eq!(spell_candidates[0].ideal_key_stroke(), "jo");
+        assert!(spell_candidates[0].is_different_from_ideal());
+        assert_eq!(spell_candidates[0].extra_key_stroke_count(), 1);
+        assert!(!spell_candidates[1].is_different_from_ideal());
+        assert_eq!(spell_candidates[1].extra_key_stroke_count(), 0);
+        assert_eq!(spell_candidates[2].key_stroke(), "shi");
+        assert_eq!(spell_candidates[2].ideal_key_stroke(), "si");
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_display_info_with_pace_to_target_speed() {
+        let vocabularies = [gen_vocabulary_entry!(
+            "あいうえおか",
+            [("あ"), ("い"), ("う"), ("え"), ("お"), ("か")]
+        )];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let pace_at = |engine: &TypingEngine, elapsed_time: Duration| {
+            engine
+                .construct_display_info_with_elapsed_time(
+                    LapRequest::Chunk(NonZeroUsize::new(1).unwrap()),
+                    elapsed_time,
+                )
+                .unwrap()
+                .pace_info()
+                .cloned()
+        };
+
+        // 目標速度を設定しなければ比較しない
+        assert_eq!(pace_at(&engine, Duration::new(1, 0)), None);
+
+        engine.set_target_wpm(NonZeroUsize::new(24));
+        assert_eq!(engine.target_kpm(), NonZeroUsize::new(120));
+
+        [('a', 500), ('i', 1000), ('u', 1500)]
+            .iter()
+            .for_each(|(key_stroke, elapsed_millis)| {
+                engine
+                    .stroke_key_with_elapsed_time(
+                        (*key_stroke).try_into().unwrap(),
+                        Duration::from_millis(*elapsed_millis),
+                    )
+                    .unwrap();
+            });
+
+        // 目標速度では1秒に2キーストローク進む
+        let pace = pace_at(&engine, Duration::new(1, 0)).unwrap();
+        assert_eq!(pace.target_cursor_position(), 2);
+        assert_eq!(pace.key_stroke_lead(), 1);
+        assert_eq!(pace.time_lead_millis(), 500);
+
+        let pace = pace_at(&engine, Duration::from_millis(2500)).unwrap();
+        assert_eq!(pace.target_cursor_position(), 5);
+        assert_eq!(pace.key_stroke_lead(), -2);
+        assert_eq!(pace.time_lead_millis(), -1000);
+
+        // 目標速度でもクエリの末尾より先には進まない
+        let pace = pace_at(&engine, Duration::new(10, 0)).unwrap();
+        assert_eq!(pace.target_cursor_position(), 7);
+        assert_eq!(pace.key_stroke_lead(), -4);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn first_correct_key_stroke_starts_clock() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.set_first_stroke_starts_clock(true);
+        engine.start().unwrap();
+        assert!(engine.is_armed());
+
+        // ミスタイプでは時計を開始しない
+        engine
+            .stroke_key_with_elapsed_time('x'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+        assert!(engine.is_armed());
+
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(3, 0))
+            .unwrap();
+        assert!(!engine.is_armed());
+
+        let result = engine
+            .construct_result_so_far_with_elapsed_time(
+                LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap()),
+                Duration::new(5, 0),
+            )
+            .unwrap();
+        assert_eq!(result.total_time(), Duration::new(2, 0));
+
+        engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::new(4, 0))
+            .unwrap();
+
+        let result = engine
+            .construst_result_statistics(LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.total_time(), Duration::new(1, 0));
+        assert_eq!(result.key_stroke().missed_count(), 1);
+
+        // 設定しなければ開始した時点から時計を開始する
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        assert!(!engine.is_armed());
+    }
 
-impl Default for TypingEngine {
-    fn default() -> Self {
-        Self::new()
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_result_with_idle_time() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.set_idle_threshold(Some(Duration::new(2, 0)));
+        engine.start().unwrap();
+
+        [('a', 1000), ('i', 6000), ('u', 7000)]
+            .iter()
+            .for_each(|(key_stroke, elapsed_millis)| {
+                engine
+                    .stroke_key_with_elapsed_time(
+                        (*key_stroke).try_into().unwrap(),
+                        Duration::from_millis(*elapsed_millis),
+                    )
+                    .unwrap();
+            });
+
+        // 閾値を超えた3秒間だけを放置した時間とする
+        let result = engine
+            .construst_result_statistics(LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.total_time(), Duration::new(7, 0));
+        assert_eq!(result.idle_time(), Duration::new(3, 0));
+        assert_eq!(result.active_time(), Duration::new(4, 0));
+
+        engine.set_idle_threshold(None);
+        let result = engine
+            .construst_result_statistics(LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.idle_time(), Duration::ZERO);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_display_info_with_lap_summaries() {
+        let vocabularies = [gen_vocabulary_entry!("あいか", [("あ"), ("い"), ("か")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        [
+            ('a', 1000),
+            ('x', 1500),
+            ('i', 2000),
+            ('k', 3000),
+            ('a', 4000),
+        ]
+        .iter()
+        .for_each(|(key_stroke, elapsed_millis)| {
+            engine
+                .stroke_key_with_elapsed_time(
+                    (*key_stroke).try_into().unwrap(),
+                    Duration::from_millis(*elapsed_millis),
+                )
+                .unwrap();
+        });
+
+        let lap_request = LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap());
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(4, 0))
+            .unwrap();
+        let lap_summaries = display_info.lap_info(&lap_request).unwrap().lap_summaries();
+
+        assert_eq!(lap_summaries.len(), 2);
+        assert_eq!(lap_summaries[0].key_stroke_count(), 2);
+        assert_eq!(lap_summaries[0].wrong_key_stroke_count(), 1);
+        assert_eq!(lap_summaries[0].duration(), Duration::new(2, 0));
+        assert_eq!(lap_summaries[0].kpm(), 60.0);
+        assert_eq!(lap_summaries[0].accuracy(), 2.0 / 3.0);
+        assert_eq!(lap_summaries[1].wrong_key_stroke_count(), 0);
+        assert_eq!(lap_summaries[1].accuracy(), 1.0);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn take_laps_of_each_vocabulary() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("か", [("か")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        let lap_request = LapRequest::Vocabulary(NonZeroUsize::new(1).unwrap());
+        engine.set_lap_request(lap_request.clone());
+        engine.start().unwrap();
+
+        let completed_lap_indices: Vec<Vec<usize>> =
+            [('a', 1000), ('i', 2000), ('k', 3000), ('a', 5000)]
+                .iter()
+                .map(|(key_stroke, elapsed_millis)| {
+                    engine
+                        .stroke_key_with_outcome_with_elapsed_time(
+                            (*key_stroke).try_into().unwrap(),
+                            Duration::from_millis(*elapsed_millis),
+                        )
+                        .unwrap()
+                        .completed_lap_indices()
+                        .to_vec()
+                })
+                .collect();
+        assert_eq!(
+            completed_lap_indices,
+            vec![vec![], vec![0], vec![], vec![1]]
+        );
+
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(5, 0))
+            .unwrap();
+        let lap_info = display_info.lap_info(&lap_request).unwrap();
+
+        // 語彙末はチャンク末でもあるので各対象のラップ末は語彙末の位置になる
+        assert_eq!(lap_info.key_stroke().lap_end_positions(), &vec![1, 3]);
+        assert_eq!(lap_info.spell().lap_end_positions(), &vec![1, 2]);
+        assert_eq!(lap_info.lap_summaries().len(), 2);
+        assert_eq!(lap_info.lap_summaries()[0].duration(), Duration::new(2, 0));
+        assert_eq!(lap_info.lap_summaries()[1].duration(), Duration::new(3, 0));
+        assert_eq!(lap_info.lap_summaries()[1].key_stroke_count(), 2);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn take_laps_by_elapsed_time() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あい", [("あ"), ("い")]),
+            gen_vocabulary_entry!("か", [("か")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        let lap_request = LapRequest::Duration(Duration::new(2, 0));
+        engine.set_lap_request(lap_request.clone());
+        engine.start().unwrap();
+
+        let completed_lap_indices: Vec<Vec<usize>> =
+            [('a', 1000), ('i', 2500), ('k', 7000), ('a', 7500)]
+                .iter()
+                .map(|(key_stroke, elapsed_millis)| {
+                    engine
+                        .stroke_key_with_outcome_with_elapsed_time(
+                            (*key_stroke).try_into().unwrap(),
+                            Duration::from_millis(*elapsed_millis),
+                        )
+                        .unwrap()
+                        .completed_lap_indices()
+                        .to_vec()
+                })
+                .collect();
+        // 打鍵のない間に過ぎたラップは次の打鍵でまとめて終える
+        assert_eq!(
+            completed_lap_indices,
+            vec![vec![], vec![0], vec![1, 2], vec![]]
+        );
+
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(8, 0))
+            .unwrap();
+        let lap_info = display_info.lap_info(&lap_request).unwrap();
+
+        assert_eq!(
+            lap_info.key_stroke().lap_end_time().unwrap(),
+            &vec![
+                Duration::new(2, 0),
+                Duration::new(4, 0),
+                Duration::new(6, 0)
+            ]
+        );
+        assert_eq!(lap_info.key_stroke().lap_end_positions(), &vec![0, 1, 1]);
+        assert_eq!(lap_info.spell().lap_end_positions(), &vec![0, 1, 1]);
+        assert_eq!(lap_info.lap_summaries().len(), 3);
+        assert_eq!(lap_info.lap_summaries()[1].key_stroke_count(), 1);
+        assert_eq!(lap_info.lap_summaries()[1].duration(), Duration::new(2, 0));
+        assert_eq!(lap_info.lap_summaries()[2].key_stroke_count(), 0);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn switch_lap_request_while_typing() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.set_lap_request(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        engine.start().unwrap();
+
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap();
+
+        // 変更前のラップ要求ではまだラップを終えていないが変更後のラップ要求では1つ目のラップを終えている
+        let lap_request = LapRequest::Chunk(NonZeroUsize::new(1).unwrap());
+        engine.set_lap_request(lap_request.clone());
+
+        let display_info = engine.construct_configured_display_info().unwrap();
+        let lap_info = display_info.lap_info(&lap_request).unwrap();
+        assert_eq!(lap_info.key_stroke().lap_end_positions(), &vec![0, 1, 2]);
+        assert_eq!(lap_info.spell().lap_end_positions(), &vec![0, 1, 2]);
+        assert_eq!(lap_info.lap_summaries().len(), 1);
+        assert_eq!(lap_info.lap_summaries()[0].duration(), Duration::new(1, 0));
+
+        let outcome = engine
+            .stroke_key_with_outcome_with_elapsed_time('i'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+        assert_eq!(outcome.completed_lap_indices(), [1]);
+        let outcome = engine
+            .stroke_key_with_outcome_with_elapsed_time('u'.try_into().unwrap(), Duration::new(4, 0))
+            .unwrap();
+        assert_eq!(outcome.completed_lap_indices(), [2]);
+        assert!(engine.is_finished());
+
+        let display_info = engine.construct_configured_display_info().unwrap();
+        let lap_info = display_info.lap_info(&lap_request).unwrap();
+        assert_eq!(lap_info.key_stroke().lap_end_positions(), &vec![0, 1, 2]);
+        assert_eq!(lap_info.lap_summaries().len(), 3);
+        assert_eq!(lap_info.lap_summaries()[1].duration(), Duration::new(1, 0));
+        assert_eq!(lap_info.lap_summaries()[2].duration(), Duration::new(2, 0));
+
+        let result = engine.construct_configured_result_statistics().unwrap();
+        assert_eq!(result.key_stroke().whole_count(), 3);
+        assert_eq!(result.total_time(), Duration::new(4, 0));
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn take_laps_at_custom_spell_positions() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")]),
+            gen_vocabulary_entry!("かき", [("か"), ("き")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        // 並べ替えていない位置も受け付ける
+        let lap_request = LapRequest::CustomSpellPositions(vec![4, 2]);
+        engine.set_lap_request(lap_request.clone());
+        engine.start().unwrap();
+
+        let completed_lap_indices: Vec<Vec<usize>> = "aiukaki"
+            .chars()
+            .enumerate()
+            .map(|(i, key_stroke)| {
+                engine
+                    .stroke_key_with_outcome_with_elapsed_time(
+                        key_stroke.try_into().unwrap(),
+                        Duration::from_secs(i as u64 + 1),
+                    )
+                    .unwrap()
+                    .completed_lap_indices()
+                    .to_vec()
+            })
+            .collect();
+        assert_eq!(
+            completed_lap_indices,
+            vec![vec![], vec![], vec![0], vec![], vec![], vec![], vec![1]]
+        );
+
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(7, 0))
+            .unwrap();
+        let lap_info = display_info.lap_info(&lap_request).unwrap();
+
+        assert_eq!(lap_info.spell().lap_end_positions(), &vec![2, 4]);
+        assert_eq!(lap_info.key_stroke().lap_end_positions(), &vec![2, 6]);
+        assert_eq!(
+            lap_info.spell().lap_end_time().unwrap(),
+            &vec![Duration::new(3, 0), Duration::new(7, 0)]
+        );
+    }
+
+    #[test]
+    fn error_kind_distinguishes_untypeable_spell() {
+        let vocabularies = [
+            gen_vocabulary_entry!("か", [("か")]),
+            gen_vocabulary_entry!("ゎ", [("ゎ")]),
+        ];
+        // かな入力では「ゎ」を入力できない
+        let mut engine = TypingEngine::new_with_scheme(InputScheme::Kana);
+
+        let error = engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::UntypeableSpell);
+
+        let error = engine
+            .init(QueryRequest::new(
+                &[],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::InvalidQueryRequest);
+
+        let error = engine.start().unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::MustBeInitialized);
+    }
+
+    #[cfg(feature = "ascii_only")]
+    #[test]
+    fn type_ascii_without_builtin_dictionary() {
+        let vocabularies = [
+            gen_vocabulary_entry!("Hi!", [("H"), ("i"), ("!")]),
+            gen_vocabulary_entry!("か", [("か")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        // 組み込みの辞書がないのでASCII以外の綴りは入力できない
+        let error = engine
+            .init(QueryRequest::new(
+                &[&vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::UntypeableSpell);
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        assert!(engine
+            .stroke_key_with_outcome('H'.try_into().unwrap())
+            .unwrap()
+            .is_correct());
+        assert!(!engine
+            .stroke_key_with_outcome('h'.try_into().unwrap())
+            .unwrap()
+            .is_correct());
+        assert!(!engine.stroke_key('i'.try_into().unwrap()).unwrap());
+        assert!(engine.stroke_key('!'.try_into().unwrap()).unwrap());
+    }
+
+    #[cfg(all(feature = "no_rare_romanization", not(feature = "ascii_only")))]
+    #[test]
+    fn rare_romanization_is_rejected() {
+        let vocabularies = [gen_vocabulary_entry!("うい", [("う"), ("い")])];
+        let mut engine = TypingEngine::new();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        // 「whu」は滅多に使われないので辞書から除かれている
+        assert!(engine
+            .stroke_key_with_outcome('w'.try_into().unwrap())
+            .unwrap()
+            .is_correct());
+        assert!(!engine
+            .stroke_key_with_outcome('h'.try_into().unwrap())
+            .unwrap()
+            .is_correct());
+        assert!(!engine.stroke_key('u'.try_into().unwrap()).unwrap());
+        // 滅多に使われないもの以外のキーストロークは残っている
+        assert!(engine
+            .stroke_key_with_outcome('y'.try_into().unwrap())
+            .unwrap()
+            .is_correct());
+        assert!(engine.stroke_key('i'.try_into().unwrap()).unwrap());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn engine_state_follows_lifecycle() {
+        let vocabularies = [gen_vocabulary_entry!("か", [("か")])];
+        let mut engine = TypingEngine::new();
+        assert_eq!(engine.state(), EngineState::Uninitialized);
+
+        let error = engine.stroke_key('k'.try_into().unwrap()).unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::MustBeStarted);
+        assert_eq!(error.state(), Some(EngineState::Uninitialized));
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert_eq!(engine.state(), EngineState::Ready);
+
+        engine.start().unwrap();
+        assert_eq!(engine.state(), EngineState::Started);
+
+        let lap_request = LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap());
+        let error = engine
+            .construst_result_statistics(lap_request.clone())
+            .unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::NotFinished);
+        assert_eq!(error.state(), Some(EngineState::Started));
+
+        engine
+            .stroke_key_with_elapsed_time('k'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+        assert_eq!(engine.state(), EngineState::Finished);
+
+        let error = engine.stroke_key('k'.try_into().unwrap()).unwrap_err();
+        assert_eq!(error.kind(), TypingEngineErrorKind::AlreadyFinished);
+        assert_eq!(error.to_string(), "already finished (engine is finished)");
+        assert!(engine.construst_result_statistics(lap_request).is_ok());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn diff_display_info_from_previous_frame() {
+        let vocabularies = [gen_vocabulary_entry!("あか", [("あ"), ("か")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let lap_request = LapRequest::Spell(NonZeroUsize::new(1).unwrap());
+        let construct = |engine: &TypingEngine| {
+            engine
+                .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(3, 0))
+                .unwrap()
+        };
+
+        let prev = construct(&engine);
+        for key_stroke in ['x', 'a'] {
+            engine
+                .stroke_key_with_elapsed_time(key_stroke.try_into().unwrap(), Duration::new(1, 0))
+                .unwrap();
+        }
+        let display_info = construct(&engine);
+
+        let diff = display_info.diff(&prev);
+        assert_eq!(diff.unchanged_key_stroke_count(), 3);
+        assert_eq!(diff.previous_key_stroke_cursor_position(), 0);
+        assert_eq!(diff.key_stroke_cursor_position(), 1);
+        assert!(diff.is_cursor_moved());
+        assert_eq!(diff.newly_missed_key_stroke_positions(), [0]);
+        assert_eq!(diff.spell_cursor_positions(), [1]);
+        assert_eq!(diff.newly_missed_spell_positions(), [0]);
+        assert_eq!(diff.newly_completed_lap_indices(), [vec![0]]);
+
+        // 変化がなければ差分もない
+        let diff = display_info.diff(&display_info);
+        assert!(!diff.is_cursor_moved());
+        assert!(diff.newly_missed_key_stroke_positions().is_empty());
+        assert_eq!(diff.newly_completed_lap_indices(), [Vec::<usize>::new()]);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn result_contains_key_stroke_log() {
+        let vocabularies = [gen_vocabulary_entry!("あか", [("あ"), ("か")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        for (i, key_stroke) in ['x', 'a', 'k', 'a'].into_iter().enumerate() {
+            engine
+                .stroke_key_with_elapsed_time(
+                    key_stroke.try_into().unwrap(),
+                    Duration::new(i as u64 + 1, 0),
+                )
+                .unwrap();
+        }
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Spell(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(
+            result.key_stroke_log(),
+            [
+                ActualKeyStroke::new(Duration::new(1, 0), 'x'.try_into().unwrap(), false),
+                ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(3, 0), 'k'.try_into().unwrap(), true),
+                ActualKeyStroke::new(Duration::new(4, 0), 'a'.try_into().unwrap(), true),
+            ]
+        );
+
+        // 続けて打った結果の経過時間は先行の全体時間分ずれる
+        let merged = result.merge(&result);
+        assert_eq!(merged.key_stroke_log().len(), 8);
+        assert_eq!(
+            merged.key_stroke_log()[4].elapsed_time(),
+            &Duration::new(5, 0)
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn combo_is_reset_by_wrong_key_stroke() {
+        let vocabularies = [gen_vocabulary_entry!("あかさ", [("あ"), ("か"), ("さ")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        for key_stroke in ['a', 'k', 'x', 'a'] {
+            engine
+                .stroke_key_with_elapsed_time(key_stroke.try_into().unwrap(), Duration::new(1, 0))
+                .unwrap();
+        }
+
+        let lap_request = LapRequest::Spell(NonZeroUsize::new(1).unwrap());
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(1, 0))
+            .unwrap();
+        assert_eq!(display_info.key_stroke_info().combo(), 1);
+        assert_eq!(display_info.key_stroke_info().max_combo(), 2);
+
+        for key_stroke in ['s', 'a'] {
+            engine
+                .stroke_key_with_elapsed_time(key_stroke.try_into().unwrap(), Duration::new(2, 0))
+                .unwrap();
+        }
+
+        let result = engine.construst_result_statistics(lap_request).unwrap();
+        assert_eq!(result.max_combo(), 3);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn terminate_by_wrong_key_strokes() {
+        let vocabularies = [gen_vocabulary_entry!("あかさ", [("あ"), ("か"), ("さ")])];
+        let query_request = || {
+            QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            )
+        };
+        let lap_request = LapRequest::Spell(NonZeroUsize::new(1).unwrap());
+
+        let mut engine = TypingEngine::new();
+        engine
+            .set_termination_condition(Some(TerminationCondition::SuddenDeath))
+            .unwrap();
+        engine.init(query_request()).unwrap();
+        engine.start().unwrap();
+
+        assert!(!engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap());
+        let outcome = engine
+            .stroke_key_with_outcome_with_elapsed_time('x'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+        assert!(outcome.is_finished());
+        assert!(outcome.is_terminated());
+        assert_eq!(
+            engine
+                .stroke_key('k'.try_into().unwrap())
+                .unwrap_err()
+                .kind(),
+            TypingEngineErrorKind::AlreadyFinished
+        );
+
+        // 打ち切られるまでに打った対象のみで結果を構築する
+        let result = engine
+            .construst_result_statistics(lap_request.clone())
+            .unwrap();
+        assert_eq!(result.end_reason(), &TypingEndReason::MissLimitReached);
+        assert_eq!(result.key_stroke().whole_count(), 1);
+        assert_eq!(result.key_stroke().missed_count(), 1);
+        assert_eq!(result.total_time(), Duration::new(2, 0));
+
+        let mut engine = TypingEngine::new();
+        engine
+            .set_termination_condition(Some(TerminationCondition::MissLimit(
+                NonZeroUsize::new(2).unwrap(),
+            )))
+            .unwrap();
+        engine.init(query_request()).unwrap();
+        engine.start().unwrap();
+
+        for (key_stroke, is_finished) in [('x', false), ('a', false), ('x', true)] {
+            assert_eq!(
+                engine
+                    .stroke_key_with_elapsed_time(
+                        key_stroke.try_into().unwrap(),
+                        Duration::new(1, 0)
+                    )
+                    .unwrap(),
+                is_finished
+            );
+        }
+        assert_eq!(
+            engine
+                .construst_result_statistics(lap_request)
+                .unwrap()
+                .end_reason(),
+            &TypingEndReason::MissLimitReached
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn skip_current_vocabulary_deems_remaining_key_strokes_missed() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あか", [("あ"), ("か")]),
+            gen_vocabulary_entry!("さ", [("さ")]),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(2).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine
+            .set_termination_condition(Some(TerminationCondition::SuddenDeath))
+            .unwrap();
+        engine.start().unwrap();
+
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap();
+        assert!(!engine
+            .skip_current_vocabulary_with_elapsed_time(Duration::new(2, 0))
+            .unwrap());
+
+        let lap_request = LapRequest::Spell(NonZeroUsize::new(1).unwrap());
+        let display_info = engine
+            .construct_display_info_with_elapsed_time(lap_request.clone(), Duration::new(2, 0))
+            .unwrap();
+        assert_eq!(
+            display_info.spell_info().current_cursor_positions(),
+            &vec![2]
+        );
+        assert_eq!(display_info.spell_info().missed_positions(), &vec![1]);
+        assert_eq!(
+            display_info.key_stroke_info().missed_positions(),
+            &vec![1, 2]
+        );
+
+        for key_stroke in ['s', 'a'] {
+            engine
+                .stroke_key_with_elapsed_time(key_stroke.try_into().unwrap(), Duration::new(3, 0))
+                .unwrap();
+        }
+
+        let result = engine.construst_result_statistics(lap_request).unwrap();
+        assert_eq!(result.end_reason(), &TypingEndReason::Completed);
+        assert_eq!(result.skipped_vocabulary_count(), 1);
+        assert_eq!(result.assisted_key_stroke_count(), 2);
+        assert_eq!(result.key_stroke().missed_count(), 2);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn hint_reveals_next_ideal_key_stroke() {
+        let vocabularies = [gen_vocabulary_entry!("しか", [("し"), ("か")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert_eq!(
+            engine.hint().unwrap_err().kind(),
+            TypingEngineErrorKind::MustBeStarted
+        );
+        engine.start().unwrap();
+
+        assert_eq!(engine.hint().unwrap(), 's');
+        engine
+            .stroke_key_with_elapsed_time('s'.try_into().unwrap(), Duration::new(1, 0))
+            .unwrap();
+        engine
+            .stroke_key_with_elapsed_time('h'.try_into().unwrap(), Duration::new(2, 0))
+            .unwrap();
+        // 理想的な候補と異なるキーストロークを打った場合にはそれに続くキーストロークを示す
+        assert_eq!(engine.hint().unwrap(), 'i');
+        engine
+            .stroke_key_with_elapsed_time('i'.try_into().unwrap(), Duration::new(3, 0))
+            .unwrap();
+        assert_eq!(engine.hint().unwrap(), 'k');
+        assert_eq!(engine.hint().unwrap(), 'k');
+
+        for key_stroke in ['k', 'a'] {
+            engine
+                .stroke_key_with_elapsed_time(key_stroke.try_into().unwrap(), Duration::new(4, 0))
+                .unwrap();
+        }
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Spell(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(
+            result.spell_hints(),
+            [
+                SpellHintStatistics::new("し".to_string(), 2),
+                SpellHintStatistics::new("か".to_string(), 2),
+            ]
+        );
+        assert_eq!(result.hint_count(), 4);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn type_query_ending_with_syllabic_nasal_at_vocabulary_boundary() {
+        let vocabularies = [gen_vocabulary_entry!("かん", [("か"), ("ん")])];
+        let mut engine = TypingEngine::new();
+
+        // 語彙の境界でのキーストローク回数は3・6・9・12なので3語彙で打ち切られる
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::KeyStrokeAtVocabularyBoundary(
+                    NonZeroUsize::new(10).unwrap(),
+                    VocabularyBoundaryRounding::Nearest,
+                ),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let mut stroke = |c: char| {
+            engine
+                .stroke_key_with_outcome(c.try_into().unwrap())
+                .unwrap()
+        };
+
+        assert!("kankankan".chars().all(|c| stroke(c).is_correct()));
+
+        // 末尾の「ん」は後続のチャンクがないので「n」では打てない
+        assert!(!stroke('k').is_correct());
+        let outcome = stroke('n');
+        assert!(outcome.is_correct());
+        assert!(outcome.is_finished());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn state_digest_ignores_timing() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let construct_engine = || {
+            let mut engine = TypingEngine::new();
+            engine
+                .init(QueryRequest::new(
+                    &[&vocabularies[0]],
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                    VocabularySeparator::None,
+                    VocabularyOrder::InOrder,
+                ))
+                .unwrap();
+            engine
+        };
+
+        let mut engine = construct_engine();
+        let mut other_engine = construct_engine();
+        assert_ne!(engine.state_digest(), TypingEngine::new().state_digest());
+        assert_eq!(engine.state_digest(), other_engine.state_digest());
+
+        engine.start().unwrap();
+        other_engine.start().unwrap();
+        engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(1))
+            .unwrap();
+        other_engine
+            .stroke_key_with_elapsed_time('a'.try_into().unwrap(), Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(engine.state_digest(), other_engine.state_digest());
+
+        engine
+            .stroke_key_with_elapsed_time('j'.try_into().unwrap(), Duration::from_secs(4))
+            .unwrap();
+        assert_ne!(engine.state_digest(), other_engine.state_digest());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn display_info_delta() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let mut engine = TypingEngine::new();
+        let lap_request = LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap());
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.display_info_delta(lap_request.clone()).is_err());
+        engine.start().unwrap();
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        let delta = engine.display_info_delta(lap_request.clone()).unwrap();
+        assert_eq!(delta.newly_confirmed_chunk_count(), 1);
+        // 最初は全体を送る
+        assert_eq!(
+            delta.spell().edits(),
+            &[TextEdit::new(0, 0, "あいう".to_string())]
+        );
+        assert_eq!(
+            delta.key_stroke().edits(),
+            &[TextEdit::new(0, 0, "aiu".to_string())]
+        );
+        assert_eq!(delta.key_stroke().cursor_positions(), &[1]);
+        #[cfg(feature = "statistics")]
+        assert_eq!(delta.newly_completed_lap_summaries().len(), 1);
+
+        engine.stroke_key('x'.try_into().unwrap()).unwrap();
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+        let delta = engine.display_info_delta(lap_request.clone()).unwrap();
+        assert_eq!(delta.newly_confirmed_chunk_count(), 1);
+        // 綴りは変わらず、キーストロークは前回確定していなかったチャンクの部分のみを送る
+        assert!(delta.spell().edits().is_empty());
+        assert_eq!(delta.spell().missed_positions_from(), 1);
+        assert_eq!(delta.spell().missed_positions(), &[1]);
+        assert_eq!(
+            delta.key_stroke().edits(),
+            &[TextEdit::new(1, 2, "iu".to_string())]
+        );
+        assert_eq!(delta.key_stroke().missed_positions_from(), 1);
+        assert_eq!(delta.key_stroke().missed_positions(), &[1]);
+        #[cfg(feature = "statistics")]
+        assert_eq!(delta.newly_completed_lap_summaries().len(), 1);
+
+        // ラップ要求が変わっても文字列は差分のみを送り、ラップは全て送る
+        let spell_lap_request = LapRequest::Spell(NonZeroUsize::new(1).unwrap());
+        let delta = engine
+            .display_info_delta(spell_lap_request.clone())
+            .unwrap();
+        assert_eq!(delta.newly_confirmed_chunk_count(), 0);
+        assert_eq!(
+            delta.key_stroke().edits(),
+            &[TextEdit::new(2, 1, "u".to_string())]
+        );
+        #[cfg(feature = "statistics")]
+        assert_eq!(
+            delta.newly_completed_lap_summaries(),
+            engine
+                .construct_display_info(spell_lap_request)
+                .unwrap()
+                .lap_infos()[0]
+                .lap_summaries()
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn applying_display_info_delta_reproduces_display_info() {
+        let vocabularies = [
+            gen_vocabulary_entry!("あき", [("あ"), ("き")]),
+            gen_vocabulary_entry!("巨", [("きょ", 1)]),
+            gen_vocabulary_entry!("っった", [("っ"), ("っ"), ("た")]),
+            gen_vocabulary_entry!("んな", [("ん"), ("な")]),
+        ];
+        let lap_requests = [
+            LapRequest::KeyStroke(NonZeroUsize::new(3).unwrap()),
+            LapRequest::Spell(NonZeroUsize::new(2).unwrap()),
+        ];
+        let query_request = |count: usize| {
+            QueryRequest::new(
+                &[
+                    &vocabularies[0],
+                    &vocabularies[1],
+                    &vocabularies[2],
+                    &vocabularies[3],
+                ],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(count).unwrap()),
+                VocabularySeparator::Newline,
+                VocabularyOrder::InOrder,
+            )
+        };
+
+        // 経過時間が一致するように時刻を進めない
+        struct StoppedClock;
+
+        impl Clock for StoppedClock {
+            fn now(&self) -> Duration {
+                Duration::new(1, 0)
+            }
+        }
+
+        // 表示する側で保持する状態
+        #[derive(Default)]
+        struct Client {
+            texts: [String; 4],
+            missed_positions: [Vec<usize>; 3],
+            #[cfg(feature = "statistics")]
+            lap_summaries: Vec<LapSummary>,
+        }
+
+        [None, NonZeroUsize::new(2)]
+            .into_iter()
+            .for_each(|candidate_window| {
+                let mut engine = TypingEngine::new();
+                engine.set_clock(StoppedClock);
+                engine.set_candidate_window(candidate_window);
+                engine.set_target_kpm(NonZeroUsize::new(300));
+                engine.set_strict_mode(true).unwrap();
+                engine.init(query_request(8)).unwrap();
+                engine.start().unwrap();
+
+                let mut client = Client::default();
+                let mut lap_request_index = 0;
+                let mut assert_reproduced = |engine: &mut TypingEngine, client: &mut Client| {
+                    // ラップ要求を切り替えた場合にも再現できる
+                    lap_request_index = (lap_request_index + 1) % 3 % 2;
+                    let lap_request = lap_requests[lap_request_index].clone();
+                    let delta = engine.display_info_delta(lap_request.clone()).unwrap();
+                    let display_info = engine.construct_display_info(lap_request).unwrap();
+
+                    [
+                        delta.view(),
+                        delta.spell(),
+                        delta.key_stroke(),
+                        delta.ideal_key_stroke(),
+                    ]
+                    .iter()
+                    .zip(client.texts.iter_mut())
+                    .for_each(|(text_delta, text)| text_delta.apply(text));
+                    [delta.view(), delta.spell(), delta.key_stroke()]
+                        .iter()
+                        .zip(client.missed_positions.iter_mut())
+                        .for_each(|(text_delta, missed_positions)| {
+                            text_delta.apply_missed_positions(missed_positions)
+                        });
+                    #[cfg(feature = "statistics")]
+                    {
+                        if delta.newly_completed_lap_summaries().len()
+                            == display_info.lap_infos()[0].lap_summaries().len()
+                        {
+                            client.lap_summaries.clear();
+                        }
+                        client
+                            .lap_summaries
+                            .extend_from_slice(delta.newly_completed_lap_summaries());
+                    }
+
+                    let view_info = display_info.view_info();
+                    assert_eq!(client.texts[0], view_info.view());
+                    assert_eq!(
+                        delta.view().cursor_positions(),
+                        view_info.current_cursor_positions()
+                    );
+                    assert_eq!(&client.missed_positions[0], view_info.missed_positions());
+                    assert_eq!(delta.view_last_position(), view_info.last_position());
+
+                    let spell_info = display_info.spell_info();
+                    assert_eq!(client.texts[1], spell_info.spell());
+                    assert_eq!(
+                        delta.spell().cursor_positions(),
+                        spell_info.current_cursor_positions()
+                    );
+                    assert_eq!(&client.missed_positions[1], spell_info.missed_positions());
+                    assert_eq!(delta.spell_last_position(), spell_info.last_position());
+
+                    let key_stroke_info = display_info.key_stroke_info();
+                    assert_eq!(client.texts[2], key_stroke_info.key_stroke());
+                    assert_eq!(
+                        delta.key_stroke().cursor_positions(),
+                        &[key_stroke_info.current_cursor_position()]
+                    );
+                    assert_eq!(
+                        &client.missed_positions[2],
+                        key_stroke_info.missed_positions()
+                    );
+                    assert_eq!(client.texts[3], key_stroke_info.ideal_key_stroke());
+                    assert_eq!(
+                        delta.ideal_key_stroke().cursor_positions(),
+                        &[key_stroke_info.ideal_cursor_position()]
+                    );
+                    #[cfg(feature = "statistics")]
+                    {
+                        assert_eq!(delta.combo(), key_stroke_info.combo());
+                        assert_eq!(delta.max_combo(), key_stroke_info.max_combo());
+                        assert_eq!(
+                            client.lap_summaries,
+                            display_info.lap_infos()[0].lap_summaries()
+                        );
+                    }
+                    assert_eq!(delta.progress_info(), display_info.progress_info());
+                    assert_eq!(delta.pace_info(), display_info.pace_info());
+                };
+                let stroke = |engine: &mut TypingEngine, key_strokes: &str| {
+                    key_strokes.chars().for_each(|c| {
+                        engine.stroke_key(c.try_into().unwrap()).unwrap();
+                    });
+                };
+                assert_reproduced(&mut engine, &mut client);
+
+                stroke(&mut engine, "ak");
+                assert_reproduced(&mut engine, &mut client);
+                stroke(&mut engine, "q");
+                assert_reproduced(&mut engine, &mut client);
+                engine.backspace().unwrap();
+                stroke(&mut engine, "i");
+                assert_reproduced(&mut engine, &mut client);
+
+                // 候補が変わると後続のチャンクのキーストロークも変わる
+                stroke(&mut engine, "ki");
+                assert_reproduced(&mut engine, &mut client);
+                stroke(&mut engine, "xyo");
+                assert_reproduced(&mut engine, &mut client);
+
+                stroke(&mut engine, "t");
+                assert_reproduced(&mut engine, &mut client);
+                engine.skip_current_vocabulary().unwrap();
+                assert_reproduced(&mut engine, &mut client);
+
+                stroke(&mut engine, "n");
+                assert_reproduced(&mut engine, &mut client);
+                engine.truncate_query(1).unwrap();
+                assert_reproduced(&mut engine, &mut client);
+                engine.append_query(query_request(3)).unwrap();
+                assert_reproduced(&mut engine, &mut client);
+
+                stroke(&mut engine, "nnaa");
+                assert_reproduced(&mut engine, &mut client);
+                stroke(&mut engine, "kixyo");
+                assert_reproduced(&mut engine, &mut client);
+
+                // 次の回では前回の文字列を全て差し替える
+                engine.reset_keeping_statistics();
+                engine.init(query_request(2)).unwrap();
+                engine.start().unwrap();
+                assert_reproduced(&mut engine, &mut client);
+                stroke(&mut engine, "a");
+                assert_reproduced(&mut engine, &mut client);
+            });
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn construct_display_info_with_lap_requests() {
+        let vocabularies = [gen_vocabulary_entry!("あいう", [("あ"), ("い"), ("う")])];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+        assert!(engine
+            .construct_display_info_with_lap_requests(&[])
+            .is_err());
+
+        let key_stroke_lap_request = LapRequest::KeyStroke(NonZeroUsize::new(1).unwrap());
+        let spell_lap_request = LapRequest::Spell(NonZeroUsize::new(2).unwrap());
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        engine.stroke_key('i'.try_into().unwrap()).unwrap();
+
+        let display_info = engine
+            .construct_display_info_with_lap_requests(&[
+                key_stroke_lap_request.clone(),
+                spell_lap_request.clone(),
+            ])
+            .unwrap();
+
+        assert_eq!(display_info.lap_infos().len(), 2);
+        assert_eq!(
+            display_info
+                .key_stroke_info()
+                .on_typing_statistics()
+                .lap_end_positions(),
+            &vec![0, 1, 2]
+        );
+
+        let key_stroke_lap_info = display_info.lap_info(&key_stroke_lap_request).unwrap();
+        assert_eq!(
+            key_stroke_lap_info.key_stroke(),
+            display_info.key_stroke_info().on_typing_statistics()
+        );
+
+        let spell_lap_info = display_info.lap_info(&spell_lap_request).unwrap();
+        assert_eq!(spell_lap_info.spell().lap_end_positions(), &vec![1]);
+        assert_eq!(spell_lap_info.key_stroke().lap_end_positions(), &vec![1]);
+        assert_eq!(spell_lap_info.spell().lap_end_time().unwrap().len(), 1);
+
+        assert!(display_info
+            .lap_info(&LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .is_none());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn clear_pending_wrong_state_in_strict_mode() {
+        let vocabularies = [gen_vocabulary_entry!("あい", [("あ"), ("い")])];
+        let mut engine = TypingEngine::new();
+
+        engine.set_strict_mode(true).unwrap();
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        assert!(engine.clear_pending_wrong_state().is_err());
+
+        engine.start().unwrap();
+        engine.stroke_key('u'.try_into().unwrap()).unwrap();
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        assert_eq!(engine.uncorrected_key_stroke_count(), Some(2));
+
+        engine.clear_pending_wrong_state().unwrap();
+        assert_eq!(engine.uncorrected_key_stroke_count(), Some(0));
+        assert_eq!(
+            engine.take_wrong_state_events(),
+            vec![WrongStateEvent::Cleared { discarded_count: 2 }]
+        );
+        assert!(engine.take_wrong_state_events().is_empty());
+
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        assert!(engine.stroke_key('i'.try_into().unwrap()).unwrap());
+        assert!(engine.clear_pending_wrong_state().is_err());
+
+        // 既に記録されたミスタイプは消えず訂正にも数えない
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().missed_count(), 2);
+        assert_eq!(result.correction_count(), 0);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn display_assets_of_current_vocabulary() {
+        let assets = |id: &str| VocabularyAssets::new(Some(format!("{}.mp3", id)), None);
+        let vocabularies = [
+            gen_vocabulary_entry!("あ", [("あ")]).with_assets(assets("a")),
+            gen_vocabulary_entry!("い", [("い")]).with_assets(assets("i")),
+        ];
+        let mut engine = TypingEngine::new();
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0], &vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(3).unwrap()),
+                VocabularySeparator::WhiteSpace,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        let current_audio_id = |engine: &TypingEngine| {
+            engine
+                .construct_display_info(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+                .unwrap()
+                .current_vocabulary_assets()
+                .and_then(|assets| assets.audio_id().map(|id| id.to_string()))
+        };
+
+        assert_eq!(current_audio_id(&engine), Some("a.mp3".to_string()));
+
+        // 語彙区切りは素材を持たない
+        engine.stroke_key('a'.try_into().unwrap()).unwrap();
+        assert_eq!(current_audio_id(&engine), None);
+
+        engine.stroke_key(' '.try_into().unwrap()).unwrap();
+        assert_eq!(current_audio_id(&engine), Some("i.mp3".to_string()));
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn type_with_azik() {
+        let vocabularies = [
+            gen_vocabulary_entry!("金鉱", [("きん"), ("こう")]),
+            gen_vocabulary_entry!("感知", [("かん"), ("ち")]),
+            gen_vocabulary_entry!("簡易", [("かん"), ("い")]),
+        ];
+
+        [("kkkp", 0), ("kanti", 1), ("kzi", 2), ("kannyi", 2)]
+            .iter()
+            .for_each(|(script, vocabulary_index)| {
+                let mut engine = TypingEngine::new_with_scheme(InputScheme::Azik);
+                engine
+                    .init(QueryRequest::new(
+                        &[&vocabularies[*vocabulary_index]],
+                        VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                        VocabularySeparator::None,
+                        VocabularyOrder::InOrder,
+                    ))
+                    .unwrap();
+                engine.start().unwrap();
+
+                let is_finished = script
+                    .chars()
+                    .map(|c| engine.stroke_key(c.try_into().unwrap()).unwrap())
+                    .last()
+                    .unwrap();
+                assert!(is_finished);
+
+                let result = engine
+                    .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+                    .unwrap();
+                assert_eq!(result.key_stroke().missed_count(), 0);
+            });
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn type_with_kana() {
+        let vocabularies = [gen_vocabulary_entry!("学校", [("がっ"), ("こう")])];
+
+        [("t@Zb4", 0), ("t@bZb4", 1)]
+            .iter()
+            .for_each(|(script, missed_count)| {
+                let mut engine = TypingEngine::new_with_scheme(InputScheme::Kana);
+                engine
+                    .init(QueryRequest::new(
+                        &[&vocabularies[0]],
+                        VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                        VocabularySeparator::None,
+                        VocabularyOrder::InOrder,
+                    ))
+                    .unwrap();
+                engine.start().unwrap();
+
+                let is_finished = script
+                    .chars()
+                    .map(|c| engine.stroke_key(c.try_into().unwrap()).unwrap())
+                    .last()
+                    .unwrap();
+                assert!(is_finished);
+
+                // 「っ」を子音の連続で打つことはできない
+                let result = engine
+                    .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+                    .unwrap();
+                assert_eq!(result.key_stroke().missed_count(), *missed_count);
+            });
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn type_with_custom_dictionary() {
+        let vocabularies = [
+            gen_vocabulary_entry!("蚊", [("か")]),
+            gen_vocabulary_entry!("木", [("き")]),
+        ];
+        let custom_dictionary = CustomDictionary::new(
+            [("か".to_string(), vec!["ka".to_string()])]
+                .into_iter()
+                .collect(),
+        )
+        .unwrap();
+
+        let mut engine = TypingEngine::with_custom_dictionary(custom_dictionary);
+        // 辞書に含まれない綴りを持つ語彙はクエリにできない
+        assert!(engine
+            .init(QueryRequest::new(
+                &[&vocabularies[1]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .is_err());
+
+        engine
+            .init(QueryRequest::new(
+                &[&vocabularies[0]],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .unwrap();
+        engine.start().unwrap();
+
+        assert!(!engine.stroke_key('c'.try_into().unwrap()).unwrap());
+        assert!(!engine.stroke_key('k'.try_into().unwrap()).unwrap());
+        assert!(engine.stroke_key('a'.try_into().unwrap()).unwrap());
+
+        let result = engine
+            .construst_result_statistics(LapRequest::Chunk(NonZeroUsize::new(1).unwrap()))
+            .unwrap();
+        assert_eq!(result.key_stroke().missed_count(), 1);
+    }
+
+    #[test]
+    fn init_with_invalid_query_request() {
+        let vocabularies = [gen_vocabulary_entry!("あ", [("あ")])];
+        let mut engine = TypingEngine::new();
+
+        assert!(engine
+            .init(QueryRequest::new(
+                &[],
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::InOrder,
+            ))
+            .is_err());
+
+        assert!(engine
+            .init(QueryRequest::new(
+                vocabularies
+                    .iter()
+                    .collect::<Vec<&VocabularyEntry>>()
+                    .as_slice(),
+                VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                VocabularySeparator::None,
+                VocabularyOrder::Arbitrary(Box::new(|_, _| 1)),
+            ))
+            .is_err());
+
+        assert!(engine.start().is_err());
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn arbitrary_inputs_do_not_panic() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        const SPELLS: [&str; 20] = [
+            "あ", "きょ", "ん", "っ", "ー", "しゃ", "ゔ", "ぁ", "にゃ", "a", "B", "!", " ", "ふぁ",
+            "っ", "ん", "か", "こ", "う", "い",
+        ];
+
+        let mut rng = StdRng::seed_from_u64(7750);
+
+        let random_lap_request = |rng: &mut StdRng| {
+            let size = NonZeroUsize::new(rng.gen_range(1..100)).unwrap();
+            match rng.gen_range(0..4) {
+                0 => LapRequest::KeyStroke(size),
+                1 => LapRequest::IdealKeyStroke(size),
+                2 => LapRequest::Spell(size),
+                _ => LapRequest::Chunk(size),
+            }
+        };
+
+        (0..100).for_each(|_| {
+            let vocabularies: Vec<VocabularyEntry> = (0..rng.gen_range(1..4))
+                .map(|_| {
+                    let spells: Vec<VocabularySpellElement> = (0..rng.gen_range(1..6))
+                        .map(|_| {
+                            VocabularySpellElement::Normal(
+                                SPELLS[rng.gen_range(0..SPELLS.len())]
+                                    .to_string()
+                                    .try_into()
+                                    .unwrap(),
+                            )
+                        })
+                        .collect();
+
+                    VocabularyEntry::new("字".repeat(spells.len()), spells).unwrap()
+                })
+                .collect();
+
+            let quantifier_count = NonZeroUsize::new(rng.gen_range(1..30)).unwrap();
+            let vocabulary_quantifier = if rng.gen_bool(0.5) {
+                VocabularyQuantifier::KeyStroke(quantifier_count)
+            } else {
+                VocabularyQuantifier::Vocabulary(quantifier_count)
+            };
+            let vocabulary_separator = match rng.gen_range(0..3) {
+                0 => VocabularySeparator::None,
+                1 => VocabularySeparator::WhiteSpace,
+                _ => VocabularySeparator::Vocabulary(vocabularies[0].clone()),
+            };
+
+            let input_scheme = match rng.gen_range(0..3) {
+                0 => InputScheme::Standard,
+                1 => InputScheme::Azik,
+                _ => InputScheme::Kana,
+            };
+
+            let mut engine = TypingEngine::new_with_scheme(input_scheme);
+            engine.set_strict_mode(rng.gen_bool(0.3)).unwrap();
+            engine
+                .init(QueryRequest::new(
+                    vocabularies
+                        .iter()
+                        .collect::<Vec<&VocabularyEntry>>()
+                        .as_slice(),
+                    vocabulary_quantifier,
+                    vocabulary_separator,
+                    VocabularyOrder::InOrder,
+                ))
+                .unwrap();
+            engine.start().unwrap();
+
+            loop {
+                if engine
+                    .uncorrected_key_stroke_count()
+                    .is_some_and(|count| count > 0)
+                    && rng.gen_bool(0.8)
+                {
+                    engine.backspace().unwrap();
+                    continue;
+                }
+
+                let display_info = engine
+                    .construct_display_info(random_lap_request(&mut rng))
+                    .unwrap();
+
+                let key_stroke_info = display_info.key_stroke_info();
+                let key_stroke = if rng.gen_bool(0.8) {
+                    key_stroke_info
+                        .key_stroke()
+                        .chars()
+                        .nth(key_stroke_info.current_cursor_position())
+                        .unwrap()
+                } else {
+                    rng.gen_range(' '..='~')
+                };
+
+                if engine.stroke_key(key_stroke.try_into().unwrap()).unwrap() {
+                    break;
+                }
+            }
+
+            #[cfg(feature = "statistics")]
+            engine
+                .construst_result_statistics(random_lap_request(&mut rng))
+                .unwrap();
+            #[cfg(feature = "analysis")]
+            engine
+                .construct_telemetry(random_lap_request(&mut rng))
+                .unwrap();
+        });
     }
 }
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// A policy of choosing key strokes injected by
+/// [`auto_complete_current_chunk`](crate::TypingEngine::auto_complete_current_chunk()).
+///
+/// Key strokes already typed are kept in any policy, so chosen key strokes are always consistent
+/// with them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AutoCompletePolicy {
+    /// Complete with ideal key strokes of the chunk if they are still consistent with typed key
+    /// strokes, otherwise same as [`MinKeyStroke`](Self::MinKeyStroke).
+    Ideal,
+    /// Complete with key strokes which need the least remaining key strokes.
+    MinKeyStroke,
+}
+
+impl AutoCompletePolicy {
+    pub(crate) fn prefers_ideal(&self) -> bool {
+        matches!(self, Self::Ideal)
+    }
+}
@@ -0,0 +1,49 @@
+/// A read-only view of a key stroke candidate of the chunk currently typed.
+///
+/// This is constructed by
+/// [`TypingEngine::inflight_candidates`](crate::TypingEngine::inflight_candidates()).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CandidateView {
+    key_stroke_elements: Vec<String>,
+    cursor_position: usize,
+    is_ideal: bool,
+}
+
+impl CandidateView {
+    pub(crate) fn new(
+        key_stroke_elements: Vec<String>,
+        cursor_position: usize,
+        is_ideal: bool,
+    ) -> Self {
+        Self {
+            key_stroke_elements,
+            cursor_position,
+            is_ideal,
+        }
+    }
+
+    /// Get whole key strokes of this candidate.
+    pub fn whole_key_stroke(&self) -> String {
+        self.key_stroke_elements.concat()
+    }
+
+    /// Get key strokes of each element of this candidate.
+    ///
+    /// A chunk of multiple characters can be typed character by character.
+    /// ex. `きょ` can be typed as `kyo` or `ki` and `lyo`.
+    /// In such a case, this returns key strokes of each character.
+    pub fn key_stroke_elements(&self) -> &[String] {
+        &self.key_stroke_elements
+    }
+
+    /// Get cursor position in [`whole_key_stroke`](Self::whole_key_stroke()), that is, count of
+    /// key strokes already typed for this candidate.
+    pub fn cursor_position(&self) -> usize {
+        self.cursor_position
+    }
+
+    /// Get whether this candidate is ideal one, that is, one used to calculate ideal key strokes.
+    pub fn is_ideal(&self) -> bool {
+        self.is_ideal
+    }
+}
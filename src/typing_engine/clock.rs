@@ -0,0 +1,105 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "std_clock")]
+use std::time::Instant;
+
+/// A monotonic clock used by [`TypingEngine`](crate::TypingEngine) to measure elapsed time by
+/// itself.
+///
+/// Implement this trait to supply time in environments where [`std::time::Instant`] is not
+/// available, such as `performance.now()` in browsers.
+pub trait Clock {
+    /// Get current time as duration from an arbitrary but fixed origin.
+    ///
+    /// Returned time must not decrease.
+    fn now(&self) -> Duration;
+}
+
+/// A [`Clock`] backed by [`std::time::Instant`].
+///
+/// This is the default clock of [`TypingEngine`](crate::TypingEngine).
+#[cfg(feature = "std_clock")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    origin: Instant,
+}
+
+#[cfg(feature = "std_clock")]
+impl StdClock {
+    /// Construct a clock whose origin is now.
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std_clock")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std_clock")]
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+// std_clockが無効な場合の既定の時計
+// 時刻が進まないので経過時間は明示的に与える必要がある
+#[cfg(not(feature = "std_clock"))]
+struct FrozenClock;
+
+#[cfg(not(feature = "std_clock"))]
+impl Clock for FrozenClock {
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+// エンジンに注入された時計
+// 時刻の取得方法はエンジンの状態ではないので比較やハッシュの対象にしない
+#[derive(Clone)]
+pub(crate) struct EngineClock(Arc<dyn Clock + Send + Sync>);
+
+impl EngineClock {
+    pub(crate) fn new<C: Clock + Send + Sync + 'static>(clock: C) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    pub(crate) fn now(&self) -> Duration {
+        self.0.now()
+    }
+}
+
+impl Default for EngineClock {
+    fn default() -> Self {
+        #[cfg(feature = "std_clock")]
+        return Self::new(StdClock::new());
+        #[cfg(not(feature = "std_clock"))]
+        return Self::new(FrozenClock);
+    }
+}
+
+impl Debug for EngineClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EngineClock")
+    }
+}
+
+impl PartialEq for EngineClock {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for EngineClock {}
+
+impl Hash for EngineClock {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+}
@@ -0,0 +1,85 @@
+/// An event emitted when transient wrong state is changed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WrongStateEvent {
+    /// Transient wrong state is cleared by
+    /// [`clear_pending_wrong_state`](crate::TypingEngine::clear_pending_wrong_state()).
+    ///
+    /// `discarded_count` is count of wrong key strokes in strict mode which no longer need to be
+    /// deleted. UI should clear highlights of errors accumulated so far.
+    Cleared { discarded_count: usize },
+}
+
+// 厳格モードにおいて訂正されていないミスタイプと訂正回数を管理する
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub(crate) struct CorrectionManager {
+    // まだ削除されていないミスタイプの数
+    uncorrected_count: usize,
+    // バックスペースによって削除したミスタイプの数
+    correction_count: usize,
+}
+
+impl CorrectionManager {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn uncorrected_count(&self) -> usize {
+        self.uncorrected_count
+    }
+
+    pub(crate) fn correction_count(&self) -> usize {
+        self.correction_count
+    }
+
+    // 訂正されていないミスタイプがある間は全てのキーストロークをミスタイプとして扱う必要がある
+    pub(crate) fn has_uncorrected(&self) -> bool {
+        self.uncorrected_count != 0
+    }
+
+    pub(crate) fn on_wrong(&mut self) {
+        self.uncorrected_count += 1;
+    }
+
+    // 訂正せずに未訂正のミスタイプを破棄する
+    // 破棄したミスタイプは訂正回数には数えない
+    pub(crate) fn discard_uncorrected(&mut self) -> usize {
+        std::mem::take(&mut self.uncorrected_count)
+    }
+
+    // 削除するミスタイプがない場合には何もしない
+    pub(crate) fn on_backspace(&mut self) {
+        if self.has_uncorrected() {
+            self.uncorrected_count -= 1;
+            self.correction_count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn correction_manager_1() {
+        let mut cm = CorrectionManager::new();
+
+        cm.on_backspace();
+        assert_eq!(cm.correction_count(), 0);
+
+        cm.on_wrong();
+        cm.on_wrong();
+        assert!(cm.has_uncorrected());
+
+        cm.on_backspace();
+        cm.on_backspace();
+        cm.on_backspace();
+        assert!(!cm.has_uncorrected());
+        assert_eq!(cm.uncorrected_count(), 0);
+        assert_eq!(cm.correction_count(), 2);
+
+        cm.on_wrong();
+        assert_eq!(cm.discard_uncorrected(), 1);
+        assert!(!cm.has_uncorrected());
+        assert_eq!(cm.correction_count(), 2);
+    }
+}
@@ -0,0 +1,89 @@
+// ダイジェストの形式のバージョン
+// ダイジェストに含める情報や符号化を変更した場合には値を変える
+const DIGEST_FORMAT_VERSION: u64 = 1;
+
+// 64bit FNV-1aのパラメータ
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// エンジンの状態からダイジェストを計算する
+///
+/// 標準ライブラリのハッシュ関数はRustのバージョン間での安定性が保証されないため自前で実装する
+/// 各値は長さや区切りを含めて符号化するので異なる値の列が同じバイト列になることはない
+pub(crate) struct StateDigester {
+    hash: u64,
+}
+
+impl StateDigester {
+    pub(crate) fn new() -> Self {
+        let mut digester = Self {
+            hash: FNV_OFFSET_BASIS,
+        };
+        digester.write_u64(DIGEST_FORMAT_VERSION);
+
+        digester
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|byte| {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        });
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    // プラットフォームによって幅が変わらないように64bitとして符号化する
+    pub(crate) fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        self.write_bytes(&[u8::from(value)]);
+    }
+
+    pub(crate) fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub(crate) fn write_usizes(&mut self, values: &[usize]) {
+        self.write_usize(values.len());
+        values.iter().for_each(|value| self.write_usize(*value));
+    }
+
+    pub(crate) fn write_optional_usize(&mut self, value: Option<usize>) {
+        self.write_bool(value.is_some());
+        if let Some(value) = value {
+            self.write_usize(value);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn digest_is_stable() {
+        let mut digester = StateDigester::new();
+        digester.write_str("abc");
+        digester.write_usizes(&[1, 2]);
+
+        // 値が変わるとダイジェストの互換性が失われるので固定値と比較する
+        assert_eq!(digester.finish(), 0x8563_64e5_f07f_a208);
+
+        let mut other_digester = StateDigester::new();
+        other_digester.write_str("ab");
+        other_digester.write_str("c");
+        other_digester.write_usizes(&[1, 2]);
+
+        assert_ne!(digester.finish(), other_digester.finish());
+    }
+}
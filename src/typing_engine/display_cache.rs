@@ -0,0 +1,506 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use crate::display_info::{
+    DisplayInfoDelta, PaceDisplayInfo, ProgressDisplayInfo, SpeedDisplayInfo, TextDelta, TextEdit,
+};
+use crate::statistics::LapRequest;
+use crate::typing_engine::processed_chunk_info::{DisplayStringBuilder, ProcessedChunkInfo};
+use crate::vocabulary::{
+    construct_view_position_of_spell_positions, convert_spell_positions_to_view_positions,
+    write_presentation_spell, ViewPosition, VocabularyInfo,
+};
+
+// 設定したラップ要求以外で確定したチャンクまでを反映した表示用の情報を保持しておく数
+const ADDITIONAL_DISPLAY_STRING_BUILDER_CAPACITY: usize = 4;
+
+/// 表示用の情報のうちキーストロークのたびには変化しない部分
+///
+/// クエリが変わるたびに求め直しておくことで表示用の情報を構築する際にクエリ全体を走査しなくて済むようにする
+#[derive(Debug, Default)]
+pub(crate) struct DisplayCache {
+    view: String,
+    view_char_count: usize,
+    presentation_spell: String,
+    view_position_of_spell_positions: Vec<ViewPosition>,
+    // 改行する語彙の末尾の表示する文字列での位置とクエリの先頭からその語彙の末尾までのチャンク数
+    line_break_view_positions: Vec<usize>,
+    line_break_chunk_counts: Vec<usize>,
+    // 設定したラップ要求で確定したチャンクまでを反映した表示用の情報
+    // キーストロークのたびに新たに確定したチャンクのみを反映する
+    configured_display_string_builder: Option<DisplayStringBuilder>,
+    // それ以外のラップ要求で確定したチャンクまでを反映した表示用の情報
+    // 表示用の情報は不変参照から構築するので内部可変性を持たせ、要求されたときに新たに確定したチャンクのみを反映する
+    additional_display_string_builders: Mutex<VecDeque<DisplayStringBuilder>>,
+    // 前回差分を構築してからクエリが変わった綴りと表示する文字列のそれぞれの先頭の位置
+    query_changed_positions: Option<(usize, usize)>,
+    delta_cursor: DisplayDeltaCursor,
+}
+
+/// 前回差分を構築した時点の表示用の情報
+#[derive(Debug, Clone, Default)]
+struct DisplayDeltaCursor {
+    // 構築し直した後に一度も差分を構築していない場合には偽であり全体が変わったとみなす
+    // 文字列全体を差し替えられるように文字数は構築し直した後も保持する
+    is_constructed: bool,
+    #[cfg(feature = "statistics")]
+    lap_request: Option<LapRequest>,
+    confirmed_chunk_count: usize,
+    // 最小の候補の連鎖から表示していた先頭のチャンクとそのキーストロークでの先頭の位置
+    first_chain_chunk_index: usize,
+    first_chain_key_stroke_head: usize,
+    view_count: usize,
+    spell_count: usize,
+    key_stroke_count: usize,
+    ideal_key_stroke_count: usize,
+    #[cfg(feature = "statistics")]
+    completed_lap_count: usize,
+}
+
+impl DisplayCache {
+    /// クエリが変わった際に語彙から求まる部分を求め直す
+    pub(crate) fn update_query(
+        &mut self,
+        vocabulary_infos: &[VocabularyInfo],
+        spell_char_count: usize,
+    ) {
+        let view: String = vocabulary_infos
+            .iter()
+            .map(|vocabulary_info| vocabulary_info.view())
+            .collect();
+        let mut presentation_spell = String::new();
+        write_presentation_spell(vocabulary_infos, spell_char_count, &mut presentation_spell);
+
+        // 変わる前と共通する先頭部分は差分として送らなくて良い
+        let changed_positions = (
+            count_common_prefix_chars(&self.presentation_spell, &presentation_spell),
+            count_common_prefix_chars(&self.view, &view),
+        );
+        self.query_changed_positions = Some(self.query_changed_positions.map_or(
+            changed_positions,
+            |(spell_position, view_position)| {
+                (
+                    spell_position.min(changed_positions.0),
+                    view_position.min(changed_positions.1),
+                )
+            },
+        ));
+
+        self.view_char_count = view.chars().count();
+        self.view = view;
+        self.presentation_spell = presentation_spell;
+        self.view_position_of_spell_positions =
+            construct_view_position_of_spell_positions(vocabulary_infos);
+
+        self.line_break_view_positions.clear();
+        self.line_break_chunk_counts.clear();
+        let mut view_position = 0;
+        let mut chunk_count = 0;
+        // クエリの末尾での改行は意味がないので最後の語彙は除く
+        vocabulary_infos
+            .iter()
+            .take(vocabulary_infos.len().saturating_sub(1))
+            .for_each(|vocabulary_info| {
+                view_position += vocabulary_info.view().chars().count();
+                chunk_count += vocabulary_info.chunk_count().get();
+
+                if vocabulary_info.has_trailing_line_break() {
+                    self.line_break_view_positions.push(view_position);
+                    self.line_break_chunk_counts.push(chunk_count);
+                }
+            });
+    }
+
+    /// 確定したチャンクまでを反映した表示用の情報を全て破棄する
+    /// 設定したラップ要求は保持する
+    pub(crate) fn reset_display_string_builders(&mut self) {
+        if let Some(display_string_builder) = self.configured_display_string_builder.as_mut() {
+            *display_string_builder =
+                DisplayStringBuilder::new(display_string_builder.lap_request().clone());
+        }
+
+        self.additional_display_string_builders
+            .get_mut()
+            .unwrap()
+            .clear();
+        self.delta_cursor.is_constructed = false;
+    }
+
+    /// キーストロークのたびに確定したチャンクを反映するラップ要求を設定する
+    pub(crate) fn set_lap_request(&mut self, lap_request: LapRequest) {
+        self.configured_display_string_builder
+            .replace(DisplayStringBuilder::new(lap_request));
+    }
+
+    /// 設定したラップ要求での表示用の情報に新たに確定したチャンクを反映する
+    pub(crate) fn apply_confirmed_chunks(
+        &mut self,
+        processed_chunk_info: &ProcessedChunkInfo,
+        vocabulary_end_chunk_counts: &[usize],
+    ) {
+        if let Some(display_string_builder) = self.configured_display_string_builder.as_mut() {
+            processed_chunk_info
+                .apply_confirmed_chunks(display_string_builder, vocabulary_end_chunk_counts);
+        }
+    }
+
+    #[cfg(feature = "statistics")]
+    pub(crate) fn configured_display_string_builder(&self) -> Option<&DisplayStringBuilder> {
+        self.configured_display_string_builder.as_ref()
+    }
+
+    /// `lap_request`で確定したチャンクまでを反映した表示用の情報を用いる
+    ///
+    /// 設定したラップ要求でなければ保持しているものに新たに確定したチャンクのみを反映して用いる
+    /// 保持していない場合には確定したチャンクを全て反映して保持する
+    pub(crate) fn with_display_string_builder<R>(
+        &self,
+        lap_request: &LapRequest,
+        processed_chunk_info: &ProcessedChunkInfo,
+        vocabulary_end_chunk_counts: &[usize],
+        f: impl FnOnce(&DisplayStringBuilder) -> R,
+    ) -> R {
+        // 設定したラップ要求のものはキーストロークのたびに反映しているので反映し損ねたチャンクがない場合のみ用いる
+        if let Some(display_string_builder) = self
+            .configured_display_string_builder
+            .as_ref()
+            .filter(|display_string_builder| {
+                display_string_builder.lap_request() == lap_request
+                    && display_string_builder.applied_chunk_count()
+                        == processed_chunk_info.confirmed_chunks().len()
+            })
+        {
+            return f(display_string_builder);
+        }
+
+        let mut display_string_builders = self.additional_display_string_builders.lock().unwrap();
+        // 最近用いたものほど後ろに置き、保持できる数を超えたら最も前のものを破棄する
+        let display_string_builder = match display_string_builders
+            .iter()
+            .position(|display_string_builder| display_string_builder.lap_request() == lap_request)
+        {
+            Some(index) => display_string_builders.remove(index).unwrap(),
+            None => {
+                if display_string_builders.len() == ADDITIONAL_DISPLAY_STRING_BUILDER_CAPACITY {
+                    display_string_builders.pop_front();
+                }
+
+                DisplayStringBuilder::new(lap_request.clone())
+            }
+        };
+        let display_string_builder = {
+            display_string_builders.push_back(display_string_builder);
+            display_string_builders.back_mut().unwrap()
+        };
+        processed_chunk_info
+            .apply_confirmed_chunks(display_string_builder, vocabulary_end_chunk_counts);
+
+        f(display_string_builder)
+    }
+
+    /// 前回差分を構築した時点からの表示用の情報の差分を構築する
+    ///
+    /// 文字列は変わりうる範囲のみを差分とし、確定したチャンクや最小の候補の連鎖で表示していて変わらない部分は送らない
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn construct_display_info_delta(
+        &mut self,
+        lap_request: &LapRequest,
+        processed_chunk_info: &mut ProcessedChunkInfo,
+        vocabulary_end_chunk_counts: &[usize],
+        progress_display_info: ProgressDisplayInfo,
+        speed_display_info: SpeedDisplayInfo,
+        pace_display_info: Option<PaceDisplayInfo>,
+    ) -> DisplayInfoDelta {
+        let cursor = std::mem::take(&mut self.delta_cursor);
+        let refreshed_chunk_index = processed_chunk_info.take_refreshed_chunk_index();
+        let query_changed_positions = self.query_changed_positions.take();
+        let processed_chunk_info = &*processed_chunk_info;
+
+        // 構築し直した後は全体が変わったとみなす
+        let (refreshed_chunk_index, query_changed_positions, prev_confirmed_chunk_count) =
+            if cursor.is_constructed {
+                (
+                    refreshed_chunk_index,
+                    query_changed_positions,
+                    cursor.confirmed_chunk_count,
+                )
+            } else {
+                (Some(0), Some((0, 0)), 0)
+            };
+        #[cfg(feature = "statistics")]
+        let prev_completed_lap_count =
+            if cursor.is_constructed && cursor.lap_request.as_ref() == Some(lap_request) {
+                cursor.completed_lap_count
+            } else {
+                0
+            };
+
+        #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+        let (spell_display_info, key_stroke_display_info, display_statistics) = self
+            .with_display_string_builder(
+                lap_request,
+                processed_chunk_info,
+                vocabulary_end_chunk_counts,
+                |display_string_builder| {
+                    processed_chunk_info.snapshot_display_info_without_strings(
+                        display_string_builder,
+                        vocabulary_end_chunk_counts,
+                    )
+                },
+            );
+
+        let first_chain_chunk_index = processed_chunk_info.first_chain_chunk_index();
+        let heads = processed_chunk_info
+            .key_stroke_heads_of(&[prev_confirmed_chunk_count, first_chain_chunk_index]);
+        let (unchanged_key_stroke_count, first_chain_key_stroke_head) = (heads[0], heads[1]);
+
+        // キーストローク
+        // 前回と今回のどちらでも最小の候補の連鎖から表示しているチャンクは連鎖を求め直していなければ変わっていない
+        let chain_unchanged_chunk_index =
+            cursor.first_chain_chunk_index.max(first_chain_chunk_index);
+        let mut key_stroke_edits = vec![];
+        if refreshed_chunk_index.is_some_and(|refreshed_chunk_index| {
+            refreshed_chunk_index <= chain_unchanged_chunk_index
+        }) {
+            let mut inserted = String::new();
+            processed_chunk_info.write_key_stroke_between(
+                prev_confirmed_chunk_count,
+                processed_chunk_info.chunk_count(),
+                &mut inserted,
+            );
+            key_stroke_edits.push(TextEdit::new(
+                unchanged_key_stroke_count,
+                cursor.key_stroke_count - unchanged_key_stroke_count,
+                inserted,
+            ));
+        } else {
+            let prev_key_stroke_head_of = |chunk_index: usize| {
+                cursor.first_chain_key_stroke_head
+                    + processed_chunk_info.chained_key_stroke_count_between(
+                        cursor.first_chain_chunk_index,
+                        chunk_index,
+                    )
+            };
+
+            let mut inserted = String::new();
+            processed_chunk_info.write_key_stroke_between(
+                prev_confirmed_chunk_count,
+                chain_unchanged_chunk_index,
+                &mut inserted,
+            );
+            key_stroke_edits.push(TextEdit::new(
+                unchanged_key_stroke_count,
+                prev_key_stroke_head_of(chain_unchanged_chunk_index) - unchanged_key_stroke_count,
+                inserted,
+            ));
+
+            // 求め直したチャンク以降は末尾まで差し替える
+            if let Some(refreshed_chunk_index) = refreshed_chunk_index {
+                let mut inserted = String::new();
+                processed_chunk_info.write_key_stroke_between(
+                    refreshed_chunk_index,
+                    processed_chunk_info.chunk_count(),
+                    &mut inserted,
+                );
+                key_stroke_edits.push(TextEdit::new(
+                    processed_chunk_info.key_stroke_heads_of(&[refreshed_chunk_index])[0],
+                    cursor.key_stroke_count - prev_key_stroke_head_of(refreshed_chunk_index),
+                    inserted,
+                ));
+            }
+        }
+        let key_stroke_delta = TextDelta::new(
+            key_stroke_edits,
+            vec![key_stroke_display_info.current_cursor_position()],
+            unchanged_key_stroke_count,
+            filter_positions_from(
+                key_stroke_display_info.missed_positions(),
+                unchanged_key_stroke_count,
+            ),
+        );
+
+        // 理想的なキーストローク
+        // 候補によらないので求め直したチャンク以降のみが変わる
+        let ideal_key_stroke_edits = refreshed_chunk_index
+            .map(|refreshed_chunk_index| {
+                let (head, ideal_key_stroke) =
+                    processed_chunk_info.ideal_key_stroke_from(refreshed_chunk_index);
+
+                TextEdit::new(
+                    head,
+                    cursor.ideal_key_stroke_count - head,
+                    ideal_key_stroke.to_string(),
+                )
+            })
+            .into_iter()
+            .collect();
+        let (ideal_cursor_position, ideal_key_stroke_count) =
+            processed_chunk_info.ideal_key_stroke_progress();
+        let ideal_key_stroke_delta = TextDelta::new(
+            ideal_key_stroke_edits,
+            vec![ideal_cursor_position],
+            0,
+            vec![],
+        );
+
+        // 綴りと表示する文字列はクエリが変わった位置以降のみが変わる
+        let unchanged_spell_count =
+            processed_chunk_info.spell_heads_of(&[prev_confirmed_chunk_count])[0];
+        let spell_delta = TextDelta::new(
+            query_changed_positions
+                .map(|(spell_position, _)| {
+                    TextEdit::new(
+                        spell_position,
+                        cursor.spell_count - spell_position,
+                        self.presentation_spell
+                            .chars()
+                            .skip(spell_position)
+                            .collect(),
+                    )
+                })
+                .into_iter()
+                .collect(),
+            spell_display_info.current_cursor_positions().clone(),
+            unchanged_spell_count,
+            filter_positions_from(spell_display_info.missed_positions(), unchanged_spell_count),
+        );
+
+        // 複数の綴りが同じ位置に対応する場合があるので全てのミス位置を変換してから絞り込む
+        let unchanged_view_count = self
+            .view_position_of_spell_positions
+            .get(unchanged_spell_count)
+            .map_or(self.view_char_count, |view_position| {
+                view_position.first_position()
+            });
+        let view_delta = TextDelta::new(
+            query_changed_positions
+                .map(|(_, view_position)| {
+                    TextEdit::new(
+                        view_position,
+                        cursor.view_count - view_position,
+                        self.view.chars().skip(view_position).collect(),
+                    )
+                })
+                .into_iter()
+                .collect(),
+            convert_spell_positions_to_view_positions(
+                spell_display_info.current_cursor_positions(),
+                &self.view_position_of_spell_positions,
+            ),
+            unchanged_view_count,
+            filter_positions_from(
+                &convert_spell_positions_to_view_positions(
+                    spell_display_info.missed_positions(),
+                    &self.view_position_of_spell_positions,
+                ),
+                unchanged_view_count,
+            ),
+        );
+        let view_last_position = self.view_position_of_spell_positions
+            [spell_display_info.last_position()]
+        .last_position();
+
+        self.delta_cursor = DisplayDeltaCursor {
+            is_constructed: true,
+            #[cfg(feature = "statistics")]
+            lap_request: Some(lap_request.clone()),
+            confirmed_chunk_count: processed_chunk_info.confirmed_chunks().len(),
+            first_chain_chunk_index,
+            first_chain_key_stroke_head,
+            view_count: self.view_char_count,
+            spell_count: processed_chunk_info.spell_count(),
+            key_stroke_count: processed_chunk_info.key_stroke_count(),
+            ideal_key_stroke_count,
+            #[cfg(feature = "statistics")]
+            completed_lap_count: display_statistics.0.len(),
+        };
+
+        DisplayInfoDelta::new(
+            processed_chunk_info.confirmed_chunks().len() - prev_confirmed_chunk_count,
+            view_delta,
+            view_last_position,
+            spell_delta,
+            spell_display_info.last_position(),
+            key_stroke_delta,
+            ideal_key_stroke_delta,
+            #[cfg(feature = "statistics")]
+            display_statistics.1,
+            #[cfg(feature = "statistics")]
+            display_statistics.0[prev_completed_lap_count..].to_vec(),
+            progress_display_info,
+            speed_display_info,
+            pace_display_info,
+        )
+    }
+
+    pub(crate) fn view(&self) -> &str {
+        &self.view
+    }
+
+    pub(crate) fn presentation_spell(&self) -> &str {
+        &self.presentation_spell
+    }
+
+    pub(crate) fn view_position_of_spell_positions(&self) -> &[ViewPosition] {
+        &self.view_position_of_spell_positions
+    }
+
+    pub(crate) fn line_break_view_positions(&self) -> &[usize] {
+        &self.line_break_view_positions
+    }
+
+    pub(crate) fn line_break_chunk_counts(&self) -> &[usize] {
+        &self.line_break_chunk_counts
+    }
+}
+
+impl Clone for DisplayCache {
+    fn clone(&self) -> Self {
+        Self {
+            view: self.view.clone(),
+            view_char_count: self.view_char_count,
+            presentation_spell: self.presentation_spell.clone(),
+            view_position_of_spell_positions: self.view_position_of_spell_positions.clone(),
+            line_break_view_positions: self.line_break_view_positions.clone(),
+            line_break_chunk_counts: self.line_break_chunk_counts.clone(),
+            configured_display_string_builder: self.configured_display_string_builder.clone(),
+            additional_display_string_builders: Mutex::new(
+                self.additional_display_string_builders
+                    .lock()
+                    .unwrap()
+                    .clone(),
+            ),
+            query_changed_positions: self.query_changed_positions,
+            delta_cursor: self.delta_cursor.clone(),
+        }
+    }
+}
+
+fn count_common_prefix_chars(a: &str, b: &str) -> usize {
+    a.chars()
+        .zip(b.chars())
+        .take_while(|(a_char, b_char)| a_char == b_char)
+        .count()
+}
+
+fn filter_positions_from(positions: &[usize], from: usize) -> Vec<usize> {
+    positions
+        .iter()
+        .filter(|position| **position >= from)
+        .copied()
+        .collect()
+}
+
+// キャッシュはエンジンの状態から導出できるので比較やハッシュの対象にしない
+impl PartialEq for DisplayCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DisplayCache {}
+
+impl Hash for DisplayCache {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+}
@@ -0,0 +1,27 @@
+use crate::key_stroke::KeyStrokeChar;
+
+/// An event occurred while typing.
+///
+/// Events are emitted by key strokes and can be taken by
+/// [`poll_events`](crate::TypingEngine::poll_events()), so UI can react to them without
+/// comparing [`DisplayInfo`](crate::DisplayInfo) every frame.
+/// Chunk and lap indices are same as those in [`DisplayInfo`](crate::DisplayInfo).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EngineEvent {
+    /// A wrong key stroke is given while typing chunk of `chunk_index`.
+    MissType {
+        chunk_index: usize,
+        key_stroke: KeyStrokeChar,
+    },
+    /// Chunk of `chunk_index` is confirmed, that is, spells of the chunk are finished.
+    ///
+    /// Some chunks are confirmed by key strokes of next chunk, so multiple chunks may be confirmed
+    /// by a key stroke.
+    ChunkConfirmed { chunk_index: usize },
+    /// Lap of `lap_index` is completed.
+    ///
+    /// This event is emitted only when [`LapRequest`](crate::LapRequest) is configured by
+    /// [`set_lap_request`](crate::TypingEngine::set_lap_request()).
+    /// Without `statistics` feature, laps are not tracked and this event is never emitted.
+    LapCompleted { lap_index: usize },
+}
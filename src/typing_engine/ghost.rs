@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use crate::display_info::DisplayInfo;
+
+/// Cursor positions of a previous session at certain time, which are useful to render a ghost
+/// cursor racing the player.
+///
+/// This is constructed by [`TypingGhost::cursor_at`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhostCursor {
+    view_cursor_positions: Vec<usize>,
+    spell_cursor_positions: Vec<usize>,
+    key_stroke_cursor_position: usize,
+    interpolated_key_stroke_cursor_position: f64,
+}
+
+impl GhostCursor {
+    /// Get cursor positions of view.
+    ///
+    /// See [`ViewDisplayInfo::current_cursor_positions`](crate::display_info::ViewDisplayInfo::current_cursor_positions()) for details.
+    pub fn view_cursor_positions(&self) -> &Vec<usize> {
+        &self.view_cursor_positions
+    }
+
+    /// Get cursor positions of spell.
+    ///
+    /// See [`SpellDisplayInfo::current_cursor_positions`](crate::display_info::SpellDisplayInfo::current_cursor_positions()) for details.
+    pub fn spell_cursor_positions(&self) -> &Vec<usize> {
+        &self.spell_cursor_positions
+    }
+
+    /// Get cursor position of key strokes of the previous session.
+    ///
+    /// Key strokes of the previous session can be different from the player's one because
+    /// candidates chosen can be different.
+    pub fn key_stroke_cursor_position(&self) -> usize {
+        self.key_stroke_cursor_position
+    }
+
+    /// Get cursor position of key strokes linearly interpolated between recorded key strokes.
+    ///
+    /// This is useful to move a ghost cursor smoothly.
+    pub fn interpolated_key_stroke_cursor_position(&self) -> f64 {
+        self.interpolated_key_stroke_cursor_position
+    }
+}
+
+/// Cursor positions of a previous session over time.
+///
+/// This is constructed by
+/// [`TypingEngine::construct_ghost`](crate::TypingEngine::construct_ghost()).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypingGhost {
+    // 開始時とキーストロークごとの経過時間とカーソル位置
+    // 経過時間の昇順に並んでいて先頭は開始時のものである
+    frames: Vec<GhostFrame>,
+}
+
+// ある時点での経過時間と各対象のカーソル位置
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct GhostFrame {
+    elapsed_time: Duration,
+    view_cursor_positions: Vec<usize>,
+    spell_cursor_positions: Vec<usize>,
+    key_stroke_cursor_position: usize,
+}
+
+impl GhostFrame {
+    pub(crate) fn new(elapsed_time: Duration, display_info: &DisplayInfo) -> Self {
+        Self {
+            elapsed_time,
+            view_cursor_positions: display_info.view_info().current_cursor_positions().clone(),
+            spell_cursor_positions: display_info.spell_info().current_cursor_positions().clone(),
+            key_stroke_cursor_position: display_info.key_stroke_info().current_cursor_position(),
+        }
+    }
+}
+
+impl TypingGhost {
+    pub(crate) fn new(frames: Vec<GhostFrame>) -> Self {
+        assert!(!frames.is_empty());
+
+        Self { frames }
+    }
+
+    /// Get cursor positions the previous session had at `elapsed_time` from start.
+    ///
+    /// After the last key stroke of the previous session, cursor positions stay there.
+    pub fn cursor_at(&self, elapsed_time: Duration) -> GhostCursor {
+        // elapsed_time以前の最後のキーストロークの位置
+        let index = self
+            .frames
+            .partition_point(|frame| frame.elapsed_time <= elapsed_time)
+            .saturating_sub(1);
+
+        let frame = &self.frames[index];
+        let key_stroke_cursor_position = frame.key_stroke_cursor_position;
+
+        // 次のキーストロークまでの時間の割合でカーソル位置を補間する
+        let interpolated_key_stroke_cursor_position = match self.frames.get(index + 1) {
+            Some(next_frame) if next_frame.elapsed_time > frame.elapsed_time => {
+                let ratio = elapsed_time
+                    .saturating_sub(frame.elapsed_time)
+                    .as_secs_f64()
+                    / (next_frame.elapsed_time - frame.elapsed_time).as_secs_f64();
+
+                key_stroke_cursor_position as f64
+                    + (next_frame.key_stroke_cursor_position as f64
+                        - key_stroke_cursor_position as f64)
+                        * ratio
+            }
+            _ => key_stroke_cursor_position as f64,
+        };
+
+        GhostCursor {
+            view_cursor_positions: frame.view_cursor_positions.clone(),
+            spell_cursor_positions: frame.spell_cursor_positions.clone(),
+            key_stroke_cursor_position,
+            interpolated_key_stroke_cursor_position,
+        }
+    }
+
+    /// Get elapsed time from start to last key stroke of the previous session.
+    pub fn duration(&self) -> Duration {
+        self.frames.last().unwrap().elapsed_time
+    }
+}
@@ -0,0 +1,208 @@
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+
+/// A configuration of lives economy.
+///
+/// Each wrong key stroke subtracts `damage_per_miss` from life and typing is finished when life
+/// becomes 0.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifeConfig {
+    max_life: NonZeroUsize,
+    damage_per_miss: usize,
+    regeneration: Option<LifeRegeneration>,
+}
+
+impl LifeConfig {
+    /// Construct a new [`LifeConfig`].
+    ///
+    /// Life starts from `max_life` and never exceeds it even if life is regenerated.
+    pub fn new(
+        max_life: NonZeroUsize,
+        damage_per_miss: usize,
+        regeneration: Option<LifeRegeneration>,
+    ) -> Self {
+        Self {
+            max_life,
+            damage_per_miss,
+            regeneration,
+        }
+    }
+
+    pub fn max_life(&self) -> NonZeroUsize {
+        self.max_life
+    }
+
+    pub fn damage_per_miss(&self) -> usize {
+        self.damage_per_miss
+    }
+
+    pub fn regeneration(&self) -> Option<&LifeRegeneration> {
+        self.regeneration.as_ref()
+    }
+}
+
+/// A configuration of life regeneration by correct key stroke streak.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifeRegeneration {
+    streak: NonZeroUsize,
+    amount: usize,
+}
+
+impl LifeRegeneration {
+    /// Construct a new [`LifeRegeneration`].
+    ///
+    /// Life is regenerated by `amount` every time `streak` correct key strokes are given in a row.
+    pub fn new(streak: NonZeroUsize, amount: usize) -> Self {
+        Self { streak, amount }
+    }
+
+    pub fn streak(&self) -> NonZeroUsize {
+        self.streak
+    }
+
+    pub fn amount(&self) -> usize {
+        self.amount
+    }
+}
+
+/// An event about lives emitted by [`TypingEngine`](crate::TypingEngine).
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LifeEvent {
+    /// Life is changed by key stroke.
+    LifeChanged { previous: usize, current: usize },
+    /// Life becomes 0 and typing is finished.
+    GameOver,
+}
+
+// ライフの増減を管理する
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub(crate) struct LifeManager {
+    config: LifeConfig,
+    life: usize,
+    // 連続して正しくタイプしたキーストローク数
+    correct_streak: usize,
+    events: Vec<LifeEvent>,
+}
+
+impl LifeManager {
+    pub(crate) fn new(config: LifeConfig) -> Self {
+        Self {
+            life: config.max_life().get(),
+            config,
+            correct_streak: 0,
+            events: vec![],
+        }
+    }
+
+    pub(crate) fn life(&self) -> usize {
+        self.life
+    }
+
+    pub(crate) fn is_game_over(&self) -> bool {
+        self.life == 0
+    }
+
+    pub(crate) fn on_correct(&mut self) {
+        assert!(!self.is_game_over());
+
+        self.correct_streak += 1;
+
+        if let Some(regeneration) = self.config.regeneration() {
+            if self
+                .correct_streak
+                .is_multiple_of(regeneration.streak().get())
+            {
+                let new_life =
+                    (self.life + regeneration.amount()).min(self.config.max_life().get());
+                self.change_life(new_life);
+            }
+        }
+    }
+
+    pub(crate) fn on_wrong(&mut self) {
+        assert!(!self.is_game_over());
+
+        self.correct_streak = 0;
+
+        let new_life = self.life.saturating_sub(self.config.damage_per_miss());
+        self.change_life(new_life);
+
+        if self.is_game_over() {
+            self.events.push(LifeEvent::GameOver);
+        }
+    }
+
+    pub(crate) fn take_events(&mut self) -> Vec<LifeEvent> {
+        self.events.drain(..).collect()
+    }
+
+    fn change_life(&mut self, new_life: usize) {
+        if new_life != self.life {
+            self.events.push(LifeEvent::LifeChanged {
+                previous: self.life,
+                current: new_life,
+            });
+            self.life = new_life;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn life_manager_1() {
+        let mut lm = LifeManager::new(LifeConfig::new(
+            NonZeroUsize::new(3).unwrap(),
+            2,
+            Some(LifeRegeneration::new(NonZeroUsize::new(2).unwrap(), 1)),
+        ));
+
+        lm.on_wrong();
+        assert_eq!(lm.life(), 1);
+
+        lm.on_correct();
+        lm.on_correct();
+        assert_eq!(lm.life(), 2);
+
+        lm.on_correct();
+        lm.on_correct();
+        lm.on_correct();
+        lm.on_correct();
+        assert_eq!(lm.life(), 3);
+
+        lm.on_wrong();
+        lm.on_wrong();
+        assert!(lm.is_game_over());
+
+        assert_eq!(
+            lm.take_events(),
+            vec![
+                LifeEvent::LifeChanged {
+                    previous: 3,
+                    current: 1
+                },
+                LifeEvent::LifeChanged {
+                    previous: 1,
+                    current: 2
+                },
+                LifeEvent::LifeChanged {
+                    previous: 2,
+                    current: 3
+                },
+                LifeEvent::LifeChanged {
+                    previous: 3,
+                    current: 1
+                },
+                LifeEvent::LifeChanged {
+                    previous: 1,
+                    current: 0
+                },
+                LifeEvent::GameOver,
+            ]
+        );
+        assert!(lm.take_events().is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+/// An outcome of a key stroke given to [`TypingEngine`](crate::TypingEngine).
+///
+/// This is returned by [`stroke_key_with_outcome`](crate::TypingEngine::stroke_key_with_outcome())
+/// and reports boundaries reached by the key stroke, so games can play sounds exactly on them.
+/// Chunk, vocabulary and lap indices are same as those in [`DisplayInfo`](crate::DisplayInfo).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyStrokeOutcome {
+    is_correct: bool,
+    is_finished: bool,
+    is_terminated: bool,
+    finished_spell_count: usize,
+    confirmed_chunk_indices: Vec<usize>,
+    completed_vocabulary_indices: Vec<usize>,
+    completed_lap_indices: Vec<usize>,
+}
+
+impl KeyStrokeOutcome {
+    pub(crate) fn new(
+        is_correct: bool,
+        is_finished: bool,
+        is_terminated: bool,
+        finished_spell_count: usize,
+        confirmed_chunk_indices: Vec<usize>,
+        completed_vocabulary_indices: Vec<usize>,
+        completed_lap_indices: Vec<usize>,
+    ) -> Self {
+        Self {
+            is_correct,
+            is_finished,
+            is_terminated,
+            finished_spell_count,
+            confirmed_chunk_indices,
+            completed_vocabulary_indices,
+            completed_lap_indices,
+        }
+    }
+
+    // 制限時間を過ぎたキーストロークは処理されずに終了する
+    pub(crate) fn time_limit_reached() -> Self {
+        Self {
+            is_finished: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the key stroke is correct.
+    ///
+    /// Key strokes not processed because time limit is reached are not correct.
+    pub fn is_correct(&self) -> bool {
+        self.is_correct
+    }
+
+    /// Whether typing is finished by the key stroke.
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    /// Whether typing is terminated by
+    /// [termination condition](crate::TypingEngine::set_termination_condition()).
+    ///
+    /// Typing is also [finished](Self::is_finished()) when it is terminated.
+    pub fn is_terminated(&self) -> bool {
+        self.is_terminated
+    }
+
+    /// Get count of spells finished by the key stroke.
+    pub fn finished_spell_count(&self) -> usize {
+        self.finished_spell_count
+    }
+
+    /// Get indices of chunks confirmed by the key stroke.
+    ///
+    /// Some chunks are confirmed by key strokes of next chunk, so multiple chunks may be confirmed
+    /// by a key stroke.
+    pub fn confirmed_chunk_indices(&self) -> &[usize] {
+        &self.confirmed_chunk_indices
+    }
+
+    /// Get indices of vocabularies completed by the key stroke.
+    pub fn completed_vocabulary_indices(&self) -> &[usize] {
+        &self.completed_vocabulary_indices
+    }
+
+    /// Get indices of laps completed by the key stroke.
+    ///
+    /// Laps are reported only when [`LapRequest`](crate::LapRequest) is configured by
+    /// [`set_lap_request`](crate::TypingEngine::set_lap_request()).
+    /// Without `statistics` feature, laps are not tracked and this is always empty.
+    pub fn completed_lap_indices(&self) -> &[usize] {
+        &self.completed_lap_indices
+    }
+}
@@ -1,40 +1,121 @@
 use std::collections::VecDeque;
+use std::num::NonZeroUsize;
 use std::time::Duration;
 
 use crate::chunk::confirmed::ConfirmedChunk;
 use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
 use crate::chunk::typed::{KeyStrokeResult, TypedChunk};
-use crate::chunk::Chunk;
-use crate::display_info::{KeyStrokeDisplayInfo, SpellDisplayInfo};
+use crate::chunk::{
+    append_key_stroke_to_chunks_followed_by, count_last_chunks_depending_on_following, Chunk,
+};
+use crate::display_info::{
+    KeyStrokeDisplayInfo, ProgressDisplayInfo, SpeedDisplayInfo, SpellDisplayInfo,
+};
+use crate::input_scheme::{InputScheme, RomanizationPreference};
 use crate::key_stroke::KeyStrokeChar;
-use crate::statistics::{LapRequest, OnTypingStatisticsManager};
-
+use crate::statistics::multi_target_position_convert::{BaseTarget, MultiTargetDeltaConverter};
+use crate::statistics::LapRequest;
+#[cfg(feature = "statistics")]
+use crate::statistics::{LapSummary, OnTypingStatisticsManager, OnTypingStatisticsTarget};
+use chunk_display_cache::ChunkDisplayCache;
+pub(crate) use display_string_builder::DisplayStringBuilder;
+
+mod chunk_display_cache;
+mod display_string_builder;
 #[cfg(test)]
 mod test;
 
+// 表示用の情報と合わせて求める終えたラップごとの集計と現在のコンボ数と最大コンボ数
+// 統計を無効にした場合には何も求めない
+#[cfg(feature = "statistics")]
+pub(crate) type DisplayStatistics = (Vec<LapSummary>, (usize, usize));
+#[cfg(not(feature = "statistics"))]
+pub(crate) type DisplayStatistics = ();
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub(crate) struct ProcessedChunkInfo {
     unprocessed_chunks: VecDeque<Chunk>,
+    // 候補を付与しておく範囲より後ろの未処理のチャンク列
+    // 末尾のチャンク以外はキーストローク候補を持たず綴りのみである
+    spell_only_chunks: VecDeque<Chunk>,
     inflight_chunk: Option<TypedChunk>,
     confirmed_chunks: Vec<ConfirmedChunk>,
+    candidate_window: Option<CandidateWindow>,
+    // 表示用の情報を構築する際にタイプ中のチャンク以外を走査しなくて済むように保持しておく情報
+    // チャンク列が変わるたびに変わったチャンク以降を求め直す
+    display_cache: ChunkDisplayCache,
+}
+
+// キーストローク候補を付与しておく未処理のチャンク数とその付与に必要な設定
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct CandidateWindow {
+    size: NonZeroUsize,
+    input_scheme: InputScheme,
+    romanization_preference: Option<RomanizationPreference>,
 }
 
 impl ProcessedChunkInfo {
     pub(crate) fn new(chunks: Vec<Chunk>) -> Self {
-        Self {
+        let mut processed_chunk_info = Self {
             unprocessed_chunks: chunks.into(),
+            spell_only_chunks: VecDeque::new(),
             inflight_chunk: None,
             confirmed_chunks: vec![],
-        }
+            candidate_window: None,
+            display_cache: ChunkDisplayCache::new(),
+        };
+        processed_chunk_info.refresh_display_cache_from(0);
+
+        processed_chunk_info
+    }
+
+    // 未処理のチャンクのうち先頭からsize個のみキーストローク候補を保持するようにする
+    // それより後ろのチャンクはカーソルが近づいたときに候補を付与し直す
+    pub(crate) fn with_candidate_window(
+        mut self,
+        size: NonZeroUsize,
+        input_scheme: InputScheme,
+        romanization_preference: Option<RomanizationPreference>,
+    ) -> Self {
+        assert!(self.inflight_chunk.is_none());
+
+        self.candidate_window.replace(CandidateWindow {
+            size,
+            input_scheme,
+            romanization_preference,
+        });
+        self.spill_over_candidate_window();
+        self.refresh_display_cache_from(0);
+
+        self
     }
 
     pub(crate) fn is_finished(&self) -> bool {
         // 処理すべきチャンクがない場合には終了である
+        // 綴りのみのチャンクがある場合には未処理のチャンクも必ずある
         self.unprocessed_chunks.is_empty() && self.inflight_chunk.is_none()
     }
 
+    /// クエリ全体の綴りを構築する
+    pub(crate) fn construct_query_spell(&self) -> String {
+        self.confirmed_chunks
+            .iter()
+            .map(|confirmed_chunk| confirmed_chunk.as_ref())
+            .chain(
+                self.inflight_chunk
+                    .iter()
+                    .map(|inflight_chunk| inflight_chunk.as_ref()),
+            )
+            .chain(self.unprocessed_chunks.iter())
+            .chain(self.spell_only_chunks.iter())
+            .map(|chunk| chunk.spell().as_ref().as_str())
+            .collect()
+    }
+
     pub(crate) fn append_chunks(&mut self, chunks: Vec<Chunk>) {
         let mut chunks: VecDeque<Chunk> = chunks.into();
+        // 末尾だったチャンクは候補が変わりうる
+        let refresh_start = self.chunk_count().saturating_sub(1);
 
         // 終了している状態で追加されたら先頭のチャンクを処理中にする必要がある
         if self.unprocessed_chunks.is_empty() && self.inflight_chunk.is_none() {
@@ -42,7 +123,247 @@ impl ProcessedChunkInfo {
                 .replace(chunks.pop_front().unwrap().into());
         }
 
-        self.unprocessed_chunks.append(&mut chunks);
+        if self.spell_only_chunks.is_empty() {
+            self.unprocessed_chunks.append(&mut chunks);
+            self.spill_over_candidate_window();
+        } else {
+            // 末尾だったチャンクも後続のチャンクを考慮して付与し直すために候補を取り除く
+            let strip_start = self.spell_only_chunks.len() - 1;
+            self.spell_only_chunks.append(&mut chunks);
+            self.strip_spell_only_chunks_from(strip_start);
+        }
+
+        self.refresh_display_cache_from(refresh_start.max(self.confirmed_chunks.len()));
+    }
+
+    /// 綴りのみのチャンクも含めた全てのチャンク数
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.confirmed_chunks.len()
+            + self.inflight_chunk.iter().count()
+            + self.unprocessed_chunks.len()
+            + self.spell_only_chunks.len()
+    }
+
+    // chunk_index番目以降のチャンクが変わったときに表示用に保持しておく情報を求め直す
+    fn refresh_display_cache_from(&mut self, chunk_index: usize) {
+        self.display_cache.refresh_from(
+            chunk_index,
+            self.inflight_chunk
+                .as_ref()
+                .map(|inflight_chunk| inflight_chunk.as_ref()),
+            &self.unprocessed_chunks,
+            &self.spell_only_chunks,
+        );
+    }
+
+    // 候補を付与しておく範囲を超えた未処理のチャンクを綴りのみのチャンク列に移す
+    fn spill_over_candidate_window(&mut self) {
+        let Some(candidate_window) = self.candidate_window.as_ref() else {
+            return;
+        };
+
+        if self.unprocessed_chunks.len() <= candidate_window.size.get() {
+            return;
+        }
+
+        assert!(self.spell_only_chunks.is_empty());
+        self.spell_only_chunks = self
+            .unprocessed_chunks
+            .split_off(candidate_window.size.get());
+        self.strip_spell_only_chunks_from(0);
+    }
+
+    // 綴りのみのチャンク列のうちstart番目以降のチャンクの候補を取り除く
+    // クエリの末尾のチャンクはキーストローク数の制限を保つために候補を残す
+    fn strip_spell_only_chunks_from(&mut self, start: usize) {
+        let end = self.spell_only_chunks.len().saturating_sub(1);
+
+        self.spell_only_chunks
+            .range_mut(start.min(end)..end)
+            .for_each(|chunk| *chunk = chunk.spell_only());
+    }
+
+    // 候補を付与しておく範囲に足りない分だけ綴りのみのチャンクに候補を付与して未処理のチャンク列に移す
+    fn fill_candidate_window(&mut self) {
+        let Some(candidate_window) = self.candidate_window.as_ref() else {
+            return;
+        };
+
+        let fill_count = candidate_window
+            .size
+            .get()
+            .saturating_sub(self.unprocessed_chunks.len())
+            .min(self.spell_only_chunks.len());
+        if fill_count == 0 {
+            return;
+        }
+
+        // 理想的な候補は直前のチャンクの理想的な候補から続けて決める
+        let next_chunk_head_constraint = self
+            .unprocessed_chunks
+            .back()
+            .or(self
+                .inflight_chunk
+                .as_ref()
+                .map(|inflight_chunk| inflight_chunk.as_ref()))
+            .and_then(|chunk| chunk.ideal_key_stroke_candidate().as_ref())
+            .and_then(|candidate| candidate.next_chunk_head_constraint().clone());
+
+        let refresh_start = self.first_unprocessed_chunk_index() + self.unprocessed_chunks.len();
+        let mut chunks: Vec<Chunk> = self.spell_only_chunks.drain(..fill_count).collect();
+        // 候補を残しているクエリの末尾のチャンクはそのまま使う
+        let spell_only_count = chunks
+            .iter()
+            .take_while(|chunk| chunk.key_stroke_candidates().is_none())
+            .count();
+        let (spell_only_chunks, following_chunks) = chunks.split_at_mut(spell_only_count);
+
+        append_key_stroke_to_chunks_followed_by(
+            spell_only_chunks,
+            following_chunks.iter().chain(self.spell_only_chunks.iter()),
+            next_chunk_head_constraint,
+            &candidate_window.input_scheme,
+            candidate_window.romanization_preference.as_ref(),
+        );
+
+        self.unprocessed_chunks.extend(chunks);
+        self.refresh_display_cache_from(refresh_start);
+    }
+
+    // 未処理のチャンク列の末尾のチャンクのキーストローク候補を後続のチャンクを考慮して付与し直す
+    // 既に打ち始めているチャンクの候補は変えられないので未処理のチャンクのみを対象とする
+    pub(crate) fn reassign_last_chunk_key_stroke(
+        &mut self,
+        next_chunk: &Chunk,
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) {
+        // 綴りのみのチャンクがある場合には追加時に末尾のチャンクの候補を取り除く
+        if !self.spell_only_chunks.is_empty() {
+            return;
+        }
+
+        if let Some(last_chunk) = self.unprocessed_chunks.back_mut() {
+            last_chunk.reassign_key_stroke(Some(next_chunk), input_scheme, romanization_preference);
+            self.refresh_display_cache_from(self.chunk_count() - 1);
+        }
+    }
+
+    // 未処理のチャンク列を先頭からcount個に切り詰める
+    // 後続のチャンクがなくなったことで候補が変わる末尾のチャンク列にはキーストローク候補を付与し直す
+    // タイプ中のチャンクまで付与し直す場合には既に打ったキーストロークを保つように候補を絞り込む
+    pub(crate) fn truncate_unprocessed_chunks(
+        &mut self,
+        count: usize,
+        input_scheme: &InputScheme,
+        romanization_preference: Option<&RomanizationPreference>,
+    ) {
+        if count >= self.unprocessed_chunks.len() + self.spell_only_chunks.len() {
+            return;
+        }
+
+        let unprocessed_count = count.min(self.unprocessed_chunks.len());
+        self.spell_only_chunks.truncate(count - unprocessed_count);
+        self.unprocessed_chunks.truncate(unprocessed_count);
+
+        let reassign_count = count_last_chunks_depending_on_following(
+            self.inflight_chunk
+                .iter()
+                .map(|inflight_chunk| inflight_chunk.as_ref())
+                .chain(self.unprocessed_chunks.iter())
+                .chain(self.spell_only_chunks.iter()),
+        );
+
+        // 綴りのみのチャンクは候補を付与する際に後続のチャンクから候補を求めるので末尾のチャンクのみ付与し直せばよい
+        if let Some(last_chunk) = self.spell_only_chunks.back_mut() {
+            last_chunk.reassign_key_stroke(None, input_scheme, romanization_preference);
+        }
+        let reassign_count = reassign_count.saturating_sub(self.spell_only_chunks.len());
+        let unprocessed_reassign_count = reassign_count.min(self.unprocessed_chunks.len());
+
+        // 理想的な候補は付与し直す範囲の直前のチャンクの理想的な候補による制限を引き継ぐ
+        if reassign_count > unprocessed_reassign_count {
+            let next_chunk_head_constraint = self
+                .confirmed_chunks
+                .last()
+                .and_then(|confirmed_chunk| {
+                    confirmed_chunk
+                        .as_ref()
+                        .ideal_key_stroke_candidate()
+                        .as_ref()
+                })
+                .and_then(|candidate| candidate.next_chunk_head_constraint().clone());
+
+            let inflight_chunk = self.inflight_chunk.as_mut().unwrap();
+            let mut reassigned_chunks = [inflight_chunk.as_ref().spell_only()];
+            append_key_stroke_to_chunks_followed_by(
+                &mut reassigned_chunks,
+                self.unprocessed_chunks
+                    .iter()
+                    .chain(self.spell_only_chunks.iter()),
+                next_chunk_head_constraint,
+                input_scheme,
+                romanization_preference,
+            );
+
+            inflight_chunk.restrict_candidates(&reassigned_chunks[0]);
+        }
+
+        if unprocessed_reassign_count > 0 {
+            let start = self.unprocessed_chunks.len() - unprocessed_reassign_count;
+            let next_chunk_head_constraint = start
+                .checked_sub(1)
+                .map(|index| &self.unprocessed_chunks[index])
+                .or(self
+                    .inflight_chunk
+                    .as_ref()
+                    .map(|inflight_chunk| inflight_chunk.as_ref()))
+                .and_then(|chunk| chunk.ideal_key_stroke_candidate().as_ref())
+                .and_then(|candidate| candidate.next_chunk_head_constraint().clone());
+
+            let chunks = &mut self.unprocessed_chunks.make_contiguous()[start..];
+            chunks
+                .iter_mut()
+                .for_each(|chunk| *chunk = chunk.spell_only());
+            append_key_stroke_to_chunks_followed_by(
+                chunks,
+                self.spell_only_chunks.iter(),
+                next_chunk_head_constraint,
+                input_scheme,
+                romanization_preference,
+            );
+        }
+
+        // タイプ中のチャンクの候補も絞り込まれうる
+        self.refresh_display_cache_from(self.confirmed_chunks.len());
+    }
+
+    // タイプ中のチャンク以降を切り離して新たな処理中のチャンク情報とする
+    // タイプ中のチャンクにキーストローク系列がある場合にはチャンクの境界ではないので切り離せない
+    // タイプ中のチャンクは前のチャンクによる先頭の制限を受けた状態で引き継ぐ
+    pub(crate) fn split_off_from_inflight_chunk(&mut self) -> Option<Self> {
+        let inflight_chunk = self.inflight_chunk.as_ref()?;
+        if !inflight_chunk.actual_key_strokes().is_empty()
+            || !inflight_chunk.pending_key_strokes().is_empty()
+        {
+            return None;
+        }
+
+        let mut unprocessed_chunks = std::mem::take(&mut self.unprocessed_chunks);
+        unprocessed_chunks.push_front(self.inflight_chunk.take().unwrap().as_ref().clone());
+
+        let mut rest_processed_chunk_info = Self {
+            unprocessed_chunks,
+            spell_only_chunks: std::mem::take(&mut self.spell_only_chunks),
+            inflight_chunk: None,
+            confirmed_chunks: vec![],
+            candidate_window: self.candidate_window.clone(),
+            display_cache: ChunkDisplayCache::new(),
+        };
+        rest_processed_chunk_info.refresh_display_cache_from(0);
+        self.refresh_display_cache_from(self.confirmed_chunks.len());
+
+        Some(rest_processed_chunk_info)
     }
 
     // 現在打っているチャンクを確定させ未処理のチャンク列の先頭のチャンクの処理を開始する
@@ -54,6 +375,8 @@ impl ProcessedChunkInfo {
 
             let mut current_confirmed_chunk: ConfirmedChunk = current_inflight_chunk.into();
             let next_chunk_head_constraint = current_confirmed_chunk.next_chunk_head_constraint();
+            self.display_cache
+                .push_confirmed_chunk(&current_confirmed_chunk);
             self.confirmed_chunks.push(current_confirmed_chunk);
 
             next_chunk_head_constraint
@@ -71,6 +394,8 @@ impl ProcessedChunkInfo {
 
             self.inflight_chunk.replace(next_inflight_chunk.into());
         }
+
+        self.fill_candidate_window();
     }
 
     // 1タイプのキーストロークを与える
@@ -96,11 +421,19 @@ impl ProcessedChunkInfo {
                 assert!(self.inflight_chunk.is_some());
                 let inflight_chunk = self.inflight_chunk.as_mut().unwrap();
 
+                // ミスタイプだったキーストロークは次のチャンクに対してもミスタイプとして扱う
                 pending_key_strokes.iter().for_each(|actual_key_stroke| {
-                    inflight_chunk.stroke_key(
-                        actual_key_stroke.key_stroke().clone(),
-                        *actual_key_stroke.elapsed_time(),
-                    );
+                    if actual_key_stroke.is_correct() {
+                        inflight_chunk.stroke_key(
+                            actual_key_stroke.key_stroke().clone(),
+                            *actual_key_stroke.elapsed_time(),
+                        );
+                    } else {
+                        inflight_chunk.stroke_wrong_key(
+                            actual_key_stroke.key_stroke().clone(),
+                            *actual_key_stroke.elapsed_time(),
+                        );
+                    }
                 });
 
                 // pendingしていたキーストロークの入力によって次のチャンクが終了する場合に対処する
@@ -118,186 +451,972 @@ impl ProcessedChunkInfo {
         result
     }
 
+    // 候補と照合せずにミスタイプとしてキーストロークを与える
+    pub(crate) fn stroke_wrong_key(&mut self, key_stroke: KeyStrokeChar, elapsed_time: Duration) {
+        assert!(self.inflight_chunk.is_some());
+
+        self.inflight_chunk
+            .as_mut()
+            .unwrap()
+            .stroke_wrong_key(key_stroke, elapsed_time);
+    }
+
+    // 1度でもキーストロークが与えられたか
+    #[cfg(feature = "statistics")]
+    pub(crate) fn has_key_strokes(&self) -> bool {
+        !self.confirmed_chunks.is_empty()
+            || self.inflight_chunk.as_ref().is_some_and(|inflight_chunk| {
+                !inflight_chunk.actual_key_strokes().is_empty()
+                    || !inflight_chunk.pending_key_strokes().is_empty()
+            })
+    }
+
+    // 最後のキーストロークの時間
+    pub(crate) fn last_key_stroke_time(&self) -> Option<Duration> {
+        self.inflight_chunk
+            .iter()
+            .flat_map(|inflight_chunk| inflight_chunk.actual_key_strokes().last())
+            .chain(
+                self.confirmed_chunks
+                    .iter()
+                    .rev()
+                    .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes().last()),
+            )
+            .map(|actual_key_stroke| *actual_key_stroke.elapsed_time())
+            .next()
+    }
+
+    pub(crate) fn inflight_chunk(&self) -> Option<&TypedChunk> {
+        self.inflight_chunk.as_ref()
+    }
+
+    /// キーストロークが次のチャンクのいずれかの候補の先頭のキーストロークかどうか
+    pub(crate) fn is_next_chunk_head_key_stroke(&self, key_stroke: &KeyStrokeChar) -> bool {
+        let key_stroke = char::from(key_stroke.clone());
+
+        self.unprocessed_chunks
+            .front()
+            .and_then(|next_chunk| next_chunk.key_stroke_candidates().as_ref())
+            .is_some_and(|candidates| {
+                candidates
+                    .iter()
+                    .any(|candidate| candidate.whole_key_stroke().starts_with(key_stroke))
+            })
+    }
+
     pub(crate) fn confirmed_chunks(&self) -> &Vec<ConfirmedChunk> {
         &self.confirmed_chunks
     }
 
-    pub(crate) fn construct_display_info(
+    // 指定したチャンク以降で打ち終えた綴りの数
+    // 複数文字を個別で入力している場合にはタイプ中のチャンクでも打ち終えた綴りがある
+    // キーストロークのたびに呼ばれるので確定済みのチャンク全体は走査しない
+    pub(crate) fn finished_spell_count_from(&self, chunk_index: usize) -> usize {
+        let confirmed_spell_count: usize = self.confirmed_chunks[chunk_index..]
+            .iter()
+            .map(|confirmed_chunk| confirmed_chunk.as_ref().spell().count())
+            .sum();
+
+        let inflight_spell_count = self.inflight_chunk.as_ref().map_or(0, |inflight_chunk| {
+            let min_candidate = inflight_chunk.as_ref().min_candidate(None);
+
+            if min_candidate.is_splitted() {
+                let cursor_position = inflight_chunk
+                    .current_key_stroke_cursor_position()
+                    .min(min_candidate.whole_key_stroke().chars().count() - 1);
+
+                min_candidate.element_index_at_key_stroke_index(cursor_position)
+            } else {
+                0
+            }
+        });
+
+        confirmed_spell_count + inflight_spell_count
+    }
+
+    #[cfg(feature = "statistics")]
+    // 打ち終えたラップ数
+    // チャンク以外を対象とするラップは確定したチャンクまでを反映済みの統計にタイプ中のチャンクのキーストロークを加えて求める
+    // キーストロークのたびに呼ぶので統計は複製せずタイプ中のチャンク以外は走査しない
+    pub(crate) fn completed_lap_count(
         &self,
-        lap_request: LapRequest,
-    ) -> (SpellDisplayInfo, KeyStrokeDisplayInfo) {
-        let mut spell = String::new();
-        let mut spell_head_position = 0;
-        let mut spell_cursor_positions;
-        let mut spell_wrong_positions: Vec<usize> = vec![];
-
-        let mut key_stroke = String::new();
-        let mut key_stroke_cursor_position = 0;
-        let mut key_stroke_wrong_positions: Vec<usize> = vec![];
-        let mut on_typing_stat_manager = OnTypingStatisticsManager::new(lap_request);
-
-        // 1. 確定したチャンク
-        // 2. タイプ中のチャンク
-        // 3. 未処理のチャンク
-        //
-        // という順番で表示用の情報を構築する
-
-        // 1. 確定したチャンク
-        self.confirmed_chunks.iter().for_each(|confirmed_chunk| {
-            let mut in_candidate_cursor_position = 0;
-            let mut wrong_spell_element_vector = confirmed_chunk.initialized_spell_element_vector();
-            let mut wrong_key_strokes_vector = confirmed_chunk.initialized_key_strokes_vector();
-            // 複数文字の綴りをまとめて打つ場合には綴りの統計は2文字分カウントする必要がある
-            let spell_count = confirmed_chunk.effective_spell_count();
+        display_string_builder: &DisplayStringBuilder,
+    ) -> usize {
+        let on_typing_stat_manager = &display_string_builder.on_typing_stat_manager;
+
+        match (
+            display_string_builder.lap_request(),
+            self.inflight_chunk.as_ref(),
+        ) {
+            (LapRequest::Chunk(chunks_per_lap), _) => {
+                self.confirmed_chunks.len() / chunks_per_lap.get()
+            }
+            // ラップを終えるたびに集計を追加するのでその数が終えたラップ数である
+            (_, None) => on_typing_stat_manager.lap_summaries().len(),
+            (_, Some(inflight_chunk)) => {
+                let key_strokes = inflight_chunk
+                    .actual_key_strokes()
+                    .iter()
+                    .zip(inflight_chunk.construct_spell_end_vector())
+                    .map(|(actual_key_stroke, spell_end)| {
+                        (
+                            actual_key_stroke.is_correct(),
+                            spell_end,
+                            *actual_key_stroke.elapsed_time(),
+                        )
+                    });
+                // 保留しているキーストロークは次のチャンクのものとみなすので綴りは打ち終えない
+                let pending_key_strokes = self
+                    .pending_spell_count()
+                    .into_iter()
+                    .flat_map(|_| inflight_chunk.pending_key_strokes().iter())
+                    .map(|actual_key_stroke| {
+                        (
+                            actual_key_stroke.is_correct(),
+                            None,
+                            *actual_key_stroke.elapsed_time(),
+                        )
+                    });
+
+                on_typing_stat_manager.completed_lap_count_with_key_strokes(
+                    inflight_chunk
+                        .as_ref()
+                        .min_candidate(None)
+                        .whole_key_stroke()
+                        .chars()
+                        .count(),
+                    inflight_chunk
+                        .as_ref()
+                        .ideal_key_stroke_candidate()
+                        .as_ref()
+                        .unwrap()
+                        .whole_key_stroke()
+                        .chars()
+                        .count(),
+                    key_strokes.chain(pending_key_strokes),
+                )
+            }
+        }
+    }
 
-            on_typing_stat_manager.set_this_candidate_key_stroke_count(
+    pub(crate) fn construct_progress_display_info(&self) -> ProgressDisplayInfo {
+        let finished_chunk_count = self.confirmed_chunks.len();
+        let whole_chunk_count = finished_chunk_count
+            + self.inflight_chunk.iter().count()
+            + self.unprocessed_chunks.len()
+            + self.spell_only_chunks.len();
+
+        // 終了している場合にはタイプ中のチャンクの進捗は0とする
+        let (in_chunk_key_stroke_cursor_position, in_chunk_key_stroke_count) =
+            match self.inflight_chunk.as_ref() {
+                Some(inflight_chunk) => (
+                    inflight_chunk.current_key_stroke_cursor_position(),
+                    inflight_chunk
+                        .as_ref()
+                        .min_candidate(None)
+                        .whole_key_stroke()
+                        .chars()
+                        .count(),
+                ),
+                None => (0, 0),
+            };
+
+        ProgressDisplayInfo::new(
+            finished_chunk_count,
+            whole_chunk_count,
+            in_chunk_key_stroke_cursor_position,
+            in_chunk_key_stroke_count,
+        )
+    }
+
+    // elapsed_time時点での直近のキーストロークの速度を構築する
+    // 全てのキーストロークを走査しないように新しいものから遡って必要な分だけ見る
+    pub(crate) fn construct_speed_display_info(&self, elapsed_time: Duration) -> SpeedDisplayInfo {
+        let rolling_duration = elapsed_time.min(SpeedDisplayInfo::ROLLING_WINDOW);
+        let rolling_window_start = elapsed_time - rolling_duration;
+
+        let mut rolling_key_stroke_count = 0;
+        let mut recent_key_stroke_times: Vec<Duration> = vec![];
+
+        let correct_key_stroke_times = self
+            .inflight_chunk
+            .iter()
+            .flat_map(|inflight_chunk| inflight_chunk.actual_key_strokes().iter().rev())
+            .chain(
+                self.confirmed_chunks
+                    .iter()
+                    .rev()
+                    .flat_map(|confirmed_chunk| confirmed_chunk.actual_key_strokes().iter().rev()),
+            )
+            .filter(|actual_key_stroke| actual_key_stroke.is_correct())
+            .map(|actual_key_stroke| *actual_key_stroke.elapsed_time())
+            // 明示的に与えた時間より後のキーストロークは無視する
+            .filter(|key_stroke_time| *key_stroke_time <= elapsed_time);
+
+        for key_stroke_time in correct_key_stroke_times {
+            let is_in_rolling_window = key_stroke_time > rolling_window_start;
+            let is_recent =
+                recent_key_stroke_times.len() < SpeedDisplayInfo::INSTANTANEOUS_KEY_STROKE_COUNT;
+
+            if !is_in_rolling_window && !is_recent {
+                break;
+            }
+
+            if is_in_rolling_window {
+                rolling_key_stroke_count += 1;
+            }
+
+            if is_recent {
+                recent_key_stroke_times.push(key_stroke_time);
+            }
+        }
+
+        // 新しいものから遡っているので先頭が最新で末尾が最古
+        let recent_key_stroke_span = match (
+            recent_key_stroke_times.first(),
+            recent_key_stroke_times.last(),
+        ) {
+            (Some(latest), Some(oldest)) => latest.saturating_sub(*oldest),
+            _ => Duration::ZERO,
+        };
+
+        SpeedDisplayInfo::new(
+            rolling_duration,
+            rolling_key_stroke_count,
+            recent_key_stroke_times.len(),
+            recent_key_stroke_span,
+        )
+    }
+
+    // 表示するキーストロークのうちカーソル位置以降の各キーストロークについて
+    // そのキーストロークを含む生き残っている候補の数を構築する
+    // 未処理のチャンクは保持している最小の候補の連鎖から写す
+    pub(crate) fn construct_upcoming_candidate_counts(&self) -> Vec<usize> {
+        let mut candidate_counts = vec![];
+
+        // タイプ中のチャンクの候補は既に打ったキーストロークで削減されている
+        if let Some(inflight_chunk) = self.inflight_chunk.as_ref() {
+            candidate_counts.extend(
+                inflight_chunk
+                    .as_ref()
+                    .construct_candidate_counts_of_min_candidate(None)
+                    .into_iter()
+                    .skip(inflight_chunk.current_key_stroke_cursor_position()),
+            );
+        }
+
+        let diverged_head_constraints = self.diverged_unprocessed_head_constraints();
+        self.unprocessed_chunks
+            .iter()
+            .zip(diverged_head_constraints.iter())
+            .for_each(|(unprocessed_chunk, head_constraint)| {
+                candidate_counts.extend(
+                    unprocessed_chunk
+                        .construct_candidate_counts_of_min_candidate(head_constraint.clone()),
+                );
+            });
+
+        let (_, chain_candidate_counts) = self.display_cache.min_candidate_key_stroke_from(
+            self.first_unprocessed_chunk_index() + diverged_head_constraints.len(),
+        );
+        candidate_counts.extend_from_slice(chain_candidate_counts);
+
+        candidate_counts
+    }
+
+    // 表示用の綴りとキーストロークの文字列における各チャンクの先頭の位置を構築する
+    // 末尾には文字列全体の長さを入れておく
+    pub(crate) fn construct_chunk_head_positions(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut spell_heads = vec![0];
+        let mut key_stroke_heads = vec![0];
+
+        let mut push_chunk = |spell_count: usize, key_stroke_count: usize| {
+            spell_heads.push(spell_heads.last().unwrap() + spell_count);
+            key_stroke_heads.push(key_stroke_heads.last().unwrap() + key_stroke_count);
+        };
+
+        self.confirmed_chunks.iter().for_each(|confirmed_chunk| {
+            push_chunk(
+                confirmed_chunk.as_ref().spell().as_ref().chars().count(),
                 confirmed_chunk
                     .confirmed_candidate()
                     .whole_key_stroke()
                     .chars()
                     .count(),
-                confirmed_chunk
-                    .as_ref()
-                    .ideal_key_stroke_candidate()
-                    .as_ref()
-                    .unwrap()
-                    .whole_key_stroke()
-                    .chars()
-                    .count(),
             );
+        });
 
-            // まず実際のキーストローク系列から統計情報を更新しチャンク内ミス位置を構築する
+        let next_chunk_head_constraint = match self.inflight_chunk.as_ref() {
+            Some(inflight_chunk) => {
+                let candidate = inflight_chunk.as_ref().min_candidate(None);
 
-            confirmed_chunk
-                .actual_key_strokes()
-                .iter()
-                .zip(confirmed_chunk.construct_spell_end_vector().iter())
-                .for_each(|(actual_key_stroke, spell_end)| {
-                    on_typing_stat_manager.on_actual_key_stroke(
-                        actual_key_stroke.is_correct(),
-                        spell_count,
-                        *actual_key_stroke.elapsed_time(),
-                    );
+                push_chunk(
+                    inflight_chunk.as_ref().spell().as_ref().chars().count(),
+                    candidate.whole_key_stroke().chars().count(),
+                );
 
-                    if actual_key_stroke.is_correct() {
-                        in_candidate_cursor_position += 1;
+                candidate.next_chunk_head_constraint().clone()
+            }
+            None => None,
+        };
 
-                        if let Some(delta) = spell_end {
-                            on_typing_stat_manager.finish_spell(*delta);
-                        }
-                    } else {
-                        wrong_key_strokes_vector[in_candidate_cursor_position] = true;
+        self.unprocessed_chunks.iter().fold(
+            next_chunk_head_constraint,
+            |next_chunk_head_constraint, unprocessed_chunk| {
+                let candidate = unprocessed_chunk.min_candidate(next_chunk_head_constraint);
 
-                        wrong_spell_element_vector[confirmed_chunk
-                            .confirmed_candidate()
-                            .element_index_at_key_stroke_index(in_candidate_cursor_position)] =
-                            true;
-                    }
-                });
+                push_chunk(
+                    unprocessed_chunk.spell().as_ref().chars().count(),
+                    candidate.whole_key_stroke().chars().count(),
+                );
 
-            // 次に構築したチャンク内ミス位置からキーストロークと綴りのそれぞれのカーソル位置とミス位置を更新する
+                candidate.next_chunk_head_constraint().clone()
+            },
+        );
 
-            wrong_key_strokes_vector
-                .iter()
-                .enumerate()
-                .for_each(|(i, is_wrong)| {
-                    if *is_wrong {
-                        key_stroke_wrong_positions.push(key_stroke_cursor_position + i);
-                    }
-                });
-            key_stroke_cursor_position += in_candidate_cursor_position;
+        // 綴りのみのチャンクのキーストロークは表示しない
+        self.spell_only_chunks.iter().for_each(|spell_only_chunk| {
+            push_chunk(spell_only_chunk.spell().as_ref().chars().count(), 0);
+        });
+
+        (spell_heads, key_stroke_heads)
+    }
+
+    /// 表示用のキーストロークの文字列における各チャンクの先頭の位置を求める
+    /// 綴りのみのチャンクのキーストロークは表示しないのでその先頭は文字列の末尾とする
+    pub(crate) fn key_stroke_heads_of(&self, chunk_indices: &[usize]) -> Vec<usize> {
+        let confirmed_chunk_count = self.confirmed_chunks.len();
+        let first_unprocessed_chunk_index = self.first_unprocessed_chunk_index();
+
+        // 最小の候補の連鎖と異なる未処理のチャンクまでの各チャンクの先頭の位置
+        let mut diverged_heads = vec![
+            self.display_cache.confirmed_key_stroke().len()
+                + self.inflight_chunk.as_ref().map_or(0, |inflight_chunk| {
+                    inflight_chunk
+                        .as_ref()
+                        .min_candidate(None)
+                        .whole_key_stroke()
+                        .chars()
+                        .count()
+                }),
+        ];
+        self.unprocessed_chunks
+            .iter()
+            .zip(self.diverged_unprocessed_head_constraints())
+            .for_each(|(unprocessed_chunk, head_constraint)| {
+                diverged_heads.push(
+                    diverged_heads.last().unwrap()
+                        + unprocessed_chunk
+                            .min_candidate(head_constraint)
+                            .whole_key_stroke()
+                            .chars()
+                            .count(),
+                );
+            });
+        let chain_head_chunk_index = first_unprocessed_chunk_index + diverged_heads.len() - 1;
+        let chain_head = self
+            .display_cache
+            .min_candidate_key_stroke_head(chain_head_chunk_index);
+
+        let end_chunk_index = first_unprocessed_chunk_index + self.unprocessed_chunks.len();
+
+        chunk_indices
+            .iter()
+            .map(|chunk_index| {
+                let chunk_index = (*chunk_index).min(end_chunk_index);
+
+                if chunk_index <= confirmed_chunk_count {
+                    self.display_cache.confirmed_key_stroke_head(chunk_index)
+                } else if chunk_index <= chain_head_chunk_index {
+                    diverged_heads[chunk_index - first_unprocessed_chunk_index]
+                } else {
+                    diverged_heads.last().unwrap()
+                        + self
+                            .display_cache
+                            .min_candidate_key_stroke_head(chunk_index)
+                        - chain_head
+                }
+            })
+            .collect()
+    }
+
+    /// 表示用のキーストロークの文字列のうち`start`番目から`end`番目の手前までのチャンクの部分を書き込む
+    /// 綴りのみのチャンクのキーストロークは表示しないので書き込まない
+    pub(crate) fn write_key_stroke_between(
+        &self,
+        start: usize,
+        end: usize,
+        key_stroke: &mut String,
+    ) {
+        let confirmed_chunk_count = self.confirmed_chunks.len();
+        let first_unprocessed_chunk_index = self.first_unprocessed_chunk_index();
+        let end = end.min(first_unprocessed_chunk_index + self.unprocessed_chunks.len());
+        if start >= end {
+            return;
+        }
+
+        if start < confirmed_chunk_count {
+            key_stroke.push_str(
+                &self.display_cache.confirmed_key_stroke()[self
+                    .display_cache
+                    .confirmed_key_stroke_head(start)
+                    ..self
+                        .display_cache
+                        .confirmed_key_stroke_head(end.min(confirmed_chunk_count))],
+            );
+        }
+
+        if let Some(inflight_chunk) = self.inflight_chunk.as_ref() {
+            if start <= confirmed_chunk_count && confirmed_chunk_count < end {
+                key_stroke.push_str(
+                    &inflight_chunk
+                        .as_ref()
+                        .min_candidate(None)
+                        .whole_key_stroke(),
+                );
+            }
+        }
+
+        let diverged_head_constraints = self.diverged_unprocessed_head_constraints();
+        self.unprocessed_chunks
+            .iter()
+            .zip(diverged_head_constraints.iter())
+            .enumerate()
+            .map(|(i, chunk_and_head_constraint)| {
+                (first_unprocessed_chunk_index + i, chunk_and_head_constraint)
+            })
+            .filter(|(chunk_index, _)| start <= *chunk_index && *chunk_index < end)
+            .for_each(|(_, (unprocessed_chunk, head_constraint))| {
+                key_stroke.push_str(
+                    &unprocessed_chunk
+                        .min_candidate(head_constraint.clone())
+                        .whole_key_stroke(),
+                );
+            });
+
+        let chain_start =
+            start.max(first_unprocessed_chunk_index + diverged_head_constraints.len());
+        if chain_start < end {
+            let (chain_key_stroke, _) = self
+                .display_cache
+                .min_candidate_key_stroke_from(chain_start);
+            key_stroke.push_str(
+                &chain_key_stroke[..self.display_cache.min_candidate_key_stroke_head(end)
+                    - self
+                        .display_cache
+                        .min_candidate_key_stroke_head(chain_start)],
+            );
+        }
+    }
+
+    /// 表示用のキーストロークの文字列全体の長さ
+    pub(crate) fn key_stroke_count(&self) -> usize {
+        self.key_stroke_heads_of(&[self.chunk_count()])[0]
+    }
+
+    /// 未処理のチャンクのうち保持している最小の候補の連鎖と同じ候補で表示する先頭のチャンクのインデックス
+    /// これ以降のチャンクの表示は保持しているものが変わらない限り変化しない
+    pub(crate) fn first_chain_chunk_index(&self) -> usize {
+        self.first_unprocessed_chunk_index() + self.diverged_unprocessed_head_constraints().len()
+    }
+
+    /// 連鎖での`start`番目から`end`番目の手前までのチャンクのキーストローク数
+    /// どちらのチャンクも連鎖に含まれている必要がある
+    pub(crate) fn chained_key_stroke_count_between(&self, start: usize, end: usize) -> usize {
+        self.display_cache.min_candidate_key_stroke_head(end)
+            - self.display_cache.min_candidate_key_stroke_head(start)
+    }
 
-            confirmed_chunk
+    /// 前回取り出してから表示が変わりうるチャンクのうち最も前のもののインデックスを取り出す
+    /// これより前のチャンクは前回取り出した時点から表示が変わっていない
+    pub(crate) fn take_refreshed_chunk_index(&mut self) -> Option<usize> {
+        self.display_cache.take_refreshed_chunk_index()
+    }
+
+    /// 理想的なキーストロークにおける`chunk_index`番目のチャンクの先頭の位置とそれ以降の部分
+    /// 候補を持たないチャンクを指定した場合には末尾を指す
+    pub(crate) fn ideal_key_stroke_from(&self, chunk_index: usize) -> (usize, &str) {
+        let ideal_key_stroke = self.display_cache.ideal_key_stroke();
+        let head = self.display_cache.ideal_key_stroke_head(
+            chunk_index.min(self.first_unprocessed_chunk_index() + self.unprocessed_chunks.len()),
+        );
+
+        (head, &ideal_key_stroke[head..])
+    }
+
+    /// 表示用の綴りの文字列における各チャンクの先頭の位置を求める
+    pub(crate) fn spell_heads_of(&self, chunk_indices: &[usize]) -> Vec<usize> {
+        chunk_indices
+            .iter()
+            .map(|chunk_index| self.display_cache.spell_head(*chunk_index))
+            .collect()
+    }
+
+    /// クエリ全体の綴りの文字数
+    pub(crate) fn spell_count(&self) -> usize {
+        self.display_cache.spell_count()
+    }
+
+    // 未処理のチャンク列の先頭のチャンクのインデックス
+    fn first_unprocessed_chunk_index(&self) -> usize {
+        self.confirmed_chunks.len() + self.inflight_chunk.iter().count()
+    }
+
+    // タイプ中のチャンクの最小の候補による次のチャンクの先頭への制限
+    fn inflight_next_chunk_head_constraint(&self) -> Option<KeyStrokeChar> {
+        self.inflight_chunk.as_ref().and_then(|inflight_chunk| {
+            inflight_chunk
                 .as_ref()
-                .spell()
+                .min_candidate(None)
+                .next_chunk_head_constraint()
+                .clone()
+        })
+    }
+
+    // 未処理のチャンクのうち保持している最小の候補の連鎖と先頭への制限が異なる先頭のチャンク列のそれぞれの先頭への制限
+    // 連鎖と異なるのはタイプ中のチャンクの最小の候補が変わった直後の数チャンクのみで
+    // それ以降のチャンクは連鎖と同じ候補になるので保持しているものを使える
+    fn diverged_unprocessed_head_constraints(&self) -> Vec<Option<KeyStrokeChar>> {
+        let first_unprocessed_chunk_index = self.first_unprocessed_chunk_index();
+        let mut head_constraint = self.inflight_next_chunk_head_constraint();
+        let mut diverged_head_constraints = vec![];
+
+        for (i, unprocessed_chunk) in self.unprocessed_chunks.iter().enumerate() {
+            if self
+                .display_cache
+                .min_candidate_head_constraint(first_unprocessed_chunk_index + i)
+                == Some(&head_constraint)
+            {
+                break;
+            }
+
+            let next_chunk_head_constraint = unprocessed_chunk
+                .min_candidate(head_constraint.clone())
+                .next_chunk_head_constraint()
+                .clone();
+            diverged_head_constraints.push(head_constraint);
+            head_constraint = next_chunk_head_constraint;
+        }
+
+        diverged_head_constraints
+    }
+
+    /// 理想的なキーストローク系列での現在のカーソル位置とその全体の長さ
+    /// 保持しているチャンクごとの理想的なキーストロークの位置から求める
+    pub(crate) fn ideal_key_stroke_progress(&self) -> (usize, usize) {
+        let cursor_position = self
+            .display_cache
+            .ideal_key_stroke_head(self.confirmed_chunks.len())
+            + self.inflight_chunk.as_ref().map_or(0, |inflight_chunk| {
+                self.inflight_ideal_key_stroke_cursor_position(inflight_chunk)
+            });
+
+        (cursor_position, self.display_cache.ideal_key_stroke().len())
+    }
+
+    // タイプ中のチャンク内のカーソル位置を理想的なキーストロークでの位置に変換する
+    fn inflight_ideal_key_stroke_cursor_position(&self, inflight_chunk: &TypedChunk) -> usize {
+        let in_chunk_cursor_position = inflight_chunk.current_key_stroke_cursor_position();
+        if in_chunk_cursor_position == 0 {
+            return 0;
+        }
+
+        let chunk = inflight_chunk.as_ref();
+
+        MultiTargetDeltaConverter::new(
+            chunk.spell().count(),
+            chunk
+                .ideal_key_stroke_candidate()
                 .as_ref()
-                .chars()
-                .enumerate()
-                .for_each(|(i, _)| {
-                    // 複数文字チャンクを個別に入力した場合はそれぞれの綴りについて
-                    // それ以外ではチャンク全体の綴りについて
-                    // タイプミス判定をする
-                    let element_index = if wrong_spell_element_vector.len() == 1 {
-                        0
-                    } else {
-                        i
-                    };
+                .unwrap()
+                .construct_key_stroke_element_count(),
+            chunk
+                .min_candidate(None)
+                .construct_key_stroke_element_count(),
+            BaseTarget::KeyStroke,
+        )
+        .ideal_key_stroke_delta(&[in_chunk_cursor_position])[0]
+    }
 
-                    if wrong_spell_element_vector[element_index] {
-                        spell_wrong_positions.push(spell_head_position);
-                    }
+    /// 理想的なキーストロークの文字列を書き込みその中での現在のカーソル位置を返す
+    /// 確定したチャンクは実際に打ったキーストロークに関わらず理想的なキーストロークで表す
+    /// 既存の文字列の領域を再利用できるように書き込む先を受け取る
+    pub(crate) fn write_ideal_key_stroke(&self, ideal_key_stroke: &mut String) -> usize {
+        ideal_key_stroke.clear();
+        ideal_key_stroke.push_str(self.display_cache.ideal_key_stroke());
 
-                    spell_head_position += 1;
-                });
+        self.ideal_key_stroke_progress().0
+    }
 
-            // 最後にチャンクの統計情報と表示用の文字列を更新する
-            key_stroke.push_str(&confirmed_chunk.confirmed_candidate().whole_key_stroke());
-            spell.push_str(confirmed_chunk.as_ref().spell().as_ref());
+    /// 全ての確定したチャンクを走査して表示用の情報を構築する
+    pub(crate) fn construct_display_info(
+        &self,
+        lap_request: LapRequest,
+        vocabulary_end_chunk_counts: &[usize],
+    ) -> (SpellDisplayInfo, KeyStrokeDisplayInfo) {
+        let mut display_string_builder = DisplayStringBuilder::new(lap_request);
+        self.apply_confirmed_chunks(&mut display_string_builder, vocabulary_end_chunk_counts);
 
-            on_typing_stat_manager.finish_chunk(
-                confirmed_chunk
+        let (spell_display_info, key_stroke_display_info, _) = self
+            .construct_display_info_with_builder(
+                &display_string_builder,
+                vocabulary_end_chunk_counts,
+            );
+
+        (spell_display_info, key_stroke_display_info)
+    }
+
+    /// 構築途中の表示用の情報にまだ反映していない確定したチャンクを反映する
+    /// 確定したチャンクは変化しないので反映済みのチャンクを再度走査する必要はない
+    pub(crate) fn apply_confirmed_chunks(
+        &self,
+        display_string_builder: &mut DisplayStringBuilder,
+        vocabulary_end_chunk_counts: &[usize],
+    ) {
+        self.confirmed_chunks[display_string_builder.applied_chunk_count()..]
+            .iter()
+            .for_each(|confirmed_chunk| {
+                display_string_builder
+                    .apply_confirmed_chunk(confirmed_chunk, vocabulary_end_chunk_counts)
+            });
+    }
+
+    /// 確定したチャンクを反映した表示用の情報にタイプ中のチャンクと未処理のチャンクを加えて表示用の情報を構築する
+    /// 合わせて終えたラップごとの集計と現在のコンボ数と最大コンボ数を返す
+    ///
+    /// 未処理のチャンクを全て走査するので検証や一度きりの構築に用いる
+    /// キーストロークのたびに構築する場合には[`snapshot_display_info`](Self::snapshot_display_info())を用いる
+    #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+    pub(crate) fn construct_display_info_with_builder(
+        &self,
+        display_string_builder: &DisplayStringBuilder,
+        vocabulary_end_chunk_counts: &[usize],
+    ) -> (SpellDisplayInfo, KeyStrokeDisplayInfo, DisplayStatistics) {
+        #[cfg(feature = "statistics")]
+        let mut on_typing_stat_manager = display_string_builder.on_typing_stat_manager.clone();
+        #[cfg(feature = "statistics")]
+        self.apply_inflight_chunk_statistics(
+            &mut on_typing_stat_manager,
+            vocabulary_end_chunk_counts,
+        );
+
+        let mut key_stroke: String = self
+            .confirmed_chunks
+            .iter()
+            .map(|confirmed_chunk| {
+                String::from(confirmed_chunk.confirmed_candidate().whole_key_stroke())
+            })
+            .collect();
+        if let Some(inflight_chunk) = self.inflight_chunk.as_ref() {
+            key_stroke.push_str(
+                &inflight_chunk
                     .as_ref()
                     .min_candidate(None)
-                    .construct_key_stroke_element_count(),
-                confirmed_chunk
-                    .as_ref()
-                    .ideal_key_stroke_candidate()
-                    .as_ref()
-                    .unwrap()
-                    .construct_key_stroke_element_count(),
-                confirmed_chunk.as_ref().spell().count(),
+                    .whole_key_stroke(),
             );
-        });
+        }
 
-        // 2. タイプ中のチャンク
+        // 未処理のチャンクは直前のチャンクの最小の候補による制限を引き継ぎながら最小の候補で表示する
+        // 統計情報は理想的なキーストロークで打つものとしてカウントする
+        self.unprocessed_chunks.iter().fold(
+            self.inflight_next_chunk_head_constraint(),
+            |next_chunk_head_constraint, unprocessed_chunk| {
+                let candidate = unprocessed_chunk.min_candidate(next_chunk_head_constraint);
+                key_stroke.push_str(&candidate.whole_key_stroke());
 
-        if self.inflight_chunk.is_none() {
-            spell_cursor_positions = vec![spell_head_position];
-            assert!(self.is_finished());
-        } else {
-            let inflight_chunk = self.inflight_chunk.as_ref().unwrap();
+                #[cfg(feature = "statistics")]
+                {
+                    let key_stroke_element_count = unprocessed_chunk
+                        .ideal_key_stroke_candidate()
+                        .as_ref()
+                        .unwrap()
+                        .construct_key_stroke_element_count();
+                    on_typing_stat_manager.add_unfinished_chunk(
+                        key_stroke_element_count.clone(),
+                        key_stroke_element_count,
+                        unprocessed_chunk.spell().count(),
+                        vocabulary_end_chunk_counts,
+                    );
+                }
 
-            let spell_count = inflight_chunk.effective_spell_count();
-            let mut wrong_spell_element_vector = inflight_chunk.initialized_spell_element_vector();
-            let mut wrong_key_strokes_vector = inflight_chunk.initialized_key_strokes_vector();
-            let mut in_candidate_cursor_position = 0;
+                candidate.next_chunk_head_constraint().clone()
+            },
+        );
+
+        // 綴りのみのチャンクはキーストロークが決まっていないので綴りのみを表示し統計情報の対象にもしない
+        self.emit_display_info(
+            display_string_builder,
+            self.construct_query_spell(),
+            key_stroke,
+            #[cfg(feature = "statistics")]
+            on_typing_stat_manager,
+        )
+    }
 
-            on_typing_stat_manager.set_this_candidate_key_stroke_count(
-                inflight_chunk
+    /// 確定したチャンクを反映した表示用の情報にタイプ中のチャンクのみを加えて表示用の情報を構築する
+    /// 合わせて終えたラップごとの集計と現在のコンボ数と最大コンボ数を返す
+    ///
+    /// 未処理のチャンクは保持しているキーストロークとチャンクごとの位置から求めるのでタイプ中のチャンク以外は走査しない
+    /// 綴りはクエリが変わるまで変化しないので与えられたものをそのまま用い
+    /// キーストロークは与えられた文字列の領域に書き込む
+    #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+    pub(crate) fn snapshot_display_info(
+        &self,
+        display_string_builder: &DisplayStringBuilder,
+        vocabulary_end_chunk_counts: &[usize],
+        spell: String,
+        mut key_stroke: String,
+    ) -> (SpellDisplayInfo, KeyStrokeDisplayInfo, DisplayStatistics) {
+        assert_eq!(spell.chars().count(), self.spell_count());
+
+        #[cfg(feature = "statistics")]
+        let on_typing_stat_manager =
+            self.snapshot_on_typing_statistics(display_string_builder, vocabulary_end_chunk_counts);
+        let first_unprocessed_chunk_index = self.first_unprocessed_chunk_index();
+
+        key_stroke.clear();
+        key_stroke.push_str(self.display_cache.confirmed_key_stroke());
+        if let Some(inflight_chunk) = self.inflight_chunk.as_ref() {
+            key_stroke.push_str(
+                &inflight_chunk
                     .as_ref()
                     .min_candidate(None)
-                    .whole_key_stroke()
-                    .chars()
-                    .count(),
-                inflight_chunk
-                    .as_ref()
+                    .whole_key_stroke(),
+            );
+        }
+
+        let diverged_head_constraints = self.diverged_unprocessed_head_constraints();
+        self.unprocessed_chunks
+            .iter()
+            .zip(diverged_head_constraints.iter())
+            .for_each(|(unprocessed_chunk, head_constraint)| {
+                key_stroke.push_str(
+                    &unprocessed_chunk
+                        .min_candidate(head_constraint.clone())
+                        .whole_key_stroke(),
+                );
+            });
+        let (chain_key_stroke, _) = self.display_cache.min_candidate_key_stroke_from(
+            first_unprocessed_chunk_index + diverged_head_constraints.len(),
+        );
+        key_stroke.push_str(chain_key_stroke);
+
+        self.emit_display_info(
+            display_string_builder,
+            spell,
+            key_stroke,
+            #[cfg(feature = "statistics")]
+            on_typing_stat_manager,
+        )
+    }
+
+    /// 確定したチャンクを反映した表示用の情報にタイプ中のチャンクのみを加えて表示用の文字列以外を構築する
+    /// 構築した表示用の情報の綴りとキーストロークは空である
+    ///
+    /// 差分で表示用の情報を構築するように文字列を別に求める場合に用いる
+    #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+    pub(crate) fn snapshot_display_info_without_strings(
+        &self,
+        display_string_builder: &DisplayStringBuilder,
+        vocabulary_end_chunk_counts: &[usize],
+    ) -> (SpellDisplayInfo, KeyStrokeDisplayInfo, DisplayStatistics) {
+        #[cfg(feature = "statistics")]
+        let on_typing_stat_manager =
+            self.snapshot_on_typing_statistics(display_string_builder, vocabulary_end_chunk_counts);
+
+        self.emit_display_info(
+            display_string_builder,
+            String::new(),
+            String::new(),
+            #[cfg(feature = "statistics")]
+            on_typing_stat_manager,
+        )
+    }
+
+    /// 確定したチャンクを反映した表示用の情報にタイプ中のチャンクのみを加えてラップの情報を構築する
+    /// 綴り・キーストローク・理想的なキーストロークのそれぞれの統計情報と終えたラップごとの集計を返す
+    ///
+    /// 2つ目以降のラップ要求のように表示用の文字列が必要ない場合に用いる
+    #[cfg(feature = "statistics")]
+    pub(crate) fn snapshot_lap_statistics(
+        &self,
+        display_string_builder: &DisplayStringBuilder,
+        vocabulary_end_chunk_counts: &[usize],
+    ) -> (
+        OnTypingStatisticsTarget,
+        OnTypingStatisticsTarget,
+        OnTypingStatisticsTarget,
+        Vec<LapSummary>,
+    ) {
+        let on_typing_stat_manager =
+            self.snapshot_on_typing_statistics(display_string_builder, vocabulary_end_chunk_counts);
+        let lap_summaries = on_typing_stat_manager.lap_summaries().to_vec();
+        let (
+            key_stroke_on_typing_statistics,
+            ideal_key_stroke_on_typing_statistics,
+            spell_on_typing_statistics,
+            _,
+        ) = on_typing_stat_manager.emit();
+
+        (
+            spell_on_typing_statistics,
+            key_stroke_on_typing_statistics,
+            ideal_key_stroke_on_typing_statistics,
+            lap_summaries,
+        )
+    }
+
+    // 確定したチャンクまでを反映した統計情報にタイプ中のチャンクと未処理のチャンクを加える
+    // 未処理のチャンクは理想的なキーストロークで打つものとしてチャンクごとの位置からまとめて加える
+    #[cfg(feature = "statistics")]
+    fn snapshot_on_typing_statistics(
+        &self,
+        display_string_builder: &DisplayStringBuilder,
+        vocabulary_end_chunk_counts: &[usize],
+    ) -> OnTypingStatisticsManager {
+        let mut on_typing_stat_manager = display_string_builder.on_typing_stat_manager.clone();
+        self.apply_inflight_chunk_statistics(
+            &mut on_typing_stat_manager,
+            vocabulary_end_chunk_counts,
+        );
+
+        let first_unprocessed_chunk_index = self.first_unprocessed_chunk_index();
+        let end_chunk_index = first_unprocessed_chunk_index + self.unprocessed_chunks.len();
+        on_typing_stat_manager.add_unfinished_chunks(
+            self.display_cache
+                .ideal_key_stroke_heads_between(first_unprocessed_chunk_index, end_chunk_index),
+            self.display_cache
+                .spell_heads_between(first_unprocessed_chunk_index, end_chunk_index),
+            |i| {
+                self.unprocessed_chunks[i]
                     .ideal_key_stroke_candidate()
                     .as_ref()
                     .unwrap()
-                    .whole_key_stroke()
-                    .chars()
-                    .count(),
-            );
+                    .construct_key_stroke_element_count()
+            },
+            vocabulary_end_chunk_counts,
+        );
 
-            // まず実際のキーストローク系列から統計情報を更新しチャンク内ミス位置を構築する
+        on_typing_stat_manager
+    }
 
+    // 確定したチャンクまでを反映した統計情報にタイプ中のチャンクを加える
+    // 遅延確定候補で保留しているキーストロークは次のチャンクのものとして加える
+    #[cfg(feature = "statistics")]
+    fn apply_inflight_chunk_statistics(
+        &self,
+        on_typing_stat_manager: &mut OnTypingStatisticsManager,
+        vocabulary_end_chunk_counts: &[usize],
+    ) {
+        let Some(inflight_chunk) = self.inflight_chunk.as_ref() else {
+            return;
+        };
+
+        let spell_count = inflight_chunk.effective_spell_count();
+        let min_candidate = inflight_chunk.as_ref().min_candidate(None);
+
+        on_typing_stat_manager.set_this_candidate_key_stroke_count(
+            min_candidate.whole_key_stroke().chars().count(),
             inflight_chunk
-                .actual_key_strokes()
+                .as_ref()
+                .ideal_key_stroke_candidate()
+                .as_ref()
+                .unwrap()
+                .whole_key_stroke()
+                .chars()
+                .count(),
+        );
+
+        inflight_chunk
+            .actual_key_strokes()
+            .iter()
+            .zip(inflight_chunk.construct_spell_end_vector().iter())
+            .for_each(|(actual_key_stroke, spell_end)| {
+                on_typing_stat_manager.on_actual_key_stroke(
+                    actual_key_stroke.is_correct(),
+                    spell_count,
+                    *actual_key_stroke.elapsed_time(),
+                );
+
+                if actual_key_stroke.is_correct() {
+                    if let Some(delta) = spell_end {
+                        on_typing_stat_manager.finish_spell(*delta);
+                    }
+                }
+            });
+
+        on_typing_stat_manager.add_unfinished_chunk(
+            min_candidate.construct_key_stroke_element_count(),
+            inflight_chunk
+                .as_ref()
+                .ideal_key_stroke_candidate()
+                .as_ref()
+                .unwrap()
+                .construct_key_stroke_element_count(),
+            inflight_chunk.as_ref().spell().count(),
+            vocabulary_end_chunk_counts,
+        );
+
+        if let Some(pending_spell_count) = self.pending_spell_count() {
+            inflight_chunk
+                .pending_key_strokes()
                 .iter()
-                .zip(inflight_chunk.construct_spell_end_vector().iter())
-                .for_each(|(actual_key_stroke, spell_end)| {
+                .for_each(|actual_key_stroke| {
                     on_typing_stat_manager.on_actual_key_stroke(
                         actual_key_stroke.is_correct(),
-                        spell_count,
+                        pending_spell_count,
                         *actual_key_stroke.elapsed_time(),
                     );
+                });
+        }
+    }
+
+    // タイプ中のチャンクが遅延確定候補を打ち終えている場合に保留しているキーストロークを次のチャンクのものとみなすときの綴りの数
+    // 次のチャンクを複数文字の綴りを個別に入力する候補で打つ場合には2となる
+    fn pending_spell_count(&self) -> Option<usize> {
+        let inflight_chunk = self.inflight_chunk.as_ref()?;
+        let next_chunk = self.unprocessed_chunks.front()?;
+
+        inflight_chunk.is_delayed_confirmable().then(|| {
+            if next_chunk
+                .min_candidate(self.inflight_next_chunk_head_constraint())
+                .is_splitted()
+            {
+                2
+            } else {
+                next_chunk.spell().count()
+            }
+        })
+    }
+
+    // 確定したチャンクまでを反映した表示用の情報にタイプ中のチャンクを加えたカーソル位置とミス位置から表示用の情報を構築する
+    fn emit_display_info(
+        &self,
+        display_string_builder: &DisplayStringBuilder,
+        spell: String,
+        key_stroke: String,
+        #[cfg(feature = "statistics")] on_typing_stat_manager: OnTypingStatisticsManager,
+    ) -> (SpellDisplayInfo, KeyStrokeDisplayInfo, DisplayStatistics) {
+        let mut spell_head_position = display_string_builder.spell_head_position;
+        let mut spell_wrong_positions = display_string_builder.spell_wrong_positions.clone();
+        let mut key_stroke_cursor_position = display_string_builder.key_stroke_cursor_position;
+        let mut key_stroke_wrong_positions =
+            display_string_builder.key_stroke_wrong_positions.clone();
+        let mut spell_cursor_positions = vec![spell_head_position];
+
+        if let Some(inflight_chunk) = self.inflight_chunk.as_ref() {
+            let mut wrong_spell_element_vector = inflight_chunk.initialized_spell_element_vector();
+            let mut wrong_key_strokes_vector = inflight_chunk.initialized_key_strokes_vector();
+            let mut in_candidate_cursor_position = 0;
+
+            // まず実際のキーストローク系列からチャンク内ミス位置を構築する
 
+            inflight_chunk
+                .actual_key_strokes()
+                .iter()
+                .for_each(|actual_key_stroke| {
                     if actual_key_stroke.is_correct() {
                         in_candidate_cursor_position += 1;
-
-                        if let Some(delta) = spell_end {
-                            on_typing_stat_manager.finish_spell(*delta);
-                        }
                     } else {
                         wrong_key_strokes_vector[in_candidate_cursor_position] = true;
 
@@ -323,152 +1442,64 @@ impl ProcessedChunkInfo {
             key_stroke_cursor_position += inflight_chunk.current_key_stroke_cursor_position();
 
             // 綴りのカーソル位置は複数ある場合がある
-            let in_chunk_current_spell_cursor_positions =
-                inflight_chunk.current_spell_cursor_positions();
-
-            spell_cursor_positions = in_chunk_current_spell_cursor_positions
+            spell_cursor_positions = inflight_chunk
+                .current_spell_cursor_positions()
                 .iter()
                 .map(|in_chunk_current_spell_cursor_position| {
                     spell_head_position + in_chunk_current_spell_cursor_position
                 })
                 .collect();
 
-            inflight_chunk
-                .as_ref()
-                .spell()
-                .as_ref()
-                .chars()
-                .enumerate()
-                .for_each(|(i, _)| {
-                    // 複数文字チャンクを個別に入力した場合はそれぞれの綴りについて
-                    // それ以外ではチャンク全体の綴りについて
-                    // タイプミス判定をする
-                    let element_index = if wrong_spell_element_vector.len() == 1 {
-                        0
-                    } else {
-                        i
-                    };
-
-                    if wrong_spell_element_vector[element_index] {
-                        spell_wrong_positions.push(spell_head_position);
-                    }
-
-                    spell_head_position += 1;
-                });
-
-            // 最後にチャンクの統計情報と表示用の文字列を更新する
-
-            key_stroke.push_str(
-                &inflight_chunk
-                    .as_ref()
-                    .min_candidate(None)
-                    .whole_key_stroke(),
-            );
-            spell.push_str(inflight_chunk.as_ref().spell().as_ref());
-
-            on_typing_stat_manager.add_unfinished_chunk(
-                inflight_chunk
-                    .as_ref()
-                    .min_candidate(None)
-                    .construct_key_stroke_element_count(),
-                inflight_chunk
-                    .as_ref()
-                    .ideal_key_stroke_candidate()
-                    .as_ref()
-                    .unwrap()
-                    .construct_key_stroke_element_count(),
-                inflight_chunk.as_ref().spell().count(),
-            );
-        }
-
-        // 3. 未処理のチャンク
-
-        let mut next_chunk_head_constraint = if self.inflight_chunk.is_some() {
-            self.inflight_chunk
-                .as_ref()
-                .unwrap()
-                .as_ref()
-                .min_candidate(None)
-                .next_chunk_head_constraint()
-                .clone()
-        } else {
-            None
-        };
-
-        self.unprocessed_chunks
-            .iter()
-            .enumerate()
-            .for_each(|(i, unprocessed_chunk)| {
-                // 未処理のチャンクの内最初のチャンクのみタイピング中のチャンクの遅延確定候補の補正をする
-
-                let candidate = unprocessed_chunk.min_candidate(next_chunk_head_constraint.clone());
-                let spell_count = if candidate.is_splitted() {
-                    2
+            (0..inflight_chunk.as_ref().spell().count()).for_each(|i| {
+                // 複数文字チャンクを個別に入力した場合はそれぞれの綴りについて
+                // それ以外ではチャンク全体の綴りについて
+                // タイプミス判定をする
+                let element_index = if wrong_spell_element_vector.len() == 1 {
+                    0
                 } else {
-                    unprocessed_chunk.spell().count()
+                    i
                 };
 
-                // 未確定のチャンクの一番最初のみ現在打っているチャンクの遅延確定候補によって補正をする
-                if i == 0 {
-                    if let Some(inflight_chunk) = self.inflight_chunk.as_ref() {
-                        if inflight_chunk.is_delayed_confirmable() {
-                            // キーストロークのカーソル位置は特に何も処理しなくて良い
-                            // 遅延確定候補の候補内カーソル位置は次のチャンク先頭を指す位置にあるため
-                            //
-                            // 綴りのカーソルは次のチャンク先頭を指す
-                            spell_cursor_positions.clear();
-                            for i in 0..spell_count {
-                                spell_cursor_positions.push(spell_head_position + i);
-                            }
-
-                            // 保留中のミスタイプは次のチャンクのミスタイプとみなす
-                            if inflight_chunk.has_wrong_stroke_in_pending_key_strokes() {
-                                key_stroke_wrong_positions.push(key_stroke_cursor_position);
-                                for i in 0..spell_count {
-                                    spell_wrong_positions.push(spell_head_position + i);
-                                }
-                            }
-
-                            inflight_chunk.pending_key_strokes().iter().for_each(
-                                |actual_key_stroke| {
-                                    on_typing_stat_manager.on_actual_key_stroke(
-                                        actual_key_stroke.is_correct(),
-                                        spell_count,
-                                        *actual_key_stroke.elapsed_time(),
-                                    );
-                                },
-                            );
-                        }
-                    }
+                if wrong_spell_element_vector[element_index] {
+                    spell_wrong_positions.push(spell_head_position);
                 }
 
-                // 表示用の文字列を更新する
-                key_stroke.push_str(&candidate.whole_key_stroke());
-
-                spell.push_str(unprocessed_chunk.spell().as_ref());
-                spell_head_position += unprocessed_chunk.spell().count();
-
-                // チャンクの統計情報を更新する
-
-                let key_stroke_element_count = unprocessed_chunk
-                    .ideal_key_stroke_candidate()
-                    .as_ref()
-                    .unwrap()
-                    .construct_key_stroke_element_count();
-
-                on_typing_stat_manager.add_unfinished_chunk(
-                    key_stroke_element_count.clone(),
-                    key_stroke_element_count,
-                    unprocessed_chunk.spell().count(),
-                );
-
-                // 次のチャンクへの制限を更新
-                match candidate.next_chunk_head_constraint().clone() {
-                    Some(constraint) => next_chunk_head_constraint.replace(constraint),
-                    None => next_chunk_head_constraint.take(),
-                };
+                spell_head_position += 1;
             });
 
+            // 遅延確定候補を打ち終えている場合には次のチャンクの先頭を指すように補正する
+            if let Some(pending_spell_count) = self.pending_spell_count() {
+                // キーストロークのカーソル位置は特に何も処理しなくて良い
+                // 遅延確定候補の候補内カーソル位置は次のチャンク先頭を指す位置にあるため
+                //
+                // 綴りのカーソルは次のチャンク先頭を指す
+                spell_cursor_positions = (0..pending_spell_count)
+                    .map(|i| spell_head_position + i)
+                    .collect();
+
+                // 保留中のミスタイプは次のチャンクのミスタイプとみなす
+                if inflight_chunk.has_wrong_stroke_in_pending_key_strokes() {
+                    key_stroke_wrong_positions.push(key_stroke_cursor_position);
+                    for i in 0..pending_spell_count {
+                        spell_wrong_positions.push(spell_head_position + i);
+                    }
+                }
+            }
+        } else {
+            assert!(self.is_finished());
+        }
+
+        #[cfg(feature = "statistics")]
+        let display_statistics = (
+            on_typing_stat_manager.lap_summaries().to_vec(),
+            (
+                on_typing_stat_manager.combo(),
+                on_typing_stat_manager.max_combo(),
+            ),
+        );
+        #[cfg(not(feature = "statistics"))]
+        let display_statistics = ();
+        #[cfg(feature = "statistics")]
         let (
             key_stroke_on_typing_statistics,
             ideal_key_stroke_on_typing_statistics,
@@ -481,16 +1512,20 @@ impl ProcessedChunkInfo {
                 spell,
                 spell_cursor_positions,
                 spell_wrong_positions,
-                spell_head_position - 1,
+                self.spell_count() - 1,
+                #[cfg(feature = "statistics")]
                 spell_on_typing_statistics,
             ),
             KeyStrokeDisplayInfo::new(
                 key_stroke,
                 key_stroke_cursor_position,
                 key_stroke_wrong_positions,
+                #[cfg(feature = "statistics")]
                 key_stroke_on_typing_statistics,
+                #[cfg(feature = "statistics")]
                 ideal_key_stroke_on_typing_statistics,
             ),
+            display_statistics,
         )
     }
 }
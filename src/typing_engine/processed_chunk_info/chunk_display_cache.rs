@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::Chunk;
+use crate::key_stroke::KeyStrokeChar;
+
+/// 表示用の情報のうちキーストロークのたびには変化しない部分
+///
+/// クエリの変更やチャンクの確定のたびに変化したチャンク以降のみを求め直しておくことで
+/// 表示用の情報を構築する際にタイプ中のチャンク以外を走査しなくて済むようにする
+#[derive(Debug, Clone)]
+pub(super) struct ChunkDisplayCache {
+    // 確定したチャンクの実際に打った候補のキーストロークとその中での各チャンクの先頭の位置
+    // 末尾には文字列全体の長さを入れておく
+    confirmed_key_stroke: String,
+    confirmed_key_stroke_heads: Vec<usize>,
+    // 候補を持つチャンクの理想的なキーストロークとその中での各チャンクの先頭の位置
+    ideal_key_stroke: String,
+    ideal_key_stroke_heads: Vec<usize>,
+    // 綴りのみのチャンクも含めた全てのチャンクの綴りにおける各チャンクの先頭の位置
+    spell_heads: Vec<usize>,
+    // 未処理のチャンクを先頭から制限を引き継ぎながら最小の候補で繋いだもの
+    min_candidate_chain: MinCandidateChain,
+    // 前回取り出してから求め直したチャンクのうち最も前のもののインデックス
+    // これより前のチャンクの表示は変わっていない
+    refreshed_chunk_index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MinCandidateChain {
+    // 連鎖の先頭のチャンクのインデックス
+    // チャンクが確定しても連鎖は求め直さないので先頭の方には処理済みのチャンクが残る
+    head_chunk_index: usize,
+    key_stroke: String,
+    // 連鎖のキーストロークにおける各チャンクの先頭の位置
+    key_stroke_heads: Vec<usize>,
+    // 各キーストロークについてそのキーストロークを含む生き残っている候補の数
+    candidate_counts: Vec<usize>,
+    // 各チャンクの先頭への制限と末尾のチャンクの次のチャンクへの制限
+    head_constraints: Vec<Option<KeyStrokeChar>>,
+}
+
+impl MinCandidateChain {
+    fn new(head_chunk_index: usize, head_constraint: Option<KeyStrokeChar>) -> Self {
+        Self {
+            head_chunk_index,
+            key_stroke: String::new(),
+            key_stroke_heads: vec![0],
+            candidate_counts: vec![],
+            head_constraints: vec![head_constraint],
+        }
+    }
+
+    // 連鎖の末尾のチャンクの次のチャンクのインデックス
+    fn end_chunk_index(&self) -> usize {
+        self.head_chunk_index + self.key_stroke_heads.len() - 1
+    }
+
+    // chunk_index番目のチャンク以降を取り除く
+    fn truncate(&mut self, chunk_index: usize) {
+        let count = chunk_index - self.head_chunk_index;
+        let key_stroke_end = self.key_stroke_heads[count];
+
+        self.key_stroke.truncate(key_stroke_end);
+        self.candidate_counts.truncate(key_stroke_end);
+        self.key_stroke_heads.truncate(count + 1);
+        self.head_constraints.truncate(count + 1);
+    }
+
+    fn push(&mut self, chunk: &Chunk) {
+        let head_constraint = self.head_constraints.last().unwrap().clone();
+        let candidate = chunk.min_candidate(head_constraint.clone());
+
+        self.key_stroke.push_str(&candidate.whole_key_stroke());
+        self.candidate_counts
+            .extend(chunk.construct_candidate_counts_of_min_candidate(head_constraint));
+        self.key_stroke_heads.push(self.key_stroke.len());
+        self.head_constraints
+            .push(candidate.next_chunk_head_constraint().clone());
+    }
+}
+
+impl ChunkDisplayCache {
+    pub(super) fn new() -> Self {
+        Self {
+            confirmed_key_stroke: String::new(),
+            confirmed_key_stroke_heads: vec![0],
+            ideal_key_stroke: String::new(),
+            ideal_key_stroke_heads: vec![0],
+            spell_heads: vec![0],
+            min_candidate_chain: MinCandidateChain::new(0, None),
+            refreshed_chunk_index: None,
+        }
+    }
+
+    /// 新たに確定したチャンクを反映する
+    pub(super) fn push_confirmed_chunk(&mut self, confirmed_chunk: &ConfirmedChunk) {
+        self.confirmed_key_stroke
+            .push_str(&confirmed_chunk.confirmed_candidate().whole_key_stroke());
+        self.confirmed_key_stroke_heads
+            .push(self.confirmed_key_stroke.len());
+    }
+
+    /// `chunk_index`番目以降のチャンクについて求め直す
+    ///
+    /// 確定していないチャンクはタイプ中のチャンク・未処理のチャンク・綴りのみのチャンクの順に受け取る
+    /// 確定したチャンクは変化しないので`chunk_index`は確定したチャンク数以上である必要がある
+    pub(super) fn refresh_from(
+        &mut self,
+        chunk_index: usize,
+        inflight_chunk: Option<&Chunk>,
+        unprocessed_chunks: &VecDeque<Chunk>,
+        spell_only_chunks: &VecDeque<Chunk>,
+    ) {
+        let confirmed_chunk_count = self.confirmed_key_stroke_heads.len() - 1;
+        assert!(chunk_index >= confirmed_chunk_count);
+
+        let candidate_chunks = inflight_chunk.into_iter().chain(unprocessed_chunks.iter());
+        let first_unprocessed_chunk_index = confirmed_chunk_count + inflight_chunk.iter().count();
+
+        // 理想的なキーストローク
+        let start = chunk_index.min(self.ideal_key_stroke_heads.len() - 1);
+        self.ideal_key_stroke
+            .truncate(self.ideal_key_stroke_heads[start]);
+        self.ideal_key_stroke_heads.truncate(start + 1);
+        candidate_chunks
+            .clone()
+            .skip(start - confirmed_chunk_count)
+            .for_each(|chunk| {
+                self.ideal_key_stroke.push_str(
+                    &chunk
+                        .ideal_key_stroke_candidate()
+                        .as_ref()
+                        .unwrap()
+                        .whole_key_stroke(),
+                );
+                self.ideal_key_stroke_heads
+                    .push(self.ideal_key_stroke.len());
+            });
+
+        // 綴り
+        let start = chunk_index.min(self.spell_heads.len() - 1);
+        self.spell_heads.truncate(start + 1);
+        candidate_chunks
+            .clone()
+            .chain(spell_only_chunks.iter())
+            .skip(start - confirmed_chunk_count)
+            .for_each(|chunk| {
+                self.spell_heads
+                    .push(self.spell_heads.last().unwrap() + chunk.spell().count());
+            });
+
+        // 最小の候補の連鎖
+        // 連鎖に含まれていない位置から求め直す場合には未処理のチャンクの先頭から求め直す
+        let chain = &mut self.min_candidate_chain;
+        let start = if chunk_index <= first_unprocessed_chunk_index
+            || chain.end_chunk_index() <= first_unprocessed_chunk_index
+        {
+            *chain = MinCandidateChain::new(
+                first_unprocessed_chunk_index,
+                inflight_chunk.and_then(|inflight_chunk| {
+                    inflight_chunk
+                        .min_candidate(None)
+                        .next_chunk_head_constraint()
+                        .clone()
+                }),
+            );
+
+            first_unprocessed_chunk_index
+        } else {
+            let start = chunk_index.min(chain.end_chunk_index());
+            chain.truncate(start);
+
+            start
+        };
+        unprocessed_chunks
+            .iter()
+            .skip(start - first_unprocessed_chunk_index)
+            .for_each(|chunk| chain.push(chunk));
+
+        // 連鎖は指定された位置より前から求め直す場合がある
+        let refreshed_chunk_index = chunk_index.min(start);
+        self.refreshed_chunk_index = Some(
+            self.refreshed_chunk_index
+                .map_or(refreshed_chunk_index, |index| {
+                    index.min(refreshed_chunk_index)
+                }),
+        );
+    }
+
+    /// 前回取り出してから求め直したチャンクのうち最も前のもののインデックスを取り出す
+    pub(super) fn take_refreshed_chunk_index(&mut self) -> Option<usize> {
+        self.refreshed_chunk_index.take()
+    }
+
+    /// 確定したチャンクの実際に打った候補のキーストローク
+    pub(super) fn confirmed_key_stroke(&self) -> &str {
+        &self.confirmed_key_stroke
+    }
+
+    /// 実際に打った候補のキーストロークにおける`chunk_index`番目の確定したチャンクの先頭の位置
+    pub(super) fn confirmed_key_stroke_head(&self, chunk_index: usize) -> usize {
+        self.confirmed_key_stroke_heads[chunk_index]
+    }
+
+    /// 候補を持つチャンクの理想的なキーストローク
+    pub(super) fn ideal_key_stroke(&self) -> &str {
+        &self.ideal_key_stroke
+    }
+
+    /// 理想的なキーストロークにおける`chunk_index`番目のチャンクの先頭の位置
+    pub(super) fn ideal_key_stroke_head(&self, chunk_index: usize) -> usize {
+        self.ideal_key_stroke_heads[chunk_index]
+    }
+
+    #[cfg(feature = "statistics")]
+    /// 理想的なキーストロークにおける`start`番目から`end`番目までのチャンクの先頭の位置
+    pub(super) fn ideal_key_stroke_heads_between(&self, start: usize, end: usize) -> &[usize] {
+        &self.ideal_key_stroke_heads[start..=end]
+    }
+
+    /// 綴りにおける`chunk_index`番目のチャンクの先頭の位置
+    pub(super) fn spell_head(&self, chunk_index: usize) -> usize {
+        self.spell_heads[chunk_index]
+    }
+
+    #[cfg(feature = "statistics")]
+    /// 綴りにおける`start`番目から`end`番目までのチャンクの先頭の位置
+    pub(super) fn spell_heads_between(&self, start: usize, end: usize) -> &[usize] {
+        &self.spell_heads[start..=end]
+    }
+
+    /// クエリ全体の綴りの文字数
+    pub(super) fn spell_count(&self) -> usize {
+        *self.spell_heads.last().unwrap()
+    }
+
+    /// 連鎖での`chunk_index`番目のチャンクの先頭への制限
+    /// 連鎖に含まれないチャンクでは存在しない
+    pub(super) fn min_candidate_head_constraint(
+        &self,
+        chunk_index: usize,
+    ) -> Option<&Option<KeyStrokeChar>> {
+        let chain = &self.min_candidate_chain;
+
+        chunk_index
+            .checked_sub(chain.head_chunk_index)
+            .filter(|_| chunk_index < chain.end_chunk_index())
+            .map(|index| &chain.head_constraints[index])
+    }
+
+    /// 連鎖での`chunk_index`番目のチャンク以降のキーストロークとそれぞれの生き残っている候補の数
+    pub(super) fn min_candidate_key_stroke_from(&self, chunk_index: usize) -> (&str, &[usize]) {
+        let chain = &self.min_candidate_chain;
+        let head = chain.key_stroke_heads[chunk_index - chain.head_chunk_index];
+
+        (&chain.key_stroke[head..], &chain.candidate_counts[head..])
+    }
+
+    /// 連鎖での`chunk_index`番目のチャンクの先頭の位置
+    pub(super) fn min_candidate_key_stroke_head(&self, chunk_index: usize) -> usize {
+        let chain = &self.min_candidate_chain;
+
+        chain.key_stroke_heads[chunk_index - chain.head_chunk_index]
+    }
+}
+
+// キャッシュは処理中のチャンク情報から導出できる状態なので比較やハッシュの対象にしない
+impl PartialEq for ChunkDisplayCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ChunkDisplayCache {}
+
+impl Hash for ChunkDisplayCache {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+}
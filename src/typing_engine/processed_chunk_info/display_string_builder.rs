@@ -0,0 +1,165 @@
+use crate::chunk::confirmed::ConfirmedChunk;
+use crate::chunk::has_actual_key_strokes::ChunkHasActualKeyStrokes;
+use crate::statistics::LapRequest;
+#[cfg(feature = "statistics")]
+use crate::statistics::OnTypingStatisticsManager;
+
+/// 表示用の情報のうち確定したチャンクまでを反映した構築途中の状態
+///
+/// 確定したチャンクは変化しないのでこの状態を保持しておけば新たに確定したチャンクのみを反映すれば良い
+/// 文字列はラップ要求に依らないので処理中のチャンク情報が保持する
+/// 統計を無効にした場合にはカーソル位置とミス位置のみを反映する
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DisplayStringBuilder {
+    pub(super) spell_head_position: usize,
+    pub(super) spell_wrong_positions: Vec<usize>,
+    pub(super) key_stroke_cursor_position: usize,
+    pub(super) key_stroke_wrong_positions: Vec<usize>,
+    #[cfg(feature = "statistics")]
+    pub(super) on_typing_stat_manager: OnTypingStatisticsManager,
+    lap_request: LapRequest,
+    // 反映済みの確定したチャンクの数
+    applied_chunk_count: usize,
+}
+
+impl DisplayStringBuilder {
+    pub(crate) fn new(lap_request: LapRequest) -> Self {
+        Self {
+            spell_head_position: 0,
+            spell_wrong_positions: vec![],
+            key_stroke_cursor_position: 0,
+            key_stroke_wrong_positions: vec![],
+            #[cfg(feature = "statistics")]
+            on_typing_stat_manager: OnTypingStatisticsManager::new(lap_request.clone()),
+            lap_request,
+            applied_chunk_count: 0,
+        }
+    }
+
+    pub(crate) fn lap_request(&self) -> &LapRequest {
+        &self.lap_request
+    }
+
+    pub(crate) fn applied_chunk_count(&self) -> usize {
+        self.applied_chunk_count
+    }
+
+    /// 確定したチャンクを1つ反映する
+    /// 語彙ごとのラップを取る場合には各語彙の末尾までのチャンク数から語彙末を判定する
+    #[cfg_attr(not(feature = "statistics"), allow(unused_variables))]
+    pub(crate) fn apply_confirmed_chunk(
+        &mut self,
+        confirmed_chunk: &ConfirmedChunk,
+        vocabulary_end_chunk_counts: &[usize],
+    ) {
+        let mut in_candidate_cursor_position = 0;
+        let mut wrong_spell_element_vector = confirmed_chunk.initialized_spell_element_vector();
+        let mut wrong_key_strokes_vector = confirmed_chunk.initialized_key_strokes_vector();
+        // 複数文字の綴りをまとめて打つ場合には綴りの統計は2文字分カウントする必要がある
+        #[cfg(feature = "statistics")]
+        let spell_count = confirmed_chunk.effective_spell_count();
+
+        #[cfg(feature = "statistics")]
+        self.on_typing_stat_manager
+            .set_this_candidate_key_stroke_count(
+                confirmed_chunk
+                    .confirmed_candidate()
+                    .whole_key_stroke()
+                    .chars()
+                    .count(),
+                confirmed_chunk
+                    .as_ref()
+                    .ideal_key_stroke_candidate()
+                    .as_ref()
+                    .unwrap()
+                    .whole_key_stroke()
+                    .chars()
+                    .count(),
+            );
+
+        // まず実際のキーストローク系列から統計情報を更新しチャンク内ミス位置を構築する
+
+        confirmed_chunk
+            .actual_key_strokes()
+            .iter()
+            .zip(confirmed_chunk.construct_spell_end_vector().iter())
+            .for_each(|(actual_key_stroke, spell_end)| {
+                #[cfg(feature = "statistics")]
+                self.on_typing_stat_manager.on_actual_key_stroke(
+                    actual_key_stroke.is_correct(),
+                    spell_count,
+                    *actual_key_stroke.elapsed_time(),
+                );
+
+                if actual_key_stroke.is_correct() {
+                    in_candidate_cursor_position += 1;
+
+                    #[cfg(feature = "statistics")]
+                    if let Some(delta) = spell_end {
+                        self.on_typing_stat_manager.finish_spell(*delta);
+                    }
+                } else {
+                    wrong_key_strokes_vector[in_candidate_cursor_position] = true;
+
+                    wrong_spell_element_vector[confirmed_chunk
+                        .confirmed_candidate()
+                        .element_index_at_key_stroke_index(in_candidate_cursor_position)] = true;
+                }
+            });
+
+        // 次に構築したチャンク内ミス位置からキーストロークと綴りのそれぞれのカーソル位置とミス位置を更新する
+
+        wrong_key_strokes_vector
+            .iter()
+            .enumerate()
+            .for_each(|(i, is_wrong)| {
+                if *is_wrong {
+                    self.key_stroke_wrong_positions
+                        .push(self.key_stroke_cursor_position + i);
+                }
+            });
+        self.key_stroke_cursor_position += in_candidate_cursor_position;
+
+        confirmed_chunk
+            .as_ref()
+            .spell()
+            .as_ref()
+            .chars()
+            .enumerate()
+            .for_each(|(i, _)| {
+                // 複数文字チャンクを個別に入力した場合はそれぞれの綴りについて
+                // それ以外ではチャンク全体の綴りについて
+                // タイプミス判定をする
+                let element_index = if wrong_spell_element_vector.len() == 1 {
+                    0
+                } else {
+                    i
+                };
+
+                if wrong_spell_element_vector[element_index] {
+                    self.spell_wrong_positions.push(self.spell_head_position);
+                }
+
+                self.spell_head_position += 1;
+            });
+
+        // 最後にチャンクの統計情報を更新する
+        #[cfg(feature = "statistics")]
+        self.on_typing_stat_manager.finish_chunk(
+            confirmed_chunk
+                .as_ref()
+                .min_candidate(None)
+                .construct_key_stroke_element_count(),
+            confirmed_chunk
+                .as_ref()
+                .ideal_key_stroke_candidate()
+                .as_ref()
+                .unwrap()
+                .construct_key_stroke_element_count(),
+            confirmed_chunk.as_ref().spell().count(),
+            vocabulary_end_chunk_counts,
+        );
+
+        self.applied_chunk_count += 1;
+    }
+}
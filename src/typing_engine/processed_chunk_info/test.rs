@@ -1,12 +1,21 @@
 use super::*;
 
+use std::collections::VecDeque;
+#[cfg(any(feature = "statistics", not(feature = "ascii_only")))]
 use std::num::NonZeroUsize;
 use std::time::Duration;
 
+#[cfg(not(feature = "ascii_only"))]
+use crate::chunk::append_key_stroke_to_chunks;
+#[cfg(not(feature = "ascii_only"))]
+use crate::gen_unprocessed_chunk;
 use crate::key_stroke::ActualKeyStroke;
+#[cfg(feature = "statistics")]
 use crate::statistics::OnTypingStatisticsTarget;
 use crate::typing_engine::processed_chunk_info::ConfirmedChunk;
+#[cfg(feature = "statistics")]
 use crate::typing_engine::processed_chunk_info::KeyStrokeDisplayInfo;
+#[cfg(feature = "statistics")]
 use crate::typing_engine::processed_chunk_info::SpellDisplayInfo;
 use crate::typing_engine::processed_chunk_info::TypedChunk;
 use crate::{gen_candidate, gen_chunk};
@@ -79,8 +88,11 @@ fn stroke_key_1() {
                 ),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -111,6 +123,8 @@ fn stroke_key_1() {
                 ),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "う",
@@ -124,6 +138,7 @@ fn stroke_key_1() {
                 .into()
             ),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -142,6 +157,8 @@ fn stroke_key_1() {
                 gen_candidate!(["wu"])
             ),]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "っ",
@@ -163,6 +180,7 @@ fn stroke_key_1() {
                     true
                 )],
             )],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -172,6 +190,8 @@ fn stroke_key_1() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "う",
@@ -202,6 +222,7 @@ fn stroke_key_1() {
                     )],
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -211,6 +232,8 @@ fn stroke_key_1() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "う",
@@ -248,6 +271,7 @@ fn stroke_key_1() {
                     )],
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -257,6 +281,8 @@ fn stroke_key_1() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![
                 ConfirmedChunk::new(
@@ -287,6 +313,7 @@ fn stroke_key_1() {
                     ]
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -306,6 +333,8 @@ fn stroke_key_1() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "う",
@@ -347,6 +376,7 @@ fn stroke_key_1() {
                     ]
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 }
@@ -393,8 +423,11 @@ fn stroke_key_2() {
                 gen_chunk!("き", vec![gen_candidate!(["ki"]),], gen_candidate!(["ki"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -416,6 +449,8 @@ fn stroke_key_2() {
                 gen_chunk!("き", vec![gen_candidate!(["ki"]),], gen_candidate!(["ki"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "か",
@@ -425,6 +460,7 @@ fn stroke_key_2() {
                 .into()
             ),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -446,6 +482,8 @@ fn stroke_key_2() {
                 gen_chunk!("き", vec![gen_candidate!(["ki"]),], gen_candidate!(["ki"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!("か", vec![gen_candidate!(["ka"])], gen_candidate!(["ka"])),
                 vec![1],
@@ -457,6 +495,7 @@ fn stroke_key_2() {
                 vec![]
             )),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -471,6 +510,8 @@ fn stroke_key_2() {
                 gen_candidate!(["ki"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "ん",
@@ -490,6 +531,7 @@ fn stroke_key_2() {
                     ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true)
                 ],
             )],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -504,6 +546,8 @@ fn stroke_key_2() {
                 gen_candidate!(["ki"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -526,6 +570,7 @@ fn stroke_key_2() {
                     ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true)
                 ],
             ),],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -541,6 +586,8 @@ fn stroke_key_2() {
                 gen_candidate!(["ki"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -567,6 +614,7 @@ fn stroke_key_2() {
                     ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true)
                 ],
             ),],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -577,6 +625,8 @@ fn stroke_key_2() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!("き", vec![gen_candidate!(["ki"])], gen_candidate!(["ki"])),
                 vec![1],
@@ -607,6 +657,7 @@ fn stroke_key_2() {
                     ),],
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -616,6 +667,8 @@ fn stroke_key_2() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![
                 ConfirmedChunk::new(
@@ -646,6 +699,7 @@ fn stroke_key_2() {
                     ],
                 ),
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -694,8 +748,11 @@ fn stroke_key_3() {
                 gen_chunk!("き", vec![gen_candidate!(["ki"]),], gen_candidate!(["ki"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -717,6 +774,8 @@ fn stroke_key_3() {
                 gen_chunk!("き", vec![gen_candidate!(["ki"]),], gen_candidate!(["ki"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "か",
@@ -726,6 +785,7 @@ fn stroke_key_3() {
                 .into()
             ),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -747,6 +807,8 @@ fn stroke_key_3() {
                 gen_chunk!("き", vec![gen_candidate!(["ki"]),], gen_candidate!(["ki"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!("か", vec![gen_candidate!(["ka"])], gen_candidate!(["ka"])),
                 vec![1],
@@ -758,6 +820,7 @@ fn stroke_key_3() {
                 vec![]
             )),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -772,6 +835,8 @@ fn stroke_key_3() {
                 gen_candidate!(["ki"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "ん",
@@ -791,6 +856,7 @@ fn stroke_key_3() {
                     ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true)
                 ],
             )],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -805,6 +871,8 @@ fn stroke_key_3() {
                 gen_candidate!(["ki"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -827,6 +895,7 @@ fn stroke_key_3() {
                     ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true)
                 ],
             ),],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -842,6 +911,8 @@ fn stroke_key_3() {
                 gen_candidate!(["ki"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -868,6 +939,7 @@ fn stroke_key_3() {
                     ActualKeyStroke::new(Duration::new(2, 0), 'a'.try_into().unwrap(), true)
                 ],
             ),],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -878,6 +950,8 @@ fn stroke_key_3() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!("き", vec![gen_candidate!(["ki"])], gen_candidate!(["ki"])).into()
             ),
@@ -902,6 +976,7 @@ fn stroke_key_3() {
                     ],
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -911,6 +986,8 @@ fn stroke_key_3() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!("き", vec![gen_candidate!(["ki"])], gen_candidate!(["ki"])),
                 vec![1],
@@ -942,6 +1019,7 @@ fn stroke_key_3() {
                     ],
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -951,6 +1029,8 @@ fn stroke_key_3() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![
                 ConfirmedChunk::new(
@@ -980,6 +1060,7 @@ fn stroke_key_3() {
                     ],
                 ),
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -1018,8 +1099,11 @@ fn stroke_key_4() {
                 gen_chunk!("ぴ", vec![gen_candidate!(["p"]),], gen_candidate!(["p"])),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -1034,6 +1118,8 @@ fn stroke_key_4() {
                 gen_candidate!(["p"])
             ),]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(
                 gen_chunk!(
                     "ん",
@@ -1047,6 +1133,7 @@ fn stroke_key_4() {
                 .into()
             ),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -1061,6 +1148,8 @@ fn stroke_key_4() {
                 gen_candidate!(["p"])
             )]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -1076,6 +1165,7 @@ fn stroke_key_4() {
                 vec![]
             )),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -1085,6 +1175,8 @@ fn stroke_key_4() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![
                 ConfirmedChunk::new(
@@ -1108,12 +1200,14 @@ fn stroke_key_4() {
                     ),],
                 )
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     assert!(pci.is_finished());
 }
 
+#[cfg(feature = "statistics")]
 #[test]
 fn construct_display_info_1() {
     // 1. 初期化
@@ -1178,6 +1272,8 @@ fn construct_display_info_1() {
                 gen_candidate!(["ky"])
             ),]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "きょ",
@@ -1225,11 +1321,12 @@ fn construct_display_info_1() {
                     ],
                 ),
             ],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     let (sdi, ksdi) =
-        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -1266,8 +1363,10 @@ fn construct_display_info_1() {
         )
     );
 
-    let (_, ksdi) =
-        pci.construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()));
+    let (_, ksdi) = pci.construct_display_info(
+        LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()),
+        &[],
+    );
 
     assert_eq!(
         ksdi,
@@ -1292,7 +1391,8 @@ fn construct_display_info_1() {
         )
     );
 
-    let (sdi, ksdi) = pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()));
+    let (sdi, ksdi) =
+        pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -1330,6 +1430,7 @@ fn construct_display_info_1() {
     );
 }
 
+#[cfg(feature = "statistics")]
 #[test]
 fn construct_display_info_2() {
     // 1. 初期化
@@ -1366,6 +1467,8 @@ fn construct_display_info_2() {
                 gen_candidate!(["zi"])
             ),]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -1385,11 +1488,12 @@ fn construct_display_info_2() {
                 ),]
             )),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     let (sdi, ksdi) =
-        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()), &[]);
 
     // 入力を終えた遅延確定候補は表示の上では確定したとみなす
     // pendingにあるミスタイプは表示状は次のチャンクに帰属させる
@@ -1423,8 +1527,10 @@ fn construct_display_info_2() {
         )
     );
 
-    let (_, ksdi) =
-        pci.construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()));
+    let (_, ksdi) = pci.construct_display_info(
+        LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()),
+        &[],
+    );
 
     assert_eq!(
         ksdi,
@@ -1445,7 +1551,8 @@ fn construct_display_info_2() {
         )
     );
 
-    let (sdi, ksdi) = pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()));
+    let (sdi, ksdi) =
+        pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -1484,6 +1591,8 @@ fn construct_display_info_2() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!("じ", vec![gen_candidate!(["ji"]),], gen_candidate!(["zi"])),
                 vec![1],
@@ -1505,11 +1614,12 @@ fn construct_display_info_2() {
                     true
                 ),],
             )],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     let (sdi, ksdi) =
-        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()), &[]);
 
     // 遅延確定候補で確定したのでミスタイプは引き続き次のチャンクに属する
     assert_eq!(
@@ -1542,8 +1652,10 @@ fn construct_display_info_2() {
         )
     );
 
-    let (_, ksdi) =
-        pci.construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()));
+    let (_, ksdi) = pci.construct_display_info(
+        LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()),
+        &[],
+    );
 
     assert_eq!(
         ksdi,
@@ -1564,7 +1676,8 @@ fn construct_display_info_2() {
         )
     );
 
-    let (sdi, ksdi) = pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()));
+    let (sdi, ksdi) =
+        pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -1597,6 +1710,7 @@ fn construct_display_info_2() {
     );
 }
 
+#[cfg(feature = "statistics")]
 #[test]
 fn construct_display_info_3() {
     // 1. 初期化
@@ -1633,6 +1747,8 @@ fn construct_display_info_3() {
                 gen_candidate!(["zi"])
             ),]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "ん",
@@ -1652,11 +1768,12 @@ fn construct_display_info_3() {
                 ),]
             )),
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     let (sdi, ksdi) =
-        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()), &[]);
 
     // 入力を終えた遅延確定候補は表示の上では確定したとみなす
     // pendingにあるミスタイプは表示状は次のチャンクに帰属させる
@@ -1690,7 +1807,8 @@ fn construct_display_info_3() {
         )
     );
 
-    let (sdi, ksdi) = pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()));
+    let (sdi, ksdi) =
+        pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -1729,6 +1847,8 @@ fn construct_display_info_3() {
         pci,
         ProcessedChunkInfo {
             unprocessed_chunks: vec![].into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "じ",
@@ -1751,11 +1871,12 @@ fn construct_display_info_3() {
                     ActualKeyStroke::new(Duration::new(3, 0), 'n'.try_into().unwrap(), true),
                 ],
             )],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     let (sdi, ksdi) =
-        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()), &[]);
 
     // 遅延確定候補ではない候補で確定したのでミスタイプはその候補に属する
     assert_eq!(
@@ -1788,8 +1909,10 @@ fn construct_display_info_3() {
         )
     );
 
-    let (_, ksdi) =
-        pci.construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()));
+    let (_, ksdi) = pci.construct_display_info(
+        LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()),
+        &[],
+    );
 
     assert_eq!(
         ksdi,
@@ -1810,7 +1933,8 @@ fn construct_display_info_3() {
         )
     );
 
-    let (sdi, ksdi) = pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()));
+    let (sdi, ksdi) =
+        pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()), &[]);
 
     // 遅延確定候補ではない候補で確定したのでミスタイプはその候補に属する
     assert_eq!(
@@ -1844,6 +1968,7 @@ fn construct_display_info_3() {
     );
 }
 
+#[cfg(feature = "statistics")]
 #[test]
 fn construct_display_info_4() {
     // 1. 初期化
@@ -1900,8 +2025,11 @@ fn construct_display_info_4() {
                 ),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: None,
             confirmed_chunks: vec![],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
@@ -1927,6 +2055,8 @@ fn construct_display_info_4() {
                 ),
             ]
             .into(),
+            spell_only_chunks: VecDeque::new(),
+            candidate_window: None,
             inflight_chunk: Some(TypedChunk::new(
                 gen_chunk!(
                     "っ",
@@ -1951,11 +2081,12 @@ fn construct_display_info_4() {
                     true
                 ),],
             )],
+            display_cache: ChunkDisplayCache::new(),
         }
     );
 
     let (sdi, ksdi) =
-        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()));
+        pci.construct_display_info(LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -1987,8 +2118,10 @@ fn construct_display_info_4() {
         )
     );
 
-    let (_, ksdi) =
-        pci.construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()));
+    let (_, ksdi) = pci.construct_display_info(
+        LapRequest::IdealKeyStroke(NonZeroUsize::new(2).unwrap()),
+        &[],
+    );
 
     assert_eq!(
         ksdi,
@@ -2009,7 +2142,8 @@ fn construct_display_info_4() {
         )
     );
 
-    let (sdi, ksdi) = pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()));
+    let (sdi, ksdi) =
+        pci.construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()), &[]);
 
     assert_eq!(
         sdi,
@@ -2041,3 +2175,237 @@ fn construct_display_info_4() {
         )
     );
 }
+
+#[test]
+fn construct_progress_display_info_1() {
+    let mut pci = ProcessedChunkInfo::new(vec![
+        gen_chunk!(
+            "きょ",
+            vec![
+                gen_candidate!(["kyo"]),
+                gen_candidate!(["ki", "lyo"]),
+                gen_candidate!(["ki", "xyo"])
+            ],
+            gen_candidate!(["kyo"])
+        ),
+        gen_chunk!("う", vec![gen_candidate!(["u"])], gen_candidate!(["u"])),
+    ]);
+
+    pci.move_next_chunk();
+
+    let pdi = pci.construct_progress_display_info();
+    assert_eq!(pdi, ProgressDisplayInfo::new(0, 2, 0, 3));
+
+    pci.stroke_key('k'.try_into().unwrap(), Duration::new(1, 0));
+    pci.stroke_key('y'.try_into().unwrap(), Duration::new(2, 0));
+
+    let pdi = pci.construct_progress_display_info();
+    assert_eq!(pdi, ProgressDisplayInfo::new(0, 2, 2, 3));
+    assert_eq!(pdi.progress(), 1.0 / 3.0);
+
+    pci.stroke_key('o'.try_into().unwrap(), Duration::new(3, 0));
+
+    let pdi = pci.construct_progress_display_info();
+    assert_eq!(pdi, ProgressDisplayInfo::new(1, 2, 0, 1));
+    assert_eq!(pdi.progress(), 0.5);
+
+    pci.stroke_key('u'.try_into().unwrap(), Duration::new(4, 0));
+
+    let pdi = pci.construct_progress_display_info();
+    assert_eq!(pdi, ProgressDisplayInfo::new(2, 2, 0, 0));
+    assert_eq!(pdi.progress(), 1.0);
+}
+
+#[test]
+fn construct_upcoming_candidate_counts_1() {
+    let mut pci = ProcessedChunkInfo::new(vec![
+        gen_chunk!(
+            "きょ",
+            vec![
+                gen_candidate!(["kyo"]),
+                gen_candidate!(["ki", "lyo"]),
+                gen_candidate!(["ki", "xyo"])
+            ],
+            gen_candidate!(["kyo"])
+        ),
+        gen_chunk!(
+            "ん",
+            vec![
+                gen_candidate!(["nn"]),
+                gen_candidate!(["xn"]),
+                gen_candidate!(["n"], ['k', 'c'])
+            ],
+            gen_candidate!(["n"], ['k', 'c'])
+        ),
+        gen_chunk!(
+            "か",
+            vec![gen_candidate!(["ka"]), gen_candidate!(["ca"])],
+            gen_candidate!(["ka"])
+        ),
+    ]);
+
+    pci.move_next_chunk();
+
+    assert_eq!(
+        pci.construct_upcoming_candidate_counts(),
+        vec![3, 1, 1, 2, 1, 1]
+    );
+
+    pci.stroke_key('k'.try_into().unwrap(), Duration::new(1, 0));
+    pci.stroke_key('i'.try_into().unwrap(), Duration::new(2, 0));
+
+    assert_eq!(
+        pci.construct_upcoming_candidate_counts(),
+        vec![1, 1, 1, 2, 1, 1]
+    );
+}
+
+#[cfg(not(feature = "ascii_only"))]
+#[test]
+fn candidate_window_keeps_same_candidates_as_whole_query() {
+    // 「ん」や「っ」の候補が範囲外のチャンクに依存するようにする
+    let mut chunks = vec![
+        gen_unprocessed_chunk!("か"),
+        gen_unprocessed_chunk!("ん"),
+        gen_unprocessed_chunk!("な"),
+        gen_unprocessed_chunk!("っ"),
+        gen_unprocessed_chunk!("た"),
+        gen_unprocessed_chunk!("ん"),
+        gen_unprocessed_chunk!("ん"),
+        gen_unprocessed_chunk!("や"),
+        gen_unprocessed_chunk!("じょ"),
+    ];
+    append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
+    // 末尾のチャンクのキーストローク数の制限は範囲外でも保たれる
+    chunks
+        .last_mut()
+        .unwrap()
+        .strict_key_stroke_count(NonZeroUsize::new(1).unwrap());
+
+    let mut whole_pci = ProcessedChunkInfo::new(chunks.clone());
+    let mut pci = ProcessedChunkInfo::new(chunks).with_candidate_window(
+        NonZeroUsize::new(1).unwrap(),
+        InputScheme::Standard,
+        None,
+    );
+    assert_eq!(pci.unprocessed_chunks.len(), 1);
+    assert_eq!(
+        pci.construct_query_spell(),
+        whole_pci.construct_query_spell()
+    );
+    assert_eq!(
+        pci.construct_chunk_head_positions().0,
+        whole_pci.construct_chunk_head_positions().0
+    );
+
+    let mut ideal_key_stroke = String::new();
+    whole_pci.write_ideal_key_stroke(&mut ideal_key_stroke);
+
+    whole_pci.move_next_chunk();
+    pci.move_next_chunk();
+
+    for (i, key_stroke) in ideal_key_stroke.chars().enumerate() {
+        let elapsed_time = Duration::new(i as u64, 0);
+        whole_pci.stroke_key(key_stroke.try_into().unwrap(), elapsed_time);
+        pci.stroke_key(key_stroke.try_into().unwrap(), elapsed_time);
+
+        assert_eq!(pci.inflight_chunk, whole_pci.inflight_chunk);
+        assert_eq!(
+            pci.unprocessed_chunks,
+            whole_pci
+                .unprocessed_chunks
+                .iter()
+                .take(1)
+                .cloned()
+                .collect::<VecDeque<Chunk>>()
+        );
+        assert_eq!(
+            pci.construct_progress_display_info(),
+            whole_pci.construct_progress_display_info()
+        );
+    }
+
+    assert!(pci.is_finished());
+    assert_eq!(pci.confirmed_chunks, whole_pci.confirmed_chunks);
+}
+
+#[cfg(not(feature = "ascii_only"))]
+#[test]
+fn ideal_key_stroke_progress_matches_ideal_key_stroke() {
+    let mut chunks = vec![
+        gen_unprocessed_chunk!("じょ"),
+        gen_unprocessed_chunk!("っ"),
+        gen_unprocessed_chunk!("た"),
+        gen_unprocessed_chunk!("ん"),
+    ];
+    append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
+
+    let mut pci = ProcessedChunkInfo::new(chunks);
+    pci.move_next_chunk();
+
+    // 理想的ではない候補で打っても理想的なキーストローク系列での位置に変換される
+    for (i, key_stroke) in "jilyottann".chars().enumerate() {
+        let mut ideal_key_stroke = String::new();
+        let cursor_position = pci.write_ideal_key_stroke(&mut ideal_key_stroke);
+
+        assert_eq!(
+            pci.ideal_key_stroke_progress(),
+            (cursor_position, ideal_key_stroke.chars().count())
+        );
+
+        pci.stroke_key(key_stroke.try_into().unwrap(), Duration::new(i as u64, 0));
+    }
+
+    assert!(pci.is_finished());
+    assert_eq!(pci.ideal_key_stroke_progress(), (7, 7));
+}
+
+#[cfg(all(feature = "statistics", not(feature = "ascii_only")))]
+#[test]
+fn completed_lap_count_is_same_as_applying_inflight_chunk_statistics() {
+    let mut chunks = vec![
+        gen_unprocessed_chunk!("きょ"),
+        gen_unprocessed_chunk!("っ"),
+        gen_unprocessed_chunk!("た"),
+        gen_unprocessed_chunk!("ん"),
+        gen_unprocessed_chunk!("じょ"),
+        gen_unprocessed_chunk!("う"),
+    ];
+    append_key_stroke_to_chunks(&mut chunks, &InputScheme::Standard, None);
+    let vocabulary_end_chunk_counts = [3, 6];
+
+    for lap_request in [
+        LapRequest::KeyStroke(NonZeroUsize::new(2).unwrap()),
+        LapRequest::IdealKeyStroke(NonZeroUsize::new(3).unwrap()),
+        LapRequest::Spell(NonZeroUsize::new(2).unwrap()),
+        LapRequest::Vocabulary(NonZeroUsize::new(1).unwrap()),
+        LapRequest::Duration(Duration::from_secs(2)),
+        LapRequest::CustomSpellPositions(vec![1, 4]),
+    ] {
+        let mut pci = ProcessedChunkInfo::new(chunks.clone());
+        pci.move_next_chunk();
+        let mut display_string_builder = DisplayStringBuilder::new(lap_request.clone());
+
+        // 理想的ではない候補・ミスタイプ・次のチャンクのものとみなす保留したキーストロークを含める
+        for (i, key_stroke) in "kixzyottanjzou".chars().enumerate() {
+            pci.stroke_key(key_stroke.try_into().unwrap(), Duration::new(i as u64, 0));
+            pci.apply_confirmed_chunks(&mut display_string_builder, &vocabulary_end_chunk_counts);
+
+            let mut on_typing_stat_manager = display_string_builder.on_typing_stat_manager.clone();
+            pci.apply_inflight_chunk_statistics(
+                &mut on_typing_stat_manager,
+                &vocabulary_end_chunk_counts,
+            );
+
+            assert_eq!(
+                pci.completed_lap_count(&display_string_builder),
+                on_typing_stat_manager.lap_summaries().len(),
+                "{:?} {}",
+                lap_request,
+                i
+            );
+        }
+
+        assert!(pci.is_finished());
+    }
+}
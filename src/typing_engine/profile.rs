@@ -0,0 +1,228 @@
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_scheme::{InputScheme, RomanizationPreference};
+use crate::keyboard_layout::KeyboardLayout;
+#[cfg(feature = "statistics")]
+use crate::statistics::result::AccumulatedResultStatistics;
+#[cfg(feature = "analysis")]
+use crate::statistics::skill::SkillStatistics;
+use crate::statistics::LapRequest;
+use crate::typing_engine::life::LifeConfig;
+use crate::typing_engine::termination::TerminationCondition;
+
+/// A bundle of configurations and statistics of a player.
+///
+/// A profile can be applied to [`TypingEngine`](crate::TypingEngine) by
+/// [`apply_profile`](crate::TypingEngine::apply_profile()) and taken from it by
+/// [`export_profile`](crate::TypingEngine::export_profile()), so players can be switched between
+/// sessions without configuring each option manually.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    name: String,
+    input_scheme: InputScheme,
+    romanization_preference: Option<RomanizationPreference>,
+    keyboard_layout: KeyboardLayout,
+    strict_mode: bool,
+    shift_key_mode: bool,
+    candidate_window: Option<NonZeroUsize>,
+    target_kpm: Option<NonZeroUsize>,
+    idle_threshold: Option<Duration>,
+    first_stroke_starts_clock: bool,
+    life_config: Option<LifeConfig>,
+    termination_condition: Option<TerminationCondition>,
+    lap_request: Option<LapRequest>,
+    #[cfg(feature = "statistics")]
+    accumulated_statistics: AccumulatedResultStatistics,
+}
+
+impl PlayerProfile {
+    /// Construct a new [`PlayerProfile`] with default configurations.
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            ..Self::default()
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which uses `input_scheme` to construct key strokes of query.
+    pub fn with_input_scheme(self, input_scheme: InputScheme) -> Self {
+        Self {
+            input_scheme,
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which prefers `romanization_preference` to construct key
+    /// strokes of query.
+    pub fn with_romanization_preference(
+        self,
+        romanization_preference: RomanizationPreference,
+    ) -> Self {
+        Self {
+            romanization_preference: Some(romanization_preference),
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which uses `keyboard_layout` to translate physical keys.
+    pub fn with_keyboard_layout(self, keyboard_layout: KeyboardLayout) -> Self {
+        Self {
+            keyboard_layout,
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which enables or disables strict mode.
+    pub fn with_strict_mode(self, strict_mode: bool) -> Self {
+        Self {
+            strict_mode,
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which enables or disables Shift key mode.
+    pub fn with_shift_key_mode(self, shift_key_mode: bool) -> Self {
+        Self {
+            shift_key_mode,
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which limits the number of candidates shown at once.
+    pub fn with_candidate_window(self, candidate_window: NonZeroUsize) -> Self {
+        Self {
+            candidate_window: Some(candidate_window),
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which configures target speed in key strokes per minute.
+    pub fn with_target_kpm(self, target_kpm: NonZeroUsize) -> Self {
+        Self {
+            target_kpm: Some(target_kpm),
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which configures threshold to regard the player as idle.
+    pub fn with_idle_threshold(self, idle_threshold: Duration) -> Self {
+        Self {
+            idle_threshold: Some(idle_threshold),
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which starts measuring elapsed time at the first correct key
+    /// stroke.
+    pub fn with_first_stroke_starts_clock(self, first_stroke_starts_clock: bool) -> Self {
+        Self {
+            first_stroke_starts_clock,
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which configures lives economy.
+    pub fn with_life_config(self, life_config: LifeConfig) -> Self {
+        Self {
+            life_config: Some(life_config),
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which finishes typing early when `termination_condition` is
+    /// met.
+    pub fn with_termination_condition(self, termination_condition: TerminationCondition) -> Self {
+        Self {
+            termination_condition: Some(termination_condition),
+            ..self
+        }
+    }
+
+    /// Construct [`PlayerProfile`] which configures lap request.
+    pub fn with_lap_request(self, lap_request: LapRequest) -> Self {
+        Self {
+            lap_request: Some(lap_request),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn input_scheme(&self) -> &InputScheme {
+        &self.input_scheme
+    }
+
+    pub fn romanization_preference(&self) -> Option<&RomanizationPreference> {
+        self.romanization_preference.as_ref()
+    }
+
+    pub fn keyboard_layout(&self) -> &KeyboardLayout {
+        &self.keyboard_layout
+    }
+
+    pub fn strict_mode(&self) -> bool {
+        self.strict_mode
+    }
+
+    pub fn shift_key_mode(&self) -> bool {
+        self.shift_key_mode
+    }
+
+    pub fn candidate_window(&self) -> Option<NonZeroUsize> {
+        self.candidate_window
+    }
+
+    pub fn target_kpm(&self) -> Option<NonZeroUsize> {
+        self.target_kpm
+    }
+
+    pub fn idle_threshold(&self) -> Option<Duration> {
+        self.idle_threshold
+    }
+
+    pub fn first_stroke_starts_clock(&self) -> bool {
+        self.first_stroke_starts_clock
+    }
+
+    pub fn life_config(&self) -> Option<&LifeConfig> {
+        self.life_config.as_ref()
+    }
+
+    pub fn termination_condition(&self) -> Option<&TerminationCondition> {
+        self.termination_condition.as_ref()
+    }
+
+    pub fn lap_request(&self) -> Option<&LapRequest> {
+        self.lap_request.as_ref()
+    }
+
+    #[cfg(feature = "statistics")]
+    /// Get statistics accumulated by this player including skill statistics.
+    pub fn accumulated_statistics(&self) -> &AccumulatedResultStatistics {
+        &self.accumulated_statistics
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Get miss statistics of each key stroke and key stroke bigram accumulated by this player.
+    ///
+    /// This is a shorthand of [`AccumulatedResultStatistics::skill_statistics`].
+    pub fn skill_statistics(&self) -> &SkillStatistics {
+        self.accumulated_statistics.skill_statistics()
+    }
+
+    #[cfg(feature = "statistics")]
+    pub(crate) fn with_accumulated_statistics(
+        self,
+        accumulated_statistics: AccumulatedResultStatistics,
+    ) -> Self {
+        Self {
+            accumulated_statistics,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::cmp::Reverse;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use crate::key_stroke::KeyStrokeChar;
+use crate::typing_engine::{TypingEngine, TypingEngineError, TypingEngineErrorKind};
+
+/// A coordinator of a race in which multiple players type the same query.
+///
+/// Each player has an independent [`TypingEngine`] cloned from the one given to
+/// [`new`](Self::new()), so all players type exactly the same query with the same settings
+/// even if vocabularies are ordered randomly.
+/// Players are identified by index from `0` to [`player_count`](Self::player_count()).
+#[derive(Debug, Clone)]
+pub struct TypingRace {
+    engines: Vec<TypingEngine>,
+}
+
+impl TypingRace {
+    /// Construct a race of `player_count` players from initialized `engine`.
+    ///
+    /// If `engine` is not initialized or already started, this method returns error.
+    pub fn new(
+        engine: TypingEngine,
+        player_count: NonZeroUsize,
+    ) -> Result<Self, TypingEngineError> {
+        if engine.is_started() {
+            return Err(engine.lifecycle_error(TypingEngineErrorKind::AlreadyStarted));
+        }
+
+        if !engine.is_initialized() {
+            return Err(engine.lifecycle_error(TypingEngineErrorKind::MustBeInitialized));
+        }
+
+        Ok(Self {
+            engines: vec![engine; player_count.get()],
+        })
+    }
+
+    /// Get the number of players.
+    pub fn player_count(&self) -> usize {
+        self.engines.len()
+    }
+
+    /// Get [`TypingEngine`] of `player`.
+    ///
+    /// This is useful to construct display info or result statistics of each player.
+    pub fn engine(&self, player: usize) -> Option<&TypingEngine> {
+        self.engines.get(player)
+    }
+
+    /// Start typing of all players.
+    pub fn start(&mut self) -> Result<(), TypingEngineError> {
+        self.engines.iter_mut().try_for_each(TypingEngine::start)
+    }
+
+    /// Give a key stroke of `player`.
+    ///
+    /// See [`TypingEngine::stroke_key`](TypingEngine::stroke_key()) for details.
+    /// If `player` does not exist, this method returns error.
+    pub fn stroke_key(
+        &mut self,
+        player: usize,
+        key_stroke: KeyStrokeChar,
+    ) -> Result<bool, TypingEngineError> {
+        self.engine_mut(player)?.stroke_key(key_stroke)
+    }
+
+    /// Give a key stroke of `player` which occurred at `elapsed_time` from start.
+    ///
+    /// See [`TypingEngine::stroke_key_with_elapsed_time`](TypingEngine::stroke_key_with_elapsed_time())
+    /// for details.
+    /// If `player` does not exist, this method returns error.
+    pub fn stroke_key_with_elapsed_time(
+        &mut self,
+        player: usize,
+        key_stroke: KeyStrokeChar,
+        elapsed_time: Duration,
+    ) -> Result<bool, TypingEngineError> {
+        self.engine_mut(player)?
+            .stroke_key_with_elapsed_time(key_stroke, elapsed_time)
+    }
+
+    /// Construct standings of all players ranked from the leader.
+    ///
+    /// Speed of each player is measured by wall clock of each engine.
+    /// If this method is called before starting, this method returns error.
+    pub fn construct_standings(&self) -> Result<Vec<RaceStanding>, TypingEngineError> {
+        self.construct_standings_inner(|engine| engine.display_elapsed_time())
+    }
+
+    /// Construct standings of all players ranked from the leader at `elapsed_time` from start.
+    ///
+    /// If this method is called before starting, this method returns error.
+    pub fn construct_standings_with_elapsed_time(
+        &self,
+        elapsed_time: Duration,
+    ) -> Result<Vec<RaceStanding>, TypingEngineError> {
+        self.construct_standings_inner(|engine| engine.elapsed_time_from_clock_start(elapsed_time))
+    }
+
+    fn engine_mut(&mut self, player: usize) -> Result<&mut TypingEngine, TypingEngineError> {
+        self.engines.get_mut(player).ok_or(TypingEngineError::new(
+            TypingEngineErrorKind::PlayerNotFound,
+        ))
+    }
+
+    fn construct_standings_inner(
+        &self,
+        elapsed_time_of: impl Fn(&TypingEngine) -> Duration,
+    ) -> Result<Vec<RaceStanding>, TypingEngineError> {
+        if let Some(engine) = self.engines.iter().find(|engine| !engine.is_started()) {
+            return Err(engine.lifecycle_error(TypingEngineErrorKind::MustBeStarted));
+        }
+
+        let mut standings: Vec<RaceStanding> = self
+            .engines
+            .iter()
+            .enumerate()
+            .map(|(player, engine)| {
+                // 打つ候補は選手ごとに異なりうるので理想的なキーストローク系列での位置で比べる
+                let (ideal_key_stroke_cursor_position, ideal_key_stroke_count) =
+                    engine.ideal_key_stroke_progress();
+
+                RaceStanding {
+                    player,
+                    rank: 0,
+                    ideal_key_stroke_cursor_position,
+                    ideal_key_stroke_count,
+                    wpm: engine
+                        .construct_speed_display_info_at(elapsed_time_of(engine))
+                        .rolling_wpm(),
+                    completion_time: engine.completion_time(),
+                }
+            })
+            .collect();
+
+        // 打ち終えた選手は打ち終えた時間が早い順にそれ以外の選手は進んでいる順に並べる
+        let rank_key = |standing: &RaceStanding| {
+            (
+                standing.completion_time.is_none(),
+                standing.completion_time,
+                Reverse(standing.ideal_key_stroke_cursor_position),
+            )
+        };
+        standings.sort_by_key(rank_key);
+
+        // 同じ順位の選手は同じ順位とし次の順位はその人数分飛ばす
+        for i in 0..standings.len() {
+            standings[i].rank = if i != 0 && rank_key(&standings[i - 1]) == rank_key(&standings[i])
+            {
+                standings[i - 1].rank
+            } else {
+                i + 1
+            };
+        }
+
+        Ok(standings)
+    }
+}
+
+/// A standing of a player in [`TypingRace`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaceStanding {
+    player: usize,
+    rank: usize,
+    ideal_key_stroke_cursor_position: usize,
+    ideal_key_stroke_count: usize,
+    wpm: f64,
+    completion_time: Option<Duration>,
+}
+
+impl RaceStanding {
+    /// Get index of the player.
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// Get rank of the player starting from `1`.
+    ///
+    /// Players who completed the query are ranked by completion time, and others are ranked by
+    /// progress.
+    /// Players with the same progress have the same rank.
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// Get cursor position of the player in ideal key strokes of the query.
+    ///
+    /// Ideal key strokes are common to all players regardless of candidates they chose, so
+    /// this is used to compare progress of players.
+    pub fn ideal_key_stroke_cursor_position(&self) -> usize {
+        self.ideal_key_stroke_cursor_position
+    }
+
+    /// Get the number of ideal key strokes of the whole query.
+    pub fn ideal_key_stroke_count(&self) -> usize {
+        self.ideal_key_stroke_count
+    }
+
+    /// Get current words per minute of the player.
+    ///
+    /// See [`SpeedDisplayInfo::rolling_wpm`](crate::display_info::SpeedDisplayInfo::rolling_wpm())
+    /// for details.
+    pub fn wpm(&self) -> f64 {
+        self.wpm
+    }
+
+    /// Get elapsed time from start when the player completed the query.
+    ///
+    /// If the player has not completed the query, this returns `None`.
+    pub fn completion_time(&self) -> Option<Duration> {
+        self.completion_time
+    }
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::AutoCompletePolicy;
+use crate::display_info::DisplayInfo;
+use crate::key_stroke::KeyStrokeChar;
+#[cfg(feature = "analysis")]
+use crate::statistics::plausibility::{validate_humanlike, HumanlikeCriteria, HumanlikeValidation};
+
+/// A record of a typing session which can be played back by
+/// [`TypingEngine::replay`](crate::TypingEngine::replay()).
+///
+/// This is constructed by
+/// [`TypingEngine::construct_replay`](crate::TypingEngine::construct_replay()).
+/// Key strokes typed by player and operations such as
+/// [`backspace`](crate::TypingEngine::backspace()) are recorded, so a session in strict mode
+/// or a session assisted by auto-complete is also reproduced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TypingReplay {
+    // 再生するエンジンのクエリが記録した時と同じかを確認するためのクエリ全体の綴り
+    query_spell: String,
+    key_strokes: Vec<ReplayKeyStroke>,
+    // キーストローク以外の操作とその操作より前に記録したキーストロークの数
+    #[serde(default)]
+    operations: Vec<(usize, ReplayOperation)>,
+}
+
+impl TypingReplay {
+    pub(crate) fn new(
+        query_spell: String,
+        key_strokes: Vec<ReplayKeyStroke>,
+        operations: Vec<(usize, ReplayOperation)>,
+    ) -> Self {
+        Self {
+            query_spell,
+            key_strokes,
+            operations,
+        }
+    }
+
+    pub(crate) fn query_spell(&self) -> &str {
+        &self.query_spell
+    }
+
+    // キーストロークと操作を記録した順に並べる
+    // 再生できない文字や記録したキーストロークの数を超える位置の操作がある場合にはNoneを返す
+    pub(crate) fn events(&self) -> Option<Vec<ReplayEvent<'_>>> {
+        let mut operations = self.operations.iter().peekable();
+        let mut events = vec![];
+
+        for (index, key_stroke) in self.key_strokes.iter().enumerate() {
+            while let Some((_, operation)) = operations.next_if(|(count, _)| *count <= index) {
+                events.push(ReplayEvent::Operation(operation));
+            }
+
+            events.push(ReplayEvent::KeyStroke(
+                key_stroke.elapsed_time,
+                key_stroke.key_stroke_char()?,
+            ));
+        }
+
+        for (count, operation) in operations {
+            if *count > self.key_strokes.len() {
+                return None;
+            }
+
+            events.push(ReplayEvent::Operation(operation));
+        }
+
+        Some(events)
+    }
+
+    /// Get recorded key strokes typed by player in order of time.
+    ///
+    /// Key strokes injected by operations such as
+    /// [`auto_complete_current_chunk`](crate::TypingEngine::auto_complete_current_chunk()) are
+    /// not included.
+    pub fn key_strokes(&self) -> &[ReplayKeyStroke] {
+        &self.key_strokes
+    }
+
+    /// Get recorded operations other than key strokes in order of time.
+    ///
+    /// Each operation is paired with count of key strokes recorded before the operation.
+    pub fn operations(&self) -> &[(usize, ReplayOperation)] {
+        &self.operations
+    }
+
+    /// Get elapsed time from start to last recorded key stroke or operation.
+    pub fn duration(&self) -> Duration {
+        self.key_strokes
+            .iter()
+            .map(|key_stroke| key_stroke.elapsed_time)
+            .chain(
+                self.operations
+                    .iter()
+                    .filter_map(|(_, operation)| operation.elapsed_time()),
+            )
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Validate whether timing of recorded key strokes is humanlike with default criteria.
+    ///
+    /// This is useful to check a replay submitted to online leaderboards.
+    pub fn validate_humanlike(&self) -> HumanlikeValidation {
+        self.validate_humanlike_with(&HumanlikeCriteria::default())
+    }
+
+    #[cfg(feature = "analysis")]
+    /// Validate whether timing of recorded key strokes is humanlike with `criteria`.
+    pub fn validate_humanlike_with(&self, criteria: &HumanlikeCriteria) -> HumanlikeValidation {
+        let key_strokes: Vec<(Duration, char)> = self
+            .key_strokes
+            .iter()
+            .map(|key_stroke| (key_stroke.elapsed_time, key_stroke.key_stroke))
+            .collect();
+
+        validate_humanlike(&key_strokes, criteria)
+    }
+}
+
+/// A key stroke recorded in [`TypingReplay`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReplayKeyStroke {
+    elapsed_time: Duration,
+    // 逆シリアライズ時に検証できないので文字のまま保持して再生時に検証する
+    key_stroke: char,
+}
+
+impl ReplayKeyStroke {
+    pub(crate) fn new(elapsed_time: Duration, key_stroke: KeyStrokeChar) -> Self {
+        Self {
+            elapsed_time,
+            key_stroke: key_stroke.into(),
+        }
+    }
+
+    /// Get elapsed time from start when this key stroke was typed.
+    pub fn elapsed_time(&self) -> Duration {
+        self.elapsed_time
+    }
+
+    /// Get typed key.
+    pub fn key_stroke(&self) -> char {
+        self.key_stroke
+    }
+
+    fn key_stroke_char(&self) -> Option<KeyStrokeChar> {
+        self.key_stroke.try_into().ok()
+    }
+}
+
+/// An operation other than key strokes recorded in [`TypingReplay`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReplayOperation {
+    /// [`backspace`](crate::TypingEngine::backspace()) was called.
+    Backspace,
+    /// [`clear_pending_wrong_state`](crate::TypingEngine::clear_pending_wrong_state()) was called.
+    ClearPendingWrongState,
+    /// The chunk was auto-completed at `elapsed_time` from start.
+    AutoComplete {
+        policy: AutoCompletePolicy,
+        elapsed_time: Duration,
+    },
+    /// The vocabulary was skipped at `elapsed_time` from start.
+    SkipVocabulary { elapsed_time: Duration },
+    /// [`hint`](crate::TypingEngine::hint()) was called.
+    Hint,
+}
+
+impl ReplayOperation {
+    /// Get elapsed time from start when this operation was performed.
+    ///
+    /// Operations which do not depend on time such as
+    /// [`Backspace`](Self::Backspace) return `None`.
+    pub fn elapsed_time(&self) -> Option<Duration> {
+        match self {
+            Self::Backspace | Self::ClearPendingWrongState | Self::Hint => None,
+            Self::AutoComplete { elapsed_time, .. } | Self::SkipVocabulary { elapsed_time } => {
+                Some(*elapsed_time)
+            }
+        }
+    }
+}
+
+// 再生するキーストロークか操作
+pub(crate) enum ReplayEvent<'a> {
+    KeyStroke(Duration, KeyStrokeChar),
+    Operation(&'a ReplayOperation),
+}
+
+/// A frame generated by playing back [`TypingReplay`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayFrame {
+    playback_time: Duration,
+    display_info: DisplayInfo,
+}
+
+impl ReplayFrame {
+    pub(crate) fn new(playback_time: Duration, display_info: DisplayInfo) -> Self {
+        Self {
+            playback_time,
+            display_info,
+        }
+    }
+
+    /// Get time from start of playback when this frame should be shown.
+    ///
+    /// This is elapsed time of original session divided by playback speed.
+    pub fn playback_time(&self) -> Duration {
+        self.playback_time
+    }
+
+    /// Get display information of this frame.
+    pub fn display_info(&self) -> &DisplayInfo {
+        &self.display_info
+    }
+}
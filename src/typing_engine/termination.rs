@@ -0,0 +1,52 @@
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+
+/// A condition to terminate typing by wrong key strokes.
+///
+/// When the condition is met, typing is finished at the wrong key stroke and end reason of result
+/// is [`TypingEndReason::MissLimitReached`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TerminationCondition {
+    /// Typing is terminated by the first wrong key stroke.
+    SuddenDeath,
+    /// Typing is terminated when total count of wrong key strokes reaches this value.
+    MissLimit(NonZeroUsize),
+}
+
+impl TerminationCondition {
+    /// Get count of wrong key strokes which terminates typing.
+    pub fn miss_limit(&self) -> NonZeroUsize {
+        match self {
+            Self::SuddenDeath => NonZeroUsize::new(1).unwrap(),
+            Self::MissLimit(miss_limit) => *miss_limit,
+        }
+    }
+}
+
+/// A reason why typing is finished.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TypingEndReason {
+    /// Whole query is typed.
+    Completed,
+    /// Life is exhausted by wrong key strokes.
+    /// Targets after last key stroke are not counted in statistics.
+    LifeExhausted,
+    /// Count of wrong key strokes reaches limit of
+    /// [termination condition](crate::TypingEngine::set_termination_condition()).
+    /// Targets after last key stroke are not counted in statistics.
+    MissLimitReached,
+    /// Typing is finished explicitly by [`finish`](crate::TypingEngine::finish()) before whole
+    /// query is typed.
+    /// Remaining targets are regarded as unfinished and are not counted in statistics.
+    Finished,
+    /// Time limit configured by [`start_with_limit`](crate::TypingEngine::start_with_limit())
+    /// is reached before whole query is typed.
+    /// Remaining targets are regarded as unfinished and are not counted in statistics.
+    TimeLimitReached,
+    /// Typing is not finished yet.
+    /// This is the end reason of result constructed by
+    /// [`construct_result_so_far`](crate::TypingEngine::construct_result_so_far()) while typing,
+    /// and targets after last key stroke are not counted in statistics.
+    InProgress,
+}
@@ -8,10 +8,31 @@ pub fn is_hiragana(c: char) -> bool {
     matches!(c,'\u{3041}'..='\u{308f}' | '\u{3092}'..='\u{3094}')
 }
 
+// ひらがなに対応するカタカナかどうか
+pub fn is_katakana(c: char) -> bool {
+    matches!(c,'\u{30a1}'..='\u{30ef}' | '\u{30f2}'..='\u{30f4}')
+}
+
+// カタカナを対応するひらがなに変換する
+// カタカナ以外はそのまま返す
+pub fn katakana_to_hiragana(c: char) -> char {
+    if is_katakana(c) {
+        char::from_u32(c as u32 - 0x60).unwrap()
+    } else {
+        c
+    }
+}
+
+// 漢字かどうか
+// 踊り字や助数詞に使う記号も漢字とみなす
+pub fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' | '々' | '〆' | 'ヶ')
+}
+
 pub fn is_japanese_symbol(c: char) -> bool {
     matches!(c,
         // 全角ダブルクオーテーション・全角シングルクオーテーション
-        '\u{2019}' | '\u{201d}' |
+        '\u{2018}'..='\u{2019}' | '\u{201c}'..='\u{201d}' |
         // 全角スペース・読点・句点
         '\u{3000}'..='\u{3002}' |
         // 鉤括弧
@@ -20,20 +41,8 @@ pub fn is_japanese_symbol(c: char) -> bool {
         '\u{301c}' |
         // 中黒・全角バー
         '\u{30fb}'..='\u{30fc}' |
-        // 全角エクスクラメーションマーク
-        '\u{ff01}' |
-        // 全角シャープ・全角ドルマーク・全角パーセント・全角アンパサンド
-        '\u{ff03}'..='\u{ff06}' |
-        // 全角丸括弧・全角アスタリスク・全角プラス
-        '\u{ff08}'..='\u{ff0b}' |
-        // 全角スラッシュ
-        '\u{ff0f}' |
-        // 全角コロン・全角セミコロン・全角山括弧・全角イコール・全角クエスチョンマーク・全角アットマーク
-        '\u{ff1a}'..='\u{ff20}' |
-        // 全角ハット・全角アンダーバー・全角バッククオート
-        '\u{ff3e}'..='\u{ff40}' |
-        // 全角波括弧・全角バーティカルバー
-        '\u{ff5b}'..='\u{ff5d}' |
+        // 全角の英数字・記号
+        '\u{ff01}'..='\u{ff5e}' |
         // 全角円マーク
         '\u{ffe5}'
     )
@@ -1,10 +1,27 @@
 use std::num::NonZeroUsize;
 
+use serde::{Deserialize, Serialize};
+
 use crate::chunk::Chunk;
-use crate::chunk_key_stroke_dictionary::CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY;
+use crate::input_scheme::InputScheme;
 use crate::spell::SpellString;
+use crate::utility::katakana_to_hiragana;
+
+#[cfg(feature = "import")]
+pub use import::{
+    import_vocabularies_from_csv, import_vocabularies_from_json, import_vocabularies_from_tsv,
+};
+pub use parser::{parse_vocabulary_entry, parse_vocabulary_file, VocabularyParseError};
+pub use ruby::{parse_aozora_ruby, parse_html_ruby};
+
+use parser::escape;
+
+#[cfg(feature = "import")]
+mod import;
+mod parser;
+mod ruby;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 /// Each spells of a vocabulary.
 /// [`Vec<VocabularySpellElement>`] represents spells for single vocabulary.
 pub enum VocabularySpellElement {
@@ -34,11 +51,83 @@ impl VocabularySpellElement {
     }
 }
 
+/// References to media assets associated with a vocabulary.
+///
+/// Each reference is an identifier defined by application such as file name, so this crate does
+/// not interpret it.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VocabularyAssets {
+    audio_id: Option<String>,
+    image_id: Option<String>,
+}
+
+impl VocabularyAssets {
+    /// Construct a new [`VocabularyAssets`].
+    ///
+    /// `audio_id` refers to audio such as reading of vocabulary and `image_id` refers to image
+    /// such as picture of vocabulary.
+    pub fn new(audio_id: Option<String>, image_id: Option<String>) -> Self {
+        Self { audio_id, image_id }
+    }
+
+    pub fn audio_id(&self) -> Option<&str> {
+        self.audio_id.as_deref()
+    }
+
+    pub fn image_id(&self) -> Option<&str> {
+        self.image_id.as_deref()
+    }
+}
+
 /// An vocabulary for used in query.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "VocabularyEntryData", into = "VocabularyEntryData")]
 pub struct VocabularyEntry {
     view: String,
     spells: Vec<VocabularySpellElement>,
+    needs_trailing_separator: bool,
+    assets: VocabularyAssets,
+    presentation_spell: Option<String>,
+}
+
+// デシリアライズ時にも語彙の条件を検証するための中間表現
+#[derive(Serialize, Deserialize)]
+struct VocabularyEntryData {
+    view: String,
+    spells: Vec<VocabularySpellElement>,
+    needs_trailing_separator: bool,
+    assets: VocabularyAssets,
+    presentation_spell: Option<String>,
+}
+
+impl TryFrom<VocabularyEntryData> for VocabularyEntry {
+    type Error = &'static str;
+
+    fn try_from(data: VocabularyEntryData) -> Result<Self, Self::Error> {
+        let vocabulary_entry = Self::new(data.view, data.spells)
+            .ok_or("view does not match spells")?
+            .with_trailing_separator(data.needs_trailing_separator)
+            .with_assets(data.assets);
+
+        match data.presentation_spell {
+            Some(presentation_spell) => vocabulary_entry
+                .with_presentation_spell(presentation_spell)
+                .ok_or("presentation spell does not match spells"),
+            None => Ok(vocabulary_entry),
+        }
+    }
+}
+
+impl From<VocabularyEntry> for VocabularyEntryData {
+    fn from(vocabulary_entry: VocabularyEntry) -> Self {
+        Self {
+            view: vocabulary_entry.view,
+            spells: vocabulary_entry.spells,
+            needs_trailing_separator: vocabulary_entry.needs_trailing_separator,
+            assets: vocabulary_entry.assets,
+            presentation_spell: vocabulary_entry.presentation_spell,
+        }
+    }
 }
 
 impl VocabularyEntry {
@@ -62,8 +151,39 @@ impl VocabularyEntry {
         if view.chars().count() != view_count {
             None
         } else {
-            Some(Self { view, spells })
+            Some(Self {
+                view,
+                spells,
+                needs_trailing_separator: true,
+                assets: VocabularyAssets::default(),
+                presentation_spell: None,
+            })
+        }
+    }
+
+    /// Construct a [`VocabularyEntry`] from text which can be typed as it is.
+    ///
+    /// Each character of `text` is used as spell of itself, so callers do not need to enumerate
+    /// [`VocabularySpellElement`] for text without kanji such as `"きょうはいいてんき"`.
+    /// Katakana is typed as corresponding hiragana.
+    ///
+    /// If `text` is empty or contains characters which cannot be used as spells, this function
+    /// returns `None`.
+    pub fn from_kana_text(text: &str) -> Option<Self> {
+        if text.is_empty() {
+            return None;
         }
+
+        let spells = text
+            .chars()
+            .map(|c| {
+                SpellString::try_from(c.to_string())
+                    .ok()
+                    .map(VocabularySpellElement::Normal)
+            })
+            .collect::<Option<Vec<VocabularySpellElement>>>()?;
+
+        Self::new(text.to_string(), spells)
     }
 
     pub fn view(&self) -> &str {
@@ -74,8 +194,101 @@ impl VocabularyEntry {
         &self.spells
     }
 
+    /// Configure whether [`VocabularySeparator`](crate::VocabularySeparator) is inserted after
+    /// this vocabulary.
+    ///
+    /// Separator is inserted by default. This is useful for vocabularies which are already
+    /// terminated by themselves such as sentences ending with `。`.
+    pub fn with_trailing_separator(mut self, needs_trailing_separator: bool) -> Self {
+        self.needs_trailing_separator = needs_trailing_separator;
+        self
+    }
+
+    /// Whether [`VocabularySeparator`](crate::VocabularySeparator) is inserted after this
+    /// vocabulary.
+    pub fn needs_trailing_separator(&self) -> bool {
+        self.needs_trailing_separator
+    }
+
+    /// Attach references to media assets to this vocabulary.
+    ///
+    /// Assets of vocabulary currently typed can be obtained from
+    /// [`DisplayInfo`](crate::DisplayInfo), so they never get out of sync with query even if
+    /// vocabularies are selected randomly.
+    pub fn with_assets(mut self, assets: VocabularyAssets) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    pub fn assets(&self) -> &VocabularyAssets {
+        &self.assets
+    }
+
+    /// Configure spell presented to user instead of spell to be typed.
+    ///
+    /// Presentation spell is used only for [`SpellDisplayInfo`](crate::display_info::SpellDisplayInfo)
+    /// and key strokes are still constructed from `spells`.
+    /// This is useful for katakana practice, ex. presenting `コーヒー` while typing `こーひー`.
+    ///
+    /// Each character of presentation spell corresponds to each character of spell, so this
+    /// method returns `None` if character counts of them are different.
+    pub fn with_presentation_spell(mut self, presentation_spell: String) -> Option<Self> {
+        if presentation_spell.chars().count() != self.construct_spell_string().chars().count() {
+            None
+        } else {
+            self.presentation_spell.replace(presentation_spell);
+            Some(self)
+        }
+    }
+
+    /// Spell presented to user if configured by
+    /// [`with_presentation_spell`](Self::with_presentation_spell()).
+    pub fn presentation_spell(&self) -> Option<&str> {
+        self.presentation_spell.as_deref()
+    }
+
+    /// Format this vocabulary in the format which can be parsed by
+    /// [`parse_vocabulary_entry`].
+    ///
+    /// Characters which have special meaning in the format are escaped.
+    /// Configurations other than view and spells, such as assets, are not contained.
+    pub fn to_parseable_string(&self) -> String {
+        let mut view_chars = self.view.chars();
+        let mut view = String::new();
+        let mut spells: Vec<String> = vec![];
+
+        self.spells.iter().for_each(|spell| match spell {
+            VocabularySpellElement::Normal(spell) => {
+                view.push_str(&escape(&view_chars.next().unwrap().to_string()));
+                spells.push(escape(spell));
+            }
+            VocabularySpellElement::Compound((spell, view_count)) => {
+                let compound_view: String = view_chars.by_ref().take(view_count.get()).collect();
+                view.push_str(&format!("[{}]", escape(&compound_view)));
+                spells.push(escape(spell));
+            }
+        });
+
+        format!("{}:{}", view, spells.join(","))
+    }
+
     // 語彙全体の綴りを構築する
     // 表示文字列の各文字に対しての綴りをつなげたもの
+    /// Estimate minimum count of key strokes to type this vocabulary with `input_scheme`.
+    ///
+    /// This is an estimation because key strokes of the last chunk can depend on the following
+    /// vocabulary in query. ex. `っ` can be typed with a single key stroke of the next chunk head.
+    ///
+    /// If this vocabulary cannot be typed with `input_scheme`, this method returns `None`.
+    pub fn estimate_min_key_stroke_count(&self, input_scheme: &InputScheme) -> Option<usize> {
+        Some(
+            self.construct_chunks(input_scheme)?
+                .iter()
+                .map(|chunk| chunk.estimate_min_key_stroke_count(input_scheme))
+                .sum(),
+        )
+    }
+
     pub(crate) fn construct_spell_string(&self) -> SpellString {
         let mut s = String::new();
 
@@ -113,49 +326,79 @@ impl VocabularyEntry {
             spell: self.construct_spell_string(),
             view_position_of_spell,
             chunk_count,
+            assets: self.assets.clone(),
+            presentation_spell: self.presentation_spell.clone(),
+            trailing_line_break: false,
         }
     }
 
     // 語彙からチャンク列を構築する
     // この段階ではそれぞれのチャンクに対するキーストローク候補は設定しない
-    pub(crate) fn construct_chunks(&self) -> Vec<Chunk> {
-        let mut chunks = Vec::<Chunk>::new();
+    // 入力方式で入力できない綴りが含まれている場合には構築できない
+    pub(crate) fn construct_chunks(&self, input_scheme: &InputScheme) -> Option<Vec<Chunk>> {
+        construct_chunks_of_spell(&self.construct_spell_string(), input_scheme)
+    }
+}
 
-        let spell_chars: Vec<char> = self.construct_spell_string().chars().collect();
+// 綴りからチャンク列を構築する
+// 入力方式で入力できない綴りが含まれている場合には構築できない
+pub(crate) fn construct_chunks_of_spell(
+    spell: &SpellString,
+    input_scheme: &InputScheme,
+) -> Option<Vec<Chunk>> {
+    let mut chunks = Vec::<Chunk>::new();
 
-        let mut i = 0;
-        while i < spell_chars.len() {
-            // uniグラムとbiグラムの内長い方をチャンクとして採用する
-            let uni = spell_chars[i];
-            let bi = if i != spell_chars.len() - 1 {
-                let mut bi = spell_chars[i].to_string();
-                bi.push(spell_chars[i + 1]);
-                bi
-            } else {
-                String::from("")
-            };
-
-            let spell =
-                if uni.is_ascii_graphic() || uni == ' ' {
-                    i += 1;
-                    uni.to_string()
-                } else if CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY.contains_key(bi.as_str()) {
-                    i += 2;
-                    bi
-                } else {
-                    assert!(
-                        CHUNK_SPELL_TO_KEY_STROKE_DICTIONARY.contains_key(uni.to_string().as_str())
-                    );
-                    i += 1;
-                    uni.to_string()
-                }
-                .try_into()
-                .unwrap();
-
-            chunks.push(Chunk::new(spell, None, None));
-        }
+    let spell_chars = normalize_spell_chars(spell, input_scheme);
 
-        chunks
+    let mut i = 0;
+    while i < spell_chars.len() {
+        let chunk_spell_length = chunk_spell_length_at(&spell_chars, i, input_scheme)?;
+        let spell: String = spell_chars[i..(i + chunk_spell_length)].iter().collect();
+        i += chunk_spell_length;
+
+        chunks.push(Chunk::new(spell.try_into().unwrap(), None, None));
+    }
+
+    Some(chunks)
+}
+
+// チャンクを構築するために綴りを辞書で引ける文字に正規化する
+// カタカナは対応するひらがなとして扱う
+// 全角の英数字・記号は辞書にない場合には対応するASCIIとして扱う
+pub(crate) fn normalize_spell_chars(spell: &str, input_scheme: &InputScheme) -> Vec<char> {
+    spell
+        .chars()
+        .map(katakana_to_hiragana)
+        .map(|c| match c {
+            '\u{ff01}'..='\u{ff5e}' if !input_scheme.contains_spell(&c.to_string()) => {
+                char::from_u32(c as u32 - 0xfee0).unwrap()
+            }
+            _ => c,
+        })
+        .collect()
+}
+
+// 綴りの特定の位置から始まるチャンクの綴りの長さ
+// uniグラムとbiグラムの内長い方をチャンクとして採用する
+// 入力方式で入力できない綴りの場合にはNoneを返す
+pub(crate) fn chunk_spell_length_at(
+    spell_chars: &[char],
+    position: usize,
+    input_scheme: &InputScheme,
+) -> Option<usize> {
+    let uni = spell_chars[position];
+    let bi: String = spell_chars[position..spell_chars.len().min(position + 2)]
+        .iter()
+        .collect();
+
+    if uni.is_ascii_graphic() || uni == ' ' {
+        Some(1)
+    } else if bi.chars().count() == 2 && input_scheme.contains_spell(bi.as_str()) {
+        Some(2)
+    } else if input_scheme.contains_spell(uni.to_string().as_str()) {
+        Some(1)
+    } else {
+        None
     }
 }
 
@@ -176,6 +419,13 @@ impl ViewPosition {
         }
     }
 
+    pub(crate) fn first_position(&self) -> usize {
+        match self {
+            Self::Normal(position) => *position,
+            Self::Compound(positions) => *(positions.first().unwrap()),
+        }
+    }
+
     pub(crate) fn last_position(&self) -> usize {
         match self {
             Self::Normal(position) => *position,
@@ -218,6 +468,12 @@ pub(crate) struct VocabularyInfo {
     spell: SpellString,
     view_position_of_spell: Vec<ViewPosition>,
     chunk_count: NonZeroUsize,
+    assets: VocabularyAssets,
+    // 表示用の綴り
+    // 設定されていない場合には打つ綴りをそのまま表示する
+    presentation_spell: Option<String>,
+    // 語彙の後で改行するか
+    trailing_line_break: bool,
 }
 
 impl VocabularyInfo {
@@ -233,6 +489,9 @@ impl VocabularyInfo {
             spell,
             view_position_of_spell,
             chunk_count,
+            assets: VocabularyAssets::default(),
+            presentation_spell: None,
+            trailing_line_break: false,
         }
     }
 
@@ -244,9 +503,97 @@ impl VocabularyInfo {
         self.view.as_str()
     }
 
+    #[cfg(feature = "statistics")]
+    pub(crate) fn spell(&self) -> &SpellString {
+        &self.spell
+    }
+
+    pub(crate) fn assets(&self) -> &VocabularyAssets {
+        &self.assets
+    }
+
     pub(crate) fn reset_chunk_count(&mut self, chunk_count: NonZeroUsize) {
         self.chunk_count = chunk_count;
     }
+
+    // 先頭からchunk_count個のチャンクに当たるspell_count文字の綴りとそれ以降とに語彙を分割する
+    // 表示する文字列は綴りの区切りで分割できるとは限らないので両方に残す
+    pub(crate) fn split_at(&self, chunk_count: NonZeroUsize, spell_count: usize) -> (Self, Self) {
+        let split_chars = |s: &str| -> (String, String) {
+            (
+                s.chars().take(spell_count).collect(),
+                s.chars().skip(spell_count).collect(),
+            )
+        };
+
+        let (former_spell, latter_spell) = split_chars(self.spell.as_str());
+        let (former_presentation_spell, latter_presentation_spell) =
+            self.presentation_spell.as_deref().map(split_chars).unzip();
+
+        (
+            Self {
+                view: self.view.clone(),
+                spell: former_spell.try_into().unwrap(),
+                view_position_of_spell: self.view_position_of_spell[..spell_count].to_vec(),
+                chunk_count,
+                assets: self.assets.clone(),
+                presentation_spell: former_presentation_spell,
+                trailing_line_break: false,
+            },
+            Self {
+                view: self.view.clone(),
+                spell: latter_spell.try_into().unwrap(),
+                view_position_of_spell: self.view_position_of_spell[spell_count..].to_vec(),
+                chunk_count: NonZeroUsize::new(self.chunk_count.get() - chunk_count.get()).unwrap(),
+                assets: self.assets.clone(),
+                presentation_spell: latter_presentation_spell,
+                trailing_line_break: self.trailing_line_break,
+            },
+        )
+    }
+
+    pub(crate) fn with_trailing_line_break(mut self, trailing_line_break: bool) -> Self {
+        self.trailing_line_break = trailing_line_break;
+        self
+    }
+
+    pub(crate) fn has_trailing_line_break(&self) -> bool {
+        self.trailing_line_break
+    }
+}
+
+// 語彙ごとにクエリの先頭からその語彙の末尾までのチャンク数を求める
+pub(crate) fn construct_vocabulary_end_chunk_counts(
+    vocabulary_infos: &[VocabularyInfo],
+) -> Vec<usize> {
+    vocabulary_infos
+        .iter()
+        .scan(0, |chunk_count, vocabulary_info| {
+            *chunk_count += vocabulary_info.chunk_count().get();
+            Some(*chunk_count)
+        })
+        .collect()
+}
+
+// クエリ全体の表示用の綴りを既存の文字列の領域を再利用して書き込む
+pub(crate) fn write_presentation_spell(
+    vocabulary_infos: &[VocabularyInfo],
+    spell_char_count: usize,
+    presentation_spell: &mut String,
+) {
+    presentation_spell.clear();
+    presentation_spell.extend(
+        vocabulary_infos
+            .iter()
+            .flat_map(|vocabulary_info| {
+                vocabulary_info
+                    .presentation_spell
+                    .as_deref()
+                    .unwrap_or(vocabulary_info.spell.as_str())
+                    .chars()
+            })
+            .take(spell_char_count),
+    );
 }
 
 pub(crate) fn construct_view_position_of_spell_positions(
@@ -274,6 +621,8 @@ pub(crate) fn construct_view_position_of_spell_positions(
 mod test {
     use crate::{gen_unprocessed_chunk, gen_vocabulary_entry};
 
+    #[cfg(not(feature = "ascii_only"))]
+    use super::VocabularyEntry;
     use super::{convert_spell_positions_to_view_positions, ViewPosition};
 
     macro_rules! equal_check_construct_chunks {
@@ -281,17 +630,20 @@ mod test {
             let ve = gen_vocabulary_entry!($vs,[$(($spell$(,$view_count)?)),*]);
 
             assert_eq!(
-                ve.construct_chunks(),
+                ve.construct_chunks(&crate::input_scheme::InputScheme::Standard)
+                    .unwrap(),
                 vec![$(gen_unprocessed_chunk!($s)),*]
             );
         };
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_chunks_from_vocabulary_entry_1() {
         equal_check_construct_chunks!(("今日", [("きょう", 2)]), ["きょ", "う"]);
     }
 
+    #[cfg(not(feature = "ascii_only"))]
     #[test]
     fn construct_chunks_from_vocabulary_entry_2() {
         equal_check_construct_chunks!((" 　", [(" "), ("　")]), [" ", "　"]);
@@ -302,6 +654,46 @@ mod test {
         equal_check_construct_chunks!(("big", [("b"), ("i"), ("g")]), ["b", "i", "g"]);
     }
 
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_chunks_from_vocabulary_entry_4() {
+        equal_check_construct_chunks!(
+            ("キャンプ", [("キャ", 2), ("ン"), ("プ")]),
+            ["きゃ", "ん", "ぷ"]
+        );
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_chunks_from_vocabulary_entry_5() {
+        // 辞書にある全角記号はそのまま，ない全角英数字はASCIIとして扱う
+        equal_check_construct_chunks!(("Ａ１！", [("Ａ"), ("１"), ("！")]), ["A", "1", "！"]);
+    }
+
+    #[cfg(not(feature = "ascii_only"))]
+    #[test]
+    fn construct_vocabulary_entry_from_kana_text() {
+        let ve = VocabularyEntry::from_kana_text("きょうはキャンプ").unwrap();
+
+        assert_eq!(ve.view(), "きょうはキャンプ");
+        assert_eq!(ve.spells().len(), 8);
+        assert_eq!(
+            ve.construct_chunks(&crate::input_scheme::InputScheme::Standard)
+                .unwrap(),
+            vec![
+                gen_unprocessed_chunk!("きょ"),
+                gen_unprocessed_chunk!("う"),
+                gen_unprocessed_chunk!("は"),
+                gen_unprocessed_chunk!("きゃ"),
+                gen_unprocessed_chunk!("ん"),
+                gen_unprocessed_chunk!("ぷ")
+            ]
+        );
+
+        assert_eq!(VocabularyEntry::from_kana_text("今日"), None);
+        assert_eq!(VocabularyEntry::from_kana_text(""), None);
+    }
+
     #[test]
     fn convert_spell_positions_to_view_positions_1() {
         let vp = convert_spell_positions_to_view_positions(
@@ -0,0 +1,263 @@
+use std::num::NonZeroUsize;
+
+use serde::Deserialize;
+
+use crate::vocabulary::parser::{construct_spell, VocabularyParseErrorKind};
+use crate::vocabulary::{VocabularyEntry, VocabularyParseError, VocabularySpellElement};
+
+// 取り込めた語彙と取り込めなかった行番号や位置ごとのエラー
+type ImportedVocabularies = (Vec<VocabularyEntry>, Vec<(usize, VocabularyParseError)>);
+
+/// Import vocabularies from CSV in which each row is a vocabulary.
+///
+/// The first column is view and the following columns are readings.
+/// When a row has a single reading, it is the reading of the whole view.
+/// Otherwise, each reading corresponds to each character of view.
+/// Fields can be quoted by `"` and `""` in quoted field represents `"`, but a field cannot
+/// contain line breaks.
+///
+/// Blank lines are skipped.
+/// Rows which cannot be imported are also skipped and returned with their line numbers, which
+/// start from 1, like [`parse_vocabulary_file`](crate::parse_vocabulary_file).
+pub fn import_vocabularies_from_csv(content: &str) -> ImportedVocabularies {
+    import_vocabularies_from_rows(content, split_csv_row)
+}
+
+/// Import vocabularies from TSV in which each row is a vocabulary.
+///
+/// Columns are the same as [`import_vocabularies_from_csv`], but fields are separated by tab and
+/// never quoted.
+pub fn import_vocabularies_from_tsv(content: &str) -> ImportedVocabularies {
+    import_vocabularies_from_rows(content, |line| {
+        Ok(line.split('\t').map(|field| field.to_string()).collect())
+    })
+}
+
+/// Import vocabularies from JSON array of objects which have `view` and `readings`.
+///
+/// `readings` is an array of readings or a single reading string.
+/// Readings correspond to view in the same way as [`import_vocabularies_from_csv`].
+/// ex. `[{"view": "今日", "readings": "きょう"}, {"view": "巨大", "readings": ["きょ", "だい"]}]`
+///
+/// Elements which cannot be imported are skipped and returned with their indices in array.
+/// If `content` is not an array in JSON, this function returns error.
+pub fn import_vocabularies_from_json(
+    content: &str,
+) -> Result<ImportedVocabularies, VocabularyParseError> {
+    let elements: Vec<serde_json::Value> = serde_json::from_str(content)
+        .map_err(|_| VocabularyParseError::new(VocabularyParseErrorKind::InvalidJson))?;
+
+    let mut vocabulary_entries = vec![];
+    let mut errors = vec![];
+
+    elements.into_iter().enumerate().for_each(|(i, element)| {
+        let vocabulary_entry = serde_json::from_value::<JsonVocabulary>(element)
+            .map_err(|_| VocabularyParseError::new(VocabularyParseErrorKind::InvalidJson))
+            .and_then(|json_vocabulary| {
+                let readings = match json_vocabulary.readings {
+                    JsonReadings::Single(reading) => vec![reading],
+                    JsonReadings::Multiple(readings) => readings,
+                };
+
+                construct_vocabulary_entry(json_vocabulary.view, readings)
+            });
+
+        match vocabulary_entry {
+            Ok(vocabulary_entry) => vocabulary_entries.push(vocabulary_entry),
+            Err(e) => errors.push((i, e)),
+        }
+    });
+
+    Ok((vocabulary_entries, errors))
+}
+
+#[derive(Deserialize)]
+struct JsonVocabulary {
+    view: String,
+    readings: JsonReadings,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonReadings {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+// 1行1語彙の形式から語彙を取り込む
+fn import_vocabularies_from_rows(
+    content: &str,
+    split_row: impl Fn(&str) -> Result<Vec<String>, VocabularyParseError>,
+) -> ImportedVocabularies {
+    let mut vocabulary_entries = vec![];
+    let mut errors = vec![];
+
+    content.lines().enumerate().for_each(|(i, line)| {
+        if line.trim().is_empty() {
+            return;
+        }
+
+        let vocabulary_entry = split_row(line).and_then(|mut fields| {
+            let view = fields.remove(0);
+            construct_vocabulary_entry(view, fields)
+        });
+
+        match vocabulary_entry {
+            Ok(vocabulary_entry) => vocabulary_entries.push(vocabulary_entry),
+            Err(e) => errors.push((i + 1, e)),
+        }
+    });
+
+    (vocabulary_entries, errors)
+}
+
+// CSVの1行をフィールドに分ける
+fn split_csv_row(line: &str) -> Result<Vec<String>, VocabularyParseError> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut is_quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, is_quoted) {
+            ('"', true) => {
+                // 引用符内で連続する引用符は引用符そのものを表す
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    is_quoted = false;
+                }
+            }
+            ('"', false) if field.is_empty() => is_quoted = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+
+    if is_quoted {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::UnclosedQuote,
+        ));
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
+
+// 表示文字列と読みから語彙を構築する
+// 読みが1つなら表示文字列全体の読みとし，そうでなければ表示文字列の各文字の読みとする
+fn construct_vocabulary_entry(
+    view: String,
+    readings: Vec<String>,
+) -> Result<VocabularyEntry, VocabularyParseError> {
+    if readings.is_empty() {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::MissingReading,
+        ));
+    }
+
+    let view_count = view.chars().count();
+    if view_count == 0 {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::EmptyViewElement,
+        ));
+    }
+
+    let spells = readings
+        .into_iter()
+        .map(construct_spell)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let spell_elements = if spells.len() == 1 && view_count != 1 {
+        vec![VocabularySpellElement::Compound((
+            spells.into_iter().next().unwrap(),
+            NonZeroUsize::new(view_count).unwrap(),
+        ))]
+    } else if spells.len() == view_count {
+        spells
+            .into_iter()
+            .map(VocabularySpellElement::Normal)
+            .collect()
+    } else {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::ElementCountMismatch,
+        ));
+    };
+
+    Ok(VocabularyEntry::new(view, spell_elements).unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::gen_vocabulary_entry;
+
+    #[test]
+    fn import_vocabularies_from_csv_1() {
+        let (vocabulary_entries, errors) = import_vocabularies_from_csv(
+            "今日,きょう\n巨大,きょ,だい\n\n\"a,b\",a,\",\",b\n巨大,き,ょ,だい\n\"今日,きょう\r\n",
+        );
+
+        assert_eq!(
+            vocabulary_entries,
+            vec![
+                gen_vocabulary_entry!("今日", [("きょう", 2)]),
+                gen_vocabulary_entry!("巨大", [("きょ"), ("だい")]),
+                gen_vocabulary_entry!("a,b", [("a"), (","), ("b")]),
+            ]
+        );
+        assert_eq!(
+            errors
+                .iter()
+                .map(|(line_number, e)| (*line_number, e.to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    5,
+                    VocabularyParseErrorKind::ElementCountMismatch.to_string()
+                ),
+                (6, VocabularyParseErrorKind::UnclosedQuote.to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn import_vocabularies_from_tsv_1() {
+        let (vocabulary_entries, errors) =
+            import_vocabularies_from_tsv("今日\tきょう\n\"\t\"\n巨大\n");
+
+        assert_eq!(
+            vocabulary_entries,
+            vec![
+                gen_vocabulary_entry!("今日", [("きょう", 2)]),
+                gen_vocabulary_entry!("\"", [("\"")]),
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 3);
+    }
+
+    #[test]
+    fn import_vocabularies_from_json_1() {
+        let (vocabulary_entries, errors) = import_vocabularies_from_json(
+            r#"[{"view": "今日", "readings": "きょう"}, {"view": "巨大", "readings": ["きょ", "だい"]}, {"view": "巨大"}, {"view": "巨大", "readings": ["漢", "だい"]}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vocabulary_entries,
+            vec![
+                gen_vocabulary_entry!("今日", [("きょう", 2)]),
+                gen_vocabulary_entry!("巨大", [("きょ"), ("だい")]),
+            ]
+        );
+        assert_eq!(
+            errors.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        assert!(import_vocabularies_from_json(r#"{"view": "今日"}"#).is_err());
+    }
+}
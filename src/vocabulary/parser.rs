@@ -0,0 +1,383 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::num::NonZeroUsize;
+
+use crate::spell::SpellString;
+use crate::vocabulary::{VocabularyEntry, VocabularySpellElement};
+
+/// An error of parsing a vocabulary by [`parse_vocabulary_entry`] or importing vocabularies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VocabularyParseError {
+    pub(crate) kind: VocabularyParseErrorKind,
+}
+
+impl VocabularyParseError {
+    pub(crate) fn new(kind: VocabularyParseErrorKind) -> Self {
+        Self { kind }
+    }
+}
+
+impl Display for VocabularyParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Error for VocabularyParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum VocabularyParseErrorKind {
+    SeparatorNotFound,
+    TrailingEscape,
+    UnexpectedCharacter(char),
+    UnclosedBracket,
+    EmptyViewElement,
+    EmptySpell,
+    InvalidSpell(char),
+    ElementCountMismatch,
+    UnclosedRuby,
+    UnclosedTag,
+    EmptyRubyBase,
+    #[cfg(feature = "import")]
+    UnclosedQuote,
+    #[cfg(feature = "import")]
+    MissingReading,
+    #[cfg(feature = "import")]
+    InvalidJson,
+}
+
+impl Display for VocabularyParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use VocabularyParseErrorKind::*;
+
+        match self {
+            SeparatorNotFound => write!(f, "separator `:` between view and spells not found"),
+            TrailingEscape => write!(f, "escape character `\\` at end of line"),
+            UnexpectedCharacter(c) => write!(f, "unexpected `{}` which must be escaped", c),
+            UnclosedBracket => write!(f, "bracket not closed"),
+            EmptyViewElement => write!(f, "empty bracket in view"),
+            EmptySpell => write!(f, "empty spell"),
+            InvalidSpell(c) => write!(f, "`{}` cannot be used as a spell", c),
+            ElementCountMismatch => {
+                write!(f, "count of spells does not match count of view elements")
+            }
+            UnclosedRuby => write!(f, "ruby annotation not closed"),
+            UnclosedTag => write!(f, "tag not closed"),
+            EmptyRubyBase => write!(f, "ruby annotation without base characters"),
+            #[cfg(feature = "import")]
+            UnclosedQuote => write!(f, "quote not closed"),
+            #[cfg(feature = "import")]
+            MissingReading => write!(f, "reading not found"),
+            #[cfg(feature = "import")]
+            InvalidJson => write!(f, "invalid json"),
+        }
+    }
+}
+
+// エスケープを解決した文字とそれがエスケープされていたかどうか
+type ParsedChar = (char, bool);
+
+/// Parse a vocabulary written in `view:spell,spell,...` format.
+///
+/// Each character of view corresponds to each spell separated by `,`.
+/// Characters enclosed by `[` and `]` in view correspond to a single spell as
+/// [`VocabularySpellElement::Compound`].
+/// `:`, `[`, `]`, `,` and `\` used as a part of view or spell must be escaped by `\`.
+///
+/// For example,
+/// * `巨大:きょ,だい` is `巨大` spelled as `きょ` and `だい`
+/// * `[今日]は:きょう,は` is `今日は` and `今日` is spelled as `きょう`
+/// * `\:\,:\:,\,` is `:,` spelled as `:` and `,`
+pub fn parse_vocabulary_entry(line: &str) -> Result<VocabularyEntry, VocabularyParseError> {
+    let chars = unescape(line)?;
+
+    let separator_position = chars
+        .iter()
+        .position(|(c, is_escaped)| *c == ':' && !is_escaped)
+        .ok_or(VocabularyParseError::new(
+            VocabularyParseErrorKind::SeparatorNotFound,
+        ))?;
+
+    let view_elements = parse_view(&chars[..separator_position])?;
+    let spells = parse_spells(&chars[(separator_position + 1)..])?;
+
+    if view_elements.len() != spells.len() {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::ElementCountMismatch,
+        ));
+    }
+
+    Ok(assemble_vocabulary_entry(&view_elements, spells))
+}
+
+// 表示文字列の各要素とそれに対応する綴りから語彙を構築する
+// 複数文字の要素は1つの綴りを共有する
+pub(crate) fn assemble_vocabulary_entry(
+    view_elements: &[String],
+    spells: Vec<SpellString>,
+) -> VocabularyEntry {
+    assert_eq!(view_elements.len(), spells.len());
+
+    let view: String = view_elements.concat();
+    let spell_elements = view_elements
+        .iter()
+        .zip(spells)
+        .map(|(view_element, spell)| {
+            if view_element.chars().count() == 1 {
+                VocabularySpellElement::Normal(spell)
+            } else {
+                VocabularySpellElement::Compound((
+                    spell,
+                    NonZeroUsize::new(view_element.chars().count()).unwrap(),
+                ))
+            }
+        })
+        .collect();
+
+    // 表示文字列の各要素に綴りを対応させているので文字数は必ず一致する
+    VocabularyEntry::new(view, spell_elements).unwrap()
+}
+
+/// Parse a word list in which each line is a vocabulary written in the format of
+/// [`parse_vocabulary_entry`].
+///
+/// Blank lines and comment lines starting with `#` are skipped.
+/// Lines which cannot be parsed are also skipped and returned with their line numbers, which
+/// start from 1, so a single wrong line does not prevent loading the whole list.
+pub fn parse_vocabulary_file(
+    content: &str,
+) -> (Vec<VocabularyEntry>, Vec<(usize, VocabularyParseError)>) {
+    let mut vocabulary_entries = vec![];
+    let mut errors = vec![];
+
+    content.lines().enumerate().for_each(|(i, line)| {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            return;
+        }
+
+        match parse_vocabulary_entry(line) {
+            Ok(vocabulary_entry) => vocabulary_entries.push(vocabulary_entry),
+            Err(e) => errors.push((i + 1, e)),
+        }
+    });
+
+    (vocabulary_entries, errors)
+}
+
+// 書式上特別な意味を持つ文字をエスケープする
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+
+    s.chars().for_each(|c| {
+        if matches!(c, ':' | '[' | ']' | ',' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    });
+
+    escaped
+}
+
+// エスケープを解決する
+fn unescape(line: &str) -> Result<Vec<ParsedChar>, VocabularyParseError> {
+    let mut chars = vec![];
+
+    let mut iter = line.chars();
+    while let Some(c) = iter.next() {
+        if c == '\\' {
+            let escaped = iter.next().ok_or(VocabularyParseError::new(
+                VocabularyParseErrorKind::TrailingEscape,
+            ))?;
+            chars.push((escaped, true));
+        } else {
+            chars.push((c, false));
+        }
+    }
+
+    Ok(chars)
+}
+
+// 表示文字列を綴りが対応する要素ごとに分ける
+// 括弧で囲まれた部分は1つの要素になる
+fn parse_view(chars: &[ParsedChar]) -> Result<Vec<String>, VocabularyParseError> {
+    let mut view_elements = vec![];
+    let mut compound: Option<String> = None;
+
+    for (c, is_escaped) in chars {
+        match (c, is_escaped, compound.as_mut()) {
+            ('[', false, None) => {
+                compound.replace(String::new());
+            }
+            (']', false, Some(_)) => {
+                let element = compound.take().unwrap();
+                if element.is_empty() {
+                    return Err(VocabularyParseError::new(
+                        VocabularyParseErrorKind::EmptyViewElement,
+                    ));
+                }
+                view_elements.push(element);
+            }
+            ('[' | ']' | ',', false, _) => {
+                return Err(VocabularyParseError::new(
+                    VocabularyParseErrorKind::UnexpectedCharacter(*c),
+                ));
+            }
+            (_, _, Some(element)) => element.push(*c),
+            (_, _, None) => view_elements.push(c.to_string()),
+        }
+    }
+
+    if compound.is_some() {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::UnclosedBracket,
+        ));
+    }
+
+    Ok(view_elements)
+}
+
+// 綴りを区切り文字で分ける
+fn parse_spells(chars: &[ParsedChar]) -> Result<Vec<SpellString>, VocabularyParseError> {
+    let mut spells = vec![];
+    let mut spell = String::new();
+
+    for (c, is_escaped) in chars {
+        match (c, is_escaped) {
+            (',', false) => spells.push(std::mem::take(&mut spell)),
+            (':' | '[' | ']', false) => {
+                return Err(VocabularyParseError::new(
+                    VocabularyParseErrorKind::UnexpectedCharacter(*c),
+                ));
+            }
+            _ => spell.push(*c),
+        }
+    }
+    spells.push(spell);
+
+    spells.into_iter().map(construct_spell).collect()
+}
+
+// 綴りとして使えるかを検証する
+pub(crate) fn construct_spell(spell: String) -> Result<SpellString, VocabularyParseError> {
+    if spell.is_empty() {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::EmptySpell,
+        ));
+    }
+
+    let invalid_char = spell
+        .chars()
+        .find(|c| SpellString::try_from(c.to_string()).is_err());
+
+    match invalid_char {
+        Some(c) => Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::InvalidSpell(c),
+        )),
+        None => Ok(spell.try_into().unwrap()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::gen_vocabulary_entry;
+
+    #[test]
+    fn parse_vocabulary_entry_1() {
+        assert_eq!(
+            parse_vocabulary_entry("巨大:きょ,だい").unwrap(),
+            gen_vocabulary_entry!("巨大", [("きょ"), ("だい")])
+        );
+        assert_eq!(
+            parse_vocabulary_entry("珈琲:コー,ヒー").unwrap(),
+            gen_vocabulary_entry!("珈琲", [("コー"), ("ヒー")])
+        );
+        assert_eq!(
+            parse_vocabulary_entry("[七夕]送り:たなばた,おく,り").unwrap(),
+            gen_vocabulary_entry!("七夕送り", [("たなばた", 2), ("おく"), ("り")])
+        );
+        assert_eq!(
+            parse_vocabulary_entry("\\:\\,\\\\:\\:,\\,,\\\\").unwrap(),
+            gen_vocabulary_entry!(":,\\", [(":"), (","), ("\\")])
+        );
+    }
+
+    #[test]
+    fn parse_vocabulary_entry_2() {
+        let kind = |line: &str| parse_vocabulary_entry(line).unwrap_err().kind;
+
+        assert_eq!(kind("巨大"), VocabularyParseErrorKind::SeparatorNotFound);
+        assert_eq!(
+            kind("巨大:きょ,だい\\"),
+            VocabularyParseErrorKind::TrailingEscape
+        );
+        assert_eq!(
+            kind("[今日:きょう"),
+            VocabularyParseErrorKind::UnclosedBracket
+        );
+        assert_eq!(
+            kind("[]:きょう"),
+            VocabularyParseErrorKind::EmptyViewElement
+        );
+        assert_eq!(kind("巨大:きょ,"), VocabularyParseErrorKind::EmptySpell);
+        assert_eq!(
+            kind("巨大:きょ"),
+            VocabularyParseErrorKind::ElementCountMismatch
+        );
+        assert_eq!(
+            kind("巨大:漢,だい"),
+            VocabularyParseErrorKind::InvalidSpell('漢')
+        );
+        assert_eq!(
+            kind("a,b:a,b"),
+            VocabularyParseErrorKind::UnexpectedCharacter(',')
+        );
+    }
+
+    #[test]
+    fn to_parseable_string_round_trip() {
+        let vocabulary_entries = [
+            gen_vocabulary_entry!("七夕送り", [("たなばた", 2), ("おく"), ("り")]),
+            gen_vocabulary_entry!("今日:", [("きょう", 2), (":")]),
+            gen_vocabulary_entry!("a,b", [("a"), (","), ("b")]),
+            gen_vocabulary_entry!("\\", [("\\")]),
+        ];
+
+        assert_eq!(
+            vocabulary_entries[0].to_parseable_string(),
+            "[七夕]送り:たなばた,おく,り"
+        );
+        assert_eq!(
+            vocabulary_entries[1].to_parseable_string(),
+            "[今日]\\::きょう,\\:"
+        );
+
+        vocabulary_entries.iter().for_each(|vocabulary_entry| {
+            assert_eq!(
+                &parse_vocabulary_entry(&vocabulary_entry.to_parseable_string()).unwrap(),
+                vocabulary_entry
+            );
+        });
+    }
+
+    #[test]
+    fn parse_vocabulary_file_1() {
+        let (vocabulary_entries, errors) =
+            parse_vocabulary_file("# 単語リスト\n巨大:きょ,だい\n\n巨大:きょ\r\n[今日]:きょう\r\n");
+
+        assert_eq!(
+            vocabulary_entries,
+            vec![
+                gen_vocabulary_entry!("巨大", [("きょ"), ("だい")]),
+                gen_vocabulary_entry!("今日", [("きょう", 2)])
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 4);
+        assert_eq!(
+            errors[0].1.kind,
+            VocabularyParseErrorKind::ElementCountMismatch
+        );
+    }
+}
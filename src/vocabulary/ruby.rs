@@ -0,0 +1,249 @@
+use std::str::Chars;
+
+use crate::spell::SpellString;
+use crate::utility::is_kanji;
+use crate::vocabulary::parser::{
+    assemble_vocabulary_entry, construct_spell, VocabularyParseErrorKind,
+};
+use crate::vocabulary::{VocabularyEntry, VocabularyParseError};
+
+/// Parse a vocabulary annotated with ruby in Aozora Bunko format.
+///
+/// Reading enclosed by `《` and `》` is a spell of kanji just before it, and the other
+/// characters are spells of themselves.
+/// When base characters of ruby are not only kanji, start of them is marked by `｜`.
+///
+/// For example,
+/// * `昨日《きのう》のこと` is `昨日のこと` and `昨日` is spelled as `きのう`
+/// * `｜青い鳥《あおいとり》` is `青い鳥` spelled as `あおいとり`
+///
+/// Kanji without ruby cannot be typed, so it is an error.
+pub fn parse_aozora_ruby(text: &str) -> Result<VocabularyEntry, VocabularyParseError> {
+    let mut ruby_parser = RubyParser::default();
+    // 綴りをまだ対応させていない文字
+    let mut pending = String::new();
+    let mut has_explicit_base = false;
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '｜' | '|' => {
+                ruby_parser.push_plain_text(&std::mem::take(&mut pending))?;
+                has_explicit_base = true;
+            }
+            '《' => {
+                let reading = take_until(&mut chars, '》').ok_or(VocabularyParseError::new(
+                    VocabularyParseErrorKind::UnclosedRuby,
+                ))?;
+
+                // 区切りがない場合には直前に連続する漢字を親文字とする
+                let base_start = if has_explicit_base {
+                    0
+                } else {
+                    pending
+                        .char_indices()
+                        .rev()
+                        .take_while(|(_, c)| is_kanji(*c))
+                        .last()
+                        .map_or(pending.len(), |(i, _)| i)
+                };
+
+                let base = pending.split_off(base_start);
+                ruby_parser.push_plain_text(&std::mem::take(&mut pending))?;
+                ruby_parser.push_ruby(base, reading)?;
+                has_explicit_base = false;
+            }
+            _ => pending.push(c),
+        }
+    }
+
+    ruby_parser.push_plain_text(&pending)?;
+    ruby_parser.finish()
+}
+
+/// Parse a vocabulary annotated with HTML `<ruby>` markup.
+///
+/// Text in `<rt>` is a spell of the preceding text in the same `<ruby>`, and text outside
+/// `<ruby>` is spelled as itself.
+/// A `<ruby>` can contain multiple pairs of base text and `<rt>`, and text in `<rp>` is ignored.
+/// Other tags such as `<rb>` are ignored while their text is kept.
+///
+/// For example, `<ruby>昨日<rp>(</rp><rt>きのう</rt><rp>)</rp></ruby>のこと` is `昨日のこと` and
+/// `昨日` is spelled as `きのう`.
+///
+/// Character references are not decoded.
+pub fn parse_html_ruby(text: &str) -> Result<VocabularyEntry, VocabularyParseError> {
+    let mut ruby_parser = RubyParser::default();
+    let mut in_ruby = false;
+    let mut in_rp = false;
+    let mut base = String::new();
+    let mut reading: Option<String> = None;
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            match (in_rp, reading.as_mut(), in_ruby) {
+                (true, _, _) => {}
+                (false, Some(reading), _) => reading.push(c),
+                (false, None, true) => base.push(c),
+                (false, None, false) => ruby_parser.push_plain_text(&c.to_string())?,
+            }
+            continue;
+        }
+
+        let tag = take_until(&mut chars, '>').ok_or(VocabularyParseError::new(
+            VocabularyParseErrorKind::UnclosedTag,
+        ))?;
+
+        // 属性は使わないのでタグ名だけで判断する
+        let tag_name = tag
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match tag_name.as_str() {
+            "ruby" => in_ruby = true,
+            "/ruby" => {
+                // 読みのない親文字はそのまま綴りとする
+                ruby_parser.push_plain_text(&std::mem::take(&mut base))?;
+                in_ruby = false;
+            }
+            "rt" => {
+                reading.replace(String::new());
+            }
+            "/rt" => {
+                if let Some(reading) = reading.take() {
+                    ruby_parser.push_ruby(std::mem::take(&mut base), reading)?;
+                }
+            }
+            "rp" => in_rp = true,
+            "/rp" => in_rp = false,
+            _ => {}
+        }
+    }
+
+    if in_ruby || reading.is_some() {
+        return Err(VocabularyParseError::new(
+            VocabularyParseErrorKind::UnclosedRuby,
+        ));
+    }
+
+    ruby_parser.finish()
+}
+
+// 閉じる文字までを取り出す
+// 閉じる文字がない場合にはNoneを返す
+fn take_until(chars: &mut Chars, end: char) -> Option<String> {
+    let mut taken = String::new();
+
+    for c in chars.by_ref() {
+        if c == end {
+            return Some(taken);
+        }
+        taken.push(c);
+    }
+
+    None
+}
+
+// 表示文字列の要素とその綴りを順に積み上げる
+#[derive(Default)]
+struct RubyParser {
+    view_elements: Vec<String>,
+    spells: Vec<SpellString>,
+}
+
+impl RubyParser {
+    // ルビのない文字は1文字ずつそれ自身を綴りとする
+    fn push_plain_text(&mut self, text: &str) -> Result<(), VocabularyParseError> {
+        text.chars().try_for_each(|c| {
+            self.spells.push(construct_spell(c.to_string())?);
+            self.view_elements.push(c.to_string());
+
+            Ok(())
+        })
+    }
+
+    fn push_ruby(&mut self, base: String, reading: String) -> Result<(), VocabularyParseError> {
+        if base.is_empty() {
+            return Err(VocabularyParseError::new(
+                VocabularyParseErrorKind::EmptyRubyBase,
+            ));
+        }
+
+        self.spells.push(construct_spell(reading)?);
+        self.view_elements.push(base);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<VocabularyEntry, VocabularyParseError> {
+        if self.view_elements.is_empty() {
+            return Err(VocabularyParseError::new(
+                VocabularyParseErrorKind::EmptySpell,
+            ));
+        }
+
+        Ok(assemble_vocabulary_entry(&self.view_elements, self.spells))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::gen_vocabulary_entry;
+
+    #[test]
+    fn parse_aozora_ruby_1() {
+        assert_eq!(
+            parse_aozora_ruby("昨日《きのう》の敵《てき》").unwrap(),
+            gen_vocabulary_entry!("昨日の敵", [("きのう", 2), ("の"), ("てき")])
+        );
+        assert_eq!(
+            parse_aozora_ruby("｜青い鳥《あおいとり》だ").unwrap(),
+            gen_vocabulary_entry!("青い鳥だ", [("あおいとり", 3), ("だ")])
+        );
+
+        let kind = |text: &str| parse_aozora_ruby(text).unwrap_err().kind;
+
+        assert_eq!(
+            kind("昨日の敵"),
+            VocabularyParseErrorKind::InvalidSpell('昨')
+        );
+        assert_eq!(kind("昨日《きのう"), VocabularyParseErrorKind::UnclosedRuby);
+        assert_eq!(
+            kind("の《きのう》"),
+            VocabularyParseErrorKind::EmptyRubyBase
+        );
+    }
+
+    #[test]
+    fn parse_html_ruby_1() {
+        assert_eq!(
+            parse_html_ruby(
+                "<ruby>昨日<rp>(</rp><rt>きのう</rt><rp>)</rp></ruby>の<ruby>敵<rt>てき</rt></ruby>"
+            )
+            .unwrap(),
+            gen_vocabulary_entry!("昨日の敵", [("きのう", 2), ("の"), ("てき")])
+        );
+        assert_eq!(
+            parse_html_ruby("<ruby class=\"a\"><rb>漢</rb><rt>かん</rt>字<rt>じ</rt></ruby>")
+                .unwrap(),
+            gen_vocabulary_entry!("漢字", [("かん"), ("じ")])
+        );
+
+        let kind = |text: &str| parse_html_ruby(text).unwrap_err().kind;
+
+        assert_eq!(
+            kind("<ruby>昨日<rt>きのう"),
+            VocabularyParseErrorKind::UnclosedRuby
+        );
+        assert_eq!(kind("<ruby"), VocabularyParseErrorKind::UnclosedTag);
+        assert_eq!(
+            kind("<ruby><rt>きのう</rt></ruby>"),
+            VocabularyParseErrorKind::EmptyRubyBase
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::query::{QueryRequest, VocabularyOrder, VocabularyQuantifier, VocabularySeparator};
+use crate::statistics::LapRequest;
+use crate::typing_engine::TypingEngine;
+use crate::vocabulary::{parse_vocabulary_entry, VocabularyEntry};
+
+// ブラウザではstd::time::Instantが使えないので既定の時計の生成時に失敗する
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "std_clock"))]
+compile_error!("wasm feature cannot be used with std_clock feature on wasm32-unknown-unknown");
+
+/// A JavaScript friendly facade of [`TypingEngine`].
+///
+/// Requests and results are exchanged as plain JavaScript objects, so web consumers do not need
+/// to construct nested types such as [`SpellString`](crate::SpellString) or
+/// [`NonZeroUsize`].
+/// Elapsed time is always given in milliseconds such as from `performance.now()`.
+///
+/// Query request is an object like
+/// `{ vocabularies: ["巨大:きょ,だい"], quantifier: { type: "vocabulary", count: 1 },
+/// separator: "whiteSpace", randomOrder: false }`
+/// in which each vocabulary is written in the format of
+/// [`parse_vocabulary_entry`](crate::parse_vocabulary_entry).
+/// `separator` and `randomOrder` can be omitted.
+///
+/// Lap request is an object like `{ type: "chunk", count: 1 }` and `type` is one of
+/// `keyStroke`, `idealKeyStroke`, `spell` and `chunk`.
+#[wasm_bindgen]
+pub struct WasmTypingEngine {
+    engine: TypingEngine,
+}
+
+#[wasm_bindgen]
+impl WasmTypingEngine {
+    /// Construct an empty engine.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            engine: TypingEngine::new(),
+        }
+    }
+
+    /// Initialize engine by query request.
+    pub fn init(&mut self, query_request: JsValue) -> Result<(), JsError> {
+        let query_request: WasmQueryRequest = serde_wasm_bindgen::from_value(query_request)?;
+        let vocabulary_entries = query_request.parse_vocabulary_entries()?;
+        let vocabulary_entry_refs: Vec<&VocabularyEntry> = vocabulary_entries.iter().collect();
+
+        self.engine
+            .init(query_request.construct_query_request(&vocabulary_entry_refs))?;
+
+        Ok(())
+    }
+
+    /// Start typing.
+    pub fn start(&mut self) -> Result<(), JsError> {
+        self.engine.start()?;
+
+        Ok(())
+    }
+
+    /// Give a key stroke at `elapsed_time_ms` from start and return whether typing is finished.
+    #[wasm_bindgen(js_name = strokeKey)]
+    pub fn stroke_key(&mut self, key_stroke: char, elapsed_time_ms: f64) -> Result<bool, JsError> {
+        let key_stroke = key_stroke
+            .try_into()
+            .map_err(|_| JsError::new("key stroke is not displayable ascii"))?;
+
+        Ok(self
+            .engine
+            .stroke_key_with_elapsed_time(key_stroke, duration_from_millis(elapsed_time_ms)?)?)
+    }
+
+    /// Finish typing explicitly at `elapsed_time_ms` from start.
+    pub fn finish(&mut self, elapsed_time_ms: f64) -> Result<(), JsError> {
+        self.engine.finish(duration_from_millis(elapsed_time_ms)?)?;
+
+        Ok(())
+    }
+
+    /// Construct display info at `elapsed_time_ms` from start.
+    #[wasm_bindgen(js_name = constructDisplayInfo)]
+    pub fn construct_display_info(
+        &self,
+        lap_request: JsValue,
+        elapsed_time_ms: f64,
+    ) -> Result<JsValue, JsError> {
+        let lap_request: WasmLapRequest = serde_wasm_bindgen::from_value(lap_request)?;
+
+        let display_info = self.engine.construct_display_info_with_elapsed_time(
+            lap_request.into(),
+            duration_from_millis(elapsed_time_ms)?,
+        )?;
+
+        Ok(serde_wasm_bindgen::to_value(&display_info)?)
+    }
+
+    /// Construct result statistics of finished typing.
+    #[wasm_bindgen(js_name = constructResult)]
+    pub fn construct_result(&self, lap_request: JsValue) -> Result<JsValue, JsError> {
+        let lap_request: WasmLapRequest = serde_wasm_bindgen::from_value(lap_request)?;
+
+        let result = self
+            .engine
+            .construst_result_statistics(lap_request.into())?;
+
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+}
+
+impl Default for WasmTypingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// JavaScriptから与えられたミリ秒の経過時間を変換する
+fn duration_from_millis(elapsed_time_ms: f64) -> Result<Duration, JsError> {
+    Duration::try_from_secs_f64(elapsed_time_ms / 1000.0)
+        .map_err(|_| JsError::new("elapsed time must be a non-negative finite number"))
+}
+
+// JavaScriptから与えられるクエリの要求
+// 語彙はparse_vocabulary_entryの形式の文字列で与える
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmQueryRequest {
+    vocabularies: Vec<String>,
+    quantifier: WasmVocabularyQuantifier,
+    #[serde(default)]
+    separator: WasmVocabularySeparator,
+    #[serde(default)]
+    random_order: bool,
+}
+
+impl WasmQueryRequest {
+    fn parse_vocabulary_entries(&self) -> Result<Vec<VocabularyEntry>, JsError> {
+        self.vocabularies
+            .iter()
+            .map(|vocabulary| parse_vocabulary_entry(vocabulary).map_err(JsError::from))
+            .collect()
+    }
+
+    fn construct_query_request<'vocabulary>(
+        &self,
+        vocabulary_entries: &[&'vocabulary VocabularyEntry],
+    ) -> QueryRequest<'vocabulary> {
+        let vocabulary_quantifier = match self.quantifier {
+            WasmVocabularyQuantifier::KeyStroke(count) => VocabularyQuantifier::KeyStroke(count),
+            WasmVocabularyQuantifier::Vocabulary(count) => VocabularyQuantifier::Vocabulary(count),
+        };
+
+        let vocabulary_separator = match &self.separator {
+            WasmVocabularySeparator::None => VocabularySeparator::None,
+            WasmVocabularySeparator::WhiteSpace => VocabularySeparator::WhiteSpace,
+            WasmVocabularySeparator::Newline => VocabularySeparator::Newline,
+            WasmVocabularySeparator::Custom(separator) => {
+                VocabularySeparator::Custom(separator.clone())
+            }
+        };
+
+        let vocabulary_order = if self.random_order {
+            VocabularyOrder::Random
+        } else {
+            VocabularyOrder::InOrder
+        };
+
+        QueryRequest::new(
+            vocabulary_entries,
+            vocabulary_quantifier,
+            vocabulary_separator,
+            vocabulary_order,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "count", rename_all = "camelCase")]
+enum WasmVocabularyQuantifier {
+    KeyStroke(NonZeroUsize),
+    Vocabulary(NonZeroUsize),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum WasmVocabularySeparator {
+    #[default]
+    None,
+    WhiteSpace,
+    Newline,
+    Custom(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "count", rename_all = "camelCase")]
+enum WasmLapRequest {
+    KeyStroke(NonZeroUsize),
+    IdealKeyStroke(NonZeroUsize),
+    Spell(NonZeroUsize),
+    Chunk(NonZeroUsize),
+    Vocabulary(NonZeroUsize),
+    // JavaScriptからはミリ秒で指定する
+    Duration(u64),
+    CustomSpellPositions(Vec<usize>),
+}
+
+impl From<WasmLapRequest> for LapRequest {
+    fn from(lap_request: WasmLapRequest) -> Self {
+        match lap_request {
+            WasmLapRequest::KeyStroke(count) => Self::KeyStroke(count),
+            WasmLapRequest::IdealKeyStroke(count) => Self::IdealKeyStroke(count),
+            WasmLapRequest::Spell(count) => Self::Spell(count),
+            WasmLapRequest::Chunk(count) => Self::Chunk(count),
+            WasmLapRequest::Vocabulary(count) => Self::Vocabulary(count),
+            WasmLapRequest::Duration(millis) => Self::Duration(Duration::from_millis(millis)),
+            WasmLapRequest::CustomSpellPositions(positions) => {
+                Self::CustomSpellPositions(positions)
+            }
+        }
+    }
+}